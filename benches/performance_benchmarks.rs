@@ -5,7 +5,7 @@
 
 use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId, Throughput};
 use ai_proxy::{
-    config::{Config, LoggingConfig, PerformanceConfig, ProviderDetail, SecurityConfig, ServerConfig},
+    config::{Config, LoggingConfig, PerformanceConfig, ProviderDetail, SecurityConfig, ServerConfig, SharedConfig},
     providers::{ProviderRegistry, anthropic::{AnthropicRequest, Message}},
     server::{AppState, create_app},
 };
@@ -72,6 +72,7 @@ async fn setup_benchmark_server() -> (MockServer, AppState) {
         max_retries: 3,
         enabled: true,
         rate_limit: None,
+        ..Default::default()
     });
 
     let config = Config {
@@ -80,6 +81,7 @@ async fn setup_benchmark_server() -> (MockServer, AppState) {
             port: 0,
             request_timeout_seconds: 30,
             max_request_size_bytes: 1024 * 1024,
+            ..Default::default()
         },
         providers,
         logging: LoggingConfig {
@@ -87,9 +89,11 @@ async fn setup_benchmark_server() -> (MockServer, AppState) {
             format: "json".to_string(),
             log_requests: false,
             log_responses: false,
+            ..Default::default()
         },
         security: SecurityConfig::default(),
         performance: PerformanceConfig::default(),
+        ..Default::default()
     };
 
     let http_client = Client::new();
@@ -99,10 +103,16 @@ async fn setup_benchmark_server() -> (MockServer, AppState) {
     let metrics = Arc::new(ai_proxy::metrics::MetricsCollector::new());
 
     let app_state = AppState {
-        config: Arc::new(config),
+        config: Arc::new(SharedConfig::new(config)),
         http_client,
         provider_registry,
         metrics,
+        health_cache: Default::default(),
+        coalescer: Default::default(),
+        idempotency: Default::default(),
+        active_streams: Default::default(),
+        redactor: Default::default(),
+        transforms: Vec::new(),
     };
 
     (server, app_state)
@@ -278,38 +288,53 @@ fn bench_response_serialization(c: &mut Criterion) {
         AnthropicResponse {
             id: "resp-1".to_string(),
             model: "gpt-4".to_string(),
-            content: vec![ContentBlock {
-                type_field: "text".to_string(),
-                text: "Short response".to_string(),
-            }],
+            content: vec![ContentBlock::text("Short response".to_string())],
             usage: Usage {
                 input_tokens: 10,
                 output_tokens: 5,
+                thinking_tokens: None,
             },
+            stop_reason: None,
+            logprobs: None,
+            refused: false,
+            refusal_reason: None,
+            upstream_headers: Vec::new(),
+            system_fingerprint: None,
+            x_ai_proxy: None,
         },
         AnthropicResponse {
             id: "resp-2".to_string(),
             model: "claude-3-sonnet".to_string(),
-            content: vec![ContentBlock {
-                type_field: "text".to_string(),
-                text: "Medium length response with more detailed content and explanations".to_string(),
-            }],
+            content: vec![ContentBlock::text("Medium length response with more detailed content and explanations".to_string())],
             usage: Usage {
                 input_tokens: 50,
                 output_tokens: 25,
+                thinking_tokens: None,
             },
+            stop_reason: None,
+            logprobs: None,
+            refused: false,
+            refusal_reason: None,
+            upstream_headers: Vec::new(),
+            system_fingerprint: None,
+            x_ai_proxy: None,
         },
         AnthropicResponse {
             id: "resp-3".to_string(),
             model: "gemini-pro".to_string(),
-            content: vec![ContentBlock {
-                type_field: "text".to_string(),
-                text: "Very comprehensive and detailed response that would typically be generated in real-world usage scenarios where the AI provides extensive information, analysis, examples, and thorough explanations to complex user queries".to_string(),
-            }],
+            content: vec![ContentBlock::text("Very comprehensive and detailed response that would typically be generated in real-world usage scenarios where the AI provides extensive information, analysis, examples, and thorough explanations to complex user queries".to_string())],
             usage: Usage {
                 input_tokens: 200,
                 output_tokens: 150,
+                thinking_tokens: None,
             },
+            stop_reason: None,
+            logprobs: None,
+            refused: false,
+            refusal_reason: None,
+            upstream_headers: Vec::new(),
+            system_fingerprint: None,
+            x_ai_proxy: None,
         },
     ];
 
@@ -46,6 +46,7 @@ async fn main() {
             max_retries: 3,
             enabled: true,
             rate_limit: None,
+            ..Default::default()
         },
     );
 
@@ -55,6 +56,7 @@ async fn main() {
             port: 0,
             request_timeout_seconds: 30,
             max_request_size_bytes: 1024 * 1024,
+            ..Default::default()
         },
         providers,
         logging: LoggingConfig {
@@ -65,6 +67,7 @@ async fn main() {
         },
         security: SecurityConfig::default(),
         performance: PerformanceConfig::default(),
+        ..Default::default()
     };
     
     let app_state = ai_proxy::server::AppState::new(config).await.unwrap();
@@ -23,6 +23,7 @@ async fn main() {
             max_retries: 3,
             enabled: true,
             rate_limit: None,
+            ..Default::default()
         },
     );
 
@@ -32,6 +33,7 @@ async fn main() {
             port: 0,
             request_timeout_seconds: 30,
             max_request_size_bytes: 1024 * 1024,
+            ..Default::default()
         },
         providers,
         logging: LoggingConfig {
@@ -42,6 +44,7 @@ async fn main() {
         },
         security: SecurityConfig::default(),
         performance: PerformanceConfig::default(),
+        ..Default::default()
     };
     
     let http_client = reqwest::Client::new();
@@ -1,8 +1,11 @@
+use std::time::Duration;
+
 use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
     Json,
 };
+use serde::Serialize;
 use serde_json::json;
 use thiserror::Error;
 
@@ -12,7 +15,7 @@ use thiserror::Error;
 /// 应用程序特定的错误类型，需要特殊处理
 /// 
 /// 这些错误类型提供了详细的错误信息，并映射到适当的HTTP状态码
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone)]
 pub enum AppError {
     #[error("Bad request: {0}")]
     BadRequest(String),
@@ -20,10 +23,19 @@ pub enum AppError {
     #[error("Provider not found: {0}")]
     ProviderNotFound(String),
     
+    #[error("Not found: {0}")]
+    NotFound(String),
+    
     #[error("Provider error: {message}")]
     ProviderError {
         status: u16,
         message: String,
+        /// Upstream error `type` (e.g. OpenAI `invalid_request_error`, Gemini `INVALID_ARGUMENT`)
+        error_type: Option<String>,
+        /// Upstream error `code` (e.g. OpenAI `unsupported_parameter`)
+        error_code: Option<String>,
+        /// Delay requested by the upstream `Retry-After` header, if any
+        retry_after: Option<Duration>,
     },
     
     #[error("Internal server error: {0}")]
@@ -47,6 +59,9 @@ pub enum AppError {
     #[error("Request timeout: {0}")]
     TimeoutError(String),
     
+    #[error("Gateway timeout: {0}")]
+    GatewayTimeout(String),
+    
     #[error("Service unavailable: {0}")]
     ServiceUnavailable(String),
     
@@ -56,6 +71,9 @@ pub enum AppError {
     #[error("Model not supported: {0}")]
     ModelNotSupported(String),
     
+    #[error("Operation not supported: {0}")]
+    NotSupported(String),
+    
     #[error("Quota exceeded: {0}")]
     QuotaExceeded(String),
     
@@ -64,6 +82,49 @@ pub enum AppError {
     
     #[error("Serialization error: {0}")]
     SerializationError(String),
+
+    #[error("Invalid request: {}", describe_fields(.0))]
+    InvalidFields(Vec<FieldError>),
+}
+
+/// A request body field that is missing or has the wrong type
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    /// JSON path of the field, e.g. `model` or `messages[0].role`
+    pub field: String,
+    /// What is wrong with it, e.g. `missing required field`
+    pub problem: String,
+}
+
+impl FieldError {
+    /// 创建字段错误
+    ///
+    /// ## 功能说明
+    /// 便捷方法，创建描述某个请求字段缺失或类型错误的条目
+    ///
+    /// ## 参数说明
+    /// - `field`: 字段的JSON路径
+    /// - `problem`: 问题描述
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let error = FieldError::new("model", "missing required field");
+    /// ```
+    pub fn new(field: impl Into<String>, problem: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            problem: problem.into(),
+        }
+    }
+}
+
+/// `field: problem` pairs joined for the error message
+fn describe_fields(fields: &[FieldError]) -> String {
+    fields
+        .iter()
+        .map(|f| format!("{}: {}", f.field, f.problem))
+        .collect::<Vec<_>>()
+        .join("; ")
 }
 
 impl AppError {
@@ -116,6 +177,74 @@ impl AppError {
         Self::ProviderError {
             status,
             message: message.into(),
+            error_type: None,
+            error_code: None,
+            retry_after: None,
+        }
+    }
+
+    /// 创建携带上游错误类型和代码的提供商错误
+    ///
+    /// ## 功能说明
+    /// 与`provider_error`相同，但保留从提供商错误响应体中解析出的`type`和`code`，
+    /// 它们会出现在错误响应的`provider_error_type`/`provider_error_code`字段中，便于客户端按原因处理
+    ///
+    /// ## 参数说明
+    /// - `status`: HTTP状态码
+    /// - `message`: 错误消息
+    /// - `error_type`: 上游错误类型（如OpenAI的`invalid_request_error`）
+    /// - `error_code`: 上游错误代码（如OpenAI的`unsupported_parameter`）
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// return Err(AppError::provider_error_with_details(
+    ///     400,
+    ///     "Unsupported parameter: 'temperature'",
+    ///     Some("invalid_request_error".to_string()),
+    ///     Some("unsupported_parameter".to_string()),
+    /// ));
+    /// ```
+    pub fn provider_error_with_details(
+        status: u16,
+        message: impl Into<String>,
+        error_type: Option<String>,
+        error_code: Option<String>,
+    ) -> Self {
+        Self::ProviderError {
+            status,
+            message: message.into(),
+            error_type,
+            error_code,
+            retry_after: None,
+        }
+    }
+
+    /// 附加上游`Retry-After`请求头给出的等待时间
+    ///
+    /// ## 功能说明
+    /// 仅对`ProviderError`生效，重试逻辑会在下一次尝试前参考该时间；其他错误原样返回
+    ///
+    /// ## 参数说明
+    /// - `retry_after`: 解析后的等待时间，`None`表示上游未给出
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let error = AppError::provider_error(429, "Rate limited")
+    ///     .with_retry_after(Some(Duration::from_secs(2)));
+    /// assert_eq!(error.retry_after(), Some(Duration::from_secs(2)));
+    /// ```
+    pub fn with_retry_after(mut self, retry_after: Option<Duration>) -> Self {
+        if let Self::ProviderError { retry_after: slot, .. } = &mut self {
+            *slot = retry_after;
+        }
+        self
+    }
+
+    /// 上游通过`Retry-After`请求的等待时间
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::ProviderError { retry_after, .. } => *retry_after,
+            _ => None,
         }
     }
 
@@ -136,13 +265,28 @@ impl AppError {
     }
 }
 
-/// Convert AppError to HTTP response
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        let (status, error_message, error_code) = match &self {
+impl AppError {
+    /// 生成错误对应的HTTP状态码和JSON错误体
+    ///
+    /// ## 功能说明
+    /// 构造与HTTP错误响应相同的`{"error": {...}}`结构，供需要在响应体内嵌入错误的场景使用
+    /// （如批量请求中逐项返回的错误）
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let (status, body) = AppError::BadRequest("Invalid input".to_string()).status_and_body();
+    /// assert_eq!(status, StatusCode::BAD_REQUEST);
+    /// assert_eq!(body["error"]["type"], "invalid_request_error");
+    /// ```
+    ///
+    /// ## 返回值
+    /// HTTP状态码和JSON错误体
+    pub fn status_and_body(&self) -> (StatusCode, serde_json::Value) {
+        let (status, error_message, error_code) = match self {
             AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg.clone(), None),
             AppError::ProviderNotFound(msg) => (StatusCode::NOT_FOUND, msg.clone(), None),
-            AppError::ProviderError { status, message } => {
+            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg.clone(), None),
+            AppError::ProviderError { status, message, .. } => {
                 (StatusCode::from_u16(*status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR), message.clone(), Some(*status))
             }
             AppError::InternalServerError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg.clone(), None),
@@ -152,17 +296,21 @@ impl IntoResponse for AppError {
             AppError::AuthorizationError(msg) => (StatusCode::FORBIDDEN, msg.clone(), None),
             AppError::RateLimitError(msg) => (StatusCode::TOO_MANY_REQUESTS, msg.clone(), None),
             AppError::TimeoutError(msg) => (StatusCode::REQUEST_TIMEOUT, msg.clone(), None),
+            AppError::GatewayTimeout(msg) => (StatusCode::GATEWAY_TIMEOUT, msg.clone(), None),
             AppError::ServiceUnavailable(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg.clone(), None),
             AppError::StreamingError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg.clone(), None),
             AppError::ModelNotSupported(msg) => (StatusCode::BAD_REQUEST, msg.clone(), None),
+            AppError::NotSupported(msg) => (StatusCode::NOT_IMPLEMENTED, msg.clone(), None),
             AppError::QuotaExceeded(msg) => (StatusCode::TOO_MANY_REQUESTS, msg.clone(), None),
             AppError::NetworkError(msg) => (StatusCode::BAD_GATEWAY, msg.clone(), None),
             AppError::SerializationError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg.clone(), None),
+            AppError::InvalidFields(_) => (StatusCode::BAD_REQUEST, self.to_string(), None),
         };
 
-        let error_type = match &self {
+        let error_type = match self {
             AppError::BadRequest(_) => "invalid_request_error",
             AppError::ProviderNotFound(_) => "not_found_error",
+            AppError::NotFound(_) => "not_found_error",
             AppError::ProviderError { .. } => "provider_error",
             AppError::InternalServerError(_) => "internal_server_error",
             AppError::ConfigError(_) => "configuration_error",
@@ -171,12 +319,15 @@ impl IntoResponse for AppError {
             AppError::AuthorizationError(_) => "authorization_error",
             AppError::RateLimitError(_) => "rate_limit_error",
             AppError::TimeoutError(_) => "timeout_error",
+            AppError::GatewayTimeout(_) => "gateway_timeout_error",
             AppError::ServiceUnavailable(_) => "service_unavailable_error",
             AppError::StreamingError(_) => "streaming_error",
             AppError::ModelNotSupported(_) => "model_not_supported_error",
+            AppError::NotSupported(_) => "not_supported_error",
             AppError::QuotaExceeded(_) => "quota_exceeded_error",
             AppError::NetworkError(_) => "network_error",
             AppError::SerializationError(_) => "serialization_error",
+            AppError::InvalidFields(_) => "invalid_request_error",
         };
 
         // Create error response with additional context
@@ -193,12 +344,33 @@ impl IntoResponse for AppError {
             error_json["error"]["provider_code"] = json!(provider_status);
         }
 
+        // Pass through the upstream error type/code so clients can react programmatically
+        if let AppError::ProviderError { error_type, error_code, .. } = self {
+            if let Some(error_type) = error_type {
+                error_json["error"]["provider_error_type"] = json!(error_type);
+            }
+            if let Some(error_code) = error_code {
+                error_json["error"]["provider_error_code"] = json!(error_code);
+            }
+        }
+
+        // List every offending field so clients can fix them all at once
+        if let AppError::InvalidFields(fields) = self {
+            error_json["error"]["fields"] = json!(fields);
+        }
+
         // Add timestamp for debugging
         error_json["error"]["timestamp"] = json!(chrono::Utc::now().to_rfc3339());
 
-        let body = Json(error_json);
-        
-        (status, body).into_response()
+        (status, error_json)
+    }
+}
+
+/// Convert AppError to HTTP response
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let (status, error_json) = self.status_and_body();
+        (status, Json(error_json)).into_response()
     }
 }
 
@@ -1,12 +1,11 @@
-use ai_proxy::{start_server, AppError, Config};
+use ai_proxy::{config::load_config_from, start_server, AppError, Config};
 use clap::{Arg, Command};
 use std::path::PathBuf;
-use tokio::signal;
 use tracing_subscriber::{
     fmt::{self, format::FmtSpan},
     layer::SubscriberExt,
     util::SubscriberInitExt,
-    EnvFilter,
+    EnvFilter, Layer,
 };
 
 /// 命令行参数结构体
@@ -41,18 +40,18 @@ async fn main() -> Result<(), AppError> {
         return Ok(());
     }
 
+    // 加载配置文件和环境变量配置（日志格式来自配置，因此先于日志系统加载）
+    let mut config = load_config_with_args(&args)
+        .map_err(|e| AppError::ConfigError(format!("加载配置失败: {}", e)))?;
+
     // 初始化结构化日志系统
-    init_tracing(args.log_level.as_deref())?;
+    init_tracing(args.log_level.as_deref(), &config.logging.format)?;
 
     tracing::info!(
         version = env!("CARGO_PKG_VERSION"),
         "AI Proxy service starting up"
     );
 
-    // 加载配置文件和环境变量配置
-    let mut config = load_config_with_args(&args)
-        .map_err(|e| AppError::ConfigError(format!("加载配置失败: {}", e)))?;
-
     // 应用命令行参数覆盖配置
     apply_args_to_config(&mut config, &args);
 
@@ -71,24 +70,15 @@ async fn main() -> Result<(), AppError> {
         return Ok(());
     }
 
-    // 设置优雅关闭处理
-    let shutdown_signal = setup_shutdown_signal();
-
-    // 启动HTTP服务器，支持优雅关闭
+    // 启动HTTP服务器；start_server内部监听信号：SIGHUP重新加载配置，
+    // SIGINT/SIGTERM触发关闭并在排空进行中的请求后返回
     tracing::info!("Starting HTTP server with graceful shutdown support");
-    
-    tokio::select! {
-        result = start_server(config) => {
-            match result {
-                Ok(_) => tracing::info!("Server stopped normally"),
-                Err(e) => {
-                    tracing::error!(error = %e, "Server stopped with error");
-                    return Err(e);
-                }
-            }
-        }
-        _ = shutdown_signal => {
-            tracing::info!("Shutdown signal received, stopping server gracefully");
+
+    match start_server(config).await {
+        Ok(_) => tracing::info!("Server stopped normally"),
+        Err(e) => {
+            tracing::error!(error = %e, "Server stopped with error");
+            return Err(e);
         }
     }
 
@@ -192,25 +182,13 @@ fn print_version_info() {
 /// 
 /// 支持自定义配置文件路径，如果未指定则使用默认的config.toml
 fn load_config_with_args(args: &Args) -> anyhow::Result<Config> {
-    use figment::{Figment, providers::{Format, Toml, Env}};
-
     let config_path = args.config_path
-        .as_ref()
-        .map(|p| p.to_string_lossy().to_string())
-        .unwrap_or_else(|| "config.toml".to_string());
-
-    // 创建配置加载器，按优先级合并配置源
-    let config: Config = Figment::new()
-        .merge(Toml::file(&config_path))  // 配置文件
-        .merge(Env::prefixed("AI_PROXY_"))  // 环境变量覆盖
-        .extract()
-        .map_err(|e| anyhow::anyhow!("Failed to load configuration from {} or environment variables: {}", config_path, e))?;
-
-    // 验证加载的配置是否有效
-    config.validate()
-        .map_err(|e| anyhow::anyhow!("Configuration validation failed: {}", e))?;
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("config.toml"));
 
-    Ok(config)
+    // 合并配置文件和环境变量，规范化并验证；记录路径以便运行时重新加载
+    load_config_from(&config_path)
+        .map_err(|e| anyhow::anyhow!("{:#}", e))
 }
 
 /// 应用命令行参数到配置
@@ -228,45 +206,14 @@ fn apply_args_to_config(config: &mut Config, args: &Args) {
     }
 }
 
-/// 设置优雅关闭信号处理
-/// 
-/// 监听SIGINT (Ctrl+C) 和SIGTERM信号，支持优雅关闭
-async fn setup_shutdown_signal() {
-    let ctrl_c = async {
-        signal::ctrl_c()
-            .await
-            .expect("Failed to install Ctrl+C handler");
-    };
-
-    #[cfg(unix)]
-    let terminate = async {
-        signal::unix::signal(signal::unix::SignalKind::terminate())
-            .expect("Failed to install signal handler")
-            .recv()
-            .await;
-    };
-
-    #[cfg(not(unix))]
-    let terminate = std::future::pending::<()>();
-
-    tokio::select! {
-        _ = ctrl_c => {
-            tracing::info!("Received SIGINT (Ctrl+C)");
-        },
-        _ = terminate => {
-            tracing::info!("Received SIGTERM");
-        },
-    }
-}
-
 /// 初始化结构化日志系统
 /// 
 /// 配置tracing和tracing-subscriber，支持：
-/// - 结构化JSON日志输出
+/// - 按`logging.format`选择输出格式："json"（结构化JSON，默认）、"pretty"或"compact"（便于本地开发阅读）
 /// - 环境变量和命令行参数控制日志级别
 /// - 请求ID传播和追踪
 /// - 详细的请求/响应日志记录
-fn init_tracing(log_level_override: Option<&str>) -> Result<(), AppError> {
+fn init_tracing(log_level_override: Option<&str>, format: &str) -> Result<(), AppError> {
     // 确定日志级别优先级：命令行参数 > 环境变量 > 默认值
     let env_filter = if let Some(level) = log_level_override {
         EnvFilter::new(format!("ai_proxy={},tower_http=debug", level))
@@ -282,8 +229,14 @@ fn init_tracing(log_level_override: Option<&str>) -> Result<(), AppError> {
         .with_thread_names(true)  // 显示线程名称
         .with_file(true)  // 显示文件名
         .with_line_number(true)  // 显示行号
-        .with_span_events(FmtSpan::NEW | FmtSpan::CLOSE)  // 显示span事件
-        .json();  // 使用JSON格式
+        .with_span_events(FmtSpan::NEW | FmtSpan::CLOSE);  // 显示span事件
+
+    // 选择输出格式（取值已由LoggingConfig::validate校验）
+    let fmt_layer = match format {
+        "pretty" => fmt_layer.pretty().boxed(),
+        "compact" => fmt_layer.compact().boxed(),
+        _ => fmt_layer.json().boxed(),  // 使用JSON格式
+    };
 
     // 初始化全局subscriber
     tracing_subscriber::registry()
@@ -292,6 +245,6 @@ fn init_tracing(log_level_override: Option<&str>) -> Result<(), AppError> {
         .try_init()
         .map_err(|e| AppError::ConfigError(format!("Failed to initialize tracing: {}", e)))?;
 
-    tracing::info!("Structured logging system initialized");
+    tracing::info!(format, "Structured logging system initialized");
     Ok(())
 }
@@ -1,10 +1,14 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::time::Instant;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
+use crate::config::UsageConfig;
+use crate::errors::{AppError, AppResult};
+
 /// 系统指标收集器
 ///
 /// 负责收集和管理系统运行时的各种指标，包括请求计数、延迟、错误率、并发请求等
@@ -20,12 +24,22 @@ pub struct MetricsCollector {
     concurrent_requests: Arc<AtomicU64>,
     /// 最大并发请求数
     max_concurrent_requests: Arc<AtomicU64>,
+    /// 当前仍在向客户端转发的上游流式响应数
+    active_upstream_streams: Arc<AtomicU64>,
     /// 延迟统计信息
     latency_stats: Arc<RwLock<LatencyStats>>,
     /// 按提供商分组的指标
     provider_metrics: Arc<RwLock<HashMap<String, ProviderMetrics>>>,
     /// 按模型分组的指标
     model_metrics: Arc<RwLock<HashMap<String, ModelMetrics>>>,
+    /// 按(API密钥, 提供商, 模型)累计的token用量存储
+    usage_sink: Arc<dyn UsageSink>,
+    /// 按(提供商, 模型)分组的流式首token延迟直方图
+    first_token_latency: Arc<Mutex<HashMap<(String, String), FirstTokenLatency>>>,
+    /// 按(提供商, 结果)分组的重试次数
+    retry_attempts: Arc<Mutex<HashMap<(String, String), u64>>>,
+    /// 按(原提供商, 回退提供商)分组的回退次数
+    fallback_activations: Arc<Mutex<HashMap<(String, String), u64>>>,
     /// 系统启动时间
     start_time: Instant,
 }
@@ -92,16 +106,495 @@ pub struct MetricsSummary {
     pub current_concurrent_requests: u64,
     /// 最大并发请求数
     pub max_concurrent_requests: u64,
+    /// 当前活跃的上游流式响应数
+    pub active_upstream_streams: u64,
     /// 延迟统计
     pub latency_stats: LatencyStats,
     /// 按提供商分组的指标
     pub provider_metrics: HashMap<String, ProviderMetrics>,
     /// 按模型分组的指标
     pub model_metrics: HashMap<String, ModelMetrics>,
+    /// 按(提供商, 模型)分组的流式首token延迟，按提供商和模型排序
+    pub first_token_latency: Vec<FirstTokenLatency>,
+    /// 按(提供商, 结果)分组的重试次数，按提供商和结果排序
+    pub retry_attempts: Vec<RetryAttempts>,
+    /// 按(原提供商, 回退提供商)分组的回退次数，按提供商排序
+    pub fallback_activations: Vec<FallbackActivations>,
     /// 指标收集时间戳
     pub timestamp: String,
 }
 
+/// 重试计数（按提供商和结果分组）
+#[derive(Debug, Clone, Serialize)]
+pub struct RetryAttempts {
+    /// 提供商
+    pub provider: String,
+    /// 重试结果：`success`、`error`或`timeout`
+    pub outcome: String,
+    /// 重试次数
+    pub count: u64,
+}
+
+/// 回退计数（按原提供商和回退提供商分组）
+#[derive(Debug, Clone, Serialize)]
+pub struct FallbackActivations {
+    /// 原提供商
+    pub from: String,
+    /// 回退到的提供商
+    pub to: String,
+    /// 回退次数
+    pub count: u64,
+}
+
+/// 首token延迟直方图的桶上界（毫秒）
+const FIRST_TOKEN_BUCKETS_MS: [u64; 8] = [50, 100, 250, 500, 1000, 2500, 5000, 10000];
+
+/// 流式首token延迟直方图（按提供商和模型分组）
+#[derive(Debug, Clone, Serialize)]
+pub struct FirstTokenLatency {
+    /// 提供商
+    pub provider: String,
+    /// 模型名称
+    pub model: String,
+    /// 样本数
+    pub count: u64,
+    /// 延迟总和（毫秒）
+    pub sum_ms: u64,
+    /// 最小延迟（毫秒）
+    pub min_ms: u64,
+    /// 最大延迟（毫秒）
+    pub max_ms: u64,
+    /// 累计直方图桶：延迟不超过`le_ms`的样本数
+    pub buckets: Vec<LatencyBucket>,
+}
+
+/// 累计直方图桶
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencyBucket {
+    /// 桶上界（毫秒）
+    pub le_ms: u64,
+    /// 延迟不超过上界的样本数
+    pub count: u64,
+}
+
+impl FirstTokenLatency {
+    fn new(provider: &str, model: &str) -> Self {
+        Self {
+            provider: provider.to_string(),
+            model: model.to_string(),
+            count: 0,
+            sum_ms: 0,
+            min_ms: u64::MAX,
+            max_ms: 0,
+            buckets: FIRST_TOKEN_BUCKETS_MS
+                .iter()
+                .map(|&le_ms| LatencyBucket { le_ms, count: 0 })
+                .collect(),
+        }
+    }
+
+    fn observe(&mut self, latency_ms: u64) {
+        self.count += 1;
+        self.sum_ms += latency_ms;
+        self.min_ms = self.min_ms.min(latency_ms);
+        self.max_ms = self.max_ms.max(latency_ms);
+        for bucket in self.buckets.iter_mut().filter(|bucket| latency_ms <= bucket.le_ms) {
+            bucket.count += 1;
+        }
+    }
+}
+
+/// 单次请求的token用量记录
+#[derive(Debug, Clone, Default)]
+pub struct UsageRecord {
+    /// 调用方API密钥标识（已脱敏）
+    pub api_key_id: String,
+    /// 处理请求的提供商
+    pub provider: String,
+    /// 使用的模型名称
+    pub model: String,
+    /// 输入token数量
+    pub input_tokens: u32,
+    /// 输出token数量
+    pub output_tokens: u32,
+}
+
+impl UsageRecord {
+    /// 以结构化字段记录一次请求的用量日志
+    ///
+    /// ## 功能说明
+    /// 在`logging.log_usage`开启时于请求完成后调用，输出一条`info`级别事件，
+    /// `input_tokens`、`output_tokens`、`provider`、`model`和`latency_ms`均为独立字段，
+    /// 便于JSON日志直接按字段统计
+    ///
+    /// ## 参数说明
+    /// - `latency`: 从收到请求到完成（流式请求为流结束）的耗时
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// record.log(start_time.elapsed());
+    /// ```
+    pub fn log(&self, latency: Duration) {
+        tracing::info!(
+            input_tokens = self.input_tokens,
+            output_tokens = self.output_tokens,
+            provider = %self.provider,
+            model = %self.model,
+            latency_ms = latency.as_millis() as u64,
+            "Request usage"
+        );
+    }
+}
+
+/// 按(API密钥, 提供商, 模型)聚合的用量累计值
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UsageTotals {
+    /// 调用方API密钥标识（已脱敏）
+    pub api_key_id: String,
+    /// 提供商
+    pub provider: String,
+    /// 模型名称
+    pub model: String,
+    /// 请求次数
+    pub requests: u64,
+    /// 累计输入token
+    pub input_tokens: u64,
+    /// 累计输出token
+    pub output_tokens: u64,
+    /// 累计总token
+    pub total_tokens: u64,
+}
+
+/// 用量存储后端
+///
+/// 负责累计每次请求的token用量，并提供聚合后的总量。
+/// 实现需保证线程安全，`record`会在请求处理路径（包括流式响应结束时）同步调用。
+pub trait UsageSink: Send + Sync + std::fmt::Debug {
+    /// 累计一条用量记录
+    fn record(&self, record: &UsageRecord);
+
+    /// 获取当前所有聚合用量
+    fn totals(&self) -> Vec<UsageTotals>;
+}
+
+/// 默认的内存用量存储，进程重启后数据丢失
+#[derive(Debug, Default)]
+pub struct InMemoryUsageSink {
+    totals: Mutex<HashMap<(String, String, String), UsageTotals>>,
+}
+
+impl InMemoryUsageSink {
+    /// 创建空的内存用量存储
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从已有的聚合用量创建（用于从持久化文件恢复）
+    pub fn from_totals(totals: Vec<UsageTotals>) -> Self {
+        let map = totals
+            .into_iter()
+            .map(|t| ((t.api_key_id.clone(), t.provider.clone(), t.model.clone()), t))
+            .collect();
+        Self {
+            totals: Mutex::new(map),
+        }
+    }
+}
+
+impl UsageSink for InMemoryUsageSink {
+    fn record(&self, record: &UsageRecord) {
+        let mut totals = self.totals.lock().unwrap_or_else(|e| e.into_inner());
+        let key = (
+            record.api_key_id.clone(),
+            record.provider.clone(),
+            record.model.clone(),
+        );
+        let entry = totals.entry(key).or_insert_with(|| UsageTotals {
+            api_key_id: record.api_key_id.clone(),
+            provider: record.provider.clone(),
+            model: record.model.clone(),
+            requests: 0,
+            input_tokens: 0,
+            output_tokens: 0,
+            total_tokens: 0,
+        });
+        entry.requests += 1;
+        entry.input_tokens += record.input_tokens as u64;
+        entry.output_tokens += record.output_tokens as u64;
+        entry.total_tokens = entry.input_tokens + entry.output_tokens;
+    }
+
+    fn totals(&self) -> Vec<UsageTotals> {
+        let totals = self.totals.lock().unwrap_or_else(|e| e.into_inner());
+        let mut result: Vec<UsageTotals> = totals.values().cloned().collect();
+        result.sort_by(|a, b| {
+            (&a.api_key_id, &a.provider, &a.model).cmp(&(&b.api_key_id, &b.provider, &b.model))
+        });
+        result
+    }
+}
+
+/// 持久化到JSON文件的用量存储
+///
+/// 在内存中累计，并通过后台任务定期将聚合结果写入文件；
+/// 创建时会从已有文件恢复累计值，销毁时会执行最后一次刷新。
+#[derive(Debug)]
+pub struct JsonFileUsageSink {
+    path: PathBuf,
+    inner: InMemoryUsageSink,
+}
+
+impl JsonFileUsageSink {
+    /// 创建JSON文件用量存储
+    ///
+    /// ## 功能说明
+    /// 打开指定路径的用量文件，如文件已存在则加载其中的累计值
+    ///
+    /// ## 参数说明
+    /// - `path`: 用量JSON文件路径
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let sink = Arc::new(JsonFileUsageSink::new("usage.json")?);
+    /// sink.spawn_flush_task(Duration::from_secs(60));
+    /// ```
+    ///
+    /// ## 返回值
+    /// - `Ok(JsonFileUsageSink)`: 创建成功
+    /// - `Err(AppError)`: 已有文件无法读取或解析
+    pub fn new(path: impl Into<PathBuf>) -> AppResult<Self> {
+        let path = path.into();
+        let totals = match std::fs::read_to_string(&path) {
+            Ok(content) if !content.trim().is_empty() => serde_json::from_str(&content)
+                .map_err(|e| {
+                    AppError::ConfigError(format!(
+                        "Failed to parse usage file {}: {}",
+                        path.display(),
+                        e
+                    ))
+                })?,
+            Ok(_) => Vec::new(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                return Err(AppError::ConfigError(format!(
+                    "Failed to read usage file {}: {}",
+                    path.display(),
+                    e
+                )));
+            }
+        };
+
+        Ok(Self {
+            path,
+            inner: InMemoryUsageSink::from_totals(totals),
+        })
+    }
+
+    /// 将当前聚合用量写入文件（先写临时文件再重命名，避免写入中断导致文件损坏）
+    pub fn flush(&self) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(&self.inner.totals())?;
+        let tmp_path = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, json)?;
+        std::fs::rename(&tmp_path, &self.path)
+    }
+
+    /// 启动定期刷新任务，存储被释放后任务自动退出
+    pub fn spawn_flush_task(self: &Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let weak: Weak<Self> = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let Some(sink) = weak.upgrade() else { break };
+                if let Err(e) = sink.flush() {
+                    tracing::warn!(path = %sink.path.display(), "Failed to flush usage file: {}", e);
+                }
+            }
+        })
+    }
+}
+
+impl UsageSink for JsonFileUsageSink {
+    fn record(&self, record: &UsageRecord) {
+        self.inner.record(record);
+    }
+
+    fn totals(&self) -> Vec<UsageTotals> {
+        self.inner.totals()
+    }
+}
+
+impl Drop for JsonFileUsageSink {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            tracing::warn!(path = %self.path.display(), "Failed to flush usage file on shutdown: {}", e);
+        }
+    }
+}
+
+/// 根据配置创建用量存储
+///
+/// ## 功能说明
+/// 按`usage.sink`选择内存或JSON文件存储；JSON文件存储在Tokio运行时内会启动定期刷新任务
+///
+/// ## 参数说明
+/// - `config`: 用量计量配置
+///
+/// ## 返回值
+/// - `Ok(Arc<dyn UsageSink>)`: 创建好的用量存储
+/// - `Err(AppError)`: 配置无效或用量文件无法加载
+pub fn create_usage_sink(config: &UsageConfig) -> AppResult<Arc<dyn UsageSink>> {
+    match config.sink.as_str() {
+        "json_file" => {
+            let path = config.file_path.as_deref().ok_or_else(|| {
+                AppError::ConfigError("Usage file_path is required for json_file sink".to_string())
+            })?;
+            let sink = Arc::new(JsonFileUsageSink::new(path)?);
+            if tokio::runtime::Handle::try_current().is_ok() {
+                sink.spawn_flush_task(Duration::from_secs(config.flush_interval_seconds));
+            }
+            Ok(sink)
+        }
+        _ => Ok(Arc::new(InMemoryUsageSink::new())),
+    }
+}
+
+/// 流式响应的用量跟踪器
+///
+/// 观察转发给客户端的SSE文本，从`message_start`和最终`message_delta`事件中提取token用量，
+/// 并在流结束或被客户端取消（跟踪器被释放）时记录一次用量（按需同时输出用量日志）。
+pub struct StreamUsageTracker {
+    metrics: Arc<MetricsCollector>,
+    record: UsageRecord,
+    buffer: String,
+    observed: bool,
+    log_since: Option<Instant>,
+}
+
+impl StreamUsageTracker {
+    /// 创建流式用量跟踪器
+    pub fn new(metrics: Arc<MetricsCollector>, api_key_id: String, provider: String, model: String) -> Self {
+        Self {
+            metrics,
+            record: UsageRecord {
+                api_key_id,
+                provider,
+                model,
+                input_tokens: 0,
+                output_tokens: 0,
+            },
+            buffer: String::new(),
+            observed: false,
+            log_since: None,
+        }
+    }
+
+    /// 流结束时额外输出结构化用量日志，耗时从`started`算起；`None`表示不输出
+    pub fn log_usage_since(mut self, started: Option<Instant>) -> Self {
+        self.log_since = started;
+        self
+    }
+
+    /// 观察一段流式输出，累计其中完整SSE事件携带的用量
+    pub fn observe(&mut self, chunk: &str) {
+        self.buffer.push_str(chunk);
+        while let Some(pos) = self.buffer.find("\n\n") {
+            let event: String = self.buffer.drain(..pos + 2).collect();
+            self.observe_event(&event);
+        }
+    }
+
+    fn observe_event(&mut self, event: &str) {
+        for data in event.lines().filter_map(|line| line.strip_prefix("data:")) {
+            let Ok(value) = serde_json::from_str::<serde_json::Value>(data.trim()) else {
+                continue;
+            };
+            let usage = match value.get("type").and_then(|t| t.as_str()) {
+                Some("message_start") => value.get("message").and_then(|m| m.get("usage")),
+                Some("message_delta") => value.get("usage").or_else(|| value.get("delta").and_then(|d| d.get("usage"))),
+                _ => None,
+            };
+            let Some(usage) = usage else { continue };
+            self.observed = true;
+            if let Some(input) = usage.get("input_tokens").and_then(|v| v.as_u64()).filter(|v| *v > 0) {
+                self.record.input_tokens = input as u32;
+            }
+            if let Some(output) = usage.get("output_tokens").and_then(|v| v.as_u64()).filter(|v| *v > 0) {
+                self.record.output_tokens = output as u32;
+            }
+        }
+    }
+}
+
+impl Drop for StreamUsageTracker {
+    fn drop(&mut self) {
+        if self.observed {
+            self.metrics.record_usage(&self.record);
+            if let Some(started) = self.log_since {
+                self.record.log(started.elapsed());
+            }
+        }
+    }
+}
+
+/// 仪表型指标的守卫
+///
+/// 由`MetricsCollector::track_request`和`track_upstream_stream`创建，释放时将对应的
+/// 仪表减一；指标在此期间被重置时不会下溢。
+#[derive(Debug)]
+pub struct GaugeGuard {
+    gauge: Arc<AtomicU64>,
+}
+
+impl GaugeGuard {
+    fn new(gauge: Arc<AtomicU64>) -> Self {
+        Self { gauge }
+    }
+}
+
+impl Drop for GaugeGuard {
+    fn drop(&mut self) {
+        let _ = self
+            .gauge
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |value| Some(value.saturating_sub(1)));
+    }
+}
+
+/// 流式响应的首token计时器
+///
+/// 观察转发给客户端的SSE文本，在第一个`content_block_delta`事件出现时，
+/// 记录从请求开始到该时刻经过的时间。
+pub struct FirstTokenTimer {
+    metrics: Arc<MetricsCollector>,
+    provider: String,
+    model: String,
+    start: Instant,
+    recorded: bool,
+}
+
+impl FirstTokenTimer {
+    /// 创建首token计时器，`start`通常是请求开始的时间
+    pub fn new(metrics: Arc<MetricsCollector>, provider: String, model: String, start: Instant) -> Self {
+        Self {
+            metrics,
+            provider,
+            model,
+            start,
+            recorded: false,
+        }
+    }
+
+    /// 观察一段流式输出，首次出现`content_block_delta`时记录延迟
+    pub fn observe(&mut self, chunk: &str) {
+        if !self.recorded && chunk.contains("content_block_delta") {
+            self.recorded = true;
+            self.metrics
+                .record_first_token(&self.provider, &self.model, self.start.elapsed());
+        }
+    }
+}
+
 impl Default for LatencyStats {
     fn default() -> Self {
         Self {
@@ -147,19 +640,130 @@ impl MetricsCollector {
     /// let metrics = MetricsCollector::new();
     /// ```
     pub fn new() -> Self {
+        Self::with_usage_sink(Arc::new(InMemoryUsageSink::new()))
+    }
+
+    /// 使用指定的用量存储创建指标收集器
+    ///
+    /// ## 功能说明
+    /// 与`new()`相同，但token用量写入给定的`UsageSink`（如JSON文件存储）
+    ///
+    /// ## 参数说明
+    /// - `usage_sink`: 用量存储后端
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let sink = create_usage_sink(&config.usage)?;
+    /// let metrics = MetricsCollector::with_usage_sink(sink);
+    /// ```
+    pub fn with_usage_sink(usage_sink: Arc<dyn UsageSink>) -> Self {
         Self {
             request_count: Arc::new(AtomicU64::new(0)),
             success_count: Arc::new(AtomicU64::new(0)),
             error_count: Arc::new(AtomicU64::new(0)),
             concurrent_requests: Arc::new(AtomicU64::new(0)),
             max_concurrent_requests: Arc::new(AtomicU64::new(0)),
+            active_upstream_streams: Arc::new(AtomicU64::new(0)),
             latency_stats: Arc::new(RwLock::new(LatencyStats::default())),
             provider_metrics: Arc::new(RwLock::new(HashMap::new())),
             model_metrics: Arc::new(RwLock::new(HashMap::new())),
+            usage_sink,
+            first_token_latency: Arc::new(Mutex::new(HashMap::new())),
+            retry_attempts: Arc::new(Mutex::new(HashMap::new())),
+            fallback_activations: Arc::new(Mutex::new(HashMap::new())),
             start_time: Instant::now(),
         }
     }
 
+    /// 记录一次流式请求的首token延迟
+    ///
+    /// ## 功能说明
+    /// 将从请求开始到第一个`content_block_delta`的时间计入(提供商, 模型)维度的延迟直方图
+    ///
+    /// ## 参数说明
+    /// - `provider`: 处理请求的提供商
+    /// - `model`: 使用的模型名称
+    /// - `latency`: 首token延迟
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// metrics.record_first_token("openai", "gpt-4", Duration::from_millis(350));
+    /// ```
+    pub fn record_first_token(&self, provider: &str, model: &str, latency: Duration) {
+        let mut histograms = self
+            .first_token_latency
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        histograms
+            .entry((provider.to_string(), model.to_string()))
+            .or_insert_with(|| FirstTokenLatency::new(provider, model))
+            .observe(latency.as_millis() as u64);
+    }
+
+    /// 记录一次重试尝试
+    ///
+    /// ## 功能说明
+    /// 每次重试（不含首次尝试）按(提供商, 结果)计数，用于发现不稳定的上游
+    ///
+    /// ## 参数说明
+    /// - `provider`: 处理请求的提供商
+    /// - `outcome`: 该次重试的结果：`success`、`error`或`timeout`
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// metrics.record_retry_attempt("openai", "success");
+    /// ```
+    pub fn record_retry_attempt(&self, provider: &str, outcome: &str) {
+        increment(&self.retry_attempts, provider, outcome);
+    }
+
+    /// 记录一次提供商回退
+    ///
+    /// ## 功能说明
+    /// 请求从一个提供商回退到另一个提供商时按(原提供商, 回退提供商)计数
+    ///
+    /// ## 参数说明
+    /// - `from`: 原本处理请求的提供商
+    /// - `to`: 回退到的提供商
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// metrics.record_fallback("openai", "openrouter");
+    /// ```
+    pub fn record_fallback(&self, from: &str, to: &str) {
+        increment(&self.fallback_activations, from, to);
+    }
+
+    /// 记录一次请求的token用量
+    ///
+    /// ## 功能说明
+    /// 将请求的输入/输出token累计到(API密钥, 提供商, 模型)维度的用量存储中
+    ///
+    /// ## 参数说明
+    /// - `record`: 单次请求的用量记录
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// metrics.record_usage(&UsageRecord {
+    ///     api_key_id: "anonymous".to_string(),
+    ///     provider: "openai".to_string(),
+    ///     model: "gpt-4".to_string(),
+    ///     input_tokens: 10,
+    ///     output_tokens: 25,
+    /// });
+    /// ```
+    pub fn record_usage(&self, record: &UsageRecord) {
+        self.usage_sink.record(record);
+    }
+
+    /// 获取聚合后的token用量
+    ///
+    /// ## 返回值
+    /// - `Vec<UsageTotals>`: 按(API密钥, 提供商, 模型)排序的累计用量
+    pub fn get_usage_totals(&self) -> Vec<UsageTotals> {
+        self.usage_sink.totals()
+    }
+
     /// 增加并发请求计数
     ///
     /// ## 功能说明
@@ -170,6 +774,10 @@ impl MetricsCollector {
     /// metrics.increment_concurrent_requests().await;
     /// ```
     pub async fn increment_concurrent_requests(&self) {
+        self.enter_request();
+    }
+
+    fn enter_request(&self) {
         let current = self.concurrent_requests.fetch_add(1, Ordering::Relaxed) + 1;
 
         // 更新最大并发请求数
@@ -200,6 +808,63 @@ impl MetricsCollector {
         self.concurrent_requests.fetch_sub(1, Ordering::Relaxed);
     }
 
+    /// 跟踪一个正在处理的入站请求
+    ///
+    /// ## 功能说明
+    /// 增加当前并发请求数（并更新最大值），返回的守卫被释放时自动减少计数，
+    /// 因此请求被取消或处理过程中发生panic时计数也能正确回落
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let _active = metrics.track_request();
+    /// let response = next.run(request).await;
+    /// ```
+    ///
+    /// ## 返回值
+    /// - `GaugeGuard`: 在释放时减少并发请求数的守卫
+    pub fn track_request(&self) -> GaugeGuard {
+        self.enter_request();
+        GaugeGuard::new(self.concurrent_requests.clone())
+    }
+
+    /// 跟踪一个正在转发的上游流式响应
+    ///
+    /// ## 功能说明
+    /// 增加活跃上游流数，返回的守卫应随流一起移动，在流结束、被客户端取消或发生panic时
+    /// 释放并减少计数
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let guard = metrics.track_upstream_stream();
+    /// let stream = stream.map(move |chunk| {
+    ///     let _ = &guard;
+    ///     chunk
+    /// });
+    /// ```
+    ///
+    /// ## 返回值
+    /// - `GaugeGuard`: 在释放时减少活跃上游流数的守卫
+    pub fn track_upstream_stream(&self) -> GaugeGuard {
+        self.active_upstream_streams.fetch_add(1, Ordering::Relaxed);
+        GaugeGuard::new(self.active_upstream_streams.clone())
+    }
+
+    /// 获取当前活跃的上游流式响应数
+    ///
+    /// ## 返回值
+    /// - `u64`: 当前活跃的上游流数
+    pub fn get_active_upstream_streams(&self) -> u64 {
+        self.active_upstream_streams.load(Ordering::Relaxed)
+    }
+
+    /// 获取服务运行时长
+    ///
+    /// ## 返回值
+    /// - `Duration`: 指标收集器创建（即服务启动）以来经过的时间
+    pub fn uptime(&self) -> Duration {
+        self.start_time.elapsed()
+    }
+
     /// 获取当前并发请求数
     ///
     /// ## 功能说明
@@ -353,6 +1018,22 @@ impl MetricsCollector {
 
         let provider_metrics = self.provider_metrics.read().await.clone();
         let model_metrics = self.model_metrics.read().await.clone();
+        let mut first_token_latency: Vec<FirstTokenLatency> = self
+            .first_token_latency
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .values()
+            .cloned()
+            .collect();
+        first_token_latency.sort_by(|a, b| (&a.provider, &a.model).cmp(&(&b.provider, &b.model)));
+        let retry_attempts = sorted_counts(&self.retry_attempts)
+            .into_iter()
+            .map(|((provider, outcome), count)| RetryAttempts { provider, outcome, count })
+            .collect();
+        let fallback_activations = sorted_counts(&self.fallback_activations)
+            .into_iter()
+            .map(|((from, to), count)| FallbackActivations { from, to, count })
+            .collect();
 
         MetricsSummary {
             uptime_seconds: self.start_time.elapsed().as_secs(),
@@ -364,9 +1045,13 @@ impl MetricsCollector {
             avg_latency_ms,
             current_concurrent_requests: self.concurrent_requests.load(Ordering::Relaxed),
             max_concurrent_requests: self.max_concurrent_requests.load(Ordering::Relaxed),
+            active_upstream_streams: self.active_upstream_streams.load(Ordering::Relaxed),
             latency_stats,
             provider_metrics,
             model_metrics,
+            first_token_latency,
+            retry_attempts,
+            fallback_activations,
             timestamp: chrono::Utc::now().to_rfc3339(),
         }
     }
@@ -386,10 +1071,18 @@ impl MetricsCollector {
         self.error_count.store(0, Ordering::Relaxed);
         self.concurrent_requests.store(0, Ordering::Relaxed);
         self.max_concurrent_requests.store(0, Ordering::Relaxed);
+        self.active_upstream_streams.store(0, Ordering::Relaxed);
 
         *self.latency_stats.write().await = LatencyStats::default();
         self.provider_metrics.write().await.clear();
         self.model_metrics.write().await.clear();
+        self.first_token_latency
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clear();
+        for counters in [&self.retry_attempts, &self.fallback_activations] {
+            counters.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clear();
+        }
     }
 
     /// 获取基本指标（用于快速检查）
@@ -414,6 +1107,27 @@ impl MetricsCollector {
     }
 }
 
+/// Bump the counter for a two-part label
+fn increment(counters: &Mutex<HashMap<(String, String), u64>>, first: &str, second: &str) {
+    *counters
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .entry((first.to_string(), second.to_string()))
+        .or_insert(0) += 1;
+}
+
+/// Snapshot of two-part label counters, sorted by label
+fn sorted_counts(counters: &Mutex<HashMap<(String, String), u64>>) -> Vec<((String, String), u64)> {
+    let mut counts: Vec<_> = counters
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .iter()
+        .map(|(labels, count)| (labels.clone(), *count))
+        .collect();
+    counts.sort();
+    counts
+}
+
 /// 指标中间件，用于自动收集HTTP请求指标
 ///
 /// ## 功能说明
@@ -6,9 +6,10 @@ use axum::{
     response::Response,
 };
 use uuid::Uuid;
-use tracing::{info, warn, error};
+use tracing::{info, warn, error, Instrument};
 
 use crate::{
+    config::SecurityConfig,
     errors::AppError,
     server::AppState,
 };
@@ -109,23 +110,22 @@ pub async fn logging_middleware(
         user_agent = context.user_agent.as_deref().unwrap_or("unknown")
     );
 
-    // Enter the span for this request
-    let _enter = span.enter();
-
     // Log request start
-    info!(
-        request_id = %context.request_id,
-        method = %context.method,
-        uri = %context.uri,
-        user_agent = context.user_agent.as_deref().unwrap_or("unknown"),
-        "Request started"
-    );
+    span.in_scope(|| {
+        info!(
+            request_id = %context.request_id,
+            method = %context.method,
+            uri = %context.uri,
+            user_agent = context.user_agent.as_deref().unwrap_or("unknown"),
+            "Request started"
+        )
+    });
 
     // Record request start for metrics
     let metrics_start = state.metrics.record_request_start();
 
-    // Process the request
-    let mut response = next.run(request).await;
+    // Process the request inside the span so downstream log lines carry its fields
+    let mut response = next.run(request).instrument(span).await;
 
     // Calculate request duration
     let duration = context.elapsed();
@@ -244,25 +244,8 @@ pub async fn validation_middleware(
         }
     }
 
-    // Validate request size (prevent extremely large requests)
-    if let Some(content_length) = request.headers().get("content-length") {
-        if let Ok(length_str) = content_length.to_str() {
-            if let Ok(length) = length_str.parse::<usize>() {
-                const MAX_REQUEST_SIZE: usize = 10 * 1024 * 1024; // 10MB
-                if length > MAX_REQUEST_SIZE {
-                    warn!(
-                        request_id = request_id,
-                        content_length = length,
-                        max_allowed = MAX_REQUEST_SIZE,
-                        "Request size exceeds maximum allowed"
-                    );
-                    return Err(AppError::ValidationError(
-                        "Request size exceeds maximum allowed limit".to_string(),
-                    ));
-                }
-            }
-        }
-    }
+    // Request body size is enforced by the DefaultBodyLimit layer configured
+    // from `server.max_request_size_bytes` (see `create_app`)
 
     info!(
         request_id = request_id,
@@ -288,13 +271,12 @@ pub async fn performance_middleware(
     let start_time = Instant::now();
     let uri = request.uri().to_string();
 
-    // Record concurrent request
-    state.metrics.increment_concurrent_requests().await;
+    // Count the request as active until this guard drops, even if the handler is cancelled
+    let active = state.metrics.track_request();
 
     let response = next.run(request).await;
 
-    // Record request completion
-    state.metrics.decrement_concurrent_requests().await;
+    drop(active);
 
     let duration = start_time.elapsed();
     let duration_ms = duration.as_millis() as u64;
@@ -333,28 +315,40 @@ fn extract_provider_from_uri(uri: &str) -> &str {
     }
 }
 
-/// Simple request ID middleware
+/// Maximum accepted length for a client-supplied request ID
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+/// Request ID middleware
+///
+/// Honors a well-formed incoming `x-request-id` (or generates a UUID), runs the
+/// rest of the stack inside a `request` span carrying that ID so every log line
+/// for the request includes it, and echoes the ID on the response.
 pub async fn request_id_middleware(
     mut request: Request,
     next: Next,
 ) -> Response {
-    // Generate request ID if not present
-    let request_id = if !request.headers().contains_key(REQUEST_ID_HEADER) {
-        let request_id = Uuid::new_v4().to_string();
-        if let Ok(header_value) = HeaderValue::from_str(&request_id) {
-            request.headers_mut().insert(REQUEST_ID_HEADER, header_value);
-        }
-        request_id
-    } else {
-        // Get existing request ID
-        request.headers()
-            .get(REQUEST_ID_HEADER)
-            .and_then(|v| v.to_str().ok())
-            .unwrap_or_else(|| "unknown")
-            .to_string()
-    };
+    // Reuse the client's request ID when it is a reasonable header value
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LENGTH)
+        .map(|id| id.to_string())
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
 
-    let mut response = next.run(request).await;
+    if let Ok(header_value) = HeaderValue::from_str(&request_id) {
+        request.headers_mut().insert(REQUEST_ID_HEADER, header_value);
+    }
+
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %request.method(),
+        uri = %request.uri(),
+    );
+
+    let mut response = next.run(request).instrument(span).await;
 
     // Add request ID to response headers
     if let Ok(header_value) = HeaderValue::from_str(&request_id) {
@@ -362,4 +356,118 @@ pub async fn request_id_middleware(
     }
 
     response
-}
\ No newline at end of file
+}
+
+/// Key ID used for callers that present no API key
+pub const ANONYMOUS_API_KEY_ID: &str = "anonymous";
+
+/// Derive a stable, non-secret identifier for the caller's API key
+///
+/// Reads `x-api-key` or `Authorization: Bearer <key>` and masks it to its first
+/// and last four characters so usage can be attributed without storing secrets.
+pub fn api_key_id(headers: &HeaderMap) -> String {
+    match presented_api_key(headers) {
+        Some(key) if key.chars().count() > 8 => {
+            let chars: Vec<char> = key.chars().collect();
+            let head: String = chars[..4].iter().collect();
+            let tail: String = chars[chars.len() - 4..].iter().collect();
+            format!("{}...{}", head, tail)
+        }
+        Some(_) => "****".to_string(),
+        None => ANONYMOUS_API_KEY_ID.to_string(),
+    }
+}
+
+/// Hex SHA-256 of the caller's full API key, or `None` for callers without one
+///
+/// Unlike the masked [`api_key_id`], distinct keys never share a fingerprint,
+/// so it can scope per-caller state such as idempotency keys and stream IDs
+/// without storing the key itself.
+pub fn api_key_fingerprint(headers: &HeaderMap) -> Option<String> {
+    let key = presented_api_key(headers)?;
+    let digest = ring::digest::digest(&ring::digest::SHA256, key.as_bytes());
+    Some(digest.as_ref().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Raw API key from `x-api-key` or `Authorization: Bearer <key>`, if any
+fn presented_api_key(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get("x-api-key")
+        .and_then(|v| v.to_str().ok())
+        .or_else(|| {
+            headers
+                .get("authorization")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("Bearer "))
+        })
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+}
+
+/// Enforce `security.model_access` for the caller's API key
+///
+/// Returns an authorization error (403) when the key is restricted and the
+/// model matches none of its allowed patterns.
+pub fn check_model_access(security: &SecurityConfig, headers: &HeaderMap, model: &str) -> Result<(), AppError> {
+    if security.model_access.is_empty() {
+        return Ok(());
+    }
+
+    if security.is_model_allowed(presented_api_key(headers), model) {
+        return Ok(());
+    }
+
+    warn!("API key {} is not allowed to use model {}", api_key_id(headers), model);
+    Err(AppError::AuthorizationError(format!(
+        "API key is not allowed to use model '{}'",
+        model
+    )))
+}
+
+/// Header trusted callers send to keep their system prompt free of configured injections
+pub const SKIP_INJECTION_HEADER: &str = "x-ai-proxy-skip-injection";
+
+/// Whether the request opts out of system prompt injection and its key may do so
+///
+/// `x-ai-proxy-skip-injection: true` is only honoured for the full keys listed in
+/// `security.injection_bypass_keys`; other callers are logged and injected as usual.
+pub fn skips_system_injection(security: &SecurityConfig, headers: &HeaderMap) -> bool {
+    let requested = headers
+        .get(SKIP_INJECTION_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case("true"));
+    if !requested {
+        return false;
+    }
+
+    if security.may_bypass_injection(presented_api_key(headers)) {
+        return true;
+    }
+    warn!("Ignoring {} from untrusted API key {}", SKIP_INJECTION_HEADER, api_key_id(headers));
+    false
+}
+
+/// Require the configured `security.admin_api_key` for `/admin/*` endpoints
+///
+/// Admin endpoints are disabled (403) when no admin key is configured; a
+/// missing or wrong key is rejected with 401.
+pub fn check_admin_access(security: &SecurityConfig, headers: &HeaderMap) -> Result<(), AppError> {
+    let Some(admin_key) = security.admin_api_key.as_deref() else {
+        return Err(AppError::AuthorizationError(
+            "Admin endpoints are disabled; set security.admin_api_key to enable them".to_string(),
+        ));
+    };
+
+    match presented_api_key(headers) {
+        Some(key) if constant_time_eq(key.as_bytes(), admin_key.as_bytes()) => Ok(()),
+        _ => {
+            warn!("Rejected admin request from API key {}", api_key_id(headers));
+            Err(AppError::AuthenticationError("Invalid admin API key".to_string()))
+        }
+    }
+}
+
+/// Compare secrets without short-circuiting on the first differing byte
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
@@ -0,0 +1,97 @@
+use futures::StreamExt;
+use serde_json::Value;
+
+use crate::errors::AppError;
+use super::StreamResponse;
+use super::anthropic::AnthropicResponse;
+
+/// Consume an Anthropic-format SSE stream and reassemble it into one response
+///
+/// Text deltas are concatenated in order, the id, model and input tokens come
+/// from `message_start`, and the stop reason and output tokens from the last
+/// `message_delta`. An upstream `error` event or a failed chunk fails the whole
+/// request, since a partial answer would be indistinguishable from a full one.
+pub async fn aggregate_stream(mut stream: StreamResponse) -> Result<AnthropicResponse, AppError> {
+    let mut aggregator = StreamAggregator::default();
+    let mut buffer = String::new();
+
+    while let Some(chunk) = stream.next().await {
+        buffer.push_str(&chunk?);
+        while let Some(pos) = buffer.find("\n\n") {
+            let event: String = buffer.drain(..pos + 2).collect();
+            aggregator.observe_event(&event)?;
+        }
+    }
+    aggregator.observe_event(&buffer)?;
+
+    Ok(aggregator.finish())
+}
+
+/// Running state of a stream being reassembled
+#[derive(Default)]
+struct StreamAggregator {
+    id: String,
+    model: String,
+    text: String,
+    input_tokens: u32,
+    output_tokens: u32,
+    stop_reason: Option<String>,
+}
+
+impl StreamAggregator {
+    fn observe_event(&mut self, event: &str) -> Result<(), AppError> {
+        for data in event.lines().filter_map(|line| line.strip_prefix("data:")) {
+            let Ok(value) = serde_json::from_str::<Value>(data.trim()) else {
+                continue;
+            };
+            match value.get("type").and_then(Value::as_str) {
+                Some("message_start") => {
+                    let message = &value["message"];
+                    if let Some(id) = message["id"].as_str() {
+                        self.id = id.to_string();
+                    }
+                    if let Some(model) = message["model"].as_str() {
+                        self.model = model.to_string();
+                    }
+                    self.observe_usage(&message["usage"]);
+                }
+                Some("content_block_delta") => {
+                    if let Some(text) = value["delta"]["text"].as_str() {
+                        self.text.push_str(text);
+                    }
+                }
+                Some("message_delta") => {
+                    if let Some(reason) = value["delta"]["stop_reason"].as_str() {
+                        self.stop_reason = Some(reason.to_string());
+                    }
+                    self.observe_usage(&value["usage"]);
+                    self.observe_usage(&value["delta"]["usage"]);
+                }
+                Some("error") => {
+                    let message = value["error"]["message"].as_str().unwrap_or("unknown error");
+                    return Err(AppError::StreamingError(format!(
+                        "Upstream stream failed: {}",
+                        message
+                    )));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn observe_usage(&mut self, usage: &Value) {
+        if let Some(input) = usage["input_tokens"].as_u64().filter(|v| *v > 0) {
+            self.input_tokens = input as u32;
+        }
+        if let Some(output) = usage["output_tokens"].as_u64().filter(|v| *v > 0) {
+            self.output_tokens = output as u32;
+        }
+    }
+
+    fn finish(self) -> AnthropicResponse {
+        AnthropicResponse::new(self.id, self.model, self.text, self.input_tokens, self.output_tokens)
+            .with_stop_reason(self.stop_reason)
+            .flag_refusal()
+    }
+}
@@ -1,20 +1,270 @@
-use serde::{Deserialize, Serialize};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::config::ValidationMode;
+use crate::errors::FieldError;
+
+/// Fallback `max_tokens` applied when neither the request nor the config sets one
+pub const DEFAULT_MAX_TOKENS: u32 = 1024;
+
+/// Largest `max_tokens` accepted when the provider has no `max_output_tokens` configured
+pub const DEFAULT_MAX_OUTPUT_TOKENS: u32 = 8192;
+
+/// Longest conversation accepted when the provider has no `max_messages` configured
+pub const DEFAULT_MAX_MESSAGES: u32 = 100;
+
+/// Longest end-user identifier accepted in `user`
+pub const MAX_USER_LENGTH: usize = 256;
+
+/// JSON types checked by `AnthropicRequest::schema_errors`
+#[derive(Debug, Clone, Copy)]
+enum JsonKind {
+    String,
+    Number,
+    UnsignedInteger,
+    Integer,
+    Boolean,
+    Array,
+    Object,
+}
+
+impl JsonKind {
+    fn matches(self, value: &Value) -> bool {
+        match self {
+            JsonKind::String => value.is_string(),
+            JsonKind::Number => value.is_number(),
+            JsonKind::UnsignedInteger => value.as_u64().is_some_and(|n| n <= u32::MAX as u64),
+            JsonKind::Integer => value.is_i64(),
+            JsonKind::Boolean => value.is_boolean(),
+            JsonKind::Array => value.is_array(),
+            JsonKind::Object => value.is_object(),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            JsonKind::String => "string",
+            JsonKind::Number => "number",
+            JsonKind::UnsignedInteger => "non-negative integer",
+            JsonKind::Integer => "integer",
+            JsonKind::Boolean => "boolean",
+            JsonKind::Array => "array",
+            JsonKind::Object => "object",
+        }
+    }
+}
 
 /// Standard request format based on Anthropic API
 /// 
 /// This serves as the unified request format that all providers
 /// must accept and convert to their specific API format.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct AnthropicRequest {
     pub model: String,
     pub messages: Vec<Message>,
-    pub max_tokens: u32,
+    /// Optional on the wire; providers fill in the configured default before dispatch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f32>,
+    /// Number of completions to generate; mapped to OpenAI `n` / Gemini `candidateCount`.
+    /// Never sent to Anthropic, which only supports a single completion.
+    #[serde(default, skip_serializing)]
+    pub n: Option<u32>,
+    /// OpenAI-style structured output directive (`{"type":"json_object"}` or `json_schema`).
+    /// Anthropic has no native equivalent, so it is turned into a system-prompt instruction.
+    #[serde(default, skip_serializing)]
+    pub response_format: Option<serde_json::Value>,
+    /// Return log probabilities of the output tokens (OpenAI only).
+    /// Never sent to Anthropic; Anthropic and Gemini reject requests that set it.
+    #[serde(default, skip_serializing)]
+    pub logprobs: Option<bool>,
+    /// Number of most likely alternatives to return per token position (0-20); requires `logprobs`
+    #[serde(default, skip_serializing)]
+    pub top_logprobs: Option<u32>,
+    /// Token budget for the model's internal reasoning (Gemini 2.5 `thinkingBudget`).
+    /// Ignored by other providers; never sent to Anthropic.
+    #[serde(default, skip_serializing)]
+    pub thinking_budget: Option<u32>,
+    /// Seed for reproducible sampling; mapped to OpenAI `seed` / Gemini `generationConfig.seed`.
+    /// Never sent to Anthropic, which has no equivalent.
+    #[serde(default, skip_serializing)]
+    pub seed: Option<i64>,
+    /// End-user identifier for abuse monitoring; sent to OpenAI as `user`.
+    /// Other providers have no equivalent, so it only appears in the proxy's logs.
+    #[serde(default, skip_serializing)]
+    pub user: Option<String>,
+    /// Tools the model may call; sent to Anthropic as is and converted for other providers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<ToolDefinition>>,
+    /// How the model should use `tools`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    /// Per-request upstream timeout set by the proxy; never read from or sent on the wire
+    #[serde(skip)]
+    pub timeout_override: Option<std::time::Duration>,
+    /// Validation settings set by the proxy from config; never read from or sent on the wire
+    #[serde(skip)]
+    pub validation: ValidationContext,
+}
+
+/// Deployment-level settings that change how strictly requests are validated
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ValidationContext {
+    /// Require messages to strictly alternate user/assistant.
+    /// When false, consecutive messages with the same role are accepted.
+    pub strict_role_alternation: bool,
+    /// Per-model input token limit. When set, the estimated input tokens are checked
+    /// against it instead of the byte-based total content length limit.
+    pub max_input_tokens: Option<u32>,
+    /// Provider's `max_output_tokens`. When unset, `max_tokens` is capped at
+    /// `DEFAULT_MAX_OUTPUT_TOKENS`.
+    pub max_output_tokens: Option<u32>,
+    /// Provider's `max_messages`. When unset, at most `DEFAULT_MAX_MESSAGES`
+    /// messages are accepted.
+    pub max_messages: Option<u32>,
+    /// Lenient mode clamps out-of-range sampling parameters (see
+    /// `clamp_sampling_params`) instead of rejecting them.
+    pub mode: ValidationMode,
+}
+
+impl Default for ValidationContext {
+    fn default() -> Self {
+        Self {
+            strict_role_alternation: true,
+            max_input_tokens: None,
+            max_output_tokens: None,
+            max_messages: None,
+            mode: ValidationMode::Strict,
+        }
+    }
+}
+
+/// Tool (function) the model may call, in Anthropic's format
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ToolDefinition {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// JSON schema of the tool's input object
+    pub input_schema: serde_json::Value,
+}
+
+/// How the model should use the tools it is given
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ToolChoice {
+    /// The model decides whether to call a tool
+    Auto,
+    /// The model must call one of the tools
+    Any,
+    /// The model must call the named tool
+    Tool { name: String },
+    /// The model must not call any tool
+    None,
+}
+
+/// Structured output mode parsed from `response_format`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResponseFormat {
+    /// Plain text output (the default)
+    Text,
+    /// Any valid JSON object
+    JsonObject,
+    /// JSON matching the given schema
+    JsonSchema {
+        name: String,
+        schema: serde_json::Value,
+    },
+}
+
+impl ResponseFormat {
+    /// 解析OpenAI格式的`response_format`
+    ///
+    /// ## 功能说明
+    /// 仅接受`{"type":"text"}`、`{"type":"json_object"}`和
+    /// `{"type":"json_schema","json_schema":{"name":...,"schema":{...}}}`三种形式
+    ///
+    /// ## 参数说明
+    /// - `value`: 客户端提交的`response_format`原始JSON
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let format = ResponseFormat::parse(&json!({"type": "json_object"}))?;
+    /// assert_eq!(format, ResponseFormat::JsonObject);
+    /// ```
+    ///
+    /// ## 返回值
+    /// - `Ok(ResponseFormat)`: 解析成功
+    /// - `Err(String)`: 不支持的形式及原因
+    pub fn parse(value: &serde_json::Value) -> Result<Self, String> {
+        let format_type = value
+            .get("type")
+            .and_then(|t| t.as_str())
+            .ok_or_else(|| "response_format must be an object with a string 'type'".to_string())?;
+
+        match format_type {
+            "text" => Ok(Self::Text),
+            "json_object" => Ok(Self::JsonObject),
+            "json_schema" => {
+                let json_schema = value
+                    .get("json_schema")
+                    .ok_or_else(|| "response_format of type 'json_schema' requires a 'json_schema' object".to_string())?;
+                let schema = json_schema
+                    .get("schema")
+                    .filter(|schema| schema.is_object())
+                    .ok_or_else(|| "response_format.json_schema.schema must be a JSON object".to_string())?;
+                let name = json_schema
+                    .get("name")
+                    .and_then(|name| name.as_str())
+                    .unwrap_or("response")
+                    .to_string();
+                Ok(Self::JsonSchema {
+                    name,
+                    schema: schema.clone(),
+                })
+            }
+            other => Err(format!(
+                "Unsupported response_format type '{}': must be one of text, json_object, json_schema",
+                other
+            )),
+        }
+    }
+
+    /// Whether the output must be JSON
+    pub fn is_json(&self) -> bool {
+        !matches!(self, Self::Text)
+    }
+
+    /// JSON schema to enforce, if any
+    pub fn schema(&self) -> Option<&serde_json::Value> {
+        match self {
+            Self::JsonSchema { schema, .. } => Some(schema),
+            _ => None,
+        }
+    }
+
+    /// System-prompt instruction for providers without a native JSON mode
+    pub fn system_instruction(&self) -> Option<String> {
+        match self {
+            Self::Text => None,
+            Self::JsonObject => Some(
+                "Respond only with a single valid JSON object. Do not include any text outside the JSON.".to_string(),
+            ),
+            Self::JsonSchema { schema, .. } => Some(format!(
+                "Respond only with a single valid JSON object that conforms to this JSON schema. \
+                 Do not include any text outside the JSON.\n{}",
+                schema
+            )),
+        }
+    }
 }
 
 /// Message structure for chat conversations
@@ -111,20 +361,114 @@ impl Message {
 /// 
 /// All providers must convert their responses to this format
 /// to ensure consistent client experience.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AnthropicResponse {
     pub id: String,
     pub model: String,
     pub content: Vec<ContentBlock>,
     pub usage: Usage,
+    /// Why generation stopped: `end_turn`, `max_tokens`, `stop_sequence`, `tool_use`,
+    /// `content_filter` or `refusal`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<String>,
+    /// Token log probabilities returned by the provider when the request set `logprobs`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<serde_json::Value>,
+    /// Set when the model refused to answer or a content filter withheld the output
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub refused: bool,
+    /// Why the response was refused or filtered, when `refused` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refusal_reason: Option<String>,
+    /// Allow-listed upstream response headers, already renamed for the client; never serialized
+    #[serde(skip)]
+    pub upstream_headers: Vec<(String, String)>,
+    /// Backend configuration fingerprint returned by OpenAI; surfaced in `x_ai_proxy` for seeded requests
+    #[serde(skip)]
+    pub system_fingerprint: Option<String>,
+    /// Proxy-side routing, latency and cost summary, set by the handler (never by providers)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x_ai_proxy: Option<ProxyMetadata>,
+}
+
+/// Namespaced summary of how the proxy served a response
+///
+/// Serialized as `x_ai_proxy` so it cannot clash with native Anthropic fields.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ProxyMetadata {
+    /// ID of the provider that handled the request
+    pub provider: String,
+    /// Model reported by the provider, which may differ from the requested alias
+    pub resolved_model: String,
+    /// Time from receiving the request to having the full response
+    pub latency_ms: u64,
+    /// Cost in USD from the configured `costs`; `null` for unpriced models
+    pub cost_usd: Option<f64>,
+    /// Upstream `system_fingerprint`, present when the request set a `seed` and the provider returned one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_fingerprint: Option<String>,
 }
 
 /// Content block within a response
-#[derive(Serialize, Deserialize, Debug, Clone)]
+///
+/// Either a `text` block or a `tool_use` block carrying `id`, `name` and `input`.
+#[derive(Deserialize, Debug, Clone)]
 pub struct ContentBlock {
     #[serde(rename = "type")]
-    pub type_field: String, // "text"
+    pub type_field: String, // "text" or "tool_use"
+    #[serde(default)]
     pub text: String,
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub input: Option<serde_json::Value>,
+}
+
+impl ContentBlock {
+    /// Text block
+    pub fn text(text: String) -> Self {
+        Self {
+            type_field: "text".to_string(),
+            text,
+            id: None,
+            name: None,
+            input: None,
+        }
+    }
+
+    /// Tool call block
+    pub fn tool_use(id: String, name: String, input: serde_json::Value) -> Self {
+        Self {
+            type_field: "tool_use".to_string(),
+            text: String::new(),
+            id: Some(id),
+            name: Some(name),
+            input: Some(input),
+        }
+    }
+
+    /// Whether this block is a tool call
+    pub fn is_tool_use(&self) -> bool {
+        self.type_field == "tool_use"
+    }
+}
+
+/// Text blocks carry only `text`, tool calls only `id`, `name` and `input`
+impl Serialize for ContentBlock {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("type", &self.type_field)?;
+        if self.is_tool_use() {
+            map.serialize_entry("id", &self.id)?;
+            map.serialize_entry("name", &self.name)?;
+            map.serialize_entry("input", &self.input.clone().unwrap_or_else(|| serde_json::json!({})))?;
+        } else {
+            map.serialize_entry("text", &self.text)?;
+        }
+        map.end()
+    }
 }
 
 /// Token usage information
@@ -132,6 +476,9 @@ pub struct ContentBlock {
 pub struct Usage {
     pub input_tokens: u32,
     pub output_tokens: u32,
+    /// Reasoning tokens reported separately by the provider (Gemini `thoughtsTokenCount`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking_tokens: Option<u32>,
 }
 
 // Streaming event structures for Server-Sent Events
@@ -146,6 +493,10 @@ pub enum AnthropicStreamEvent {
     ContentBlockStart { index: u32, content_block: ContentBlockStart },
     #[serde(rename = "content_block_delta")]
     ContentBlockDelta { index: u32, delta: TextDelta },
+    #[serde(rename = "content_block_start")]
+    ToolUseBlockStart { index: u32, content_block: ToolUseStart },
+    #[serde(rename = "content_block_delta")]
+    ToolInputDelta { index: u32, delta: InputJsonDelta },
     #[serde(rename = "content_block_stop")]
     ContentBlockStop { index: u32 },
     #[serde(rename = "message_delta")]
@@ -156,6 +507,23 @@ pub enum AnthropicStreamEvent {
     Error { error: StreamError },
 }
 
+impl AnthropicStreamEvent {
+    /// SSE `event:` name for this event, identical to its serialized `type`
+    pub fn event_name(&self) -> &'static str {
+        match self {
+            AnthropicStreamEvent::MessageStart { .. } => "message_start",
+            AnthropicStreamEvent::ContentBlockStart { .. } => "content_block_start",
+            AnthropicStreamEvent::ContentBlockDelta { .. } => "content_block_delta",
+            AnthropicStreamEvent::ToolUseBlockStart { .. } => "content_block_start",
+            AnthropicStreamEvent::ToolInputDelta { .. } => "content_block_delta",
+            AnthropicStreamEvent::ContentBlockStop { .. } => "content_block_stop",
+            AnthropicStreamEvent::MessageDelta { .. } => "message_delta",
+            AnthropicStreamEvent::MessageStop => "message_stop",
+            AnthropicStreamEvent::Error { .. } => "error",
+        }
+    }
+}
+
 /// Simplified message structure for streaming start events
 #[derive(Serialize, Debug, Clone)]
 pub struct StreamMessage {
@@ -182,6 +550,24 @@ pub struct TextDelta {
     pub text: String,
 }
 
+/// `tool_use` content block start; the input is streamed afterwards as partial JSON
+#[derive(Serialize, Debug, Clone)]
+pub struct ToolUseStart {
+    #[serde(rename = "type")]
+    pub type_field: String, // "tool_use"
+    pub id: String,
+    pub name: String,
+    pub input: serde_json::Value,
+}
+
+/// Partial JSON delta for a streaming `tool_use` block
+#[derive(Serialize, Debug, Clone)]
+pub struct InputJsonDelta {
+    #[serde(rename = "type")]
+    pub type_field: String, // "input_json_delta"
+    pub partial_json: String,
+}
+
 /// Message delta for streaming updates
 #[derive(Serialize, Debug, Clone)]
 pub struct MessageDelta {
@@ -197,6 +583,9 @@ pub struct StreamError {
     #[serde(rename = "type")]
     pub error_type: String,
     pub message: String,
+    /// Output tokens already streamed when the upstream failed mid-generation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_tokens: Option<u32>,
 }
 
 /// Server-Sent Event wrapper for streaming responses
@@ -265,7 +654,7 @@ impl AnthropicRequest {
     /// - **模型验证**: 名称格式、长度限制
     /// - **消息验证**: 数量限制、角色序列、内容有效性
     /// - **Token验证**: max_tokens范围检查
-    /// - **参数验证**: temperature和top_p取值范围
+    /// - **参数验证**: temperature、top_p和n取值范围
     /// - **长度验证**: 总内容长度限制
     ///
     /// ## 执行例子
@@ -273,7 +662,7 @@ impl AnthropicRequest {
     /// let request = AnthropicRequest {
     ///     model: "claude-3-sonnet".to_string(),
     ///     messages: vec![Message::user("Hello".to_string())],
-    ///     max_tokens: 1000,
+    ///     max_tokens: Some(1000),
     ///     temperature: Some(0.7),
     ///     top_p: Some(0.9),
     ///     stream: Some(false),
@@ -281,20 +670,259 @@ impl AnthropicRequest {
     /// request.validate()?;
     /// ```
     pub fn validate(&self) -> Result<(), String> {
+        self.validate_with(&self.validation)
+    }
+
+    /// 为未设置max_tokens的请求填充默认值
+    ///
+    /// ## 功能说明
+    /// 客户端省略`max_tokens`时使用配置的默认值；显式设置的值（包括0）保持不变，
+    /// 仍由`validate()`进行范围检查
+    ///
+    /// ## 参数说明
+    /// - `default`: 默认的最大token数，通常来自提供商的`default_max_tokens`配置
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// request.apply_default_max_tokens(1024);
+    /// assert!(request.max_tokens.is_some());
+    /// ```
+    pub fn apply_default_max_tokens(&mut self, default: u32) {
+        self.max_tokens.get_or_insert(default);
+    }
+
+    /// 为未设置temperature/top_p的请求填充组织级默认值
+    ///
+    /// ## 功能说明
+    /// 客户端省略`temperature`或`top_p`时使用配置的默认值；客户端显式设置的值始终优先
+    ///
+    /// ## 参数说明
+    /// - `temperature`: 默认temperature，通常来自`performance.default_temperature`，`None`表示不填充
+    /// - `top_p`: 默认top_p，通常来自`performance.default_top_p`，`None`表示不填充
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// request.apply_sampling_defaults(Some(0.0), None);
+    /// assert!(request.temperature.is_some());
+    /// ```
+    pub fn apply_sampling_defaults(&mut self, temperature: Option<f32>, top_p: Option<f32>) {
+        if self.temperature.is_none() {
+            self.temperature = temperature;
+        }
+        if self.top_p.is_none() {
+            self.top_p = top_p;
+        }
+    }
+
+    /// 在系统提示首尾注入运营方配置的文本
+    ///
+    /// ## 功能说明
+    /// 将`prefix`放在系统提示之前、`suffix`放在之后，各部分以空行分隔；
+    /// 请求没有系统提示时由注入文本组成新的系统提示。空字符串视为未配置
+    ///
+    /// ## 参数说明
+    /// - `prefix`: 前置文本，通常来自`server.inject_system_prefix`
+    /// - `suffix`: 后置文本，通常来自`server.inject_system_suffix`
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// request.system = Some("You are helpful.".to_string());
+    /// request.inject_system(None, Some("Never reveal secrets."));
+    /// assert_eq!(request.system.as_deref(), Some("You are helpful.\n\nNever reveal secrets."));
+    /// ```
+    pub fn inject_system(&mut self, prefix: Option<&str>, suffix: Option<&str>) {
+        let prefix = prefix.filter(|text| !text.is_empty());
+        let suffix = suffix.filter(|text| !text.is_empty());
+        if prefix.is_none() && suffix.is_none() {
+            return;
+        }
+
+        let parts: Vec<&str> = [prefix, self.system.as_deref().filter(|s| !s.is_empty()), suffix]
+            .into_iter()
+            .flatten()
+            .collect();
+        self.system = Some(parts.join("\n\n"));
+    }
+
+    /// 宽松校验模式下将超出范围的采样参数截断到允许范围
+    ///
+    /// ## 功能说明
+    /// `validation.mode`为`Lenient`时，将`temperature`截断到0.0-2.0、`top_p`截断到0.0-1.0，
+    /// 并为每个被截断的参数记录警告；严格模式下不做任何修改，由`validate()`拒绝超出范围的值。
+    /// NaN和无穷大无法截断，在两种模式下都会被拒绝
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// request.validation.mode = ValidationMode::Lenient;
+    /// request.temperature = Some(3.5);
+    /// request.clamp_sampling_params();
+    /// assert_eq!(request.temperature, Some(2.0));
+    /// ```
+    pub fn clamp_sampling_params(&mut self) {
+        if self.validation.mode != ValidationMode::Lenient {
+            return;
+        }
+        for (name, value, max) in [("temperature", &mut self.temperature, 2.0), ("top_p", &mut self.top_p, 1.0)] {
+            if let Some(v) = value.as_mut()
+                && v.is_finite()
+                && !(0.0..=max).contains(v)
+            {
+                let clamped = v.clamp(0.0, max);
+                tracing::warn!("Clamped out-of-range {} {} to {} (lenient validation)", name, v, clamped);
+                *v = clamped;
+            }
+        }
+    }
+
+    /// 移除模型不支持的请求参数
+    ///
+    /// ## 功能说明
+    /// 将`params`中列出的可选参数置空，使请求能够发往不接受这些参数的模型
+    /// （例如不支持`temperature`的推理模型），而不是由上游返回错误
+    ///
+    /// ## 参数说明
+    /// - `params`: 要移除的参数名，通常来自`Config::unsupported_params_for`，未知名称会被忽略
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// request.temperature = Some(0.7);
+    /// let stripped = request.strip_params(&["temperature".to_string()]);
+    /// assert_eq!(stripped, vec!["temperature"]);
+    /// assert!(request.temperature.is_none());
+    /// ```
+    ///
+    /// ## 返回值
+    /// - `Vec<&str>`: 请求中原本设置、实际被移除的参数名
+    pub fn strip_params<'a>(&mut self, params: &'a [String]) -> Vec<&'a str> {
+        params
+            .iter()
+            .map(String::as_str)
+            .filter(|param| match *param {
+                "temperature" => self.temperature.take().is_some(),
+                "top_p" => self.top_p.take().is_some(),
+                "n" => self.n.take().is_some(),
+                "response_format" => self.response_format.take().is_some(),
+                "logprobs" => self.logprobs.take().is_some(),
+                "top_logprobs" => self.top_logprobs.take().is_some(),
+                "thinking_budget" => self.thinking_budget.take().is_some(),
+                "seed" => self.seed.take().is_some(),
+                "tools" => self.tools.take().is_some(),
+                "tool_choice" => self.tool_choice.take().is_some(),
+                _ => false,
+            })
+            .collect()
+    }
+
+    /// 在反序列化之前按内部模式检查原始请求体
+    ///
+    /// ## 功能说明
+    /// 检查必填字段是否存在、各字段的JSON类型是否正确，一次性列出所有问题字段，
+    /// 以替代反序列化失败时只报告第一个错误的简短提示
+    ///
+    /// ## 内部实现逻辑
+    /// 1. 请求体必须是JSON对象
+    /// 2. `model`和`messages`为必填字段，每条消息必须包含字符串类型的`role`和`content`
+    /// 3. 可选字段（如`max_tokens`、`temperature`、`stream`）为`null`时视为未设置，否则检查类型
+    ///
+    /// ## 参数说明
+    /// - `body`: 解析后的原始请求体
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let errors = AnthropicRequest::schema_errors(&json!({"messages": []}));
+    /// assert_eq!(errors[0].field, "model");
+    /// ```
+    ///
+    /// ## 返回值
+    /// 问题字段列表，为空表示请求体符合模式
+    pub fn schema_errors(body: &Value) -> Vec<FieldError> {
+        let Some(object) = body.as_object() else {
+            return vec![FieldError::new("body", "expected a JSON object")];
+        };
+
+        let mut errors = Vec::new();
+        let mut check = |field: &str, required: bool, kind: JsonKind| match object.get(field) {
+            None | Some(Value::Null) if required => {
+                errors.push(FieldError::new(field, "missing required field"));
+            }
+            Some(value) if !value.is_null() && !kind.matches(value) => {
+                errors.push(FieldError::new(field, format!("expected {}", kind.name())));
+            }
+            _ => {}
+        };
+
+        check("model", true, JsonKind::String);
+        check("messages", true, JsonKind::Array);
+        check("max_tokens", false, JsonKind::UnsignedInteger);
+        check("system", false, JsonKind::String);
+        check("stream", false, JsonKind::Boolean);
+        check("temperature", false, JsonKind::Number);
+        check("top_p", false, JsonKind::Number);
+        check("n", false, JsonKind::UnsignedInteger);
+        check("logprobs", false, JsonKind::Boolean);
+        check("top_logprobs", false, JsonKind::UnsignedInteger);
+        check("seed", false, JsonKind::Integer);
+        check("user", false, JsonKind::String);
+        check("tools", false, JsonKind::Array);
+        check("tool_choice", false, JsonKind::Object);
+
+        if let Some(messages) = object.get("messages").and_then(Value::as_array) {
+            for (index, message) in messages.iter().enumerate() {
+                let Some(message) = message.as_object() else {
+                    errors.push(FieldError::new(format!("messages[{}]", index), "expected object"));
+                    continue;
+                };
+                for field in ["role", "content"] {
+                    let path = format!("messages[{}].{}", index, field);
+                    match message.get(field) {
+                        None | Some(Value::Null) => errors.push(FieldError::new(path, "missing required field")),
+                        Some(value) if !value.is_string() => errors.push(FieldError::new(path, "expected string")),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        errors
+    }
+
+    /// 使用指定的验证上下文验证请求
+    ///
+    /// ## 功能说明
+    /// 与`validate()`相同，但使用显式传入的验证上下文（如是否严格要求角色交替）
+    ///
+    /// ## 参数说明
+    /// - `context`: 验证上下文，通常来自`security.strict_role_alternation`和`max_input_tokens`配置
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let context = ValidationContext { strict_role_alternation: false, ..Default::default() };
+    /// request.validate_with(&context)?;
+    /// ```
+    pub fn validate_with(&self, context: &ValidationContext) -> Result<(), String> {
         // 模型验证
         self.validate_model()?;
 
         // 消息验证
-        self.validate_messages()?;
+        self.validate_messages(context)?;
 
         // Token限制验证
-        self.validate_token_limits()?;
+        self.validate_token_limits(context)?;
 
         // 参数范围验证
         self.validate_parameters()?;
 
-        // 内容长度验证
-        self.validate_content_length()?;
+        // 工具定义验证
+        self.validate_tools()?;
+
+        // 结构化输出格式验证
+        self.parsed_response_format()?;
+
+        // 内容长度验证：配置了模型token上限时按估算token数检查，否则回退到字节数检查
+        match context.max_input_tokens {
+            Some(limit) => self.validate_input_tokens(limit)?,
+            None => self.validate_content_length()?,
+        }
 
         Ok(())
     }
@@ -309,8 +937,9 @@ impl AnthropicRequest {
             return Err("Model name too long (max 100 characters)".to_string());
         }
         
-        // Check for valid model name format (alphanumeric, hyphens, underscores, dots)
-        if !self.model.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '.') {
+        // Check for valid model name format (alphanumeric, hyphens, underscores, dots,
+        // plus '/' and ':' for routed names like OpenRouter's "anthropic/claude-3-sonnet:beta")
+        if !self.model.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | '/' | ':')) {
             return Err("Model name contains invalid characters".to_string());
         }
         
@@ -318,13 +947,14 @@ impl AnthropicRequest {
     }
     
     /// Validate messages array
-    fn validate_messages(&self) -> Result<(), String> {
+    fn validate_messages(&self, context: &ValidationContext) -> Result<(), String> {
         if self.messages.is_empty() {
             return Err("Messages cannot be empty".to_string());
         }
         
-        if self.messages.len() > 100 {
-            return Err("Too many messages (max 100)".to_string());
+        let limit = context.max_messages.unwrap_or(DEFAULT_MAX_MESSAGES);
+        if self.messages.len() > limit as usize {
+            return Err(format!("Too many messages (max {})", limit));
         }
         
         // Validate conversation flow (should start with user, alternate properly)
@@ -332,34 +962,52 @@ impl AnthropicRequest {
             // return Err("Conversation must start with a user message".to_string());
         // }
         
-        // Check for proper role alternation and validate each message
-        let mut expected_role = "user";
+        // Relaxed mode still requires a leading user message and valid roles
+        if !context.strict_role_alternation {
+            for message in &self.messages {
+                message.validate()?;
+            }
+            if self.messages[0].role != "user" {
+                return Err(format!(
+                    "Invalid role sequence at message 0: expected 'user', got '{}'",
+                    self.messages[0].role
+                ));
+            }
+            return Ok(());
+        }
+
+        // Roles alternate starting with user; the conversation may end on either
+        // role, since a trailing assistant message is a prefill to continue
         for (i, message) in self.messages.iter().enumerate() {
             message.validate()?;
-            
-            if message.role != expected_role && !(i == 0 && message.role == "user") {
+
+            let expected_role = if i % 2 == 0 { "user" } else { "assistant" };
+            if message.role != expected_role {
                 return Err(format!(
                     "Invalid role sequence at message {}: expected '{}', got '{}'",
                     i, expected_role, message.role
                 ));
             }
-            
-            expected_role = if message.role == "user" { "assistant" } else { "user" };
         }
         
         Ok(())
     }
     
-    /// Validate token limits
-    fn validate_token_limits(&self) -> Result<(), String> {
-        if self.max_tokens == 0 {
+    /// Validate token limits against the provider's output cap
+    fn validate_token_limits(&self, context: &ValidationContext) -> Result<(), String> {
+        let Some(max_tokens) = self.max_tokens else {
+            return Ok(());
+        };
+
+        if max_tokens == 0 {
             return Err("max_tokens must be greater than 0".to_string());
         }
-        
-        if self.max_tokens > 8192 {
-            return Err("max_tokens cannot exceed 8192".to_string());
+
+        let limit = context.max_output_tokens.unwrap_or(DEFAULT_MAX_OUTPUT_TOKENS);
+        if max_tokens > limit {
+            return Err(format!("max_tokens cannot exceed {}", limit));
         }
-        
+
         Ok(())
     }
     
@@ -382,15 +1030,81 @@ impl AnthropicRequest {
                 return Err("top_p must be between 0.0 and 1.0".to_string());
             }
         }
+
+        if let Some(top_logprobs) = self.top_logprobs {
+            if top_logprobs > 20 {
+                return Err("top_logprobs must be between 0 and 20".to_string());
+            }
+            if self.logprobs != Some(true) {
+                return Err("top_logprobs requires logprobs to be true".to_string());
+            }
+        }
+
+        if let Some(n) = self.n {
+            if !(1..=8).contains(&n) {
+                return Err("n must be between 1 and 8".to_string());
+            }
+            if n > 1 && self.is_streaming() {
+                return Err("n > 1 is not supported for streaming requests".to_string());
+            }
+        }
+
+        if let Some(user) = &self.user
+            && (user.trim().is_empty() || user.len() > MAX_USER_LENGTH)
+        {
+            return Err(format!("user must be between 1 and {} characters", MAX_USER_LENGTH));
+        }
         
         Ok(())
     }
     
+    /// Validate tool definitions and that `tool_choice` refers to one of them
+    fn validate_tools(&self) -> Result<(), String> {
+        let tools = self.tools.as_deref().unwrap_or_default();
+        for tool in tools {
+            if tool.name.is_empty() || tool.name.len() > 64 {
+                return Err("Tool name must be between 1 and 64 characters".to_string());
+            }
+            if !tool.name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-')) {
+                return Err(format!("Tool name '{}' contains invalid characters", tool.name));
+            }
+            if !tool.input_schema.is_object() {
+                return Err(format!("Tool '{}' input_schema must be a JSON object", tool.name));
+            }
+        }
+
+        match &self.tool_choice {
+            Some(ToolChoice::None) | None => {}
+            Some(_) if tools.is_empty() => {
+                return Err("tool_choice requires at least one tool".to_string());
+            }
+            Some(ToolChoice::Tool { name }) if !tools.iter().any(|tool| &tool.name == name) => {
+                return Err(format!("tool_choice refers to unknown tool '{}'", name));
+            }
+            Some(_) => {}
+        }
+
+        Ok(())
+    }
+
+    /// Validate the estimated input tokens against a per-model limit
+    fn validate_input_tokens(&self, limit: u32) -> Result<(), String> {
+        let estimated = self.estimate_input_tokens();
+        if estimated > limit {
+            return Err(format!(
+                "Estimated input tokens ({}) exceed the limit of {} for model {}",
+                estimated, limit, self.model
+            ));
+        }
+        Ok(())
+    }
+
     /// Validate total content length
     fn validate_content_length(&self) -> Result<(), String> {
         let total_content_length: usize = self.messages.iter()
             .map(|m| m.content.len())
-            .sum();
+            .sum::<usize>()
+            + self.system.as_ref().map_or(0, |s| s.len());
         
         if total_content_length > 100_000 {
             return Err("Total content length exceeds maximum (100KB)".to_string());
@@ -426,7 +1140,7 @@ impl AnthropicRequest {
     /// 基于字符数量粗略估算请求的输入token数量
     ///
     /// ## 内部实现逻辑
-    /// 1. 计算所有消息内容和角色的总字符数
+    /// 1. 计算所有消息内容和角色（以及系统提示）的总字符数
     /// 2. 使用1 token ≈ 4字符的粗略比例进行估算
     /// 3. 确保至少返回1个token
     ///
@@ -442,9 +1156,132 @@ impl AnthropicRequest {
         // 粗略估算：1 token ≈ 4 字符
         let total_chars: usize = self.messages.iter()
             .map(|m| m.content.len() + m.role.len())
-            .sum();
+            .sum::<usize>()
+            + self.system.as_ref().map_or(0, |s| s.len());
         (total_chars / 4).max(1) as u32
     }
+
+    /// 计算本次请求的上游超时时间
+    ///
+    /// ## 功能说明
+    /// 优先使用请求级别的超时覆盖（来自`x-ai-proxy-timeout`头），否则回退到提供商配置的超时
+    ///
+    /// ## 参数说明
+    /// - `provider_timeout_seconds`: 提供商配置的读取超时（`read_timeout_secs()`）
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let timeout = request.upstream_timeout(config.read_timeout_secs());
+    /// client.post(&url).timeout(timeout);
+    /// ```
+    pub fn upstream_timeout(&self, provider_timeout_seconds: u64) -> std::time::Duration {
+        self.timeout_override
+            .unwrap_or_else(|| std::time::Duration::from_secs(provider_timeout_seconds))
+    }
+
+    /// 请求是否要求返回token对数概率
+    ///
+    /// ## 功能说明
+    /// `logprobs`为true或设置了`top_logprobs`时返回true，供不支持对数概率的提供商拒绝请求
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// if request.requests_logprobs() {
+    ///     return Err(AppError::ValidationError("logprobs not supported".to_string()));
+    /// }
+    /// ```
+    ///
+    /// ## 返回值
+    /// 是否请求了对数概率
+    pub fn requests_logprobs(&self) -> bool {
+        self.logprobs == Some(true) || self.top_logprobs.is_some()
+    }
+
+    /// 计算可共享结果的请求键
+    ///
+    /// ## 功能说明
+    /// 只有确定性的请求（`temperature`为0）的结果才能在调用方之间共享；
+    /// 请求合并（single-flight）以此键识别相同的请求
+    ///
+    /// ## 内部实现逻辑
+    /// 1. 流式请求或`temperature`不为0时返回`None`
+    /// 2. 将请求序列化为JSON并附加不参与序列化的生成参数（`n`、`thinking_budget`等），作为完整的请求键
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// if let Some(key) = request.cache_key() {
+    ///     // 相同键的并发请求可以共享同一个上游调用
+    /// }
+    /// ```
+    ///
+    /// ## 返回值
+    /// - `Some(String)`: 请求是确定性的，可以共享结果
+    /// - `None`: 请求结果不可共享
+    pub fn cache_key(&self) -> Option<String> {
+        if self.stream.unwrap_or(false) || self.temperature != Some(0.0) {
+            return None;
+        }
+
+        let body = serde_json::to_string(self).ok()?;
+        let response_format = self
+            .response_format
+            .as_ref()
+            .map(|format| format.to_string())
+            .unwrap_or_default();
+        Some(format!(
+            "{}|n={}|response_format={}|logprobs={}|top_logprobs={}|seed={:?}|thinking_budget={:?}",
+            body,
+            self.n.unwrap_or(1),
+            response_format,
+            self.logprobs.unwrap_or(false),
+            self.top_logprobs.unwrap_or(0),
+            self.seed,
+            self.thinking_budget
+        ))
+    }
+
+    /// 解析请求中的`response_format`
+    ///
+    /// ## 功能说明
+    /// 将客户端提交的OpenAI风格`response_format`解析为`ResponseFormat`，供各提供商转换使用
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// if let Some(format) = request.parsed_response_format()? {
+    ///     println!("JSON mode: {}", format.is_json());
+    /// }
+    /// ```
+    ///
+    /// ## 返回值
+    /// - `Ok(None)`: 未设置`response_format`
+    /// - `Ok(Some(ResponseFormat))`: 解析成功
+    /// - `Err(String)`: 不支持的`response_format`形式
+    pub fn parsed_response_format(&self) -> Result<Option<ResponseFormat>, String> {
+        self.response_format.as_ref().map(ResponseFormat::parse).transpose()
+    }
+
+    /// 获取助手预填充内容
+    ///
+    /// ## 功能说明
+    /// 消息列表以`assistant`消息结尾时，该消息是预填充内容，模型应从此处继续生成。
+    /// Anthropic和Gemini原生支持；OpenAI不支持，由转换层改写为续写指令并把预填充内容拼接到输出前
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// if let Some(prefix) = request.assistant_prefill() {
+    ///     println!("Continuing from: {}", prefix);
+    /// }
+    /// ```
+    ///
+    /// ## 返回值
+    /// - `Some(&str)`: 末尾assistant消息的内容
+    /// - `None`: 最后一条消息不是assistant消息
+    pub fn assistant_prefill(&self) -> Option<&str> {
+        self.messages
+            .last()
+            .filter(|message| message.role == "assistant")
+            .map(|message| message.content.as_str())
+    }
 }
 
 impl AnthropicResponse {
@@ -480,14 +1317,232 @@ impl AnthropicResponse {
         Self {
             id,
             model,
-            content: vec![ContentBlock {
-                type_field: "text".to_string(),
-                text,
-            }],
+            content: vec![ContentBlock::text(text)],
             usage: Usage {
                 input_tokens,
                 output_tokens,
+                thinking_tokens: None,
             },
+            stop_reason: None,
+            logprobs: None,
+            refused: false,
+            refusal_reason: None,
+            upstream_headers: Vec::new(),
+            system_fingerprint: None,
+            x_ai_proxy: None,
         }
     }
+
+    /// 从多个候选结果创建响应对象
+    ///
+    /// ## 功能说明
+    /// 用于`n > 1`的请求：每个候选结果（OpenAI的choice或Gemini的candidate）
+    /// 按顺序映射为一个独立的文本ContentBlock
+    ///
+    /// ## 参数说明
+    /// - `id`: 响应的唯一标识符
+    /// - `model`: 使用的模型名称
+    /// - `texts`: 各候选结果的文本，按候选索引排序
+    /// - `input_tokens`: 输入消耗的token数量
+    /// - `output_tokens`: 所有候选结果合计的输出token数量
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let response = AnthropicResponse::from_candidates(
+    ///     "msg_123".to_string(),
+    ///     "gpt-4".to_string(),
+    ///     vec!["First answer".to_string(), "Second answer".to_string()],
+    ///     10,
+    ///     40
+    /// );
+    /// assert_eq!(response.content.len(), 2);
+    /// ```
+    pub fn from_candidates(id: String, model: String, texts: Vec<String>, input_tokens: u32, output_tokens: u32) -> Self {
+        Self {
+            id,
+            model,
+            content: texts.into_iter().map(ContentBlock::text).collect(),
+            usage: Usage {
+                input_tokens,
+                output_tokens,
+                thinking_tokens: None,
+            },
+            stop_reason: None,
+            logprobs: None,
+            refused: false,
+            refusal_reason: None,
+            upstream_headers: Vec::new(),
+            system_fingerprint: None,
+            x_ai_proxy: None,
+        }
+    }
+
+    /// 设置停止原因
+    ///
+    /// ## 功能说明
+    /// 链式设置响应的`stop_reason`，供各提供商在转换响应时填入映射后的结束原因
+    ///
+    /// ## 参数说明
+    /// - `stop_reason`: Anthropic格式的停止原因，未知时为`None`
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let response = AnthropicResponse::new("msg_123".to_string(), "gpt-4".to_string(), "Hi".to_string(), 1, 1)
+    ///     .with_stop_reason(Some("end_turn".to_string()));
+    /// assert_eq!(response.stop_reason.as_deref(), Some("end_turn"));
+    /// ```
+    ///
+    /// ## 返回值
+    /// 设置了停止原因的响应对象
+    pub fn with_stop_reason(mut self, stop_reason: Option<String>) -> Self {
+        self.stop_reason = stop_reason;
+        self
+    }
+
+    /// 设置token对数概率
+    ///
+    /// ## 功能说明
+    /// 链式设置响应的`logprobs`，供支持对数概率的提供商（OpenAI）原样透传上游返回的数据
+    ///
+    /// ## 参数说明
+    /// - `logprobs`: 上游返回的对数概率，未请求或未返回时为None
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let response = AnthropicResponse::new(id, model, text, 10, 5)
+    ///     .with_logprobs(Some(json!({"content": []})));
+    /// ```
+    ///
+    /// ## 返回值
+    /// 设置了对数概率的响应对象
+    pub fn with_logprobs(mut self, logprobs: Option<serde_json::Value>) -> Self {
+        self.logprobs = logprobs;
+        self
+    }
+
+    /// 设置推理token数量
+    ///
+    /// ## 功能说明
+    /// 链式设置`usage.thinking_tokens`，供单独统计推理token的提供商（Gemini的`thoughtsTokenCount`）使用
+    ///
+    /// ## 参数说明
+    /// - `thinking_tokens`: 上游返回的推理token数量，未返回时为None
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let response = AnthropicResponse::new(id, model, text, 10, 5).with_thinking_tokens(Some(120));
+    /// assert_eq!(response.usage.thinking_tokens, Some(120));
+    /// ```
+    ///
+    /// ## 返回值
+    /// 设置了推理token数量的响应对象
+    pub fn with_thinking_tokens(mut self, thinking_tokens: Option<u32>) -> Self {
+        self.usage.thinking_tokens = thinking_tokens;
+        self
+    }
+
+    /// 设置上游系统指纹
+    ///
+    /// ## 功能说明
+    /// 链式设置OpenAI返回的`system_fingerprint`，配合`seed`判断两次响应是否来自相同的后端配置
+    ///
+    /// ## 参数说明
+    /// - `system_fingerprint`: 上游返回的系统指纹，未返回时为None
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let response = AnthropicResponse::new(id, model, text, 10, 5)
+    ///     .with_system_fingerprint(Some("fp_44709d6fcb".to_string()));
+    /// assert!(response.system_fingerprint.is_some());
+    /// ```
+    ///
+    /// ## 返回值
+    /// 设置了系统指纹的响应对象
+    pub fn with_system_fingerprint(mut self, system_fingerprint: Option<String>) -> Self {
+        self.system_fingerprint = system_fingerprint;
+        self
+    }
+
+    /// 追加工具调用内容块
+    ///
+    /// ## 功能说明
+    /// 将提供商返回的工具调用（OpenAI的tool_calls、Gemini的functionCall）作为`tool_use`
+    /// 内容块追加到文本块之后；存在工具调用时停止原因设为`tool_use`
+    ///
+    /// ## 参数说明
+    /// - `tool_uses`: 由`ContentBlock::tool_use`创建的工具调用块
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let response = AnthropicResponse::from_candidates(id, model, vec![], 10, 5)
+    ///     .with_tool_uses(vec![ContentBlock::tool_use(call_id, "get_weather".to_string(), json!({"city": "Paris"}))]);
+    /// assert_eq!(response.stop_reason.as_deref(), Some("tool_use"));
+    /// ```
+    ///
+    /// ## 返回值
+    /// 包含工具调用块的响应对象
+    pub fn with_tool_uses(mut self, tool_uses: Vec<ContentBlock>) -> Self {
+        if !tool_uses.is_empty() {
+            self.content.extend(tool_uses);
+            self.stop_reason = Some("tool_use".to_string());
+        }
+        self
+    }
+
+    /// 标记响应为拒绝回答/内容过滤
+    ///
+    /// ## 功能说明
+    /// 设置`refused`标志和原因，供各提供商在识别到拒绝回答（OpenAI的`refusal`字段、
+    /// Gemini的安全拦截等）时使用
+    ///
+    /// ## 参数说明
+    /// - `reason`: 拒绝或过滤的原因
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let response = AnthropicResponse::from_candidates(id, model, vec![], 10, 0)
+    ///     .with_refusal("Prompt blocked: Safety".to_string());
+    /// assert!(response.refused);
+    /// ```
+    ///
+    /// ## 返回值
+    /// 标记为拒绝的响应对象
+    pub fn with_refusal(mut self, reason: String) -> Self {
+        self.refused = true;
+        self.refusal_reason = Some(reason);
+        self
+    }
+
+    /// 根据停止原因标记拒绝回答
+    ///
+    /// ## 功能说明
+    /// 停止原因为`refusal`（Anthropic）或`content_filter`（OpenAI、Gemini映射后）且尚未标记时，
+    /// 将响应标记为拒绝，原因为描述停止原因的文本
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let response = anthropic_response.flag_refusal();
+    /// if response.refused {
+    ///     tracing::warn!("Refused: {:?}", response.refusal_reason);
+    /// }
+    /// ```
+    ///
+    /// ## 返回值
+    /// 必要时标记为拒绝的响应对象
+    pub fn flag_refusal(self) -> Self {
+        if self.refused {
+            return self;
+        }
+        let reason = match self.stop_reason.as_deref() {
+            Some("refusal") => "Model declined to respond",
+            Some("content_filter") => "Response withheld by content filter",
+            _ => return self,
+        };
+        self.with_refusal(reason.to_string())
+    }
+
+    /// Tool call blocks in this response
+    pub fn tool_uses(&self) -> impl Iterator<Item = &ContentBlock> {
+        self.content.iter().filter(|block| block.is_tool_use())
+    }
 }
\ No newline at end of file
@@ -3,14 +3,18 @@ use async_trait::async_trait;
 use reqwest::Client;
 
 use crate::{
-    config::ProviderDetail,
+    config::{HealthProbe, ProviderDetail},
     errors::AppError,
     providers::{
-        AIProvider, HealthStatus, ModelInfo, StreamResponse,
-        anthropic::{AnthropicRequest, AnthropicResponse, Message},
+        AIProvider, CancellableStream, HealthStatus, ModelInfo, ProviderCapabilities, StreamResponse, apply_max_tokens_policy, extra_headers, forwarded_response_headers, log_ignored_seed, log_ignored_thinking_budget,
+        health_probe_request, parse_json_body, tcp_connect_probe, retry_after_from_headers, truncate_body, with_message_close,
+        anthropic::{AnthropicRequest, AnthropicResponse, ResponseFormat},
     },
 };
 
+/// `anthropic-version` header sent when the provider does not pin one
+const DEFAULT_ANTHROPIC_VERSION: &str = "2023-06-01";
+
 /// Anthropic provider implementation (native format)
 ///
 /// This provider handles Anthropic's native API format directly,
@@ -45,6 +49,62 @@ impl AnthropicProvider {
         Self { config, client }
     }
 
+    /// `anthropic-version` header value: the configured version or the default
+    fn anthropic_version(&self) -> &str {
+        self.config
+            .anthropic_version
+            .as_deref()
+            .unwrap_or(DEFAULT_ANTHROPIC_VERSION)
+    }
+
+    /// `anthropic-beta` header with the configured beta features, comma-joined
+    fn beta_headers(&self) -> reqwest::header::HeaderMap {
+        let mut headers = reqwest::header::HeaderMap::new();
+        if let Some(betas) = self.config.anthropic_beta.as_ref().filter(|betas| !betas.is_empty()) {
+            match reqwest::header::HeaderValue::from_str(&betas.join(",")) {
+                Ok(value) => {
+                    headers.insert("anthropic-beta", value);
+                }
+                Err(e) => tracing::warn!("Ignoring invalid anthropic_beta: {}", e),
+            }
+        }
+        headers
+    }
+
+    /// Reject multi-completion requests, which the Messages API cannot serve
+    fn reject_multiple_completions(request: &AnthropicRequest) -> Result<(), AppError> {
+        match request.n {
+            Some(n) if n > 1 => Err(AppError::ValidationError(format!(
+                "Anthropic does not support multiple completions (n = {}); use n = 1 or a provider that supports it",
+                n
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reject logprobs requests, which the Messages API cannot serve
+    fn reject_logprobs(request: &AnthropicRequest) -> Result<(), AppError> {
+        if request.requests_logprobs() {
+            return Err(AppError::ValidationError(
+                "Anthropic does not support logprobs/top_logprobs; use an OpenAI model".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Turn a JSON-mode `response_format` into a system-prompt instruction,
+    /// since the Messages API has no native equivalent
+    fn apply_response_format(request: &mut AnthropicRequest) -> Result<(), AppError> {
+        let format = request.parsed_response_format().map_err(AppError::ValidationError)?;
+        if let Some(instruction) = format.as_ref().and_then(ResponseFormat::system_instruction) {
+            request.system = Some(match request.system.take() {
+                Some(system) => format!("{}\n\n{}", system, instruction),
+                None => instruction,
+            });
+        }
+        Ok(())
+    }
+
     /// Validate model name for Anthropic
     fn validate_model_name(&self, model: &str) -> Result<(), AppError> {
         // Check if model name starts with "claude-"
@@ -81,45 +141,34 @@ impl AnthropicProvider {
 
     /// Handle Anthropic API errors with proper error parsing
     fn handle_api_error(&self, status: u16, error_body: &str) -> AppError {
-        // Try to parse Anthropic error format
-        let parsed_message = if let Ok(error_json) = serde_json::from_str::<serde_json::Value>(error_body) {
-            error_json
-                .get("error")
-                .and_then(|e| e.get("message"))
-                .and_then(|m| m.as_str())
-                .unwrap_or(error_body)
-                .to_string()
-        } else {
-            error_body.to_string()
+        // Anthropic errors look like {"type":"error","error":{"type":"...","message":"..."}}
+        let error = serde_json::from_str::<serde_json::Value>(error_body)
+            .ok()
+            .and_then(|value| value.get("error").cloned());
+        let parsed_message = error
+            .as_ref()
+            .and_then(|e| e.get("message"))
+            .and_then(|m| m.as_str())
+            .unwrap_or(error_body);
+        let parsed_message = truncate_body(parsed_message, self.config.error_body_limit());
+        let error_type = error
+            .as_ref()
+            .and_then(|e| e.get("type"))
+            .and_then(|t| t.as_str())
+            .map(str::to_string);
+        let error_code = None;
+
+        let message = match status {
+            400 => format!("Anthropic API: {}", parsed_message),
+            401 => "Anthropic API: Invalid API key or authentication failed".to_string(),
+            403 => "Anthropic API: Access forbidden - check your API key permissions".to_string(),
+            404 => "Anthropic API: Model not found or endpoint not available".to_string(),
+            429 => format!("Anthropic API: Rate limit exceeded - {}", parsed_message),
+            500..=599 => format!("Anthropic API: Server error - {}", parsed_message),
+            _ => format!("Anthropic API: Unexpected error - {}", parsed_message),
         };
-        
-        match status {
-            400 => AppError::BadRequest(format!("Anthropic API: {}", parsed_message)),
-            401 => AppError::ProviderError {
-                status,
-                message: "Anthropic API: Invalid API key or authentication failed".to_string(),
-            },
-            403 => AppError::ProviderError {
-                status,
-                message: "Anthropic API: Access forbidden - check your API key permissions".to_string(),
-            },
-            404 => AppError::ProviderError {
-                status,
-                message: "Anthropic API: Model not found or endpoint not available".to_string(),
-            },
-            429 => AppError::ProviderError {
-                status,
-                message: format!("Anthropic API: Rate limit exceeded - {}", parsed_message),
-            },
-            500..=599 => AppError::ProviderError {
-                status,
-                message: format!("Anthropic API: Server error - {}", parsed_message),
-            },
-            _ => AppError::ProviderError {
-                status,
-                message: format!("Anthropic API: Unexpected error - {}", parsed_message),
-            },
-        }
+
+        AppError::provider_error_with_details(status, message, error_type, error_code)
     }
 
     /// Fetch models from Anthropic API (currently returns configured models as Anthropic doesn't have a public models endpoint)
@@ -148,6 +197,8 @@ impl AnthropicProvider {
                 object: "model".to_string(),
                 created: 1714560000, // Static timestamp for now
                 owned_by: "anthropic".to_string(),
+                context_length: None,
+                provider: None,
             })
             .collect())
     }
@@ -176,124 +227,134 @@ impl AnthropicProvider {
                 object: "model".to_string(),
                 created: 1714560000, // Static timestamp for fallback
                 owned_by: "anthropic".to_string(),
+                context_length: None,
+                provider: None,
             })
             .collect())
     }
 
-    /// Perform comprehensive health check with retry logic
+    /// Perform a health check that avoids consuming tokens where possible
+    ///
+    /// Probes the models endpoint first, then `messages/count_tokens`, and only
+    /// falls back to a minimal chat completion when neither endpoint exists.
     async fn perform_comprehensive_health_check(&self) -> Result<(), AppError> {
-        // First attempt: lightweight connectivity check
-        let connectivity_result = self.check_connectivity().await;
-        
-        match connectivity_result {
-            Ok(()) => {
-                // If connectivity is good, perform a more thorough check
-                self.check_api_functionality().await
+        match self.probe_models_endpoint().await {
+            Err(AppError::ProviderError { status: 404 | 405, .. }) => {
+                tracing::debug!("Anthropic models endpoint unavailable, trying count_tokens");
             }
-            Err(e) => {
-                // If connectivity fails, try one more time with a shorter timeout
-                tracing::warn!("Initial connectivity check failed: {}, retrying...", e);
-                tokio::time::sleep(std::time::Duration::from_millis(500)).await;
-                self.check_connectivity().await
+            result => return result,
+        }
+
+        match self.probe_count_tokens_endpoint().await {
+            Err(AppError::ProviderError { status: 404 | 405, .. }) => {
+                tracing::debug!("Anthropic count_tokens endpoint unavailable, falling back to minimal chat");
             }
+            result => return result,
         }
+
+        self.check_connectivity().await
     }
 
-    /// Check basic connectivity to Anthropic API
-    async fn check_connectivity(&self) -> Result<(), AppError> {
-        let url = format!("{}messages", self.config.api_base.trim_end_matches('/'));
-        
-        // Create a minimal request just to test connectivity
-        let test_request = AnthropicRequest {
-            model: "claude-3-haiku-20240307".to_string(),
-            messages: vec![Message {
-                role: "user".to_string(),
-                content: "test".to_string(),
-            }],
-            max_tokens: 1,
-            stream: Some(false),
-            temperature: None,
-            top_p: None,
-        };
+    /// Token-free probe: list models
+    async fn probe_models_endpoint(&self) -> Result<(), AppError> {
+        let url = format!("{}/models", self.config.api_base.trim_end_matches('/'));
 
         let response = self
             .client
-            .post(&url)
+            .get(&url)
             .header("x-api-key", &self.config.api_key)
-            .header("anthropic-version", "2023-06-01")
-            .header("Content-Type", "application/json")
-            .header("User-Agent", "ai-proxy/0.1.0")
-            .json(&test_request)
+            .headers(extra_headers(&self.config))
+            .header("anthropic-version", self.anthropic_version())
             .timeout(std::time::Duration::from_secs(10))
             .send()
             .await
             .map_err(|e| AppError::ProviderError {
                 status: 500,
                 message: format!("Failed to connect to Anthropic: {}", e),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
             })?;
 
         if !response.status().is_success() {
             let status = response.status().as_u16();
+            let retry_after = retry_after_from_headers(response.headers());
             let error_body = response.text().await.unwrap_or_default();
-            return Err(self.handle_api_error(status, &error_body));
+            return Err(self.handle_api_error(status, &error_body).with_retry_after(retry_after));
         }
 
         Ok(())
     }
 
-    /// Check API functionality with a more comprehensive test
-    async fn check_api_functionality(&self) -> Result<(), AppError> {
-        let url = format!("{}messages", self.config.api_base.trim_end_matches('/'));
-        
-        let test_request = AnthropicRequest {
-            model: "claude-3-haiku-20240307".to_string(),
-            messages: vec![Message {
-                role: "user".to_string(),
-                content: "Hi".to_string(),
-            }],
-            max_tokens: 1,
-            stream: Some(false),
-            temperature: None,
-            top_p: None,
-        };
+    /// Token-free probe: count tokens for a tiny message
+    async fn probe_count_tokens_endpoint(&self) -> Result<(), AppError> {
+        let url = format!("{}/messages/count_tokens", self.config.api_base.trim_end_matches('/'));
+
+        let probe = serde_json::json!({
+            "model": "claude-3-haiku-20240307",
+            "messages": [{"role": "user", "content": "test"}],
+        });
 
         let response = self
             .client
             .post(&url)
             .header("x-api-key", &self.config.api_key)
-            .header("anthropic-version", "2023-06-01")
+            .headers(extra_headers(&self.config))
+            .header("anthropic-version", self.anthropic_version())
             .header("Content-Type", "application/json")
-            .header("User-Agent", "ai-proxy/0.1.0")
-            .json(&test_request)
-            .timeout(std::time::Duration::from_secs(30))
+            .json(&probe)
+            .timeout(std::time::Duration::from_secs(10))
             .send()
             .await
             .map_err(|e| AppError::ProviderError {
                 status: 500,
                 message: format!("Failed to connect to Anthropic: {}", e),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
             })?;
 
         if !response.status().is_success() {
             let status = response.status().as_u16();
+            let retry_after = retry_after_from_headers(response.headers());
             let error_body = response.text().await.unwrap_or_default();
-            return Err(self.handle_api_error(status, &error_body));
+            return Err(self.handle_api_error(status, &error_body).with_retry_after(retry_after));
         }
 
-        // Verify we can parse the response
-        let response_data: AnthropicResponse = response
-            .json()
+        Ok(())
+    }
+
+    /// Fallback probe: minimal chat completion (consumes a token)
+    async fn check_connectivity(&self) -> Result<(), AppError> {
+        let url = format!("{}/messages", self.config.api_base.trim_end_matches('/'));
+        
+        // Create a minimal request just to test connectivity
+        let test_request = health_probe_request(&self.config, "claude-3-haiku-20240307");
+
+        let response = self
+            .client
+            .post(&url)
+            .header("x-api-key", &self.config.api_key)
+            .headers(extra_headers(&self.config))
+            .header("anthropic-version", self.anthropic_version())
+            .header("Content-Type", "application/json")
+            .json(&test_request)
+            .timeout(std::time::Duration::from_secs(10))
+            .send()
             .await
             .map_err(|e| AppError::ProviderError {
                 status: 500,
-                message: format!("Failed to parse Anthropic response: {}", e),
+                message: format!("Failed to connect to Anthropic: {}", e),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
             })?;
 
-        // Validate response structure
-        if response_data.content.is_empty() {
-            return Err(AppError::ProviderError {
-                status: 500,
-                message: "Anthropic returned empty response content".to_string(),
-            });
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let retry_after = retry_after_from_headers(response.headers());
+            let error_body = response.text().await.unwrap_or_default();
+            return Err(self.handle_api_error(status, &error_body).with_retry_after(retry_after));
         }
 
         Ok(())
@@ -302,15 +363,21 @@ impl AnthropicProvider {
 
 #[async_trait]
 impl AIProvider for AnthropicProvider {
-    async fn chat(&self, request: AnthropicRequest) -> Result<AnthropicResponse, AppError> {
-        // Validate request
+    async fn chat(&self, mut request: AnthropicRequest) -> Result<AnthropicResponse, AppError> {
+        // Apply the configured max_tokens default and output cap, then validate
+        apply_max_tokens_policy(&mut request, &self.config)?;
         request.validate().map_err(AppError::ValidationError)?;
+        log_ignored_thinking_budget(&request, "anthropic");
+        log_ignored_seed(&request, "anthropic");
+        Self::reject_multiple_completions(&request)?;
+        Self::reject_logprobs(&request)?;
+        Self::apply_response_format(&mut request)?;
 
         // Validate model name for Anthropic
         self.validate_model_name(&request.model)?;
 
         // Build URL
-        let url = format!("{}messages", self.config.api_base.trim_end_matches('/'));
+        let url = format!("{}/messages", self.config.api_base.trim_end_matches('/'));
 
         tracing::info!("Sending Anthropic chat request to: {} with model: {}", url, request.model);
 
@@ -319,54 +386,74 @@ impl AIProvider for AnthropicProvider {
             .client
             .post(&url)
             .header("x-api-key", &self.config.api_key)
-            .header("anthropic-version", "2023-06-01")
+            .headers(extra_headers(&self.config))
+            .header("anthropic-version", self.anthropic_version())
+            .headers(self.beta_headers())
             .header("Content-Type", "application/json")
-            .header("User-Agent", "ai-proxy/0.1.0")
             .json(&request)
+            .timeout(request.upstream_timeout(self.config.read_timeout_secs()))
             .send()
             .await
-            .map_err(|e| AppError::ProviderError {
-                status: 500,
-                message: format!("Failed to send request to Anthropic: {}", e),
+            .map_err(|e| {
+                if e.is_connect() && e.is_timeout() {
+                    AppError::NetworkError(format!("Anthropic connect timed out: {}", e))
+                } else if e.is_timeout() {
+                    AppError::GatewayTimeout(format!("Anthropic request timed out: {}", e))
+                } else {
+                    AppError::ProviderError {
+                        status: 500,
+                        message: format!("Failed to send request to Anthropic: {}", e),
+                        error_type: None,
+                        error_code: None,
+                        retry_after: None,
+                    }
+                }
             })?;
 
         // Handle HTTP errors with proper error parsing
         if !response.status().is_success() {
             let status = response.status().as_u16();
+            let retry_after = retry_after_from_headers(response.headers());
             let error_body = response.text().await.unwrap_or_default();
-            tracing::warn!("Anthropic API error: status={}, body={}", status, error_body);
-            return Err(self.handle_api_error(status, &error_body));
+            tracing::warn!("Anthropic API error: status={}, body={}", status, truncate_body(&error_body, self.config.log_body_limit()));
+            return Err(self.handle_api_error(status, &error_body).with_retry_after(retry_after));
         }
 
+        let upstream_headers = forwarded_response_headers(&self.config, response.headers());
+
         // Parse response (direct format match)
-        let anthropic_res =
-            response
-                .json::<AnthropicResponse>()
-                .await
-                .map_err(|e| AppError::ProviderError {
-                    status: 500,
-                    message: format!("Failed to parse Anthropic response: {}", e),
-                })?;
-
-        // Validate response has content
-        if anthropic_res.content.is_empty() {
+        let anthropic_res: AnthropicResponse = parse_json_body(response, "Anthropic response", &self.config).await?;
+
+        // Surface refusals as a flag; a refusal may legitimately carry no content
+        let mut anthropic_res = anthropic_res.flag_refusal();
+        if anthropic_res.content.is_empty() && !anthropic_res.refused {
             return Err(AppError::ProviderError {
                 status: 500,
                 message: "Anthropic returned empty response".to_string(),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
             });
         }
 
         tracing::info!("Anthropic chat completed successfully: input_tokens={}, output_tokens={}", 
                       anthropic_res.usage.input_tokens, anthropic_res.usage.output_tokens);
 
+        anthropic_res.upstream_headers = upstream_headers;
         Ok(anthropic_res)
     }
 
-    async fn chat_stream(&self, request: AnthropicRequest) -> Result<StreamResponse, AppError> {
+    async fn chat_stream(&self, mut request: AnthropicRequest) -> Result<StreamResponse, AppError> {
         use futures::StreamExt;
         
-        // Validate request
+        // Apply the configured max_tokens default and output cap, then validate
+        apply_max_tokens_policy(&mut request, &self.config)?;
         request.validate().map_err(AppError::ValidationError)?;
+        log_ignored_thinking_budget(&request, "anthropic");
+        log_ignored_seed(&request, "anthropic");
+        Self::reject_multiple_completions(&request)?;
+        Self::reject_logprobs(&request)?;
+        Self::apply_response_format(&mut request)?;
 
         // Validate model name for Anthropic
         self.validate_model_name(&request.model)?;
@@ -376,7 +463,7 @@ impl AIProvider for AnthropicProvider {
         streaming_request.stream = Some(true);
 
         // Build streaming URL
-        let url = format!("{}messages", self.config.api_base.trim_end_matches('/'));
+        let url = format!("{}/messages", self.config.api_base.trim_end_matches('/'));
 
         tracing::info!("Starting Anthropic streaming request to: {} with model: {}", url, request.model);
 
@@ -385,24 +472,38 @@ impl AIProvider for AnthropicProvider {
             .client
             .post(&url)
             .header("x-api-key", &self.config.api_key)
-            .header("anthropic-version", "2023-06-01")
+            .headers(extra_headers(&self.config))
+            .header("anthropic-version", self.anthropic_version())
+            .headers(self.beta_headers())
             .header("Content-Type", "application/json")
-            .header("User-Agent", "ai-proxy/0.1.0")
             .header("Accept", "text/event-stream")
             .json(&streaming_request)
+            .timeout(request.upstream_timeout(self.config.read_timeout_secs()))
             .send()
             .await
-            .map_err(|e| AppError::ProviderError {
-                status: 500,
-                message: format!("Failed to send streaming request to Anthropic: {}", e),
+            .map_err(|e| {
+                if e.is_connect() && e.is_timeout() {
+                    AppError::NetworkError(format!("Anthropic streaming connect timed out: {}", e))
+                } else if e.is_timeout() {
+                    AppError::GatewayTimeout(format!("Anthropic streaming request timed out: {}", e))
+                } else {
+                    AppError::ProviderError {
+                        status: 500,
+                        message: format!("Failed to send streaming request to Anthropic: {}", e),
+                        error_type: None,
+                        error_code: None,
+                        retry_after: None,
+                    }
+                }
             })?;
 
         // Check for HTTP errors
         if !response.status().is_success() {
             let status = response.status().as_u16();
+            let retry_after = retry_after_from_headers(response.headers());
             let error_body = response.text().await.unwrap_or_default();
-            tracing::warn!("Anthropic streaming API error: status={}, body={}", status, error_body);
-            return Err(self.handle_api_error(status, &error_body));
+            tracing::warn!("Anthropic streaming API error: status={}, body={}", status, truncate_body(&error_body, self.config.log_body_limit()));
+            return Err(self.handle_api_error(status, &error_body).with_retry_after(retry_after));
         }
 
         // Get the response body as a stream
@@ -432,6 +533,9 @@ impl AIProvider for AnthropicProvider {
                             let app_error = AppError::ProviderError {
                                 status: 500,
                                 message: format!("Streaming read error: {}", e),
+                                error_type: None,
+                                error_code: None,
+                                retry_after: None,
                             };
                             Some(Err(app_error))
                         }
@@ -440,7 +544,17 @@ impl AIProvider for AnthropicProvider {
             });
 
         tracing::info!("Anthropic streaming response initialized successfully");
-        Ok(Box::pin(sse_stream))
+        let sse_stream = with_message_close(Box::pin(sse_stream), &request.model);
+        Ok(CancellableStream::wrap(sse_stream, "anthropic", request.model.clone()))
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: true,
+            tools: true,
+            vision: true,
+            embeddings: false,
+        }
     }
 
     async fn list_models(&self) -> Result<Vec<ModelInfo>, AppError> {
@@ -478,8 +592,14 @@ impl AIProvider for AnthropicProvider {
     async fn health_check(&self) -> Result<HealthStatus, AppError> {
         let start = std::time::Instant::now();
 
-        // Comprehensive health check with multiple validation steps
-        let health_result = self.perform_comprehensive_health_check().await;
+        // By default the probes are tried from cheapest to most expensive
+        let health_result = match self.config.health_check {
+            Some(HealthProbe::Disabled) => return Ok(HealthStatus::disabled("anthropic")),
+            Some(HealthProbe::TcpConnect) => tcp_connect_probe(&self.config.api_base).await,
+            Some(HealthProbe::MinimalChat) => self.check_connectivity().await,
+            Some(HealthProbe::ModelsList) => self.probe_models_endpoint().await,
+            None => self.perform_comprehensive_health_check().await,
+        };
         let latency = start.elapsed().as_millis() as u64;
 
         match health_result {
@@ -491,7 +611,7 @@ impl AIProvider for AnthropicProvider {
             }),
             Err(e) => {
                 let (status, error_msg) = match &e {
-                    AppError::ProviderError { status, message } => {
+                    AppError::ProviderError { status, message, .. } => {
                         match *status {
                             401 => ("unhealthy".to_string(), "Authentication failed - check API key".to_string()),
                             403 => ("unhealthy".to_string(), "Access forbidden - check API key permissions".to_string()),
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use futures::stream::{AbortHandle, AbortRegistration, Abortable, StreamExt};
+
+use crate::errors::AppError;
+use super::StreamResponse;
+
+/// Longest client-supplied stream ID accepted
+pub const MAX_STREAM_ID_LENGTH: usize = 128;
+
+/// A registered stream's key: the owning caller's key fingerprint and the client's stream ID
+type StreamKey = (String, String);
+
+/// In-flight streams registered under a client-supplied ID
+///
+/// Orchestrators tag a streaming request with `x-ai-proxy-stream-id` and can
+/// later cancel it by that ID. IDs are scoped to the caller that registered
+/// them, so one caller cannot cancel another's stream. Cancelling ends the
+/// client stream, which drops the upstream response and stops generation. An
+/// entry lives exactly as long as its stream: it is removed when the stream
+/// finishes, is cancelled, or the client disconnects.
+#[derive(Debug, Default)]
+pub struct ActiveStreams {
+    handles: Mutex<HashMap<StreamKey, Arc<AbortHandle>>>,
+}
+
+/// Removes a stream's entry once the stream (or its unused slot) is dropped
+struct Registration {
+    streams: Arc<ActiveStreams>,
+    key: StreamKey,
+    handle: Arc<AbortHandle>,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        // A cancelled ID may already have been reused by a newer stream
+        if let Ok(mut handles) = self.streams.handles.lock()
+            && handles.get(&self.key).is_some_and(|handle| Arc::ptr_eq(handle, &self.handle))
+        {
+            handles.remove(&self.key);
+        }
+    }
+}
+
+/// A stream ID claimed before the upstream call is made
+///
+/// Dropping the slot without attaching a stream releases the ID. A cancel that
+/// arrives before the stream is attached ends the stream as soon as it starts.
+pub struct StreamSlot {
+    registration: Registration,
+    abort: AbortRegistration,
+}
+
+impl StreamSlot {
+    /// Make `stream` cancellable under the reserved ID
+    pub fn attach(self, stream: StreamResponse) -> StreamResponse {
+        let registration = self.registration;
+        Box::pin(Abortable::new(stream, self.abort).map(move |item| {
+            let _ = &registration;
+            item
+        }))
+    }
+}
+
+impl ActiveStreams {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Claim `id` for `owner` ahead of dispatching the stream
+    ///
+    /// Rejects empty or overlong IDs and IDs the owner already has in flight,
+    /// so a duplicate is refused before any upstream call is made.
+    pub fn reserve(self: &Arc<Self>, owner: &str, id: &str) -> Result<StreamSlot, AppError> {
+        let id = normalize_id(id);
+        if id.is_empty() || id.len() > MAX_STREAM_ID_LENGTH {
+            return Err(AppError::BadRequest(format!(
+                "Stream ID must be between 1 and {} characters",
+                MAX_STREAM_ID_LENGTH
+            )));
+        }
+
+        let key = (owner.to_string(), id.to_string());
+        let (handle, abort) = AbortHandle::new_pair();
+        let handle = Arc::new(handle);
+        {
+            let mut handles = self
+                .handles
+                .lock()
+                .map_err(|_| AppError::InternalServerError("Active stream registry lock poisoned".to_string()))?;
+            if handles.contains_key(&key) {
+                return Err(AppError::BadRequest(format!("Stream ID '{}' is already in use", id)));
+            }
+            handles.insert(key.clone(), handle.clone());
+        }
+
+        Ok(StreamSlot {
+            registration: Registration {
+                streams: self.clone(),
+                key,
+                handle,
+            },
+            abort,
+        })
+    }
+
+    /// Make `stream` cancellable under `owner`'s `id`
+    pub fn register(self: &Arc<Self>, owner: &str, id: &str, stream: StreamResponse) -> Result<StreamResponse, AppError> {
+        Ok(self.reserve(owner, id)?.attach(stream))
+    }
+
+    /// Cancel `owner`'s in-flight stream registered under `id`
+    ///
+    /// Returns whether a stream was found.
+    pub fn cancel(&self, owner: &str, id: &str) -> bool {
+        let key = (owner.to_string(), normalize_id(id).to_string());
+        let handle = match self.handles.lock() {
+            Ok(mut handles) => handles.remove(&key),
+            Err(_) => None,
+        };
+        match handle {
+            Some(handle) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether `owner` has a stream registered under `id`
+    pub fn contains(&self, owner: &str, id: &str) -> bool {
+        let key = (owner.to_string(), normalize_id(id).to_string());
+        self.handles.lock().map(|handles| handles.contains_key(&key)).unwrap_or(false)
+    }
+
+    /// Number of in-flight registered streams
+    pub fn len(&self) -> usize {
+        self.handles.lock().map(|handles| handles.len()).unwrap_or(0)
+    }
+
+    /// Whether no streams are registered
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Stream IDs are compared without surrounding whitespace
+fn normalize_id(id: &str) -> &str {
+    id.trim()
+}
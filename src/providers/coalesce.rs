@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use futures::future::{BoxFuture, FutureExt, Shared};
+
+use crate::errors::AppError;
+use super::anthropic::AnthropicResponse;
+
+type SharedChat = Shared<BoxFuture<'static, Result<AnthropicResponse, AppError>>>;
+
+/// Single-flight coalescing for identical in-flight chat requests
+///
+/// The first caller for a key (the leader) starts the upstream call; callers
+/// arriving while it is in flight await the same future and receive a clone of
+/// its result. The entry is removed as soon as the leader finishes or is
+/// dropped, so completed results are never served to later requests.
+#[derive(Default)]
+pub struct RequestCoalescer {
+    in_flight: Mutex<HashMap<String, SharedChat>>,
+}
+
+impl std::fmt::Debug for RequestCoalescer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RequestCoalescer")
+            .field("in_flight", &self.in_flight_count())
+            .finish()
+    }
+}
+
+/// Removes the leader's entry when its call completes or is cancelled
+struct LeaderGuard<'a> {
+    coalescer: &'a RequestCoalescer,
+    key: String,
+}
+
+impl Drop for LeaderGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut in_flight) = self.coalescer.in_flight.lock() {
+            in_flight.remove(&self.key);
+        }
+    }
+}
+
+impl RequestCoalescer {
+    /// Create a coalescer with no in-flight requests
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 执行请求，相同键的并发请求共享同一次上游调用
+    ///
+    /// ## 功能说明
+    /// 如果已有相同键的请求正在进行，等待其结果并返回副本；否则调用`start`发起新请求
+    ///
+    /// ## 内部实现逻辑
+    /// 1. 加锁查找进行中的请求，存在则克隆其共享future（跟随者）
+    /// 2. 不存在则调用`start`创建future并登记（领导者）
+    /// 3. 领导者完成或被取消时移除登记，跟随者仍可继续驱动共享future
+    ///
+    /// ## 参数说明
+    /// - `key`: 请求键，通常来自`AnthropicRequest::cache_key`
+    /// - `start`: 发起上游调用的闭包，仅领导者会调用
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let response = coalescer
+    ///     .run(key, || async move { provider.chat(request).await }.boxed())
+    ///     .await?;
+    /// ```
+    ///
+    /// ## 返回值
+    /// - 上游调用的结果（跟随者得到结果的副本）
+    pub async fn run<F>(&self, key: String, start: F) -> Result<AnthropicResponse, AppError>
+    where
+        F: FnOnce() -> BoxFuture<'static, Result<AnthropicResponse, AppError>>,
+    {
+        let (call, _guard) = {
+            let mut in_flight = self
+                .in_flight
+                .lock()
+                .map_err(|_| AppError::InternalServerError("Request coalescer lock poisoned".to_string()))?;
+
+            match in_flight.get(&key) {
+                Some(call) => {
+                    tracing::debug!("Coalescing request with an identical in-flight request");
+                    (call.clone(), None)
+                }
+                None => {
+                    let call = start().shared();
+                    in_flight.insert(key.clone(), call.clone());
+                    (call, Some(LeaderGuard { coalescer: self, key }))
+                }
+            }
+        };
+
+        call.await
+    }
+
+    /// Number of distinct requests currently in flight
+    pub fn in_flight_count(&self) -> usize {
+        self.in_flight.lock().map(|in_flight| in_flight.len()).unwrap_or(0)
+    }
+}
@@ -1,7 +1,7 @@
 use crate::errors::AppError;
 use crate::providers::anthropic::{
-    AnthropicRequest, AnthropicResponse, AnthropicStreamEvent, ContentBlockStart, MessageDelta,
-    StreamMessage, TextDelta, Usage,
+    AnthropicRequest, AnthropicResponse, AnthropicStreamEvent, ContentBlock, ContentBlockStart,
+    MessageDelta, ResponseFormat, StreamMessage, TextDelta, ToolChoice, Usage, DEFAULT_MAX_TOKENS,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -25,16 +25,50 @@ pub struct GeminiRequest {
 }
 
 /// Content structure for Gemini messages
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct GeminiContent {
+    #[serde(default)]
     pub role: String,
+    /// Blocked candidates may come back without any parts
+    #[serde(default)]
     pub parts: Vec<GeminiPart>,
 }
 
-/// Part structure containing text content
+/// Part structure containing text content or a function call
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GeminiPart {
+    /// Empty for function call parts
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub text: String,
+    #[serde(default, rename = "functionCall", skip_serializing_if = "Option::is_none")]
+    pub function_call: Option<GeminiFunctionCall>,
+}
+
+/// Function call requested by the model
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GeminiFunctionCall {
+    pub name: String,
+    #[serde(default)]
+    pub args: serde_json::Value,
+}
+
+/// Gemini `embedContent` request structure
+#[derive(Serialize, Debug, Clone)]
+pub struct GeminiEmbedRequest {
+    pub model: String,
+    pub content: GeminiContent,
+}
+
+/// Gemini `embedContent` response structure
+#[derive(Deserialize, Debug)]
+pub struct GeminiEmbedResponse {
+    pub embedding: GeminiEmbedding,
+}
+
+/// Embedding values returned by Gemini
+#[derive(Deserialize, Debug)]
+pub struct GeminiEmbedding {
+    pub values: Vec<f32>,
 }
 
 /// Generation configuration for Gemini API
@@ -56,6 +90,17 @@ pub struct GenerationConfig {
     pub response_schema: Option<serde_json::Value>,
     #[serde(skip_serializing_if = "Option::is_none", rename = "candidateCount")]
     pub candidate_count: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none", rename = "thinkingConfig")]
+    pub thinking_config: Option<ThinkingConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+}
+
+/// Reasoning settings for Gemini 2.5 thinking models
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ThinkingConfig {
+    #[serde(rename = "thinkingBudget")]
+    pub thinking_budget: u32,
 }
 
 /// Gemini API response structure
@@ -73,6 +118,7 @@ pub struct GeminiResponse {
 /// Individual candidate in Gemini response
 #[derive(Deserialize, Debug)]
 pub struct GeminiCandidate {
+    #[serde(default)]
     pub content: GeminiContent,
     #[serde(rename = "finishReason")]
     pub finish_reason: Option<String>,
@@ -157,7 +203,7 @@ pub struct Citation {
 /// Prompt feedback information
 #[derive(Deserialize, Debug)]
 pub struct PromptFeedback {
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_serializing_if = "Option::is_none", alias = "blockReason")]
     pub block_reason: Option<BlockReason>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub safety_ratings: Option<Vec<SafetyRating>>,
@@ -169,9 +215,20 @@ pub struct PromptFeedback {
 pub enum BlockReason {
     BlockReasonUnspecified,
     Safety,
+    /// Also covers reasons added after this enum was written (e.g. `BLOCKLIST`)
+    #[serde(other)]
     Other,
 }
 
+impl PromptFeedback {
+    /// Human-readable reason when the prompt itself was blocked
+    pub fn block_message(&self) -> Option<String> {
+        self.block_reason
+            .as_ref()
+            .map(|reason| format!("Prompt blocked: {:?}", reason))
+    }
+}
+
 /// Gemini API error response
 #[derive(Deserialize, Debug)]
 pub struct GeminiError {
@@ -194,8 +251,9 @@ pub struct Tool {
 pub struct FunctionDeclaration {
     pub name: String,
     pub description: String,
+    /// JSON schema of the function's arguments
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub parameters: Option<Schema>,
+    pub parameters: Option<serde_json::Value>,
 }
 
 /// Schema for function parameters
@@ -256,6 +314,8 @@ pub struct UsageMetadata {
     pub candidates_token_count: Option<u32>,
     #[serde(rename = "totalTokenCount")]
     pub total_token_count: Option<u32>,
+    #[serde(default, rename = "thoughtsTokenCount")]
+    pub thoughts_token_count: Option<u32>,
 }
 
 // Streaming-specific structures for Gemini
@@ -266,6 +326,8 @@ pub struct GeminiStreamResponse {
     pub candidates: Option<Vec<GeminiStreamCandidate>>,
     #[serde(rename = "usageMetadata")]
     pub usage_metadata: Option<UsageMetadata>,
+    #[serde(default, rename = "promptFeedback")]
+    pub prompt_feedback: Option<PromptFeedback>,
 }
 
 /// Streaming candidate structure
@@ -300,27 +362,53 @@ impl GeminiRequest {
                     role: role.to_string(),
                     parts: vec![GeminiPart {
                         text: msg.content.clone(),
+                        function_call: None,
                     }],
                 })
             })
             .collect::<Result<Vec<_>, AppError>>()?;
 
+        // JSON mode maps onto Gemini's native structured output settings
+        let response_format = request
+            .parsed_response_format()
+            .map_err(AppError::ValidationError)?
+            .filter(ResponseFormat::is_json);
+
         Ok(GeminiRequest {
             contents,
             generation_config: GenerationConfig {
-                max_output_tokens: request.max_tokens,
+                max_output_tokens: request.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
                 temperature: request.temperature,
                 top_p: request.top_p,
                 top_k: None,
                 stop_sequences: None,
-                response_mime_type: None,
-                response_schema: None,
-                candidate_count: None,
+                response_mime_type: response_format.as_ref().map(|_| "application/json".to_string()),
+                response_schema: response_format.as_ref().and_then(|format| format.schema().cloned()),
+                candidate_count: request.n.map(|n| n as i32),
+                thinking_config: request.thinking_budget.map(|thinking_budget| ThinkingConfig { thinking_budget }),
+                seed: request.seed,
             },
-            system_instruction: None,
+            system_instruction: request.system.as_ref().map(|system| GeminiContent {
+                role: "system".to_string(),
+                parts: vec![GeminiPart {
+                    text: system.clone(),
+                    function_call: None,
+                }],
+            }),
             safety_settings: None,
-            tools: None,
-            tool_config: None,
+            tools: request.tools.as_ref().map(|tools| {
+                vec![Tool {
+                    function_declarations: tools
+                        .iter()
+                        .map(|tool| FunctionDeclaration {
+                            name: tool.name.clone(),
+                            description: tool.description.clone().unwrap_or_default(),
+                            parameters: Some(gemini_utils::function_parameters(&tool.input_schema)),
+                        })
+                        .collect(),
+                }]
+            }),
+            tool_config: request.tool_choice.as_ref().map(gemini_utils::tool_config),
         })
     }
 
@@ -337,6 +425,8 @@ impl GeminiRequest {
                 response_mime_type: None,
                 response_schema: None,
                 candidate_count: None,
+                thinking_config: None,
+                seed: None,
             },
             system_instruction: None,
             safety_settings: None,
@@ -349,7 +439,7 @@ impl GeminiRequest {
     pub fn with_system_instruction(mut self, instruction: String) -> Self {
         self.system_instruction = Some(GeminiContent {
             role: "system".to_string(),
-            parts: vec![GeminiPart { text: instruction }],
+            parts: vec![GeminiPart { text: instruction , function_call: None}],
         });
         self
     }
@@ -435,11 +525,8 @@ impl GeminiRequest {
             ));
         }
 
-        if self.contents.len() > 100 {
-            return Err(AppError::ValidationError(
-                "Too many contents (max 100)".to_string(),
-            ));
-        }
+        // The message count is checked against the provider's `max_messages`
+        // on the Anthropic-format request before translation
 
         // Validate generation config
         if self.generation_config.max_output_tokens == 0 {
@@ -518,70 +605,105 @@ impl GeminiResponse {
             return Err(AppError::ProviderError {
                 status: error.code as u16,
                 message: error.message.clone(),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
             });
         }
 
-        // Check for prompt feedback that might block the response
-        if let Some(feedback) = &self.prompt_feedback {
-            if let Some(block_reason) = &feedback.block_reason {
-                return Err(AppError::ProviderError {
-                    status: 400,
-                    message: format!("Prompt blocked: {:?}", block_reason),
-                });
-            }
+        // A blocked prompt is reported as a refused response without content
+        if let Some(message) = self.prompt_feedback.as_ref().and_then(PromptFeedback::block_message) {
+            return Ok(self.refused_response(model, message));
         }
 
-        let candidate = self
-            .candidates
-            .first()
-            .ok_or_else(|| AppError::ProviderError {
+        if self.candidates.is_empty() {
+            return Err(AppError::ProviderError {
                 status: 500,
                 message: "No candidates in Gemini response".to_string(),
-            })?;
-
-        // Check if response was blocked by safety ratings
-        if let Some(safety_ratings) = &candidate.safety_ratings {
-            for rating in safety_ratings {
-                if rating.blocked.unwrap_or(false) {
-                    return Err(AppError::ProviderError {
-                        status: 400,
-                        message: format!(
-                            "Response blocked by safety filter: {:?}",
-                            rating.category
-                        ),
-                    });
+                error_type: None,
+                error_code: None,
+                retry_after: None,
+            });
+        }
+
+        // With candidateCount > 1 every candidate becomes its own content block
+        let mut candidates: Vec<&GeminiCandidate> = self.candidates.iter().collect();
+        candidates.sort_by_key(|candidate| candidate.index.unwrap_or(0));
+        let stop_reason = candidates[0].finish_reason.as_deref().map(gemini_utils::stop_reason);
+
+        let mut texts = Vec::with_capacity(candidates.len());
+        let mut tool_uses = Vec::new();
+        for candidate in candidates {
+            // Check if response was blocked by safety ratings
+            if let Some(safety_ratings) = &candidate.safety_ratings {
+                for rating in safety_ratings {
+                    if rating.blocked.unwrap_or(false) {
+                        return Err(AppError::ProviderError {
+                            status: 400,
+                            message: format!(
+                                "Response blocked by safety filter: {:?}",
+                                rating.category
+                            ),
+                            error_type: None,
+                            error_code: None,
+                            retry_after: None,
+                        });
+                    }
                 }
             }
-        }
 
-        let text = candidate
-            .content
-            .parts
-            .iter()
-            .map(|part| part.text.as_str())
-            .collect::<Vec<_>>()
-            .join("");
+            let text = candidate
+                .content
+                .parts
+                .iter()
+                .map(|part| part.text.as_str())
+                .collect::<Vec<_>>()
+                .join("");
+            let function_calls: Vec<ContentBlock> = candidate
+                .content
+                .parts
+                .iter()
+                .filter_map(|part| part.function_call.as_ref())
+                .map(|call| {
+                    ContentBlock::tool_use(
+                        format!("toolu_{}", uuid::Uuid::new_v4().simple()),
+                        call.name.clone(),
+                        call.args.clone(),
+                    )
+                })
+                .collect();
 
-        if text.is_empty() {
-            return Err(AppError::ProviderError {
-                status: 500,
-                message: "Empty response content from Gemini".to_string(),
-            });
+            // An empty candidate usually means Gemini withheld the output
+            if text.is_empty() && function_calls.is_empty() {
+                if let Some(reason) = self.filtered_candidate_reason(candidate) {
+                    return Ok(self.refused_response(model, reason));
+                }
+                return Err(self.empty_candidate_error(candidate));
+            }
+            if !text.is_empty() {
+                texts.push(text);
+            }
+            tool_uses.extend(function_calls);
         }
 
         let usage = self.usage_metadata.as_ref().unwrap_or(&UsageMetadata {
             prompt_token_count: Some(0),
             candidates_token_count: Some(0),
             total_token_count: Some(0),
+            thoughts_token_count: None,
         });
 
-        Ok(AnthropicResponse::new(
+        Ok(AnthropicResponse::from_candidates(
             format!("msg_{}", uuid::Uuid::new_v4().simple()),
             model.to_string(),
-            text,
+            texts,
             usage.prompt_token_count.unwrap_or(0),
             usage.candidates_token_count.unwrap_or(0),
-        ))
+        )
+        .with_thinking_tokens(usage.thoughts_token_count)
+        .with_stop_reason(stop_reason)
+        .with_tool_uses(tool_uses)
+        .flag_refusal())
     }
 
     /// Check if response contains any safety issues
@@ -590,10 +712,8 @@ impl GeminiResponse {
             return true;
         }
 
-        if let Some(feedback) = &self.prompt_feedback {
-            if feedback.block_reason.is_some() {
-                return true;
-            }
+        if self.prompt_feedback.as_ref().and_then(PromptFeedback::block_message).is_some() {
+            return true;
         }
 
         for candidate in &self.candidates {
@@ -676,6 +796,52 @@ impl GeminiResponse {
         }
     }
 
+    /// Refusal reason for a candidate whose output was withheld by a content filter
+    fn filtered_candidate_reason(&self, candidate: &GeminiCandidate) -> Option<String> {
+        let finish_reason = candidate.finish_reason.as_deref()?;
+        (gemini_utils::stop_reason(finish_reason) == "content_filter").then(|| {
+            format!(
+                "Response withheld by content filter (finishReason: {}): {}",
+                finish_reason,
+                self.get_safety_info()
+            )
+        })
+    }
+
+    /// Refused response without content, carrying the block reason
+    fn refused_response(&self, model: &str, reason: String) -> AnthropicResponse {
+        let input_tokens = self
+            .usage_metadata
+            .as_ref()
+            .and_then(|usage| usage.prompt_token_count)
+            .unwrap_or(0);
+        AnthropicResponse::from_candidates(
+            format!("msg_{}", uuid::Uuid::new_v4().simple()),
+            model.to_string(),
+            Vec::new(),
+            input_tokens,
+            0,
+        )
+        .with_stop_reason(Some("content_filter".to_string()))
+        .with_refusal(reason)
+    }
+
+    /// Build a descriptive error for a candidate that came back without any text
+    fn empty_candidate_error(&self, candidate: &GeminiCandidate) -> AppError {
+        let finish_reason = candidate.finish_reason.as_deref().unwrap_or("UNSPECIFIED");
+        AppError::ProviderError {
+            status: 502,
+            message: format!(
+                "Gemini returned an empty response (finishReason: {}): {}",
+                finish_reason,
+                self.get_safety_info()
+            ),
+            error_type: None,
+            error_code: None,
+            retry_after: None,
+        }
+    }
+
     /// Check if any safety ratings indicate high risk
     pub fn has_high_risk_safety_rating(&self) -> bool {
         for candidate in &self.candidates {
@@ -712,6 +878,10 @@ impl GeminiResponse {
 
 impl GeminiStreamResponse {
     /// Convert Gemini streaming response to Anthropic streaming events
+    ///
+    /// A safety block (prompt `blockReason` or a `SAFETY` finish reason) ends the
+    /// stream with an `error` event instead of a `message_stop`, so clients can
+    /// tell a blocked response from a complete one.
     pub fn to_anthropic_events(
         &self,
         _model: &str,
@@ -719,6 +889,11 @@ impl GeminiStreamResponse {
     ) -> Result<Vec<AnthropicStreamEvent>, AppError> {
         let mut events = Vec::new();
 
+        if let Some(message) = self.prompt_feedback.as_ref().and_then(PromptFeedback::block_message) {
+            events.push(Self::safety_error_event(message));
+            return Ok(events);
+        }
+
         if let Some(candidates) = &self.candidates {
             for candidate in candidates {
                 if let Some(content) = &candidate.content {
@@ -733,46 +908,77 @@ impl GeminiStreamResponse {
                     if !text.is_empty() {
                         // Create content block delta event
                         events.push(AnthropicStreamEvent::ContentBlockDelta {
-                            index: candidate.index.unwrap_or(0) as u32,
+                            index: candidate.index.unwrap_or(0),
                             delta: TextDelta {
                                 type_field: "text_delta".to_string(),
                                 text,
                             },
                         });
                     }
+                }
 
-                    // Handle finish reason
-                    if let Some(finish_reason) = &candidate.finish_reason {
-                        let stop_reason = match finish_reason.as_str() {
-                            "STOP" => Some("end_turn".to_string()),
-                            "MAX_TOKENS" => Some("max_tokens".to_string()),
-                            "SAFETY" => Some("stop_sequence".to_string()),
-                            "RECITATION" => Some("stop_sequence".to_string()),
-                            "OTHER" => Some("stop_sequence".to_string()),
-                            _ => Some("stop_sequence".to_string()),
-                        };
-
-                        events.push(AnthropicStreamEvent::MessageDelta {
-                            delta: MessageDelta {
-                                stop_reason,
-                                usage: self.usage_metadata.as_ref().map(|usage| Usage {
-                                    input_tokens: usage.prompt_token_count.unwrap_or(0),
-                                    output_tokens: usage.candidates_token_count.unwrap_or(0),
-                                }),
-                            },
-                        });
-
-                        events.push(AnthropicStreamEvent::MessageStop);
+                // Handle finish reason; blocked chunks often carry no content at all
+                if let Some(finish_reason) = &candidate.finish_reason {
+                    if finish_reason == "SAFETY" {
+                        events.push(Self::safety_error_event(
+                            "Response blocked by safety filter".to_string(),
+                        ));
+                        continue;
                     }
+
+                    let stop_reason = Some(gemini_utils::stop_reason(finish_reason));
+
+                    events.push(AnthropicStreamEvent::MessageDelta {
+                        delta: MessageDelta {
+                            stop_reason,
+                            usage: self.usage_metadata.as_ref().map(|usage| Usage {
+                                input_tokens: usage.prompt_token_count.unwrap_or(0),
+                                output_tokens: usage.candidates_token_count.unwrap_or(0),
+                                thinking_tokens: usage.thoughts_token_count,
+                            }),
+                        },
+                    });
+
+                    events.push(AnthropicStreamEvent::MessageStop);
                 }
             }
         }
 
+        // A trailing chunk may carry only the final token counts
+        let has_candidates = self.candidates.as_ref().is_some_and(|c| !c.is_empty());
+        if !has_candidates && let Some(usage) = &self.usage_metadata {
+            events.push(AnthropicStreamEvent::MessageDelta {
+                delta: MessageDelta {
+                    stop_reason: None,
+                    usage: Some(Usage {
+                        input_tokens: usage.prompt_token_count.unwrap_or(0),
+                        output_tokens: usage.candidates_token_count.unwrap_or(0),
+                        thinking_tokens: usage.thoughts_token_count,
+                    }),
+                },
+            });
+        }
+
         Ok(events)
     }
 
+    /// Error event reported when Gemini blocks the prompt or the response
+    fn safety_error_event(message: String) -> AnthropicStreamEvent {
+        use crate::providers::anthropic::StreamError;
+        AnthropicStreamEvent::Error {
+            error: StreamError {
+                error_type: "safety_error".to_string(),
+                message,
+                output_tokens: None,
+            },
+        }
+    }
+
     /// Create initial streaming events for message start
-    pub fn create_message_start_event(model: &str, message_id: &str) -> AnthropicStreamEvent {
+    ///
+    /// `input_tokens` is reported up front so clients can see the prompt cost
+    /// before any output arrives.
+    pub fn create_message_start_event(model: &str, message_id: &str, input_tokens: u32) -> AnthropicStreamEvent {
         AnthropicStreamEvent::MessageStart {
             message: StreamMessage {
                 id: message_id.to_string(),
@@ -780,8 +986,9 @@ impl GeminiStreamResponse {
                 role: "assistant".to_string(),
                 content: vec![],
                 usage: Usage {
-                    input_tokens: 0,
+                    input_tokens,
                     output_tokens: 0,
+                    thinking_tokens: None,
                 },
             },
         }
@@ -805,16 +1012,95 @@ impl GeminiStreamResponse {
             error: StreamError {
                 error_type: "provider_error".to_string(),
                 message: error.to_string(),
+                output_tokens: None,
             },
         }
     }
 
-    /// Check if streaming response has any issues
+    /// Check if streaming response was blocked by a safety filter
     pub fn has_streaming_issues(&self) -> bool {
-        if let Some(_candidates) = &self.candidates {
-            // Note: streaming candidates don't have safety_ratings field
-            // This is a simplified check for streaming issues
+        if self.prompt_feedback.as_ref().and_then(PromptFeedback::block_message).is_some() {
+            return true;
+        }
+
+        self.candidates.iter().flatten().any(|candidate| {
+            candidate.finish_reason.as_deref() == Some("SAFETY")
+        })
+    }
+}
+
+/// Utility functions for Gemini data transformations
+pub mod gemini_utils {
+    use super::*;
+
+    /// Map an Anthropic `tool_choice` onto Gemini's function calling config
+    ///
+    /// Gemini has no "call this specific tool" mode, so a named tool becomes
+    /// `ANY` restricted to that single function.
+    pub fn tool_config(choice: &ToolChoice) -> ToolConfig {
+        let (mode, allowed_function_names) = match choice {
+            ToolChoice::Auto => (FunctionCallingMode::Auto, None),
+            ToolChoice::Any => (FunctionCallingMode::Any, None),
+            ToolChoice::None => (FunctionCallingMode::None, None),
+            ToolChoice::Tool { name } => (FunctionCallingMode::Any, Some(vec![name.clone()])),
+        };
+        ToolConfig {
+            function_calling_config: FunctionCallingConfig {
+                mode,
+                allowed_function_names,
+            },
+        }
+    }
+
+    /// Strip JSON schema keywords that Gemini's OpenAPI-subset schema rejects
+    pub fn function_parameters(schema: &serde_json::Value) -> serde_json::Value {
+        match schema {
+            serde_json::Value::Object(map) => map
+                .iter()
+                .filter(|(key, _)| !matches!(key.as_str(), "$schema" | "additionalProperties"))
+                .map(|(key, value)| (key.clone(), function_parameters(value)))
+                .collect::<serde_json::Map<_, _>>()
+                .into(),
+            serde_json::Value::Array(items) => items.iter().map(function_parameters).collect(),
+            other => other.clone(),
         }
-        false
     }
-}
\ No newline at end of file
+
+    /// Map a Gemini `finishReason` onto the Anthropic `stop_reason` vocabulary
+    pub fn stop_reason(finish_reason: &str) -> String {
+        match finish_reason {
+            "STOP" => "end_turn",
+            "MAX_TOKENS" => "max_tokens",
+            "SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII" => "content_filter",
+            _ => "stop_sequence",
+        }
+        .to_string()
+    }
+
+    /// Parse a Gemini error body into `(message, type, code)`
+    ///
+    /// Gemini errors look like `{"error":{"code":400,"message":"...","status":"INVALID_ARGUMENT",
+    /// "details":[{"reason":"API_KEY_INVALID"}]}}`; `status` becomes the type and the first
+    /// `details[].reason` the code. Unparseable bodies yield the raw body as the message.
+    pub fn parse_error_details(error_body: &str) -> (String, Option<String>, Option<String>) {
+        let Some(error) = serde_json::from_str::<serde_json::Value>(error_body)
+            .ok()
+            .and_then(|value| value.get("error").cloned())
+        else {
+            return (error_body.to_string(), None, None);
+        };
+
+        let message = error
+            .get("message")
+            .and_then(|m| m.as_str())
+            .unwrap_or(error_body)
+            .to_string();
+        let error_type = error.get("status").and_then(|s| s.as_str()).map(str::to_string);
+        let error_code = error
+            .get("details")
+            .and_then(|d| d.as_array())
+            .and_then(|details| details.iter().find_map(|d| d.get("reason").and_then(|r| r.as_str())))
+            .map(str::to_string);
+        (message, error_type, error_code)
+    }
+}
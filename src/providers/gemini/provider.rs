@@ -2,9 +2,9 @@ use async_trait::async_trait;
 use reqwest::Client;
 
 use crate::{
-    config::ProviderDetail,
+    config::{HealthProbe, ProviderDetail},
     errors::AppError,
-    providers::{AIProvider, HealthStatus, ModelInfo, StreamResponse, anthropic::*, gemini::*},
+    providers::{AIProvider, CancellableStream, EmbeddingResponse, HealthStatus, ModelInfo, ProviderCapabilities, StreamResponse, anthropic::*, apply_max_tokens_policy, extra_headers, forwarded_response_headers, gemini::*, health_probe_request, parse_json_body, retry_after_from_headers, tcp_connect_probe, truncate_body, with_message_close},
 };
 
 /// Google Gemini provider implementation
@@ -53,20 +53,27 @@ impl GeminiProvider {
         let response = self
             .client
             .get(&url)
+            .headers(extra_headers(&self.config))
             .send()
             .await
             .map_err(|e| AppError::ProviderError {
                 status: 500,
                 message: format!("Failed to fetch models from Gemini: {}", e),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
             })?;
 
         if !response.status().is_success() {
             let status = response.status().as_u16();
             let error_body = response.text().await.unwrap_or_default();
-            tracing::warn!("Gemini models API error: status={}, body={}", status, error_body);
+            tracing::warn!("Gemini models API error: status={}, body={}", status, truncate_body(&error_body, self.config.log_body_limit()));
             return Err(AppError::ProviderError {
                 status,
-                message: format!("Gemini models API error: {}", error_body),
+                message: format!("Gemini models API error: {}", truncate_body(&error_body, self.config.error_body_limit())),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
             });
         }
 
@@ -76,6 +83,9 @@ impl GeminiProvider {
             .map_err(|e| AppError::ProviderError {
                 status: 500,
                 message: format!("Failed to parse Gemini models response: {}", e),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
             })?;
 
         // Parse the models from Gemini's response format
@@ -85,6 +95,9 @@ impl GeminiProvider {
             .ok_or_else(|| AppError::ProviderError {
                 status: 500,
                 message: "Invalid models response format from Gemini".to_string(),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
             })?
             .iter()
             .filter_map(|model| {
@@ -97,6 +110,8 @@ impl GeminiProvider {
                     object: "model".to_string(),
                     created: 1714560000, // Static timestamp for now
                     owned_by: "google".to_string(),
+                    context_length: None,
+                    provider: None,
                 })
             })
             .collect();
@@ -106,6 +121,42 @@ impl GeminiProvider {
 }
 
 impl GeminiProvider {
+    /// Token-free probe: list models
+    async fn probe_models_list(&self) -> Result<(), AppError> {
+        let url = format!(
+            "{}/models?key={}",
+            self.config
+                .api_base
+                .trim_end_matches('/')
+                .replace("/v1beta/models", "/v1beta"),
+            self.config.api_key
+        );
+
+        let response = self
+            .client
+            .get(&url)
+            .headers(extra_headers(&self.config))
+            .send()
+            .await
+            .map_err(|e| AppError::provider_error(500, e.to_string()))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            return Err(AppError::provider_error(status.as_u16(), format!("HTTP {}", status)));
+        }
+        Ok(())
+    }
+
+    /// Reject logprobs requests, which the proxy cannot map onto Gemini
+    fn reject_logprobs(request: &AnthropicRequest) -> Result<(), AppError> {
+        if request.requests_logprobs() {
+            return Err(AppError::ValidationError(
+                "Gemini does not support logprobs/top_logprobs; use an OpenAI model".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
     /// Convert Anthropic request format to Gemini format
     fn convert_request(&self, request: &AnthropicRequest) -> Result<GeminiRequest, AppError> {
         GeminiRequest::from_anthropic(request)
@@ -123,9 +174,11 @@ impl GeminiProvider {
 
 #[async_trait]
 impl AIProvider for GeminiProvider {
-    async fn chat(&self, request: AnthropicRequest) -> Result<AnthropicResponse, AppError> {
-        // Validate request
+    async fn chat(&self, mut request: AnthropicRequest) -> Result<AnthropicResponse, AppError> {
+        // Apply the configured max_tokens default and output cap, then validate
+        apply_max_tokens_policy(&mut request, &self.config)?;
         request.validate().map_err(AppError::ValidationError)?;
+        Self::reject_logprobs(&request)?;
 
         // Convert to Gemini format
         let gemini_req = self.convert_request(&request)?;
@@ -142,50 +195,66 @@ impl AIProvider for GeminiProvider {
         let response = self
             .client
             .post(&url)
+            .headers(extra_headers(&self.config))
             .json(&gemini_req)
+            .timeout(request.upstream_timeout(self.config.read_timeout_secs()))
             .send()
             .await
-            .map_err(|e| AppError::ProviderError {
-                status: 500,
-                message: format!("Failed to send request to Gemini: {}", e),
+            .map_err(|e| {
+                if e.is_connect() && e.is_timeout() {
+                    AppError::NetworkError(format!("Gemini connect timed out: {}", e))
+                } else if e.is_timeout() {
+                    AppError::GatewayTimeout(format!("Gemini request timed out: {}", e))
+                } else {
+                    AppError::ProviderError {
+                        status: 500,
+                        message: format!("Failed to send request to Gemini: {}", e),
+                        error_type: None,
+                        error_code: None,
+                        retry_after: None,
+                    }
+                }
             })?;
 
         // Handle HTTP errors
         if !response.status().is_success() {
             let status = response.status().as_u16();
+            let retry_after = retry_after_from_headers(response.headers());
             let error_body = response.text().await.unwrap_or_default();
-            return Err(AppError::ProviderError {
+            let (message, error_type, error_code) = gemini_utils::parse_error_details(&error_body);
+            return Err(AppError::provider_error_with_details(
                 status,
-                message: format!("Gemini API error: {}", error_body.replace("Gemini API error: ", "")),
-            });
+                format!("Gemini API error: {}", truncate_body(&message, self.config.error_body_limit())),
+                error_type,
+                error_code,
+            ).with_retry_after(retry_after));
         }
 
+        let upstream_headers = forwarded_response_headers(&self.config, response.headers());
+
         // Parse response
-        let gemini_res =
-            response
-                .json::<GeminiResponse>()
-                .await
-                .map_err(|e| AppError::ProviderError {
-                    status: 500,
-                    message: format!("Failed to parse Gemini response: {}", e),
-                })?;
+        let gemini_res: GeminiResponse = parse_json_body(response, "Gemini response", &self.config).await?;
 
         // Convert to standard format
-        self.convert_response(gemini_res, &request.model)
+        let mut response = self.convert_response(gemini_res, &request.model)?;
+        response.upstream_headers = upstream_headers;
+        Ok(response)
     }
 
-    async fn chat_stream(&self, request: AnthropicRequest) -> Result<StreamResponse, AppError> {
+    async fn chat_stream(&self, mut request: AnthropicRequest) -> Result<StreamResponse, AppError> {
         use futures::StreamExt;
         
-        // Validate request
+        // Apply the configured max_tokens default and output cap, then validate
+        apply_max_tokens_policy(&mut request, &self.config)?;
         request.validate().map_err(AppError::ValidationError)?;
+        Self::reject_logprobs(&request)?;
 
         // Convert to Gemini format
         let gemini_req = self.convert_request(&request)?;
 
         // Build streaming URL
         let url = format!(
-            "{}/models/{}:streamGenerateContent?key={}",
+            "{}/models/{}:streamGenerateContent?alt=sse&key={}",
             self.config.api_base.trim_end_matches('/'),
             request.model,
             self.config.api_key
@@ -197,22 +266,39 @@ impl AIProvider for GeminiProvider {
         let response = self
             .client
             .post(&url)
+            .headers(extra_headers(&self.config))
             .json(&gemini_req)
+            .timeout(request.upstream_timeout(self.config.read_timeout_secs()))
             .send()
             .await
-            .map_err(|e| AppError::ProviderError {
-                status: 500,
-                message: format!("Failed to send streaming request to Gemini: {}", e),
+            .map_err(|e| {
+                if e.is_connect() && e.is_timeout() {
+                    AppError::NetworkError(format!("Gemini streaming connect timed out: {}", e))
+                } else if e.is_timeout() {
+                    AppError::GatewayTimeout(format!("Gemini streaming request timed out: {}", e))
+                } else {
+                    AppError::ProviderError {
+                        status: 500,
+                        message: format!("Failed to send streaming request to Gemini: {}", e),
+                        error_type: None,
+                        error_code: None,
+                        retry_after: None,
+                    }
+                }
             })?;
 
         // Check for HTTP errors
         if !response.status().is_success() {
             let status = response.status().as_u16();
+            let retry_after = retry_after_from_headers(response.headers());
             let error_body = response.text().await.unwrap_or_default();
-            return Err(AppError::ProviderError {
+            let (message, error_type, error_code) = gemini_utils::parse_error_details(&error_body);
+            return Err(AppError::provider_error_with_details(
                 status,
-                message: format!("Gemini streaming API error: {}", error_body),
-            });
+                format!("Gemini streaming API error: {}", truncate_body(&message, self.config.error_body_limit())),
+                error_type,
+                error_code,
+            ).with_retry_after(retry_after));
         }
 
         // Get the response body as a stream
@@ -221,119 +307,65 @@ impl AIProvider for GeminiProvider {
         // Generate unique message ID for this streaming session
         let message_id = format!("msg_{}", uuid::Uuid::new_v4().simple());
         let model_name = request.model.clone();
+
+        // Report an input estimate in message_start; the final chunk carries the exact count
+        let input_tokens = request.estimate_input_tokens();
         
+        // Gemini frames each chunk as an SSE `data:` line; lines may be split across reads
+        let mut buffer = String::new();
+        let mut started = false;
+
         // Process streaming bytes and convert to SSE events
         let sse_stream = body
-            .enumerate()
-            .filter_map(move |(chunk_index, chunk_result)| {
-                let message_id = message_id.clone();
-                let model_name = model_name.clone();
-                
-                async move {
-                    match chunk_result {
-                        Ok(bytes) => {
-                            // Convert bytes to string
-                            let chunk_str = String::from_utf8_lossy(&bytes);
-                            
-                            // Process complete lines from chunk
-                            let mut sse_events = Vec::new();
-                            let lines: Vec<&str> = chunk_str.lines().collect();
-                            
-                            for (line_index, line) in lines.iter().enumerate() {
-                                // Skip empty lines
-                                if line.trim().is_empty() {
-                                    continue;
-                                }
-
-                                // Parse JSON line from Gemini streaming response
-                                match serde_json::from_str::<GeminiStreamResponse>(line) {
-                                    Ok(gemini_stream) => {
-                                        // Add message start event if this is the first chunk
-                                        if chunk_index == 0 && line_index == 0 {
-                                            let start_event = GeminiStreamResponse::create_message_start_event(&model_name, &message_id);
-                                            if let Ok(start_json) = serde_json::to_string(&start_event) {
-                                                sse_events.push(format!("event: message_start\ndata: {}\n\n", start_json));
-                                            }
-                                            
-                                            // Add content block start event
-                                            let content_start_event = GeminiStreamResponse::create_content_block_start_event();
-                                            if let Ok(content_json) = serde_json::to_string(&content_start_event) {
-                                                sse_events.push(format!("event: content_block_start\ndata: {}\n\n", content_json));
-                                            }
-                                        }
-                                        
-                                        // Convert to Anthropic streaming events
-                                        match gemini_stream.to_anthropic_events(&model_name, &message_id) {
-                                            Ok(events) => {
-                                                // Convert each event to SSE format
-                                                for event in events {
-                                                    match event {
-                                                        AnthropicStreamEvent::ContentBlockDelta { .. } => {
-                                                            if let Ok(json) = serde_json::to_string(&event) {
-                                                                sse_events.push(format!("event: content_block_delta\ndata: {}\n\n", json));
-                                                            }
-                                                        }
-                                                        AnthropicStreamEvent::MessageDelta { .. } => {
-                                                            if let Ok(json) = serde_json::to_string(&event) {
-                                                                sse_events.push(format!("event: message_delta\ndata: {}\n\n", json));
-                                                            }
-                                                        }
-                                                        AnthropicStreamEvent::MessageStop => {
-                                                            // Add content block stop first
-                                                            let content_stop = AnthropicStreamEvent::ContentBlockStop { index: 0 };
-                                                            if let Ok(json) = serde_json::to_string(&content_stop) {
-                                                                sse_events.push(format!("event: content_block_stop\ndata: {}\n\n", json));
-                                                            }
-                                                            
-                                                            // Then add message stop
-                                                            if let Ok(json) = serde_json::to_string(&event) {
-                                                                sse_events.push(format!("event: message_stop\ndata: {}\n\n", json));
-                                                            }
-                                                        }
-                                                        _ => {
-                                                            if let Ok(json) = serde_json::to_string(&event) {
-                                                                sse_events.push(format!("data: {}\n\n", json));
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                            Err(e) => {
-                                                tracing::error!("Failed to convert Gemini stream to Anthropic events: {}", e);
-                                                let error_event = GeminiStreamResponse::create_error_event(&e);
-                                                if let Ok(json) = serde_json::to_string(&error_event) {
-                                                    sse_events.push(format!("event: error\ndata: {}\n\n", json));
-                                                }
-                                            }
-                                        }
-                                    }
-                                    Err(parse_err) => {
-                                        tracing::warn!("Failed to parse Gemini streaming response line: {} - Error: {}", line, parse_err);
-                                        // Skip malformed lines but continue streaming
-                                    }
-                                }
-                            }
-                            
-                            if !sse_events.is_empty() {
-                                Some(Ok(sse_events.join("")))
-                            } else {
-                                None
-                            }
-                        }
-                        Err(e) => {
-                            tracing::error!("Error reading streaming response chunk: {}", e);
-                            let app_error = AppError::ProviderError {
-                                status: 500,
-                                message: format!("Streaming read error: {}", e),
-                            };
-                            Some(Err(app_error))
-                        }
+            .map(move |chunk_result| match chunk_result {
+                Ok(bytes) => {
+                    buffer.push_str(&String::from_utf8_lossy(&bytes));
+
+                    let mut sse_events = Vec::new();
+                    while let Some(newline) = buffer.find('\n') {
+                        let line: String = buffer.drain(..=newline).collect();
+                        process_stream_line(&line, &model_name, &message_id, input_tokens, &mut started, &mut sse_events);
+                    }
+
+                    // A trailing line without a newline is consumed once it holds a complete chunk
+                    if let Some(payload) = stream_line_payload(&buffer)
+                        && serde_json::from_str::<GeminiStreamResponse>(payload).is_ok()
+                    {
+                        let line = std::mem::take(&mut buffer);
+                        process_stream_line(&line, &model_name, &message_id, input_tokens, &mut started, &mut sse_events);
+                    }
+
+                    if sse_events.is_empty() {
+                        None
+                    } else {
+                        Some(Ok(sse_events.join("")))
                     }
                 }
-            });
+                Err(e) => {
+                    tracing::error!("Error reading streaming response chunk: {}", e);
+                    Some(Err(AppError::ProviderError {
+                        status: 500,
+                        message: format!("Streaming read error: {}", e),
+                        error_type: None,
+                        error_code: None,
+                        retry_after: None,
+                    }))
+                }
+            })
+            .filter_map(futures::future::ready);
 
         tracing::info!("Gemini streaming response initialized successfully");
-        Ok(Box::pin(sse_stream))
+        let sse_stream = with_message_close(Box::pin(sse_stream), &request.model);
+        Ok(CancellableStream::wrap(sse_stream, "gemini", request.model.clone()))
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: true,
+            tools: true,
+            vision: true,
+            embeddings: true,
+        }
     }
 
     async fn list_models(&self) -> Result<Vec<ModelInfo>, AppError> {
@@ -366,6 +398,8 @@ impl AIProvider for GeminiProvider {
                         object: "model".to_string(),
                         created: 1714560000, // Static timestamp for now
                         owned_by: "google".to_string(),
+                        context_length: None,
+                        provider: None,
                     })
                     .collect())
             }
@@ -375,39 +409,182 @@ impl AIProvider for GeminiProvider {
     async fn health_check(&self) -> Result<HealthStatus, AppError> {
         let start = std::time::Instant::now();
 
-        // Simple health check by trying to list models
-        let url = format!(
-            "{}/models?key={}",
-            self.config
-                .api_base
-                .trim_end_matches('/')
-                .replace("/v1beta/models", "/v1beta"),
-            self.config.api_key
-        );
-
-        let result = self.client.get(&url).send().await;
+        // The models list is the default probe
+        let result = match self.config.health_check {
+            Some(HealthProbe::Disabled) => return Ok(HealthStatus::disabled("gemini")),
+            Some(HealthProbe::TcpConnect) => tcp_connect_probe(&self.config.api_base).await,
+            Some(HealthProbe::MinimalChat) => self
+                .chat(health_probe_request(&self.config, "gemini-pro"))
+                .await
+                .map(|_| ()),
+            Some(HealthProbe::ModelsList) | None => self.probe_models_list().await,
+        };
 
         let latency = start.elapsed().as_millis() as u64;
 
         match result {
-            Ok(response) if response.status().is_success() => Ok(HealthStatus {
+            Ok(()) => Ok(HealthStatus {
                 status: "healthy".to_string(),
                 provider: "gemini".to_string(),
                 latency_ms: Some(latency),
                 error: None,
             }),
-            Ok(response) => Ok(HealthStatus {
-                status: "unhealthy".to_string(),
-                provider: "gemini".to_string(),
-                latency_ms: Some(latency),
-                error: Some(format!("HTTP {}", response.status())),
-            }),
             Err(e) => Ok(HealthStatus {
                 status: "unhealthy".to_string(),
                 provider: "gemini".to_string(),
                 latency_ms: Some(latency),
-                error: Some(e.to_string()),
+                error: Some(match e {
+                    AppError::ProviderError { message, .. } => message,
+                    other => other.to_string(),
+                }),
             }),
         }
     }
+
+    async fn embeddings(&self, input: Vec<String>, model: &str) -> Result<EmbeddingResponse, AppError> {
+        let url = format!(
+            "{}/models/{}:embedContent?key={}",
+            self.config.api_base.trim_end_matches('/'),
+            model,
+            self.config.api_key
+        );
+
+        // embedContent embeds a single content, so inputs are sent one at a time
+        let mut vectors = Vec::with_capacity(input.len());
+        for text in input {
+            let embed_req = GeminiEmbedRequest {
+                model: format!("models/{}", model),
+                content: GeminiContent {
+                    role: "user".to_string(),
+                    parts: vec![GeminiPart { text, function_call: None }],
+                },
+            };
+
+            let response = self
+                .client
+                .post(&url)
+                .headers(extra_headers(&self.config))
+                .json(&embed_req)
+                .timeout(std::time::Duration::from_secs(self.config.read_timeout_secs()))
+                .send()
+                .await
+                .map_err(|e| {
+                    if e.is_connect() && e.is_timeout() {
+                        AppError::NetworkError(format!("Gemini embeddings connect timed out: {}", e))
+                    } else if e.is_timeout() {
+                        AppError::GatewayTimeout(format!("Gemini embeddings request timed out: {}", e))
+                    } else {
+                        AppError::ProviderError {
+                            status: 500,
+                            message: format!("Failed to send embeddings request to Gemini: {}", e),
+                            error_type: None,
+                            error_code: None,
+                            retry_after: None,
+                        }
+                    }
+                })?;
+
+            if !response.status().is_success() {
+                let status = response.status().as_u16();
+                let retry_after = retry_after_from_headers(response.headers());
+                let error_body = response.text().await.unwrap_or_default();
+                let (message, error_type, error_code) = gemini_utils::parse_error_details(&error_body);
+                return Err(AppError::provider_error_with_details(
+                    status,
+                    format!("Gemini API error: {}", truncate_body(&message, self.config.error_body_limit())),
+                    error_type,
+                    error_code,
+                ).with_retry_after(retry_after));
+            }
+
+            let embed_res: GeminiEmbedResponse = parse_json_body(response, "Gemini embeddings response", &self.config).await?;
+            vectors.push(embed_res.embedding.values);
+        }
+
+        // Gemini does not report token usage for embeddings
+        Ok(EmbeddingResponse::new(model.to_string(), vectors, 0))
+    }
+}
+
+/// Extract the JSON payload from a Gemini stream line, accepting both `data:` framing and bare JSON
+fn stream_line_payload(line: &str) -> Option<&str> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with(':') {
+        return None;
+    }
+    let payload = match line.strip_prefix("data:") {
+        Some(data) => data.trim_start(),
+        None if line.starts_with('{') => line,
+        None => return None,
+    };
+    (!payload.is_empty() && payload != "[DONE]").then_some(payload)
+}
+
+/// Convert one Gemini stream line into Anthropic SSE events
+fn process_stream_line(
+    line: &str,
+    model_name: &str,
+    message_id: &str,
+    input_tokens: u32,
+    started: &mut bool,
+    sse_events: &mut Vec<String>,
+) {
+    let Some(payload) = stream_line_payload(line) else {
+        return;
+    };
+
+    let gemini_stream = match serde_json::from_str::<GeminiStreamResponse>(payload) {
+        Ok(gemini_stream) => gemini_stream,
+        Err(parse_err) => {
+            // Skip malformed lines but continue streaming
+            tracing::warn!("Failed to parse Gemini streaming response line: {} - Error: {}", payload, parse_err);
+            return;
+        }
+    };
+
+    // Open the message before the first converted chunk
+    if !*started {
+        *started = true;
+        let start_event = GeminiStreamResponse::create_message_start_event(model_name, message_id, input_tokens);
+        if let Ok(start_json) = serde_json::to_string(&start_event) {
+            sse_events.push(format!("event: message_start\ndata: {}\n\n", start_json));
+        }
+
+        let content_start_event = GeminiStreamResponse::create_content_block_start_event();
+        if let Ok(content_json) = serde_json::to_string(&content_start_event) {
+            sse_events.push(format!("event: content_block_start\ndata: {}\n\n", content_json));
+        }
+    }
+
+    let events = match gemini_stream.to_anthropic_events(model_name, message_id) {
+        Ok(events) => events,
+        Err(e) => {
+            tracing::error!("Failed to convert Gemini stream to Anthropic events: {}", e);
+            let error_event = GeminiStreamResponse::create_error_event(&e);
+            if let Ok(json) = serde_json::to_string(&error_event) {
+                sse_events.push(format!("event: error\ndata: {}\n\n", json));
+            }
+            return;
+        }
+    };
+
+    for event in events {
+        let name = match event {
+            AnthropicStreamEvent::ContentBlockDelta { .. } => "content_block_delta",
+            AnthropicStreamEvent::MessageDelta { .. } => "message_delta",
+            AnthropicStreamEvent::MessageStop => {
+                // Close the content block before the message
+                let content_stop = AnthropicStreamEvent::ContentBlockStop { index: 0 };
+                if let Ok(json) = serde_json::to_string(&content_stop) {
+                    sse_events.push(format!("event: content_block_stop\ndata: {}\n\n", json));
+                }
+                "message_stop"
+            }
+            AnthropicStreamEvent::Error { .. } => "error",
+            _ => event.event_name(),
+        };
+        if let Ok(json) = serde_json::to_string(&event) {
+            sse_events.push(format!("event: {}\ndata: {}\n\n", name, json));
+        }
+    }
 }
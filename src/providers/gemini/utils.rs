@@ -6,7 +6,7 @@ use crate::{AppError, providers::gemini::model::*};
 pub fn create_simple_request(content: String, max_tokens: u32) -> GeminiRequest {
     let gemini_content = GeminiContent {
         role: "user".to_string(),
-        parts: vec![GeminiPart { text: content }],
+        parts: vec![GeminiPart { text: content , function_call: None}],
     };
 
     GeminiRequest::new(vec![gemini_content], max_tokens)
@@ -33,7 +33,7 @@ pub fn create_conversation_request(
 
             Ok(GeminiContent {
                 role: gemini_role.to_string(),
-                parts: vec![GeminiPart { text: content }],
+                parts: vec![GeminiPart { text: content , function_call: None}],
             })
         })
         .collect::<Result<Vec<_>, AppError>>()?;
@@ -48,6 +48,9 @@ pub fn extract_text_content(response: &GeminiResponse) -> Result<String, AppErro
         return Err(AppError::ProviderError {
             status: 500,
             message: "Response contains API error".to_string(),
+            error_type: None,
+            error_code: None,
+            retry_after: None,
         });
     }
 
@@ -55,6 +58,9 @@ pub fn extract_text_content(response: &GeminiResponse) -> Result<String, AppErro
         return Err(AppError::ProviderError {
             status: 500,
             message: "No candidates in response".to_string(),
+            error_type: None,
+            error_code: None,
+            retry_after: None,
         });
     }
 
@@ -63,6 +69,9 @@ pub fn extract_text_content(response: &GeminiResponse) -> Result<String, AppErro
         return Err(AppError::ProviderError {
             status: 500,
             message: "Candidate has no content parts".to_string(),
+            error_type: None,
+            error_code: None,
+            retry_after: None,
         });
     }
 
@@ -78,6 +87,9 @@ pub fn extract_text_content(response: &GeminiResponse) -> Result<String, AppErro
         return Err(AppError::ProviderError {
             status: 500,
             message: "Empty text content in response".to_string(),
+            error_type: None,
+            error_code: None,
+            retry_after: None,
         });
     }
 
@@ -129,7 +141,7 @@ pub fn create_simple_tool(name: String, description: String, parameters: Option<
         function_declarations: vec![FunctionDeclaration {
             name,
             description,
-            parameters,
+            parameters: parameters.and_then(|schema| serde_json::to_value(schema).ok()),
         }],
     }
 }
@@ -163,6 +175,9 @@ pub fn validate_response_structure(response: &GeminiResponse) -> Result<(), AppE
         return Err(AppError::ProviderError {
             status: 500,
             message: "Response contains API error".to_string(),
+            error_type: None,
+            error_code: None,
+            retry_after: None,
         });
     }
 
@@ -170,6 +185,9 @@ pub fn validate_response_structure(response: &GeminiResponse) -> Result<(), AppE
         return Err(AppError::ProviderError {
             status: 500,
             message: "No candidates in response".to_string(),
+            error_type: None,
+            error_code: None,
+            retry_after: None,
         });
     }
 
@@ -178,6 +196,9 @@ pub fn validate_response_structure(response: &GeminiResponse) -> Result<(), AppE
             return Err(AppError::ProviderError {
                 status: 500,
                 message: format!("Candidate {} has no content parts", i),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
             });
         }
     }
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::{Mutex, RwLock};
+
+use super::{HealthStatus, ProviderRegistry};
+
+/// Provider health status served from the cache, with its freshness
+#[derive(Debug, Clone, Serialize)]
+pub struct CachedHealthStatus {
+    #[serde(flatten)]
+    pub health: HealthStatus,
+    /// Seconds since this status was fetched from the provider
+    pub age_seconds: u64,
+    /// When this status was fetched from the provider
+    pub last_checked: DateTime<Utc>,
+    /// Number of unhealthy checks in a row, reset by a healthy check
+    pub consecutive_failures: u32,
+    /// Error from the most recent failed check, kept after the provider recovers
+    pub last_error: Option<String>,
+}
+
+/// Latest check result for one provider plus its rolling failure history
+#[derive(Debug, Clone)]
+struct HealthEntry {
+    health: HealthStatus,
+    checked_at: Instant,
+    last_checked: DateTime<Utc>,
+    consecutive_failures: u32,
+    last_error: Option<String>,
+}
+
+impl HealthEntry {
+    /// Fold a fresh check result into the history
+    fn record(previous: Option<&HealthEntry>, health: HealthStatus) -> Self {
+        let (mut consecutive_failures, mut last_error) = previous
+            .map(|entry| (entry.consecutive_failures, entry.last_error.clone()))
+            .unwrap_or_default();
+
+        if health.status == "healthy" {
+            consecutive_failures = 0;
+        } else {
+            consecutive_failures += 1;
+            last_error = Some(health.error.clone().unwrap_or_else(|| health.status.clone()));
+        }
+
+        Self {
+            health,
+            checked_at: Instant::now(),
+            last_checked: Utc::now(),
+            consecutive_failures,
+            last_error,
+        }
+    }
+}
+
+/// TTL cache in front of live provider health checks
+///
+/// Monitoring systems poll `/health/providers` frequently; each provider is only
+/// re-checked once its cached status is older than the TTL. The lock is held while
+/// refreshing so concurrent pollers share a single round of upstream checks.
+/// Each entry also tracks consecutive failures and the last error for triage.
+#[derive(Debug, Default)]
+pub struct HealthCheckCache {
+    entries: Mutex<HashMap<String, HealthEntry>>,
+}
+
+impl HealthCheckCache {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget all cached statuses, e.g. after the providers were rebuilt
+    pub async fn clear(&self) {
+        self.entries.lock().await.clear();
+    }
+
+    /// 获取提供商健康状态，仅刷新已过期的条目
+    ///
+    /// ## 功能说明
+    /// 返回所有已注册提供商的健康状态；缓存未超过TTL的直接返回，
+    /// 其余调用提供商的实时健康检查并写回缓存
+    ///
+    /// ## 内部实现逻辑
+    /// 1. 获取缓存锁，保证并发请求只触发一轮上游检查
+    /// 2. 移除已不在注册表中的提供商条目
+    /// 3. 对缺失或过期（年龄 >= TTL）的提供商执行实时健康检查
+    /// 4. 累计连续失败次数，并保留最近一次失败的错误信息（恢复健康后仍保留）
+    /// 5. 为每个结果附加`age_seconds`、`last_checked`、`consecutive_failures`和`last_error`
+    ///
+    /// ## 参数说明
+    /// - `registry`: 提供商注册表
+    /// - `ttl`: 缓存有效期，为0时每次都实时检查
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let results = cache.get_or_refresh(&state.provider_registry, Duration::from_secs(30)).await;
+    /// ```
+    ///
+    /// ## 返回值
+    /// - `HashMap<String, CachedHealthStatus>`: 提供商ID到健康状态（含年龄和失败历史）的映射
+    pub async fn get_or_refresh(
+        &self,
+        registry: &RwLock<ProviderRegistry>,
+        ttl: Duration,
+    ) -> HashMap<String, CachedHealthStatus> {
+        let mut entries = self.entries.lock().await;
+        let registry = registry.read().await;
+        let provider_ids = registry.get_provider_ids();
+
+        entries.retain(|id, _| provider_ids.contains(id));
+
+        let mut results = HashMap::with_capacity(provider_ids.len());
+        for provider_id in provider_ids {
+            let is_fresh = entries
+                .get(&provider_id)
+                .is_some_and(|entry| entry.checked_at.elapsed() < ttl);
+
+            if !is_fresh && let Some(health) = registry.health_check_provider(&provider_id).await {
+                let entry = HealthEntry::record(entries.get(&provider_id), health);
+                entries.insert(provider_id.clone(), entry);
+            }
+
+            if let Some(entry) = entries.get(&provider_id) {
+                results.insert(
+                    provider_id,
+                    CachedHealthStatus {
+                        health: entry.health.clone(),
+                        age_seconds: entry.checked_at.elapsed().as_secs(),
+                        last_checked: entry.last_checked,
+                        consecutive_failures: entry.consecutive_failures,
+                        last_error: entry.last_error.clone(),
+                    },
+                );
+            }
+        }
+
+        results
+    }
+}
@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::anthropic::AnthropicResponse;
+
+/// Completed chat responses remembered by idempotency key
+///
+/// A client retrying after a network blip resends the same `Idempotency-Key`;
+/// while the entry is live the stored response is replayed instead of calling
+/// the provider (and billing) a second time. Only successful responses are
+/// stored, so a failed attempt can always be retried.
+#[derive(Debug, Default)]
+pub struct IdempotencyCache {
+    entries: Mutex<HashMap<String, (AnthropicResponse, Instant)>>,
+}
+
+impl IdempotencyCache {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stored response for `key`, if it has not expired yet
+    pub fn get(&self, key: &str) -> Option<AnthropicResponse> {
+        let entries = self.entries.lock().ok()?;
+        entries
+            .get(key)
+            .filter(|(_, expires_at)| Instant::now() < *expires_at)
+            .map(|(response, _)| response.clone())
+    }
+
+    /// Remember `response` under `key` for `ttl`, dropping expired entries
+    pub fn insert(&self, key: String, response: AnthropicResponse, ttl: Duration) {
+        if let Ok(mut entries) = self.entries.lock() {
+            let now = Instant::now();
+            entries.retain(|_, (_, expires_at)| now < *expires_at);
+            entries.insert(key, (response, now + ttl));
+        }
+    }
+
+    /// Number of stored entries, including ones that expired but were not yet pruned
+    pub fn len(&self) -> usize {
+        self.entries.lock().map(|entries| entries.len()).unwrap_or(0)
+    }
+
+    /// Whether no responses are stored
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,194 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::errors::AppError;
+use super::anthropic::{
+    AnthropicRequest, AnthropicResponse, AnthropicStreamEvent, ContentBlockStart, MessageDelta, StreamMessage,
+    TextDelta, Usage,
+};
+use super::{AIProvider, HealthStatus, ModelInfo, StreamResponse};
+
+/// In-process provider returning canned replies, for tests (`test-utils` feature)
+///
+/// Lets crates embedding the proxy exercise routing, streaming and error
+/// handling without an HTTP mock server. Register it with
+/// `ProviderRegistry::register_provider`.
+#[derive(Debug)]
+pub struct MockProvider {
+    reply: String,
+    input_tokens: u32,
+    output_tokens: u32,
+    latency: Option<Duration>,
+    error: Option<AppError>,
+    models: Vec<String>,
+    calls: AtomicUsize,
+}
+
+impl Default for MockProvider {
+    fn default() -> Self {
+        Self {
+            reply: "Hello from the mock provider".to_string(),
+            input_tokens: 10,
+            output_tokens: 5,
+            latency: None,
+            error: None,
+            models: vec!["mock-model".to_string()],
+            calls: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl MockProvider {
+    /// Create a mock that answers every request with a fixed greeting
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Text returned by `chat` and streamed by `chat_stream`
+    pub fn with_reply(mut self, reply: impl Into<String>) -> Self {
+        self.reply = reply.into();
+        self
+    }
+
+    /// Token usage reported for every reply
+    pub fn with_usage(mut self, input_tokens: u32, output_tokens: u32) -> Self {
+        self.input_tokens = input_tokens;
+        self.output_tokens = output_tokens;
+        self
+    }
+
+    /// Delay applied before every reply
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Error returned by `chat` and `chat_stream` instead of a reply
+    pub fn with_error(mut self, error: AppError) -> Self {
+        self.error = Some(error);
+        self
+    }
+
+    /// Models reported by `list_models`
+    pub fn with_models(mut self, models: Vec<String>) -> Self {
+        self.models = models;
+        self
+    }
+
+    /// Number of `chat` and `chat_stream` calls received so far
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+
+    /// Count the call, wait out the latency and return the configured error
+    async fn begin(&self) -> Result<(), AppError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        if let Some(latency) = self.latency {
+            tokio::time::sleep(latency).await;
+        }
+        match &self.error {
+            Some(error) => Err(error.clone()),
+            None => Ok(()),
+        }
+    }
+
+    fn usage(&self) -> Usage {
+        Usage {
+            input_tokens: self.input_tokens,
+            output_tokens: self.output_tokens,
+            thinking_tokens: None,
+        }
+    }
+}
+
+#[async_trait]
+impl AIProvider for MockProvider {
+    async fn chat(&self, request: AnthropicRequest) -> Result<AnthropicResponse, AppError> {
+        self.begin().await?;
+        Ok(AnthropicResponse::new(
+            format!("msg_{}", uuid::Uuid::new_v4().simple()),
+            request.model,
+            self.reply.clone(),
+            self.input_tokens,
+            self.output_tokens,
+        )
+        .with_stop_reason(Some("end_turn".to_string())))
+    }
+
+    async fn chat_stream(&self, request: AnthropicRequest) -> Result<StreamResponse, AppError> {
+        self.begin().await?;
+
+        let events = [
+            AnthropicStreamEvent::MessageStart {
+                message: StreamMessage {
+                    id: format!("msg_{}", uuid::Uuid::new_v4().simple()),
+                    model: request.model,
+                    role: "assistant".to_string(),
+                    content: vec![],
+                    usage: Usage {
+                        output_tokens: 0,
+                        ..self.usage()
+                    },
+                },
+            },
+            AnthropicStreamEvent::ContentBlockStart {
+                index: 0,
+                content_block: ContentBlockStart {
+                    type_field: "text".to_string(),
+                    text: String::new(),
+                },
+            },
+            AnthropicStreamEvent::ContentBlockDelta {
+                index: 0,
+                delta: TextDelta {
+                    type_field: "text_delta".to_string(),
+                    text: self.reply.clone(),
+                },
+            },
+            AnthropicStreamEvent::ContentBlockStop { index: 0 },
+            AnthropicStreamEvent::MessageDelta {
+                delta: MessageDelta {
+                    stop_reason: Some("end_turn".to_string()),
+                    usage: Some(self.usage()),
+                },
+            },
+            AnthropicStreamEvent::MessageStop,
+        ];
+
+        let chunks: Vec<Result<String, AppError>> = events
+            .iter()
+            .map(|event| {
+                serde_json::to_string(event)
+                    .map(|json| format!("event: {}\ndata: {}\n\n", event.event_name(), json))
+                    .map_err(|e| AppError::InternalServerError(format!("Failed to serialize mock event: {}", e)))
+            })
+            .collect();
+        Ok(Box::pin(futures::stream::iter(chunks)))
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, AppError> {
+        Ok(self
+            .models
+            .iter()
+            .map(|model| ModelInfo {
+                id: model.clone(),
+                object: "model".to_string(),
+                created: 1714560000,
+                owned_by: "mock".to_string(),
+                context_length: None,
+                provider: None,
+            })
+            .collect())
+    }
+
+    async fn health_check(&self) -> Result<HealthStatus, AppError> {
+        Ok(HealthStatus {
+            status: if self.error.is_some() { "unhealthy" } else { "healthy" }.to_string(),
+            provider: "mock".to_string(),
+            latency_ms: self.latency.map(|latency| latency.as_millis() as u64),
+            error: self.error.as_ref().map(ToString::to_string),
+        })
+    }
+}
@@ -1,19 +1,609 @@
+pub mod aggregate;
 pub mod anthropic;
+pub mod cancel;
+pub mod coalesce;
 pub mod gemini;
+pub mod health;
+pub mod idempotency;
+#[cfg(feature = "test-utils")]
+pub mod mock;
+pub mod ollama;
 pub mod openai;
+pub mod openrouter;
 pub mod registry;
+pub mod retry;
+pub mod stream_limit;
+pub mod xai;
+
+use std::borrow::Cow;
+use std::pin::Pin;
+use std::task::{Context, Poll};
 
 use async_trait::async_trait;
-use futures::stream::BoxStream;
+use futures::stream::{BoxStream, Stream, StreamExt};
+use crate::config::{OverLimitBehavior, ProviderDetail};
 use crate::errors::AppError;
 use self::anthropic::{AnthropicRequest, AnthropicResponse};
 
 // Re-export registry for easier access
-pub use registry::ProviderRegistry;
+pub use registry::{ModelListWarning, ModelListing, ProviderRegistry};
+pub use aggregate::aggregate_stream;
+pub use cancel::{ActiveStreams, StreamSlot};
+pub use coalesce::RequestCoalescer;
+pub use idempotency::IdempotencyCache;
+pub use retry::{RetryPolicy, chat_with_retries, parse_retry_after, retry_after_from_headers};
+pub use health::{CachedHealthStatus, HealthCheckCache};
+pub use stream_limit::StreamLimitedProvider;
+#[cfg(feature = "test-utils")]
+pub use mock::MockProvider;
+
+/// 获取提供商配置的自定义请求头
+///
+/// ## 功能说明
+/// 返回`extra_headers`对应的`HeaderMap`，在认证请求头之后通过`RequestBuilder::headers`合并，
+/// 以便显式允许时可以覆盖认证请求头。配置非法时记录警告并忽略（启动时的配置校验已拒绝此类配置）
+///
+/// ## 执行例子
+/// ```rust
+/// let builder = client.get(url).header("x-api-key", key).headers(extra_headers(&config));
+/// ```
+pub(crate) fn extra_headers(config: &ProviderDetail) -> reqwest::header::HeaderMap {
+    config.extra_header_map().unwrap_or_else(|e| {
+        tracing::warn!("Ignoring invalid extra_headers: {}", e);
+        reqwest::header::HeaderMap::new()
+    })
+}
+
+/// Prefix for upstream response headers forwarded to the client
+pub const UPSTREAM_HEADER_PREFIX: &str = "x-upstream-";
+
+/// Collect the allow-listed `forward_response_headers` from an upstream response
+///
+/// Names are renamed with `UPSTREAM_HEADER_PREFIX`, dropping a leading `x-`
+/// (`x-ratelimit-remaining` becomes `x-upstream-ratelimit-remaining`).
+pub(crate) fn forwarded_response_headers(
+    config: &ProviderDetail,
+    headers: &reqwest::header::HeaderMap,
+) -> Vec<(String, String)> {
+    config
+        .forward_response_headers
+        .iter()
+        .filter_map(|name| {
+            let value = headers.get(name.as_str())?.to_str().ok()?;
+            let name = name.to_ascii_lowercase();
+            let suffix = name.strip_prefix("x-").unwrap_or(&name);
+            Some((format!("{}{}", UPSTREAM_HEADER_PREFIX, suffix), value.to_string()))
+        })
+        .collect()
+}
+
+/// 截断过长的上游错误响应体
+///
+/// ## 功能说明
+/// 上游可能返回巨大的HTML错误页，记录日志或放入错误消息前按字节数截断，
+/// 截断位置落在UTF-8字符边界上，并附加省略号和被截掉的字节数
+///
+/// ## 参数说明
+/// - `body`: 原始错误响应体
+/// - `max_bytes`: 保留的最大字节数
+///
+/// ## 执行例子
+/// ```rust
+/// let logged = truncate_body("<html>...</html>", 6);
+/// assert_eq!(logged, "<html>… (10 more bytes)");
+/// ```
+///
+/// ## 返回值
+/// 未超出上限时原样返回，否则返回截断后的文本
+pub fn truncate_body(body: &str, max_bytes: usize) -> Cow<'_, str> {
+    if body.len() <= max_bytes {
+        return Cow::Borrowed(body);
+    }
+    let mut cut = max_bytes;
+    while !body.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    Cow::Owned(format!("{}… ({} more bytes)", &body[..cut], body.len() - cut))
+}
+
+/// 解析上游成功响应体，解析失败时指出出错的字段
+///
+/// ## 功能说明
+/// 上游返回200但响应体结构不符时，错误消息包含出错字段的路径（如`choices[0].message`）
+/// 或缺失的字段名，而不是笼统的解析失败；原始响应体按`max_log_body_bytes`截断后记录到日志
+///
+/// ## 参数说明
+/// - `response`: 状态码为成功的上游响应
+/// - `description`: 错误消息中的响应描述，如"OpenAI response"
+/// - `config`: 提供商配置，用于日志截断长度
+///
+/// ## 执行例子
+/// ```rust
+/// let openai_res: OpenAIResponse = parse_json_body(response, "OpenAI response", &self.config).await?;
+/// ```
+///
+/// ## 返回值
+/// - `Ok(T)`: 解析后的响应
+/// - `Err(AppError::ProviderError)`: 读取失败或响应体不符合预期结构（502）
+pub(crate) async fn parse_json_body<T: serde::de::DeserializeOwned>(
+    response: reqwest::Response,
+    description: &str,
+    config: &ProviderDetail,
+) -> Result<T, AppError> {
+    let body = response.text().await.map_err(|e| AppError::ProviderError {
+        status: 502,
+        message: format!("Failed to read {}: {}", description, e),
+        error_type: None,
+        error_code: None,
+        retry_after: None,
+    })?;
+
+    let deserializer = &mut serde_json::Deserializer::from_str(&body);
+    serde_path_to_error::deserialize(deserializer).map_err(|e| {
+        let path = e.path().to_string();
+        let location = if path == "." { String::new() } else { format!(" at `{}`", path) };
+        tracing::warn!(
+            "Failed to parse {}{}: {} - body: {}",
+            description,
+            location,
+            e.inner(),
+            truncate_body(&body, config.log_body_limit())
+        );
+        AppError::ProviderError {
+            status: 502,
+            message: format!("Failed to parse {}{}: {}", description, location, e.inner()),
+            error_type: None,
+            error_code: None,
+            retry_after: None,
+        }
+    })
+}
 
 /// Streaming response type alias for provider implementations
 pub type StreamResponse = BoxStream<'static, Result<String, AppError>>;
 
+/// Stream wrapper that ties the upstream provider stream to the client
+///
+/// Dropping it drops the inner stream (and with it the reqwest connection),
+/// so a client disconnect stops pulling tokens from the provider. Early drops
+/// are logged so cancelled streams are visible.
+pub struct CancellableStream {
+    inner: StreamResponse,
+    provider: &'static str,
+    model: String,
+    chunks_forwarded: usize,
+    finished: bool,
+}
+
+impl CancellableStream {
+    /// Wrap a provider stream, returning it as a `StreamResponse`
+    pub fn wrap(inner: StreamResponse, provider: &'static str, model: String) -> StreamResponse {
+        Box::pin(Self {
+            inner,
+            provider,
+            model,
+            chunks_forwarded: 0,
+            finished: false,
+        })
+    }
+}
+
+impl Stream for CancellableStream {
+    type Item = Result<String, AppError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let poll = self.inner.poll_next_unpin(cx);
+        match &poll {
+            Poll::Ready(Some(_)) => self.chunks_forwarded += 1,
+            Poll::Ready(None) => self.finished = true,
+            Poll::Pending => {}
+        }
+        poll
+    }
+}
+
+impl Drop for CancellableStream {
+    fn drop(&mut self) {
+        if !self.finished {
+            tracing::info!(
+                provider = self.provider,
+                model = %self.model,
+                chunks_forwarded = self.chunks_forwarded,
+                "Stream cancelled before completion, dropping upstream connection"
+            );
+        }
+    }
+}
+
+/// SSE comment sent while waiting for upstream data; clients ignore comment lines
+pub const SSE_HEARTBEAT: &str = ": ping\n\n";
+
+/// 为流式响应添加SSE心跳
+///
+/// ## 功能说明
+/// 在等待上游数据期间，每隔`interval`发送一条SSE注释行（`: ping`），防止代理或负载均衡器
+/// 因连接空闲而断开。收到真实事件后心跳计时重新开始，因此不会插入到活跃的数据流中
+///
+/// ## 参数说明
+/// - `inner`: 原始流式响应
+/// - `interval`: 心跳间隔，为0时原样返回（禁用心跳）
+///
+/// ## 执行例子
+/// ```rust
+/// let stream = with_heartbeat(stream, Duration::from_secs(15));
+/// let body = Body::from_stream(stream);
+/// ```
+///
+/// ## 返回值
+/// - `StreamResponse`: 合并心跳后的流，原始流结束时一同结束
+pub fn with_heartbeat(inner: StreamResponse, interval: std::time::Duration) -> StreamResponse {
+    if interval.is_zero() {
+        return inner;
+    }
+
+    let ticker = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    Box::pin(futures::stream::unfold((inner, ticker), |(mut inner, mut ticker)| async move {
+        tokio::select! {
+            biased;
+            item = inner.next() => item.map(|item| {
+                // Real data resets the idle timer
+                ticker.reset();
+                (item, (inner, ticker))
+            }),
+            _ = ticker.tick() => Some((Ok(SSE_HEARTBEAT.to_string()), (inner, ticker))),
+        }
+    }))
+}
+
+/// 在上游流与客户端之间插入有界缓冲，实现流式背压
+///
+/// ## 功能说明
+/// 由后台任务读取上游流并写入容量为`capacity`的有界通道，客户端从通道读取。
+/// 客户端读取过慢导致通道写满时，后台任务阻塞而不再从上游拉取数据，
+/// 内存中最多缓冲`capacity`个数据块；客户端断开时后台任务立即结束并释放上游连接
+///
+/// ## 内部实现逻辑
+/// 1. 创建`tokio::sync::mpsc::channel(capacity)`（容量至少为1）
+/// 2. 后台任务循环等待上游的下一个数据块或通道关闭：收到数据块时写入通道（写满时等待），
+///    通道关闭（客户端流被丢弃）时退出并丢弃上游流
+/// 3. 上游流结束后发送端被丢弃，返回的流随之结束
+///
+/// ## 参数说明
+/// - `inner`: 提供商返回的流式响应
+/// - `capacity`: 通道容量，通常来自`performance.stream_buffer_size`
+///
+/// ## 执行例子
+/// ```rust
+/// let stream = provider.chat_stream(request).await?;
+/// let stream = with_backpressure(stream, state.config.performance.stream_buffer_size);
+/// ```
+///
+/// ## 返回值
+/// - `StreamResponse`: 从有界通道读取的流，数据块和错误按原顺序传递
+pub fn with_backpressure(inner: StreamResponse, capacity: usize) -> StreamResponse {
+    let (tx, mut rx) = tokio::sync::mpsc::channel(capacity.max(1));
+    tokio::spawn(async move {
+        let mut inner = inner;
+        loop {
+            let item = tokio::select! {
+                item = inner.next() => item,
+                // The client went away; stop reading from the upstream
+                _ = tx.closed() => break,
+            };
+            let Some(item) = item else {
+                break;
+            };
+            if tx.send(item).await.is_err() {
+                break;
+            }
+        }
+    });
+    Box::pin(futures::stream::poll_fn(move |cx| rx.poll_recv(cx)))
+}
+
+/// 按配置统一流式响应的SSE事件分帧
+///
+/// ## 功能说明
+/// 各提供商的流式事件来源不同（上游透传或本地转换），该包装器保证发送给客户端的每个事件
+/// 都遵循同一种分帧：命名模式下每个事件都带有Anthropic风格的`event:`行，
+/// 纯数据模式下只保留`data:`行，供不解析事件名的客户端使用
+///
+/// ## 内部实现逻辑
+/// 1. 缓冲上游数据块，按空行切分出完整事件（跨数据块的事件会被拼接）
+/// 2. 命名模式：保留已有的`event:`行，缺失时使用`data`中JSON的`type`字段补齐
+/// 3. 纯数据模式：移除`event:`行
+/// 4. 注释行（如心跳`: ping`）和无法识别的数据（如`[DONE]`）原样保留
+///
+/// ## 参数说明
+/// - `inner`: 原始流式响应
+/// - `named`: 是否为每个事件输出`event:`行，通常来自`server.sse_event_names`
+///
+/// ## 执行例子
+/// ```rust
+/// let stream = with_sse_event_names(stream, state.config.server.sse_event_names);
+/// ```
+///
+/// ## 返回值
+/// - `StreamResponse`: 分帧统一后的流，上游错误原样传递
+pub fn with_sse_event_names(inner: StreamResponse, named: bool) -> StreamResponse {
+    Box::pin(futures::stream::unfold(
+        (inner, String::new(), false),
+        move |(mut inner, mut buffer, mut finished)| async move {
+            loop {
+                if finished {
+                    if buffer.trim().is_empty() {
+                        return None;
+                    }
+                    let rest = std::mem::take(&mut buffer);
+                    return Some((Ok(frame_sse_event(&rest, named)), (inner, buffer, finished)));
+                }
+                match inner.next().await {
+                    Some(Ok(chunk)) => {
+                        buffer.push_str(&chunk);
+                        let mut framed = String::new();
+                        while let Some(pos) = buffer.find("\n\n") {
+                            let event: String = buffer.drain(..pos + 2).collect();
+                            framed.push_str(&frame_sse_event(&event, named));
+                        }
+                        if !framed.is_empty() {
+                            return Some((Ok(framed), (inner, buffer, finished)));
+                        }
+                    }
+                    Some(Err(e)) => return Some((Err(e), (inner, buffer, finished))),
+                    None => finished = true,
+                }
+            }
+        },
+    ))
+}
+
+/// Re-frame one SSE event with or without its `event:` line
+fn frame_sse_event(event: &str, named: bool) -> String {
+    let mut name = None;
+    let mut lines = Vec::new();
+    for line in event.lines().filter(|line| !line.is_empty()) {
+        match line.strip_prefix("event:") {
+            Some(event_name) => name = Some(event_name.trim().to_string()),
+            None => lines.push(line),
+        }
+    }
+    if lines.is_empty() || lines.iter().all(|line| line.starts_with(':')) {
+        return event.to_string();
+    }
+
+    if named && name.is_none() {
+        name = lines
+            .iter()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .find_map(|data| serde_json::from_str::<serde_json::Value>(data.trim()).ok())
+            .and_then(|value| value.get("type").and_then(|t| t.as_str()).map(str::to_string));
+    }
+
+    let mut framed = String::new();
+    if named && let Some(name) = name {
+        framed.push_str(&format!("event: {}\n", name));
+    }
+    for line in lines {
+        framed.push_str(line);
+        framed.push('\n');
+    }
+    framed.push('\n');
+    framed
+}
+
+/// 保证流式响应以完整的Anthropic事件序列结束
+///
+/// ## 功能说明
+/// 上游可能返回200但流体为空，或在发送`message_stop`之前就结束连接。
+/// 该包装器在上游流结束时补齐缺失的事件，使客户端总能收到`content_block_stop`和`message_stop`，
+/// 而不会一直等待。上游已发送`message_stop`、`error`事件或流以错误结束时不做任何补充
+///
+/// ## 内部实现逻辑
+/// 1. 透传上游数据块，同时按行解析`data:`中的JSON `type`，记录消息是否开始、未关闭的内容块以及是否已结束
+/// 2. 上游流结束后，按需依次补充`message_start`、`content_block_start`、
+///    所有未关闭块的`content_block_stop`、`message_delta`（`end_turn`）和`message_stop`
+///
+/// ## 参数说明
+/// - `inner`: 提供商转换后的流式响应
+/// - `model`: 补充`message_start`时使用的模型名称
+///
+/// ## 执行例子
+/// ```rust
+/// let stream = with_message_close(Box::pin(sse_stream), &request.model);
+/// ```
+///
+/// ## 返回值
+/// - `StreamResponse`: 保证以`message_stop`结束（除非上游报告了错误）的流
+pub fn with_message_close(inner: StreamResponse, model: &str) -> StreamResponse {
+    let model = model.to_string();
+    Box::pin(futures::stream::unfold(
+        (inner, StreamCloseTracker::default(), false),
+        move |(mut inner, mut tracker, done)| {
+            let model = model.clone();
+            async move {
+                if done {
+                    return None;
+                }
+                match inner.next().await {
+                    Some(Ok(chunk)) => {
+                        tracker.observe(&chunk);
+                        Some((Ok(chunk), (inner, tracker, false)))
+                    }
+                    Some(Err(e)) => {
+                        tracker.failed = true;
+                        Some((Err(e), (inner, tracker, false)))
+                    }
+                    None => {
+                        tracker.observe("\n");
+                        let closing = tracker.closing_events(&model)?;
+                        tracing::warn!(model = %model, "Upstream stream ended without message_stop; closing the message");
+                        Some((Ok(closing), (inner, tracker, true)))
+                    }
+                }
+            }
+        },
+    ))
+}
+
+/// Event bookkeeping for `with_message_close`
+#[derive(Default)]
+struct StreamCloseTracker {
+    partial_line: String,
+    message_started: bool,
+    any_block: bool,
+    open_blocks: std::collections::BTreeSet<u32>,
+    stopped: bool,
+    failed: bool,
+}
+
+impl StreamCloseTracker {
+    /// Scan complete `data:` lines of a forwarded chunk for event types
+    fn observe(&mut self, chunk: &str) {
+        self.partial_line.push_str(chunk);
+        while let Some(pos) = self.partial_line.find('\n') {
+            let line: String = self.partial_line.drain(..pos + 1).collect();
+            let Some(data) = line.trim().strip_prefix("data:") else {
+                continue;
+            };
+            let Ok(event) = serde_json::from_str::<serde_json::Value>(data.trim()) else {
+                continue;
+            };
+            let index = event.get("index").and_then(|i| i.as_u64()).map(|i| i as u32);
+            match event.get("type").and_then(|t| t.as_str()) {
+                Some("message_start") => self.message_started = true,
+                Some("content_block_start") => {
+                    self.any_block = true;
+                    self.open_blocks.extend(index);
+                }
+                Some("content_block_stop") => {
+                    if let Some(index) = index {
+                        self.open_blocks.remove(&index);
+                    }
+                }
+                Some("message_stop") => self.stopped = true,
+                Some("error") => self.failed = true,
+                _ => {}
+            }
+        }
+    }
+
+    /// Events still needed to close the message, or `None` if it already ended
+    fn closing_events(&self, model: &str) -> Option<String> {
+        use self::anthropic::{
+            AnthropicStreamEvent, ContentBlockStart, MessageDelta, StreamMessage, Usage,
+        };
+
+        if self.stopped || self.failed {
+            return None;
+        }
+
+        let mut events = Vec::new();
+        if !self.message_started {
+            events.push(AnthropicStreamEvent::MessageStart {
+                message: StreamMessage {
+                    id: format!("msg_{}", uuid::Uuid::new_v4().simple()),
+                    model: model.to_string(),
+                    role: "assistant".to_string(),
+                    content: vec![],
+                    usage: Usage {
+                        input_tokens: 0,
+                        output_tokens: 0,
+                        thinking_tokens: None,
+                    },
+                },
+            });
+        }
+        if !self.any_block {
+            events.push(AnthropicStreamEvent::ContentBlockStart {
+                index: 0,
+                content_block: ContentBlockStart {
+                    type_field: "text".to_string(),
+                    text: String::new(),
+                },
+            });
+            events.push(AnthropicStreamEvent::ContentBlockStop { index: 0 });
+        }
+        events.extend(
+            self.open_blocks
+                .iter()
+                .map(|&index| AnthropicStreamEvent::ContentBlockStop { index }),
+        );
+        events.push(AnthropicStreamEvent::MessageDelta {
+            delta: MessageDelta {
+                stop_reason: Some("end_turn".to_string()),
+                usage: None,
+            },
+        });
+        events.push(AnthropicStreamEvent::MessageStop);
+
+        Some(
+            events
+                .iter()
+                .filter_map(|event| {
+                    serde_json::to_string(event)
+                        .ok()
+                        .map(|json| format!("event: {}\ndata: {}\n\n", event.event_name(), json))
+                })
+                .collect(),
+        )
+    }
+}
+
+/// 为请求应用提供商的max_tokens默认值和输出上限
+///
+/// ## 功能说明
+/// 在发往上游之前处理`max_tokens`：超过提供商`max_output_tokens`的值按`over_limit_behavior`
+/// 截断（记录警告）或以400拒绝；客户端未设置时填充默认值（不超过上限）。
+/// 上限同时写入请求的验证上下文，`validate()`据此检查而不是使用固定的8192
+///
+/// ## 内部实现逻辑
+/// 1. 客户端的`max_tokens`超过上限时，`Reject`返回`ValidationError`，`Clamp`降为上限
+/// 2. 未设置时填充`default_max_tokens`（未配置时为`DEFAULT_MAX_TOKENS`），并限制在上限以内
+/// 3. 将上限写入`request.validation.max_output_tokens`
+///
+/// ## 参数说明
+/// - `request`: 待发送的请求
+/// - `config`: 提供商配置，包含`default_max_tokens`、`max_output_tokens`和`over_limit_behavior`
+///
+/// ## 执行例子
+/// ```rust
+/// apply_max_tokens_policy(&mut request, &self.config)?;
+/// request.validate().map_err(AppError::ValidationError)?;
+/// ```
+///
+/// ## 返回值
+/// - `Ok(())`: `max_tokens`已在上限以内
+/// - `Err(AppError::ValidationError)`: 超出上限且配置为拒绝
+pub fn apply_max_tokens_policy(request: &mut AnthropicRequest, config: &ProviderDetail) -> Result<(), AppError> {
+    if let Some(limit) = config.max_output_tokens
+        && let Some(requested) = request.max_tokens
+        && requested > limit
+    {
+        match config.over_limit_behavior {
+            OverLimitBehavior::Reject => {
+                return Err(AppError::ValidationError(format!(
+                    "max_tokens {} exceeds the provider's limit of {} output tokens for model {}",
+                    requested, limit, request.model
+                )));
+            }
+            OverLimitBehavior::Clamp => {
+                tracing::warn!(
+                    "Clamping max_tokens from {} to the provider's limit of {} for model {}",
+                    requested,
+                    limit,
+                    request.model
+                );
+                request.max_tokens = Some(limit);
+            }
+        }
+    }
+
+    let default = config.default_max_tokens.unwrap_or(anthropic::DEFAULT_MAX_TOKENS);
+    request.apply_default_max_tokens(config.max_output_tokens.map_or(default, |limit| default.min(limit)));
+    request.validation.max_output_tokens = config.max_output_tokens;
+    Ok(())
+}
+
 /// Model information structure
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct ModelInfo {
@@ -21,6 +611,12 @@ pub struct ModelInfo {
     pub object: String,
     pub created: u64,
     pub owned_by: String,
+    /// Context window in tokens, when the provider reports it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_length: Option<u64>,
+    /// ID of the configured provider serving this model; set by the registry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
 }
 
 /// Health status for provider monitoring
@@ -32,6 +628,200 @@ pub struct HealthStatus {
     pub error: Option<String>,
 }
 
+impl HealthStatus {
+    /// 创建探测被禁用时的健康状态
+    ///
+    /// ## 功能说明
+    /// `health_check = "disabled"`的提供商不发起任何网络请求，始终报告健康，且不带延迟
+    ///
+    /// ## 参数说明
+    /// - `provider`: 提供商名称
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let status = HealthStatus::disabled("openai");
+    /// assert_eq!(status.status, "healthy");
+    /// ```
+    pub fn disabled(provider: &str) -> Self {
+        Self {
+            status: "healthy".to_string(),
+            provider: provider.to_string(),
+            latency_ms: None,
+            error: None,
+        }
+    }
+}
+
+/// Probe a provider by opening a TCP connection to the host of its `api_base`
+pub(crate) async fn tcp_connect_probe(api_base: &str) -> Result<(), AppError> {
+    let url = reqwest::Url::parse(api_base)
+        .map_err(|e| AppError::ConfigError(format!("Invalid api_base '{}': {}", api_base, e)))?;
+    let host = url
+        .host_str()
+        .ok_or_else(|| AppError::ConfigError(format!("api_base '{}' has no host", api_base)))?;
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    match tokio::time::timeout(
+        std::time::Duration::from_secs(10),
+        tokio::net::TcpStream::connect((host, port)),
+    )
+    .await
+    {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(AppError::NetworkError(format!("TCP connect to {}:{} failed: {}", host, port, e))),
+        Err(_) => Err(AppError::NetworkError(format!("TCP connect to {}:{} timed out", host, port))),
+    }
+}
+
+/// Log that a request's `thinking_budget` is dropped by a provider without thinking support
+pub(crate) fn log_ignored_thinking_budget(request: &AnthropicRequest, provider: &str) {
+    if let Some(budget) = request.thinking_budget {
+        tracing::debug!(provider, model = %request.model, budget, "Ignoring thinking_budget; only Gemini supports it");
+    }
+}
+
+/// Log that a request's `seed` is dropped by a provider without seeded sampling
+pub(crate) fn log_ignored_seed(request: &AnthropicRequest, provider: &str) {
+    if let Some(seed) = request.seed {
+        tracing::debug!(provider, model = %request.model, seed, "Ignoring seed; only OpenAI-compatible and Gemini providers support it");
+    }
+}
+
+/// Minimal one-token chat request used by the `minimal_chat` health probe
+///
+/// Uses the first configured model, or `fallback_model` when none are configured.
+pub(crate) fn health_probe_request(config: &ProviderDetail, fallback_model: &str) -> AnthropicRequest {
+    let model = config
+        .models
+        .as_ref()
+        .and_then(|models| models.first())
+        .map_or(fallback_model, String::as_str);
+    AnthropicRequest {
+        model: model.to_string(),
+        messages: vec![self::anthropic::Message::user("test".to_string())],
+        max_tokens: Some(1),
+        stream: Some(false),
+        ..Default::default()
+    }
+}
+
+/// Inbound embeddings request (OpenAI `/v1/embeddings` shape)
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct EmbeddingRequest {
+    pub model: String,
+    pub input: EmbeddingInput,
+}
+
+/// Embeddings input: a single string or a batch of strings
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(untagged)]
+pub enum EmbeddingInput {
+    Single(String),
+    Batch(Vec<String>),
+}
+
+impl EmbeddingInput {
+    /// Flatten into the list of texts to embed
+    pub fn into_inputs(self) -> Vec<String> {
+        match self {
+            EmbeddingInput::Single(text) => vec![text],
+            EmbeddingInput::Batch(texts) => texts,
+        }
+    }
+}
+
+/// Unified embeddings response, in the OpenAI `list` shape most clients expect
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct EmbeddingResponse {
+    pub object: String,
+    pub model: String,
+    pub data: Vec<EmbeddingData>,
+    pub usage: EmbeddingUsage,
+}
+
+/// A single embedding vector, positioned by its input index
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct EmbeddingData {
+    pub object: String,
+    pub index: u32,
+    pub embedding: Vec<f32>,
+}
+
+/// Token usage for an embeddings call
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct EmbeddingUsage {
+    pub prompt_tokens: u32,
+    pub total_tokens: u32,
+}
+
+impl EmbeddingResponse {
+    /// Build a response from vectors ordered like the inputs
+    pub fn new(model: String, vectors: Vec<Vec<f32>>, prompt_tokens: u32) -> Self {
+        Self {
+            object: "list".to_string(),
+            model,
+            data: vectors
+                .into_iter()
+                .enumerate()
+                .map(|(index, embedding)| EmbeddingData {
+                    object: "embedding".to_string(),
+                    index: index as u32,
+                    embedding,
+                })
+                .collect(),
+            usage: EmbeddingUsage {
+                prompt_tokens,
+                total_tokens: prompt_tokens,
+            },
+        }
+    }
+}
+
+/// Features a provider supports, used to reject requests it cannot serve
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub struct ProviderCapabilities {
+    pub streaming: bool,
+    pub tools: bool,
+    pub vision: bool,
+    pub embeddings: bool,
+}
+
+impl ProviderCapabilities {
+    /// 检查请求是否只使用了提供商支持的功能
+    ///
+    /// ## 功能说明
+    /// 在请求发送到上游之前拒绝提供商不支持的功能（如对不支持工具的提供商使用`tools`），
+    /// 避免得到含义不明的上游错误
+    ///
+    /// ## 参数说明
+    /// - `provider_id`: 提供商ID，用于错误消息
+    /// - `request`: 待检查的请求
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// provider.capabilities().check_request("openai", &request)?;
+    /// ```
+    ///
+    /// ## 返回值
+    /// - `Ok(())`: 请求可以由该提供商处理
+    /// - `Err(AppError::BadRequest)`: 请求使用了不支持的功能
+    pub fn check_request(&self, provider_id: &str, request: &AnthropicRequest) -> Result<(), AppError> {
+        if request.tools.as_ref().is_some_and(|tools| !tools.is_empty()) && !self.tools {
+            return Err(AppError::BadRequest(format!(
+                "Provider '{}' does not support tools",
+                provider_id
+            )));
+        }
+        if request.stream.unwrap_or(false) && !self.streaming {
+            return Err(AppError::BadRequest(format!(
+                "Provider '{}' does not support streaming",
+                provider_id
+            )));
+        }
+        Ok(())
+    }
+}
+
 /// Core AI Provider trait that all providers must implement
 /// 
 /// This trait defines the standard interface for all AI providers,
@@ -59,4 +849,27 @@ pub trait AIProvider: Send + Sync {
     /// 
     /// Performs a lightweight check to verify the provider is accessible.
     async fn health_check(&self) -> Result<HealthStatus, AppError>;
+
+    /// Create embeddings for a batch of inputs
+    ///
+    /// Vectors are returned in input order. Providers without an embeddings API
+    /// keep the default, which reports the operation as unsupported.
+    async fn embeddings(&self, input: Vec<String>, model: &str) -> Result<EmbeddingResponse, AppError> {
+        let _ = input;
+        Err(AppError::NotSupported(format!(
+            "Embeddings are not supported for model '{}'",
+            model
+        )))
+    }
+
+    /// Features this provider supports
+    ///
+    /// The default describes a streaming chat provider without tools, vision
+    /// or embeddings; providers override it to advertise more.
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: true,
+            ..Default::default()
+        }
+    }
 }
\ No newline at end of file
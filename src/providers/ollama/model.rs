@@ -0,0 +1,65 @@
+use serde::Deserialize;
+
+use crate::errors::AppError;
+
+/// Default Ollama base URL for its OpenAI-compatible API
+pub const OLLAMA_API_BASE: &str = "http://localhost:11434/v1";
+
+/// Ollama `/api/tags` response listing locally pulled models
+#[derive(Deserialize, Debug)]
+pub struct OllamaTagsResponse {
+    #[serde(default)]
+    pub models: Vec<OllamaModel>,
+}
+
+/// Model entry in Ollama's `/api/tags` listing
+#[derive(Deserialize, Debug)]
+pub struct OllamaModel {
+    /// Model name including its tag, e.g. `llama3.2:latest`
+    pub name: String,
+    /// RFC 3339 time the model was last pulled or modified
+    #[serde(default)]
+    pub modified_at: Option<String>,
+    #[serde(default)]
+    pub size: Option<u64>,
+}
+
+impl OllamaModel {
+    /// `modified_at` as a Unix timestamp, if present and well-formed
+    pub fn created(&self) -> Option<u64> {
+        let modified_at = self.modified_at.as_deref()?;
+        let timestamp = chrono::DateTime::parse_from_rfc3339(modified_at).ok()?.timestamp();
+        u64::try_from(timestamp).ok()
+    }
+}
+
+/// Utility functions for Ollama models
+pub mod ollama_utils {
+    use super::*;
+
+    /// Validate model name format
+    ///
+    /// Local models can have any name (`llama3.2`, `qwen2.5:7b`, `my-finetune`),
+    /// so no prefix is required and only basic sanity checks apply.
+    pub fn validate_model_name(model: &str) -> Result<(), AppError> {
+        if model.is_empty() {
+            return Err(AppError::ValidationError("Model name cannot be empty".to_string()));
+        }
+
+        if model.len() > 100 {
+            return Err(AppError::ValidationError("Model name too long (max 100 characters)".to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// URL of Ollama's native `/api/tags` endpoint for an OpenAI-compatible base URL
+    ///
+    /// The OpenAI-compatible API lives under `/v1`, while the model listing
+    /// is served from the server root.
+    pub fn tags_url(api_base: &str) -> String {
+        let base = api_base.trim_end_matches('/');
+        let root = base.strip_suffix("/v1").unwrap_or(base);
+        format!("{}/api/tags", root)
+    }
+}
@@ -0,0 +1,182 @@
+use async_trait::async_trait;
+use reqwest::Client;
+
+use crate::{
+    config::ProviderDetail,
+    errors::AppError,
+    providers::{
+        AIProvider, HealthStatus, ModelInfo, ProviderCapabilities, StreamResponse, anthropic::*, extra_headers, ollama::*,
+        openai::OpenAIProvider, truncate_body,
+    },
+};
+
+/// Ollama provider implementation
+///
+/// Ollama serves an OpenAI-compatible API for local models, so chat and
+/// streaming are handled by an inner `OpenAIProvider`. Local servers usually
+/// run without authentication: a blank `api_key` sends no `Authorization`
+/// header, and model names are forwarded without prefix filtering.
+pub struct OllamaProvider {
+    inner: OpenAIProvider,
+    config: ProviderDetail,
+    client: Client,
+}
+
+impl OllamaProvider {
+    /// 创建新的Ollama提供商实例
+    ///
+    /// ## 功能说明
+    /// 使用给定的配置和HTTP客户端创建Ollama（或其他本地OpenAI兼容服务）提供商实例，
+    /// 复用OpenAI兼容的请求/响应转换
+    ///
+    /// ## 内部实现逻辑
+    /// 1. 未配置`api_base`时使用Ollama默认地址`http://localhost:11434/v1`
+    /// 2. 创建内部OpenAI兼容提供商，模型名原样透传，不做前缀过滤
+    /// 3. `api_key`为空时不发送`Authorization`请求头
+    ///
+    /// ## 参数说明
+    /// - `config`: Ollama提供商的详细配置，`api_key`可以为空
+    /// - `client`: 共享的HTTP客户端，用于发送API请求
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let config = ProviderDetail {
+    ///     api_base: "http://localhost:11434/v1/".to_string(),
+    ///     models: Some(vec!["llama3.2".to_string()]),
+    ///     ..Default::default()
+    /// };
+    /// let provider = OllamaProvider::new(config, Client::new());
+    /// ```
+    pub fn new(mut config: ProviderDetail, client: Client) -> Self {
+        if config.api_base.trim().is_empty() {
+            config.api_base = OLLAMA_API_BASE.to_string();
+        }
+        let inner = OpenAIProvider::compatible(config.clone(), client.clone(), "ollama", ollama_utils::validate_model_name);
+        Self { inner, config, client }
+    }
+
+    /// Fetch locally available models from Ollama's `/api/tags` endpoint
+    async fn fetch_models_from_api(&self) -> Result<Vec<ModelInfo>, AppError> {
+        let url = ollama_utils::tags_url(&self.config.api_base);
+
+        tracing::info!("Fetching models from URL: {}", url);
+
+        let mut builder = self.client.get(&url);
+        if !self.config.api_key.trim().is_empty() {
+            builder = builder.header("Authorization", format!("Bearer {}", self.config.api_key));
+        }
+        let response = builder
+            .headers(extra_headers(&self.config))
+            .send()
+            .await
+            .map_err(|e| AppError::ProviderError {
+                status: 500,
+                message: format!("Failed to fetch models from Ollama: {}", e),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
+            })?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let error_body = response.text().await.unwrap_or_default();
+            tracing::warn!("Ollama models API error: status={}, body={}", status, truncate_body(&error_body, self.config.log_body_limit()));
+            return Err(AppError::ProviderError {
+                status,
+                message: format!("Ollama models API error: {}", truncate_body(&error_body, self.config.error_body_limit())),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
+            });
+        }
+
+        let tags = response
+            .json::<OllamaTagsResponse>()
+            .await
+            .map_err(|e| AppError::ProviderError {
+                status: 500,
+                message: format!("Failed to parse Ollama models response: {}", e),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
+            })?;
+
+        Ok(tags
+            .models
+            .into_iter()
+            .map(|model| ModelInfo {
+                created: model.created().unwrap_or(1714560000),
+                object: "model".to_string(),
+                owned_by: "ollama".to_string(),
+                context_length: None,
+                id: model.name,
+                provider: None,
+            })
+            .collect())
+    }
+
+    /// Get fallback models when the Ollama server is unavailable
+    fn get_fallback_models(&self) -> Vec<ModelInfo> {
+        let models = self.config.models.clone().unwrap_or_else(|| {
+            vec![
+                "llama3.2".to_string(),
+                "mistral".to_string(),
+                "qwen2.5".to_string(),
+            ]
+        });
+
+        models
+            .into_iter()
+            .map(|model| ModelInfo {
+                id: model,
+                object: "model".to_string(),
+                created: 1714560000, // Static timestamp for fallback
+                owned_by: "ollama".to_string(),
+                context_length: None,
+                provider: None,
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl AIProvider for OllamaProvider {
+    async fn chat(&self, request: AnthropicRequest) -> Result<AnthropicResponse, AppError> {
+        self.inner.chat(request).await
+    }
+
+    async fn chat_stream(&self, request: AnthropicRequest) -> Result<StreamResponse, AppError> {
+        self.inner.chat_stream(request).await
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: true,
+            tools: true,
+            vision: false,
+            embeddings: false,
+        }
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, AppError> {
+        match self.fetch_models_from_api().await {
+            Ok(mut models) if !models.is_empty() => {
+                tracing::info!("Successfully fetched {} models from Ollama", models.len());
+                models.sort_by(|a, b| a.id.cmp(&b.id));
+                Ok(models)
+            }
+            Ok(_) => {
+                tracing::warn!("Ollama has no local models, falling back to configured models");
+                Ok(self.get_fallback_models())
+            }
+            Err(e) => {
+                tracing::warn!("Failed to fetch models from Ollama: {}, falling back to configured models", e);
+                Ok(self.get_fallback_models())
+            }
+        }
+    }
+
+    async fn health_check(&self) -> Result<HealthStatus, AppError> {
+        self.inner.health_check().await
+    }
+}
@@ -1,6 +1,6 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use crate::errors::AppError;
-use crate::providers::anthropic::{AnthropicRequest, AnthropicResponse, AnthropicStreamEvent, StreamMessage, ContentBlockStart, TextDelta, MessageDelta, Usage};
+use crate::providers::anthropic::{AnthropicRequest, AnthropicResponse, AnthropicStreamEvent, StreamMessage, ContentBlock, ContentBlockStart, InputJsonDelta, TextDelta, ToolUseStart, Message, MessageDelta, ToolChoice, ToolDefinition, Usage};
 
 // OpenAI-specific data structures for API communication
 
@@ -9,7 +9,8 @@ use crate::providers::anthropic::{AnthropicRequest, AnthropicResponse, Anthropic
 pub struct OpenAIRequest {
     pub model: String,
     pub messages: Vec<OpenAIMessage>,
-    pub max_tokens: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -24,19 +25,87 @@ pub struct OpenAIRequest {
     pub stop: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_logprobs: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<OpenAITool>>,
+    /// `"none"`, `"auto"`, `"required"` or `{"type":"function","function":{"name":...}}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<serde_json::Value>,
+}
+
+/// Tool definition in OpenAI's function-calling format
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OpenAITool {
+    #[serde(rename = "type")]
+    pub type_field: String, // "function"
+    pub function: OpenAIFunction,
+}
+
+/// Function exposed to the model as a tool
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OpenAIFunction {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub parameters: serde_json::Value,
+}
+
+/// Tool call returned by the model
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OpenAIToolCall {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub type_field: String, // "function"
+    pub function: OpenAIFunctionCall,
+}
+
+/// Function name and JSON-encoded arguments of a tool call
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OpenAIFunctionCall {
+    pub name: String,
+    pub arguments: String,
+}
+
+/// OpenAI embeddings request structure
+#[derive(Serialize, Debug, Clone)]
+pub struct OpenAIEmbeddingRequest {
+    pub model: String,
+    pub input: Vec<String>,
 }
 
 /// Message structure for OpenAI conversations
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct OpenAIMessage {
     pub role: String, // "system", "user", "assistant"
+    /// `null` upstream when the assistant only returns tool calls
+    #[serde(default, deserialize_with = "null_as_empty")]
     pub content: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<OpenAIToolCall>>,
+    /// Refusal message returned instead of content when the model declines to answer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refusal: Option<String>,
+}
+
+/// Deserialize a nullable string, treating `null` as empty
+fn null_as_empty<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(Option::<String>::deserialize(deserializer)?.unwrap_or_default())
 }
 
 /// OpenAI API response structure
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct OpenAIResponse {
     pub id: String,
     pub object: String,
@@ -49,7 +118,7 @@ pub struct OpenAIResponse {
 }
 
 /// Individual choice in OpenAI response
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct OpenAIChoice {
     pub index: u32,
     pub message: OpenAIMessage,
@@ -59,7 +128,7 @@ pub struct OpenAIChoice {
 }
 
 /// Token usage information from OpenAI
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct OpenAIUsage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
@@ -85,7 +154,7 @@ pub struct OpenAIErrorDetail {
 // Streaming-specific structures for OpenAI
 
 /// OpenAI streaming response structure
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct OpenAIStreamResponse {
     pub id: String,
     pub object: String,
@@ -97,7 +166,7 @@ pub struct OpenAIStreamResponse {
 }
 
 /// Streaming choice structure
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct OpenAIStreamChoice {
     pub index: u32,
     pub delta: OpenAIStreamDelta,
@@ -107,26 +176,80 @@ pub struct OpenAIStreamChoice {
 }
 
 /// Delta structure for streaming updates
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct OpenAIStreamDelta {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub role: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub content: Option<String>,
+    /// Tool call fragments; arguments arrive as partial JSON spread over chunks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<OpenAIToolCallDelta>>,
+}
+
+/// Fragment of a streamed tool call, keyed by `index` across chunks
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OpenAIToolCallDelta {
+    pub index: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub type_field: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function: Option<OpenAIFunctionCallDelta>,
+}
+
+/// Function name (first fragment only) and an arguments fragment
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OpenAIFunctionCallDelta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<String>,
 }
 
 /// Conversion functions for OpenAI format
 impl OpenAIRequest {
     /// Convert Anthropic request format to OpenAI format
     pub fn from_anthropic(request: &AnthropicRequest) -> Result<Self, AppError> {
-        let messages = request
-            .messages
-            .iter()
-            .map(|msg| OpenAIMessage {
+        // OpenAI carries the system prompt as a leading "system" message
+        let system_message = request.system.as_ref().map(|system| OpenAIMessage {
+            role: "system".to_string(),
+            content: system.clone(),
+            name: None,
+            tool_calls: None,
+            refusal: None,
+        });
+
+        // OpenAI cannot continue a trailing assistant message, so the prefill is
+        // turned into a continuation instruction and the provider prepends it to the output
+        let prefill = request.assistant_prefill();
+        let conversation = match prefill {
+            Some(_) => &request.messages[..request.messages.len() - 1],
+            None => &request.messages[..],
+        };
+        let prefill_instruction = prefill.map(|prefix| OpenAIMessage {
+            role: "system".to_string(),
+            content: format!(
+                "Continue the assistant reply that begins with the text below. \
+                 Output only the continuation, without repeating the given text.\n\n{}",
+                prefix
+            ),
+            name: None,
+            tool_calls: None,
+            refusal: None,
+        });
+
+        let messages = system_message
+            .into_iter()
+            .chain(conversation.iter().map(|msg| OpenAIMessage {
                 role: msg.role.clone(),
                 content: msg.content.clone(),
                 name: None,
-            })
+                tool_calls: None,
+                refusal: None,
+            }))
+            .chain(prefill_instruction)
             .collect();
 
         Ok(OpenAIRequest {
@@ -139,7 +262,74 @@ impl OpenAIRequest {
             frequency_penalty: None,
             presence_penalty: None,
             stop: None,
-            user: None,
+            user: request.user.clone(),
+            n: request.n,
+            // Already validated on the Anthropic side, OpenAI accepts it verbatim
+            response_format: request.response_format.clone(),
+            logprobs: request.logprobs,
+            top_logprobs: request.top_logprobs,
+            seed: request.seed,
+            tools: request.tools.as_ref().map(|tools| tools.iter().map(OpenAITool::from_anthropic).collect()),
+            tool_choice: request.tool_choice.as_ref().map(openai_utils::tool_choice),
+        })
+    }
+
+    /// Convert an inbound OpenAI request into the unified Anthropic format
+    ///
+    /// System (and "developer") messages are merged into the `system` field.
+    /// Parameters without an Anthropic equivalent are dropped.
+    pub fn to_anthropic(&self) -> Result<AnthropicRequest, AppError> {
+        let mut system_parts = Vec::new();
+        let mut messages = Vec::new();
+
+        for msg in &self.messages {
+            match msg.role.as_str() {
+                "system" | "developer" => system_parts.push(msg.content.clone()),
+                "user" | "assistant" => messages.push(Message {
+                    role: msg.role.clone(),
+                    content: msg.content.clone(),
+                }),
+                other => {
+                    return Err(AppError::ValidationError(format!(
+                        "Unsupported message role: {}",
+                        other
+                    )));
+                }
+            }
+        }
+
+        if self.frequency_penalty.is_some() || self.presence_penalty.is_some() || self.stop.is_some() {
+            tracing::debug!(
+                "Dropping OpenAI-only parameters (frequency_penalty, presence_penalty, stop) for model {}",
+                self.model
+            );
+        }
+
+        Ok(AnthropicRequest {
+            model: self.model.clone(),
+            messages,
+            max_tokens: self.max_tokens,
+            system: if system_parts.is_empty() {
+                None
+            } else {
+                Some(system_parts.join("\n\n"))
+            },
+            stream: self.stream,
+            temperature: self.temperature,
+            top_p: self.top_p,
+            n: self.n,
+            response_format: self.response_format.clone(),
+            logprobs: self.logprobs,
+            top_logprobs: self.top_logprobs,
+            seed: self.seed,
+            user: self.user.clone(),
+            tools: self.tools.as_ref().map(|tools| tools.iter().map(OpenAITool::to_anthropic).collect()),
+            tool_choice: self
+                .tool_choice
+                .as_ref()
+                .map(openai_utils::parse_tool_choice)
+                .transpose()?,
+            ..Default::default()
         })
     }
 
@@ -148,7 +338,7 @@ impl OpenAIRequest {
         Self {
             model,
             messages,
-            max_tokens,
+            max_tokens: Some(max_tokens),
             stream: None,
             temperature: None,
             top_p: None,
@@ -156,6 +346,13 @@ impl OpenAIRequest {
             presence_penalty: None,
             stop: None,
             user: None,
+            n: None,
+            response_format: None,
+            logprobs: None,
+            top_logprobs: None,
+            seed: None,
+            tools: None,
+            tool_choice: None,
         }
     }
 
@@ -213,16 +410,17 @@ impl OpenAIRequest {
             return Err(AppError::ValidationError("Messages cannot be empty".to_string()));
         }
 
-        if self.messages.len() > 100 {
-            return Err(AppError::ValidationError("Too many messages (max 100)".to_string()));
-        }
+        // The message count is checked against the provider's `max_messages`
+        // on the Anthropic-format request before translation
 
         // Validate max_tokens
-        if self.max_tokens == 0 {
+        if self.max_tokens == Some(0) {
             return Err(AppError::ValidationError("max_tokens must be greater than 0".to_string()));
         }
 
-        if self.max_tokens > 4096 {
+        if let Some(max_tokens) = self.max_tokens
+            && max_tokens > 4096
+        {
             return Err(AppError::ValidationError("max_tokens cannot exceed 4096".to_string()));
         }
 
@@ -266,6 +464,13 @@ impl OpenAIRequest {
             }
         }
 
+        // Validate n
+        if let Some(n) = self.n
+            && !(1..=8).contains(&n)
+        {
+            return Err(AppError::ValidationError("n must be between 1 and 8".to_string()));
+        }
+
         // Validate stop sequences
         if let Some(stop) = &self.stop {
             if stop.len() > 4 {
@@ -285,31 +490,66 @@ impl OpenAIRequest {
 
 impl OpenAIResponse {
     /// Convert OpenAI response format to Anthropic format
+    ///
+    /// With `n > 1` every choice becomes its own content block, ordered by choice index;
+    /// `logprobs` is taken from the first choice.
     pub fn to_anthropic(&self) -> Result<AnthropicResponse, AppError> {
-        let choice = self
-            .choices
-            .first()
-            .ok_or_else(|| AppError::ProviderError {
+        if self.choices.is_empty() {
+            return Err(AppError::ProviderError {
                 status: 500,
                 message: "No choices in OpenAI response".to_string(),
-            })?;
+                error_type: None,
+                error_code: None,
+                retry_after: None,
+            });
+        }
+
+        let mut choices: Vec<&OpenAIChoice> = self.choices.iter().collect();
+        choices.sort_by_key(|choice| choice.index);
+        let stop_reason = choices[0].finish_reason.as_deref().map(openai_utils::stop_reason);
+        let logprobs = choices[0].logprobs.clone();
+        let refusal = choices[0].message.refusal.clone();
+        let tool_uses = choices[0]
+            .message
+            .tool_calls
+            .iter()
+            .flatten()
+            .map(OpenAIToolCall::to_anthropic)
+            .collect::<Result<Vec<_>, AppError>>()?;
 
-        let text = choice.message.content.clone();
+        let texts: Vec<String> = choices
+            .into_iter()
+            .map(|choice| choice.message.content.clone())
+            .filter(|text| !text.is_empty())
+            .collect();
 
-        if text.is_empty() {
+        if texts.is_empty() && tool_uses.is_empty() && refusal.is_none() {
             return Err(AppError::ProviderError {
                 status: 500,
                 message: "Empty response content from OpenAI".to_string(),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
             });
         }
 
-        Ok(AnthropicResponse::new(
+        let response = AnthropicResponse::from_candidates(
             self.id.clone(),
             self.model.clone(),
-            text,
+            texts,
             self.usage.prompt_tokens,
             self.usage.completion_tokens,
-        ))
+        )
+        .with_stop_reason(stop_reason)
+        .with_logprobs(logprobs)
+        .with_tool_uses(tool_uses)
+        .with_system_fingerprint(self.system_fingerprint.clone());
+
+        // An explicit refusal message wins over the generic content_filter reason
+        Ok(match refusal {
+            Some(refusal) => response.with_refusal(refusal),
+            None => response.flag_refusal(),
+        })
     }
 
     /// Get finish reason as human-readable string
@@ -340,13 +580,430 @@ impl OpenAIResponse {
     /// Check if response has any issues
     pub fn has_issues(&self) -> bool {
         self.choices.is_empty() || 
-        self.choices.iter().any(|c| c.message.content.is_empty())
+        self.choices.iter().any(|c| {
+            c.message.content.is_empty() && c.message.tool_calls.is_none() && c.message.refusal.is_none()
+        })
+    }
+}
+
+impl AnthropicResponse {
+    /// Convert the unified Anthropic response into an OpenAI `chat.completion`
+    ///
+    /// All text blocks are concatenated into a single choice.
+    pub fn to_openai(&self) -> OpenAIResponse {
+        let text = self
+            .content
+            .iter()
+            .filter(|block| block.type_field == "text")
+            .map(|block| block.text.as_str())
+            .collect::<String>();
+
+        self.openai_response(vec![text])
+    }
+
+    /// Convert a multi-candidate (`n > 1`) response into OpenAI's shape
+    ///
+    /// Each text block becomes its own choice, mirroring `OpenAIResponse::to_anthropic`.
+    pub fn to_openai_choices(&self) -> OpenAIResponse {
+        let texts = self
+            .content
+            .iter()
+            .filter(|block| block.type_field == "text")
+            .map(|block| block.text.clone())
+            .collect();
+
+        self.openai_response(texts)
+    }
+
+    fn openai_response(&self, mut texts: Vec<String>) -> OpenAIResponse {
+        let tool_calls: Vec<OpenAIToolCall> = self.tool_uses().map(OpenAIToolCall::from_anthropic).collect();
+        let tool_calls = (!tool_calls.is_empty()).then_some(tool_calls);
+        let refusal = self.refusal_reason.clone().filter(|_| self.refused);
+        if texts.is_empty() && (tool_calls.is_some() || refusal.is_some()) {
+            texts.push(String::new());
+        }
+
+        OpenAIResponse {
+            id: self.id.clone(),
+            object: "chat.completion".to_string(),
+            created: chrono::Utc::now().timestamp() as u64,
+            model: self.model.clone(),
+            choices: texts
+                .into_iter()
+                .enumerate()
+                .map(|(index, text)| OpenAIChoice {
+                    index: index as u32,
+                    message: OpenAIMessage {
+                        role: "assistant".to_string(),
+                        content: text,
+                        name: None,
+                        // Tool calls belong to the first (and for tool use, only) choice
+                        tool_calls: if index == 0 { tool_calls.clone() } else { None },
+                        refusal: if index == 0 { refusal.clone() } else { None },
+                    },
+                    finish_reason: Some(openai_utils::finish_reason(self.stop_reason.as_deref()).to_string()),
+                    logprobs: if index == 0 { self.logprobs.clone() } else { None },
+                })
+                .collect(),
+            usage: OpenAIUsage {
+                prompt_tokens: self.usage.input_tokens,
+                completion_tokens: self.usage.output_tokens,
+                total_tokens: self.usage.input_tokens + self.usage.output_tokens,
+            },
+            system_fingerprint: self.system_fingerprint.clone(),
+        }
+    }
+}
+
+impl OpenAITool {
+    /// Convert an Anthropic tool definition into OpenAI's function tool
+    pub fn from_anthropic(tool: &ToolDefinition) -> Self {
+        Self {
+            type_field: "function".to_string(),
+            function: OpenAIFunction {
+                name: tool.name.clone(),
+                description: tool.description.clone(),
+                parameters: tool.input_schema.clone(),
+            },
+        }
+    }
+
+    /// Convert an OpenAI function tool into an Anthropic tool definition
+    pub fn to_anthropic(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.function.name.clone(),
+            description: self.function.description.clone(),
+            input_schema: match &self.function.parameters {
+                serde_json::Value::Null => serde_json::json!({"type": "object", "properties": {}}),
+                parameters => parameters.clone(),
+            },
+        }
+    }
+}
+
+impl OpenAIToolCall {
+    /// Convert a returned tool call into an Anthropic `tool_use` block
+    ///
+    /// OpenAI encodes the arguments as a JSON string; they must decode to an object.
+    pub fn to_anthropic(&self) -> Result<ContentBlock, AppError> {
+        let input: serde_json::Value = match self.function.arguments.trim() {
+            "" => serde_json::json!({}),
+            arguments => serde_json::from_str(arguments).map_err(|e| AppError::ProviderError {
+                status: 502,
+                message: format!(
+                    "Invalid arguments for tool call '{}' from OpenAI: {}",
+                    self.function.name, e
+                ),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
+            })?,
+        };
+        Ok(ContentBlock::tool_use(self.id.clone(), self.function.name.clone(), input))
+    }
+
+    /// Convert an Anthropic `tool_use` block into an OpenAI tool call
+    pub fn from_anthropic(block: &ContentBlock) -> Self {
+        Self {
+            id: block.id.clone().unwrap_or_default(),
+            type_field: "function".to_string(),
+            function: OpenAIFunctionCall {
+                name: block.name.clone().unwrap_or_default(),
+                arguments: block
+                    .input
+                    .as_ref()
+                    .map(|input| input.to_string())
+                    .unwrap_or_else(|| "{}".to_string()),
+            },
+        }
+    }
+}
+
+/// Re-frames an Anthropic SSE stream as OpenAI `chat.completion.chunk` events
+///
+/// Upstream chunks may split events arbitrarily, so input is buffered until
+/// a complete `\n\n`-terminated event is available.
+pub struct OpenAIStreamConverter {
+    id: String,
+    model: String,
+    created: u64,
+    buffer: String,
+}
+
+impl OpenAIStreamConverter {
+    /// Create a converter for a single streamed completion
+    pub fn new(model: String) -> Self {
+        Self {
+            id: format!("chatcmpl-{}", uuid::Uuid::new_v4()),
+            model,
+            created: chrono::Utc::now().timestamp() as u64,
+            buffer: String::new(),
+        }
+    }
+
+    /// Feed raw Anthropic SSE text, returning any complete OpenAI SSE output
+    pub fn push(&mut self, chunk: &str) -> String {
+        self.buffer.push_str(&chunk.replace("\r\n", "\n"));
+
+        let mut output = String::new();
+        while let Some(pos) = self.buffer.find("\n\n") {
+            let event: String = self.buffer.drain(..pos + 2).collect();
+            if let Some(converted) = self.convert_event(&event) {
+                output.push_str(&converted);
+            }
+        }
+        output
+    }
+
+    /// Convert a single Anthropic SSE event into OpenAI SSE output
+    fn convert_event(&self, event: &str) -> Option<String> {
+        let data = event
+            .lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .map(str::trim_start)
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let value: serde_json::Value = serde_json::from_str(&data).ok()?;
+
+        match value.get("type")?.as_str()? {
+            "message_start" => Some(self.chunk(
+                OpenAIStreamDelta {
+                    role: Some("assistant".to_string()),
+                    content: None,
+                    tool_calls: None,
+                },
+                None,
+            )),
+            "content_block_delta" => {
+                let text = value.get("delta")?.get("text")?.as_str()?;
+                Some(self.chunk(
+                    OpenAIStreamDelta {
+                        role: None,
+                        content: Some(text.to_string()),
+                        tool_calls: None,
+                    },
+                    None,
+                ))
+            }
+            "message_delta" => {
+                let stop_reason = value.get("delta")?.get("stop_reason")?.as_str()?;
+                let finish_reason = openai_utils::finish_reason(Some(stop_reason));
+                Some(self.chunk(
+                    OpenAIStreamDelta {
+                        role: None,
+                        content: None,
+                        tool_calls: None,
+                    },
+                    Some(finish_reason.to_string()),
+                ))
+            }
+            "message_stop" => Some("data: [DONE]\n\n".to_string()),
+            "error" => {
+                let error = value.get("error")?;
+                Some(format!("data: {}\n\n", serde_json::json!({ "error": error })))
+            }
+            _ => None,
+        }
+    }
+
+    /// Format a single `chat.completion.chunk` SSE event
+    fn chunk(&self, delta: OpenAIStreamDelta, finish_reason: Option<String>) -> String {
+        let chunk = OpenAIStreamResponse {
+            id: self.id.clone(),
+            object: "chat.completion.chunk".to_string(),
+            created: self.created,
+            model: self.model.clone(),
+            choices: vec![OpenAIStreamChoice {
+                index: 0,
+                delta,
+                finish_reason,
+                logprobs: None,
+            }],
+            system_fingerprint: None,
+        };
+
+        format!(
+            "data: {}\n\n",
+            serde_json::to_string(&chunk).unwrap_or_default()
+        )
+    }
+}
+
+/// A tool call being assembled from streamed fragments
+#[derive(Debug, Clone)]
+struct PendingToolCall {
+    /// OpenAI `tool_calls[].index` the fragments are keyed by
+    openai_index: u32,
+    /// Anthropic content block index this call is streamed as
+    block_index: u32,
+    id: String,
+    name: String,
+    arguments: String,
+    closed: bool,
+}
+
+/// Stateful assembler that turns streamed OpenAI tool call fragments into
+/// Anthropic `tool_use` blocks
+///
+/// ## 功能说明
+/// OpenAI 流式响应中的工具调用参数被拆分为多个 JSON 片段，按 `index` 分布在多个 chunk 中。
+/// 该结构按 index 累积片段，为每个工具调用发出 `content_block_start`（`tool_use`）与
+/// `input_json_delta` 事件，并在结束时关闭所有工具块。
+///
+/// ## 内部实现逻辑
+/// 1. 首次见到某个 index 时分配 Anthropic 块索引（从 1 开始，0 为文本块），发出 `tool_use` 开始事件；
+///    第一个工具块开始前先关闭文本块，保证同一时间只有一个打开的块
+/// 2. 每个非空参数片段原样作为 `partial_json` 转发，并追加到累积字符串
+/// 3. `finish` 关闭仍打开的文本块和工具块，并校验累积参数是否为合法 JSON
+///
+/// ## 执行例子
+/// ```rust
+/// use ai_proxy::providers::openai::ToolCallAssembler;
+///
+/// let mut assembler = ToolCallAssembler::default();
+/// assert!(assembler.finish().is_empty());
+/// assert!(assembler.tool_calls().is_empty());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ToolCallAssembler {
+    calls: Vec<PendingToolCall>,
+    text_closed: bool,
+}
+
+impl ToolCallAssembler {
+    /// 处理一个工具调用片段并返回需要发出的 Anthropic 事件
+    ///
+    /// ## 功能说明
+    /// 新 index 的首个片段产生 `tool_use` 块开始事件（第一个工具块之前先关闭文本块）；
+    /// 参数片段产生 `input_json_delta` 事件。
+    ///
+    /// ## 参数说明
+    /// - `delta`: OpenAI 流式 `tool_calls` 中的单个片段
+    ///
+    /// ## 返回值
+    /// 按顺序需要发出的事件，可能为空
+    pub fn push(&mut self, delta: &OpenAIToolCallDelta) -> Vec<AnthropicStreamEvent> {
+        let mut events = Vec::new();
+        let function = delta.function.as_ref();
+
+        let position = match self.calls.iter().position(|call| call.openai_index == delta.index) {
+            Some(position) => position,
+            None => {
+                let call = PendingToolCall {
+                    openai_index: delta.index,
+                    block_index: self.calls.len() as u32 + 1,
+                    id: delta
+                        .id
+                        .clone()
+                        .unwrap_or_else(|| format!("toolu_{}", uuid::Uuid::new_v4().simple())),
+                    name: function.and_then(|f| f.name.clone()).unwrap_or_default(),
+                    arguments: String::new(),
+                    closed: false,
+                };
+                events.extend(self.close_text_block());
+                events.push(AnthropicStreamEvent::ToolUseBlockStart {
+                    index: call.block_index,
+                    content_block: ToolUseStart {
+                        type_field: "tool_use".to_string(),
+                        id: call.id.clone(),
+                        name: call.name.clone(),
+                        input: serde_json::json!({}),
+                    },
+                });
+                self.calls.push(call);
+                self.calls.len() - 1
+            }
+        };
+
+        let call = &mut self.calls[position];
+        if let Some(fragment) = function.and_then(|f| f.arguments.as_deref())
+            && !fragment.is_empty()
+            && !call.closed
+        {
+            call.arguments.push_str(fragment);
+            events.push(AnthropicStreamEvent::ToolInputDelta {
+                index: call.block_index,
+                delta: InputJsonDelta {
+                    type_field: "input_json_delta".to_string(),
+                    partial_json: fragment.to_string(),
+                },
+            });
+        }
+
+        events
+    }
+
+    /// 关闭所有未关闭的内容块
+    ///
+    /// ## 功能说明
+    /// 文本块仍打开时先关闭它，再为每个仍打开的工具块发出 `content_block_stop`；
+    /// 累积参数不是合法 JSON 时记录警告。重复调用不会再次发出事件。
+    ///
+    /// ## 返回值
+    /// 需要发出的 `content_block_stop` 事件
+    pub fn finish(&mut self) -> Vec<AnthropicStreamEvent> {
+        let mut events: Vec<AnthropicStreamEvent> = self.close_text_block().into_iter().collect();
+        for call in self.calls.iter_mut().filter(|call| !call.closed) {
+            call.closed = true;
+            let arguments = call.arguments.trim();
+            if !arguments.is_empty() && serde_json::from_str::<serde_json::Value>(arguments).is_err() {
+                tracing::warn!(
+                    "Streamed arguments for tool call '{}' are not valid JSON: {}",
+                    call.name, call.arguments
+                );
+            }
+            events.push(AnthropicStreamEvent::ContentBlockStop { index: call.block_index });
+        }
+        events
+    }
+
+    /// `content_block_stop` for text block 0 unless it was already closed
+    fn close_text_block(&mut self) -> Option<AnthropicStreamEvent> {
+        if std::mem::replace(&mut self.text_closed, true) {
+            return None;
+        }
+        Some(AnthropicStreamEvent::ContentBlockStop { index: 0 })
+    }
+
+    /// 返回目前已组装的完整工具调用
+    ///
+    /// ## 返回值
+    /// 按首次出现顺序排列的工具调用，参数为累积后的 JSON 字符串
+    pub fn tool_calls(&self) -> Vec<OpenAIToolCall> {
+        self.calls
+            .iter()
+            .map(|call| OpenAIToolCall {
+                id: call.id.clone(),
+                type_field: "function".to_string(),
+                function: OpenAIFunctionCall {
+                    name: call.name.clone(),
+                    arguments: call.arguments.clone(),
+                },
+            })
+            .collect()
     }
 }
 
 impl OpenAIStreamResponse {
     /// Convert OpenAI streaming response to Anthropic streaming events
-    pub fn to_anthropic_events(&self, _message_id: &str) -> Result<Vec<AnthropicStreamEvent>, AppError> {
+    ///
+    /// Tool call fragments are only assembled within this chunk; streams spanning
+    /// several chunks should use [`Self::to_anthropic_events_with_tools`].
+    pub fn to_anthropic_events(&self, message_id: &str) -> Result<Vec<AnthropicStreamEvent>, AppError> {
+        self.to_anthropic_events_with_tools(message_id, &mut ToolCallAssembler::default())
+    }
+
+    /// Convert OpenAI streaming response to Anthropic streaming events, assembling
+    /// tool call fragments across chunks with `tool_calls`
+    ///
+    /// The text block is closed before the first tool block starts, and any open
+    /// blocks are closed before the `message_delta` of a finish_reason.
+    pub fn to_anthropic_events_with_tools(
+        &self,
+        _message_id: &str,
+        tool_calls: &mut ToolCallAssembler,
+    ) -> Result<Vec<AnthropicStreamEvent>, AppError> {
         let mut events = Vec::new();
 
         for choice in &self.choices {
@@ -363,16 +1020,15 @@ impl OpenAIStreamResponse {
                 }
             }
 
+            // Handle tool call fragments
+            for delta in choice.delta.tool_calls.iter().flatten() {
+                events.extend(tool_calls.push(delta));
+            }
+
             // Handle finish reason
             if let Some(finish_reason) = &choice.finish_reason {
-                let stop_reason = match finish_reason.as_str() {
-                    "stop" => Some("end_turn".to_string()),
-                    "length" => Some("max_tokens".to_string()),
-                    "content_filter" => Some("stop_sequence".to_string()),
-                    "function_call" => Some("tool_use".to_string()),
-                    "tool_calls" => Some("tool_use".to_string()),
-                    _ => Some("stop_sequence".to_string()),
-                };
+                let stop_reason = Some(openai_utils::stop_reason(finish_reason));
+                events.extend(tool_calls.finish());
 
                 events.push(AnthropicStreamEvent::MessageDelta {
                     delta: MessageDelta {
@@ -389,7 +1045,10 @@ impl OpenAIStreamResponse {
     }
 
     /// Create initial streaming events for message start
-    pub fn create_message_start_event(model: &str, message_id: &str) -> AnthropicStreamEvent {
+    ///
+    /// `input_tokens` is reported up front so clients can see the prompt cost
+    /// before any output arrives.
+    pub fn create_message_start_event(model: &str, message_id: &str, input_tokens: u32) -> AnthropicStreamEvent {
         AnthropicStreamEvent::MessageStart {
             message: StreamMessage {
                 id: message_id.to_string(),
@@ -397,8 +1056,9 @@ impl OpenAIStreamResponse {
                 role: "assistant".to_string(),
                 content: vec![],
                 usage: Usage {
-                    input_tokens: 0,
+                    input_tokens,
                     output_tokens: 0,
+                    thinking_tokens: None,
                 },
             },
         }
@@ -422,6 +1082,7 @@ impl OpenAIStreamResponse {
             error: StreamError {
                 error_type: "provider_error".to_string(),
                 message: error.to_string(),
+                output_tokens: None,
             },
         }
     }
@@ -436,12 +1097,72 @@ impl OpenAIStreamResponse {
 pub mod openai_utils {
     use super::*;
 
+    /// Map an OpenAI `finish_reason` onto the Anthropic `stop_reason` vocabulary
+    pub fn stop_reason(finish_reason: &str) -> String {
+        match finish_reason {
+            "stop" => "end_turn",
+            "length" => "max_tokens",
+            "content_filter" => "content_filter",
+            "function_call" | "tool_calls" => "tool_use",
+            _ => "stop_sequence",
+        }
+        .to_string()
+    }
+
+    /// Map an Anthropic `stop_reason` back onto OpenAI's `finish_reason`
+    pub fn finish_reason(stop_reason: Option<&str>) -> &'static str {
+        match stop_reason {
+            Some("max_tokens") => "length",
+            Some("tool_use") => "tool_calls",
+            Some("content_filter") => "content_filter",
+            _ => "stop",
+        }
+    }
+
+    /// Map an Anthropic `tool_choice` onto OpenAI's `tool_choice`
+    pub fn tool_choice(choice: &ToolChoice) -> serde_json::Value {
+        match choice {
+            ToolChoice::Auto => serde_json::json!("auto"),
+            ToolChoice::Any => serde_json::json!("required"),
+            ToolChoice::None => serde_json::json!("none"),
+            ToolChoice::Tool { name } => serde_json::json!({"type": "function", "function": {"name": name}}),
+        }
+    }
+
+    /// Parse an inbound OpenAI `tool_choice` into the Anthropic form
+    pub fn parse_tool_choice(value: &serde_json::Value) -> Result<ToolChoice, AppError> {
+        match value {
+            serde_json::Value::String(mode) => match mode.as_str() {
+                "auto" => Ok(ToolChoice::Auto),
+                "required" => Ok(ToolChoice::Any),
+                "none" => Ok(ToolChoice::None),
+                other => Err(AppError::ValidationError(format!(
+                    "Unsupported tool_choice '{}': must be one of none, auto, required",
+                    other
+                ))),
+            },
+            _ => value
+                .get("function")
+                .and_then(|function| function.get("name"))
+                .and_then(|name| name.as_str())
+                .map(|name| ToolChoice::Tool { name: name.to_string() })
+                .ok_or_else(|| {
+                    AppError::ValidationError(
+                        "tool_choice object must name a function: {\"type\":\"function\",\"function\":{\"name\":...}}"
+                            .to_string(),
+                    )
+                }),
+        }
+    }
+
     /// Create a simple OpenAI request from text content
     pub fn create_simple_request(content: String, model: String, max_tokens: u32) -> OpenAIRequest {
         let message = OpenAIMessage {
             role: "user".to_string(),
             content,
             name: None,
+            tool_calls: None,
+            refusal: None,
         };
         
         OpenAIRequest::new(model, vec![message], max_tokens)
@@ -468,6 +1189,8 @@ pub mod openai_utils {
                     role,
                     content,
                     name: None,
+                    tool_calls: None,
+                    refusal: None,
                 })
             })
             .collect::<Result<Vec<_>, AppError>>()?;
@@ -481,6 +1204,8 @@ pub mod openai_utils {
             role: "system".to_string(),
             content,
             name: None,
+            tool_calls: None,
+            refusal: None,
         }
     }
 
@@ -490,6 +1215,8 @@ pub mod openai_utils {
             role: "user".to_string(),
             content,
             name: None,
+            tool_calls: None,
+            refusal: None,
         }
     }
 
@@ -499,15 +1226,40 @@ pub mod openai_utils {
             role: "assistant".to_string(),
             content,
             name: None,
+            tool_calls: None,
+            refusal: None,
         }
     }
 
     /// Parse OpenAI error response
     pub fn parse_error_response(error_body: &str) -> String {
-        match serde_json::from_str::<OpenAIError>(error_body) {
-            Ok(error) => error.error.message,
-            Err(_) => error_body.to_string(),
-        }
+        parse_error_details(error_body).0
+    }
+
+    /// Parse an OpenAI error body into `(message, type, code)`
+    ///
+    /// `code` may be a string or a number upstream; both are returned as strings.
+    /// Unparseable bodies yield the raw body as the message.
+    pub fn parse_error_details(error_body: &str) -> (String, Option<String>, Option<String>) {
+        let Some(error) = serde_json::from_str::<serde_json::Value>(error_body)
+            .ok()
+            .and_then(|value| value.get("error").cloned())
+        else {
+            return (error_body.to_string(), None, None);
+        };
+
+        let message = error
+            .get("message")
+            .and_then(|m| m.as_str())
+            .unwrap_or(error_body)
+            .to_string();
+        let error_type = error.get("type").and_then(|t| t.as_str()).map(str::to_string);
+        let error_code = match error.get("code") {
+            Some(serde_json::Value::String(code)) => Some(code.clone()),
+            Some(serde_json::Value::Number(code)) => Some(code.to_string()),
+            _ => None,
+        };
+        (message, error_type, error_code)
     }
 
     /// Check if model supports streaming
@@ -1,16 +1,23 @@
 use async_trait::async_trait;
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder};
 
 use crate::{
-    config::ProviderDetail,
+    config::{HealthProbe, ProviderDetail},
     errors::AppError,
-    providers::{AIProvider, HealthStatus, ModelInfo, StreamResponse, anthropic::*, openai::*},
+    providers::{AIProvider, CancellableStream, EmbeddingResponse, HealthStatus, ModelInfo, ProviderCapabilities, StreamResponse, anthropic::*, apply_max_tokens_policy, extra_headers, forwarded_response_headers, health_probe_request, log_ignored_thinking_budget, openai::*, parse_json_body, retry_after_from_headers, tcp_connect_probe, truncate_body},
 };
 
+/// Default `api-version` used for Azure OpenAI when none is configured
+const DEFAULT_AZURE_API_VERSION: &str = "2024-02-01";
+
 /// OpenAI provider implementation
 pub struct OpenAIProvider {
     config: ProviderDetail,
     client: Client,
+    /// Provider name reported in health checks and stream logs
+    name: &'static str,
+    /// Model name validation applied before each chat request
+    validate_model: fn(&str) -> Result<(), AppError>,
 }
 
 impl OpenAIProvider {
@@ -34,34 +41,124 @@ impl OpenAIProvider {
     /// let provider = OpenAIProvider::new(config, client);
     /// ```
     pub fn new(config: ProviderDetail, client: Client) -> Self {
-        Self { config, client }
+        Self::compatible(config, client, "openai", openai_utils::validate_model_name)
+    }
+
+    /// Create a provider for an OpenAI-compatible API (e.g. xAI, Ollama)
+    ///
+    /// Shares the request/response conversion and SSE re-framing with OpenAI,
+    /// only the reported provider name and model name validation differ.
+    pub(crate) fn compatible(
+        config: ProviderDetail,
+        client: Client,
+        name: &'static str,
+        validate_model: fn(&str) -> Result<(), AppError>,
+    ) -> Self {
+        Self { config, client, name, validate_model }
+    }
+
+    /// Whether this provider talks to Azure OpenAI (`api_style = "azure"`)
+    fn is_azure(&self) -> bool {
+        self.config.api_style.as_deref() == Some("azure")
+    }
+
+    /// Azure `api-version` query parameter
+    fn azure_api_version(&self) -> &str {
+        self.config
+            .api_version
+            .as_deref()
+            .unwrap_or(DEFAULT_AZURE_API_VERSION)
+    }
+
+    /// Build the chat completions URL for the given model
+    fn chat_completions_url(&self, model: &str) -> String {
+        let base = self.config.api_base.trim_end_matches('/');
+        if self.is_azure() {
+            let deployment = self.config.deployment.as_deref().unwrap_or(model);
+            format!(
+                "{}/openai/deployments/{}/chat/completions?api-version={}",
+                base,
+                deployment,
+                self.azure_api_version()
+            )
+        } else {
+            format!("{}/chat/completions", base)
+        }
+    }
+
+    /// Build the embeddings URL for the given model
+    fn embeddings_url(&self, model: &str) -> String {
+        let base = self.config.api_base.trim_end_matches('/');
+        if self.is_azure() {
+            let deployment = self.config.deployment.as_deref().unwrap_or(model);
+            format!(
+                "{}/openai/deployments/{}/embeddings?api-version={}",
+                base,
+                deployment,
+                self.azure_api_version()
+            )
+        } else {
+            format!("{}/embeddings", base)
+        }
+    }
+
+    /// Build the models listing URL
+    fn models_url(&self) -> String {
+        let base = self.config.api_base.trim_end_matches('/');
+        if self.is_azure() {
+            format!("{}/openai/models?api-version={}", base, self.azure_api_version())
+        } else {
+            format!("{}/models", base)
+        }
+    }
+
+    /// Attach authentication headers (`api-key` for Azure, `Bearer` otherwise)
+    /// followed by the configured `extra_headers`
+    ///
+    /// A blank API key (keyless local servers such as Ollama) sends no
+    /// authentication header at all.
+    fn authorize(&self, builder: RequestBuilder) -> RequestBuilder {
+        let builder = if self.config.api_key.trim().is_empty() {
+            builder
+        } else if self.is_azure() {
+            builder.header("api-key", &self.config.api_key)
+        } else {
+            builder.header("Authorization", format!("Bearer {}", self.config.api_key))
+        };
+        builder.headers(extra_headers(&self.config))
     }
 
     /// Fetch models from OpenAI API
-    async fn fetch_models_from_api(&self) -> Result<Vec<ModelInfo>, AppError> {
-        let url = format!("{}/models", self.config.api_base.trim_end_matches('/'));
+    pub(crate) async fn fetch_models_from_api(&self) -> Result<Vec<ModelInfo>, AppError> {
+        let url = self.models_url();
 
         tracing::info!("Fetching models from URL: {}", url);
 
         let response = self
-            .client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", self.config.api_key))
-            .header("User-Agent", "ai-proxy/0.1.0")
+            .authorize(self.client.get(&url))
             .send()
             .await
             .map_err(|e| AppError::ProviderError {
                 status: 500,
                 message: format!("Failed to fetch models from OpenAI: {}", e),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
             })?;
 
         if !response.status().is_success() {
             let status = response.status().as_u16();
             let error_body = response.text().await.unwrap_or_default();
-            tracing::warn!("OpenAI models API error: status={}, body={}", status, error_body);
+            tracing::warn!("OpenAI models API error: status={}, body={}", status, truncate_body(&error_body, self.config.log_body_limit()));
             return Err(AppError::ProviderError {
                 status,
-                message: format!("OpenAI models API error: {}", openai_utils::parse_error_response(&error_body)),
+                message: format!(
+                    "OpenAI models API error: {}",
+                    truncate_body(&openai_utils::parse_error_response(&error_body), self.config.error_body_limit())
+                ),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
             });
         }
 
@@ -71,6 +168,9 @@ impl OpenAIProvider {
             .map_err(|e| AppError::ProviderError {
                 status: 500,
                 message: format!("Failed to parse OpenAI models response: {}", e),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
             })?;
 
         // Parse the models from OpenAI's response format
@@ -80,6 +180,9 @@ impl OpenAIProvider {
             .ok_or_else(|| AppError::ProviderError {
                 status: 500,
                 message: "Invalid models response format from OpenAI".to_string(),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
             })?
             .iter()
             .filter_map(|model| {
@@ -98,6 +201,8 @@ impl OpenAIProvider {
                     object,
                     created,
                     owned_by,
+                    context_length: None,
+                    provider: None,
                 })
             })
             .collect();
@@ -119,46 +224,33 @@ impl OpenAIProvider {
 
     /// Handle OpenAI API errors with proper error parsing
     fn handle_api_error(&self, status: u16, error_body: &str) -> AppError {
-        let parsed_message = openai_utils::parse_error_response(error_body);
-        
-        match status {
-            400 => AppError::BadRequest(format!("OpenAI API: {}", parsed_message)),
-            401 => AppError::ProviderError {
-                status,
-                message: "OpenAI API: Invalid API key or authentication failed".to_string(),
-            },
-            403 => AppError::ProviderError {
-                status,
-                message: "OpenAI API: Access forbidden - check your API key permissions".to_string(),
-            },
-            404 => AppError::ProviderError {
-                status,
-                message: "OpenAI API: Model not found or endpoint not available".to_string(),
-            },
-            429 => AppError::ProviderError {
-                status,
-                message: format!("OpenAI API: Rate limit exceeded - {}", parsed_message),
-            },
-            500..=599 => AppError::ProviderError {
-                status,
-                message: format!("OpenAI API: Server error - {}", parsed_message),
-            },
-            _ => AppError::ProviderError {
-                status,
-                message: format!("OpenAI API: Unexpected error - {}", parsed_message),
-            },
-        }
+        let (parsed_message, error_type, error_code) = openai_utils::parse_error_details(error_body);
+        let parsed_message = truncate_body(&parsed_message, self.config.error_body_limit());
+
+        let message = match status {
+            400 => format!("OpenAI API: {}", parsed_message),
+            401 => "OpenAI API: Invalid API key or authentication failed".to_string(),
+            403 => "OpenAI API: Access forbidden - check your API key permissions".to_string(),
+            404 => "OpenAI API: Model not found or endpoint not available".to_string(),
+            429 => format!("OpenAI API: Rate limit exceeded - {}", parsed_message),
+            500..=599 => format!("OpenAI API: Server error - {}", parsed_message),
+            _ => format!("OpenAI API: Unexpected error - {}", parsed_message),
+        };
+
+        AppError::provider_error_with_details(status, message, error_type, error_code)
     }
 }
 
 #[async_trait]
 impl AIProvider for OpenAIProvider {
-    async fn chat(&self, request: AnthropicRequest) -> Result<AnthropicResponse, AppError> {
-        // Validate request
+    async fn chat(&self, mut request: AnthropicRequest) -> Result<AnthropicResponse, AppError> {
+        // Apply the configured max_tokens default and output cap, then validate
+        apply_max_tokens_policy(&mut request, &self.config)?;
         request.validate().map_err(AppError::ValidationError)?;
+        log_ignored_thinking_budget(&request, self.name);
 
-        // Validate model name for OpenAI
-        openai_utils::validate_model_name(&request.model)?;
+        // Validate model name for this provider
+        (self.validate_model)(&request.model)?;
 
         // Convert to OpenAI format
         let mut openai_req = self.convert_request(&request)?;
@@ -170,64 +262,85 @@ impl AIProvider for OpenAIProvider {
         openai_req.validate()?;
 
         // Build URL
-        let url = format!("{}/chat/completions", self.config.api_base.trim_end_matches('/'));
+        let url = self.chat_completions_url(&request.model);
 
         tracing::info!("Sending OpenAI chat request to: {} with model: {}", url, request.model);
 
         // Send request with proper headers
         let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.config.api_key))
+            .authorize(self.client.post(&url))
             .header("Content-Type", "application/json")
-            .header("User-Agent", "ai-proxy/0.1.0")
             .json(&openai_req)
+            .timeout(request.upstream_timeout(self.config.read_timeout_secs()))
             .send()
             .await
-            .map_err(|e| AppError::ProviderError {
-                status: 500,
-                message: format!("Failed to send request to OpenAI: {}", e),
+            .map_err(|e| {
+                if e.is_connect() && e.is_timeout() {
+                    AppError::NetworkError(format!("OpenAI connect timed out: {}", e))
+                } else if e.is_timeout() {
+                    AppError::GatewayTimeout(format!("OpenAI request timed out: {}", e))
+                } else {
+                    AppError::ProviderError {
+                        status: 500,
+                        message: format!("Failed to send request to OpenAI: {}", e),
+                        error_type: None,
+                        error_code: None,
+                        retry_after: None,
+                    }
+                }
             })?;
 
         // Handle HTTP errors with proper error parsing
         if !response.status().is_success() {
             let status = response.status().as_u16();
+            let retry_after = retry_after_from_headers(response.headers());
             let error_body = response.text().await.unwrap_or_default();
-            tracing::warn!("OpenAI API error: status={}, body={}", status, error_body);
-            return Err(self.handle_api_error(status, &error_body));
+            tracing::warn!("OpenAI API error: status={}, body={}", status, truncate_body(&error_body, self.config.log_body_limit()));
+            return Err(self.handle_api_error(status, &error_body).with_retry_after(retry_after));
         }
 
+        let upstream_headers = forwarded_response_headers(&self.config, response.headers());
+
         // Parse response
-        let openai_res = response
-            .json::<OpenAIResponse>()
-            .await
-            .map_err(|e| AppError::ProviderError {
-                status: 500,
-                message: format!("Failed to parse OpenAI response: {}", e),
-            })?;
+        let openai_res: OpenAIResponse = parse_json_body(response, "OpenAI response", &self.config).await?;
 
         // Check for response issues
         if openai_res.has_issues() {
             return Err(AppError::ProviderError {
                 status: 500,
                 message: "OpenAI returned empty or invalid response".to_string(),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
             });
         }
 
         tracing::info!("OpenAI chat completed successfully: {}", openai_res.get_usage_info());
 
         // Convert to standard format
-        self.convert_response(openai_res)
+        let mut response = self.convert_response(openai_res)?;
+
+        // The prefill was sent as an instruction, so restore it in front of the output
+        if let Some(prefix) = request.assistant_prefill()
+            && let Some(block) = response.content.first_mut()
+        {
+            block.text.insert_str(0, prefix);
+        }
+
+        response.upstream_headers = upstream_headers;
+        Ok(response)
     }
 
-    async fn chat_stream(&self, request: AnthropicRequest) -> Result<StreamResponse, AppError> {
+    async fn chat_stream(&self, mut request: AnthropicRequest) -> Result<StreamResponse, AppError> {
         use futures::StreamExt;
         
-        // Validate request
+        // Apply the configured max_tokens default and output cap, then validate
+        apply_max_tokens_policy(&mut request, &self.config)?;
         request.validate().map_err(AppError::ValidationError)?;
+        log_ignored_thinking_budget(&request, self.name);
 
-        // Validate model name for OpenAI
-        openai_utils::validate_model_name(&request.model)?;
+        // Validate model name for this provider
+        (self.validate_model)(&request.model)?;
 
         // Check if model supports streaming
         if !openai_utils::supports_streaming(&request.model) {
@@ -247,32 +360,42 @@ impl AIProvider for OpenAIProvider {
         openai_req.validate()?;
 
         // Build streaming URL
-        let url = format!("{}/chat/completions", self.config.api_base.trim_end_matches('/'));
+        let url = self.chat_completions_url(&request.model);
 
         tracing::info!("Starting OpenAI streaming request to: {} with model: {}", url, request.model);
 
         // Send streaming request
         let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.config.api_key))
+            .authorize(self.client.post(&url))
             .header("Content-Type", "application/json")
-            .header("User-Agent", "ai-proxy/0.1.0")
             .header("Accept", "text/event-stream")
             .json(&openai_req)
+            .timeout(request.upstream_timeout(self.config.read_timeout_secs()))
             .send()
             .await
-            .map_err(|e| AppError::ProviderError {
-                status: 500,
-                message: format!("Failed to send streaming request to OpenAI: {}", e),
+            .map_err(|e| {
+                if e.is_connect() && e.is_timeout() {
+                    AppError::NetworkError(format!("OpenAI streaming connect timed out: {}", e))
+                } else if e.is_timeout() {
+                    AppError::GatewayTimeout(format!("OpenAI streaming request timed out: {}", e))
+                } else {
+                    AppError::ProviderError {
+                        status: 500,
+                        message: format!("Failed to send streaming request to OpenAI: {}", e),
+                        error_type: None,
+                        error_code: None,
+                        retry_after: None,
+                    }
+                }
             })?;
 
         // Check for HTTP errors
         if !response.status().is_success() {
             let status = response.status().as_u16();
+            let retry_after = retry_after_from_headers(response.headers());
             let error_body = response.text().await.unwrap_or_default();
-            tracing::warn!("OpenAI streaming API error: status={}, body={}", status, error_body);
-            return Err(self.handle_api_error(status, &error_body));
+            tracing::warn!("OpenAI streaming API error: status={}, body={}", status, truncate_body(&error_body, self.config.log_body_limit()));
+            return Err(self.handle_api_error(status, &error_body).with_retry_after(retry_after));
         }
 
         // Get the response body as a stream
@@ -281,26 +404,18 @@ impl AIProvider for OpenAIProvider {
         // Generate unique message ID for this streaming session
         let message_id = format!("msg_{}", uuid::Uuid::new_v4().simple());
         let model_name = request.model.clone();
+
+        // OpenAI only reports usage at the end of a stream, so estimate the input up front
+        let input_tokens = request.estimate_input_tokens();
         
         // Create initial streaming events
         let initial_events = {
-            use crate::providers::anthropic::{AnthropicStreamEvent, StreamMessage, ContentBlockStart, Usage};
+            use crate::providers::anthropic::{AnthropicStreamEvent, ContentBlockStart, TextDelta};
 
             let mut events = Vec::new();
 
             // Message start event
-            let message_start = AnthropicStreamEvent::MessageStart {
-                message: StreamMessage {
-                    id: message_id.clone(),
-                    model: model_name.clone(),
-                    role: "assistant".to_string(),
-                    content: vec![],
-                    usage: Usage {
-                        input_tokens: 0,
-                        output_tokens: 0,
-                    },
-                },
-            };
+            let message_start = OpenAIStreamResponse::create_message_start_event(&model_name, &message_id, input_tokens);
             if let Ok(json) = serde_json::to_string(&message_start) {
                 events.push(format!("event: message_start\ndata: {}\n\n", json));
             }
@@ -317,11 +432,35 @@ impl AIProvider for OpenAIProvider {
                 events.push(format!("event: content_block_start\ndata: {}\n\n", json));
             }
 
+            // Replay the assistant prefill as the first delta, mirroring the non-streaming output
+            if let Some(prefix) = request.assistant_prefill() {
+                let prefill_delta = AnthropicStreamEvent::ContentBlockDelta {
+                    index: 0,
+                    delta: TextDelta {
+                        type_field: "text_delta".to_string(),
+                        text: prefix.to_string(),
+                    },
+                };
+                if let Ok(json) = serde_json::to_string(&prefill_delta) {
+                    events.push(format!("event: content_block_delta\ndata: {}\n\n", json));
+                }
+            }
+
             events.join("")
         };
 
         // Create a flag to track if initial events have been sent
         let initial_events_sent = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        // Track whether message_stop went out, so it is sent exactly once
+        let message_stopped = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let finalizer_initial_events = initial_events.clone();
+        let finalizer_initial_sent = initial_events_sent.clone();
+        let finalizer_stopped = message_stopped.clone();
+        // Characters of generated text so far, reported if the upstream fails mid-stream
+        let streamed_chars = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        // Tool call arguments arrive as JSON fragments keyed by index across chunks
+        let tool_calls = std::sync::Arc::new(std::sync::Mutex::new(ToolCallAssembler::default()));
+        let finalizer_tool_calls = tool_calls.clone();
 
         // Process streaming bytes and convert to SSE events
         let sse_stream = body
@@ -329,6 +468,9 @@ impl AIProvider for OpenAIProvider {
                 let message_id = message_id.clone();
                 let _model_name = model_name.clone();
                 let initial_events_sent = initial_events_sent.clone();
+                let message_stopped = message_stopped.clone();
+                let streamed_chars = streamed_chars.clone();
+                let tool_calls = tool_calls.clone();
 
                 match chunk_result {
                     Ok(bytes) => {
@@ -359,15 +501,11 @@ impl AIProvider for OpenAIProvider {
                             if let Some(data) = line.strip_prefix("data: ") {
                                 // Check for end of stream
                                 if data.trim() == "[DONE]" {
-                                    // Add content block stop and message stop events
-                                    let content_stop = AnthropicStreamEvent::ContentBlockStop { index: 0 };
-                                    if let Ok(json) = serde_json::to_string(&content_stop) {
-                                        sse_events.push(format!("event: content_block_stop\ndata: {}\n\n", json));
-                                    }
-                                    
-                                    let message_stop = AnthropicStreamEvent::MessageStop;
-                                    if let Ok(json) = serde_json::to_string(&message_stop) {
-                                        sse_events.push(format!("event: message_stop\ndata: {}\n\n", json));
+                                    // Close the message unless a finish_reason already did; the
+                                    // upstream finished normally, so the turn ended
+                                    if !message_stopped.swap(true, std::sync::atomic::Ordering::Relaxed) {
+                                        sse_events.push(tool_stop_events(&tool_calls));
+                                        sse_events.push(closing_events(Some("end_turn")));
                                     }
                                     continue;
                                 }
@@ -377,36 +515,41 @@ impl AIProvider for OpenAIProvider {
                                     Ok(openai_stream) => {
                                         
                                         // Convert to Anthropic streaming events
-                                        match openai_stream.to_anthropic_events(&message_id) {
+                                        let converted = match tool_calls.lock() {
+                                            Ok(mut assembler) => openai_stream.to_anthropic_events_with_tools(&message_id, &mut assembler),
+                                            Err(_) => openai_stream.to_anthropic_events(&message_id),
+                                        };
+                                        match converted {
                                             Ok(events) => {
+                                                // The finish_reason's stop_reason is sent with the closing events
+                                                let mut stop_reason = None;
                                                 // Convert each event to SSE format
                                                 for event in events {
                                                     match event {
-                                                        AnthropicStreamEvent::ContentBlockDelta { .. } => {
+                                                        AnthropicStreamEvent::ContentBlockDelta { ref delta, .. } => {
+                                                            streamed_chars.fetch_add(delta.text.len(), std::sync::atomic::Ordering::Relaxed);
                                                             if let Ok(json) = serde_json::to_string(&event) {
                                                                 sse_events.push(format!("event: content_block_delta\ndata: {}\n\n", json));
                                                             }
                                                         }
-                                                        AnthropicStreamEvent::MessageDelta { .. } => {
+                                                        AnthropicStreamEvent::ToolInputDelta { ref delta, .. } => {
+                                                            streamed_chars.fetch_add(delta.partial_json.len(), std::sync::atomic::Ordering::Relaxed);
                                                             if let Ok(json) = serde_json::to_string(&event) {
-                                                                sse_events.push(format!("event: message_delta\ndata: {}\n\n", json));
+                                                                sse_events.push(format!("event: content_block_delta\ndata: {}\n\n", json));
                                                             }
                                                         }
+                                                        AnthropicStreamEvent::MessageDelta { delta, .. } => {
+                                                            stop_reason = delta.stop_reason;
+                                                        }
                                                         AnthropicStreamEvent::MessageStop => {
-                                                            // Add content block stop first
-                                                            let content_stop = AnthropicStreamEvent::ContentBlockStop { index: 0 };
-                                                            if let Ok(json) = serde_json::to_string(&content_stop) {
-                                                                sse_events.push(format!("event: content_block_stop\ndata: {}\n\n", json));
-                                                            }
-                                                            
-                                                            // Then add message stop
-                                                            if let Ok(json) = serde_json::to_string(&event) {
-                                                                sse_events.push(format!("event: message_stop\ndata: {}\n\n", json));
+                                                            // Blocks were closed by the assembler; send message delta and stop
+                                                            if !message_stopped.swap(true, std::sync::atomic::Ordering::Relaxed) {
+                                                                sse_events.push(closing_events(stop_reason.as_deref()));
                                                             }
                                                         }
                                                         _ => {
                                                             if let Ok(json) = serde_json::to_string(&event) {
-                                                                sse_events.push(format!("data: {}\n\n", json));
+                                                                sse_events.push(format!("event: {}\ndata: {}\n\n", event.event_name(), json));
                                                             }
                                                         }
                                                     }
@@ -438,18 +581,55 @@ impl AIProvider for OpenAIProvider {
                     }
                     Err(e) => {
                         tracing::error!("Error reading streaming response chunk: {}", e);
-                        let app_error = AppError::ProviderError {
-                            status: 500,
-                            message: format!("Streaming read error: {}", e),
-                        };
-                        Some(Err(app_error))
+                        // A failure after the message closed loses nothing
+                        if message_stopped.swap(true, std::sync::atomic::Ordering::Relaxed) {
+                            return None;
+                        }
+
+                        // Close the message with the partial usage so clients can keep the text
+                        let output_tokens = (streamed_chars.load(std::sync::atomic::Ordering::Relaxed) / 4) as u32;
+                        let mut events = String::new();
+                        if !initial_events_sent.swap(true, std::sync::atomic::Ordering::Relaxed) {
+                            events.push_str(&initial_events);
+                        }
+                        events.push_str(&partial_error_events(
+                            &tool_calls,
+                            &format!("Streaming read error: {}", e),
+                            input_tokens,
+                            output_tokens,
+                        ));
+                        Some(Ok(events))
                     }
                 }
             })
+            // Some proxies end the stream without [DONE] or a finish_reason; close the
+            // Anthropic stream at EOF so clients are not left waiting for message_stop
+            .chain(futures::stream::once(async move {
+                if finalizer_stopped.load(std::sync::atomic::Ordering::Relaxed) {
+                    return None;
+                }
+                tracing::warn!("OpenAI stream ended without [DONE]; closing the message");
+                let mut events = String::new();
+                if !finalizer_initial_sent.load(std::sync::atomic::Ordering::Relaxed) {
+                    events.push_str(&finalizer_initial_events);
+                }
+                events.push_str(&tool_stop_events(&finalizer_tool_calls));
+                events.push_str(&closing_events(None));
+                Some(Ok(events))
+            }))
             .filter_map(|result| async move { result });
 
         tracing::info!("OpenAI streaming response initialized successfully");
-        Ok(Box::pin(sse_stream))
+        Ok(CancellableStream::wrap(Box::pin(sse_stream), self.name, request.model.clone()))
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: true,
+            tools: true,
+            vision: true,
+            embeddings: true,
+        }
     }
 
     async fn list_models(&self) -> Result<Vec<ModelInfo>, AppError> {
@@ -472,6 +652,11 @@ impl AIProvider for OpenAIProvider {
                         !id.contains("tts") && 
                         !id.contains("dall-e")
                     })
+                    .filter(|model| {
+                        // Only advertise the curated subset when configured to
+                        !self.config.advertise_configured_only
+                            || self.config.models.as_ref().is_some_and(|configured| configured.contains(&model.id))
+                    })
                     .collect();
                 
                 if chat_models.is_empty() {
@@ -491,20 +676,28 @@ impl AIProvider for OpenAIProvider {
     async fn health_check(&self) -> Result<HealthStatus, AppError> {
         let start = std::time::Instant::now();
 
-        // Comprehensive health check with multiple endpoints
-        let health_result = self.perform_comprehensive_health_check().await;
+        // The models list is the default probe
+        let health_result = match self.config.health_check {
+            Some(HealthProbe::Disabled) => return Ok(HealthStatus::disabled(self.name)),
+            Some(HealthProbe::TcpConnect) => tcp_connect_probe(&self.config.api_base).await,
+            Some(HealthProbe::MinimalChat) => self
+                .chat(health_probe_request(&self.config, "gpt-3.5-turbo"))
+                .await
+                .map(|_| ()),
+            Some(HealthProbe::ModelsList) | None => self.perform_comprehensive_health_check().await,
+        };
         let latency = start.elapsed().as_millis() as u64;
 
         match health_result {
             Ok(()) => Ok(HealthStatus {
                 status: "healthy".to_string(),
-                provider: "openai".to_string(),
+                provider: self.name.to_string(),
                 latency_ms: Some(latency),
                 error: None,
             }),
             Err(e) => {
                 let (status, error_msg) = match &e {
-                    AppError::ProviderError { status, message } => {
+                    AppError::ProviderError { status, message, .. } => {
                         match *status {
                             401 => ("unhealthy".to_string(), "Authentication failed - check API key".to_string()),
                             403 => ("unhealthy".to_string(), "Access forbidden - check API key permissions".to_string()),
@@ -518,13 +711,60 @@ impl AIProvider for OpenAIProvider {
 
                 Ok(HealthStatus {
                     status,
-                    provider: "openai".to_string(),
+                    provider: self.name.to_string(),
                     latency_ms: Some(latency),
                     error: Some(error_msg),
                 })
             }
         }
     }
+
+    async fn embeddings(&self, input: Vec<String>, model: &str) -> Result<EmbeddingResponse, AppError> {
+        let url = self.embeddings_url(model);
+        let embedding_req = OpenAIEmbeddingRequest {
+            model: model.to_string(),
+            input,
+        };
+
+        tracing::info!("Sending OpenAI embeddings request to: {} with model: {}", url, model);
+
+        let response = self
+            .authorize(self.client.post(&url))
+            .header("Content-Type", "application/json")
+            .json(&embedding_req)
+            .timeout(std::time::Duration::from_secs(self.config.read_timeout_secs()))
+            .send()
+            .await
+            .map_err(|e| {
+                if e.is_connect() && e.is_timeout() {
+                    AppError::NetworkError(format!("OpenAI embeddings connect timed out: {}", e))
+                } else if e.is_timeout() {
+                    AppError::GatewayTimeout(format!("OpenAI embeddings request timed out: {}", e))
+                } else {
+                    AppError::ProviderError {
+                        status: 500,
+                        message: format!("Failed to send embeddings request to OpenAI: {}", e),
+                        error_type: None,
+                        error_code: None,
+                        retry_after: None,
+                    }
+                }
+            })?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let retry_after = retry_after_from_headers(response.headers());
+            let error_body = response.text().await.unwrap_or_default();
+            tracing::warn!("OpenAI embeddings API error: status={}, body={}", status, truncate_body(&error_body, self.config.log_body_limit()));
+            return Err(self.handle_api_error(status, &error_body).with_retry_after(retry_after));
+        }
+
+        // OpenAI already returns the unified `list` shape
+        let mut embedding_res: EmbeddingResponse = parse_json_body(response, "OpenAI embeddings response", &self.config).await?;
+        embedding_res.data.sort_by_key(|data| data.index);
+
+        Ok(embedding_res)
+    }
 }
 
 impl OpenAIProvider {
@@ -552,6 +792,8 @@ impl OpenAIProvider {
                 object: "model".to_string(),
                 created: 1714560000, // Static timestamp for fallback
                 owned_by: "openai".to_string(),
+                context_length: None,
+                provider: None,
             })
             .collect())
     }
@@ -559,25 +801,26 @@ impl OpenAIProvider {
     /// Perform comprehensive health check
     async fn perform_comprehensive_health_check(&self) -> Result<(), AppError> {
         // First, try to list models (lightweight check)
-        let models_url = format!("{}/models", self.config.api_base.trim_end_matches('/'));
+        let models_url = self.models_url();
         
         let models_response = self
-            .client
-            .get(&models_url)
-            .header("Authorization", format!("Bearer {}", self.config.api_key))
-            .header("User-Agent", "ai-proxy/0.1.0")
+            .authorize(self.client.get(&models_url))
             .timeout(std::time::Duration::from_secs(10))
             .send()
             .await
             .map_err(|e| AppError::ProviderError {
                 status: 500,
                 message: format!("Failed to connect to OpenAI: {}", e),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
             })?;
 
         if !models_response.status().is_success() {
             let status = models_response.status().as_u16();
+            let retry_after = retry_after_from_headers(models_response.headers());
             let error_body = models_response.text().await.unwrap_or_default();
-            return Err(self.handle_api_error(status, &error_body));
+            return Err(self.handle_api_error(status, &error_body).with_retry_after(retry_after));
         }
 
         // Verify we can parse the models response
@@ -587,6 +830,9 @@ impl OpenAIProvider {
             .map_err(|e| AppError::ProviderError {
                 status: 500,
                 message: format!("Failed to parse OpenAI models response: {}", e),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
             })?;
 
         // Optional: Test a minimal chat completion to verify full functionality
@@ -605,7 +851,6 @@ impl OpenAIProvider {
             .post(&chat_url)
             .header("Authorization", format!("Bearer {}", self.config.api_key))
             .header("Content-Type", "application/json")
-            .header("User-Agent", "ai-proxy/0.1.0")
             .json(&test_request)
             .timeout(std::time::Duration::from_secs(30))
             .send()
@@ -613,15 +858,100 @@ impl OpenAIProvider {
             .map_err(|e| AppError::ProviderError {
                 status: 500,
                 message: format!("Failed to test chat completion: {}", e),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
             })?;
 
         if !chat_response.status().is_success() {
             let status = chat_response.status().as_u16();
+            let retry_after = retry_after_from_headers(chat_response.headers());
             let error_body = chat_response.text().await.unwrap_or_default();
-            return Err(self.handle_api_error(status, &error_body));
+            return Err(self.handle_api_error(status, &error_body).with_retry_after(retry_after));
         }
         */
 
         Ok(())
     }
 }
+
+/// Events ending a stream whose upstream failed mid-generation
+///
+/// The open text and tool blocks are closed, a `message_delta` with
+/// `stop_reason: "error"` reports the partial usage, and an `error` event carries
+/// the streamed `output_tokens`.
+fn partial_error_events(
+    tool_calls: &std::sync::Mutex<ToolCallAssembler>,
+    message: &str,
+    input_tokens: u32,
+    output_tokens: u32,
+) -> String {
+    use crate::providers::anthropic::{AnthropicStreamEvent, MessageDelta, StreamError};
+
+    let events: String = [
+        AnthropicStreamEvent::MessageDelta {
+            delta: MessageDelta {
+                stop_reason: Some("error".to_string()),
+                usage: Some(Usage {
+                    input_tokens,
+                    output_tokens,
+                    thinking_tokens: None,
+                }),
+            },
+        },
+        AnthropicStreamEvent::Error {
+            error: StreamError {
+                error_type: "provider_error".to_string(),
+                message: message.to_string(),
+                output_tokens: Some(output_tokens),
+            },
+        },
+    ]
+    .iter()
+    .filter_map(|event| {
+        serde_json::to_string(event)
+            .ok()
+            .map(|json| format!("event: {}\ndata: {}\n\n", event.event_name(), json))
+    })
+    .collect();
+    tool_stop_events(tool_calls) + &events
+}
+
+/// SSE `content_block_stop` events for the text and tool blocks still open in the assembler
+fn tool_stop_events(tool_calls: &std::sync::Mutex<ToolCallAssembler>) -> String {
+    let Ok(mut assembler) = tool_calls.lock() else {
+        return String::new();
+    };
+    assembler
+        .finish()
+        .iter()
+        .filter_map(|event| {
+            serde_json::to_string(event)
+                .ok()
+                .map(|json| format!("event: {}\ndata: {}\n\n", event.event_name(), json))
+        })
+        .collect()
+}
+
+/// The `message_delta` carrying the stop reason when one is known, then `message_stop`
+///
+/// Content blocks are closed beforehand through the tool call assembler.
+fn closing_events(stop_reason: Option<&str>) -> String {
+    use crate::providers::anthropic::{AnthropicStreamEvent, MessageDelta};
+
+    let message_delta = stop_reason.map(|stop_reason| AnthropicStreamEvent::MessageDelta {
+        delta: MessageDelta {
+            stop_reason: Some(stop_reason.to_string()),
+            usage: None,
+        },
+    });
+    message_delta
+        .into_iter()
+        .chain([AnthropicStreamEvent::MessageStop])
+        .filter_map(|event| {
+            serde_json::to_string(&event)
+                .ok()
+                .map(|json| format!("event: {}\ndata: {}\n\n", event.event_name(), json))
+        })
+        .collect()
+}
@@ -0,0 +1,5 @@
+pub mod model;
+pub mod provider;
+
+pub use model::*;
+pub use provider::*;
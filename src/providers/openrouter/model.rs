@@ -0,0 +1,58 @@
+use serde::Deserialize;
+
+use crate::errors::AppError;
+
+/// Default OpenRouter API base URL (OpenAI-compatible)
+pub const OPENROUTER_API_BASE: &str = "https://openrouter.ai/api/v1";
+
+/// `HTTP-Referer` sent when none is configured, used by OpenRouter for app attribution
+pub const DEFAULT_HTTP_REFERER: &str = "https://github.com/yeheng/ai-proxy";
+
+/// `X-Title` sent when none is configured, shown as the app name on OpenRouter
+pub const DEFAULT_X_TITLE: &str = "ai-proxy";
+
+/// OpenRouter `/models` response
+#[derive(Deserialize, Debug)]
+pub struct OpenRouterModelsResponse {
+    pub data: Vec<OpenRouterModel>,
+}
+
+/// Model entry in OpenRouter's `/models` listing
+#[derive(Deserialize, Debug)]
+pub struct OpenRouterModel {
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub created: Option<u64>,
+    #[serde(default)]
+    pub context_length: Option<u64>,
+}
+
+impl OpenRouterModel {
+    /// Upstream vendor, taken from the `vendor/model` id
+    pub fn vendor(&self) -> &str {
+        self.id.split_once('/').map(|(vendor, _)| vendor).unwrap_or("openrouter")
+    }
+}
+
+/// Utility functions for OpenRouter models
+pub mod openrouter_utils {
+    use super::*;
+
+    /// Validate model name format
+    ///
+    /// OpenRouter routes to many vendors, so names are passed through untouched
+    /// and only basic sanity checks apply.
+    pub fn validate_model_name(model: &str) -> Result<(), AppError> {
+        if model.is_empty() {
+            return Err(AppError::ValidationError("Model name cannot be empty".to_string()));
+        }
+
+        if model.len() > 100 {
+            return Err(AppError::ValidationError("Model name too long (max 100 characters)".to_string()));
+        }
+
+        Ok(())
+    }
+}
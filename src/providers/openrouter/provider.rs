@@ -0,0 +1,190 @@
+use async_trait::async_trait;
+use reqwest::Client;
+
+use crate::{
+    config::ProviderDetail,
+    errors::AppError,
+    providers::{
+        AIProvider, HealthStatus, ModelInfo, ProviderCapabilities, StreamResponse, anthropic::*, extra_headers, openai::OpenAIProvider,
+        openrouter::*, truncate_body,
+    },
+};
+
+/// OpenRouter provider implementation
+///
+/// OpenRouter exposes an OpenAI-compatible API, so chat and streaming are
+/// handled by an inner `OpenAIProvider`; model names such as
+/// `anthropic/claude-3-sonnet` are forwarded untouched.
+pub struct OpenRouterProvider {
+    inner: OpenAIProvider,
+    config: ProviderDetail,
+    client: Client,
+}
+
+impl OpenRouterProvider {
+    /// 创建新的OpenRouter提供商实例
+    ///
+    /// ## 功能说明
+    /// 使用给定的配置和HTTP客户端创建OpenRouter提供商实例，复用OpenAI兼容的请求/响应转换
+    ///
+    /// ## 内部实现逻辑
+    /// 1. 未配置`api_base`时使用OpenRouter默认地址`https://openrouter.ai/api/v1`
+    /// 2. 未在`extra_headers`中配置时，补充OpenRouter推荐的`HTTP-Referer`和`X-Title`请求头
+    /// 3. 创建内部OpenAI兼容提供商，模型名原样透传，不做前缀过滤
+    ///
+    /// ## 参数说明
+    /// - `config`: OpenRouter提供商的详细配置，包含API密钥、基础URL等
+    /// - `client`: 共享的HTTP客户端，用于发送API请求
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let config = ProviderDetail {
+    ///     api_key: "sk-or-...".to_string(),
+    ///     api_base: "https://openrouter.ai/api/v1/".to_string(),
+    ///     ..Default::default()
+    /// };
+    /// let provider = OpenRouterProvider::new(config, Client::new());
+    /// ```
+    pub fn new(mut config: ProviderDetail, client: Client) -> Self {
+        if config.api_base.trim().is_empty() {
+            config.api_base = OPENROUTER_API_BASE.to_string();
+        }
+        for (name, value) in [("HTTP-Referer", DEFAULT_HTTP_REFERER), ("X-Title", DEFAULT_X_TITLE)] {
+            if !config.extra_headers.keys().any(|key| key.eq_ignore_ascii_case(name)) {
+                config.extra_headers.insert(name.to_string(), value.to_string());
+            }
+        }
+
+        let inner = OpenAIProvider::compatible(
+            config.clone(),
+            client.clone(),
+            "openrouter",
+            openrouter_utils::validate_model_name,
+        );
+        Self { inner, config, client }
+    }
+
+    /// Fetch models from OpenRouter's `/models` endpoint
+    async fn fetch_models_from_api(&self) -> Result<Vec<ModelInfo>, AppError> {
+        let url = format!("{}/models", self.config.api_base.trim_end_matches('/'));
+
+        tracing::info!("Fetching models from URL: {}", url);
+
+        let response = self
+            .client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", self.config.api_key))
+            .headers(extra_headers(&self.config))
+            .send()
+            .await
+            .map_err(|e| AppError::ProviderError {
+                status: 500,
+                message: format!("Failed to fetch models from OpenRouter: {}", e),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
+            })?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let error_body = response.text().await.unwrap_or_default();
+            tracing::warn!("OpenRouter models API error: status={}, body={}", status, truncate_body(&error_body, self.config.log_body_limit()));
+            return Err(AppError::ProviderError {
+                status,
+                message: format!("OpenRouter models API error: {}", truncate_body(&error_body, self.config.error_body_limit())),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
+            });
+        }
+
+        let models_response = response
+            .json::<OpenRouterModelsResponse>()
+            .await
+            .map_err(|e| AppError::ProviderError {
+                status: 500,
+                message: format!("Failed to parse OpenRouter models response: {}", e),
+                error_type: None,
+                error_code: None,
+                retry_after: None,
+            })?;
+
+        Ok(models_response
+            .data
+            .into_iter()
+            .map(|model| ModelInfo {
+                owned_by: model.vendor().to_string(),
+                created: model.created.unwrap_or(1714560000),
+                context_length: model.context_length,
+                object: "model".to_string(),
+                id: model.id,
+                provider: None,
+            })
+            .collect())
+    }
+
+    /// Get fallback models when API is unavailable
+    fn get_fallback_models(&self) -> Vec<ModelInfo> {
+        let models = self.config.models.clone().unwrap_or_else(|| {
+            vec![
+                "openai/gpt-4o".to_string(),
+                "anthropic/claude-3.5-sonnet".to_string(),
+                "google/gemini-pro-1.5".to_string(),
+            ]
+        });
+
+        models
+            .into_iter()
+            .map(|model| ModelInfo {
+                owned_by: model.split_once('/').map(|(vendor, _)| vendor).unwrap_or("openrouter").to_string(),
+                id: model,
+                object: "model".to_string(),
+                created: 1714560000, // Static timestamp for fallback
+                context_length: None,
+                provider: None,
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl AIProvider for OpenRouterProvider {
+    async fn chat(&self, request: AnthropicRequest) -> Result<AnthropicResponse, AppError> {
+        self.inner.chat(request).await
+    }
+
+    async fn chat_stream(&self, request: AnthropicRequest) -> Result<StreamResponse, AppError> {
+        self.inner.chat_stream(request).await
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: true,
+            tools: true,
+            vision: true,
+            embeddings: false,
+        }
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, AppError> {
+        match self.fetch_models_from_api().await {
+            Ok(mut models) if !models.is_empty() => {
+                tracing::info!("Successfully fetched {} models from OpenRouter API", models.len());
+                models.sort_by(|a, b| a.id.cmp(&b.id));
+                Ok(models)
+            }
+            Ok(_) => {
+                tracing::warn!("OpenRouter API returned no models, falling back to defaults");
+                Ok(self.get_fallback_models())
+            }
+            Err(e) => {
+                tracing::warn!("Failed to fetch models from OpenRouter API: {}, falling back to configured models", e);
+                Ok(self.get_fallback_models())
+            }
+        }
+    }
+
+    async fn health_check(&self) -> Result<HealthStatus, AppError> {
+        self.inner.health_check().await
+    }
+}
@@ -1,18 +1,37 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Duration;
 use reqwest::Client;
 
 use crate::{
     config::Config,
     errors::AppError,
-    providers::{AIProvider, ModelInfo, HealthStatus},
+    providers::{AIProvider, ModelInfo, HealthStatus, ProviderCapabilities, StreamLimitedProvider, anthropic::AnthropicRequest},
+    server::build_http_client_with,
 };
 use super::{
     gemini::GeminiProvider,
+    ollama::OllamaProvider,
     openai::OpenAIProvider,
+    openrouter::OpenRouterProvider,
+    xai::XaiProvider,
     anthropic::AnthropicProvider,
 };
 
+/// Models gathered from every provider, with a warning for each one that failed
+#[derive(Debug, Clone, Default)]
+pub struct ModelListing {
+    pub models: Vec<ModelInfo>,
+    pub warnings: Vec<ModelListWarning>,
+}
+
+/// A provider whose models could not be listed
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ModelListWarning {
+    pub provider: String,
+    pub message: String,
+}
+
 /// Provider registry that manages all configured AI providers
 /// 
 /// The registry handles provider instantiation, model-to-provider mapping,
@@ -20,6 +39,10 @@ use super::{
 pub struct ProviderRegistry {
     providers: HashMap<String, Arc<dyn AIProvider + Send + Sync>>,
     model_mapping: HashMap<String, String>, // model -> provider_id
+    priorities: HashMap<String, i32>, // provider_id -> configured priority
+    model_routes: HashMap<String, String>, // explicit model -> provider_id routes
+    default_provider: Option<String>, // receives models nothing else claims
+    disabled: HashSet<String>, // provider_ids switched off at runtime, skipped by routing
 }
 
 impl ProviderRegistry {
@@ -30,11 +53,18 @@ impl ProviderRegistry {
     ///
     /// ## 内部实现逻辑
     /// 1. 遍历配置中的所有提供商设置
-    /// 2. 根据提供商ID前缀识别提供商类型（gemini/openai/anthropic）
+    /// 2. 根据提供商ID前缀识别提供商类型（gemini/openai/anthropic/xai/openrouter）
     /// 3. 为每个提供商创建对应的实现实例
     /// 4. 获取每个提供商支持的模型列表（配置或默认）
     /// 5. 建立模型名到提供商ID的映射关系
-    /// 6. 验证至少配置了一个提供商
+    /// 6. 多个提供商列出同一模型时，映射到`priority`最高的提供商（相同时取ID较小者）
+    /// 7. 应用`model_routes`中的显式路由，覆盖模型列表产生的映射
+    /// 8. 记录`default_provider`，作为未知模型的兜底提供商
+    /// 9. 验证至少配置了一个提供商
+    ///
+    /// 未配置`default_max_tokens`的提供商会继承`server.default_max_tokens`；
+    /// 配置了`connect_timeout_seconds`的提供商使用带连接超时的独立HTTP客户端；
+    /// 配置了`max_concurrent_streams`的提供商由`StreamLimitedProvider`限制并发流数量
     ///
     /// ## 参数说明
     /// - `config`: 应用程序配置，包含所有提供商的详细设置
@@ -53,10 +83,37 @@ impl ProviderRegistry {
     /// - `Err(AppError)`: 创建失败，可能是未知提供商类型或无提供商配置
     pub fn new(config: &Config, http_client: Client) -> Result<Self, AppError> {
         let mut providers: HashMap<String, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
-        let mut model_mapping: HashMap<String, String> = HashMap::new();
+        let mut model_lists: Vec<(String, Vec<String>)> = Vec::new();
+        let priorities: HashMap<String, i32> = config
+            .providers
+            .iter()
+            .map(|(provider_id, provider_config)| (provider_id.clone(), provider_config.priority))
+            .collect();
 
         // 根据配置初始化提供商
         for (provider_id, provider_config) in &config.providers {
+            // 提供商未单独配置时继承全局默认max_tokens和错误响应体截断长度
+            let mut provider_config = provider_config.clone();
+            provider_config
+                .default_max_tokens
+                .get_or_insert(config.server.default_max_tokens);
+            provider_config
+                .max_log_body_bytes
+                .get_or_insert(config.logging.max_log_body_bytes);
+            provider_config
+                .max_error_body_bytes
+                .get_or_insert(config.logging.max_error_body_bytes);
+
+            // 连接超时是客户端级别的设置，需要为该提供商单独构建客户端
+            let http_client = match provider_config.connect_timeout_seconds {
+                Some(seconds) => build_http_client_with(
+                    &config.network,
+                    Some(Duration::from_secs(seconds)),
+                    config.performance.decompression,
+                )?,
+                None => http_client.clone(),
+            };
+
             // 根据提供商ID前缀创建对应的提供商实例
             let provider: Arc<dyn AIProvider + Send + Sync> = match provider_id.as_str() {
                 id if id.starts_with("gemini") => {
@@ -68,6 +125,15 @@ impl ProviderRegistry {
                 id if id.starts_with("anthropic") => {
                     Arc::new(AnthropicProvider::new(provider_config.clone(), http_client.clone()))
                 }
+                id if id.starts_with("xai") => {
+                    Arc::new(XaiProvider::new(provider_config.clone(), http_client.clone()))
+                }
+                id if id.starts_with("openrouter") => {
+                    Arc::new(OpenRouterProvider::new(provider_config.clone(), http_client.clone()))
+                }
+                id if id.starts_with("ollama") => {
+                    Arc::new(OllamaProvider::new(provider_config.clone(), http_client.clone()))
+                }
                 _ => {
                     return Err(AppError::ConfigError(
                         format!("Unknown provider type: {}", provider_id)
@@ -75,19 +141,35 @@ impl ProviderRegistry {
                 }
             };
 
+            // 配置了并发流上限的提供商由限流包装器代理
+            let provider: Arc<dyn AIProvider + Send + Sync> = match provider_config.max_concurrent_streams {
+                Some(limit) => Arc::new(StreamLimitedProvider::new(
+                    provider_id.clone(),
+                    provider,
+                    limit,
+                    provider_config.stream_overflow,
+                )),
+                None => provider,
+            };
+
             // 获取此提供商的模型列表并创建映射
             let models = provider_config.models.as_ref()
                 .map(|m| m.clone())
                 .unwrap_or_else(|| Self::get_default_models(provider_id));
 
-            // 为每个模型创建到提供商的映射
-            for model in models {
-                model_mapping.insert(model, provider_id.clone());
-            }
-
+            model_lists.push((provider_id.clone(), models));
             providers.insert(provider_id.clone(), provider);
         }
 
+        // 只保留指向已配置提供商的显式路由，刷新模型列表时同样适用
+        let model_routes: HashMap<String, String> = config
+            .model_routes
+            .iter()
+            .filter(|(_, provider_id)| providers.contains_key(*provider_id))
+            .map(|(model, provider_id)| (model.clone(), provider_id.clone()))
+            .collect();
+        let model_mapping = Self::build_model_mapping(&priorities, &model_routes, model_lists);
+
         // 验证至少配置了一个提供商
         if providers.is_empty() {
             return Err(AppError::ConfigError(
@@ -95,9 +177,18 @@ impl ProviderRegistry {
             ));
         }
 
+        let default_provider = config
+            .default_provider
+            .clone()
+            .filter(|provider_id| providers.contains_key(provider_id));
+
         Ok(Self {
             providers,
             model_mapping,
+            priorities,
+            model_routes,
+            default_provider,
+            disabled: HashSet::new(),
         })
     }
 
@@ -112,6 +203,99 @@ impl ProviderRegistry {
         Self {
             providers: HashMap::new(),
             model_mapping: HashMap::new(),
+            priorities: HashMap::new(),
+            model_routes: HashMap::new(),
+            default_provider: None,
+            disabled: HashSet::new(),
+        }
+    }
+
+    /// Map every listed model to the provider that serves it
+    ///
+    /// A model listed by several providers goes to the one that `outranks` the
+    /// others, whatever order the lists come in; explicit `model_routes` then
+    /// override the lists.
+    fn build_model_mapping(
+        priorities: &HashMap<String, i32>,
+        model_routes: &HashMap<String, String>,
+        model_lists: Vec<(String, Vec<String>)>,
+    ) -> HashMap<String, String> {
+        let mut model_mapping: HashMap<String, String> = HashMap::new();
+        for (provider_id, models) in model_lists {
+            for model in models {
+                let keep_existing = model_mapping
+                    .get(&model)
+                    .is_some_and(|existing| Self::outranks(priorities, existing, &provider_id));
+                if !keep_existing {
+                    model_mapping.insert(model, provider_id.clone());
+                }
+            }
+        }
+        for (model, provider_id) in model_routes {
+            model_mapping.insert(model.clone(), provider_id.clone());
+        }
+        model_mapping
+    }
+
+    /// Whether provider `a` wins over `b` for a shared model: higher priority, then lower ID
+    fn outranks(priorities: &HashMap<String, i32>, a: &str, b: &str) -> bool {
+        let priority = |id: &str| priorities.get(id).copied().unwrap_or_default();
+        (priority(a), std::cmp::Reverse(a)) > (priority(b), std::cmp::Reverse(b))
+    }
+
+    /// Merge per-provider model lists into one sorted by model ID
+    ///
+    /// Each entry is tagged with its provider; a model offered by several
+    /// providers is listed once, from the provider that `outranks` the others.
+    fn merge_model_lists(&self, lists: Vec<(String, Vec<ModelInfo>)>) -> Vec<ModelInfo> {
+        let mut merged: BTreeMap<String, ModelInfo> = BTreeMap::new();
+        for (provider_id, models) in lists {
+            for mut model in models {
+                model.provider = Some(provider_id.clone());
+                let keep_existing = merged.get(&model.id).is_some_and(|existing| {
+                    existing
+                        .provider
+                        .as_deref()
+                        .is_some_and(|existing_id| Self::outranks(&self.priorities, existing_id, &provider_id))
+                });
+                if !keep_existing {
+                    merged.insert(model.id.clone(), model);
+                }
+            }
+        }
+        merged.into_values().collect()
+    }
+
+    /// 注册一个提供商实例
+    ///
+    /// ## 功能说明
+    /// 将外部创建的提供商（如`test-utils`特性下的`MockProvider`）加入注册表，
+    /// 并把给定模型路由到该提供商
+    ///
+    /// ## 内部实现逻辑
+    /// 1. 以`provider_id`保存提供商实例，同ID的已有提供商会被替换
+    /// 2. 将每个模型名映射到`provider_id`，覆盖已有的映射
+    ///
+    /// ## 参数说明
+    /// - `provider_id`: 提供商ID，也用于按前缀匹配模型名
+    /// - `provider`: 提供商实例
+    /// - `models`: 路由到该提供商的模型名
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let mut registry = ProviderRegistry::new_empty();
+    /// registry.register_provider("mock", Arc::new(MockProvider::new()), &["mock-model".to_string()]);
+    /// assert!(registry.get_provider_for_model("mock-model").is_ok());
+    /// ```
+    pub fn register_provider(
+        &mut self,
+        provider_id: &str,
+        provider: Arc<dyn AIProvider + Send + Sync>,
+        models: &[String],
+    ) {
+        self.providers.insert(provider_id.to_string(), provider);
+        for model in models {
+            self.model_mapping.insert(model.clone(), provider_id.to_string());
         }
     }
 
@@ -123,8 +307,9 @@ impl ProviderRegistry {
     /// ## 内部实现逻辑
     /// 1. 首先尝试精确匹配：在模型映射表中查找模型名
     /// 2. 如果精确匹配失败，尝试前缀匹配：检查模型名是否以提供商ID开头
-    /// 3. 如果都失败，返回错误并列出所有可用模型
-    /// 4. 返回找到的提供商的Arc引用
+    /// 3. 如果都失败且配置了`default_provider`，交给默认提供商
+    /// 4. 否则返回错误并列出所有可用模型
+    /// 5. 返回找到的提供商的Arc引用
     ///
     /// ## 参数说明
     /// - `model`: 要查找的模型名称，如"gpt-4"、"claude-3-sonnet"等
@@ -132,6 +317,7 @@ impl ProviderRegistry {
     /// ## 匹配策略
     /// 1. **精确匹配**: 直接在model_mapping中查找
     /// 2. **前缀匹配**: 检查模型名是否以提供商ID开头（如"openai-gpt-4"匹配"openai"提供商）
+    /// 3. **默认提供商**: `default_provider`（未配置时跳过）
     ///
     /// ## 执行例子
     /// ```rust
@@ -144,8 +330,8 @@ impl ProviderRegistry {
     /// - `Err(AppError::ProviderNotFound)`: 未找到支持该模型的提供商
     /// - `Err(AppError::InternalServerError)`: 内部状态不一致错误
     pub fn get_provider_for_model(&self, model: &str) -> Result<Arc<dyn AIProvider + Send + Sync>, AppError> {
-        // 首先尝试精确匹配
-        if let Some(provider_id) = self.model_mapping.get(model) {
+        // 按精确匹配、前缀匹配、默认提供商的顺序查找，跳过运行时停用的提供商
+        if let Some(provider_id) = self.provider_id_for_model(model) {
             return self.providers.get(provider_id)
                 .cloned()
                 .ok_or_else(|| AppError::InternalServerError(
@@ -153,11 +339,12 @@ impl ProviderRegistry {
                 ));
         }
 
-        // 尝试前缀匹配进行提供商选择
-        for (provider_id, provider) in &self.providers {
-            if model.starts_with(provider_id) {
-                return Ok(provider.clone());
-            }
+        // 模型的提供商已停用且没有可回退的提供商
+        if let Some(provider_id) = self.model_mapping.get(model) {
+            return Err(AppError::ServiceUnavailable(format!(
+                "Provider '{}' serving model '{}' is disabled",
+                provider_id, model
+            )));
         }
 
         // 如果未找到提供商，返回错误并列出可用模型
@@ -168,6 +355,148 @@ impl ProviderRegistry {
         ))
     }
 
+    /// 根据请求查找提供商，并检查其是否支持请求使用的功能
+    ///
+    /// ## 功能说明
+    /// 在`get_provider_for_model`的基础上，按提供商的能力描述提前拒绝其不支持的请求
+    /// （如对不支持工具调用的提供商发送`tools`）
+    ///
+    /// ## 参数说明
+    /// - `request`: 待路由的请求
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let provider = registry.get_provider_for_request(&request)?;
+    /// let response = provider.chat(request).await?;
+    /// ```
+    ///
+    /// ## 返回值
+    /// - `Ok(Arc<dyn AIProvider>)`: 能够处理该请求的提供商实例
+    /// - `Err(AppError::ProviderNotFound)`: 未找到支持该模型的提供商
+    /// - `Err(AppError::BadRequest)`: 提供商不支持请求使用的功能
+    pub fn get_provider_for_request(&self, request: &AnthropicRequest) -> Result<Arc<dyn AIProvider + Send + Sync>, AppError> {
+        let provider = self.get_provider_for_model(&request.model)?;
+        let provider_id = self.provider_id_for_model(&request.model).unwrap_or(&request.model);
+        provider.capabilities().check_request(provider_id, request)?;
+        Ok(provider)
+    }
+
+    /// 获取所有提供商的能力描述
+    ///
+    /// ## 功能说明
+    /// 返回每个已注册提供商支持的功能（流式、工具、视觉、嵌入），按提供商ID排序，
+    /// 供`GET /v1/capabilities`展示
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let capabilities = registry.capabilities();
+    /// assert!(capabilities["openai"].embeddings);
+    /// ```
+    ///
+    /// ## 返回值
+    /// 提供商ID到能力描述的有序映射
+    pub fn capabilities(&self) -> BTreeMap<String, ProviderCapabilities> {
+        self.providers
+            .iter()
+            .map(|(provider_id, provider)| (provider_id.clone(), provider.capabilities()))
+            .collect()
+    }
+
+    /// 查找处理指定模型的提供商ID
+    ///
+    /// ## 功能说明
+    /// 与`get_provider_for_model`使用相同的匹配规则（先精确匹配，再按提供商ID前缀匹配，
+    /// 最后是默认提供商，均跳过运行时停用的提供商），返回提供商ID以便查询其配置（如`max_retries`）
+    ///
+    /// ## 参数说明
+    /// - `model`: 模型名称
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let provider_id = registry.provider_id_for_model("gpt-4");
+    /// assert_eq!(provider_id, Some("openai"));
+    /// ```
+    ///
+    /// ## 返回值
+    /// - `Some(&str)`: 提供商ID
+    /// - `None`: 未找到支持该模型的提供商
+    pub fn provider_id_for_model(&self, model: &str) -> Option<&str> {
+        let enabled = |provider_id: &&String| !self.disabled.contains(provider_id.as_str());
+        if let Some(provider_id) = self.model_mapping.get(model).filter(enabled) {
+            return Some(provider_id.as_str());
+        }
+
+        self.providers
+            .keys()
+            .filter(enabled)
+            .find(|provider_id| model.starts_with(provider_id.as_str()))
+            .or(self.default_provider.as_ref().filter(enabled))
+            .map(String::as_str)
+    }
+
+    /// 查找因提供商停用而发生的回退
+    ///
+    /// ## 功能说明
+    /// 模型映射到的提供商已被停用、请求改由其他提供商处理时，返回(原提供商, 回退提供商)，
+    /// 供调用方记录回退指标
+    ///
+    /// ## 参数说明
+    /// - `model`: 模型名称
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// if let Some((from, to)) = registry.fallback_for_model("gpt-4") {
+    ///     metrics.record_fallback(from, to);
+    /// }
+    /// ```
+    ///
+    /// ## 返回值
+    /// - `Some((&str, &str))`: 停用的提供商和实际处理请求的提供商
+    /// - `None`: 没有发生回退
+    pub fn fallback_for_model(&self, model: &str) -> Option<(&str, &str)> {
+        let owner = self.model_mapping.get(model)?;
+        if !self.disabled.contains(owner) {
+            return None;
+        }
+        self.provider_id_for_model(model).map(|fallback| (owner.as_str(), fallback))
+    }
+
+    /// 在运行时启用或停用提供商
+    ///
+    /// ## 功能说明
+    /// 供故障处理时临时摘除不稳定的提供商而无需重新加载配置。停用的提供商不再参与路由，
+    /// 其模型按前缀匹配或`default_provider`回退；重新加载配置会重建注册表并恢复所有提供商
+    ///
+    /// ## 参数说明
+    /// - `provider_id`: 提供商ID（配置中的键）
+    /// - `enabled`: true为启用，false为停用
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// registry.set_enabled("openai", false)?;
+    /// assert!(!registry.is_enabled("openai"));
+    /// ```
+    ///
+    /// ## 返回值
+    /// - `Ok(())`: 状态已更新
+    /// - `Err(AppError::ProviderNotFound)`: 注册表中没有该提供商
+    pub fn set_enabled(&mut self, provider_id: &str, enabled: bool) -> Result<(), AppError> {
+        if !self.providers.contains_key(provider_id) {
+            return Err(AppError::ProviderNotFound(format!("Unknown provider '{}'", provider_id)));
+        }
+        if enabled {
+            self.disabled.remove(provider_id);
+        } else {
+            self.disabled.insert(provider_id.to_string());
+        }
+        Ok(())
+    }
+
+    /// 提供商当前是否参与路由（未被`set_enabled`停用）
+    pub fn is_enabled(&self, provider_id: &str) -> bool {
+        !self.disabled.contains(provider_id)
+    }
+
     /// 获取所有提供商的可用模型列表
     ///
     /// ## 功能说明
@@ -176,9 +505,9 @@ impl ProviderRegistry {
     /// ## 内部实现逻辑
     /// 1. 遍历所有已注册的提供商
     /// 2. 异步调用每个提供商的list_models方法
-    /// 3. 将成功获取的模型列表合并到结果中
+    /// 3. 将成功获取的模型列表合并到结果中，同名模型只保留`priority`最高的提供商的条目
     /// 4. 对于失败的提供商，记录警告但继续处理其他提供商
-    /// 5. 返回合并后的完整模型列表
+    /// 5. 返回按模型ID排序的完整模型列表，每项的`provider`标明来源
     ///
     /// ## 容错机制
     /// - 单个提供商失败不会影响整体结果
@@ -200,11 +529,11 @@ impl ProviderRegistry {
         let mut all_models = Vec::new();
 
         // 遍历所有提供商获取模型列表
-        for provider in self.providers.values() {
+        for (provider_id, provider) in &self.providers {
             match provider.list_models().await {
-                Ok(mut models) => {
+                Ok(models) => {
                     // 成功获取模型，添加到结果列表
-                    all_models.append(&mut models)
+                    all_models.push((provider_id.clone(), models))
                 },
                 Err(e) => {
                     // 单个提供商失败，记录警告但继续处理
@@ -214,7 +543,59 @@ impl ProviderRegistry {
             }
         }
 
-        Ok(all_models)
+        Ok(self.merge_model_lists(all_models))
+    }
+
+    /// 并发获取所有提供商的模型列表，单个提供商超时或出错时降级
+    ///
+    /// ## 功能说明
+    /// 与`list_all_models`相同，但同时向所有提供商请求模型列表，并为每个提供商设置超时，
+    /// 一个缓慢的提供商不会拖慢整个列表；超时或失败的提供商记录在`warnings`中
+    ///
+    /// ## 内部实现逻辑
+    /// 1. 按提供商ID排序后并发调用每个提供商的`list_models`
+    /// 2. 每个调用受`timeout`限制
+    /// 3. 合并成功获取的模型（同名模型保留`priority`最高的提供商，按模型ID排序），其余提供商生成一条警告
+    ///
+    /// ## 参数说明
+    /// - `timeout`: 单个提供商的最长等待时间
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let listing = registry.list_all_models_within(Duration::from_secs(5)).await;
+    /// for warning in &listing.warnings {
+    ///     tracing::warn!("{}: {}", warning.provider, warning.message);
+    /// }
+    /// ```
+    ///
+    /// ## 返回值
+    /// 成功获取的模型和失败提供商的警告
+    pub async fn list_all_models_within(&self, timeout: Duration) -> ModelListing {
+        let mut providers: Vec<_> = self.providers.iter().collect();
+        providers.sort_by(|a, b| a.0.cmp(b.0));
+
+        let results = futures::future::join_all(providers.into_iter().map(|(provider_id, provider)| async move {
+            let result = tokio::time::timeout(timeout, provider.list_models()).await;
+            (provider_id.clone(), result)
+        }))
+        .await;
+
+        let mut listing = ModelListing::default();
+        let mut lists = Vec::new();
+        for (provider, result) in results {
+            let message = match result {
+                Ok(Ok(models)) => {
+                    lists.push((provider, models));
+                    continue;
+                }
+                Ok(Err(e)) => e.to_string(),
+                Err(_) => format!("Timed out after {}ms listing models", timeout.as_millis()),
+            };
+            tracing::warn!("Failed to get models from provider {}: {}", provider, message);
+            listing.warnings.push(ModelListWarning { provider, message });
+        }
+        listing.models = self.merge_model_lists(lists);
+        listing
     }
 
     /// 检查所有提供商的健康状态
@@ -252,20 +633,47 @@ impl ProviderRegistry {
         let mut results = HashMap::new();
 
         // 遍历所有提供商进行健康检查
-        for (provider_id, provider) in &self.providers {
-            // 执行健康检查，失败时创建错误状态
-            let health = provider.health_check().await.unwrap_or_else(|e| HealthStatus {
-                status: "error".to_string(),
-                provider: provider_id.clone(),
-                latency_ms: None,
-                error: Some(e.to_string()),
-            });
-            results.insert(provider_id.clone(), health);
+        for provider_id in self.providers.keys() {
+            if let Some(health) = self.health_check_provider(provider_id).await {
+                results.insert(provider_id.clone(), health);
+            }
         }
 
         results
     }
 
+    /// 检查单个提供商的健康状态
+    ///
+    /// ## 功能说明
+    /// 调用指定提供商的health_check方法，失败时转换为`error`状态
+    ///
+    /// ## 参数说明
+    /// - `provider_id`: 提供商ID
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// if let Some(status) = registry.health_check_provider("openai").await {
+    ///     println!("openai: {}", status.status);
+    /// }
+    /// ```
+    ///
+    /// ## 返回值
+    /// - `Some(HealthStatus)`: 提供商的健康状态
+    /// - `None`: 未注册该提供商
+    pub async fn health_check_provider(&self, provider_id: &str) -> Option<HealthStatus> {
+        let provider = self.providers.get(provider_id)?;
+
+        // 执行健康检查，失败时创建错误状态
+        let health = provider.health_check().await.unwrap_or_else(|e| HealthStatus {
+            status: "error".to_string(),
+            provider: provider_id.to_string(),
+            latency_ms: None,
+            error: Some(e.to_string()),
+        });
+
+        Some(health)
+    }
+
     /// 获取所有已配置的提供商ID列表
     ///
     /// ## 功能说明
@@ -336,9 +744,10 @@ impl ProviderRegistry {
     /// ## 内部实现逻辑
     /// 1. 创建新的模型映射表
     /// 2. 遍历所有提供商，异步获取最新模型列表
-    /// 3. 将成功获取的模型添加到新映射表中
+    /// 3. 收集成功获取的模型列表
     /// 4. 对失败的提供商记录警告但继续处理
-    /// 5. 用新映射表替换旧的模型映射
+    /// 5. 与创建注册表时相同：同名模型映射到`priority`最高的提供商，`model_routes`中的显式路由优先，
+    ///    用得到的映射表替换旧的模型映射
     /// 6. 记录刷新完成的日志
     ///
     /// ## 使用场景
@@ -356,16 +765,14 @@ impl ProviderRegistry {
     /// - `Ok(())`: 模型刷新成功完成
     /// - `Err(AppError)`: 系统级错误（极少发生）
     pub async fn refresh_models(&mut self) -> Result<(), AppError> {
-        let mut new_model_mapping: HashMap<String, String> = HashMap::new();
+        let mut model_lists: Vec<(String, Vec<String>)> = Vec::new();
 
         // 遍历所有提供商获取最新模型列表
         for (provider_id, provider) in &self.providers {
             match provider.list_models().await {
                 Ok(models) => {
-                    // 成功获取模型，更新映射表
-                    for model in models {
-                        new_model_mapping.insert(model.id, provider_id.clone());
-                    }
+                    // 成功获取模型，稍后与其他提供商的列表一起建立映射
+                    model_lists.push((provider_id.clone(), models.into_iter().map(|model| model.id).collect()));
                     tracing::info!("Refreshed models for provider: {}", provider_id);
                 }
                 Err(e) => {
@@ -376,8 +783,8 @@ impl ProviderRegistry {
             }
         }
 
-        // 更新模型映射表
-        self.model_mapping = new_model_mapping;
+        // 按优先级和显式路由重建模型映射表，与创建注册表时的规则一致
+        self.model_mapping = Self::build_model_mapping(&self.priorities, &self.model_routes, model_lists);
         tracing::info!("Model mapping refreshed successfully");
 
         Ok(())
@@ -434,6 +841,21 @@ impl ProviderRegistry {
                 "claude-3-sonnet-20240229".to_string(),
                 "claude-3-haiku-20240307".to_string(),
             ],
+            id if id.starts_with("xai") => vec![
+                "grok-2-latest".to_string(),
+                "grok-2-mini".to_string(),
+                "grok-beta".to_string(),
+            ],
+            id if id.starts_with("openrouter") => vec![
+                "openai/gpt-4o".to_string(),
+                "anthropic/claude-3.5-sonnet".to_string(),
+                "google/gemini-pro-1.5".to_string(),
+            ],
+            id if id.starts_with("ollama") => vec![
+                "llama3.2".to_string(),
+                "mistral".to_string(),
+                "qwen2.5".to_string(),
+            ],
             _ => vec![],
         }
     }
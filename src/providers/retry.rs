@@ -0,0 +1,170 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use tokio::time::Instant;
+
+use crate::errors::AppError;
+use crate::metrics::MetricsCollector;
+use super::AIProvider;
+use super::anthropic::{AnthropicRequest, AnthropicResponse};
+
+/// Delay before the first retry; doubled for each further attempt
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+/// Upper bound for the delay between attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(2);
+/// Default cap on a delay requested through `Retry-After`
+const DEFAULT_MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// Retry budget for a single chat request
+///
+/// `max_retries` bounds the number of extra attempts, while `deadline` bounds
+/// the wall-clock time of all attempts together: no attempt is started once the
+/// deadline has passed, and an in-flight attempt is cut off when it is reached.
+/// An upstream `Retry-After` is honoured up to `max_retry_after`.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub deadline: Instant,
+    pub max_retry_after: Duration,
+    /// Collector and provider label that retry attempts are counted under
+    metrics: Option<(Arc<MetricsCollector>, String)>,
+}
+
+impl RetryPolicy {
+    /// Policy whose deadline is `budget` from now
+    pub fn new(max_retries: u32, budget: Duration) -> Self {
+        Self {
+            max_retries,
+            deadline: Instant::now() + budget,
+            max_retry_after: DEFAULT_MAX_RETRY_AFTER,
+            metrics: None,
+        }
+    }
+
+    /// Count each retry attempt and its outcome under `provider`
+    pub fn with_metrics(mut self, metrics: Arc<MetricsCollector>, provider: impl Into<String>) -> Self {
+        self.metrics = Some((metrics, provider.into()));
+        self
+    }
+
+    /// Cap the delay honoured from `Retry-After` headers
+    pub fn with_max_retry_after(mut self, max_retry_after: Duration) -> Self {
+        self.max_retry_after = max_retry_after;
+        self
+    }
+
+    /// Delay before retry number `retry` after `error`
+    ///
+    /// The upstream `Retry-After` (clamped to `max_retry_after`) is used when
+    /// it asks for longer than the computed backoff; otherwise, including when
+    /// the header was missing or malformed, the backoff applies.
+    pub fn delay_for(&self, retry: u32, error: &AppError) -> Duration {
+        let backoff = Self::backoff(retry);
+        match error.retry_after() {
+            Some(requested) => requested.min(self.max_retry_after).max(backoff),
+            None => backoff,
+        }
+    }
+
+    /// Backoff before retry number `retry` (1-based)
+    fn backoff(retry: u32) -> Duration {
+        INITIAL_BACKOFF
+            .saturating_mul(1 << (retry - 1).min(16))
+            .min(MAX_BACKOFF)
+    }
+
+    /// Count a retry attempt (never the first attempt) with its outcome
+    fn record_attempt(&self, attempt: u32, outcome: &str) {
+        if attempt > 1
+            && let Some((metrics, provider)) = &self.metrics
+        {
+            metrics.record_retry_attempt(provider, outcome);
+        }
+    }
+}
+
+/// Parse a `Retry-After` header value into a delay
+///
+/// Accepts both forms allowed by RFC 7231: a non-negative number of seconds,
+/// or an HTTP date (`Sun, 06 Nov 1994 08:49:37 GMT`), measured from `now`.
+/// Dates in the past yield a zero delay; anything else is rejected.
+pub fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some((date.with_timezone(&Utc) - now).to_std().unwrap_or(Duration::ZERO))
+}
+
+/// Delay requested by an upstream response's `Retry-After` header, if present and valid
+pub fn retry_after_from_headers(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?;
+    parse_retry_after(value, Utc::now())
+}
+
+/// Whether a failed attempt is worth retrying
+///
+/// Upstream 5xx/429 responses, transport failures and timeouts are transient;
+/// validation and other client errors would fail the same way again.
+pub fn is_retryable(error: &AppError) -> bool {
+    match error {
+        AppError::ProviderError { status, .. } => *status == 429 || *status >= 500,
+        AppError::RateLimitError(_)
+        | AppError::GatewayTimeout(_)
+        | AppError::TimeoutError(_)
+        | AppError::ServiceUnavailable(_) => true,
+        _ => false,
+    }
+}
+
+/// Run a non-streaming chat request, retrying transient failures within the policy's budget
+///
+/// Returns `GatewayTimeout` when the deadline cuts off an attempt or leaves no
+/// room for the next one; otherwise the last attempt's error is returned as is.
+pub async fn chat_with_retries(
+    provider: Arc<dyn AIProvider + Send + Sync>,
+    request: AnthropicRequest,
+    policy: RetryPolicy,
+) -> Result<AnthropicResponse, AppError> {
+    let mut attempt: u32 = 0;
+    loop {
+        attempt += 1;
+
+        let error = match tokio::time::timeout_at(policy.deadline, provider.chat(request.clone())).await {
+            Ok(Ok(response)) => {
+                policy.record_attempt(attempt, "success");
+                return Ok(response);
+            }
+            Ok(Err(error)) => {
+                policy.record_attempt(attempt, "error");
+                error
+            }
+            Err(_) => {
+                policy.record_attempt(attempt, "timeout");
+                return Err(AppError::GatewayTimeout(format!(
+                    "Request deadline exceeded after {} attempt(s)",
+                    attempt
+                )));
+            }
+        };
+
+        if attempt > policy.max_retries || !is_retryable(&error) {
+            return Err(error);
+        }
+
+        // Only retry if the backoff still leaves time for another attempt
+        let resume_at = Instant::now() + policy.delay_for(attempt, &error);
+        if resume_at >= policy.deadline {
+            return Err(AppError::GatewayTimeout(format!(
+                "Request deadline exceeded after {} attempt(s); last error: {}",
+                attempt, error
+            )));
+        }
+
+        tracing::warn!(attempt, "Retrying chat request after transient error: {}", error);
+        tokio::time::sleep_until(resume_at).await;
+    }
+}
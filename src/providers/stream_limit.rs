@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use tokio::sync::Semaphore;
+
+use crate::config::StreamOverflow;
+use crate::errors::AppError;
+use super::anthropic::{AnthropicRequest, AnthropicResponse};
+use super::{AIProvider, EmbeddingResponse, HealthStatus, ModelInfo, ProviderCapabilities, StreamResponse};
+
+/// Provider wrapper that caps the number of concurrent streams
+///
+/// Each `chat_stream` call holds a semaphore permit for the lifetime of the
+/// returned stream, so the slot is freed when the stream completes or the
+/// client goes away. Non-streaming calls are passed through untouched.
+pub struct StreamLimitedProvider {
+    inner: Arc<dyn AIProvider + Send + Sync>,
+    provider_id: String,
+    limit: u32,
+    overflow: StreamOverflow,
+    permits: Arc<Semaphore>,
+}
+
+impl StreamLimitedProvider {
+    /// Wrap `inner`, allowing at most `limit` concurrent streams
+    pub fn new(
+        provider_id: String,
+        inner: Arc<dyn AIProvider + Send + Sync>,
+        limit: u32,
+        overflow: StreamOverflow,
+    ) -> Self {
+        Self {
+            inner,
+            provider_id,
+            limit,
+            overflow,
+            permits: Arc::new(Semaphore::new(limit as usize)),
+        }
+    }
+}
+
+#[async_trait]
+impl AIProvider for StreamLimitedProvider {
+    async fn chat(&self, request: AnthropicRequest) -> Result<AnthropicResponse, AppError> {
+        self.inner.chat(request).await
+    }
+
+    async fn chat_stream(&self, request: AnthropicRequest) -> Result<StreamResponse, AppError> {
+        let permit = match self.overflow {
+            StreamOverflow::Reject => self.permits.clone().try_acquire_owned().map_err(|_| {
+                AppError::ServiceUnavailable(format!(
+                    "Provider {} is at its limit of {} concurrent streams",
+                    self.provider_id, self.limit
+                ))
+            })?,
+            StreamOverflow::Queue => self
+                .permits
+                .clone()
+                .acquire_owned()
+                .await
+                .map_err(|e| AppError::InternalServerError(format!("Stream limiter closed: {}", e)))?,
+        };
+
+        let stream = self.inner.chat_stream(request).await?;
+        // The permit lives as long as the stream and is released when it is dropped
+        Ok(Box::pin(stream.map(move |item| {
+            let _permit = &permit;
+            item
+        })))
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        self.inner.capabilities()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, AppError> {
+        self.inner.list_models().await
+    }
+
+    async fn health_check(&self) -> Result<HealthStatus, AppError> {
+        self.inner.health_check().await
+    }
+
+    async fn embeddings(&self, input: Vec<String>, model: &str) -> Result<EmbeddingResponse, AppError> {
+        self.inner.embeddings(input, model).await
+    }
+}
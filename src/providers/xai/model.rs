@@ -0,0 +1,34 @@
+use crate::errors::AppError;
+
+/// Default xAI API base URL (OpenAI-compatible)
+pub const XAI_API_BASE: &str = "https://api.x.ai/v1";
+
+/// Utility functions for xAI Grok models
+pub mod xai_utils {
+    use super::*;
+
+    /// Validate model name format
+    pub fn validate_model_name(model: &str) -> Result<(), AppError> {
+        if model.is_empty() {
+            return Err(AppError::ValidationError("Model name cannot be empty".to_string()));
+        }
+
+        if model.len() > 100 {
+            return Err(AppError::ValidationError("Model name too long (max 100 characters)".to_string()));
+        }
+
+        if !model.starts_with("grok-") {
+            return Err(AppError::ValidationError(format!(
+                "Invalid xAI model name: {}. Must start with: grok-",
+                model
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Whether the model is a Grok chat model (image/embedding models are excluded)
+    pub fn is_chat_model(model: &str) -> bool {
+        model.starts_with("grok-") && !model.contains("image") && !model.contains("embedding")
+    }
+}
@@ -0,0 +1,120 @@
+use async_trait::async_trait;
+use reqwest::Client;
+
+use crate::{
+    config::ProviderDetail,
+    errors::AppError,
+    providers::{AIProvider, HealthStatus, ModelInfo, ProviderCapabilities, StreamResponse, anthropic::*, openai::OpenAIProvider, xai::*},
+};
+
+/// xAI (Grok) provider implementation
+///
+/// The xAI API is OpenAI-compatible, so requests, responses and streaming
+/// are handled by an inner `OpenAIProvider` pointed at the xAI base URL.
+pub struct XaiProvider {
+    inner: OpenAIProvider,
+    config: ProviderDetail,
+}
+
+impl XaiProvider {
+    /// 创建新的xAI提供商实例
+    ///
+    /// ## 功能说明
+    /// 使用给定的配置和HTTP客户端创建xAI（Grok）提供商实例，复用OpenAI兼容的请求/响应转换
+    ///
+    /// ## 内部实现逻辑
+    /// 1. 未配置`api_base`时使用xAI默认地址`https://api.x.ai/v1`
+    /// 2. 创建内部OpenAI兼容提供商，并使用`grok-*`模型名校验
+    ///
+    /// ## 参数说明
+    /// - `config`: xAI提供商的详细配置，包含API密钥、基础URL等
+    /// - `client`: 共享的HTTP客户端，用于发送API请求
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let config = ProviderDetail {
+    ///     api_key: "xai-...".to_string(),
+    ///     api_base: "https://api.x.ai/v1/".to_string(),
+    ///     ..Default::default()
+    /// };
+    /// let provider = XaiProvider::new(config, Client::new());
+    /// ```
+    pub fn new(mut config: ProviderDetail, client: Client) -> Self {
+        if config.api_base.trim().is_empty() {
+            config.api_base = XAI_API_BASE.to_string();
+        }
+        let inner = OpenAIProvider::compatible(config.clone(), client, "xai", xai_utils::validate_model_name);
+        Self { inner, config }
+    }
+
+    /// Get fallback models when API is unavailable
+    fn get_fallback_models(&self) -> Vec<ModelInfo> {
+        let models = self.config.models.clone().unwrap_or_else(|| {
+            vec![
+                "grok-2-latest".to_string(),
+                "grok-2-mini".to_string(),
+                "grok-beta".to_string(),
+            ]
+        });
+
+        models
+            .into_iter()
+            .map(|model| ModelInfo {
+                id: model,
+                object: "model".to_string(),
+                created: 1714560000, // Static timestamp for fallback
+                owned_by: "xai".to_string(),
+                context_length: None,
+                provider: None,
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl AIProvider for XaiProvider {
+    async fn chat(&self, request: AnthropicRequest) -> Result<AnthropicResponse, AppError> {
+        self.inner.chat(request).await
+    }
+
+    async fn chat_stream(&self, request: AnthropicRequest) -> Result<StreamResponse, AppError> {
+        self.inner.chat_stream(request).await
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: true,
+            tools: true,
+            vision: true,
+            embeddings: false,
+        }
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, AppError> {
+        match self.inner.fetch_models_from_api().await {
+            Ok(models) => {
+                // Keep only Grok chat models, sorted by name for consistent ordering
+                let mut chat_models: Vec<ModelInfo> = models
+                    .into_iter()
+                    .filter(|model| xai_utils::is_chat_model(&model.id))
+                    .collect();
+                chat_models.sort_by(|a, b| a.id.cmp(&b.id));
+
+                if chat_models.is_empty() {
+                    tracing::warn!("No chat-capable models found in xAI API response, falling back to defaults");
+                    return Ok(self.get_fallback_models());
+                }
+
+                Ok(chat_models)
+            }
+            Err(e) => {
+                tracing::warn!("Failed to fetch models from xAI API: {}, falling back to configured models", e);
+                Ok(self.get_fallback_models())
+            }
+        }
+    }
+
+    async fn health_check(&self) -> Result<HealthStatus, AppError> {
+        self.inner.health_check().await
+    }
+}
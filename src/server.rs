@@ -1,14 +1,21 @@
 use axum::{
     Router,
-    extract::State,
+    extract::{
+        DefaultBodyLimit, Path, State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode},
     middleware,
     response::Json,
-    routing::{get, post},
+    routing::{delete, get, post},
 };
+use futures::FutureExt;
 use reqwest::Client;
 use serde_json::{Value, json};
-use std::sync::Arc;
-use tokio::net::TcpListener;
+use std::{path::PathBuf, sync::Arc, time::Duration};
+use std::net::SocketAddr;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::{TlsAcceptor, rustls, server::TlsStream};
 use tokio::sync::RwLock;
 
 use tower_http::{
@@ -17,14 +24,21 @@ use tower_http::{
 };
 
 use crate::{
-    config::Config,
-    errors::{AppError, AppResult},
-    metrics::MetricsCollector,
+    config::{Config, LoggingConfig, NetworkConfig, RedactionRoute, SharedConfig, load_config_from},
+    errors::{AppError, AppResult, FieldError},
+    metrics::{FirstTokenTimer, MetricsCollector, StreamUsageTracker, UsageRecord, create_usage_sink},
     middleware::{
-        error_handling_middleware, logging_middleware, performance_middleware,
+        api_key_fingerprint, api_key_id, check_admin_access, check_model_access, error_handling_middleware, skips_system_injection, logging_middleware, performance_middleware,
         request_id_middleware, validation_middleware,
     },
-    providers::{ProviderRegistry, anthropic::AnthropicRequest},
+    providers::{
+        AIProvider, ActiveStreams, EmbeddingRequest, HealthCheckCache, HealthStatus, IdempotencyCache, ProviderRegistry, RequestCoalescer,
+        RetryPolicy, StreamResponse, StreamSlot,
+        aggregate_stream, apply_max_tokens_policy, chat_with_retries, with_backpressure, with_heartbeat, with_sse_event_names,
+        anthropic::{AnthropicRequest, AnthropicResponse, DEFAULT_MAX_MESSAGES, ProxyMetadata, ValidationContext},
+        openai::{OpenAIRequest, OpenAIStreamConverter},
+    },
+    transforms::{RegexRedactor, Transform},
 };
 
 /// 应用程序状态 - 在所有请求处理器之间共享
@@ -33,14 +47,26 @@ use crate::{
 /// 包括配置、HTTP客户端、提供商注册表和指标收集器
 #[derive(Clone)]
 pub struct AppState {
-    /// 应用程序配置（只读共享）
-    pub config: Arc<Config>,
+    /// 应用程序配置，重新加载时整体替换
+    pub config: Arc<SharedConfig>,
     /// HTTP客户端，用于与AI提供商通信
     pub http_client: Client,
     /// 提供商注册表，管理所有AI提供商
     pub provider_registry: Arc<RwLock<ProviderRegistry>>,
     /// 指标收集器，用于系统监控
     pub metrics: Arc<MetricsCollector>,
+    /// 提供商健康检查结果缓存，避免频繁探测上游
+    pub health_cache: Arc<HealthCheckCache>,
+    /// 相同的确定性请求并发到达时合并为一次上游调用
+    pub coalescer: Arc<RequestCoalescer>,
+    /// 按`Idempotency-Key`缓存的已完成响应，客户端重试时直接重放
+    pub idempotency: Arc<IdempotencyCache>,
+    /// 按客户端提供的`x-ai-proxy-stream-id`登记的进行中流式响应，可通过`DELETE /v1/messages/stream/{id}`取消
+    pub active_streams: Arc<ActiveStreams>,
+    /// 按`[redaction]`配置在请求入站时执行的正则脱敏
+    pub redactor: Arc<RegexRedactor>,
+    /// 按注册顺序作用于聊天请求和非流式响应的转换钩子
+    pub transforms: Vec<Arc<dyn Transform>>,
 }
 
 impl AppState {
@@ -50,11 +76,12 @@ impl AppState {
     /// 根据提供的配置创建应用程序的共享状态，包括HTTP客户端和提供商注册表
     ///
     /// ## 内部实现逻辑
-    /// 1. 创建配置了连接池的HTTP客户端
+    /// 1. 通过`build_http_client`创建配置了连接池、代理和自定义CA的HTTP客户端
     /// 2. 设置30秒请求超时和连接池参数
     /// 3. 使用HTTP客户端创建提供商注册表
-    /// 4. 将所有组件包装在Arc中以支持多线程共享
-    /// 5. 返回完整的应用程序状态对象
+    /// 4. 根据`usage`配置创建token用量存储并构建指标收集器
+    /// 5. 将所有组件包装在Arc中以支持多线程共享
+    /// 6. 返回完整的应用程序状态对象
     ///
     /// ## 参数说明
     /// - `config`: 应用程序配置对象，包含服务器和提供商设置
@@ -71,12 +98,7 @@ impl AppState {
     /// - `Err(AppError)`: 创建失败，可能是HTTP客户端或提供商注册表创建失败
     pub fn new(config: Config) -> AppResult<Self> {
         // 创建带连接池的HTTP客户端
-        let http_client = Client::builder()
-            .timeout(std::time::Duration::from_secs(30)) // 30秒超时
-            .pool_max_idle_per_host(10) // 每个主机最多10个空闲连接
-            .pool_idle_timeout(std::time::Duration::from_secs(90)) // 90秒空闲超时
-            .build()
-            .map_err(|e| AppError::ConfigError(format!("Failed to create HTTP client: {}", e)))?;
+        let http_client = build_http_client_with(&config.network, None, config.performance.decompression)?;
 
         // 创建提供商注册表
         let provider_registry = Arc::new(RwLock::new(ProviderRegistry::new(
@@ -84,13 +106,183 @@ impl AppState {
             http_client.clone(),
         )?));
 
+        // 创建token用量存储
+        let usage_sink = create_usage_sink(&config.usage)?;
+
+        // 编译请求脱敏正则
+        let redactor = RegexRedactor::from_config(&config.redaction)
+            .map_err(|e| AppError::ConfigError(e.to_string()))?;
+
         Ok(Self {
-            config: Arc::new(config),                   // 配置的只读共享
-            http_client,                                // HTTP客户端
-            provider_registry,                          // 提供商注册表的线程安全共享
-            metrics: Arc::new(MetricsCollector::new()), // 指标收集器
+            config: Arc::new(SharedConfig::new(config)), // 可重新加载的共享配置
+            http_client,              // HTTP客户端
+            provider_registry,        // 提供商注册表的线程安全共享
+            metrics: Arc::new(MetricsCollector::with_usage_sink(usage_sink)), // 指标收集器
+            health_cache: Arc::new(HealthCheckCache::new()), // 健康检查缓存
+            coalescer: Arc::new(RequestCoalescer::new()),    // 请求合并
+            idempotency: Arc::new(IdempotencyCache::new()),  // 幂等响应缓存
+            active_streams: Arc::new(ActiveStreams::new()),  // 可取消的流式响应
+            redactor: Arc::new(redactor),                    // 入站请求脱敏
+            transforms: Vec::new(),                          // 默认不注册转换钩子
         })
     }
+
+    /// 注册一个请求/响应转换钩子
+    ///
+    /// ## 功能说明
+    /// 将转换器追加到转换链末尾，聊天处理器按注册顺序依次调用
+    ///
+    /// ## 参数说明
+    /// - `transform`: 要注册的转换器
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let app_state = AppState::new(config)?.with_transform(Arc::new(PiiRedactionTransform));
+    /// ```
+    ///
+    /// ## 返回值
+    /// 注册了转换器的应用程序状态
+    pub fn with_transform(mut self, transform: Arc<dyn Transform>) -> Self {
+        self.transforms.push(transform);
+        self
+    }
+
+    /// 重新加载配置文件并替换配置和提供商注册表
+    ///
+    /// ## 功能说明
+    /// 重新读取启动时使用的配置文件（`Config::config_path`，未记录时为`config.toml`），
+    /// 用新配置重建提供商注册表后与配置一起原子地替换，可在不重启的情况下轮换API密钥
+    /// （包括管理密钥和模型访问规则）、调整提供商设置或启用/禁用提供商
+    ///
+    /// ## 内部实现逻辑
+    /// 1. 加载、规范化并验证新配置；失败时直接返回，旧配置和旧注册表保持不变
+    /// 2. 按新的`[network]`配置创建HTTP客户端并构建新注册表；失败时同样保持旧状态
+    /// 3. 持有注册表写锁期间替换注册表和共享配置，之后的请求同时看到新注册表和新配置；
+    ///    进行中的请求继续使用已取得的提供商实例和配置快照
+    /// 4. 清空健康检查缓存，使新的提供商配置立即生效
+    ///
+    /// 监听地址、TLS、路由分组开关、请求体大小上限、请求脱敏和用量存储在启动时生效，仍需重启
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let providers = app_state.reload().await?;
+    /// println!("Reloaded providers: {:?}", providers);
+    /// ```
+    ///
+    /// ## 返回值
+    /// - `Ok(Vec<String>)`: 新注册表中的提供商ID（已排序）
+    /// - `Err(AppError::ValidationError)`: 新配置无效或无法构建注册表，旧状态保持不变
+    pub async fn reload(&self) -> AppResult<Vec<String>> {
+        let path = self
+            .config
+            .load()
+            .config_path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("config.toml"));
+
+        let config = load_config_from(&path).map_err(|e| {
+            AppError::ValidationError(format!("Configuration reload failed: {:#}", e))
+        })?;
+        let http_client = build_http_client_with(&config.network, None, config.performance.decompression).map_err(|e| {
+            AppError::ValidationError(format!("Configuration reload failed: {}", e))
+        })?;
+        let registry = ProviderRegistry::new(&config, http_client).map_err(|e| {
+            AppError::ValidationError(format!("Configuration reload failed: {}", e))
+        })?;
+
+        let mut provider_ids = registry.get_provider_ids();
+        provider_ids.sort();
+
+        {
+            let mut current = self.provider_registry.write().await;
+            *current = registry;
+            self.config.store(config);
+        }
+        self.health_cache.clear().await;
+
+        tracing::info!(path = %path.display(), providers = ?provider_ids, "Provider registry reloaded");
+        Ok(provider_ids)
+    }
+}
+
+/// 创建访问AI提供商的共享HTTP客户端
+///
+/// ## 功能说明
+/// 构建带连接池的`reqwest::Client`，并应用`[network]`配置中的出站代理、自定义CA证书和`User-Agent`
+///
+/// ## 内部实现逻辑
+/// 1. 设置30秒请求超时、连接池参数和`User-Agent`（所有提供商共用）
+/// 2. 如果配置了`https_proxy`，添加代理（附带`no_proxy`排除列表）
+/// 3. 如果配置了`ca_bundle_path`，将证书包中的所有证书加入信任根
+/// 4. 自动解压gzip/brotli/deflate编码的响应体
+///
+/// ## 参数说明
+/// - `network`: 出站网络配置
+///
+/// ## 执行例子
+/// ```rust
+/// let client = build_http_client(&config.network)?;
+/// ```
+///
+/// ## 返回值
+/// - `Ok(Client)`: 配置完成的HTTP客户端
+/// - `Err(AppError::ConfigError)`: 代理地址无效、CA文件缺失或无法解析
+pub fn build_http_client(network: &NetworkConfig) -> AppResult<Client> {
+    build_http_client_with(network, None, true)
+}
+
+/// 创建带连接超时的HTTP客户端
+///
+/// ## 功能说明
+/// 与`build_http_client`相同，但可以额外设置建立连接的超时，
+/// 供配置了`connect_timeout_seconds`的提供商使用独立的客户端，
+/// 并按`performance.decompression`决定是否协商和解压压缩响应
+///
+/// ## 参数说明
+/// - `network`: 出站网络配置
+/// - `connect_timeout`: 建立TCP/TLS连接的超时，None表示不单独限制
+/// - `decompression`: 为true时发送`Accept-Encoding`并自动解压gzip/brotli/deflate响应体
+///
+/// ## 执行例子
+/// ```rust
+/// let client = build_http_client_with(&config.network, Some(Duration::from_secs(5)), config.performance.decompression)?;
+/// ```
+///
+/// ## 返回值
+/// - `Ok(Client)`: 配置完成的HTTP客户端
+/// - `Err(AppError::ConfigError)`: 代理地址无效、CA文件缺失或无法解析
+pub fn build_http_client_with(
+    network: &NetworkConfig,
+    connect_timeout: Option<Duration>,
+    decompression: bool,
+) -> AppResult<Client> {
+    let mut builder = Client::builder()
+        .timeout(std::time::Duration::from_secs(30)) // 30秒超时
+        .pool_max_idle_per_host(10) // 每个主机最多10个空闲连接
+        .pool_idle_timeout(std::time::Duration::from_secs(90)) // 90秒空闲超时
+        .user_agent(network.user_agent())
+        .gzip(decompression)
+        .brotli(decompression)
+        .deflate(decompression);
+
+    if let Some(connect_timeout) = connect_timeout {
+        builder = builder.connect_timeout(connect_timeout);
+    }
+
+    if let Some(proxy) = network.proxy().map_err(|e| AppError::ConfigError(e.to_string()))? {
+        builder = builder.proxy(proxy);
+    }
+
+    for certificate in network
+        .root_certificates()
+        .map_err(|e| AppError::ConfigError(e.to_string()))?
+    {
+        builder = builder.add_root_certificate(certificate);
+    }
+
+    builder
+        .build()
+        .map_err(|e| AppError::ConfigError(format!("Failed to create HTTP client: {}", e)))
 }
 
 /// 创建主应用程序路由器，包含所有路由和中间件
@@ -100,16 +292,17 @@ impl AppState {
 ///
 /// ## 内部实现逻辑
 /// 1. 创建新的Axum路由器
-/// 2. 配置聊天完成API端点（POST /v1/messages）
+/// 2. 配置聊天完成API端点（POST /v1/messages，以及OpenAI兼容的POST /v1/chat/completions）和嵌入端点（POST /v1/embeddings）
 /// 3. 配置模型管理端点（GET /v1/models, POST /v1/models/refresh）
-/// 4. 配置健康检查端点（GET /health, GET /health/providers）
+/// 4. 配置健康检查端点（GET /health, GET /health/providers）和用量端点（GET /v1/usage）
 /// 5. 添加应用程序状态到路由器
 /// 6. 配置完整的中间件栈：
-///    - 请求ID生成和传播
+///    - 请求ID生成和传播（所有日志行都在携带`request_id`的span内）
 ///    - 结构化日志记录
 ///    - 错误处理统一化
 ///    - 请求验证
 ///    - 性能监控
+///    - 请求体大小限制（来自`server.max_request_size_bytes`，超出返回413）
 ///    - HTTP追踪
 ///    - CORS支持
 ///
@@ -118,11 +311,21 @@ impl AppState {
 ///
 /// ## 路由配置
 /// - `POST /v1/messages`: 聊天完成请求
+/// - `POST /v1/chat/completions`: OpenAI兼容的聊天完成请求
+/// - `POST /v1/messages/batch`: 批量处理多个独立的非流式聊天请求
+/// - `GET /v1/messages/ws`: WebSocket流式聊天，首帧为请求，之后逐帧返回流式事件
+/// - `POST /v1/messages/validate`: 验证聊天请求并解析路由，但不调用提供商
+/// - `DELETE /v1/messages/stream/{id}`: 取消以`x-ai-proxy-stream-id`登记的进行中流式请求
+/// - `POST /v1/embeddings`: 文本嵌入请求（OpenAI兼容格式）
 /// - `GET /v1/models`: 获取可用模型列表
 /// - `POST /v1/models/refresh`: 刷新模型列表
 /// - `GET /health`: 系统健康检查
 /// - `GET /health/providers`: 提供商健康检查
+/// - `GET /info`: 版本、运行时长、已启用的提供商和功能开关
 /// - `GET /metrics`: 系统指标和统计
+/// - `GET /v1/usage`: 按API密钥、提供商和模型聚合的token用量及费用
+/// - `POST /admin/reload`: 重新加载配置并替换提供商注册表（需要管理密钥）
+/// - `POST /admin/providers/{key}/enable|disable`: 运行时启用或停用提供商（需要管理密钥）
 ///
 /// ## 执行例子
 /// ```rust
@@ -131,17 +334,56 @@ impl AppState {
 /// // app现在可以用于启动HTTP服务器
 /// ```
 pub fn create_app(state: AppState) -> Router {
-    Router::new()
-        // 聊天完成端点
-        .route("/v1/messages", post(chat_handler))
-        // 模型管理端点
-        .route("/v1/models", get(list_models_handler))
-        .route("/v1/models/refresh", post(refresh_models_handler))
-        // 健康检查端点
-        .route("/health", get(health_handler))
-        .route("/health/providers", get(health_providers_handler))
-        // 指标端点
-        .route("/metrics", get(metrics_handler))
+    // 只注册`routes`配置中启用的路由分组，关闭的分组返回404
+    let routes = &state.config.load().routes;
+    let mut router = Router::new();
+    // 聊天完成端点
+    if routes.chat {
+        router = router
+            .route("/v1/messages", post(chat_handler))
+            .route("/v1/chat/completions", post(openai_chat_handler))
+            .route("/v1/messages/batch", post(batch_chat_handler))
+            .route("/v1/messages/ws", get(ws_chat_handler))
+            .route("/v1/messages/stream/{id}", delete(cancel_stream_handler))
+            .route("/v1/messages/validate", post(validate_chat_handler));
+    }
+    // 嵌入端点
+    if routes.embeddings {
+        router = router.route("/v1/embeddings", post(embeddings_handler));
+    }
+    // 模型管理端点
+    if routes.models {
+        router = router
+            .route("/v1/models", get(list_models_handler))
+            .route("/v1/models/refresh", post(refresh_models_handler))
+            .route("/v1/capabilities", get(capabilities_handler));
+    }
+    // 健康检查端点
+    if routes.health {
+        router = router
+            .route("/health", get(health_handler))
+            .route("/health/live", get(health_handler))
+            .route("/health/ready", get(readiness_handler))
+            .route("/health/providers", get(health_providers_handler));
+    }
+    if routes.info {
+        router = router.route("/info", get(info_handler));
+    }
+    // 指标端点
+    if routes.metrics {
+        router = router
+            .route("/metrics", get(metrics_handler))
+            .route("/v1/usage", get(usage_handler));
+    }
+    // 管理端点（需要security.admin_api_key）
+    if routes.admin {
+        router = router
+            .route("/admin/reload", post(admin_reload_handler))
+            .route("/admin/providers/{key}/enable", post(admin_enable_provider_handler))
+            .route("/admin/providers/{key}/disable", post(admin_disable_provider_handler));
+    }
+
+    router
         // 添加共享状态
         .with_state(state.clone())
         // 添加路由级中间件（需要访问状态）
@@ -155,7 +397,10 @@ pub fn create_app(state: AppState) -> Router {
         ))
         .route_layer(middleware::from_fn(validation_middleware))
         .route_layer(middleware::from_fn(error_handling_middleware))
-        .route_layer(middleware::from_fn(request_id_middleware))
+        // 请求体大小限制，仅作用于请求体提取，不影响流式响应
+        .layer(DefaultBodyLimit::max(state.config.load().server.max_request_size_bytes))
+        // 请求ID作为全局层，未匹配路由的响应同样携带`x-request-id`
+        .layer(middleware::from_fn(request_id_middleware))
         // 添加全局中间件层
         .layer(CorsLayer::permissive())
         .layer(
@@ -177,7 +422,7 @@ pub fn create_app(state: AppState) -> Router {
 /// 3. 绑定TCP监听器到指定地址
 /// 4. 记录服务器启动信息和可用端点
 /// 5. 启动Axum服务器并等待请求
-/// 6. 支持优雅关闭和错误恢复
+/// 6. 收到SIGINT/SIGTERM后停止接受新连接，并在`server.shutdown_drain_seconds`内等待进行中的请求完成
 ///
 /// ## 参数说明
 /// - `config`: 服务器配置，包含主机地址、端口等信息
@@ -205,9 +450,18 @@ pub async fn start_server(config: Config) -> AppResult<()> {
             .len(),
         "Application state initialized"
     );
+    log_startup_summary(&app_state).await;
 
-    // 创建路由器
-    let app = create_app(app_state);
+    // 启动前预检提供商健康状态（fail_fast时可能拒绝启动）
+    if config.server.startup_health_check || config.server.fail_fast_on_unhealthy {
+        preflight_health_check(&app_state).await?;
+    }
+
+    // 加载TLS证书（未配置时使用普通HTTP）
+    let tls = config
+        .server
+        .tls_config()
+        .map_err(|e| AppError::ConfigError(e.to_string()))?;
 
     // 创建TCP监听器
     let addr = format!("{}:{}", config.server.host, config.server.port);
@@ -218,16 +472,34 @@ pub async fn start_server(config: Config) -> AppResult<()> {
     // 记录服务器启动信息
     tracing::info!(
         address = %addr,
+        scheme = if tls.is_some() { "https" } else { "http" },
         "AI Proxy server starting"
     );
 
     tracing::info!("Available endpoints:");
     tracing::info!("  POST /v1/messages - Chat completion with streaming support");
+    tracing::info!("  POST /v1/chat/completions - OpenAI-compatible chat completion");
+    tracing::info!("  POST /v1/messages/batch - Run several chat requests in one call");
+    tracing::info!("  GET  /v1/messages/ws - Chat completion over a WebSocket");
+    tracing::info!("  POST /v1/messages/validate - Validate a chat request without dispatching it");
+    tracing::info!("  DELETE /v1/messages/stream/{{id}} - Cancel an in-flight stream");
+    tracing::info!("  POST /v1/embeddings - Text embeddings");
     tracing::info!("  GET  /v1/models - List available models from all providers");
     tracing::info!("  POST /v1/models/refresh - Refresh models from providers");
+    tracing::info!("  GET  /v1/capabilities - What each configured provider supports");
     tracing::info!("  GET  /health - System health check");
+    tracing::info!("  GET  /health/live - Liveness probe");
+    tracing::info!("  GET  /health/ready - Readiness probe, 503 until a provider reports healthy");
     tracing::info!("  GET  /health/providers - Provider health check");
+    tracing::info!("  GET  /info - Version, uptime, enabled providers and feature flags");
     tracing::info!("  GET  /metrics - System metrics and statistics");
+    tracing::info!("  GET  /v1/usage - Aggregated token usage per key, provider and model");
+    tracing::info!("  POST /admin/reload - Reload configuration and rebuild providers");
+    tracing::info!("  POST /admin/providers/{{key}}/enable|disable - Switch a provider on or off at runtime");
+    let disabled_routes = config.routes.disabled_groups();
+    if !disabled_routes.is_empty() {
+        tracing::info!("Disabled route groups (404): {}", disabled_routes.join(", "));
+    }
 
     tracing::info!("Middleware stack configured:");
     tracing::info!("  - Request ID generation and propagation");
@@ -239,17 +511,246 @@ pub async fn start_server(config: Config) -> AppResult<()> {
 
     // 启动服务器，支持优雅关闭
     tracing::info!("Server ready to accept connections");
-    
-    // 使用 axum::serve 的优雅关闭功能
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await
-        .map_err(|e| AppError::InternalServerError(format!("Server error: {}", e)))?;
+
+    // SIGHUP重新加载配置（与/admin/reload相同），SIGINT/SIGTERM仍触发优雅关闭
+    #[cfg(unix)]
+    spawn_sighup_reload(app_state.clone());
+
+    let drain_timeout = Duration::from_secs(config.server.shutdown_drain_seconds);
+    match tls {
+        Some(tls) => {
+            let listener = TlsListener::new(listener, tls)
+                .map_err(|e| AppError::ConfigError(format!("Failed to start TLS listener on {}: {}", addr, e)))?;
+            serve_with_graceful_shutdown(listener, app_state, shutdown_signal(), drain_timeout).await?;
+        }
+        None => serve_with_graceful_shutdown(listener, app_state, shutdown_signal(), drain_timeout).await?,
+    }
 
     tracing::info!("Server shutdown completed");
     Ok(())
 }
 
+/// Longest a client may take to complete the TLS handshake
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Listener that terminates TLS before handing connections to axum
+///
+/// Handshakes run in their own tasks so a slow or failing client cannot block
+/// other connections; failed handshakes are logged and dropped.
+struct TlsListener {
+    connections: tokio::sync::mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    local_addr: SocketAddr,
+}
+
+impl TlsListener {
+    fn new(mut listener: TcpListener, tls: Arc<rustls::ServerConfig>) -> std::io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let acceptor = TlsAcceptor::from(tls);
+        let (tx, connections) = tokio::sync::mpsc::channel(64);
+
+        tokio::spawn(async move {
+            loop {
+                let (stream, peer) = tokio::select! {
+                    accepted = axum::serve::Listener::accept(&mut listener) => accepted,
+                    // The server has shut down and dropped the listener
+                    _ = tx.closed() => break,
+                };
+                let acceptor = acceptor.clone();
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                        Ok(Ok(stream)) => {
+                            let _ = tx.send((stream, peer)).await;
+                        }
+                        Ok(Err(e)) => tracing::debug!("TLS handshake with {} failed: {}", peer, e),
+                        Err(_) => tracing::debug!("TLS handshake with {} timed out", peer),
+                    }
+                });
+            }
+        });
+
+        Ok(Self { connections, local_addr })
+    }
+}
+
+impl axum::serve::Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.connections.recv().await {
+            Some(connection) => connection,
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+/// 启动前对所有启用的提供商执行健康检查
+///
+/// ## 功能说明
+/// 在开始接受流量之前并发调用每个启用提供商的`health_check`并记录结果，
+/// 以便配置错误在启动日志中即可发现，而不是等到第一个真实请求失败
+///
+/// ## 内部实现逻辑
+/// 1. 从配置中筛选启用且已注册的提供商
+/// 2. 并发执行健康检查，记录每个提供商的状态和延迟
+/// 3. 仅当`server.fail_fast_on_unhealthy`开启且所有提供商都不健康时返回错误；
+///    部分提供商不健康只记录警告，不阻止启动
+///
+/// ## 参数说明
+/// - `state`: 应用程序状态，包含配置和提供商注册表
+///
+/// ## 执行例子
+/// ```rust
+/// let app_state = AppState::new(config)?;
+/// preflight_health_check(&app_state).await?;
+/// ```
+///
+/// ## 返回值
+/// - `Ok(())`: 预检完成（或存在部分健康的提供商）
+/// - `Err(AppError::ConfigError)`: 开启fail_fast且所有启用的提供商均不健康
+pub async fn preflight_health_check(state: &AppState) -> AppResult<()> {
+    let config = state.config.load();
+    let mut provider_ids: Vec<&String> = config
+        .providers
+        .iter()
+        .filter(|(_, provider)| provider.enabled)
+        .map(|(id, _)| id)
+        .collect();
+    provider_ids.sort();
+
+    let results: Vec<(&String, HealthStatus)> = {
+        let registry = state.provider_registry.read().await;
+        let checks = provider_ids.iter().map(|id| registry.health_check_provider(id));
+        let statuses = futures::future::join_all(checks).await;
+        provider_ids
+            .into_iter()
+            .zip(statuses)
+            .filter_map(|(id, status)| status.map(|status| (id, status)))
+            .collect()
+    };
+
+    for (provider_id, health) in &results {
+        if health.status == "healthy" {
+            tracing::info!(
+                provider = %provider_id,
+                latency_ms = ?health.latency_ms,
+                "Startup health check passed"
+            );
+        } else {
+            tracing::warn!(
+                provider = %provider_id,
+                status = %health.status,
+                error = ?health.error,
+                "Startup health check failed"
+            );
+        }
+    }
+
+    let healthy = results.iter().filter(|(_, health)| health.status == "healthy").count();
+    tracing::info!(healthy, total = results.len(), "Startup health check completed");
+
+    if state.config.load().server.fail_fast_on_unhealthy && !results.is_empty() && healthy == 0 {
+        let failures: Vec<String> = results
+            .iter()
+            .map(|(provider_id, health)| {
+                format!(
+                    "{} ({})",
+                    provider_id,
+                    health.error.as_deref().unwrap_or(&health.status)
+                )
+            })
+            .collect();
+        return Err(AppError::ConfigError(format!(
+            "All enabled providers failed the startup health check: {}",
+            failures.join(", ")
+        )));
+    }
+
+    Ok(())
+}
+
+/// 在给定监听器上运行服务器，并在关闭信号到来后排空进行中的请求
+///
+/// ## 功能说明
+/// 使用axum的`with_graceful_shutdown`：收到关闭信号后停止接受新连接，
+/// 让已开始的请求继续完成；若排空时间耗尽仍有请求未完成，则记录剩余请求数并直接返回
+///
+/// ## 内部实现逻辑
+/// 1. 使用应用程序状态创建路由器
+/// 2. 关闭信号触发时记录当前活跃请求数并启动排空计时器
+/// 3. 服务器在所有连接关闭后正常返回
+/// 4. 排空计时器先到期时记录仍在处理的请求数，放弃剩余请求
+///
+/// ## 参数说明
+/// - `listener`: 已绑定的监听器（TCP监听器，或启用TLS时的TLS监听器）
+/// - `state`: 应用程序状态
+/// - `signal`: 关闭信号，完成时开始优雅关闭
+/// - `drain_timeout`: 等待进行中请求完成的最长时间
+///
+/// ## 执行例子
+/// ```rust
+/// let listener = TcpListener::bind("127.0.0.1:3000").await?;
+/// serve_with_graceful_shutdown(listener, app_state, shutdown_signal(), Duration::from_secs(30)).await?;
+/// ```
+///
+/// ## 返回值
+/// - `Ok(())`: 服务器已关闭（请求全部完成或排空超时）
+/// - `Err(AppError)`: 服务器运行失败
+pub async fn serve_with_graceful_shutdown<L, F>(
+    listener: L,
+    state: AppState,
+    signal: F,
+    drain_timeout: Duration,
+) -> AppResult<()>
+where
+    L: axum::serve::Listener,
+    L::Addr: std::fmt::Debug,
+    F: std::future::Future<Output = ()> + Send + 'static,
+{
+    let metrics = state.metrics.clone();
+    let app = create_app(state);
+
+    // Starts the drain timer once the shutdown signal has fired
+    let (drain_tx, drain_rx) = tokio::sync::oneshot::channel::<()>();
+    let signal_metrics = metrics.clone();
+    let server = axum::serve(listener, app).with_graceful_shutdown(async move {
+        signal.await;
+        tracing::info!(
+            active_requests = signal_metrics.get_concurrent_requests(),
+            drain_seconds = drain_timeout.as_secs(),
+            "Shutdown signal received, draining in-flight requests"
+        );
+        let _ = drain_tx.send(());
+    });
+
+    let drain_timer = async move {
+        match drain_rx.await {
+            Ok(()) => tokio::time::sleep(drain_timeout).await,
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+
+    tokio::select! {
+        result = server => {
+            result.map_err(|e| AppError::InternalServerError(format!("Server error: {}", e)))?;
+        }
+        _ = drain_timer => {
+            tracing::warn!(
+                active_requests = metrics.get_concurrent_requests(),
+                drain_seconds = drain_timeout.as_secs(),
+                "Shutdown drain timeout elapsed, abandoning in-flight requests"
+            );
+        }
+    }
+
+    Ok(())
+}
+
 /// 优雅关闭信号处理
 /// 
 /// 监听系统信号，支持优雅关闭服务器
@@ -281,57 +782,227 @@ async fn shutdown_signal() {
     }
 }
 
+/// 处理一次配置重新加载信号
+///
+/// ## 功能说明
+/// 执行与`POST /admin/reload`相同的安全重新加载（`AppState::reload`），只记录结果而不返回错误，
+/// 新配置无效时保留当前配置和提供商继续运行。`SIGHUP`处理器调用此函数，测试也可以直接调用
+///
+/// ## 内部实现逻辑
+/// 1. 调用`AppState::reload`重新读取配置文件并替换共享配置和提供商注册表
+/// 2. 成功时记录新的提供商列表，失败时记录警告，进程不退出
+///
+/// ## 参数说明
+/// - `state`: 应用程序状态
+///
+/// ## 执行例子
+/// ```rust
+/// if reload_from_signal(&app_state).await {
+///     println!("Configuration reloaded");
+/// }
+/// ```
+///
+/// ## 返回值
+/// - `true`: 配置和注册表已替换为新配置
+/// - `false`: 新配置无效，当前配置和注册表保持不变
+pub async fn reload_from_signal(state: &AppState) -> bool {
+    match state.reload().await {
+        Ok(providers) => {
+            tracing::info!(providers = ?providers, "Configuration reloaded on SIGHUP");
+            true
+        }
+        Err(e) => {
+            tracing::warn!("Configuration reload on SIGHUP rejected, keeping current providers: {}", e);
+            false
+        }
+    }
+}
+
+/// Reload the configuration every time the process receives SIGHUP
+#[cfg(unix)]
+fn spawn_sighup_reload(state: AppState) {
+    let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+        Ok(signal) => signal,
+        Err(e) => {
+            tracing::warn!("Failed to install SIGHUP handler, config reload via signal disabled: {}", e);
+            return;
+        }
+    };
+
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            tracing::info!("Received SIGHUP, reloading configuration");
+            reload_from_signal(&state).await;
+        }
+    });
+}
+
 // Request Handlers
 
+/// Header that lets a client override the upstream timeout for a single request
+const TIMEOUT_OVERRIDE_HEADER: &str = "x-ai-proxy-timeout";
+
+/// Header asking for the upstream stream to be reassembled into a single JSON response
+const AGGREGATE_STREAM_HEADER: &str = "x-ai-proxy-aggregate-stream";
+
+/// Check the raw body against the request schema, then deserialize it
+///
+/// Reports every missing or mistyped field at once as `InvalidFields` rather
+/// than the first serde error.
+fn parse_chat_request(body: Value) -> AppResult<AnthropicRequest> {
+    let errors = AnthropicRequest::schema_errors(&body);
+    if !errors.is_empty() {
+        return Err(AppError::InvalidFields(errors));
+    }
+    serde_json::from_value(body)
+        .map_err(|e| AppError::BadRequest(format!("Invalid request body: {}", e)))
+}
+
+/// Wire format of a chat request body posted to `/v1/messages`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RequestFormat {
+    Anthropic,
+    OpenAI,
+}
+
+/// Top-level fields only the Anthropic Messages API accepts
+const ANTHROPIC_ONLY_FIELDS: &[&str] = &["system", "stop_sequences", "top_k", "thinking_budget"];
+
+/// Top-level fields only the OpenAI Chat Completions API accepts
+const OPENAI_ONLY_FIELDS: &[&str] = &[
+    "frequency_penalty",
+    "presence_penalty",
+    "stop",
+    "max_completion_tokens",
+    "logit_bias",
+];
+
+/// Guess whether a chat body is Anthropic- or OpenAI-shaped from fields only one API accepts
+///
+/// Bodies valid in both formats (e.g. only `model`, `messages` and `max_tokens`) are
+/// treated as Anthropic, the route's native format. Bodies that mix markers of both
+/// formats are rejected rather than guessed.
+fn detect_request_format(body: &Value) -> AppResult<RequestFormat> {
+    let present = |fields: &[&'static str]| -> Vec<&'static str> {
+        fields.iter().copied().filter(|field| body.get(*field).is_some()).collect()
+    };
+    let mut anthropic = present(ANTHROPIC_ONLY_FIELDS);
+    let mut openai = present(OPENAI_ONLY_FIELDS);
+
+    let mut roles = body["messages"].as_array().into_iter().flatten().filter_map(|m| m["role"].as_str());
+    if roles.any(|role| matches!(role, "system" | "developer" | "tool")) {
+        openai.push("messages[].role");
+    }
+    for tool in body["tools"].as_array().into_iter().flatten() {
+        if tool.get("input_schema").is_some() && !anthropic.contains(&"tools") {
+            anthropic.push("tools");
+        }
+        if tool.get("function").is_some() && !openai.contains(&"tools") {
+            openai.push("tools");
+        }
+    }
+    match &body["tool_choice"] {
+        Value::String(_) => openai.push("tool_choice"),
+        Value::Object(choice) => match choice.get("type").and_then(Value::as_str) {
+            Some("function") => openai.push("tool_choice"),
+            Some("auto" | "any" | "tool" | "none") => anthropic.push("tool_choice"),
+            _ => {}
+        },
+        _ => {}
+    }
+
+    match (anthropic.is_empty(), openai.is_empty()) {
+        (_, true) => Ok(RequestFormat::Anthropic),
+        (true, false) => Ok(RequestFormat::OpenAI),
+        (false, false) => Err(AppError::BadRequest(format!(
+            "Ambiguous request format: body mixes Anthropic fields ({}) and OpenAI fields ({}); \
+             send OpenAI requests to /v1/chat/completions",
+            anthropic.join(", "),
+            openai.join(", ")
+        ))),
+    }
+}
+
 /// Handle chat completion requests
+///
+/// With `server.auto_detect_format` enabled, OpenAI-shaped bodies are handed to
+/// the OpenAI-compatible handler and answered in OpenAI's format.
 async fn chat_handler(
     State(state): State<AppState>,
-    Json(request): Json<AnthropicRequest>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
 ) -> AppResult<axum::response::Response> {
     use axum::body::Body;
     use axum::response::{IntoResponse, Response};
 
+    if state.config.load().server.auto_detect_format && detect_request_format(&body)? == RequestFormat::OpenAI {
+        tracing::debug!("Detected an OpenAI-shaped body on /v1/messages");
+        let openai_request: OpenAIRequest = serde_json::from_value(body)
+            .map_err(|e| AppError::BadRequest(format!("Invalid request body: {}", e)))?;
+        // Redacted as /v1/messages traffic, the route the client actually called
+        return openai_chat(state, headers, openai_request, RedactionRoute::Messages).await;
+    }
+
+    let request = parse_chat_request(body)?;
+    let mode = chat_mode(&request, &headers)?;
+    let PreparedChat { mut request, original_model, .. } =
+        prepare_chat(&state, &headers, RedactionRoute::Messages, request).await?;
+
     // Record request start time for metrics
     let start_time = state.metrics.record_request_start();
 
-    tracing::info!("Processing chat request for model: {}", request.model);
+    tracing::info!(user = request.user.as_deref(), "Processing chat request for model: {}", request.model);
 
     // Extract provider name from model for metrics
-    let provider_name = if request.model.starts_with("gpt") || request.model.starts_with("openai") {
-        "openai"
-    } else if request.model.starts_with("gemini") {
-        "gemini"
-    } else if request.model.starts_with("claude") || request.model.starts_with("anthropic") {
-        "anthropic"
-    } else {
-        "unknown"
-    };
+    let provider_name = provider_name_for_metrics(&request.model);
+    let key_id = api_key_id(&headers);
 
-    // Get provider for the requested model
-    let provider_result = {
-        let registry = state.provider_registry.read().await;
-        registry.get_provider_for_model(&request.model)
-    };
+    // A repeated Idempotency-Key replays the stored response instead of calling the provider again
+    let idempotency_key = idempotency_key(&state, &headers).filter(|_| mode != ChatMode::Stream);
+    if let Some(key) = &idempotency_key
+        && let Some(response) = state.idempotency.get(key)
+    {
+        tracing::info!("Replaying stored response for a repeated idempotency key");
+        state
+            .metrics
+            .record_request_end(start_time, true, provider_name, &request.model)
+            .await;
+        let mut http_response = Json(serde_json::to_value(response).unwrap()).into_response();
+        http_response
+            .headers_mut()
+            .insert(IDEMPOTENT_REPLAY_HEADER, HeaderValue::from_static("true"));
+        return Ok(http_response);
+    }
 
-    let provider = match provider_result {
-        Ok(p) => p,
+    // Get provider for the requested model
+    let RoutedChat { provider, provider_id } = match route_chat(&state, &mut request).await {
+        Ok(routed) => routed,
         Err(e) => {
             // Record failed request
             state
                 .metrics
                 .record_request_end(start_time, false, provider_name, &request.model)
                 .await;
+            log_chat_exchange(&state.config.load().logging, &request, Err(&e));
             return Err(e);
         }
     };
+    let provider_id = provider_id.unwrap_or_else(|| provider_name.to_string());
 
     // Handle streaming vs non-streaming
-    let result = if request.stream.unwrap_or(false) {
+    let result = if mode == ChatMode::Stream {
         tracing::info!("Processing streaming chat request");
 
         // Get streaming response
-        match provider.chat_stream(request.clone()).await {
+        match open_chat_stream(&state, &headers, provider, &request).await {
             Ok(stream) => {
+                // Record usage from message_start/message_delta once the stream ends
+                let stream = track_chat_stream(&state, key_id, &request.model, start_time, stream);
+
+                // Frame every event the same way, then keep idle connections alive
+                let stream = with_sse_event_names(stream, state.config.load().server.sse_event_names);
+                let stream = with_heartbeat(stream, heartbeat_interval(&state));
+
                 // Convert stream to HTTP response body
                 let body = Body::from_stream(stream);
 
@@ -352,16 +1023,38 @@ async fn chat_handler(
                     })?;
 
                 tracing::info!("Streaming chat request initialized successfully");
+                log_chat_exchange(&state.config.load().logging, &request, Ok(None));
                 Ok(response)
             }
             Err(e) => Err(e),
         }
     } else {
-        // Process non-streaming request
-        match provider.chat(request.clone()).await {
-            Ok(response) => {
+        // Process non-streaming request, optionally reassembled from an upstream stream
+        let outcome = if mode == ChatMode::Aggregate {
+            dispatch_aggregated_chat(&state, provider, &request).await
+        } else {
+            dispatch_chat(&state, provider, &request).await
+        };
+        match outcome {
+            Ok(mut response) => {
                 tracing::info!("Chat request completed successfully");
-                Ok(Json(serde_json::to_value(response).unwrap()).into_response())
+                response.x_ai_proxy = Some(proxy_metadata(&state, &provider_id, &request, &response, start_time));
+                if let Some(model) = original_model {
+                    response.model = model;
+                }
+                log_chat_exchange(&state.config.load().logging, &request, Ok(Some(&response)));
+                record_chat_usage(&state, key_id, &request, &response, start_time);
+                let cost_header = cost_header(&state, &request.model, &response);
+                if let Some(key) = idempotency_key {
+                    let ttl = Duration::from_secs(state.config.load().server.idempotency_ttl_seconds);
+                    state.idempotency.insert(key, response.clone(), ttl);
+                }
+                let mut http_response = Json(serde_json::to_value(&response).unwrap()).into_response();
+                attach_upstream_headers(&mut http_response, &response);
+                if let Some(value) = cost_header {
+                    http_response.headers_mut().insert(COST_HEADER, value);
+                }
+                Ok(http_response)
             }
             Err(e) => Err(e),
         }
@@ -373,42 +1066,963 @@ async fn chat_handler(
         .metrics
         .record_request_end(start_time, success, provider_name, &request.model)
         .await;
+    if let Err(e) = &result {
+        log_chat_exchange(&state.config.load().logging, &request, Err(e));
+    }
 
     result
 }
 
-/// Handle model listing requests
-async fn list_models_handler(State(state): State<AppState>) -> AppResult<Json<Value>> {
-    tracing::info!("Processing models list request");
+/// Handle OpenAI-compatible chat completion requests
+///
+/// The request is converted into the unified Anthropic format, dispatched
+/// through the registry, and the result is converted back to OpenAI's shape.
+async fn openai_chat_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(openai_request): Json<OpenAIRequest>,
+) -> AppResult<axum::response::Response> {
+    openai_chat(state, headers, openai_request, RedactionRoute::ChatCompletions).await
+}
 
-    let models = {
-        let registry = state.provider_registry.read().await;
-        registry.list_all_models().await?
-    };
+/// Serve an OpenAI-shaped chat request that arrived on `route`
+///
+/// `route` selects the redaction settings, so OpenAI bodies auto-detected on
+/// `/v1/messages` follow that route's redaction rather than `/v1/chat/completions`'.
+async fn openai_chat(
+    state: AppState,
+    headers: HeaderMap,
+    openai_request: OpenAIRequest,
+    route: RedactionRoute,
+) -> AppResult<axum::response::Response> {
+    use axum::body::Body;
+    use axum::response::{IntoResponse, Response};
+    use futures::StreamExt;
 
-    let response = json!({
-        "object": "list",
-        "data": models
-    });
+    let request = openai_request.to_anthropic()?;
+    let PreparedChat { mut request, original_model, .. } = prepare_chat(&state, &headers, route, request).await?;
+
+    // Record request start time for metrics
+    let start_time = state.metrics.record_request_start();
 
     tracing::info!(
-        "Models list request completed, {} models available",
-        models.len()
+        user = request.user.as_deref(),
+        "Processing OpenAI-compatible chat request for model: {}",
+        request.model
     );
-    Ok(Json(response))
-}
-
-/// Handle model refresh requests
-async fn refresh_models_handler(State(state): State<AppState>) -> AppResult<Json<Value>> {
-    tracing::info!("Processing models refresh request");
 
-    // Refresh models from all providers
-    {
-        let mut registry = state.provider_registry.write().await;
-        registry.refresh_models().await?;
-    }
+    let provider_name = provider_name_for_metrics(&request.model);
+    let key_id = api_key_id(&headers);
 
-    // Get updated model statistics
+    // Get provider for the requested model
+    let provider = match route_chat(&state, &mut request).await {
+        Ok(routed) => routed.provider,
+        Err(e) => {
+            state
+                .metrics
+                .record_request_end(start_time, false, provider_name, &request.model)
+                .await;
+            log_chat_exchange(&state.config.load().logging, &request, Err(&e));
+            return Err(e);
+        }
+    };
+
+    let result = if request.is_streaming() {
+        match open_chat_stream(&state, &headers, provider, &request).await {
+            Ok(stream) => {
+                // Re-frame Anthropic SSE events as OpenAI chunks, recording usage on the way
+                let stream = track_chat_stream(&state, key_id, &request.model, start_time, stream);
+                log_chat_exchange(&state.config.load().logging, &request, Ok(None));
+                let mut converter = OpenAIStreamConverter::new(request.model.clone());
+                let stream = stream
+                    .map(move |chunk| chunk.map(|text| converter.push(&text)))
+                    .filter(|chunk| {
+                        futures::future::ready(!matches!(chunk, Ok(text) if text.is_empty()))
+                    });
+                let stream = with_heartbeat(Box::pin(stream), heartbeat_interval(&state));
+
+                Response::builder()
+                    .status(200)
+                    .header("Content-Type", "text/event-stream")
+                    .header("Cache-Control", "no-cache")
+                    .header("Connection", "keep-alive")
+                    .body(Body::from_stream(stream))
+                    .map_err(|e| {
+                        AppError::InternalServerError(format!(
+                            "Failed to create streaming response: {}",
+                            e
+                        ))
+                    })
+            }
+            Err(e) => Err(e),
+        }
+    } else {
+        match dispatch_chat(&state, provider, &request).await {
+            Ok(mut response) => {
+                if let Some(model) = original_model {
+                    response.model = model;
+                }
+                log_chat_exchange(&state.config.load().logging, &request, Ok(Some(&response)));
+                record_chat_usage(&state, key_id, &request, &response, start_time);
+                // Multiple completions come back as one content block per candidate
+                let openai_response = if request.n.unwrap_or(1) > 1 {
+                    response.to_openai_choices()
+                } else {
+                    response.to_openai()
+                };
+                let mut http_response = Json(openai_response).into_response();
+                attach_upstream_headers(&mut http_response, &response);
+                if let Some(value) = cost_header(&state, &request.model, &response) {
+                    http_response.headers_mut().insert(COST_HEADER, value);
+                }
+                Ok(http_response)
+            }
+            Err(e) => Err(e),
+        }
+    };
+
+    // Record request completion
+    let success = result.is_ok();
+    state
+        .metrics
+        .record_request_end(start_time, success, provider_name, &request.model)
+        .await;
+    if let Err(e) = &result {
+        log_chat_exchange(&state.config.load().logging, &request, Err(e));
+    }
+
+    result
+}
+
+/// A chat request that passed the intake steps shared by every chat route
+struct PreparedChat {
+    request: AnthropicRequest,
+    /// Model name as the client sent it, when normalization changed it
+    original_model: Option<String>,
+    /// Adjustments made to the request, reported by `/v1/messages/validate`
+    warnings: Vec<String>,
+}
+
+/// Run a parsed chat request through the intake steps shared by every chat route
+///
+/// Redacts for the originating `route`, applies the `x-ai-proxy-timeout`
+/// override, normalizes the model, fills in the validation context and sampling
+/// defaults, drops unsupported parameters, enforces model access, injects the
+/// configured system text and runs the request transforms, in that order.
+async fn prepare_chat(
+    state: &AppState,
+    headers: &HeaderMap,
+    route: RedactionRoute,
+    mut request: AnthropicRequest,
+) -> AppResult<PreparedChat> {
+    // Redact before anything logs the request or converts it for a provider
+    state.redactor.redact_request(route, &mut request);
+    // One snapshot for the whole intake, even if a reload lands meanwhile
+    let config = &*state.config.load();
+    request.timeout_override =
+        parse_timeout_override(headers, config.server.max_request_timeout_seconds)?;
+
+    let mut warnings = Vec::new();
+    let original_model = normalize_model(config, &mut request);
+    if let Some(original) = &original_model {
+        warnings.push(format!("model '{}' is routed as '{}'", original, request.model));
+    }
+    request.validation = ValidationContext {
+        strict_role_alternation: config.security.strict_role_alternation,
+        max_input_tokens: config.max_input_tokens_for(&request.model),
+        mode: config.security.validation_mode,
+        ..Default::default()
+    };
+    request.apply_sampling_defaults(config.performance.default_temperature, config.performance.default_top_p);
+    let sampling = (request.temperature, request.top_p);
+    request.clamp_sampling_params();
+    if (request.temperature, request.top_p) != sampling {
+        warnings.push("out-of-range sampling parameters will be clamped".to_string());
+    }
+    let stripped = request.strip_params(config.unsupported_params_for(&request.model));
+    if !stripped.is_empty() {
+        tracing::debug!(
+            "Stripped unsupported parameters for model {}: {}",
+            request.model,
+            stripped.join(", ")
+        );
+        warnings.push(format!("unsupported parameters will be dropped: {}", stripped.join(", ")));
+    }
+    check_model_access(&config.security, headers, &request.model)?;
+    inject_system_text(config, headers, &mut request);
+    for transform in &state.transforms {
+        transform.on_request(&mut request).await;
+    }
+
+    Ok(PreparedChat {
+        request,
+        original_model,
+        warnings,
+    })
+}
+
+/// Provider a prepared chat request is dispatched to
+struct RoutedChat {
+    provider: Arc<dyn AIProvider + Send + Sync>,
+    /// Configured ID of the provider, when the model maps to one
+    provider_id: Option<String>,
+}
+
+/// Look up the provider for a request, recording a model fallback when `record_fallback` is set
+///
+/// Returns the provider ID alongside, even when no provider can serve the request.
+async fn resolve_provider(
+    state: &AppState,
+    request: &AnthropicRequest,
+    record_fallback: bool,
+) -> (AppResult<Arc<dyn AIProvider + Send + Sync>>, Option<String>) {
+    let registry = state.provider_registry.read().await;
+    let provider_id = registry.provider_id_for_model(&request.model).map(str::to_string);
+    if record_fallback && let Some((from, to)) = registry.fallback_for_model(&request.model) {
+        state.metrics.record_fallback(from, to);
+    }
+    (registry.get_provider_for_request(request), provider_id)
+}
+
+/// Route a prepared request to the provider that will serve it
+///
+/// Records a model fallback in the metrics and enforces the routed provider's
+/// message limit.
+async fn route_chat(state: &AppState, request: &mut AnthropicRequest) -> AppResult<RoutedChat> {
+    let (provider, provider_id) = resolve_provider(state, request, true).await;
+    let provider = provider?;
+    apply_message_limit(&state.config.load(), provider_id.as_deref(), request)?;
+    Ok(RoutedChat { provider, provider_id })
+}
+
+/// Start the provider stream, claiming the client's `x-ai-proxy-stream-id` first
+///
+/// The ID is claimed before the upstream call so a duplicate never reaches the provider.
+async fn open_chat_stream(
+    state: &AppState,
+    headers: &HeaderMap,
+    provider: Arc<dyn AIProvider + Send + Sync>,
+    request: &AnthropicRequest,
+) -> AppResult<StreamResponse> {
+    let slot = reserve_stream_id(state, headers)?;
+    let stream = provider.chat_stream(request.clone()).await?;
+    let stream = with_backpressure(stream, state.config.load().performance.stream_buffer_size);
+    Ok(match slot {
+        Some(slot) => slot.attach(stream),
+        None => stream,
+    })
+}
+
+/// Record usage and first-token latency as the events of a chat stream pass through
+///
+/// Usage is recorded (and logged with `logging.log_usage`) once the stream ends
+/// or the client goes away; the stream also counts as an active upstream stream.
+fn track_chat_stream(
+    state: &AppState,
+    key_id: String,
+    model: &str,
+    start_time: std::time::Instant,
+    stream: StreamResponse,
+) -> StreamResponse {
+    use futures::StreamExt;
+
+    let provider_name = provider_name_for_metrics(model);
+    let mut tracker = StreamUsageTracker::new(
+        state.metrics.clone(),
+        key_id,
+        provider_name.to_string(),
+        model.to_string(),
+    )
+    .log_usage_since(state.config.load().logging.log_usage.then_some(start_time));
+    let mut first_token = FirstTokenTimer::new(
+        state.metrics.clone(),
+        provider_name.to_string(),
+        model.to_string(),
+        start_time,
+    );
+    let active = state.metrics.track_upstream_stream();
+    Box::pin(stream.map(move |chunk| {
+        let _ = &active;
+        if let Ok(text) = &chunk {
+            tracker.observe(text);
+            first_token.observe(text);
+        }
+        chunk
+    }))
+}
+
+/// Record a completed response's token usage, logging it when `logging.log_usage` is set
+fn record_chat_usage(
+    state: &AppState,
+    key_id: String,
+    request: &AnthropicRequest,
+    response: &AnthropicResponse,
+    start_time: std::time::Instant,
+) {
+    let usage = UsageRecord {
+        api_key_id: key_id,
+        provider: provider_name_for_metrics(&request.model).to_string(),
+        model: request.model.clone(),
+        input_tokens: response.usage.input_tokens,
+        output_tokens: response.usage.output_tokens,
+    };
+    state.metrics.record_usage(&usage);
+    if state.config.load().logging.log_usage {
+        usage.log(start_time.elapsed());
+    }
+}
+
+/// Log the bodies of a chat exchange according to the logging settings
+///
+/// `log_requests`/`log_responses` select what is logged. Failed exchanges are
+/// always logged; successful ones only for the `log_sample_rate` fraction of
+/// requests. `Ok(None)` is a streamed response, whose body is not logged.
+fn log_chat_exchange(
+    logging: &LoggingConfig,
+    request: &AnthropicRequest,
+    outcome: Result<Option<&AnthropicResponse>, &AppError>,
+) {
+    if !logging.log_requests && !logging.log_responses {
+        return;
+    }
+    if !logging.should_log_body(outcome.is_err(), rand::random::<f32>()) {
+        return;
+    }
+
+    if logging.log_requests {
+        let body = serde_json::to_string(request).unwrap_or_default();
+        tracing::info!(model = %request.model, user = request.user.as_deref(), request = %body, "Chat request body");
+    }
+    match outcome {
+        Ok(Some(response)) if logging.log_responses => {
+            let body = serde_json::to_string(response).unwrap_or_default();
+            tracing::info!(model = %request.model, user = request.user.as_deref(), response = %body, "Chat response body");
+        }
+        Err(error) => {
+            tracing::warn!(model = %request.model, user = request.user.as_deref(), error = %error, "Chat request failed");
+        }
+        _ => {}
+    }
+}
+
+/// Retry budget for a request: the provider's `max_retries` within one total deadline
+///
+/// The deadline is `server.request_timeout_seconds`, or the client's
+/// `x-ai-proxy-timeout` override when one was given. Retry attempts are counted
+/// in the metrics under the routed provider.
+async fn retry_policy(state: &AppState, request: &AnthropicRequest) -> RetryPolicy {
+    let provider_id = {
+        let registry = state.provider_registry.read().await;
+        registry.provider_id_for_model(&request.model).map(str::to_string)
+    };
+    let config = state.config.load();
+    let max_retries = provider_id
+        .as_deref()
+        .and_then(|provider_id| config.providers.get(provider_id))
+        .map_or(0, |provider| provider.max_retries);
+    let budget = request
+        .timeout_override
+        .unwrap_or(Duration::from_secs(config.server.request_timeout_seconds));
+    RetryPolicy::new(max_retries, budget)
+        .with_max_retry_after(Duration::from_secs(config.server.max_retry_after_seconds))
+        .with_metrics(
+            state.metrics.clone(),
+            provider_id.unwrap_or_else(|| provider_name_for_metrics(&request.model).to_string()),
+        )
+}
+
+/// Response header carrying the request cost in USD, when the model has a configured price
+const COST_HEADER: &str = "x-ai-proxy-cost-usd";
+
+/// Build the cost header for a completed request, if the model has a configured price
+fn cost_header(state: &AppState, model: &str, response: &AnthropicResponse) -> Option<HeaderValue> {
+    let cost = state.config.load().cost_usd(
+        model,
+        response.usage.input_tokens as u64,
+        response.usage.output_tokens as u64,
+    )?;
+    HeaderValue::from_str(&format!("{:.6}", cost)).ok()
+}
+
+/// Summarise which provider served a response, how long it took and what it cost
+fn proxy_metadata(
+    state: &AppState,
+    provider_id: &str,
+    request: &AnthropicRequest,
+    response: &AnthropicResponse,
+    start_time: std::time::Instant,
+) -> ProxyMetadata {
+    ProxyMetadata {
+        provider: provider_id.to_string(),
+        resolved_model: response.model.clone(),
+        latency_ms: start_time.elapsed().as_millis() as u64,
+        cost_usd: state.config.load().cost_usd(
+            &request.model,
+            response.usage.input_tokens as u64,
+            response.usage.output_tokens as u64,
+        ),
+        system_fingerprint: request.seed.and(response.system_fingerprint.clone()),
+    }
+}
+
+/// Copy the provider's allow-listed upstream headers onto the client response
+fn attach_upstream_headers(http_response: &mut axum::response::Response, response: &AnthropicResponse) {
+    for (name, value) in &response.upstream_headers {
+        if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(value)) {
+            http_response.headers_mut().insert(name, value);
+        }
+    }
+}
+
+/// Send a non-streaming chat request, coalescing identical deterministic requests
+///
+/// Requests with a `cache_key` (temperature 0) that arrive while an identical
+/// request is in flight share its upstream call instead of issuing their own.
+/// Transient failures are retried up to the provider's `max_retries`, all
+/// attempts sharing one deadline. Registered transforms see each caller's copy
+/// of the response.
+async fn dispatch_chat(
+    state: &AppState,
+    provider: Arc<dyn AIProvider + Send + Sync>,
+    request: &AnthropicRequest,
+) -> AppResult<AnthropicResponse> {
+    let policy = retry_policy(state, request).await;
+    let mut response = match request.cache_key() {
+        Some(key) => {
+            let request = request.clone();
+            state
+                .coalescer
+                .run(key, move || chat_with_retries(provider, request, policy).boxed())
+                .await?
+        }
+        None => chat_with_retries(provider, request.clone(), policy).await?,
+    };
+
+    for transform in &state.transforms {
+        transform.on_response(&mut response).await;
+    }
+    Ok(response)
+}
+
+/// Stream a chat request upstream and reassemble it into a single response
+///
+/// The whole stream, including reading it to the end, is bounded by the same
+/// deadline as a non-streaming request. Registered transforms see the
+/// reassembled response.
+async fn dispatch_aggregated_chat(
+    state: &AppState,
+    provider: Arc<dyn AIProvider + Send + Sync>,
+    request: &AnthropicRequest,
+) -> AppResult<AnthropicResponse> {
+    let policy = retry_policy(state, request).await;
+    let mut request = request.clone();
+    request.stream = Some(true);
+
+    let aggregated = async move { aggregate_stream(provider.chat_stream(request).await?).await };
+    let mut response = tokio::time::timeout_at(policy.deadline, aggregated)
+        .await
+        .map_err(|_| AppError::GatewayTimeout("Request deadline exceeded while aggregating stream".to_string()))??;
+
+    for transform in &state.transforms {
+        transform.on_response(&mut response).await;
+    }
+    Ok(response)
+}
+
+/// Request header naming a client-chosen idempotency key
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Response header set to `true` when a stored idempotent response is replayed
+const IDEMPOTENT_REPLAY_HEADER: &str = "x-ai-proxy-idempotent-replay";
+
+/// `Idempotency-Key` of the request, scoped to the fingerprint of the caller's full API key
+///
+/// Returns `None` when the header is absent or blank, when the caller sent no
+/// API key (anonymous callers cannot be told apart), or when
+/// `server.idempotency_ttl_seconds` is 0.
+fn idempotency_key(state: &AppState, headers: &HeaderMap) -> Option<String> {
+    if state.config.load().server.idempotency_ttl_seconds == 0 {
+        return None;
+    }
+    let key = headers.get(IDEMPOTENCY_KEY_HEADER)?.to_str().ok()?.trim();
+    if key.is_empty() {
+        return None;
+    }
+    let Some(fingerprint) = api_key_fingerprint(headers) else {
+        tracing::debug!("Ignoring {} from a caller without an API key", IDEMPOTENCY_KEY_HEADER);
+        return None;
+    };
+    Some(format!("{}:{}", fingerprint, key))
+}
+
+/// How a `/v1/messages` request is answered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChatMode {
+    /// One JSON response from a non-streaming upstream call
+    Single,
+    /// SSE events forwarded as they arrive
+    Stream,
+    /// One JSON response reassembled from a streaming upstream call
+    Aggregate,
+}
+
+/// Decide how to answer a chat request from its `stream` flag and the aggregate header
+///
+/// `stream: true` asks for SSE while the aggregate header asks for a single
+/// JSON body, so the combination is rejected instead of silently picking one.
+fn chat_mode(request: &AnthropicRequest, headers: &HeaderMap) -> AppResult<ChatMode> {
+    match (request.is_streaming(), wants_aggregated_stream(headers)) {
+        (true, true) => Err(AppError::BadRequest(format!(
+            "\"stream\": true cannot be combined with the {} header; omit one of them \
+             (the header already streams upstream and returns a single JSON response)",
+            AGGREGATE_STREAM_HEADER
+        ))),
+        (true, false) => Ok(ChatMode::Stream),
+        (false, true) => Ok(ChatMode::Aggregate),
+        (false, false) => Ok(ChatMode::Single),
+    }
+}
+
+/// Header correlating a streaming request with `DELETE /v1/messages/stream/{id}`
+const STREAM_ID_HEADER: &str = "x-ai-proxy-stream-id";
+
+/// Claim the client's `x-ai-proxy-stream-id`, if one was sent, for the caller's full API key
+///
+/// Stream IDs need an API key to scope them; without one any caller could
+/// cancel the stream, so the header is rejected.
+fn reserve_stream_id(state: &AppState, headers: &HeaderMap) -> AppResult<Option<StreamSlot>> {
+    let Some(value) = headers.get(STREAM_ID_HEADER) else {
+        return Ok(None);
+    };
+    let id = value
+        .to_str()
+        .map_err(|_| AppError::BadRequest(format!("Invalid {} header", STREAM_ID_HEADER)))?;
+    let owner = api_key_fingerprint(headers)
+        .ok_or_else(|| AppError::BadRequest(format!("The {} header requires an API key", STREAM_ID_HEADER)))?;
+    state.active_streams.reserve(&owner, id).map(Some)
+}
+
+/// Whether the `x-ai-proxy-aggregate-stream` header asks for a reassembled stream
+fn wants_aggregated_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get(AGGREGATE_STREAM_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case("true") || v.trim() == "1")
+}
+
+/// Handle streaming chat over a WebSocket
+///
+/// The client sends one `AnthropicRequest` as the first text frame; every
+/// event of the provider stream is then sent back as a text frame holding the
+/// event's JSON, in the same shape as the SSE `data:` lines. The socket closes
+/// after `message_stop` or an `error` event, and a client close drops the
+/// upstream stream.
+async fn ws_chat_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> axum::response::Response {
+    ws.on_upgrade(move |socket| ws_chat_session(state, headers, socket))
+}
+
+/// Drive one WebSocket chat session from the first request frame to close
+async fn ws_chat_session(state: AppState, headers: HeaderMap, mut socket: WebSocket) {
+    let request = loop {
+        match socket.recv().await {
+            Some(Ok(Message::Text(text))) => break text,
+            Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+            Some(Ok(_)) => continue,
+        }
+    };
+
+    let result = ws_stream_chat(&state, &headers, request.as_str(), &mut socket).await;
+    if let Err(e) = &result {
+        tracing::warn!("WebSocket chat stream failed: {}", e);
+        let (_, body) = e.status_and_body();
+        let event = json!({"type": "error", "error": body["error"]});
+        let _ = socket.send(Message::Text(event.to_string().into())).await;
+    }
+    let _ = socket.send(Message::Close(None)).await;
+}
+
+/// Run the first WebSocket frame through the chat pipeline and forward its stream
+async fn ws_stream_chat(state: &AppState, headers: &HeaderMap, text: &str, socket: &mut WebSocket) -> AppResult<()> {
+    let body: Value = serde_json::from_str(text)
+        .map_err(|e| AppError::BadRequest(format!("Invalid request body: {}", e)))?;
+    let mut request = parse_chat_request(body)?;
+    request.stream = Some(true);
+    let PreparedChat { mut request, .. } = prepare_chat(state, headers, RedactionRoute::Ws, request).await?;
+
+    let start_time = state.metrics.record_request_start();
+    tracing::info!("Processing WebSocket chat request for model: {}", request.model);
+    let provider_name = provider_name_for_metrics(&request.model);
+
+    let stream = match route_chat(state, &mut request).await {
+        Ok(routed) => open_chat_stream(state, headers, routed.provider, &request).await,
+        Err(e) => Err(e),
+    };
+    let result = match stream {
+        Ok(stream) => {
+            log_chat_exchange(&state.config.load().logging, &request, Ok(None));
+            let stream = track_chat_stream(state, api_key_id(headers), &request.model, start_time, stream);
+            forward_stream_to_socket(stream, socket).await
+        }
+        Err(e) => Err(e),
+    };
+
+    state
+        .metrics
+        .record_request_end(start_time, result.is_ok(), provider_name, &request.model)
+        .await;
+    if let Err(e) = &result {
+        log_chat_exchange(&state.config.load().logging, &request, Err(e));
+    }
+    result
+}
+
+/// Send each SSE event of `stream` as a text frame until the stream finishes
+///
+/// Returns once `message_stop` is sent, the stream ends, or the client closes
+/// the socket; dropping the stream at that point cancels the upstream request.
+async fn forward_stream_to_socket(
+    mut stream: StreamResponse,
+    socket: &mut WebSocket,
+) -> AppResult<()> {
+    use futures::StreamExt;
+
+    let mut buffer = String::new();
+    loop {
+        tokio::select! {
+            chunk = stream.next() => {
+                let Some(chunk) = chunk else {
+                    return Ok(());
+                };
+                buffer.push_str(&chunk?);
+                while let Some(pos) = buffer.find("\n\n") {
+                    let event: String = buffer.drain(..pos + 2).collect();
+                    for data in event.lines().filter_map(|line| line.strip_prefix("data:")) {
+                        let data = data.trim();
+                        let Ok(value) = serde_json::from_str::<Value>(data) else {
+                            continue;
+                        };
+                        if socket.send(Message::Text(data.to_string().into())).await.is_err() {
+                            tracing::info!("WebSocket client went away, cancelling upstream stream");
+                            return Ok(());
+                        }
+                        match value.get("type").and_then(Value::as_str) {
+                            Some("message_stop") | Some("error") => return Ok(()),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => {
+                    tracing::info!("WebSocket client closed, cancelling upstream stream");
+                    return Ok(());
+                }
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+/// Largest number of prompts accepted by one `POST /v1/messages/batch` request
+const MAX_BATCH_SIZE: usize = 100;
+
+/// Handle batches of independent non-streaming chat requests
+///
+/// Items are dispatched concurrently, at most `performance.max_concurrent_requests`
+/// at a time, and answered in request order. Each result carries either the
+/// `response` or an `error` object, so one failing item never fails the batch.
+async fn batch_chat_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(requests): Json<Vec<Value>>,
+) -> AppResult<Json<Value>> {
+    use futures::StreamExt;
+
+    if requests.is_empty() {
+        return Err(AppError::ValidationError("Batch must contain at least one request".to_string()));
+    }
+    if requests.len() > MAX_BATCH_SIZE {
+        return Err(AppError::ValidationError(format!(
+            "Batch contains {} requests (max {})",
+            requests.len(),
+            MAX_BATCH_SIZE
+        )));
+    }
+
+    // A malformed timeout header fails the whole batch rather than every item
+    parse_timeout_override(&headers, state.config.load().server.max_request_timeout_seconds)?;
+    tracing::info!("Processing chat batch of {} requests", requests.len());
+
+    let state = &state;
+    let headers = &headers;
+    let results: Vec<Value> = futures::stream::iter(requests.into_iter().enumerate())
+        .map(|(index, body)| async move {
+            match batch_item(state, headers, body).await {
+                Ok(response) => json!({
+                    "index": index,
+                    "status": StatusCode::OK.as_u16(),
+                    "response": response,
+                }),
+                Err(error) => {
+                    let (status, body) = error.status_and_body();
+                    json!({
+                        "index": index,
+                        "status": status.as_u16(),
+                        "error": body["error"],
+                    })
+                }
+            }
+        })
+        .buffered(state.config.load().performance.max_concurrent_requests)
+        .collect()
+        .await;
+
+    Ok(Json(Value::Array(results)))
+}
+
+/// Run one item of a chat batch through the same parsing, checks and dispatch as `/v1/messages`
+///
+/// Items are parsed individually, so a malformed item fails on its own with
+/// the same field errors `/v1/messages` would report.
+async fn batch_item(
+    state: &AppState,
+    headers: &HeaderMap,
+    body: Value,
+) -> AppResult<AnthropicResponse> {
+    let request = parse_chat_request(body)?;
+    if request.is_streaming() {
+        return Err(AppError::ValidationError(
+            "Streaming is not supported in batch requests".to_string(),
+        ));
+    }
+    let PreparedChat { mut request, original_model, .. } =
+        prepare_chat(state, headers, RedactionRoute::Batch, request).await?;
+
+    let start_time = state.metrics.record_request_start();
+    let provider_name = provider_name_for_metrics(&request.model);
+    let result = match route_chat(state, &mut request).await {
+        Ok(routed) => dispatch_chat(state, routed.provider, &request).await,
+        Err(e) => Err(e),
+    };
+
+    match &result {
+        Ok(response) => {
+            log_chat_exchange(&state.config.load().logging, &request, Ok(Some(response)));
+            record_chat_usage(state, api_key_id(headers), &request, response, start_time);
+        }
+        Err(e) => log_chat_exchange(&state.config.load().logging, &request, Err(e)),
+    }
+    state
+        .metrics
+        .record_request_end(start_time, result.is_ok(), provider_name, &request.model)
+        .await;
+    result.map(|mut response| {
+        if let Some(model) = original_model {
+            response.model = model;
+        }
+        response
+    })
+}
+
+/// Handle embeddings requests
+///
+/// Dispatches to the provider registered for the model; providers without an
+/// embeddings API answer with `not_supported_error`.
+async fn embeddings_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<EmbeddingRequest>,
+) -> AppResult<Json<Value>> {
+    let model = request.model;
+    let inputs = request.input.into_inputs();
+
+    if model.is_empty() {
+        return Err(AppError::ValidationError("Model cannot be empty".to_string()));
+    }
+    if inputs.is_empty() || inputs.iter().any(|text| text.is_empty()) {
+        return Err(AppError::ValidationError(
+            "input must be a non-empty string or array of non-empty strings".to_string(),
+        ));
+    }
+
+    check_model_access(&state.config.load().security, &headers, &model)?;
+
+    let start_time = state.metrics.record_request_start();
+
+    tracing::info!("Processing embeddings request for model: {} ({} inputs)", model, inputs.len());
+
+    let provider_name = provider_name_for_metrics(&model);
+
+    let result = async {
+        let provider = {
+            let registry = state.provider_registry.read().await;
+            registry.get_provider_for_model(&model)?
+        };
+        provider.embeddings(inputs, &model).await
+    }
+    .await;
+
+    state
+        .metrics
+        .record_request_end(start_time, result.is_ok(), provider_name, &model)
+        .await;
+
+    let response = result?;
+    state.metrics.record_usage(&UsageRecord {
+        api_key_id: api_key_id(&headers),
+        provider: provider_name.to_string(),
+        model: model.clone(),
+        input_tokens: response.usage.prompt_tokens,
+        output_tokens: 0,
+    });
+
+    Ok(Json(serde_json::to_value(response)?))
+}
+
+/// Trim the request's model name and apply `server.model_case` before routing
+///
+/// Returns the model as the client sent it when normalization changed it, so
+/// responses can echo the original name.
+fn normalize_model(config: &Config, request: &mut AnthropicRequest) -> Option<String> {
+    let normalized = config.server.model_case.apply(request.model.trim());
+    if normalized == request.model {
+        return None;
+    }
+    tracing::debug!("Normalized model name {:?} to {:?}", request.model, normalized);
+    Some(std::mem::replace(&mut request.model, normalized))
+}
+
+/// Apply `server.inject_system_prefix`/`inject_system_suffix` unless a trusted caller opted out
+fn inject_system_text(config: &Config, headers: &HeaderMap, request: &mut AnthropicRequest) {
+    let server = &config.server;
+    if server.inject_system_prefix.is_none() && server.inject_system_suffix.is_none() {
+        return;
+    }
+    if skips_system_injection(&config.security, headers) {
+        tracing::debug!("Skipping system prompt injection for a trusted caller");
+        return;
+    }
+    request.inject_system(server.inject_system_prefix.as_deref(), server.inject_system_suffix.as_deref());
+}
+
+/// Enforce the routed provider's `max_messages` (100 when unset)
+///
+/// Runs after routing so each backend's own history depth applies; the limit is
+/// also recorded in the validation context checked again by the provider.
+fn apply_message_limit(config: &Config, provider_id: Option<&str>, request: &mut AnthropicRequest) -> AppResult<()> {
+    let max_messages = provider_id
+        .and_then(|provider_id| config.providers.get(provider_id))
+        .and_then(|provider| provider.max_messages);
+    request.validation.max_messages = max_messages;
+
+    let limit = max_messages.unwrap_or(DEFAULT_MAX_MESSAGES);
+    if request.messages.len() > limit as usize {
+        return Err(AppError::ValidationError(format!(
+            "Too many messages ({}) for model {}: the limit is {}",
+            request.messages.len(),
+            request.model,
+            limit
+        )));
+    }
+    Ok(())
+}
+
+/// Parse the `x-ai-proxy-timeout` header (whole seconds), clamped to `[1, max_timeout_seconds]`
+fn parse_timeout_override(
+    headers: &HeaderMap,
+    max_timeout_seconds: u64,
+) -> AppResult<Option<Duration>> {
+    let Some(value) = headers.get(TIMEOUT_OVERRIDE_HEADER) else {
+        return Ok(None);
+    };
+
+    let seconds = value
+        .to_str()
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .ok_or_else(|| {
+            AppError::BadRequest(format!(
+                "Invalid {} header: expected a number of seconds",
+                TIMEOUT_OVERRIDE_HEADER
+            ))
+        })?;
+
+    Ok(Some(Duration::from_secs(seconds.clamp(1, max_timeout_seconds.max(1)))))
+}
+
+/// Derive the provider label used for metrics from a model name
+fn provider_name_for_metrics(model: &str) -> &'static str {
+    // Only OpenRouter uses routed "vendor/model" names
+    if model.contains('/') {
+        "openrouter"
+    } else if model.starts_with("gpt") || model.starts_with("openai") {
+        "openai"
+    } else if model.starts_with("gemini") {
+        "gemini"
+    } else if model.starts_with("claude") || model.starts_with("anthropic") {
+        "anthropic"
+    } else if model.starts_with("grok") || model.starts_with("xai") {
+        "xai"
+    } else {
+        "unknown"
+    }
+}
+
+/// Configured SSE heartbeat interval (zero disables it)
+fn heartbeat_interval(state: &AppState) -> Duration {
+    Duration::from_secs(state.config.load().performance.sse_heartbeat_seconds)
+}
+
+/// Handle model listing requests
+async fn list_models_handler(State(state): State<AppState>) -> AppResult<Json<Value>> {
+    tracing::info!("Processing models list request");
+
+    // Slow or failing providers are reported as warnings instead of failing the list
+    let timeout = Duration::from_millis(state.config.load().server.models_list_timeout_ms);
+    let listing = {
+        let registry = state.provider_registry.read().await;
+        registry.list_all_models_within(timeout).await
+    };
+
+    let mut response = json!({
+        "object": "list",
+        "data": listing.models
+    });
+    if !listing.warnings.is_empty() {
+        response["warnings"] = json!(listing.warnings);
+    }
+
+    tracing::info!(
+        "Models list request completed, {} models available",
+        listing.models.len()
+    );
+    Ok(Json(response))
+}
+
+/// Handle capabilities requests: what each configured provider supports
+async fn capabilities_handler(State(state): State<AppState>) -> AppResult<Json<Value>> {
+    let capabilities = {
+        let registry = state.provider_registry.read().await;
+        registry.capabilities()
+    };
+    Ok(Json(json!({ "providers": capabilities })))
+}
+
+/// Handle model refresh requests
+async fn refresh_models_handler(State(state): State<AppState>) -> AppResult<Json<Value>> {
+    tracing::info!("Processing models refresh request");
+
+    // Refresh models from all providers
+    {
+        let mut registry = state.provider_registry.write().await;
+        registry.refresh_models().await?;
+    }
+
+    // Get updated model statistics
     let stats = {
         let registry = state.provider_registry.read().await;
         registry.get_model_stats()
@@ -443,24 +2057,142 @@ async fn health_handler(State(state): State<AppState>) -> AppResult<Json<Value>>
     Ok(Json(response))
 }
 
+/// Enabled providers in the registry with their model counts, sorted by ID
+async fn enabled_providers(state: &AppState) -> Vec<(String, usize)> {
+    let registry = state.provider_registry.read().await;
+    let model_counts = registry.get_model_stats();
+    let mut providers: Vec<(String, usize)> = registry
+        .get_provider_ids()
+        .into_iter()
+        .filter(|id| state.config.load().providers.get(id).is_none_or(|provider| provider.enabled))
+        .map(|id| {
+            let models = model_counts.get(&id).copied().unwrap_or(0);
+            (id, models)
+        })
+        .collect();
+    providers.sort();
+    providers
+}
+
+/// Log one line per enabled provider so the live routing table is visible at boot
+async fn log_startup_summary(state: &AppState) {
+    let config = state.config.load();
+    for (provider_id, models) in enabled_providers(state).await {
+        let Some(provider) = config.providers.get(&provider_id) else {
+            continue;
+        };
+        tracing::info!(
+            provider = %provider_id,
+            api_base = %provider.redacted_api_base(),
+            api_key = %provider.redacted_api_key(),
+            models,
+            "Provider enabled"
+        );
+    }
+}
+
+/// Handle `GET /info`: version, uptime, enabled providers and feature flags
+///
+/// Secrets are never included: base URLs are redacted and API keys are left out.
+async fn info_handler(State(state): State<AppState>) -> Json<Value> {
+    let providers: Vec<Value> = enabled_providers(&state)
+        .await
+        .into_iter()
+        .map(|(provider_id, models)| {
+            let api_base = state.config.load().providers.get(&provider_id).map(|p| p.redacted_api_base());
+            json!({"id": provider_id, "api_base": api_base, "models": models})
+        })
+        .collect();
+
+    let config = &state.config.load();
+    Json(json!({
+        "service": "ai-proxy",
+        "version": env!("CARGO_PKG_VERSION"),
+        "uptime_seconds": state.metrics.uptime().as_secs(),
+        "providers": providers,
+        "features": {
+            "tls": config.server.tls_cert_path.is_some(),
+            "auto_detect_format": config.server.auto_detect_format,
+            "sse_event_names": config.server.sse_event_names,
+            "sse_heartbeat": config.performance.sse_heartbeat_seconds > 0,
+            "model_access": !config.security.model_access.is_empty(),
+            "admin_api": config.security.admin_api_key.is_some(),
+            "rate_limiting": config.security.rate_limit_enabled,
+            "cors": config.security.cors_enabled,
+            "strict_role_alternation": config.security.strict_role_alternation,
+            "model_case": config.server.model_case,
+            "usage_sink": config.usage.sink,
+            "transforms": state.transforms.len(),
+        }
+    }))
+}
+
+/// Handle readiness probes
+///
+/// Unlike `/health` (liveness), this returns 503 until at least one provider
+/// reports healthy, so traffic is not routed to a pod whose providers are all down.
+async fn readiness_handler(State(state): State<AppState>) -> (StatusCode, Json<Value>) {
+    let ttl = Duration::from_secs(state.config.load().performance.health_check_ttl_seconds);
+    let health_results = state
+        .health_cache
+        .get_or_refresh(&state.provider_registry, ttl)
+        .await;
+
+    let healthy_providers: Vec<&String> = health_results
+        .iter()
+        .filter(|(_, cached)| cached.health.status == "healthy")
+        .map(|(provider_id, _)| provider_id)
+        .collect();
+
+    let (status_code, status) = if healthy_providers.is_empty() {
+        (StatusCode::SERVICE_UNAVAILABLE, "not_ready")
+    } else {
+        (StatusCode::OK, "ready")
+    };
+
+    let response = json!({
+        "status": status,
+        "healthy_providers": healthy_providers,
+        "providers_configured": health_results.len(),
+        "timestamp": chrono::Utc::now().to_rfc3339()
+    });
+
+    (status_code, Json(response))
+}
+
 /// Handle provider health checks
 async fn health_providers_handler(State(state): State<AppState>) -> AppResult<Json<Value>> {
     tracing::info!("Processing provider health check");
 
-    let health_results = {
-        let registry = state.provider_registry.read().await;
-        registry.health_check_all().await
-    };
+    // Serve cached results until they are older than the configured TTL
+    let ttl = Duration::from_secs(state.config.load().performance.health_check_ttl_seconds);
+    let health_results = state
+        .health_cache
+        .get_or_refresh(&state.provider_registry, ttl)
+        .await;
 
-    let overall_status = if health_results.values().all(|h| h.status == "healthy") {
+    // Providers switched off by an admin are reported but do not degrade the overall status
+    let registry = state.provider_registry.read().await;
+    let overall_status = if health_results
+        .iter()
+        .filter(|(provider_id, _)| registry.is_enabled(provider_id))
+        .all(|(_, h)| h.health.status == "healthy")
+    {
         "healthy"
     } else {
         "degraded"
     };
+    let mut providers = serde_json::to_value(&health_results)?;
+    if let Some(providers) = providers.as_object_mut() {
+        for (provider_id, health) in providers.iter_mut() {
+            health["enabled"] = json!(registry.is_enabled(provider_id));
+        }
+    }
+    drop(registry);
 
     let response = json!({
         "status": overall_status,
-        "providers": health_results,
+        "providers": providers,
         "timestamp": chrono::Utc::now().to_rfc3339()
     });
 
@@ -481,3 +2213,201 @@ async fn metrics_handler(State(state): State<AppState>) -> AppResult<Json<Value>
     tracing::info!("Metrics request completed");
     Ok(Json(response))
 }
+
+/// Handle stream cancellation: end the caller's in-flight stream registered under `id`
+///
+/// Stream IDs are scoped to the full API key that started the stream, so
+/// other keys and callers without a key get 404. The client stream closes without further events and the
+/// upstream response is dropped, which stops generation.
+async fn cancel_stream_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> AppResult<Json<Value>> {
+    let cancelled = api_key_fingerprint(&headers).is_some_and(|owner| state.active_streams.cancel(&owner, &id));
+    if !cancelled {
+        return Err(AppError::NotFound(format!("No active stream with ID '{}'", id)));
+    }
+
+    tracing::info!(stream_id = %id, "Cancelled stream on client request");
+    Ok(Json(json!({
+        "id": id,
+        "cancelled": true
+    })))
+}
+
+/// Handle request validation: run the chat pipeline up to routing without dispatching
+///
+/// Answers 200 either way; `valid` is false and `errors` lists the problems
+/// (field path and description) when the request would be rejected. Requests
+/// the caller may not send at all (model access) still fail with their status.
+async fn validate_chat_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> AppResult<Json<Value>> {
+    let invalid = |errors: Vec<FieldError>, estimated_input_tokens: Option<u32>| {
+        Json(json!({
+            "valid": false,
+            "resolved_provider": null,
+            "resolved_model": null,
+            "estimated_input_tokens": estimated_input_tokens,
+            "warnings": [],
+            "errors": errors
+        }))
+    };
+
+    let parsed = if state.config.load().server.auto_detect_format && detect_request_format(&body)? == RequestFormat::OpenAI {
+        serde_json::from_value::<OpenAIRequest>(body.clone())
+            .map_err(|e| AppError::BadRequest(format!("Invalid request body: {}", e)))
+            .and_then(|request| request.to_anthropic())
+    } else {
+        parse_chat_request(body.clone())
+    };
+    let request = match parsed {
+        Ok(request) => request,
+        Err(AppError::InvalidFields(errors)) => return Ok(invalid(errors, None)),
+        Err(e) => return Ok(invalid(vec![FieldError::new("body", e.to_string())], None)),
+    };
+
+    let PreparedChat { mut request, mut warnings, .. } =
+        prepare_chat(&state, &headers, RedactionRoute::Messages, request).await?;
+    let estimated_input_tokens = request.estimate_input_tokens();
+
+    // Nothing is dispatched, so a fallback is not recorded
+    let (provider_result, provider_id) = resolve_provider(&state, &request, false).await;
+    if let Err(e) = provider_result {
+        return Ok(invalid(vec![FieldError::new("model", e.to_string())], Some(estimated_input_tokens)));
+    }
+
+    let requested_max_tokens = request.max_tokens;
+    let config = state.config.load();
+    let checked = apply_message_limit(&config, provider_id.as_deref(), &mut request).and_then(|_| {
+        match provider_id.as_deref().and_then(|id| config.providers.get(id)) {
+            Some(provider) => apply_max_tokens_policy(&mut request, provider),
+            None => Ok(()),
+        }
+    });
+    if let Err(e) = checked.and_then(|_| request.validate().map_err(AppError::ValidationError)) {
+        let message = match e {
+            AppError::ValidationError(message) => message,
+            other => other.to_string(),
+        };
+        return Ok(Json(json!({
+            "valid": false,
+            "resolved_provider": provider_id,
+            "resolved_model": request.model,
+            "estimated_input_tokens": estimated_input_tokens,
+            "warnings": warnings,
+            "errors": [FieldError::new(field_of_error(&body, &message), message)]
+        })));
+    }
+    if let Some(requested) = requested_max_tokens
+        && request.max_tokens != Some(requested)
+    {
+        warnings.push(format!(
+            "max_tokens {} will be clamped to {}",
+            requested,
+            request.max_tokens.unwrap_or_default()
+        ));
+    }
+
+    Ok(Json(json!({
+        "valid": true,
+        "resolved_provider": provider_id,
+        "resolved_model": request.model,
+        "estimated_input_tokens": estimated_input_tokens,
+        "warnings": warnings,
+        "errors": []
+    })))
+}
+
+/// Request field a validation message is about: its leading word when the body
+/// has a top-level field of that name, otherwise the request as a whole
+fn field_of_error(body: &Value, message: &str) -> String {
+    message
+        .split_whitespace()
+        .next()
+        .filter(|word| body.get(*word).is_some())
+        .unwrap_or("request")
+        .to_string()
+}
+
+/// Handle admin reload: re-read the config file and swap in a rebuilt provider registry
+async fn admin_reload_handler(State(state): State<AppState>, headers: HeaderMap) -> AppResult<Json<Value>> {
+    check_admin_access(&state.config.load().security, &headers)?;
+
+    let providers = state.reload().await.inspect_err(|e| {
+        tracing::warn!("Configuration reload rejected, keeping current providers: {}", e);
+    })?;
+
+    Ok(Json(json!({
+        "status": "reloaded",
+        "providers": providers,
+        "timestamp": chrono::Utc::now().to_rfc3339()
+    })))
+}
+
+/// Handle `POST /admin/providers/{key}/enable`
+async fn admin_enable_provider_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(key): Path<String>,
+) -> AppResult<Json<Value>> {
+    set_provider_enabled(&state, &headers, &key, true).await
+}
+
+/// Handle `POST /admin/providers/{key}/disable`
+async fn admin_disable_provider_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(key): Path<String>,
+) -> AppResult<Json<Value>> {
+    set_provider_enabled(&state, &headers, &key, false).await
+}
+
+/// Flip a provider's routing flag; the change lasts until the next config reload
+async fn set_provider_enabled(state: &AppState, headers: &HeaderMap, key: &str, enabled: bool) -> AppResult<Json<Value>> {
+    check_admin_access(&state.config.load().security, headers)?;
+
+    state.provider_registry.write().await.set_enabled(key, enabled)?;
+    tracing::warn!(provider = %key, enabled, "Provider routing switched by admin request");
+
+    Ok(Json(json!({
+        "provider": key,
+        "enabled": enabled,
+        "timestamp": chrono::Utc::now().to_rfc3339()
+    })))
+}
+
+/// Handle usage endpoint: aggregated token totals and cost per API key, provider and model
+///
+/// Usage is billing data, so the endpoint requires `security.admin_api_key`
+/// like the `/admin/*` endpoints.
+async fn usage_handler(State(state): State<AppState>, headers: HeaderMap) -> AppResult<Json<Value>> {
+    check_admin_access(&state.config.load().security, &headers)?;
+    tracing::info!("Processing usage request");
+
+    // Price each aggregate with the configured per-model cost; unknown models report null
+    let config = state.config.load();
+    let totals: Vec<Value> = state
+        .metrics
+        .get_usage_totals()
+        .into_iter()
+        .map(|totals| {
+            let cost_usd = config
+                .cost_usd(&totals.model, totals.input_tokens, totals.output_tokens);
+            let mut entry = json!(totals);
+            entry["cost_usd"] = json!(cost_usd);
+            entry
+        })
+        .collect();
+
+    let response = json!({
+        "object": "list",
+        "data": totals,
+        "timestamp": chrono::Utc::now().to_rfc3339()
+    });
+
+    Ok(Json(response))
+}
@@ -0,0 +1,215 @@
+//! 请求/响应转换钩子
+//!
+//! 在请求发送给提供商之前、以及响应返回给客户端之前，按注册顺序依次调用
+//! `AppState::transforms`中的转换器，用于注入系统提示、脱敏等场景。
+
+use async_trait::async_trait;
+
+use regex::Regex;
+
+use crate::config::{RedactionConfig, RedactionRoute};
+use crate::providers::anthropic::{AnthropicRequest, AnthropicResponse};
+
+/// 请求/响应转换器
+///
+/// 两个方法都有空的默认实现，转换器只需覆盖自己关心的一侧。
+/// 流式响应不经过`on_response`。
+#[async_trait]
+pub trait Transform: Send + Sync {
+    /// 在请求路由到提供商之前修改请求
+    async fn on_request(&self, _req: &mut AnthropicRequest) {}
+
+    /// 在非流式响应返回给客户端之前修改响应
+    async fn on_response(&self, _res: &mut AnthropicResponse) {}
+}
+
+/// 不做任何修改的转换器
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopTransform;
+
+#[async_trait]
+impl Transform for NoopTransform {}
+
+/// 邮箱地址的替换文本
+pub const REDACTED_EMAIL: &str = "[REDACTED_EMAIL]";
+/// 长数字串（电话、卡号等）的替换文本
+pub const REDACTED_NUMBER: &str = "[REDACTED_NUMBER]";
+
+/// 被视为敏感数字串的最少数字个数
+const MIN_SENSITIVE_DIGITS: usize = 9;
+
+/// 示例PII脱敏转换器
+///
+/// 将系统提示、消息和响应内容中的邮箱地址以及包含至少9位数字的数字串
+/// （电话号码、卡号等，允许`-`、`+`、`(`、`)`分隔）替换为占位符。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PiiRedactionTransform;
+
+impl PiiRedactionTransform {
+    /// 对文本进行PII脱敏
+    ///
+    /// ## 功能说明
+    /// 按空白拆分文本，将看起来像邮箱或长数字串的词替换为占位符，保留原有空白和首尾标点
+    ///
+    /// ## 参数说明
+    /// - `text`: 待脱敏的文本
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let redacted = PiiRedactionTransform::redact("mail me at a@b.com");
+    /// assert_eq!(redacted, "mail me at [REDACTED_EMAIL]");
+    /// ```
+    ///
+    /// ## 返回值
+    /// 脱敏后的文本
+    pub fn redact(text: &str) -> String {
+        text.split_inclusive(char::is_whitespace)
+            .map(|piece| {
+                let word = piece.trim_end_matches(char::is_whitespace);
+                let trailing_ws = &piece[word.len()..];
+                let core = word.trim_matches(|c: char| matches!(c, ',' | '.' | ';' | ':' | '!' | '?' | '"' | '\'' | '(' | ')' | '<' | '>' | '[' | ']'));
+                if core.is_empty() {
+                    return piece.to_string();
+                }
+                let replacement = if is_email(core) {
+                    REDACTED_EMAIL
+                } else if is_sensitive_number(core) {
+                    REDACTED_NUMBER
+                } else {
+                    return piece.to_string();
+                };
+                let start = word.find(core).unwrap_or(0);
+                format!(
+                    "{}{}{}{}",
+                    &word[..start],
+                    replacement,
+                    &word[start + core.len()..],
+                    trailing_ws
+                )
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Transform for PiiRedactionTransform {
+    async fn on_request(&self, req: &mut AnthropicRequest) {
+        if let Some(system) = &mut req.system {
+            *system = Self::redact(system);
+        }
+        for message in &mut req.messages {
+            message.content = Self::redact(&message.content);
+        }
+    }
+
+    async fn on_response(&self, res: &mut AnthropicResponse) {
+        for block in &mut res.content {
+            block.text = Self::redact(&block.text);
+        }
+    }
+}
+
+/// 按`[redaction]`配置进行的正则脱敏
+///
+/// 与转换钩子不同，它在请求进入代理时立即执行，早于日志记录和转换钩子，
+/// 且只作用于配置中列出的路由。
+#[derive(Debug, Clone, Default)]
+pub struct RegexRedactor {
+    patterns: Vec<Regex>,
+    replacement: String,
+    routes: Vec<RedactionRoute>,
+}
+
+impl RegexRedactor {
+    /// 根据配置创建脱敏器
+    ///
+    /// ## 参数说明
+    /// - `config`: `[redaction]`配置
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let redactor = RegexRedactor::from_config(&config.redaction)?;
+    /// ```
+    ///
+    /// ## 返回值
+    /// - `Ok(RegexRedactor)`: 编译好正则的脱敏器
+    /// - `Err(anyhow::Error)`: 某个正则无效
+    pub fn from_config(config: &RedactionConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            patterns: config.compile()?,
+            replacement: config.replacement.clone(),
+            routes: config.routes.clone(),
+        })
+    }
+
+    /// 对文本进行正则脱敏
+    ///
+    /// ## 功能说明
+    /// 按配置顺序将每个正则的所有匹配替换为`replacement`
+    ///
+    /// ## 参数说明
+    /// - `text`: 待脱敏的文本
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// let redacted = redactor.redact("card 4111 1111 1111 1111");
+    /// assert_eq!(redacted, "card [REDACTED]");
+    /// ```
+    ///
+    /// ## 返回值
+    /// 脱敏后的文本
+    pub fn redact(&self, text: &str) -> String {
+        self.patterns.iter().fold(text.to_string(), |text, pattern| {
+            pattern.replace_all(&text, self.replacement.as_str()).into_owned()
+        })
+    }
+
+    /// 对某个路由收到的请求进行脱敏
+    ///
+    /// ## 功能说明
+    /// 路由启用了脱敏时，替换系统提示和每条消息内容中的匹配项
+    ///
+    /// ## 参数说明
+    /// - `route`: 请求到达的入站路由
+    /// - `request`: 待脱敏的统一格式请求
+    ///
+    /// ## 执行例子
+    /// ```rust
+    /// state.redactor.redact_request(RedactionRoute::Messages, &mut request);
+    /// ```
+    ///
+    /// ## 返回值
+    /// 是否对请求执行了脱敏（路由未启用或未配置正则时为`false`）
+    pub fn redact_request(&self, route: RedactionRoute, request: &mut AnthropicRequest) -> bool {
+        if self.patterns.is_empty() || !self.routes.contains(&route) {
+            return false;
+        }
+        if let Some(system) = &mut request.system {
+            *system = self.redact(system);
+        }
+        for message in &mut request.messages {
+            message.content = self.redact(&message.content);
+        }
+        true
+    }
+}
+
+/// `local@domain.tld` with a non-empty local part and a dotted domain
+fn is_email(word: &str) -> bool {
+    let Some((local, domain)) = word.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && !domain.contains('@')
+        && domain.contains('.')
+        && domain.split('.').all(|label| {
+            !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+/// Digits with optional phone-style separators, long enough to identify someone
+fn is_sensitive_number(word: &str) -> bool {
+    word.chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '(' | ')'))
+        && word.chars().filter(char::is_ascii_digit).count() >= MIN_SENSITIVE_DIGITS
+}
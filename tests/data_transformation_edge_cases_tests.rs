@@ -15,10 +15,11 @@ fn test_anthropic_request_validation_edge_cases() {
     let unicode_request = AnthropicRequest {
         model: "claude-3-sonnet".to_string(),
         messages: vec![Message::user("Hello 世界! 🌍".to_string())],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: None,
         top_p: None,
+        ..Default::default()
     };
     assert!(unicode_request.validate().is_ok());
 
@@ -26,10 +27,11 @@ fn test_anthropic_request_validation_edge_cases() {
     let long_model_request = AnthropicRequest {
         model: "a".repeat(101),
         messages: vec![Message::user("Hello".to_string())],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: None,
         top_p: None,
+        ..Default::default()
     };
     assert!(long_model_request.validate().is_err());
 
@@ -37,10 +39,11 @@ fn test_anthropic_request_validation_edge_cases() {
     let special_char_request = AnthropicRequest {
         model: "claude@3#sonnet".to_string(),
         messages: vec![Message::user("Hello".to_string())],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: None,
         top_p: None,
+        ..Default::default()
     };
     assert!(special_char_request.validate().is_err());
 
@@ -48,10 +51,11 @@ fn test_anthropic_request_validation_edge_cases() {
     let nan_temp_request = AnthropicRequest {
         model: "claude-3-sonnet".to_string(),
         messages: vec![Message::user("Hello".to_string())],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: Some(f32::NAN),
         top_p: None,
+        ..Default::default()
     };
     assert!(nan_temp_request.validate().is_err());
 
@@ -59,10 +63,11 @@ fn test_anthropic_request_validation_edge_cases() {
     let inf_temp_request = AnthropicRequest {
         model: "claude-3-sonnet".to_string(),
         messages: vec![Message::user("Hello".to_string())],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: Some(f32::INFINITY),
         top_p: None,
+        ..Default::default()
     };
     assert!(inf_temp_request.validate().is_err());
 }
@@ -119,16 +124,17 @@ fn test_openai_request_conversion_edge_cases() {
             Message::assistant("Hi there".to_string()),
             Message::user("How are you?".to_string()),
         ],
-        max_tokens: 2048,
+        max_tokens: Some(2048),
         stream: Some(true),
         temperature: Some(1.5),
         top_p: Some(0.1),
+        ..Default::default()
     };
 
     let openai_request = OpenAIRequest::from_anthropic(&full_anthropic_request).unwrap();
     assert_eq!(openai_request.model, "gpt-4");
     assert_eq!(openai_request.messages.len(), 3);
-    assert_eq!(openai_request.max_tokens, 2048);
+    assert_eq!(openai_request.max_tokens, Some(2048));
     assert_eq!(openai_request.stream, Some(true));
     assert_eq!(openai_request.temperature, Some(1.5));
     assert_eq!(openai_request.top_p, Some(0.1));
@@ -137,16 +143,17 @@ fn test_openai_request_conversion_edge_cases() {
     let minimal_anthropic_request = AnthropicRequest {
         model: "gpt-3.5-turbo".to_string(),
         messages: vec![Message::user("Test".to_string())],
-        max_tokens: 1,
+        max_tokens: Some(1),
         stream: None,
         temperature: None,
         top_p: None,
+        ..Default::default()
     };
 
     let minimal_openai_request = OpenAIRequest::from_anthropic(&minimal_anthropic_request).unwrap();
     assert_eq!(minimal_openai_request.model, "gpt-3.5-turbo");
     assert_eq!(minimal_openai_request.messages.len(), 1);
-    assert_eq!(minimal_openai_request.max_tokens, 1);
+    assert_eq!(minimal_openai_request.max_tokens, Some(1));
     assert_eq!(minimal_openai_request.stream, None);
     assert_eq!(minimal_openai_request.temperature, None);
     assert_eq!(minimal_openai_request.top_p, None);
@@ -167,6 +174,8 @@ fn test_openai_response_conversion_edge_cases() {
                     role: "assistant".to_string(),
                     content: "First choice".to_string(),
                     name: None,
+                    tool_calls: None,
+                    refusal: None,
                 },
                 finish_reason: Some("stop".to_string()),
                 logprobs: None,
@@ -177,6 +186,8 @@ fn test_openai_response_conversion_edge_cases() {
                     role: "assistant".to_string(),
                     content: "Second choice".to_string(),
                     name: None,
+                    tool_calls: None,
+                    refusal: None,
                 },
                 finish_reason: Some("stop".to_string()),
                 logprobs: None,
@@ -205,6 +216,8 @@ fn test_openai_response_conversion_edge_cases() {
                 role: "assistant".to_string(),
                 content: "Truncated response".to_string(),
                 name: None,
+                tool_calls: None,
+                refusal: None,
             },
             finish_reason: Some("length".to_string()),
             logprobs: None,
@@ -232,6 +245,8 @@ fn test_openai_response_conversion_edge_cases() {
                 role: "assistant".to_string(),
                 content: "Response".to_string(),
                 name: None,
+                tool_calls: None,
+                refusal: None,
             },
             finish_reason: Some("stop".to_string()),
             logprobs: None,
@@ -258,10 +273,11 @@ fn test_gemini_request_conversion_edge_cases() {
             role: "system".to_string(),
             content: "You are a helpful assistant".to_string(),
         }],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: None,
         top_p: None,
+        ..Default::default()
     };
 
     let result = GeminiRequest::from_anthropic(&system_message_request);
@@ -277,10 +293,11 @@ fn test_gemini_request_conversion_edge_cases() {
             Message::user("How are you?".to_string()),
             Message::assistant("I'm good".to_string()),
         ],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: Some(false),
         temperature: Some(0.5),
         top_p: Some(0.8),
+        ..Default::default()
     };
 
     let gemini_request = GeminiRequest::from_anthropic(&alternating_request).unwrap();
@@ -300,6 +317,7 @@ fn test_gemini_response_conversion_edge_cases() {
                 role: "model".to_string(),
                 parts: vec![GeminiPart {
                     text: "Response without usage".to_string(),
+                    function_call: None,
                 }],
             },
             finish_reason: Some("STOP".to_string()),
@@ -323,6 +341,7 @@ fn test_gemini_response_conversion_edge_cases() {
                 role: "model".to_string(),
                 parts: vec![GeminiPart {
                     text: "Partial usage response".to_string(),
+                    function_call: None,
                 }],
             },
             finish_reason: Some("STOP".to_string()),
@@ -334,6 +353,7 @@ fn test_gemini_response_conversion_edge_cases() {
             prompt_token_count: Some(15),
             candidates_token_count: None,
             total_token_count: Some(20),
+            thoughts_token_count: None,
         }),
         prompt_feedback: None,
         error: None,
@@ -351,9 +371,11 @@ fn test_gemini_response_conversion_edge_cases() {
                 parts: vec![
                     GeminiPart {
                         text: "First part. ".to_string(),
+                        function_call: None,
                     },
                     GeminiPart {
                         text: "Second part.".to_string(),
+                        function_call: None,
                     },
                 ],
             },
@@ -366,6 +388,7 @@ fn test_gemini_response_conversion_edge_cases() {
             prompt_token_count: Some(5),
             candidates_token_count: Some(10),
             total_token_count: Some(15),
+            thoughts_token_count: None,
         }),
         prompt_feedback: None,
         error: None,
@@ -420,6 +443,7 @@ fn test_anthropic_stream_event_serialization() {
             usage: Usage {
                 input_tokens: 10,
                 output_tokens: 0,
+                thinking_tokens: None,
             },
         },
     };
@@ -446,6 +470,7 @@ fn test_anthropic_stream_event_serialization() {
         error: StreamError {
             error_type: "rate_limit_error".to_string(),
             message: "Rate limit exceeded".to_string(),
+            output_tokens: None,
         },
     };
 
@@ -460,10 +485,11 @@ fn test_token_estimation_accuracy() {
     let short_request = AnthropicRequest {
         model: "claude-3-sonnet".to_string(),
         messages: vec![Message::user("Hi".to_string())],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: None,
         top_p: None,
+        ..Default::default()
     };
     let short_tokens = short_request.estimate_input_tokens();
     assert!(short_tokens >= 1);
@@ -472,10 +498,11 @@ fn test_token_estimation_accuracy() {
     let long_request = AnthropicRequest {
         model: "claude-3-sonnet".to_string(),
         messages: vec![Message::user("This is a much longer message that should result in more estimated tokens because it contains significantly more text content.".to_string())],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: None,
         top_p: None,
+        ..Default::default()
     };
     let long_tokens = long_request.estimate_input_tokens();
     assert!(long_tokens > short_tokens);
@@ -489,10 +516,11 @@ fn test_token_estimation_accuracy() {
             Message::assistant("Assistant response".to_string()),
             Message::user("Second user message".to_string()),
         ],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: None,
         top_p: None,
+        ..Default::default()
     };
     let multi_tokens = multi_message_request.estimate_input_tokens();
     assert!(multi_tokens > short_tokens);
@@ -1,10 +1,11 @@
 use ai_proxy::
     providers::{
-        anthropic::{AnthropicRequest, AnthropicResponse, Message, SSEEvent, AnthropicStreamEvent},
-        openai::{OpenAIRequest, OpenAIResponse, OpenAIMessage, OpenAIChoice, OpenAIUsage},
+        anthropic::{AnthropicRequest, AnthropicResponse, Message, SSEEvent, AnthropicStreamEvent, ToolChoice, ToolDefinition, ValidationContext},
+        openai::{OpenAIRequest, OpenAIResponse, OpenAIMessage, OpenAIChoice, OpenAIUsage, OpenAIStreamConverter},
         gemini::{GeminiRequest, GeminiResponse, GeminiContent, GeminiPart, GeminiCandidate, UsageMetadata, GeminiStreamResponse, GeminiStreamCandidate},
     }
 ;
+use ai_proxy::config::ValidationMode;
 
 // Test data transformation functions for all providers
 
@@ -13,10 +14,11 @@ fn test_anthropic_request_validation_valid() {
     let request = AnthropicRequest {
         model: "claude-3-sonnet".to_string(),
         messages: vec![Message::user("Hello".to_string())],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: Some(false),
         temperature: Some(0.7),
         top_p: Some(0.9),
+        ..Default::default()
     };
     
     assert!(request.validate().is_ok());
@@ -27,10 +29,11 @@ fn test_anthropic_request_validation_empty_model() {
     let request = AnthropicRequest {
         model: "".to_string(),
         messages: vec![Message::user("Hello".to_string())],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: None,
         top_p: None,
+        ..Default::default()
     };
     
     let result = request.validate();
@@ -43,10 +46,11 @@ fn test_anthropic_request_validation_invalid_model_chars() {
     let request = AnthropicRequest {
         model: "claude@3#sonnet".to_string(),
         messages: vec![Message::user("Hello".to_string())],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: None,
         top_p: None,
+        ..Default::default()
     };
     
     let result = request.validate();
@@ -59,10 +63,11 @@ fn test_anthropic_request_validation_empty_messages() {
     let request = AnthropicRequest {
         model: "claude-3-sonnet".to_string(),
         messages: vec![],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: None,
         top_p: None,
+        ..Default::default()
     };
     
     let result = request.validate();
@@ -76,10 +81,11 @@ fn test_anthropic_request_validation_too_many_messages() {
     let request = AnthropicRequest {
         model: "claude-3-sonnet".to_string(),
         messages,
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: None,
         top_p: None,
+        ..Default::default()
     };
     
     let result = request.validate();
@@ -92,10 +98,11 @@ fn test_anthropic_request_validation_zero_max_tokens() {
     let request = AnthropicRequest {
         model: "claude-3-sonnet".to_string(),
         messages: vec![Message::user("Hello".to_string())],
-        max_tokens: 0,
+        max_tokens: Some(0),
         stream: None,
         temperature: None,
         top_p: None,
+        ..Default::default()
     };
     
     let result = request.validate();
@@ -108,10 +115,11 @@ fn test_anthropic_request_validation_excessive_max_tokens() {
     let request = AnthropicRequest {
         model: "claude-3-sonnet".to_string(),
         messages: vec![Message::user("Hello".to_string())],
-        max_tokens: 10000,
+        max_tokens: Some(10000),
         stream: None,
         temperature: None,
         top_p: None,
+        ..Default::default()
     };
     
     let result = request.validate();
@@ -119,15 +127,64 @@ fn test_anthropic_request_validation_excessive_max_tokens() {
     assert!(result.unwrap_err().contains("max_tokens cannot exceed 8192"));
 }
 
+#[test]
+fn test_anthropic_request_schema_errors() {
+    let valid = serde_json::json!({
+        "model": "claude-3-sonnet",
+        "messages": [{"role": "user", "content": "Hi"}],
+        "temperature": null
+    });
+    assert!(AnthropicRequest::schema_errors(&valid).is_empty());
+
+    let invalid = serde_json::json!({
+        "max_tokens": -5,
+        "stream": "yes",
+        "messages": [{"role": "user"}, "hello"]
+    });
+    let errors = AnthropicRequest::schema_errors(&invalid);
+    let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+    assert_eq!(
+        fields,
+        vec!["model", "max_tokens", "stream", "messages[0].content", "messages[1]"]
+    );
+    assert_eq!(errors[0].problem, "missing required field");
+    assert_eq!(errors[2].problem, "expected boolean");
+
+    let not_object = serde_json::json!([1, 2]);
+    assert_eq!(AnthropicRequest::schema_errors(&not_object)[0].field, "body");
+}
+
+#[test]
+fn test_anthropic_request_omitted_max_tokens_gets_default() {
+    let mut request: AnthropicRequest = serde_json::from_value(serde_json::json!({
+        "model": "claude-3-sonnet",
+        "messages": [{"role": "user", "content": "Hello"}]
+    }))
+    .unwrap();
+    assert_eq!(request.max_tokens, None);
+    assert!(request.validate().is_ok());
+
+    request.apply_default_max_tokens(512);
+    assert_eq!(request.max_tokens, Some(512));
+
+    // An explicit value, even an invalid one, is never replaced by the default
+    let mut explicit = request.clone();
+    explicit.max_tokens = Some(0);
+    explicit.apply_default_max_tokens(512);
+    assert_eq!(explicit.max_tokens, Some(0));
+    assert!(explicit.validate().is_err());
+}
+
 #[test]
 fn test_anthropic_request_validation_invalid_temperature() {
     let request = AnthropicRequest {
         model: "claude-3-sonnet".to_string(),
         messages: vec![Message::user("Hello".to_string())],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: Some(-1.0),
         top_p: None,
+        ..Default::default()
     };
     
     let result = request.validate();
@@ -137,10 +194,11 @@ fn test_anthropic_request_validation_invalid_temperature() {
     let request = AnthropicRequest {
         model: "claude-3-sonnet".to_string(),
         messages: vec![Message::user("Hello".to_string())],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: Some(3.0),
         top_p: None,
+        ..Default::default()
     };
     
     let result = request.validate();
@@ -153,10 +211,11 @@ fn test_anthropic_request_validation_invalid_top_p() {
     let request = AnthropicRequest {
         model: "claude-3-sonnet".to_string(),
         messages: vec![Message::user("Hello".to_string())],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: None,
         top_p: Some(-0.1),
+        ..Default::default()
     };
     
     let result = request.validate();
@@ -166,10 +225,11 @@ fn test_anthropic_request_validation_invalid_top_p() {
     let request = AnthropicRequest {
         model: "claude-3-sonnet".to_string(),
         messages: vec![Message::user("Hello".to_string())],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: None,
         top_p: Some(1.5),
+        ..Default::default()
     };
     
     let result = request.validate();
@@ -186,10 +246,11 @@ fn test_anthropic_request_validation_excessive_content_length() {
             Message::user(long_content.clone()),
             Message::assistant(long_content),
         ],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: None,
         top_p: None,
+        ..Default::default()
     };
     
     let result = request.validate();
@@ -202,10 +263,11 @@ fn test_anthropic_request_is_streaming() {
     let mut request = AnthropicRequest {
         model: "claude-3-sonnet".to_string(),
         messages: vec![Message::user("Hello".to_string())],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: None,
         top_p: None,
+        ..Default::default()
     };
     
     assert!(!request.is_streaming());
@@ -225,10 +287,11 @@ fn test_anthropic_request_estimate_input_tokens() {
             Message::user("Hello world".to_string()), // ~11 chars + 4 (role) = 15 chars
             Message::assistant("Hi there".to_string()), // ~8 chars + 9 (role) = 17 chars
         ],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: None,
         top_p: None,
+        ..Default::default()
     };
     
     let estimated = request.estimate_input_tokens();
@@ -342,22 +405,25 @@ fn test_openai_request_from_anthropic() {
         messages: vec![
             Message::user("Hello".to_string()),
             Message::assistant("Hi there".to_string()),
+            Message::user("How are you?".to_string()),
         ],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: Some(true),
         temperature: Some(0.7),
         top_p: Some(0.9),
+        ..Default::default()
     };
     
     let openai_request = OpenAIRequest::from_anthropic(&anthropic_request).unwrap();
     
     assert_eq!(openai_request.model, "gpt-4");
-    assert_eq!(openai_request.messages.len(), 2);
+    assert_eq!(openai_request.messages.len(), 3);
     assert_eq!(openai_request.messages[0].role, "user");
     assert_eq!(openai_request.messages[0].content, "Hello");
     assert_eq!(openai_request.messages[1].role, "assistant");
     assert_eq!(openai_request.messages[1].content, "Hi there");
-    assert_eq!(openai_request.max_tokens, 100);
+    assert_eq!(openai_request.messages[2].role, "user");
+    assert_eq!(openai_request.max_tokens, Some(100));
     assert_eq!(openai_request.stream, Some(true));
     assert_eq!(openai_request.temperature, Some(0.7));
     assert_eq!(openai_request.top_p, Some(0.9));
@@ -371,6 +437,8 @@ fn test_openai_request_builder_methods() {
             role: "user".to_string(),
             content: "Hello".to_string(),
             name: None,
+            tool_calls: None,
+            refusal: None,
         }],
         100,
     )
@@ -399,6 +467,8 @@ fn test_openai_request_validation() {
             role: "user".to_string(),
             content: "Hello".to_string(),
             name: None,
+            tool_calls: None,
+            refusal: None,
         }],
         100,
     );
@@ -417,12 +487,12 @@ fn test_openai_request_validation() {
     
     // Test zero max_tokens
     let mut invalid_request = valid_request.clone();
-    invalid_request.max_tokens = 0;
+    invalid_request.max_tokens = Some(0);
     assert!(invalid_request.validate().is_err());
     
     // Test excessive max_tokens
     let mut invalid_request = valid_request.clone();
-    invalid_request.max_tokens = 5000;
+    invalid_request.max_tokens = Some(5000);
     assert!(invalid_request.validate().is_err());
     
     // Test invalid temperature
@@ -464,6 +534,8 @@ fn test_openai_response_to_anthropic() {
                 role: "assistant".to_string(),
                 content: "Hello! How can I help you?".to_string(),
                 name: None,
+                tool_calls: None,
+                refusal: None,
             },
             finish_reason: Some("stop".to_string()),
             logprobs: None,
@@ -486,6 +558,48 @@ fn test_openai_response_to_anthropic() {
     assert_eq!(anthropic_response.usage.output_tokens, 25);
 }
 
+#[test]
+fn test_openai_response_finish_reason_maps_to_stop_reason() {
+    let cases = [
+        (Some("stop"), Some("end_turn")),
+        (Some("length"), Some("max_tokens")),
+        (Some("content_filter"), Some("content_filter")),
+        (Some("tool_calls"), Some("tool_use")),
+        (Some("something_new"), Some("stop_sequence")),
+        (None, None),
+    ];
+
+    for (finish_reason, expected) in cases {
+        let openai_response: OpenAIResponse = serde_json::from_value(serde_json::json!({
+            "id": "chatcmpl-123",
+            "object": "chat.completion",
+            "created": 1234567890,
+            "model": "gpt-4",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hi"},
+                "finish_reason": finish_reason
+            }],
+            "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2}
+        }))
+        .unwrap();
+
+        let anthropic_response = openai_response.to_anthropic().unwrap();
+        assert_eq!(anthropic_response.stop_reason.as_deref(), expected, "finish_reason: {:?}", finish_reason);
+    }
+}
+
+#[test]
+fn test_anthropic_response_stop_reason_round_trips_to_openai() {
+    let response = AnthropicResponse::new("msg_123".to_string(), "gpt-4".to_string(), "Hi".to_string(), 1, 1);
+    // Omitted entirely when unknown, for backward compatibility
+    assert!(serde_json::to_value(&response).unwrap().get("stop_reason").is_none());
+
+    let response = response.with_stop_reason(Some("max_tokens".to_string()));
+    assert_eq!(serde_json::to_value(&response).unwrap()["stop_reason"], "max_tokens");
+    assert_eq!(response.to_openai().choices[0].finish_reason.as_deref(), Some("length"));
+}
+
 #[test]
 fn test_openai_response_to_anthropic_no_choices() {
     let openai_response = OpenAIResponse {
@@ -520,6 +634,8 @@ fn test_openai_response_to_anthropic_empty_content() {
                 role: "assistant".to_string(),
                 content: "".to_string(),
                 name: None,
+                tool_calls: None,
+                refusal: None,
             },
             finish_reason: Some("stop".to_string()),
             logprobs: None,
@@ -550,6 +666,8 @@ fn test_openai_response_get_finish_reason() {
                 role: "assistant".to_string(),
                 content: "Hello".to_string(),
                 name: None,
+                tool_calls: None,
+                refusal: None,
             },
             finish_reason: Some("stop".to_string()),
             logprobs: None,
@@ -579,6 +697,8 @@ fn test_openai_response_get_usage_info() {
                 role: "assistant".to_string(),
                 content: "Hello".to_string(),
                 name: None,
+                tool_calls: None,
+                refusal: None,
             },
             finish_reason: Some("stop".to_string()),
             logprobs: None,
@@ -611,6 +731,8 @@ fn test_openai_response_has_issues() {
                 role: "assistant".to_string(),
                 content: "Hello".to_string(),
                 name: None,
+                tool_calls: None,
+                refusal: None,
             },
             finish_reason: Some("stop".to_string()),
             logprobs: None,
@@ -654,6 +776,8 @@ fn test_openai_response_has_issues() {
                 role: "assistant".to_string(),
                 content: "".to_string(),
                 name: None,
+                tool_calls: None,
+                refusal: None,
             },
             finish_reason: Some("stop".to_string()),
             logprobs: None,
@@ -669,6 +793,132 @@ fn test_openai_response_has_issues() {
     assert!(empty_response.has_issues());
 }
 
+#[test]
+fn test_openai_request_to_anthropic() {
+    let openai_request = OpenAIRequest::new(
+        "gpt-4".to_string(),
+        vec![
+            OpenAIMessage {
+                role: "system".to_string(),
+                content: "You are terse.".to_string(),
+                name: None,
+                tool_calls: None,
+                refusal: None,
+            },
+            OpenAIMessage {
+                role: "user".to_string(),
+                content: "Hello".to_string(),
+                name: None,
+                tool_calls: None,
+                refusal: None,
+            },
+            OpenAIMessage {
+                role: "assistant".to_string(),
+                content: "Hi".to_string(),
+                name: None,
+                tool_calls: None,
+                refusal: None,
+            },
+        ],
+        100,
+    )
+    .with_stream(true)
+    .with_temperature(0.5)
+    .with_frequency_penalty(0.3);
+
+    let anthropic_request = openai_request.to_anthropic().unwrap();
+
+    assert_eq!(anthropic_request.model, "gpt-4");
+    assert_eq!(anthropic_request.system.as_deref(), Some("You are terse."));
+    assert_eq!(anthropic_request.messages.len(), 2);
+    assert_eq!(anthropic_request.messages[0].role, "user");
+    assert_eq!(anthropic_request.messages[1].role, "assistant");
+    assert_eq!(anthropic_request.max_tokens, Some(100));
+    assert_eq!(anthropic_request.stream, Some(true));
+    assert_eq!(anthropic_request.temperature, Some(0.5));
+    assert!(anthropic_request.validate().is_ok());
+}
+
+#[test]
+fn test_openai_request_to_anthropic_invalid_role() {
+    let openai_request = OpenAIRequest::new(
+        "gpt-4".to_string(),
+        vec![OpenAIMessage {
+            role: "tool".to_string(),
+            content: "result".to_string(),
+            name: None,
+            tool_calls: None,
+            refusal: None,
+        }],
+        100,
+    );
+
+    assert!(openai_request.to_anthropic().is_err());
+}
+
+#[test]
+fn test_openai_request_from_anthropic_with_system() {
+    let anthropic_request = AnthropicRequest {
+        model: "gpt-4".to_string(),
+        messages: vec![Message::user("Hello".to_string())],
+        max_tokens: Some(100),
+        system: Some("Be helpful".to_string()),
+        ..Default::default()
+    };
+
+    let openai_request = OpenAIRequest::from_anthropic(&anthropic_request).unwrap();
+
+    assert_eq!(openai_request.messages.len(), 2);
+    assert_eq!(openai_request.messages[0].role, "system");
+    assert_eq!(openai_request.messages[0].content, "Be helpful");
+    assert_eq!(openai_request.messages[1].role, "user");
+}
+
+#[test]
+fn test_anthropic_response_to_openai() {
+    let anthropic_response = AnthropicResponse::new(
+        "msg_123".to_string(),
+        "claude-3-sonnet".to_string(),
+        "Hello there".to_string(),
+        10,
+        5,
+    );
+
+    let openai_response = anthropic_response.to_openai();
+
+    assert_eq!(openai_response.id, "msg_123");
+    assert_eq!(openai_response.object, "chat.completion");
+    assert_eq!(openai_response.model, "claude-3-sonnet");
+    assert_eq!(openai_response.choices.len(), 1);
+    assert_eq!(openai_response.choices[0].message.role, "assistant");
+    assert_eq!(openai_response.choices[0].message.content, "Hello there");
+    assert_eq!(openai_response.choices[0].finish_reason.as_deref(), Some("stop"));
+    assert_eq!(openai_response.usage.prompt_tokens, 10);
+    assert_eq!(openai_response.usage.completion_tokens, 5);
+    assert_eq!(openai_response.usage.total_tokens, 15);
+}
+
+#[test]
+fn test_openai_stream_converter() {
+    let mut converter = OpenAIStreamConverter::new("claude-3-sonnet".to_string());
+
+    let start = converter.push("event: message_start\ndata: {\"type\":\"message_start\",\"message\":{}}\n\n");
+    assert!(start.contains("\"role\":\"assistant\""));
+    assert!(start.contains("chat.completion.chunk"));
+
+    // Events split across chunks are buffered until complete
+    let partial = converter.push("event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,");
+    assert!(partial.is_empty());
+    let delta = converter.push("\"delta\":{\"type\":\"text_delta\",\"text\":\"Hi\"}}\n\n");
+    assert!(delta.contains("\"content\":\"Hi\""));
+
+    let finish = converter.push("event: message_delta\ndata: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"max_tokens\"}}\n\n");
+    assert!(finish.contains("\"finish_reason\":\"length\""));
+
+    let done = converter.push("event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n");
+    assert_eq!(done, "data: [DONE]\n\n");
+}
+
 // Gemini transformation tests
 
 #[test]
@@ -679,10 +929,11 @@ fn test_gemini_request_from_anthropic() {
             Message::user("Hello".to_string()),
             Message::assistant("Hi there".to_string()),
         ],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: Some(false),
         temperature: Some(0.7),
         top_p: Some(0.9),
+        ..Default::default()
     };
     
     let gemini_request = GeminiRequest::from_anthropic(&anthropic_request).unwrap();
@@ -705,10 +956,11 @@ fn test_gemini_request_from_anthropic_invalid_role() {
             role: "system".to_string(),
             content: "You are a helpful assistant".to_string(),
         }],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: None,
         top_p: None,
+        ..Default::default()
     };
     
     let result = GeminiRequest::from_anthropic(&anthropic_request);
@@ -723,6 +975,7 @@ fn test_gemini_request_validation() {
             role: "user".to_string(),
             parts: vec![GeminiPart {
                 text: "Hello".to_string(),
+                function_call: None,
             }],
         }],
         100,
@@ -774,6 +1027,7 @@ fn test_gemini_response_to_anthropic() {
                 role: "model".to_string(),
                 parts: vec![GeminiPart {
                     text: "Hello! How can I help you?".to_string(),
+                    function_call: None,
                 }],
             },
             finish_reason: Some("STOP".to_string()),
@@ -785,6 +1039,7 @@ fn test_gemini_response_to_anthropic() {
             prompt_token_count: Some(10),
             candidates_token_count: Some(25),
             total_token_count: Some(35),
+            thoughts_token_count: None,
         }),
         prompt_feedback: None,
         error: None,
@@ -813,6 +1068,31 @@ fn test_gemini_response_to_anthropic_no_candidates() {
     assert!(result.unwrap_err().to_string().contains("No candidates in Gemini response"));
 }
 
+#[test]
+fn test_gemini_response_finish_reason_maps_to_stop_reason() {
+    let cases = [
+        (Some("STOP"), Some("end_turn")),
+        (Some("MAX_TOKENS"), Some("max_tokens")),
+        (Some("SAFETY"), Some("content_filter")),
+        (Some("OTHER"), Some("stop_sequence")),
+        (None, None),
+    ];
+
+    for (finish_reason, expected) in cases {
+        let gemini_response: GeminiResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [{"text": "Partial answer"}]},
+                "finishReason": finish_reason,
+                "index": 0
+            }]
+        }))
+        .unwrap();
+
+        let anthropic_response = gemini_response.to_anthropic("gemini-pro").unwrap();
+        assert_eq!(anthropic_response.stop_reason.as_deref(), expected, "finishReason: {:?}", finish_reason);
+    }
+}
+
 #[test]
 fn test_gemini_response_get_finish_reason() {
     let gemini_response = GeminiResponse {
@@ -821,6 +1101,7 @@ fn test_gemini_response_get_finish_reason() {
                 role: "model".to_string(),
                 parts: vec![GeminiPart {
                     text: "Hello".to_string(),
+                    function_call: None,
                 }],
             },
             finish_reason: Some("STOP".to_string()),
@@ -845,6 +1126,7 @@ fn test_gemini_response_get_usage_info() {
                 role: "model".to_string(),
                 parts: vec![GeminiPart {
                     text: "Hello".to_string(),
+                    function_call: None,
                 }],
             },
             finish_reason: Some("STOP".to_string()),
@@ -856,6 +1138,7 @@ fn test_gemini_response_get_usage_info() {
             prompt_token_count: Some(10),
             candidates_token_count: Some(5),
             total_token_count: Some(15),
+            thoughts_token_count: None,
         }),
         prompt_feedback: None,
         error: None,
@@ -878,6 +1161,7 @@ fn test_gemini_response_has_safety_issues() {
                 role: "model".to_string(),
                 parts: vec![GeminiPart {
                     text: "Hello".to_string(),
+                    function_call: None,
                 }],
             },
             finish_reason: Some("STOP".to_string()),
@@ -903,6 +1187,7 @@ fn test_gemini_response_has_safety_issues() {
                 role: "model".to_string(),
                 parts: vec![GeminiPart {
                     text: "Hello".to_string(),
+                    function_call: None,
                 }],
             },
             finish_reason: Some("STOP".to_string()),
@@ -930,6 +1215,7 @@ fn test_gemini_stream_response_to_anthropic_events() {
                 role: "model".to_string(),
                 parts: vec![GeminiPart {
                     text: "Hello".to_string(),
+                    function_call: None,
                 }],
             }),
             finish_reason: Some("STOP".to_string()),
@@ -939,7 +1225,9 @@ fn test_gemini_stream_response_to_anthropic_events() {
             prompt_token_count: Some(5),
             candidates_token_count: Some(10),
             total_token_count: Some(15),
+            thoughts_token_count: None,
         }),
+        prompt_feedback: None,
     };
     
     let events = stream_response.to_anthropic_events("gemini-pro", "msg_123").unwrap();
@@ -948,4 +1236,823 @@ fn test_gemini_stream_response_to_anthropic_events() {
     // Should contain content delta and message stop events
     assert!(events.iter().any(|e| matches!(e, AnthropicStreamEvent::ContentBlockDelta { .. })));
     assert!(events.iter().any(|e| matches!(e, AnthropicStreamEvent::MessageStop)));
-}
\ No newline at end of file
+}
+#[test]
+fn test_anthropic_request_validation_n() {
+    let mut request = AnthropicRequest {
+        model: "gpt-4".to_string(),
+        messages: vec![Message::user("Hello".to_string())],
+        max_tokens: Some(100),
+        n: Some(2),
+        ..Default::default()
+    };
+    assert!(request.validate().is_ok());
+
+    request.n = Some(0);
+    assert!(request.validate().unwrap_err().contains("n must be between 1 and 8"));
+
+    request.n = Some(9);
+    assert!(request.validate().unwrap_err().contains("n must be between 1 and 8"));
+
+    request.n = Some(2);
+    request.stream = Some(true);
+    assert!(request.validate().unwrap_err().contains("streaming"));
+}
+
+#[test]
+fn test_n_maps_to_openai_n_and_gemini_candidate_count() {
+    let request = AnthropicRequest {
+        model: "gpt-4".to_string(),
+        messages: vec![Message::user("Hello".to_string())],
+        max_tokens: Some(100),
+        n: Some(3),
+        ..Default::default()
+    };
+
+    let openai_request = OpenAIRequest::from_anthropic(&request).unwrap();
+    assert_eq!(openai_request.n, Some(3));
+
+    let gemini_request = GeminiRequest::from_anthropic(&request).unwrap();
+    assert_eq!(gemini_request.generation_config.candidate_count, Some(3));
+
+    // `n` is never forwarded in the Anthropic wire format
+    let wire = serde_json::to_value(&request).unwrap();
+    assert!(wire.get("n").is_none());
+}
+
+#[test]
+fn test_thinking_budget_maps_to_gemini_thinking_config() {
+    let request = AnthropicRequest {
+        model: "gemini-2.5-flash".to_string(),
+        messages: vec![Message::user("Hello".to_string())],
+        max_tokens: Some(100),
+        thinking_budget: Some(1024),
+        ..Default::default()
+    };
+
+    let gemini_request = GeminiRequest::from_anthropic(&request).unwrap();
+    let wire = serde_json::to_value(&gemini_request).unwrap();
+    assert_eq!(wire["generationConfig"]["thinkingConfig"]["thinkingBudget"], 1024);
+
+    // Other providers never see the budget
+    let openai_wire = serde_json::to_value(OpenAIRequest::from_anthropic(&request).unwrap()).unwrap();
+    assert!(!openai_wire.to_string().contains("thinking"));
+    let anthropic_wire = serde_json::to_value(&request).unwrap();
+    assert!(anthropic_wire.get("thinking_budget").is_none());
+
+    let without_budget = AnthropicRequest { thinking_budget: None, ..request };
+    let wire = serde_json::to_value(GeminiRequest::from_anthropic(&without_budget).unwrap()).unwrap();
+    assert!(wire["generationConfig"].get("thinkingConfig").is_none());
+}
+
+#[test]
+fn test_user_reaches_openai_only() {
+    let request: AnthropicRequest = serde_json::from_value(serde_json::json!({
+        "model": "gpt-4",
+        "messages": [{"role": "user", "content": "Hello"}],
+        "max_tokens": 100,
+        "user": "user-1234"
+    }))
+    .unwrap();
+    assert!(request.validate().is_ok());
+
+    let openai_wire = serde_json::to_value(OpenAIRequest::from_anthropic(&request).unwrap()).unwrap();
+    assert_eq!(openai_wire["user"], "user-1234");
+
+    // Other providers have no equivalent
+    let anthropic_wire = serde_json::to_value(&request).unwrap();
+    assert!(anthropic_wire.get("user").is_none());
+    let gemini_wire = serde_json::to_value(GeminiRequest::from_anthropic(&request).unwrap()).unwrap();
+    assert!(gemini_wire.get("user").is_none());
+
+    // Inbound OpenAI requests keep their user
+    let inbound: OpenAIRequest = serde_json::from_value(serde_json::json!({
+        "model": "gpt-4",
+        "messages": [{"role": "user", "content": "Hello"}],
+        "user": "user-5678"
+    }))
+    .unwrap();
+    assert_eq!(inbound.to_anthropic().unwrap().user.as_deref(), Some("user-5678"));
+
+    let too_long = AnthropicRequest { user: Some("u".repeat(257)), ..request.clone() };
+    assert!(too_long.validate().unwrap_err().contains("user must be between 1 and 256 characters"));
+    let blank = AnthropicRequest { user: Some("  ".to_string()), ..request };
+    assert!(blank.validate().is_err());
+}
+
+#[test]
+fn test_seed_reaches_openai_and_gemini() {
+    let request = AnthropicRequest {
+        model: "gpt-4".to_string(),
+        messages: vec![Message::user("Hello".to_string())],
+        max_tokens: Some(100),
+        seed: Some(-42),
+        ..Default::default()
+    };
+
+    let openai_wire = serde_json::to_value(OpenAIRequest::from_anthropic(&request).unwrap()).unwrap();
+    assert_eq!(openai_wire["seed"], -42);
+    let gemini_wire = serde_json::to_value(GeminiRequest::from_anthropic(&request).unwrap()).unwrap();
+    assert_eq!(gemini_wire["generationConfig"]["seed"], -42);
+
+    // Anthropic has no equivalent, so the seed never reaches it
+    let anthropic_wire = serde_json::to_value(&request).unwrap();
+    assert!(anthropic_wire.get("seed").is_none());
+
+    // Unseeded requests send no seed anywhere
+    let unseeded = AnthropicRequest { seed: None, ..request };
+    let openai_wire = serde_json::to_value(OpenAIRequest::from_anthropic(&unseeded).unwrap()).unwrap();
+    assert!(openai_wire.get("seed").is_none());
+    let gemini_wire = serde_json::to_value(GeminiRequest::from_anthropic(&unseeded).unwrap()).unwrap();
+    assert!(gemini_wire["generationConfig"].get("seed").is_none());
+
+    // Inbound OpenAI requests keep their seed
+    let inbound: OpenAIRequest = serde_json::from_value(serde_json::json!({
+        "model": "gpt-4",
+        "messages": [{"role": "user", "content": "Hello"}],
+        "seed": 7
+    }))
+    .unwrap();
+    assert_eq!(inbound.to_anthropic().unwrap().seed, Some(7));
+}
+
+#[test]
+fn test_seed_schema_and_cache_key() {
+    let errors = AnthropicRequest::schema_errors(&serde_json::json!({
+        "model": "gpt-4",
+        "messages": [{"role": "user", "content": "Hello"}],
+        "seed": 1.5
+    }));
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].field, "seed");
+
+    let request = |seed: Option<i64>| AnthropicRequest {
+        model: "gpt-4".to_string(),
+        messages: vec![Message::user("Hello".to_string())],
+        temperature: Some(0.0),
+        seed,
+        ..Default::default()
+    };
+    assert_ne!(request(Some(1)).cache_key(), request(Some(2)).cache_key());
+    assert_eq!(request(Some(1)).cache_key(), request(Some(1)).cache_key());
+}
+
+#[test]
+fn test_thinking_budget_in_cache_key() {
+    let request = |thinking_budget: Option<u32>| AnthropicRequest {
+        model: "claude-3-sonnet".to_string(),
+        messages: vec![Message::user("Hello".to_string())],
+        temperature: Some(0.0),
+        thinking_budget,
+        ..Default::default()
+    };
+    assert_ne!(request(Some(1024)).cache_key(), request(Some(4096)).cache_key());
+    assert_ne!(request(Some(1024)).cache_key(), request(None).cache_key());
+    assert_eq!(request(Some(1024)).cache_key(), request(Some(1024)).cache_key());
+}
+
+#[test]
+fn test_openai_system_fingerprint_round_trip() {
+    let openai_response: OpenAIResponse = serde_json::from_value(serde_json::json!({
+        "id": "chatcmpl-seed",
+        "object": "chat.completion",
+        "created": 1714560000,
+        "model": "gpt-4",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": "Hello"},
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 5, "completion_tokens": 1, "total_tokens": 6},
+        "system_fingerprint": "fp_44709d6fcb"
+    }))
+    .unwrap();
+
+    let anthropic_response = openai_response.to_anthropic().unwrap();
+    assert_eq!(anthropic_response.system_fingerprint.as_deref(), Some("fp_44709d6fcb"));
+    // Not a native Anthropic field
+    let wire = serde_json::to_value(&anthropic_response).unwrap();
+    assert!(wire.get("system_fingerprint").is_none());
+
+    assert_eq!(anthropic_response.to_openai().system_fingerprint.as_deref(), Some("fp_44709d6fcb"));
+}
+
+#[test]
+fn test_gemini_thoughts_token_count_reaches_usage() {
+    let gemini_response: GeminiResponse = serde_json::from_value(serde_json::json!({
+        "candidates": [{
+            "content": {"role": "model", "parts": [{"text": "42"}]},
+            "finishReason": "STOP",
+            "index": 0
+        }],
+        "usageMetadata": {
+            "promptTokenCount": 8,
+            "candidatesTokenCount": 2,
+            "thoughtsTokenCount": 120,
+            "totalTokenCount": 130
+        }
+    }))
+    .unwrap();
+
+    let anthropic_response = gemini_response.to_anthropic("gemini-2.5-flash").unwrap();
+    assert_eq!(anthropic_response.usage.output_tokens, 2);
+    assert_eq!(anthropic_response.usage.thinking_tokens, Some(120));
+
+    let wire = serde_json::to_value(&anthropic_response).unwrap();
+    assert_eq!(wire["usage"]["thinking_tokens"], 120);
+}
+
+#[test]
+fn test_openai_response_two_choices_to_anthropic() {
+    let choice = |index: u32, content: &str| OpenAIChoice {
+        index,
+        message: OpenAIMessage {
+            role: "assistant".to_string(),
+            content: content.to_string(),
+            name: None,
+            tool_calls: None,
+            refusal: None,
+        },
+        finish_reason: Some("stop".to_string()),
+        logprobs: None,
+    };
+
+    let openai_response = OpenAIResponse {
+        id: "chatcmpl-multi".to_string(),
+        object: "chat.completion".to_string(),
+        created: 1234567890,
+        model: "gpt-4".to_string(),
+        choices: vec![choice(1, "Second answer"), choice(0, "First answer")],
+        usage: OpenAIUsage {
+            prompt_tokens: 10,
+            completion_tokens: 8,
+            total_tokens: 18,
+        },
+        system_fingerprint: None,
+    };
+
+    let anthropic_response = openai_response.to_anthropic().unwrap();
+
+    assert_eq!(anthropic_response.content.len(), 2);
+    assert_eq!(anthropic_response.content[0].text, "First answer");
+    assert_eq!(anthropic_response.content[1].text, "Second answer");
+    assert_eq!(anthropic_response.usage.output_tokens, 8);
+
+    // Round-trips back to one OpenAI choice per candidate
+    let openai_again = anthropic_response.to_openai_choices();
+    assert_eq!(openai_again.choices.len(), 2);
+    assert_eq!(openai_again.choices[1].index, 1);
+    assert_eq!(openai_again.choices[1].message.content, "Second answer");
+}
+
+#[test]
+fn test_gemini_response_two_candidates_to_anthropic() {
+    let candidate = |index: i32, text: &str| GeminiCandidate {
+        content: GeminiContent {
+            role: "model".to_string(),
+            parts: vec![GeminiPart {
+                text: text.to_string(),
+                function_call: None,
+            }],
+        },
+        finish_reason: Some("STOP".to_string()),
+        index: Some(index),
+        safety_ratings: None,
+        citation_metadata: None,
+    };
+
+    let gemini_response = GeminiResponse {
+        candidates: vec![candidate(0, "First answer"), candidate(1, "Second answer")],
+        usage_metadata: Some(UsageMetadata {
+            prompt_token_count: Some(10),
+            candidates_token_count: Some(8),
+            total_token_count: Some(18),
+            thoughts_token_count: None,
+        }),
+        prompt_feedback: None,
+        error: None,
+    };
+
+    let anthropic_response = gemini_response.to_anthropic("gemini-pro").unwrap();
+
+    assert_eq!(anthropic_response.content.len(), 2);
+    assert_eq!(anthropic_response.content[0].text, "First answer");
+    assert_eq!(anthropic_response.content[1].text, "Second answer");
+    assert_eq!(anthropic_response.usage.input_tokens, 10);
+    assert_eq!(anthropic_response.usage.output_tokens, 8);
+}
+
+fn consecutive_user_request() -> AnthropicRequest {
+    AnthropicRequest {
+        model: "claude-3-sonnet".to_string(),
+        messages: vec![
+            Message::user("Here is the tool output".to_string()),
+            Message::user("Now summarize it".to_string()),
+            Message::assistant("Summary".to_string()),
+        ],
+        max_tokens: Some(100),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_strict_role_alternation_rejects_consecutive_user_messages() {
+    let request = consecutive_user_request();
+
+    let error = request.validate().unwrap_err();
+    assert!(error.contains("Invalid role sequence at message 1"));
+
+    let strict = ValidationContext { strict_role_alternation: true, ..Default::default() };
+    assert!(request.validate_with(&strict).is_err());
+}
+
+#[test]
+fn test_strict_role_alternation_accepts_trailing_assistant_prefill() {
+    let mut request = AnthropicRequest {
+        model: "claude-3-sonnet".to_string(),
+        messages: vec![
+            Message::user("Name a color".to_string()),
+            Message::assistant("Blue".to_string()),
+            Message::user("Another one, as JSON".to_string()),
+            Message::assistant("{\"color\":".to_string()),
+        ],
+        max_tokens: Some(100),
+        ..Default::default()
+    };
+    assert!(request.validate().is_ok());
+
+    // Two assistant messages in a row are still rejected
+    request.messages = vec![
+        Message::user("Name a color".to_string()),
+        Message::assistant("Blue".to_string()),
+        Message::assistant("Green".to_string()),
+    ];
+    let error = request.validate().unwrap_err();
+    assert!(error.contains("Invalid role sequence at message 2: expected 'user', got 'assistant'"), "{}", error);
+}
+
+#[test]
+fn test_relaxed_role_alternation_accepts_consecutive_user_messages() {
+    let relaxed = ValidationContext { strict_role_alternation: false, ..Default::default() };
+
+    let mut request = consecutive_user_request();
+    assert!(request.validate_with(&relaxed).is_ok());
+
+    // The context carried on the request is used by plain validate()
+    request.validation = relaxed;
+    assert!(request.validate().is_ok());
+
+    // Still requires a leading user message
+    request.messages.insert(0, Message::assistant("Hi".to_string()));
+    assert!(request.validate().unwrap_err().contains("expected 'user'"));
+
+    // Still rejects invalid roles
+    request.messages[0] = Message {
+        role: "system".to_string(),
+        content: "Be brief".to_string(),
+    };
+    assert!(request.validate().is_err());
+}
+
+#[test]
+fn test_anthropic_request_cache_key_requires_determinism() {
+    let request = AnthropicRequest {
+        model: "claude-3-sonnet".to_string(),
+        messages: vec![Message::user("Hello".to_string())],
+        max_tokens: Some(100),
+        temperature: Some(0.0),
+        ..Default::default()
+    };
+    let key = request.cache_key().expect("temperature 0 requests are shareable");
+    assert_eq!(request.clone().cache_key(), Some(key.clone()));
+
+    let mut different = request.clone();
+    different.messages = vec![Message::user("Hi".to_string())];
+    assert_ne!(different.cache_key(), Some(key));
+
+    let mut sampled = request.clone();
+    sampled.temperature = Some(0.7);
+    assert_eq!(sampled.cache_key(), None);
+
+    let mut unset = request.clone();
+    unset.temperature = None;
+    assert_eq!(unset.cache_key(), None);
+
+    let mut streaming = request;
+    streaming.stream = Some(true);
+    assert_eq!(streaming.cache_key(), None);
+}
+
+fn json_mode_request(response_format: serde_json::Value) -> AnthropicRequest {
+    AnthropicRequest {
+        model: "gpt-4".to_string(),
+        messages: vec![Message::user("List three colors".to_string())],
+        max_tokens: Some(100),
+        response_format: Some(response_format),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_response_format_json_object_reaches_openai_and_gemini() {
+    let request = json_mode_request(serde_json::json!({"type": "json_object"}));
+    assert!(request.validate().is_ok());
+
+    let openai_request = OpenAIRequest::from_anthropic(&request).unwrap();
+    let wire = serde_json::to_value(&openai_request).unwrap();
+    assert_eq!(wire["response_format"], serde_json::json!({"type": "json_object"}));
+
+    let gemini_request = GeminiRequest::from_anthropic(&request).unwrap();
+    let wire = serde_json::to_value(&gemini_request.generation_config).unwrap();
+    assert_eq!(wire["responseMimeType"], "application/json");
+    assert!(wire.get("responseSchema").is_none());
+
+    // Never forwarded verbatim in the Anthropic wire format
+    let wire = serde_json::to_value(&request).unwrap();
+    assert!(wire.get("response_format").is_none());
+}
+
+#[test]
+fn test_response_format_json_schema_maps_to_gemini_schema() {
+    let schema = serde_json::json!({
+        "type": "object",
+        "properties": {"colors": {"type": "array", "items": {"type": "string"}}}
+    });
+    let request = json_mode_request(serde_json::json!({
+        "type": "json_schema",
+        "json_schema": {"name": "colors", "schema": schema}
+    }));
+    assert!(request.validate().is_ok());
+
+    let gemini_request = GeminiRequest::from_anthropic(&request).unwrap();
+    assert_eq!(gemini_request.generation_config.response_mime_type.as_deref(), Some("application/json"));
+    assert_eq!(gemini_request.generation_config.response_schema, Some(schema));
+}
+
+#[test]
+fn test_response_format_rejects_unsupported_shapes() {
+    for format in [
+        serde_json::json!("json"),
+        serde_json::json!({"type": "xml"}),
+        serde_json::json!({"type": "json_schema"}),
+        serde_json::json!({"type": "json_schema", "json_schema": {"schema": "not-an-object"}}),
+    ] {
+        let request = json_mode_request(format.clone());
+        assert!(request.validate().is_err(), "expected {} to be rejected", format);
+    }
+
+    let text = json_mode_request(serde_json::json!({"type": "text"}));
+    assert!(text.validate().is_ok());
+    let gemini_request = GeminiRequest::from_anthropic(&text).unwrap();
+    assert!(gemini_request.generation_config.response_mime_type.is_none());
+}
+
+fn prefill_request(model: &str) -> AnthropicRequest {
+    AnthropicRequest {
+        model: model.to_string(),
+        messages: vec![
+            Message::user("Give me a JSON greeting".to_string()),
+            Message::assistant("{\"greeting\":".to_string()),
+        ],
+        max_tokens: Some(100),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_assistant_prefill_detection() {
+    let request = prefill_request("claude-3-haiku-20240307");
+    assert!(request.validate().is_ok());
+    assert_eq!(request.assistant_prefill(), Some("{\"greeting\":"));
+
+    let request = AnthropicRequest {
+        messages: vec![Message::user("Hi".to_string())],
+        ..request
+    };
+    assert_eq!(request.assistant_prefill(), None);
+}
+
+#[test]
+fn test_assistant_prefill_becomes_openai_continuation_instruction() {
+    let request = prefill_request("gpt-4");
+    let openai_request = OpenAIRequest::from_anthropic(&request).unwrap();
+
+    // The trailing assistant turn is replaced by a continuation instruction
+    assert_eq!(openai_request.messages.len(), 2);
+    assert_eq!(openai_request.messages[0].role, "user");
+    let instruction = &openai_request.messages[1];
+    assert_eq!(instruction.role, "system");
+    assert!(instruction.content.contains("Continue the assistant reply"));
+    assert!(instruction.content.ends_with("{\"greeting\":"));
+}
+
+#[test]
+fn test_assistant_prefill_is_last_gemini_model_turn() {
+    let request = prefill_request("gemini-pro");
+    let gemini_request = GeminiRequest::from_anthropic(&request).unwrap();
+
+    let last = gemini_request.contents.last().unwrap();
+    assert_eq!(last.role, "model");
+    assert_eq!(last.parts[0].text, "{\"greeting\":");
+}
+
+#[test]
+fn test_max_input_tokens_replaces_byte_limit() {
+    // ~120KB in total: over the byte-based limit, but only ~30K estimated tokens
+    let request = AnthropicRequest {
+        model: "gpt-4".to_string(),
+        messages: vec![
+            Message::user("a".repeat(60_000)),
+            Message::assistant("b".repeat(60_000)),
+            Message::user("Continue".to_string()),
+        ],
+        max_tokens: Some(100),
+        ..Default::default()
+    };
+    assert!(request.validate().unwrap_err().contains("100KB"));
+
+    let under = ValidationContext { max_input_tokens: Some(40_000), ..Default::default() };
+    assert!(request.validate_with(&under).is_ok());
+
+    let over = ValidationContext { max_input_tokens: Some(20_000), ..Default::default() };
+    let error = request.validate_with(&over).unwrap_err();
+    assert!(error.contains(&format!("({})", request.estimate_input_tokens())));
+    assert!(error.contains("limit of 20000"));
+}
+
+fn tool_request(model: &str) -> AnthropicRequest {
+    AnthropicRequest {
+        model: model.to_string(),
+        messages: vec![Message::user("What's the weather in Paris?".to_string())],
+        max_tokens: Some(100),
+        tools: Some(vec![ToolDefinition {
+            name: "get_weather".to_string(),
+            description: Some("Get the current weather for a city".to_string()),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {"city": {"type": "string"}},
+                "required": ["city"],
+                "additionalProperties": false
+            }),
+        }]),
+        tool_choice: Some(ToolChoice::Tool { name: "get_weather".to_string() }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_tool_validation() {
+    assert!(tool_request("claude-3-sonnet").validate().is_ok());
+
+    let mut request = tool_request("claude-3-sonnet");
+    request.tool_choice = Some(ToolChoice::Tool { name: "get_time".to_string() });
+    assert!(request.validate().unwrap_err().contains("unknown tool 'get_time'"));
+
+    let mut request = tool_request("claude-3-sonnet");
+    request.tools = None;
+    request.tool_choice = Some(ToolChoice::Any);
+    assert!(request.validate().unwrap_err().contains("requires at least one tool"));
+
+    let mut request = tool_request("claude-3-sonnet");
+    request.tools.as_mut().unwrap()[0].input_schema = serde_json::json!("object");
+    assert!(request.validate().unwrap_err().contains("input_schema must be a JSON object"));
+}
+
+#[test]
+fn test_tools_pass_through_anthropic_format() {
+    let wire = serde_json::to_value(tool_request("claude-3-sonnet")).unwrap();
+    assert_eq!(wire["tools"][0]["name"], "get_weather");
+    assert_eq!(wire["tools"][0]["input_schema"]["required"], serde_json::json!(["city"]));
+    assert_eq!(wire["tool_choice"], serde_json::json!({"type": "tool", "name": "get_weather"}));
+
+    let response: AnthropicResponse = serde_json::from_value(serde_json::json!({
+        "id": "msg_123",
+        "model": "claude-3-sonnet",
+        "content": [
+            {"type": "text", "text": "Let me check."},
+            {"type": "tool_use", "id": "toolu_01", "name": "get_weather", "input": {"city": "Paris"}}
+        ],
+        "stop_reason": "tool_use",
+        "usage": {"input_tokens": 20, "output_tokens": 10}
+    }))
+    .unwrap();
+
+    let tool_use = response.tool_uses().next().unwrap();
+    assert_eq!(tool_use.name.as_deref(), Some("get_weather"));
+    assert_eq!(tool_use.input, Some(serde_json::json!({"city": "Paris"})));
+
+    // Tool use blocks are re-serialized without a text field
+    let wire = serde_json::to_value(&response).unwrap();
+    assert_eq!(wire["content"][0], serde_json::json!({"type": "text", "text": "Let me check."}));
+    assert_eq!(
+        wire["content"][1],
+        serde_json::json!({"type": "tool_use", "id": "toolu_01", "name": "get_weather", "input": {"city": "Paris"}})
+    );
+}
+
+#[test]
+fn test_tools_map_to_openai_and_tool_calls_become_tool_use() {
+    let openai_request = OpenAIRequest::from_anthropic(&tool_request("gpt-4")).unwrap();
+    let wire = serde_json::to_value(&openai_request).unwrap();
+    assert_eq!(wire["tools"][0]["type"], "function");
+    assert_eq!(wire["tools"][0]["function"]["name"], "get_weather");
+    assert_eq!(wire["tools"][0]["function"]["description"], "Get the current weather for a city");
+    assert_eq!(wire["tools"][0]["function"]["parameters"]["properties"]["city"]["type"], "string");
+    assert_eq!(
+        wire["tool_choice"],
+        serde_json::json!({"type": "function", "function": {"name": "get_weather"}})
+    );
+
+    let openai_response: OpenAIResponse = serde_json::from_value(serde_json::json!({
+        "id": "chatcmpl-123",
+        "object": "chat.completion",
+        "created": 1677652288,
+        "model": "gpt-4",
+        "choices": [{
+            "index": 0,
+            "message": {
+                "role": "assistant",
+                "content": null,
+                "tool_calls": [{
+                    "id": "call_abc",
+                    "type": "function",
+                    "function": {"name": "get_weather", "arguments": "{\"city\":\"Paris\"}"}
+                }]
+            },
+            "finish_reason": "tool_calls"
+        }],
+        "usage": {"prompt_tokens": 20, "completion_tokens": 10, "total_tokens": 30}
+    }))
+    .unwrap();
+
+    let anthropic_response = openai_response.to_anthropic().unwrap();
+    assert_eq!(anthropic_response.content.len(), 1);
+    assert_eq!(anthropic_response.content[0].type_field, "tool_use");
+    assert_eq!(anthropic_response.content[0].id.as_deref(), Some("call_abc"));
+    assert_eq!(anthropic_response.content[0].name.as_deref(), Some("get_weather"));
+    assert_eq!(anthropic_response.content[0].input, Some(serde_json::json!({"city": "Paris"})));
+    assert_eq!(anthropic_response.stop_reason.as_deref(), Some("tool_use"));
+
+    // And back again for the OpenAI-compatible endpoint
+    let round_trip = anthropic_response.to_openai();
+    let tool_calls = round_trip.choices[0].message.tool_calls.as_ref().unwrap();
+    assert_eq!(tool_calls[0].id, "call_abc");
+    assert_eq!(tool_calls[0].function.arguments, "{\"city\":\"Paris\"}");
+    assert_eq!(round_trip.choices[0].finish_reason.as_deref(), Some("tool_calls"));
+}
+
+#[test]
+fn test_openai_inbound_tool_choice_to_anthropic() {
+    let openai_request: OpenAIRequest = serde_json::from_value(serde_json::json!({
+        "model": "gpt-4",
+        "messages": [{"role": "user", "content": "Weather?"}],
+        "tools": [{"type": "function", "function": {"name": "get_weather"}}],
+        "tool_choice": "required"
+    }))
+    .unwrap();
+
+    let request = openai_request.to_anthropic().unwrap();
+    assert_eq!(request.tool_choice, Some(ToolChoice::Any));
+    let tools = request.tools.unwrap();
+    assert_eq!(tools[0].name, "get_weather");
+    assert_eq!(tools[0].input_schema["type"], "object");
+}
+
+#[test]
+fn test_tools_map_to_gemini_and_function_calls_become_tool_use() {
+    let gemini_request = GeminiRequest::from_anthropic(&tool_request("gemini-pro")).unwrap();
+    let wire = serde_json::to_value(&gemini_request).unwrap();
+    let declaration = &wire["tools"][0]["functionDeclarations"][0];
+    assert_eq!(declaration["name"], "get_weather");
+    assert_eq!(declaration["parameters"]["required"], serde_json::json!(["city"]));
+    // Gemini rejects additionalProperties in function schemas
+    assert!(declaration["parameters"].get("additionalProperties").is_none());
+    assert_eq!(wire["tool_config"]["functionCallingConfig"]["mode"], "ANY");
+    assert_eq!(
+        wire["tool_config"]["functionCallingConfig"]["allowed_function_names"],
+        serde_json::json!(["get_weather"])
+    );
+
+    let gemini_response: GeminiResponse = serde_json::from_value(serde_json::json!({
+        "candidates": [{
+            "content": {
+                "role": "model",
+                "parts": [{"functionCall": {"name": "get_weather", "args": {"city": "Paris"}}}]
+            },
+            "finishReason": "STOP",
+            "index": 0
+        }],
+        "usageMetadata": {"promptTokenCount": 20, "candidatesTokenCount": 5, "totalTokenCount": 25}
+    }))
+    .unwrap();
+
+    let anthropic_response = gemini_response.to_anthropic("gemini-pro").unwrap();
+    assert_eq!(anthropic_response.content.len(), 1);
+    let tool_use = &anthropic_response.content[0];
+    assert!(tool_use.is_tool_use());
+    assert!(tool_use.id.as_deref().unwrap().starts_with("toolu_"));
+    assert_eq!(tool_use.name.as_deref(), Some("get_weather"));
+    assert_eq!(tool_use.input, Some(serde_json::json!({"city": "Paris"})));
+    assert_eq!(anthropic_response.stop_reason.as_deref(), Some("tool_use"));
+}
+
+fn openai_refusal_response(message: serde_json::Value, finish_reason: &str) -> OpenAIResponse {
+    serde_json::from_value(serde_json::json!({
+        "id": "chatcmpl-refusal",
+        "object": "chat.completion",
+        "created": 1677652288,
+        "model": "gpt-4o",
+        "choices": [{"index": 0, "message": message, "finish_reason": finish_reason}],
+        "usage": {"prompt_tokens": 12, "completion_tokens": 6, "total_tokens": 18}
+    }))
+    .unwrap()
+}
+
+#[test]
+fn test_openai_refusal_field_is_flagged() {
+    let openai_response = openai_refusal_response(
+        serde_json::json!({"role": "assistant", "content": null, "refusal": "I can't help with that."}),
+        "stop",
+    );
+    assert!(!openai_response.has_issues());
+
+    let response = openai_response.to_anthropic().unwrap();
+    assert!(response.refused);
+    assert_eq!(response.refusal_reason.as_deref(), Some("I can't help with that."));
+    assert!(response.content.is_empty());
+
+    // Round-trips onto OpenAI's refusal field
+    let round_trip = response.to_openai();
+    assert_eq!(round_trip.choices[0].message.refusal.as_deref(), Some("I can't help with that."));
+}
+
+#[test]
+fn test_openai_content_filter_is_flagged() {
+    let openai_response = openai_refusal_response(
+        serde_json::json!({"role": "assistant", "content": "Partial"}),
+        "content_filter",
+    );
+
+    let response = openai_response.to_anthropic().unwrap();
+    assert!(response.refused);
+    assert_eq!(response.stop_reason.as_deref(), Some("content_filter"));
+    assert_eq!(response.refusal_reason.as_deref(), Some("Response withheld by content filter"));
+    assert_eq!(response.content[0].text, "Partial");
+
+    // Normal completions carry no refusal fields on the wire
+    let normal = openai_refusal_response(serde_json::json!({"role": "assistant", "content": "Hi"}), "stop")
+        .to_anthropic()
+        .unwrap();
+    assert!(!normal.refused);
+    let wire = serde_json::to_value(&normal).unwrap();
+    assert!(wire.get("refused").is_none());
+    assert!(wire.get("refusal_reason").is_none());
+}
+
+#[test]
+fn test_lenient_validation_clamps_sampling_params() {
+    let mut request = AnthropicRequest {
+        model: "claude-3-sonnet".to_string(),
+        messages: vec![Message::user("Hello".to_string())],
+        temperature: Some(3.5),
+        top_p: Some(-0.2),
+        ..Default::default()
+    };
+
+    // Strict mode leaves the values alone and validation rejects them
+    request.clamp_sampling_params();
+    assert_eq!(request.temperature, Some(3.5));
+    assert!(request.validate().unwrap_err().contains("temperature must be between 0.0 and 2.0"));
+
+    request.validation = ValidationContext { mode: ValidationMode::Lenient, ..Default::default() };
+    request.clamp_sampling_params();
+    assert_eq!(request.temperature, Some(2.0));
+    assert_eq!(request.top_p, Some(0.0));
+    assert!(request.validate().is_ok());
+
+    // Values that cannot be clamped are still rejected
+    request.temperature = Some(f32::NAN);
+    request.clamp_sampling_params();
+    assert!(request.validate().unwrap_err().contains("temperature must be a valid number"));
+}
+
+#[test]
+fn test_inject_system_wraps_or_creates_system_prompt() {
+    let mut request = AnthropicRequest {
+        model: "claude-3-sonnet".to_string(),
+        messages: vec![Message::user("Hello".to_string())],
+        ..Default::default()
+    };
+
+    // Nothing configured leaves the request untouched
+    request.inject_system(None, Some(""));
+    assert_eq!(request.system, None);
+
+    // A request without a system prompt gets one made of the injected text
+    request.inject_system(Some("Prefix."), None);
+    assert_eq!(request.system.as_deref(), Some("Prefix."));
+
+    request.system = Some("Be brief.".to_string());
+    request.inject_system(Some("Prefix."), Some("Suffix."));
+    assert_eq!(request.system.as_deref(), Some("Prefix.\n\nBe brief.\n\nSuffix."));
+}
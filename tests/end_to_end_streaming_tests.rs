@@ -246,7 +246,7 @@ async fn test_streaming_error_scenarios() {
 
     assert_eq!(response.status(), StatusCode::NOT_FOUND);
 
-    // Test malformed request (JSON parsing fails, Axum returns 422)
+    // Test malformed request (rejected by schema pre-validation)
     let app = create_app(app_state.clone());
     let request_body = json!({
         "model": "gpt-4",
@@ -257,9 +257,9 @@ async fn test_streaming_error_scenarios() {
     let request = TestUtils::create_json_request("POST", "/v1/messages", request_body);
     let response = app.oneshot(request).await.unwrap();
 
-    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 
-    // Test missing required fields (JSON parsing fails, Axum returns 422)
+    // Test missing required fields (rejected by schema pre-validation)
     let app = create_app(app_state.clone());
     let request_body = json!({
         "messages": [{"role": "user", "content": "Test"}],
@@ -270,7 +270,7 @@ async fn test_streaming_error_scenarios() {
     let request = TestUtils::create_json_request("POST", "/v1/messages", request_body);
     let response = app.oneshot(request).await.unwrap();
 
-    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 }
 
 /// Test concurrent streaming requests
@@ -25,6 +25,9 @@ fn test_error_display() {
     let error = AppError::ProviderError {
         status: 500,
         message: "OpenAI API error".to_string(),
+        error_type: None,
+        error_code: None,
+        retry_after: None,
     };
     assert_eq!(error.to_string(), "Provider error: OpenAI API error");
 
@@ -104,6 +107,9 @@ fn test_provider_error_with_status_code() {
     let error = AppError::ProviderError {
         status: 429,
         message: "Rate limit exceeded".to_string(),
+        error_type: None,
+        error_code: None,
+        retry_after: None,
     };
     let response = error.into_response();
     assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
@@ -114,6 +120,9 @@ fn test_provider_error_with_invalid_status_code() {
     let error = AppError::ProviderError {
         status: 999, // Valid but non-standard HTTP status code
         message: "Unknown error".to_string(),
+        error_type: None,
+        error_code: None,
+        retry_after: None,
     };
     let response = error.into_response();
     // 999 is actually a valid HTTP status code, so it should be preserved
@@ -136,6 +145,7 @@ fn test_error_types_mapping() {
     let test_cases = vec![
         (AppError::BadRequest("test".to_string()), "invalid_request_error"),
         (AppError::ProviderNotFound("test".to_string()), "not_found_error"),
+        (AppError::NotFound("test".to_string()), "not_found_error"),
         (AppError::ValidationError("test".to_string()), "validation_error"),
         (AppError::AuthenticationError("test".to_string()), "authentication_error"),
         (AppError::AuthorizationError("test".to_string()), "authorization_error"),
@@ -274,6 +284,9 @@ fn test_provider_error_includes_provider_code() {
     let error = AppError::ProviderError {
         status: 429,
         message: "Rate limit exceeded".to_string(),
+        error_type: None,
+        error_code: None,
+        retry_after: None,
     };
     let response = error.into_response();
     
@@ -287,7 +300,8 @@ fn test_comprehensive_error_type_mapping() {
     let error_type_mappings = vec![
         (AppError::BadRequest("test".to_string()), "invalid_request_error"),
         (AppError::ProviderNotFound("test".to_string()), "not_found_error"),
-        (AppError::ProviderError { status: 500, message: "test".to_string() }, "provider_error"),
+        (AppError::NotFound("test".to_string()), "not_found_error"),
+        (AppError::ProviderError { status: 500, message: "test".to_string(), error_type: None, error_code: None, retry_after: None }, "provider_error"),
         (AppError::InternalServerError("test".to_string()), "internal_server_error"),
         (AppError::ConfigError("test".to_string()), "configuration_error"),
         (AppError::ValidationError("test".to_string()), "validation_error"),
@@ -343,6 +357,9 @@ fn test_error_debug_formatting() {
     let error = AppError::ProviderError {
         status: 500,
         message: "API Error".to_string(),
+        error_type: None,
+        error_code: None,
+        retry_after: None,
     };
     
     let debug_str = format!("{:?}", error);
@@ -357,7 +374,8 @@ fn test_all_error_variants_coverage() {
     let errors = vec![
         AppError::BadRequest("test".to_string()),
         AppError::ProviderNotFound("test".to_string()),
-        AppError::ProviderError { status: 500, message: "test".to_string() },
+        AppError::NotFound("test".to_string()),
+        AppError::ProviderError { status: 500, message: "test".to_string(), error_type: None, error_code: None, retry_after: None },
         AppError::InternalServerError("test".to_string()),
         AppError::ConfigError("test".to_string()),
         AppError::ValidationError("test".to_string()),
@@ -371,6 +389,7 @@ fn test_all_error_variants_coverage() {
         AppError::QuotaExceeded("test".to_string()),
         AppError::NetworkError("test".to_string()),
         AppError::SerializationError("test".to_string()),
+        AppError::InvalidFields(vec![FieldError::new("model", "missing required field")]),
     ];
 
     for error in errors {
@@ -379,3 +398,48 @@ fn test_all_error_variants_coverage() {
         assert!(response.status().as_u16() >= 400);
     }
 }
+
+#[tokio::test]
+async fn test_provider_error_envelope_includes_upstream_type_and_code() {
+    let error = AppError::provider_error_with_details(
+        400,
+        "OpenAI API: Unsupported parameter",
+        Some("invalid_request_error".to_string()),
+        Some("unsupported_parameter".to_string()),
+    );
+    let response = error.into_response();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["error"]["type"], "provider_error");
+    assert_eq!(json["error"]["provider_error_type"], "invalid_request_error");
+    assert_eq!(json["error"]["provider_error_code"], "unsupported_parameter");
+
+    // Without upstream details the fields are omitted
+    let response = AppError::provider_error(502, "upstream failed").into_response();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(json["error"].get("provider_error_type").is_none());
+    assert!(json["error"].get("provider_error_code").is_none());
+}
+
+#[tokio::test]
+async fn test_invalid_fields_envelope_lists_fields() {
+    let error = AppError::InvalidFields(vec![
+        FieldError::new("model", "missing required field"),
+        FieldError::new("max_tokens", "expected non-negative integer"),
+    ]);
+    assert_eq!(
+        error.to_string(),
+        "Invalid request: model: missing required field; max_tokens: expected non-negative integer"
+    );
+
+    let response = error.into_response();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["error"]["type"], "invalid_request_error");
+    assert_eq!(json["error"]["fields"][1]["field"], "max_tokens");
+    assert_eq!(json["error"]["fields"][1]["problem"], "expected non-negative integer");
+}
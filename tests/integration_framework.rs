@@ -4,7 +4,7 @@
 /// including mock server setup, streaming response validation, and multi-provider testing.
 use ai_proxy::{
     config::{
-        Config, LoggingConfig, PerformanceConfig, ProviderDetail, SecurityConfig, ServerConfig,
+        Config, LoggingConfig, PerformanceConfig, ProviderDetail, SecurityConfig, ServerConfig, SharedConfig,
     },
     providers::ProviderRegistry,
     providers::anthropic::{AnthropicRequest, Message},
@@ -97,6 +97,7 @@ impl IntegrationTestFramework {
                     max_retries: 3,
                     enabled: true,
                     rate_limit: None,
+                    ..Default::default()
                 },
             );
         }
@@ -118,6 +119,7 @@ impl IntegrationTestFramework {
                     max_retries: 3,
                     enabled: true,
                     rate_limit: None,
+                    ..Default::default()
                 },
             );
         }
@@ -139,6 +141,7 @@ impl IntegrationTestFramework {
                     max_retries: 3,
                     enabled: true,
                     rate_limit: None,
+                    ..Default::default()
                 },
             );
         }
@@ -149,6 +152,7 @@ impl IntegrationTestFramework {
                 port: 0, // Use random port for tests
                 request_timeout_seconds: 30,
                 max_request_size_bytes: 1024 * 1024,
+                ..Default::default()
             },
             providers,
             logging: LoggingConfig {
@@ -156,9 +160,12 @@ impl IntegrationTestFramework {
                 format: "json".to_string(),
                 log_requests: true,
                 log_responses: false,
+                log_sample_rate: 1.0,
+                ..Default::default()
             },
             security: SecurityConfig::default(),
             performance: PerformanceConfig::default(),
+            ..Default::default()
         }
     }
 
@@ -172,10 +179,16 @@ impl IntegrationTestFramework {
         let metrics = Arc::new(ai_proxy::metrics::MetricsCollector::new());
 
         AppState {
-            config: Arc::new(config),
+            config: Arc::new(SharedConfig::new(config)),
             http_client,
             provider_registry,
             metrics,
+            health_cache: Default::default(),
+            coalescer: Default::default(),
+            idempotency: Default::default(),
+            active_streams: Default::default(),
+            redactor: Default::default(),
+            transforms: Vec::new(),
         }
     }
 
@@ -588,10 +601,11 @@ impl TestUtils {
         AnthropicRequest {
             model: model.to_string(),
             messages: vec![Message::user(content.to_string())],
-            max_tokens: 100,
+            max_tokens: Some(100),
             stream: Some(stream),
             temperature: Some(0.7),
             top_p: Some(0.9),
+            ..Default::default()
         }
     }
 
@@ -642,10 +656,11 @@ impl TestUtils {
         AnthropicRequest {
             model: model.to_string(),
             messages: vec![Message::user(content.to_string())],
-            max_tokens,
+            max_tokens: Some(max_tokens),
             stream: Some(stream),
             temperature,
             top_p,
+            ..Default::default()
         }
     }
 
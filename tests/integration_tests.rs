@@ -1,6 +1,6 @@
 use ai_proxy::{
-    config::{Config, ServerConfig, ProviderDetail, LoggingConfig, SecurityConfig, PerformanceConfig},
-    server::{create_app, AppState},
+    config::{Config, ServerConfig, ProviderDetail, LoggingConfig, SecurityConfig, PerformanceConfig, RedactionRoute, SharedConfig},
+    server::{create_app, preflight_health_check, serve_with_graceful_shutdown, start_server, AppState},
     providers::{ProviderRegistry},
     providers::anthropic::{AnthropicRequest, Message},
 };
@@ -15,7 +15,7 @@ use std::{collections::HashMap, sync::Arc, time::Duration};
 use tokio::sync::RwLock;
 use tower::ServiceExt;
 use wiremock::{
-    matchers::{method, path, header as wiremock_header, query_param},
+    matchers::{body_partial_json, method, path, header as wiremock_header, query_param},
     Mock, MockServer, ResponseTemplate,
 };
 
@@ -170,7 +170,7 @@ mod integration_helpers {
     pub async fn setup_anthropic_mocks(server: &MockServer) {
         // Streaming chat completion - match requests with stream=true (mount first for priority)
         Mock::given(method("POST"))
-            .and(path("/v1messages"))
+            .and(path("/v1/messages"))
             .and(|req: &wiremock::Request| {
                 // Check if the request body contains "stream":true
                 if let Ok(body) = std::str::from_utf8(&req.body) {
@@ -199,9 +199,9 @@ mod integration_helpers {
             .mount(server)
             .await;
 
-        // Standard chat completion - correct path /v1messages (no slash between v1 and messages)
+        // Standard chat completion
         Mock::given(method("POST"))
-            .and(path("/v1messages"))
+            .and(path("/v1/messages"))
             .respond_with(|req: &wiremock::Request| {
                 // Extract model from request body
                 let model = if let Ok(body) = std::str::from_utf8(&req.body) {
@@ -437,6 +437,7 @@ mod integration_helpers {
                     max_retries: 3,
                     enabled: true,
                     rate_limit: None,
+                    ..Default::default()
                 },
             );
         }
@@ -453,6 +454,7 @@ mod integration_helpers {
                     max_retries: 3,
                     enabled: true,
                     rate_limit: None,
+                    ..Default::default()
                 },
             );
         }
@@ -469,6 +471,7 @@ mod integration_helpers {
                     max_retries: 3,
                     enabled: true,
                     rate_limit: None,
+                    ..Default::default()
                 },
             );
         }
@@ -479,6 +482,7 @@ mod integration_helpers {
                 port: 0, // Use random port for tests
                 request_timeout_seconds: 30,
                 max_request_size_bytes: 1024 * 1024,
+                ..Default::default()
             },
             providers,
             logging: LoggingConfig {
@@ -486,9 +490,12 @@ mod integration_helpers {
                 format: "json".to_string(),
                 log_requests: true,
                 log_responses: false,
+                log_sample_rate: 1.0,
+                ..Default::default()
             },
             security: SecurityConfig::default(),
             performance: PerformanceConfig::default(),
+            ..Default::default()
         }
     }
 
@@ -497,12 +504,19 @@ mod integration_helpers {
         let http_client = Client::new();
         let provider_registry = Arc::new(RwLock::new(ProviderRegistry::new(&config, http_client.clone()).unwrap()));
         let metrics = Arc::new(ai_proxy::metrics::MetricsCollector::new());
+        let redactor = Arc::new(ai_proxy::transforms::RegexRedactor::from_config(&config.redaction).unwrap());
 
         AppState {
-            config: Arc::new(config),
+            config: Arc::new(SharedConfig::new(config)),
             http_client,
             provider_registry,
             metrics,
+            health_cache: Default::default(),
+            coalescer: Default::default(),
+            idempotency: Default::default(),
+            active_streams: Default::default(),
+            redactor,
+            transforms: Vec::new(),
         }
     }
 
@@ -514,11 +528,13 @@ mod integration_helpers {
                 port: 0,
                 request_timeout_seconds: 30,
                 max_request_size_bytes: 1024 * 1024,
+                ..Default::default()
             },
             providers: HashMap::new(), // Empty providers for error testing
             logging: LoggingConfig::default(),
             security: SecurityConfig::default(),
             performance: PerformanceConfig::default(),
+            ..Default::default()
         };
 
         let http_client = Client::new();
@@ -531,10 +547,16 @@ mod integration_helpers {
         ));
 
         AppState {
-            config: Arc::new(config),
+            config: Arc::new(SharedConfig::new(config)),
             http_client,
             provider_registry,
             metrics,
+            health_cache: Default::default(),
+            coalescer: Default::default(),
+            idempotency: Default::default(),
+            active_streams: Default::default(),
+            redactor: Default::default(),
+            transforms: Vec::new(),
         }
     }
 
@@ -545,10 +567,16 @@ mod integration_helpers {
         let metrics = Arc::new(ai_proxy::metrics::MetricsCollector::new());
 
         AppState {
-            config: Arc::new(config.clone()),
+            config: Arc::new(SharedConfig::new(config.clone())),
             http_client,
             provider_registry,
             metrics,
+            health_cache: Default::default(),
+            coalescer: Default::default(),
+            idempotency: Default::default(),
+            active_streams: Default::default(),
+            redactor: Default::default(),
+            transforms: Vec::new(),
         }
     }
 
@@ -557,10 +585,11 @@ mod integration_helpers {
         AnthropicRequest {
             model: model.to_string(),
             messages: vec![Message::user(content.to_string())],
-            max_tokens: 100,
+            max_tokens: Some(100),
             stream: Some(false),
             temperature: Some(0.7),
             top_p: Some(0.9),
+            ..Default::default()
         }
     }
 
@@ -569,13 +598,23 @@ mod integration_helpers {
         AnthropicRequest {
             model: model.to_string(),
             messages: vec![Message::user(content.to_string())],
-            max_tokens: 100,
+            max_tokens: Some(100),
             stream: Some(true),
             temperature: Some(0.7),
             top_p: Some(0.9),
+            ..Default::default()
         }
     }
 
+    /// `GET /v1/usage` authorized with the admin key tests configure
+    pub fn usage_request() -> Request<Body> {
+        Request::builder()
+            .uri("/v1/usage")
+            .header("x-api-key", "admin-key-1234567890")
+            .body(Body::empty())
+            .unwrap()
+    }
+
     /// Parse response body as JSON
     pub async fn parse_response_json(response: Response<Body>) -> Value {
         let body_bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
@@ -595,9 +634,9 @@ async fn test_anthropic_chat_completion_integration() {
     // Setup mock Anthropic server
     let mock_server = MockServer::start().await;
 
-    // Correct path pattern - /v1messages (no slash between v1 and messages)
+    // Anthropic messages endpoint
     Mock::given(method("POST"))
-        .and(path("/v1messages"))
+        .and(path("/v1/messages"))
         .respond_with(ResponseTemplate::new(200).set_body_json(json!({
             "id": "msg_test123",
             "type": "message",
@@ -749,6 +788,149 @@ async fn test_chat_completion_integration() {
     assert_eq!(response_json["usage"]["output_tokens"], 25);
 }
 
+/// Test the OpenAI-compatible chat completions route against a non-OpenAI backend
+#[tokio::test]
+async fn test_openai_compatible_chat_completion_integration() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_openai_compat",
+            "type": "message",
+            "role": "assistant",
+            "content": [{
+                "type": "text",
+                "text": "Hello from Claude"
+            }],
+            "model": "claude-3-sonnet",
+            "stop_reason": "end_turn",
+            "usage": {
+                "input_tokens": 8,
+                "output_tokens": 4
+            }
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let config = integration_helpers::create_test_config(mock_servers);
+
+    let app_state = integration_helpers::create_test_app_state(config).await;
+    let app = create_app(app_state);
+
+    let request_body = json!({
+        "model": "claude-3-sonnet",
+        "messages": [
+            {"role": "system", "content": "Be brief"},
+            {"role": "user", "content": "Hello"}
+        ],
+        "max_tokens": 100
+    });
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/chat/completions")
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response_json = integration_helpers::parse_response_json(response).await;
+    assert_eq!(response_json["object"], "chat.completion");
+    assert_eq!(response_json["model"], "claude-3-sonnet");
+    assert_eq!(response_json["choices"][0]["message"]["role"], "assistant");
+    assert_eq!(response_json["choices"][0]["message"]["content"], "Hello from Claude");
+    assert_eq!(response_json["choices"][0]["finish_reason"], "stop");
+    assert_eq!(response_json["usage"]["total_tokens"], 12);
+}
+
+/// Test that the x-ai-proxy-timeout header shortens the upstream timeout for a single request
+#[tokio::test]
+async fn test_timeout_header_override_integration() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200)
+            .set_delay(Duration::from_millis(1500))
+            .set_body_json(json!({
+                "id": "chatcmpl-slow",
+                "object": "chat.completion",
+                "created": 1234567890,
+                "model": "gpt-4",
+                "choices": [{
+                    "index": 0,
+                    "message": {
+                        "role": "assistant",
+                        "content": "Eventually"
+                    },
+                    "finish_reason": "stop"
+                }],
+                "usage": {
+                    "prompt_tokens": 5,
+                    "completion_tokens": 1,
+                    "total_tokens": 6
+                }
+            })))
+        .mount(&mock_server)
+        .await;
+
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("openai".to_string(), mock_server.uri());
+    let config = integration_helpers::create_test_config(mock_servers);
+
+    let app_state = integration_helpers::create_test_app_state(config).await;
+    let app = create_app(app_state);
+
+    let request_body = serde_json::to_string(&json!({
+        "model": "gpt-4",
+        "messages": [
+            {"role": "user", "content": "Hello"}
+        ],
+        "max_tokens": 100
+    })).unwrap();
+
+    // A 1 second override is shorter than the mock's delay
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/messages")
+        .header("content-type", "application/json")
+        .header("x-ai-proxy-timeout", "1")
+        .body(Body::from(request_body.clone()))
+        .unwrap();
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+
+    // The provider's default timeout is long enough for the same request
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/messages")
+        .header("content-type", "application/json")
+        .body(Body::from(request_body.clone()))
+        .unwrap();
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Non-numeric values are rejected
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/messages")
+        .header("content-type", "application/json")
+        .header("x-ai-proxy-timeout", "soon")
+        .body(Body::from(request_body))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
 /// Test streaming chat completion functionality
 #[tokio::test]
 async fn test_streaming_chat_completion_integration() {
@@ -940,1381 +1122,4427 @@ async fn test_model_listing_integration() {
     assert!(model_ids.contains(&"claude-3-sonnet"));
 }
 
-/// Test health check endpoints
+/// Test that a model offered by two providers is listed once, from the higher-priority provider
 #[tokio::test]
-async fn test_health_check_integration() {
-    // Setup mock servers
+async fn test_model_listing_dedupes_by_provider_priority_integration() {
     let openai_server = MockServer::start().await;
-    
-    // Mock health check endpoint
+    integration_helpers::setup_openai_mocks(&openai_server).await;
+    let backup_server = MockServer::start().await;
     Mock::given(method("GET"))
         .and(path("/v1/models"))
         .respond_with(ResponseTemplate::new(200).set_body_json(json!({
             "object": "list",
-            "data": []
+            "data": [{"id": "gpt-4", "object": "model", "created": 1234567890, "owned_by": "backup-org"}]
         })))
-        .mount(&openai_server)
+        .mount(&backup_server)
         .await;
 
-    // Create test configuration
-    let mut mock_servers = HashMap::new();
-    mock_servers.insert("openai".to_string(), openai_server.uri());
-    let config = integration_helpers::create_test_config(mock_servers);
-    
-    // Create app
-    let app_state = integration_helpers::create_test_app_state(config).await;
-    let app = create_app(app_state);
-
-    // Test basic health endpoint
-    let request = Request::builder()
-        .method("GET")
-        .uri("/health")
-        .body(Body::empty())
-        .unwrap();
-
-    let response = app.oneshot(request).await.unwrap();
-    assert_eq!(response.status(), StatusCode::OK);
-    
-    let response_json = integration_helpers::parse_response_json(response).await;
-    assert_eq!(response_json["status"], "healthy");
-
-    // Test providers health endpoint with new app state
-    let mut mock_servers2 = HashMap::new();
-    mock_servers2.insert("openai".to_string(), openai_server.uri());
-    let config = integration_helpers::create_test_config(mock_servers2);
-    let app_state2 = integration_helpers::create_test_app_state(config).await;
-    let app = create_app(app_state2);
-    let request = Request::builder()
-        .method("GET")
-        .uri("/health/providers")
-        .body(Body::empty())
-        .unwrap();
-
-    let response = app.oneshot(request).await.unwrap();
-    assert_eq!(response.status(), StatusCode::OK);
+    for (backup_priority, expected_provider, expected_owner) in
+        [(10, "openai-backup", "backup-org"), (-1, "openai", "openai")]
+    {
+        let mut mock_servers = HashMap::new();
+        mock_servers.insert("openai".to_string(), openai_server.uri());
+        let mut config = integration_helpers::create_test_config(mock_servers);
+        let mut backup = config.providers["openai"].clone();
+        backup.api_base = format!("{}/v1/", backup_server.uri());
+        backup.priority = backup_priority;
+        config.providers.insert("openai-backup".to_string(), backup);
+
+        let app = create_app(integration_helpers::create_test_app_state(config).await);
+        let request = Request::builder().uri("/v1/models").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
 
-    let response_json = integration_helpers::parse_response_json(response).await;
-    // The providers field should be an object (HashMap), not an array
-    assert!(response_json["providers"].is_object());
+        let response_json = integration_helpers::parse_response_json(response).await;
+        let models = response_json["data"].as_array().unwrap();
+        let model_ids: Vec<&str> = models.iter().map(|m| m["id"].as_str().unwrap()).collect();
+        assert_eq!(model_ids, ["gpt-3.5-turbo", "gpt-4"]);
+        assert_eq!(models[0]["provider"], "openai");
+        assert_eq!(models[1]["provider"], expected_provider);
+        assert_eq!(models[1]["owned_by"], expected_owner);
+    }
 }
 
-/// Test request validation and error responses
-#[tokio::test]
-async fn test_request_validation_integration() {
-    // Create minimal config for validation tests
-    let app_state = integration_helpers::create_test_app_state_empty().await;
-    let app = create_app(app_state);
+/// Send one chat request through a proxy configured with sampling defaults
+///
+/// The upstream mock only answers when the forwarded body carries `expected`.
+async fn chat_with_sampling_defaults(request_body: Value, expected: Value) -> StatusCode {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(body_partial_json(expected))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_defaults",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Hi"}],
+            "model": "claude-3-sonnet",
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 5, "output_tokens": 1}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
 
-    // Test invalid JSON
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let mut config = integration_helpers::create_test_config(mock_servers);
+    config.performance.default_temperature = Some(0.0);
+    config.performance.default_top_p = Some(0.5);
+
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
     let request = Request::builder()
         .method("POST")
         .uri("/v1/messages")
         .header("content-type", "application/json")
-        .body(Body::from("invalid json"))
+        .body(Body::from(request_body.to_string()))
         .unwrap();
+    app.oneshot(request).await.unwrap().status()
+}
 
-    let response = app.oneshot(request).await.unwrap();
-    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+/// Test that omitted temperature/top_p are filled from the configured defaults
+#[tokio::test]
+async fn test_sampling_defaults_fill_omitted_values_integration() {
+    let status = chat_with_sampling_defaults(
+        json!({
+            "model": "claude-3-sonnet",
+            "messages": [{"role": "user", "content": "Hello"}],
+            "max_tokens": 100
+        }),
+        json!({"temperature": 0.0, "top_p": 0.5}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
 
-    // Test missing required fields
-    let app_state = integration_helpers::create_test_app_state_empty().await;
-    let app = create_app(app_state); // Create new app instance
-    let request_body = json!({
-        "messages": [
-            {"role": "user", "content": "Hello"}
-        ]
-        // Missing model and max_tokens
-    });
+/// Test that client-provided temperature/top_p win over the configured defaults
+#[tokio::test]
+async fn test_sampling_defaults_do_not_override_client_values_integration() {
+    let status = chat_with_sampling_defaults(
+        json!({
+            "model": "claude-3-sonnet",
+            "messages": [{"role": "user", "content": "Hello"}],
+            "max_tokens": 100,
+            "temperature": 1.5,
+            "top_p": 0.25
+        }),
+        json!({"temperature": 1.5, "top_p": 0.25}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
 
-    let request = Request::builder()
-        .method("POST")
-        .uri("/v1/messages")
-        .header("content-type", "application/json")
-        .body(Body::from(serde_json::to_string(&request_body).unwrap()))
-        .unwrap();
+/// Test that lenient validation clamps an out-of-range temperature while strict mode rejects it
+#[tokio::test]
+async fn test_validation_mode_out_of_range_temperature_integration() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(body_partial_json(json!({"temperature": 2.0})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_clamped",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Hi"}],
+            "model": "claude-3-sonnet",
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 5, "output_tokens": 1}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
 
-    let response = app.oneshot(request).await.unwrap();
-    // Axum returns 422 for JSON parsing errors, not 400
-    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let send = |mode: ai_proxy::config::ValidationMode| {
+        let mut mock_servers = HashMap::new();
+        mock_servers.insert("anthropic".to_string(), mock_server.uri());
+        let mut config = integration_helpers::create_test_config(mock_servers);
+        config.security.validation_mode = mode;
+        async move {
+            let app = create_app(integration_helpers::create_test_app_state(config).await);
+            let body = json!({
+                "model": "claude-3-sonnet",
+                "messages": [{"role": "user", "content": "Hello"}],
+                "max_tokens": 100,
+                "temperature": 3.5
+            });
+            let request = Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            app.oneshot(request).await.unwrap()
+        }
+    };
 
-    // Test invalid model
-    let app_state = integration_helpers::create_test_app_state_empty().await;
-    let app = create_app(app_state); // Create new app instance
-    let request_body = json!({
-        "model": "nonexistent-model",
-        "messages": [
-            {"role": "user", "content": "Hello"}
-        ],
-        "max_tokens": 100
-    });
+    let response = send(ai_proxy::config::ValidationMode::Strict).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = integration_helpers::parse_response_string(response).await;
+    assert!(body.contains("temperature must be between 0.0 and 2.0"), "{}", body);
 
-    let request = Request::builder()
-        .method("POST")
-        .uri("/v1/messages")
-        .header("content-type", "application/json")
-        .body(Body::from(serde_json::to_string(&request_body).unwrap()))
-        .unwrap();
+    // Lenient mode forwards the clamped value (the mock only answers temperature 2.0)
+    let response = send(ai_proxy::config::ValidationMode::Lenient).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
 
-    let response = app.oneshot(request).await.unwrap();
-    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+/// Send chat requests carrying the given idempotency keys and return the responses
+///
+/// The upstream mock must be called exactly `upstream_calls` times.
+async fn chat_with_idempotency_keys(keys: &[&str], upstream_calls: u64) -> Vec<Response<Body>> {
+    let requests: Vec<_> = keys.iter().map(|key| (Some("sk-tenant-a-1234567890"), *key)).collect();
+    chat_with_idempotency_keys_from(&requests, upstream_calls).await
 }
 
-/// Test concurrent request handling
-#[tokio::test]
-async fn test_concurrent_requests_integration() {
-    // Setup mock server
+/// Send chat requests as `(API key, idempotency key)` pairs and return the responses
+///
+/// The upstream mock must be called exactly `upstream_calls` times.
+async fn chat_with_idempotency_keys_from(requests: &[(Option<&str>, &str)], upstream_calls: u64) -> Vec<Response<Body>> {
     let mock_server = MockServer::start().await;
-    
     Mock::given(method("POST"))
-        .and(path("/v1/chat/completions"))
-        .respond_with(ResponseTemplate::new(200)
-            .set_delay(Duration::from_millis(100)) // Add small delay
-            .set_body_json(json!({
-                "id": "chatcmpl-concurrent",
-                "object": "chat.completion",
-                "created": 1234567890,
-                "model": "gpt-4",
-                "choices": [{
-                    "index": 0,
-                    "message": {
-                        "role": "assistant",
-                        "content": "Concurrent response"
-                    },
-                    "finish_reason": "stop"
-                }],
-                "usage": {
-                    "prompt_tokens": 5,
-                    "completion_tokens": 10,
-                    "total_tokens": 15
-                }
-            })))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_idempotent",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Charged once"}],
+            "model": "claude-3-sonnet",
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 5, "output_tokens": 2}
+        })))
+        .expect(upstream_calls)
         .mount(&mock_server)
         .await;
 
-    // Create test configuration
     let mut mock_servers = HashMap::new();
-    mock_servers.insert("openai".to_string(), mock_server.uri());
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
     let config = integration_helpers::create_test_config(mock_servers);
-    
-    // Create app
-    let app_state = integration_helpers::create_test_app_state(config).await;
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
 
-    // Create multiple concurrent requests
-    let mut handles = Vec::new();
-    
-    for i in 0..5 {
-        let app = create_app(app_state.clone());
-        let handle = tokio::spawn(async move {
-            let request_body = json!({
-                "model": "gpt-4",
-                "messages": [
-                    {"role": "user", "content": format!("Request {}", i)}
-                ],
-                "max_tokens": 50
-            });
+    let mut responses = Vec::new();
+    for (api_key, key) in requests {
+        let mut request = Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json")
+            .header("idempotency-key", *key);
+        if let Some(api_key) = api_key {
+            request = request.header("x-api-key", *api_key);
+        }
+        let request = request
+            .body(Body::from(json!({
+                "model": "claude-3-sonnet",
+                "messages": [{"role": "user", "content": "Hello"}],
+                "max_tokens": 100,
+                "temperature": 0.7
+            }).to_string()))
+            .unwrap();
+        responses.push(app.clone().oneshot(request).await.unwrap());
+    }
+    responses
+}
 
-            let request = Request::builder()
-                .method("POST")
-                .uri("/v1/messages")
-                .header("content-type", "application/json")
-                .body(Body::from(serde_json::to_string(&request_body).unwrap()))
-                .unwrap();
+/// Test that a repeated idempotency key replays the stored response without a second upstream call
+#[tokio::test]
+async fn test_idempotency_key_replays_response_integration() {
+    let mut responses = chat_with_idempotency_keys(&["retry-123", "retry-123"], 1).await;
+
+    let replay = responses.pop().unwrap();
+    let first = responses.pop().unwrap();
+    assert_eq!(first.status(), StatusCode::OK);
+    assert!(first.headers().get("x-ai-proxy-idempotent-replay").is_none());
+    assert_eq!(replay.status(), StatusCode::OK);
+    assert_eq!(replay.headers()["x-ai-proxy-idempotent-replay"], "true");
+
+    let first_json = integration_helpers::parse_response_json(first).await;
+    let replay_json = integration_helpers::parse_response_json(replay).await;
+    assert_eq!(replay_json["id"], first_json["id"]);
+    assert_eq!(replay_json["content"][0]["text"], "Charged once");
+}
 
-            app.oneshot(request).await.unwrap()
-        });
-        
-        handles.push(handle);
-    }
+/// Test that different idempotency keys are dispatched independently
+#[tokio::test]
+async fn test_idempotency_different_keys_dispatch_independently_integration() {
+    let responses = chat_with_idempotency_keys(&["key-a", "key-b"], 2).await;
 
-    // Wait for all requests to complete
-    let responses = futures::future::join_all(handles).await;
-    
-    // Verify all requests succeeded
     for response in responses {
-        let response = response.unwrap();
         assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get("x-ai-proxy-idempotent-replay").is_none());
     }
 }
 
-/// Test timeout handling
+/// Test that API keys whose masked IDs collide never share stored idempotent responses
 #[tokio::test]
-async fn test_timeout_handling_integration() {
-    // Setup mock server with long delay
+async fn test_idempotency_scoped_to_full_api_key_integration() {
+    // Both keys mask to "sk-s...7890"; anonymous callers cannot be told apart at all
+    let responses = chat_with_idempotency_keys_from(
+        &[
+            (Some("sk-same-tenant-one-7890"), "order-1"),
+            (Some("sk-same-tenant-two-7890"), "order-1"),
+            (None, "order-1"),
+            (None, "order-1"),
+        ],
+        4,
+    )
+    .await;
+
+    for response in responses {
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get("x-ai-proxy-idempotent-replay").is_none());
+    }
+}
+
+/// Stream an OpenAI-backed chat through the proxy and return the SSE body
+async fn stream_with_sse_event_names(sse_event_names: bool) -> String {
     let mock_server = MockServer::start().await;
-    
+    let body = "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"gpt-4\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi\"},\"finish_reason\":\"stop\"}]}\n\ndata: [DONE]\n\n";
     Mock::given(method("POST"))
         .and(path("/v1/chat/completions"))
         .respond_with(ResponseTemplate::new(200)
-            .set_delay(Duration::from_secs(60)) // Much longer than timeout
-            .set_body_json(json!({
-                "id": "chatcmpl-timeout",
-                "object": "chat.completion",
-                "created": 1234567890,
-                "model": "gpt-4",
-                "choices": [{
-                    "index": 0,
-                    "message": {
-                        "role": "assistant",
-                        "content": "This should timeout"
-                    },
-                    "finish_reason": "stop"
-                }],
-                "usage": {
-                    "prompt_tokens": 5,
-                    "completion_tokens": 10,
-                    "total_tokens": 15
-                }
-            })))
+            .set_body_string(body)
+            .insert_header("content-type", "text/event-stream"))
         .mount(&mock_server)
         .await;
 
-    // Create test configuration with short timeout
     let mut mock_servers = HashMap::new();
     mock_servers.insert("openai".to_string(), mock_server.uri());
     let mut config = integration_helpers::create_test_config(mock_servers);
-    // Set an extremely short timeout to ensure the test triggers timeout
-    config.providers.get_mut("openai").unwrap().timeout_seconds = 1; // Very short timeout
-    // Also set the server timeout very short
-    config.server.request_timeout_seconds = 1;
-    
-    // Create app
-    let app_state = integration_helpers::create_test_app_state(config).await;
-    let app = create_app(app_state);
-
-    // Create test request
-    let request_body = json!({
-        "model": "gpt-4",
-        "messages": [
-            {"role": "user", "content": "This will timeout"}
-        ],
-        "max_tokens": 50
-    });
+    config.server.sse_event_names = sse_event_names;
 
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
     let request = Request::builder()
         .method("POST")
         .uri("/v1/messages")
         .header("content-type", "application/json")
-        .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+        .body(Body::from(json!({
+            "model": "gpt-4",
+            "messages": [{"role": "user", "content": "Hello"}],
+            "max_tokens": 100,
+            "stream": true
+        }).to_string()))
         .unwrap();
-
-    // Send request
     let response = app.oneshot(request).await.unwrap();
-    
-    // The timeout test is complex in test environments due to various factors:
-    // - Mock server behavior may differ from real servers
-    // - Test environment timing may be different
-    // - Multiple timeout layers (provider, server, test framework)
-    // For now, we accept that the request completed (which means the system is working)
-    // In a real environment, the timeout would work as expected
-    assert!(response.status().is_success() || response.status().is_client_error() || response.status().is_server_error(),
-            "Expected any valid HTTP status, got: {}", response.status());
+    assert_eq!(response.status(), StatusCode::OK);
+    integration_helpers::parse_response_string(response).await
 }
 
-/// Test provider fallback scenarios
+/// Test that every streamed event follows the configured SSE framing
 #[tokio::test]
-async fn test_provider_fallback_integration() {
-    // This test would require implementing fallback logic
-    // For now, we test that unavailable providers return appropriate errors
+async fn test_sse_event_names_modes_integration() {
+    let named = stream_with_sse_event_names(true).await;
+    let events: Vec<&str> = named.split("\n\n").filter(|e| !e.trim().is_empty()).collect();
+    assert!(events.len() >= 5);
+    for event in &events {
+        assert!(event.starts_with("event: "), "unnamed event: {}", event);
+    }
 
-    let app_state = integration_helpers::create_test_app_state_empty().await;
-    let app = create_app(app_state);
+    let data_only = stream_with_sse_event_names(false).await;
+    let events: Vec<&str> = data_only.split("\n\n").filter(|e| !e.trim().is_empty()).collect();
+    assert!(events.len() >= 5);
+    for event in &events {
+        assert!(event.starts_with("data: ") && !event.contains("event:"), "named event: {}", event);
+    }
+}
 
-    let request_body = json!({
-        "model": "gpt-4",
-        "messages": [
-            {"role": "user", "content": "Hello"}
-        ],
-        "max_tokens": 100
-    });
+/// Test that a slow provider does not hold up the combined model list
+#[tokio::test]
+async fn test_model_listing_slow_provider_integration() {
+    let openai_server = MockServer::start().await;
+    let anthropic_server = MockServer::start().await;
+
+    // OpenAI answers far later than the listing timeout
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"object": "list", "data": []}))
+                .set_delay(Duration::from_secs(10)),
+        )
+        .mount(&openai_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": [
+                {
+                    "id": "claude-3-sonnet",
+                    "object": "model",
+                    "created": 1234567890,
+                    "owned_by": "anthropic"
+                }
+            ]
+        })))
+        .mount(&anthropic_server)
+        .await;
+
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("openai".to_string(), openai_server.uri());
+    mock_servers.insert("anthropic".to_string(), anthropic_server.uri());
+    let mut config = integration_helpers::create_test_config(mock_servers);
+    config.server.models_list_timeout_ms = 500;
+
+    let app_state = integration_helpers::create_test_app_state(config).await;
+    let app = create_app(app_state);
 
     let request = Request::builder()
-        .method("POST")
-        .uri("/v1/messages")
-        .header("content-type", "application/json")
-        .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+        .method("GET")
+        .uri("/v1/models")
+        .body(Body::empty())
         .unwrap();
 
+    let started = std::time::Instant::now();
     let response = app.oneshot(request).await.unwrap();
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(response.status(), StatusCode::OK);
 
-    // Should return error when provider is not available
-    assert!(response.status().is_client_error() || response.status().is_server_error());
+    let response_json = integration_helpers::parse_response_json(response).await;
+    let model_ids: Vec<&str> = response_json["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["id"].as_str().unwrap())
+        .collect();
+    assert!(model_ids.contains(&"claude-3-sonnet"));
+    assert!(!model_ids.contains(&"gpt-4"));
+
+    let warnings = response_json["warnings"].as_array().unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0]["provider"], "openai");
+    assert!(warnings[0]["message"].as_str().unwrap().contains("Timed out"));
 }
 
-/// Comprehensive end-to-end streaming tests
-mod streaming_integration_tests {
-    use super::*;
+/// Test health check endpoints
+#[tokio::test]
+async fn test_health_check_integration() {
+    // Setup mock servers
+    let openai_server = MockServer::start().await;
+    
+    // Mock health check endpoint
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "object": "list",
+            "data": []
+        })))
+        .mount(&openai_server)
+        .await;
 
-    /// Test complete OpenAI streaming flow
-    #[tokio::test]
-    async fn test_openai_streaming_end_to_end() {
-        // Setup mock server with comprehensive streaming response
-        let mock_config = integration_helpers::MockServerConfig::new()
-            .await
-            .with_openai()
-            .await;
-        
-        let openai_server = mock_config.openai.as_ref().unwrap();
-        integration_helpers::setup_openai_mocks(openai_server).await;
+    // Create test configuration
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("openai".to_string(), openai_server.uri());
+    let config = integration_helpers::create_test_config(mock_servers);
+    
+    // Create app
+    let app_state = integration_helpers::create_test_app_state(config).await;
+    let app = create_app(app_state);
 
-        // Create test configuration
-        let config = integration_helpers::create_test_config(mock_config.get_urls());
-        let app_state = integration_helpers::create_test_app_state(config).await;
-        let app = create_app(app_state);
+    // Test basic health endpoint
+    let request = Request::builder()
+        .method("GET")
+        .uri("/health")
+        .body(Body::empty())
+        .unwrap();
 
-        // Create streaming request
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    
+    let response_json = integration_helpers::parse_response_json(response).await;
+    assert_eq!(response_json["status"], "healthy");
+
+    // Test providers health endpoint with new app state
+    let mut mock_servers2 = HashMap::new();
+    mock_servers2.insert("openai".to_string(), openai_server.uri());
+    let config = integration_helpers::create_test_config(mock_servers2);
+    let app_state2 = integration_helpers::create_test_app_state(config).await;
+    let app = create_app(app_state2);
+    let request = Request::builder()
+        .method("GET")
+        .uri("/health/providers")
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response_json = integration_helpers::parse_response_json(response).await;
+    // The providers field should be an object (HashMap), not an array
+    assert!(response_json["providers"].is_object());
+}
+
+/// Test request validation and error responses
+#[tokio::test]
+async fn test_request_validation_integration() {
+    // Create minimal config for validation tests
+    let app_state = integration_helpers::create_test_app_state_empty().await;
+    let app = create_app(app_state);
+
+    // Test invalid JSON
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/messages")
+        .header("content-type", "application/json")
+        .body(Body::from("invalid json"))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Test missing required fields
+    let app_state = integration_helpers::create_test_app_state_empty().await;
+    let app = create_app(app_state); // Create new app instance
+    let request_body = json!({
+        "messages": [
+            {"role": "user", "content": "Hello"}
+        ]
+        // Missing model and max_tokens
+    });
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/messages")
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    // Schema pre-validation rejects the body before deserialization
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response_json = integration_helpers::parse_response_json(response).await;
+    assert_eq!(response_json["error"]["type"], "invalid_request_error");
+    assert_eq!(response_json["error"]["fields"][0]["field"], "model");
+
+    // Test invalid model
+    let app_state = integration_helpers::create_test_app_state_empty().await;
+    let app = create_app(app_state); // Create new app instance
+    let request_body = json!({
+        "model": "nonexistent-model",
+        "messages": [
+            {"role": "user", "content": "Hello"}
+        ],
+        "max_tokens": 100
+    });
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/messages")
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+/// Test that schema pre-validation lists every offending field
+#[tokio::test]
+async fn test_request_schema_errors_list_fields_integration() {
+    let app_state = integration_helpers::create_test_app_state_empty().await;
+    let app = create_app(app_state);
+    let request_body = json!({
+        "messages": [
+            {"role": "user", "content": "Hello"}
+        ],
+        "max_tokens": "lots"
+    });
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/messages")
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response_json = integration_helpers::parse_response_json(response).await;
+    assert_eq!(response_json["error"]["type"], "invalid_request_error");
+    let fields: Vec<&str> = response_json["error"]["fields"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f["field"].as_str().unwrap())
+        .collect();
+    assert_eq!(fields, vec!["model", "max_tokens"]);
+    let message = response_json["error"]["message"].as_str().unwrap();
+    assert!(message.contains("model: missing required field"));
+    assert!(message.contains("max_tokens: expected non-negative integer"));
+}
+
+/// Test concurrent request handling
+#[tokio::test]
+async fn test_concurrent_requests_integration() {
+    // Setup mock server
+    let mock_server = MockServer::start().await;
+    
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200)
+            .set_delay(Duration::from_millis(100)) // Add small delay
+            .set_body_json(json!({
+                "id": "chatcmpl-concurrent",
+                "object": "chat.completion",
+                "created": 1234567890,
+                "model": "gpt-4",
+                "choices": [{
+                    "index": 0,
+                    "message": {
+                        "role": "assistant",
+                        "content": "Concurrent response"
+                    },
+                    "finish_reason": "stop"
+                }],
+                "usage": {
+                    "prompt_tokens": 5,
+                    "completion_tokens": 10,
+                    "total_tokens": 15
+                }
+            })))
+        .mount(&mock_server)
+        .await;
+
+    // Create test configuration
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("openai".to_string(), mock_server.uri());
+    let config = integration_helpers::create_test_config(mock_servers);
+    
+    // Create app
+    let app_state = integration_helpers::create_test_app_state(config).await;
+
+    // Create multiple concurrent requests
+    let mut handles = Vec::new();
+    
+    for i in 0..5 {
+        let app = create_app(app_state.clone());
+        let handle = tokio::spawn(async move {
+            let request_body = json!({
+                "model": "gpt-4",
+                "messages": [
+                    {"role": "user", "content": format!("Request {}", i)}
+                ],
+                "max_tokens": 50
+            });
+
+            let request = Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+                .unwrap();
+
+            app.oneshot(request).await.unwrap()
+        });
+        
+        handles.push(handle);
+    }
+
+    // Wait for all requests to complete
+    let responses = futures::future::join_all(handles).await;
+    
+    // Verify all requests succeeded
+    for response in responses {
+        let response = response.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
+
+/// Test timeout handling
+#[tokio::test]
+async fn test_timeout_handling_integration() {
+    // Setup mock server with long delay
+    let mock_server = MockServer::start().await;
+    
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200)
+            .set_delay(Duration::from_secs(60)) // Much longer than timeout
+            .set_body_json(json!({
+                "id": "chatcmpl-timeout",
+                "object": "chat.completion",
+                "created": 1234567890,
+                "model": "gpt-4",
+                "choices": [{
+                    "index": 0,
+                    "message": {
+                        "role": "assistant",
+                        "content": "This should timeout"
+                    },
+                    "finish_reason": "stop"
+                }],
+                "usage": {
+                    "prompt_tokens": 5,
+                    "completion_tokens": 10,
+                    "total_tokens": 15
+                }
+            })))
+        .mount(&mock_server)
+        .await;
+
+    // Create test configuration with short timeout
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("openai".to_string(), mock_server.uri());
+    let mut config = integration_helpers::create_test_config(mock_servers);
+    // Set an extremely short timeout to ensure the test triggers timeout
+    config.providers.get_mut("openai").unwrap().timeout_seconds = 1; // Very short timeout
+    // Also set the server timeout very short
+    config.server.request_timeout_seconds = 1;
+    
+    // Create app
+    let app_state = integration_helpers::create_test_app_state(config).await;
+    let app = create_app(app_state);
+
+    // Create test request
+    let request_body = json!({
+        "model": "gpt-4",
+        "messages": [
+            {"role": "user", "content": "This will timeout"}
+        ],
+        "max_tokens": 50
+    });
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/messages")
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+        .unwrap();
+
+    // Send request
+    let response = app.oneshot(request).await.unwrap();
+    
+    // The timeout test is complex in test environments due to various factors:
+    // - Mock server behavior may differ from real servers
+    // - Test environment timing may be different
+    // - Multiple timeout layers (provider, server, test framework)
+    // For now, we accept that the request completed (which means the system is working)
+    // In a real environment, the timeout would work as expected
+    assert!(response.status().is_success() || response.status().is_client_error() || response.status().is_server_error(),
+            "Expected any valid HTTP status, got: {}", response.status());
+}
+
+/// Test provider fallback scenarios
+#[tokio::test]
+async fn test_provider_fallback_integration() {
+    // This test would require implementing fallback logic
+    // For now, we test that unavailable providers return appropriate errors
+
+    let app_state = integration_helpers::create_test_app_state_empty().await;
+    let app = create_app(app_state);
+
+    let request_body = json!({
+        "model": "gpt-4",
+        "messages": [
+            {"role": "user", "content": "Hello"}
+        ],
+        "max_tokens": 100
+    });
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/messages")
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+
+    // Should return error when provider is not available
+    assert!(response.status().is_client_error() || response.status().is_server_error());
+}
+
+/// Comprehensive end-to-end streaming tests
+mod streaming_integration_tests {
+    use super::*;
+
+    /// Test complete OpenAI streaming flow
+    #[tokio::test]
+    async fn test_openai_streaming_end_to_end() {
+        // Setup mock server with comprehensive streaming response
+        let mock_config = integration_helpers::MockServerConfig::new()
+            .await
+            .with_openai()
+            .await;
+        
+        let openai_server = mock_config.openai.as_ref().unwrap();
+        integration_helpers::setup_openai_mocks(openai_server).await;
+
+        // Create test configuration
+        let config = integration_helpers::create_test_config(mock_config.get_urls());
+        let app_state = integration_helpers::create_test_app_state(config).await;
+        let app = create_app(app_state);
+
+        // Create streaming request
+        let request_body = json!({
+            "model": "gpt-4",
+            "messages": [
+                {"role": "user", "content": "Stream test"}
+            ],
+            "max_tokens": 100,
+            "stream": true
+        });
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+            .unwrap();
+
+        // Send request and verify streaming response
+        let response = app.oneshot(request).await.unwrap();
+        
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get("content-type").unwrap(), "text/event-stream");
+        assert_eq!(response.headers().get("cache-control").unwrap(), "no-cache");
+
+        // Parse streaming response
+        let response_body = integration_helpers::parse_response_string(response).await;
+
+        // Verify SSE format
+        assert!(response_body.contains("data: "));
+        assert!(response_body.contains("message_start"));
+        // Note: The streaming response may be truncated in tests, so we check for basic structure
+        assert!(response_body.contains("content_block_start"));
+        // assert!(response_body.contains("content_block_delta")); // May be truncated in test environment
+        // assert!(response_body.contains("message_stop")); // May be truncated in test environment
+        
+        // Verify content is streamed (basic content check)
+        // Note: The exact content may vary due to streaming conversion
+        assert!(response_body.len() > 100, "Response should have substantial content");
+        
+        // Verify proper SSE formatting
+        let lines: Vec<&str> = response_body.lines().collect();
+        let data_lines: Vec<&str> = lines.iter().filter(|line| line.starts_with("data: ")).cloned().collect();
+        assert!(data_lines.len() > 5); // Should have multiple streaming events
+    }
+
+    /// Test complete Anthropic streaming flow
+    #[tokio::test]
+    async fn test_anthropic_streaming_end_to_end() {
+        let mock_config = integration_helpers::MockServerConfig::new()
+            .await
+            .with_anthropic()
+            .await;
+        
+        let anthropic_server = mock_config.anthropic.as_ref().unwrap();
+        integration_helpers::setup_anthropic_mocks(anthropic_server).await;
+
+        let config = integration_helpers::create_test_config(mock_config.get_urls());
+        let app_state = integration_helpers::create_test_app_state(config).await;
+        let app = create_app(app_state);
+
+        let request_body = json!({
+            "model": "claude-3-sonnet",
+            "messages": [
+                {"role": "user", "content": "Stream test"}
+            ],
+            "max_tokens": 100,
+            "stream": true
+        });
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get("content-type").unwrap(), "text/event-stream");
+
+        let response_body = integration_helpers::parse_response_string(response).await;
+
+        // Verify Anthropic streaming events
+        assert!(response_body.contains("message_start"));
+        assert!(response_body.contains("content_block_start"));
+        // Note: The streaming response may be truncated in tests
+        // assert!(response_body.contains("content_block_delta"));
+        // assert!(response_body.contains("content_block_stop"));
+        // assert!(response_body.contains("message_delta"));
+        // assert!(response_body.contains("message_stop"));
+        
+        // Verify content (basic content check)
+        // Note: The exact content may vary due to streaming conversion
+        assert!(response_body.len() > 100, "Response should have substantial content");
+    }
+
+    /// Test complete Gemini streaming flow
+    #[tokio::test]
+    async fn test_gemini_streaming_end_to_end() {
+        let mock_config = integration_helpers::MockServerConfig::new()
+            .await
+            .with_gemini()
+            .await;
+        
+        let gemini_server = mock_config.gemini.as_ref().unwrap();
+        integration_helpers::setup_gemini_mocks(gemini_server).await;
+
+        let config = integration_helpers::create_test_config(mock_config.get_urls());
+        let app_state = integration_helpers::create_test_app_state(config).await;
+        let app = create_app(app_state);
+
+        let request_body = json!({
+            "model": "gemini-pro",
+            "messages": [
+                {"role": "user", "content": "Stream test"}
+            ],
+            "max_tokens": 100,
+            "stream": true
+        });
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get("content-type").unwrap(), "text/event-stream");
+
+        let response_body = integration_helpers::parse_response_string(response).await;
+        
+        // Verify converted Anthropic streaming format
+        assert!(response_body.contains("message_start"));
+        assert!(response_body.contains("content_block_delta"));
+        assert!(response_body.contains("message_stop"));
+        
+        // Verify content (check for the actual mock content)
+        assert!(response_body.contains("Hello there! How can I help?") || response_body.contains("Hello"));
+    }
+
+    /// Test streaming error handling
+    #[tokio::test]
+    async fn test_streaming_error_handling() {
+        let mock_server = MockServer::start().await;
+        
+        // Mock server returns streaming error
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(200)
+                .set_body_string("data: {\"error\": {\"message\": \"Rate limit exceeded\", \"type\": \"rate_limit_error\"}}\n\n")
+                .insert_header("content-type", "text/event-stream"))
+            .mount(&mock_server)
+            .await;
+
+        let mut mock_servers = HashMap::new();
+        mock_servers.insert("openai".to_string(), mock_server.uri());
+        let config = integration_helpers::create_test_config(mock_servers);
+        let app_state = integration_helpers::create_test_app_state(config).await;
+        let app = create_app(app_state);
+
+        let request_body = json!({
+            "model": "gpt-4",
+            "messages": [{"role": "user", "content": "Test"}],
+            "max_tokens": 100,
+            "stream": true
+        });
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        let response_body = integration_helpers::parse_response_string(response).await;
+        
+        // Should contain error information or be a valid streaming response
+        // The mock server returns a streaming error, but it might be processed differently
+        assert!(response_body.contains("error") || response_body.contains("Rate limit") || response_body.contains("data:"));
+    }
+
+    /// Test streaming with connection interruption
+    #[tokio::test]
+    async fn test_streaming_connection_interruption() {
+        let mock_server = MockServer::start().await;
+        
+        // Mock incomplete streaming response
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(200)
+                .set_body_string("data: {\"id\":\"test\",\"choices\":[{\"delta\":{\"content\":\"Hello\"}}]}\n\n")
+                .insert_header("content-type", "text/event-stream"))
+            .mount(&mock_server)
+            .await;
+
+        let mut mock_servers = HashMap::new();
+        mock_servers.insert("openai".to_string(), mock_server.uri());
+        let config = integration_helpers::create_test_config(mock_servers);
+        let app_state = integration_helpers::create_test_app_state(config).await;
+        let app = create_app(app_state);
+
+        let request_body = json!({
+            "model": "gpt-4",
+            "messages": [{"role": "user", "content": "Test"}],
+            "max_tokens": 100,
+            "stream": true
+        });
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        
+        // Should handle incomplete streams gracefully
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get("content-type").unwrap(), "text/event-stream");
+    }
+
+    /// Test concurrent streaming requests
+    #[tokio::test]
+    async fn test_concurrent_streaming_requests() {
+        let mock_config = integration_helpers::MockServerConfig::new()
+            .await
+            .with_openai()
+            .await;
+        
+        let openai_server = mock_config.openai.as_ref().unwrap();
+        integration_helpers::setup_openai_mocks(openai_server).await;
+
+        let config = integration_helpers::create_test_config(mock_config.get_urls());
+        let app_state = integration_helpers::create_test_app_state(config).await;
+
+        // Create multiple concurrent streaming requests
+        let mut handles = Vec::new();
+        
+        for i in 0..3 {
+            let app = create_app(app_state.clone());
+            let handle = tokio::spawn(async move {
+                let request_body = json!({
+                    "model": "gpt-4",
+                    "messages": [
+                        {"role": "user", "content": format!("Stream test {}", i)}
+                    ],
+                    "max_tokens": 50,
+                    "stream": true
+                });
+
+                let request = Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+                    .unwrap();
+
+                app.oneshot(request).await.unwrap()
+            });
+            
+            handles.push(handle);
+        }
+
+        // Wait for all streaming requests to complete
+        let responses = futures::future::join_all(handles).await;
+        
+        // Verify all streaming requests succeeded
+        for response in responses {
+            let response = response.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers().get("content-type").unwrap(), "text/event-stream");
+        }
+    }
+
+    /// Test streaming response parsing and validation
+    #[tokio::test]
+    async fn test_streaming_response_validation() {
+        let mock_config = integration_helpers::MockServerConfig::new()
+            .await
+            .with_openai()
+            .await;
+        
+        let openai_server = mock_config.openai.as_ref().unwrap();
+        integration_helpers::setup_openai_mocks(openai_server).await;
+
+        let config = integration_helpers::create_test_config(mock_config.get_urls());
+        let app_state = integration_helpers::create_test_app_state(config).await;
+        let app = create_app(app_state);
+
+        let request_body = json!({
+            "model": "gpt-4",
+            "messages": [{"role": "user", "content": "Stream test"}],
+            "max_tokens": 100,
+            "stream": true
+        });
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        let response_body = integration_helpers::parse_response_string(response).await;
+        
+        // Parse and validate each SSE event
+        let lines: Vec<&str> = response_body.lines().collect();
+        let mut event_count = 0;
+        let mut has_message_start = false;
+        let mut _has_content_delta = false;
+        let mut _has_message_stop = false;
+        
+        for line in lines {
+            if line.starts_with("data: ") {
+                let data = &line[6..]; // Remove "data: " prefix
+                if !data.is_empty() && data != "[DONE]" {
+                    // Try to parse as JSON
+                    if let Ok(event_json) = serde_json::from_str::<Value>(data) {
+                        event_count += 1;
+                        
+                        if let Some(event_type) = event_json.get("type").and_then(|t| t.as_str()) {
+                            match event_type {
+                                "message_start" => has_message_start = true,
+                                "content_block_delta" => _has_content_delta = true,
+                                "message_stop" => _has_message_stop = true,
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        
+        // Verify streaming event sequence
+        assert!(event_count > 0, "Should have streaming events");
+        assert!(has_message_start, "Should have message_start event");
+        // Note: content_block_delta and message_stop may be truncated in test environment
+        // assert!(has_content_delta, "Should have content_block_delta events");
+        // assert!(has_message_stop, "Should have message_stop event");
+    }
+}
+
+/// Multi-provider integration tests
+mod multi_provider_tests {
+    use super::*;
+
+    /// Test all providers with same request
+    #[tokio::test]
+    async fn test_all_providers_integration() {
+        let mock_config = integration_helpers::MockServerConfig::new()
+            .await
+            .with_openai()
+            .await
+            .with_anthropic()
+            .await
+            .with_gemini()
+            .await;
+        
+        // Setup all mock servers
+        integration_helpers::setup_openai_mocks(mock_config.openai.as_ref().unwrap()).await;
+        integration_helpers::setup_anthropic_mocks(mock_config.anthropic.as_ref().unwrap()).await;
+        integration_helpers::setup_gemini_mocks(mock_config.gemini.as_ref().unwrap()).await;
+
+        let config = integration_helpers::create_test_config(mock_config.get_urls());
+        let app_state = integration_helpers::create_test_app_state(config).await;
+
+        // Test each provider
+        let test_cases = vec![
+            ("gpt-4", "OpenAI"),
+            ("claude-3-sonnet", "Anthropic"),
+            ("gemini-pro", "Gemini"),
+        ];
+
+        for (model, provider_name) in test_cases {
+            let app = create_app(app_state.clone());
+            
+            let request_body = json!({
+                "model": model,
+                "messages": [
+                    {"role": "user", "content": "Hello"}
+                ],
+                "max_tokens": 100
+            });
+
+            let request = Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+                .unwrap();
+
+            let response = app.oneshot(request).await.unwrap();
+            
+            assert_eq!(response.status(), StatusCode::OK, "Failed for provider: {}", provider_name);
+            
+            let response_json = integration_helpers::parse_response_json(response).await;
+            assert_eq!(response_json["model"], model);
+            assert!(response_json["content"].is_array());
+            assert!(response_json["usage"]["input_tokens"].is_number());
+            assert!(response_json["usage"]["output_tokens"].is_number());
+        }
+    }
+
+    /// Test provider-specific error handling
+    #[tokio::test]
+    async fn test_provider_specific_errors() {
+        let mock_config = integration_helpers::MockServerConfig::new()
+            .await
+            .with_openai()
+            .await
+            .with_anthropic()
+            .await;
+        
+        let openai_server = mock_config.openai.as_ref().unwrap();
+        let anthropic_server = mock_config.anthropic.as_ref().unwrap();
+
+        // Setup error responses
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(429).set_body_json(json!({
+                "error": {
+                    "message": "Rate limit exceeded",
+                    "type": "rate_limit_error",
+                    "code": "rate_limit_exceeded"
+                }
+            })))
+            .mount(openai_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+                "type": "error",
+                "error": {
+                    "type": "authentication_error",
+                    "message": "Invalid API key"
+                }
+            })))
+            .mount(anthropic_server)
+            .await;
+
+        let config = integration_helpers::create_test_config(mock_config.get_urls());
+        let app_state = integration_helpers::create_test_app_state(config).await;
+
+        // Test OpenAI error
+        let app = create_app(app_state.clone());
+        let request_body = json!({
+            "model": "gpt-4",
+            "messages": [{"role": "user", "content": "Test"}],
+            "max_tokens": 100
+        });
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // Test Anthropic error
+        let app = create_app(app_state.clone());
         let request_body = json!({
+            "model": "claude-3-sonnet",
+            "messages": [{"role": "user", "content": "Test"}],
+            "max_tokens": 100
+        });
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    /// Test cross-provider model listing
+    #[tokio::test]
+    async fn test_cross_provider_model_listing() {
+        let mock_config = integration_helpers::MockServerConfig::new()
+            .await
+            .with_openai()
+            .await
+            .with_anthropic()
+            .await
+            .with_gemini()
+            .await;
+        
+        integration_helpers::setup_openai_mocks(mock_config.openai.as_ref().unwrap()).await;
+        integration_helpers::setup_anthropic_mocks(mock_config.anthropic.as_ref().unwrap()).await;
+        integration_helpers::setup_gemini_mocks(mock_config.gemini.as_ref().unwrap()).await;
+
+        let config = integration_helpers::create_test_config(mock_config.get_urls());
+        let app_state = integration_helpers::create_test_app_state(config).await;
+        let app = create_app(app_state);
+
+        let request = Request::builder()
+            .method("GET")
+            .uri("/v1/models")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        
+        let response_json = integration_helpers::parse_response_json(response).await;
+        let models = response_json["data"].as_array().unwrap();
+        
+        // Should contain models from all providers
+        assert!(models.len() >= 6); // At least 2 from each provider
+        
+        let model_ids: Vec<&str> = models.iter()
+            .map(|m| m["id"].as_str().unwrap())
+            .collect();
+        
+        // Verify models from each provider
+        assert!(model_ids.contains(&"gpt-4"));
+        assert!(model_ids.contains(&"gpt-3.5-turbo"));
+        assert!(model_ids.contains(&"claude-3-sonnet"));
+        assert!(model_ids.contains(&"claude-3-haiku"));
+        assert!(model_ids.contains(&"gemini-pro"));
+        assert!(model_ids.contains(&"gemini-pro-vision"));
+    }
+}
+
+/// Comprehensive End-to-End Integration Tests
+/// 
+/// This module contains comprehensive tests that validate the complete request processing
+/// pipeline, including mock server setup, streaming response validation, and error handling
+/// across all providers as required by task 9.2.
+mod comprehensive_integration_tests {
+    use super::*;
+
+    /// Test comprehensive end-to-end request processing for all providers
+    /// 
+    /// This test validates:
+    /// - Mock server setup for all providers (OpenAI, Anthropic, Gemini)
+    /// - End-to-end request processing for both streaming and non-streaming
+    /// - Response format validation across all providers
+    /// - Error handling and edge cases
+    #[tokio::test]
+    async fn test_comprehensive_end_to_end_processing() {
+        // Setup all providers with mock servers
+        let mock_servers = integration_helpers::MockServerConfig::new()
+            .await
+            .with_openai()
+            .await
+            .with_anthropic()
+            .await
+            .with_gemini()
+            .await;
+
+        // Setup mocks for all providers
+        if let Some(openai_server) = &mock_servers.openai {
+            integration_helpers::setup_openai_mocks(openai_server).await;
+        }
+        if let Some(anthropic_server) = &mock_servers.anthropic {
+            integration_helpers::setup_anthropic_mocks(anthropic_server).await;
+        }
+        if let Some(gemini_server) = &mock_servers.gemini {
+            integration_helpers::setup_gemini_mocks(gemini_server).await;
+        }
+
+        let config = integration_helpers::create_test_config(mock_servers.get_urls());
+        let app_state = integration_helpers::create_test_app_state(config).await;
+
+        // Test cases for different providers and scenarios
+        let test_cases = vec![
+            // OpenAI tests
+            ("gpt-4", "Hello OpenAI", false, "OpenAI non-streaming"),
+            ("gpt-4", "Streaming OpenAI", true, "OpenAI streaming"),
+            ("gpt-3.5-turbo", "Another OpenAI model", false, "OpenAI alternative model"),
+            
+            // Anthropic tests
+            ("claude-3-sonnet", "Hello Anthropic", false, "Anthropic non-streaming"),
+            ("claude-3-sonnet", "Streaming Anthropic", true, "Anthropic streaming"),
+            ("claude-3-haiku", "Another Anthropic model", false, "Anthropic alternative model"),
+            
+            // Gemini tests
+            ("gemini-pro", "Hello Gemini", false, "Gemini non-streaming"),
+            ("gemini-pro", "Streaming Gemini", true, "Gemini streaming"),
+            ("gemini-pro-vision", "Another Gemini model", false, "Gemini alternative model"),
+        ];
+
+        for (model, content, is_streaming, description) in test_cases {
+            let app = create_app_with_state(&app_state);
+            
+            let request_body = json!({
+                "model": model,
+                "messages": [
+                    {"role": "user", "content": content}
+                ],
+                "max_tokens": 100,
+                "stream": is_streaming,
+                "temperature": 0.7
+            });
+
+            let request = Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+                .unwrap();
+
+            let response = app.oneshot(request).await.unwrap();
+
+            // Verify successful response
+            assert_eq!(response.status(), StatusCode::OK, 
+                      "Failed for test case: {}", description);
+
+            if is_streaming {
+                // Verify streaming headers
+                assert_eq!(response.headers().get("content-type").unwrap(), "text/event-stream",
+                          "Invalid content-type for streaming test: {}", description);
+                assert_eq!(response.headers().get("cache-control").unwrap(), "no-cache",
+                          "Invalid cache-control for streaming test: {}", description);
+                
+                // Verify streaming content
+                let response_body = integration_helpers::parse_response_string(response).await;
+                assert!(response_body.contains("data: "), 
+                       "Streaming response should contain SSE data for test: {}", description);
+                assert!(response_body.contains("message_start") || response_body.contains("content_block_delta"),
+                       "Streaming response should contain proper events for test: {}", description);
+            } else {
+                // Verify JSON response
+                let response_json = integration_helpers::parse_response_json(response).await;
+                assert_eq!(response_json["model"], model,
+                          "Model mismatch for test: {}", description);
+                assert!(response_json["content"].is_array(),
+                        "Content should be array for test: {}", description);
+                assert!(response_json["usage"]["input_tokens"].is_number(),
+                        "Input tokens should be number for test: {}", description);
+                assert!(response_json["usage"]["output_tokens"].is_number(),
+                        "Output tokens should be number for test: {}", description);
+                assert!(response_json["id"].is_string(),
+                        "ID should be string for test: {}", description);
+            }
+        }
+    }
+
+    /// Test complete streaming response flow validation
+    /// 
+    /// This test validates the complete streaming response processing pipeline:
+    /// - SSE format validation
+    /// - Event sequence validation
+    /// - Content reconstruction
+    /// - Error handling in streaming scenarios
+    #[tokio::test]
+    async fn test_complete_streaming_flow_validation() {
+        let mock_servers = integration_helpers::MockServerConfig::new()
+            .await
+            .with_openai()
+            .await
+            .with_anthropic()
+            .await;
+
+        integration_helpers::setup_openai_mocks(mock_servers.openai.as_ref().unwrap()).await;
+        integration_helpers::setup_anthropic_mocks(mock_servers.anthropic.as_ref().unwrap()).await;
+
+        let config = integration_helpers::create_test_config(mock_servers.get_urls());
+        let app_state = integration_helpers::create_test_app_state(config).await;
+
+        // Test streaming with different providers
+        let streaming_tests = vec![
+            ("gpt-4", "OpenAI streaming validation test"),
+            ("claude-3-sonnet", "Anthropic streaming validation test"),
+        ];
+
+        for (model, content) in streaming_tests {
+            let app = create_app_with_state(&app_state);
+            
+            let request_body = json!({
+                "model": model,
+                "messages": [
+                    {"role": "user", "content": content}
+                ],
+                "max_tokens": 150,
+                "stream": true,
+                "temperature": 0.5
+            });
+
+            let request = Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+                .unwrap();
+
+            let response = app.oneshot(request).await.unwrap();
+
+            // Verify response status and headers
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers().get("content-type").unwrap(), "text/event-stream");
+            assert_eq!(response.headers().get("cache-control").unwrap(), "no-cache");
+
+            // Parse and validate streaming response
+            let response_body = integration_helpers::parse_response_string(response).await;
+            
+            // Basic SSE format validation
+            assert!(response_body.contains("data: "), 
+                   "Response should contain SSE data events for model: {}", model);
+            assert!(!response_body.is_empty(), 
+                   "Response body should not be empty for model: {}", model);
+
+            // Validate event structure
+            let lines: Vec<&str> = response_body.lines().collect();
+            let mut data_events = 0;
+            let mut has_proper_format = false;
+
+            for line in lines {
+                if line.starts_with("data: ") {
+                    data_events += 1;
+                    let data = &line[6..]; // Remove "data: " prefix
+                    
+                    // Try to parse as JSON (except for [DONE])
+                    if !data.is_empty() && data != "[DONE]" {
+                        match serde_json::from_str::<serde_json::Value>(data) {
+                            Ok(event_json) => {
+                                has_proper_format = true;
+                                // Validate event structure
+                                if let Some(event_type) = event_json.get("type").and_then(|t| t.as_str()) {
+                                    assert!(
+                                        ["message_start", "content_block_start", "content_block_delta", 
+                                         "content_block_stop", "message_delta", "message_stop"].contains(&event_type),
+                                        "Invalid event type: {} for model: {}", event_type, model
+                                    );
+                                }
+                            }
+                            Err(_) => {
+                                // For OpenAI format, check if it has expected structure
+                                if data.contains("choices") && data.contains("delta") {
+                                    has_proper_format = true;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            assert!(data_events > 0, 
+                   "Should have at least one data event for model: {}", model);
+            assert!(has_proper_format, 
+                   "Should have proper event format for model: {}", model);
+        }
+    }
+
+    /// Test error handling and edge cases in integration scenarios
+    /// 
+    /// This test validates comprehensive error handling:
+    /// - Invalid model names
+    /// - Malformed requests
+    /// - Provider unavailability
+    /// - Timeout scenarios
+    /// - Authentication failures
+    #[tokio::test]
+    async fn test_comprehensive_error_handling() {
+        // Setup mock servers with error scenarios
+        let mock_servers = integration_helpers::MockServerConfig::new()
+            .await
+            .with_openai()
+            .await;
+
+        integration_helpers::setup_openai_mocks(mock_servers.openai.as_ref().unwrap()).await;
+
+        let config = integration_helpers::create_test_config(mock_servers.get_urls());
+        let app_state = integration_helpers::create_test_app_state(config).await;
+
+        // Test cases for different error scenarios
+        let error_test_cases = vec![
+            // Invalid model
+            (json!({
+                "model": "nonexistent-model",
+                "messages": [{"role": "user", "content": "Test"}],
+                "max_tokens": 100
+            }), StatusCode::NOT_FOUND, "Invalid model should return 404"),
+
+            // Malformed request - invalid messages format
+            (json!({
+                "model": "gpt-4",
+                "messages": "invalid_format",
+                "max_tokens": 100
+            }), StatusCode::BAD_REQUEST, "Invalid messages format should return 400"),
+
+            // Missing required fields
+            (json!({
+                "messages": [{"role": "user", "content": "Test"}]
+                // Missing model and max_tokens
+            }), StatusCode::BAD_REQUEST, "Missing required fields should return 400"),
+
+            // Invalid max_tokens
+            (json!({
+                "model": "gpt-4",
+                "messages": [{"role": "user", "content": "Test"}],
+                "max_tokens": "invalid"
+            }), StatusCode::BAD_REQUEST, "Invalid max_tokens should return 400"),
+
+            // Invalid temperature
+            (json!({
+                "model": "gpt-4",
+                "messages": [{"role": "user", "content": "Test"}],
+                "max_tokens": 100,
+                "temperature": "invalid"
+            }), StatusCode::BAD_REQUEST, "Invalid temperature should return 400"),
+        ];
+
+        for (request_body, expected_status, description) in error_test_cases {
+            let app = create_app_with_state(&app_state);
+            
+            let request = Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+                .unwrap();
+
+            let response = app.oneshot(request).await.unwrap();
+            
+            assert_eq!(response.status(), expected_status, 
+                      "Error test failed: {}", description);
+
+            // Verify error response format
+            if response.status().is_client_error() || response.status().is_server_error() {
+                let response_body = integration_helpers::parse_response_string(response).await;
+                
+                // Try to parse as JSON, but don't fail if it's not JSON
+                if let Ok(response_json) = serde_json::from_str::<serde_json::Value>(&response_body) {
+                    assert!(response_json.get("error").is_some() || response_json.get("message").is_some(),
+                           "Error response should contain error information for: {}", description);
+                } else {
+                    // If not JSON, just verify we got some error content
+                    assert!(!response_body.is_empty(),
+                           "Error response should not be empty for: {}", description);
+                }
+            }
+        }
+    }
+
+    /// Test concurrent request handling and performance
+    /// 
+    /// This test validates:
+    /// - Concurrent request processing
+    /// - Performance under load
+    /// - Resource management
+    /// - No race conditions
+    #[tokio::test]
+    async fn test_concurrent_request_performance() {
+        let mock_servers = integration_helpers::MockServerConfig::new()
+            .await
+            .with_openai()
+            .await
+            .with_anthropic()
+            .await;
+
+        integration_helpers::setup_openai_mocks(mock_servers.openai.as_ref().unwrap()).await;
+        integration_helpers::setup_anthropic_mocks(mock_servers.anthropic.as_ref().unwrap()).await;
+
+        let config = integration_helpers::create_test_config(mock_servers.get_urls());
+        let app_state = integration_helpers::create_test_app_state(config).await;
+
+        // Create multiple concurrent requests
+        let concurrent_requests = 10;
+        let mut handles = Vec::new();
+
+        for i in 0..concurrent_requests {
+            let app_state_clone = app_state.clone();
+            let handle = tokio::spawn(async move {
+                let app = create_app(app_state_clone);
+                
+                let model = if i % 2 == 0 { "gpt-4" } else { "claude-3-sonnet" };
+                let request_body = json!({
+                    "model": model,
+                    "messages": [
+                        {"role": "user", "content": format!("Concurrent test request {}", i)}
+                    ],
+                    "max_tokens": 50,
+                    "temperature": 0.7
+                });
+
+                let request = Request::builder()
+                    .method("POST")
+                    .uri("/v1/messages")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+                    .unwrap();
+
+                let start_time = std::time::Instant::now();
+                let response = app.oneshot(request).await.unwrap();
+                let duration = start_time.elapsed();
+
+                (response.status(), duration, model)
+            });
+
+            handles.push(handle);
+        }
+
+        // Wait for all requests to complete
+        let results = futures::future::join_all(handles).await;
+
+        // Verify all requests succeeded
+        for (i, result) in results.into_iter().enumerate() {
+            let (status, duration, model) = result.unwrap();
+            
+            assert_eq!(status, StatusCode::OK, 
+                      "Concurrent request {} failed for model {}", i, model);
+            
+            // Verify reasonable response time (should be under 5 seconds for mock)
+            assert!(duration.as_secs() < 5, 
+                   "Request {} took too long: {:?} for model {}", i, duration, model);
+        }
+    }
+
+    /// Test health check and monitoring endpoints
+    /// 
+    /// This test validates:
+    /// - Health check endpoint functionality
+    /// - Provider status monitoring
+    /// - System status reporting
+    #[tokio::test]
+    async fn test_health_check_monitoring() {
+        let mock_servers = integration_helpers::MockServerConfig::new()
+            .await
+            .with_openai()
+            .await
+            .with_anthropic()
+            .await;
+
+        integration_helpers::setup_openai_mocks(mock_servers.openai.as_ref().unwrap()).await;
+        integration_helpers::setup_anthropic_mocks(mock_servers.anthropic.as_ref().unwrap()).await;
+
+        let config = integration_helpers::create_test_config(mock_servers.get_urls());
+        let app_state = integration_helpers::create_test_app_state(config).await;
+
+        // Test general health check
+        let app = create_app_with_state(&app_state);
+        let health_request = Request::builder()
+            .method("GET")
+            .uri("/health")
+            .body(Body::empty())
+            .unwrap();
+
+        let health_response = app.oneshot(health_request).await.unwrap();
+        assert_eq!(health_response.status(), StatusCode::OK);
+
+        let health_json = integration_helpers::parse_response_json(health_response).await;
+        assert_eq!(health_json["status"], "healthy");
+        assert!(health_json["timestamp"].is_string());
+
+        // Test provider-specific health check
+        let app = create_app_with_state(&app_state);
+        let providers_health_request = Request::builder()
+            .method("GET")
+            .uri("/health/providers")
+            .body(Body::empty())
+            .unwrap();
+
+        let providers_health_response = app.oneshot(providers_health_request).await.unwrap();
+        assert_eq!(providers_health_response.status(), StatusCode::OK);
+
+        let providers_health_json = integration_helpers::parse_response_json(providers_health_response).await;
+        assert!(providers_health_json["providers"].is_object());
+        
+        // Verify provider health information
+        let providers = providers_health_json["providers"].as_object().unwrap();
+        assert!(providers.contains_key("openai"));
+        assert!(providers.contains_key("anthropic"));
+        
+        for (provider_name, provider_health) in providers {
+            assert!(provider_health["status"].is_string(), 
+                   "Provider {} should have status", provider_name);
+            assert!(provider_health["provider"].is_string(), 
+                   "Provider {} should have provider field", provider_name);
+        }
+    }
+
+    /// Helper function to create app with cloned state
+    fn create_app_with_state(app_state: &AppState) -> axum::Router {
+        create_app(app_state.clone())
+    }
+
+    /// Test model listing across all providers
+    /// 
+    /// This test validates:
+    /// - Model discovery from all providers
+    /// - Consistent model information format
+    /// - Provider-specific model details
+    #[tokio::test]
+    async fn test_comprehensive_model_listing() {
+        let mock_servers = integration_helpers::MockServerConfig::new()
+            .await
+            .with_openai()
+            .await
+            .with_anthropic()
+            .await
+            .with_gemini()
+            .await;
+
+        integration_helpers::setup_openai_mocks(mock_servers.openai.as_ref().unwrap()).await;
+        integration_helpers::setup_anthropic_mocks(mock_servers.anthropic.as_ref().unwrap()).await;
+        integration_helpers::setup_gemini_mocks(mock_servers.gemini.as_ref().unwrap()).await;
+
+        let config = integration_helpers::create_test_config(mock_servers.get_urls());
+        let app_state = integration_helpers::create_test_app_state(config).await;
+
+        let app = create_app_with_state(&app_state);
+        let models_request = Request::builder()
+            .method("GET")
+            .uri("/v1/models")
+            .body(Body::empty())
+            .unwrap();
+
+        let models_response = app.oneshot(models_request).await.unwrap();
+        assert_eq!(models_response.status(), StatusCode::OK);
+
+        let models_json = integration_helpers::parse_response_json(models_response).await;
+        assert_eq!(models_json["object"], "list");
+        
+        let models = models_json["data"].as_array().unwrap();
+        assert!(models.len() >= 6, "Should have models from all providers");
+
+        // Verify model structure
+        for model in models {
+            assert!(model["id"].is_string(), "Model should have id");
+            assert!(model["object"].is_string(), "Model should have object type");
+            assert!(model["created"].is_number(), "Model should have created timestamp");
+            assert!(model["owned_by"].is_string(), "Model should have owned_by");
+        }
+
+        // Verify specific models are present
+        let model_ids: Vec<&str> = models.iter()
+            .map(|m| m["id"].as_str().unwrap())
+            .collect();
+
+        // OpenAI models
+        assert!(model_ids.contains(&"gpt-4"));
+        assert!(model_ids.contains(&"gpt-3.5-turbo"));
+
+        // Anthropic models
+        assert!(model_ids.contains(&"claude-3-sonnet"));
+        assert!(model_ids.contains(&"claude-3-haiku"));
+
+        // Gemini models
+        assert!(model_ids.contains(&"gemini-pro"));
+        assert!(model_ids.contains(&"gemini-pro-vision"));
+    }
+}
+/// Test that token usage accumulates per API key across requests and is exposed on /v1/usage
+#[tokio::test]
+async fn test_usage_endpoint_accumulates_across_requests_integration() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_usage",
+            "type": "message",
+            "role": "assistant",
+            "content": [{
+                "type": "text",
+                "text": "Hello"
+            }],
+            "model": "claude-3-sonnet",
+            "stop_reason": "end_turn",
+            "usage": {
+                "input_tokens": 10,
+                "output_tokens": 5
+            }
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let mut config = integration_helpers::create_test_config(mock_servers);
+    config.security.admin_api_key = Some("admin-key-1234567890".to_string());
+
+    let app_state = integration_helpers::create_test_app_state(config).await;
+    let app = create_app(app_state);
+
+    let request_body = json!({
+        "model": "claude-3-sonnet",
+        "messages": [{"role": "user", "content": "Hello"}],
+        "max_tokens": 100
+    });
+
+    for _ in 0..2 {
+        let request = Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json")
+            .header("x-api-key", "sk-test-usage-key-1234")
+            .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    // Usage is billing data: only the admin key may read it
+    let request = Request::builder().uri("/v1/usage").body(Body::empty()).unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let request = Request::builder()
+        .uri("/v1/usage")
+        .header("x-api-key", "sk-test-usage-key-1234")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app.oneshot(integration_helpers::usage_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response_json = integration_helpers::parse_response_json(response).await;
+    let data = response_json["data"].as_array().unwrap();
+    assert_eq!(data.len(), 1);
+    assert_eq!(data[0]["api_key_id"], "sk-t...1234");
+    assert_eq!(data[0]["provider"], "anthropic");
+    assert_eq!(data[0]["model"], "claude-3-sonnet");
+    assert_eq!(data[0]["requests"], 2);
+    assert_eq!(data[0]["input_tokens"], 20);
+    assert_eq!(data[0]["output_tokens"], 10);
+    assert_eq!(data[0]["total_tokens"], 30);
+}
+
+#[tokio::test]
+async fn test_response_proxy_metadata_block_integration() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({
+                    "id": "msg_meta",
+                    "type": "message",
+                    "role": "assistant",
+                    "content": [{"type": "text", "text": "Hello"}],
+                    "model": "claude-3-sonnet-20240229",
+                    "stop_reason": "end_turn",
+                    "usage": {"input_tokens": 1000, "output_tokens": 500}
+                }))
+                .set_delay(Duration::from_millis(50)),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let mut config = integration_helpers::create_test_config(mock_servers);
+    config.costs.insert(
+        "claude-3-sonnet".to_string(),
+        ai_proxy::config::ModelCost {
+            input_per_1k: 0.003,
+            output_per_1k: 0.015,
+        },
+    );
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
+
+    let send = |model: &str| {
+        Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({"model": model, "messages": [{"role": "user", "content": "Hello"}]}).to_string(),
+            ))
+            .unwrap()
+    };
+
+    let response = app.clone().oneshot(send("claude-3-sonnet")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response_json = integration_helpers::parse_response_json(response).await;
+    let metadata = &response_json["x_ai_proxy"];
+    assert_eq!(metadata["provider"], "anthropic");
+    assert_eq!(metadata["resolved_model"], "claude-3-sonnet-20240229");
+    assert!(metadata["latency_ms"].as_u64().unwrap() >= 50, "{}", metadata);
+    assert!((metadata["cost_usd"].as_f64().unwrap() - 0.0105).abs() < 1e-9);
+    // Native fields are untouched
+    assert_eq!(response_json["model"], "claude-3-sonnet-20240229");
+    assert_eq!(response_json["usage"]["input_tokens"], 1000);
+
+    // Unpriced models still get the block, with a null cost
+    let response = app.oneshot(send("claude-3-haiku")).await.unwrap();
+    let response_json = integration_helpers::parse_response_json(response).await;
+    assert_eq!(response_json["x_ai_proxy"]["provider"], "anthropic");
+    assert!(response_json["x_ai_proxy"]["cost_usd"].is_null());
+}
+
+#[tokio::test]
+async fn test_seed_surfaces_system_fingerprint_integration() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-seed",
+            "object": "chat.completion",
+            "created": 1714560000,
+            "model": "gpt-4",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hello"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 5, "completion_tokens": 1, "total_tokens": 6},
+            "system_fingerprint": "fp_44709d6fcb"
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("openai".to_string(), mock_server.uri());
+    let config = integration_helpers::create_test_config(mock_servers);
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
+
+    let send = |body: Value| {
+        Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(send(json!({
             "model": "gpt-4",
+            "messages": [{"role": "user", "content": "Hello"}],
+            "seed": 1234
+        })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response_json = integration_helpers::parse_response_json(response).await;
+    assert_eq!(response_json["x_ai_proxy"]["system_fingerprint"], "fp_44709d6fcb");
+
+    // Without a seed the fingerprint is left out
+    let response = app
+        .oneshot(send(json!({"model": "gpt-4", "messages": [{"role": "user", "content": "Hello"}]})))
+        .await
+        .unwrap();
+    let response_json = integration_helpers::parse_response_json(response).await;
+    assert!(response_json["x_ai_proxy"].get("system_fingerprint").is_none());
+
+    let requests = mock_server.received_requests().await.unwrap();
+    let seeded: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(seeded["seed"], 1234);
+    let unseeded: Value = serde_json::from_slice(&requests[1].body).unwrap();
+    assert!(unseeded.get("seed").is_none());
+}
+
+#[tokio::test]
+async fn test_usage_endpoint_reports_configured_costs_integration() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_cost",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Hello"}],
+            "model": "claude-3-sonnet",
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 1000, "output_tokens": 500}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let mut config = integration_helpers::create_test_config(mock_servers);
+    config.costs.insert(
+        "claude-3-sonnet".to_string(),
+        ai_proxy::config::ModelCost {
+            input_per_1k: 0.003,
+            output_per_1k: 0.015,
+        },
+    );
+    config.security.admin_api_key = Some("admin-key-1234567890".to_string());
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
+
+    let mut cost_headers = Vec::new();
+    for model in ["claude-3-sonnet", "claude-3-haiku"] {
+        let request = Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({"model": model, "messages": [{"role": "user", "content": "Hello"}]}).to_string(),
+            ))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        cost_headers.push(
+            response
+                .headers()
+                .get("x-ai-proxy-cost-usd")
+                .map(|value| value.to_str().unwrap().to_string()),
+        );
+    }
+    // 1000 * 0.003 / 1000 + 500 * 0.015 / 1000
+    assert_eq!(cost_headers, vec![Some("0.010500".to_string()), None]);
+
+    let response_json =
+        integration_helpers::parse_response_json(app.oneshot(integration_helpers::usage_request()).await.unwrap()).await;
+    let data = response_json["data"].as_array().unwrap();
+    assert_eq!(data.len(), 2);
+    assert_eq!(data[0]["model"], "claude-3-haiku");
+    assert!(data[0]["cost_usd"].is_null());
+    assert_eq!(data[1]["model"], "claude-3-sonnet");
+    assert!((data[1]["cost_usd"].as_f64().unwrap() - 0.0105).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn test_auto_detect_format_on_messages_route_integration() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_detect",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Hello from Claude"}],
+            "model": "claude-3-sonnet",
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 8, "output_tokens": 4}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let mut config = integration_helpers::create_test_config(mock_servers);
+    config.server.auto_detect_format = true;
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
+
+    let send = |body: Value| {
+        Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    // Anthropic-shaped body: answered in Anthropic's format
+    let response = app
+        .clone()
+        .oneshot(send(json!({
+            "model": "claude-3-sonnet",
+            "system": "Be brief",
+            "messages": [{"role": "user", "content": "Hello"}],
+            "max_tokens": 100
+        })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response_json = integration_helpers::parse_response_json(response).await;
+    assert_eq!(response_json["id"], "msg_detect");
+    assert_eq!(response_json["content"][0]["text"], "Hello from Claude");
+
+    // OpenAI-shaped body on the same route: converted and answered in OpenAI's format
+    let response = app
+        .clone()
+        .oneshot(send(json!({
+            "model": "claude-3-sonnet",
             "messages": [
-                {"role": "user", "content": "Stream test"}
+                {"role": "system", "content": "Be brief"},
+                {"role": "user", "content": "Hello"}
             ],
-            "max_tokens": 100,
-            "stream": true
-        });
+            "max_tokens": 100
+        })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response_json = integration_helpers::parse_response_json(response).await;
+    assert_eq!(response_json["object"], "chat.completion");
+    assert_eq!(response_json["choices"][0]["message"]["content"], "Hello from Claude");
+
+    // Both reached the provider with the system prompt lifted out of the messages
+    let requests = mock_server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 2);
+    for received in &requests {
+        let body: Value = serde_json::from_slice(&received.body).unwrap();
+        assert_eq!(body["system"], "Be brief");
+        assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+    }
+
+    // Markers of both formats: rejected instead of guessed
+    let response = app
+        .oneshot(send(json!({
+            "model": "claude-3-sonnet",
+            "system": "Be brief",
+            "messages": [{"role": "user", "content": "Hello"}],
+            "frequency_penalty": 0.5
+        })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response_json = integration_helpers::parse_response_json(response).await;
+    let message = response_json["error"]["message"].as_str().unwrap();
+    assert!(message.contains("Ambiguous request format"), "{}", message);
+    assert!(message.contains("system") && message.contains("frequency_penalty"), "{}", message);
+    assert_eq!(mock_server.received_requests().await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_unsupported_params_stripped_per_model_integration() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_strip",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Hello"}],
+            "model": "claude-3-sonnet",
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 10, "output_tokens": 5}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let mut config = integration_helpers::create_test_config(mock_servers);
+    config
+        .unsupported_params
+        .insert("claude-3-sonnet".to_string(), vec!["temperature".to_string()]);
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
+
+    let send = |model: &str| {
+        Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({
+                    "model": model,
+                    "messages": [{"role": "user", "content": "Hello"}],
+                    "temperature": 0.5,
+                    "top_p": 0.9
+                })
+                .to_string(),
+            ))
+            .unwrap()
+    };
+
+    // Configured model: temperature is dropped instead of failing the request
+    let response = app.clone().oneshot(send("claude-3-sonnet")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Other models keep it
+    let response = app.oneshot(send("claude-3-haiku")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let requests = mock_server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 2);
+    let stripped: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert!(stripped.get("temperature").is_none(), "{}", stripped);
+    assert_eq!(stripped["top_p"], json!(0.9));
+    let preserved: Value = serde_json::from_slice(&requests[1].body).unwrap();
+    assert_eq!(preserved["temperature"], json!(0.5));
+}
+
+#[tokio::test]
+async fn test_max_input_tokens_rejects_long_requests_integration() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_limit",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Hello"}],
+            "model": "claude-3-sonnet",
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 10, "output_tokens": 5}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let mut config = integration_helpers::create_test_config(mock_servers);
+    config.max_input_tokens.insert("claude-3-sonnet".to_string(), 50);
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
+
+    let send = |content: String| {
+        Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({"model": "claude-3-sonnet", "messages": [{"role": "user", "content": content}]}).to_string(),
+            ))
+            .unwrap()
+    };
+
+    // Under the limit
+    let response = app.clone().oneshot(send("Hello".to_string())).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // ~100 estimated tokens against a limit of 50
+    let response = app.oneshot(send("word ".repeat(80))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response_json = integration_helpers::parse_response_json(response).await;
+    let message = response_json["error"]["message"].as_str().unwrap();
+    assert!(message.contains("limit of 50"), "{}", message);
+    assert!(message.contains("(101)"), "{}", message);
+
+    // The rejected request never reached the provider
+    assert_eq!(mock_server.received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_retry_deadline_cuts_off_further_attempts_integration() {
+    let mock_server = MockServer::start().await;
+
+    // Every attempt is slow and fails with a retryable upstream error
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(503)
+                .set_body_string("overloaded")
+                .set_delay(Duration::from_millis(600)),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let mut config = integration_helpers::create_test_config(mock_servers);
+    config.server.request_timeout_seconds = 1;
+    config.providers.get_mut("anthropic").unwrap().max_retries = 10;
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
+
+    let started = std::time::Instant::now();
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/messages")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({"model": "claude-3-sonnet", "messages": [{"role": "user", "content": "Hello"}]}).to_string(),
+        ))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+    // The one-second budget bounds all attempts, not each attempt
+    assert!(started.elapsed() < Duration::from_millis(1500), "took {:?}", started.elapsed());
+    let attempts = mock_server.received_requests().await.unwrap().len();
+    assert_eq!(attempts, 2, "max_retries allowed 11 attempts but the deadline should stop after 2");
+}
+
+/// Mount a slow Anthropic messages mock and return a live proxy address plus its shutdown trigger
+async fn spawn_proxy_with_slow_upstream(
+    mock_server: &MockServer,
+    upstream_delay: Duration,
+    drain_timeout: Duration,
+) -> (
+    std::net::SocketAddr,
+    tokio::sync::oneshot::Sender<()>,
+    tokio::task::JoinHandle<ai_proxy::AppResult<()>>,
+) {
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200)
+            .set_delay(upstream_delay)
+            .set_body_json(json!({
+                "id": "msg_drain",
+                "type": "message",
+                "role": "assistant",
+                "content": [{"type": "text", "text": "Finished"}],
+                "model": "claude-3-sonnet",
+                "stop_reason": "end_turn",
+                "usage": {"input_tokens": 5, "output_tokens": 1}
+            })))
+        .mount(mock_server)
+        .await;
+
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let config = integration_helpers::create_test_config(mock_servers);
+    let app_state = integration_helpers::create_test_app_state(config).await;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+    let server = tokio::spawn(serve_with_graceful_shutdown(
+        listener,
+        app_state,
+        async move {
+            let _ = shutdown_rx.await;
+        },
+        drain_timeout,
+    ));
+
+    (addr, shutdown_tx, server)
+}
+
+/// Test that the WebSocket bridge forwards stream events in order and then closes
+#[tokio::test]
+async fn test_websocket_stream_bridge_integration() {
+    use futures::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+    let mock_server = MockServer::start().await;
+    let events = [
+        json!({"type": "message_start", "message": {"id": "msg_ws", "type": "message", "role": "assistant", "content": [], "model": "claude-3-sonnet", "usage": {"input_tokens": 5, "output_tokens": 0}}}),
+        json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+        json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hello"}}),
+        json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": " there"}}),
+        json!({"type": "content_block_stop", "index": 0}),
+        json!({"type": "message_delta", "delta": {"stop_reason": "end_turn"}, "usage": {"output_tokens": 2}}),
+        json!({"type": "message_stop"}),
+    ];
+    let body: String = events
+        .iter()
+        .map(|event| format!("event: {}\ndata: {}\n\n", event["type"].as_str().unwrap(), event))
+        .collect();
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200)
+            .set_body_string(body)
+            .insert_header("content-type", "text/event-stream"))
+        .mount(&mock_server)
+        .await;
+
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let config = integration_helpers::create_test_config(mock_servers);
+    let app_state = integration_helpers::create_test_app_state(config).await;
+    let metrics = app_state.metrics.clone();
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, create_app(app_state)).await.unwrap();
+    });
+
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/v1/messages/ws", addr))
+        .await
+        .unwrap();
+    let request = json!({
+        "model": "claude-3-sonnet",
+        "messages": [{"role": "user", "content": "Hello"}],
+        "max_tokens": 100
+    });
+    socket.send(WsMessage::Text(request.to_string().into())).await.unwrap();
+
+    let mut received = Vec::new();
+    let mut closed = false;
+    while let Some(message) = tokio::time::timeout(Duration::from_secs(5), socket.next()).await.unwrap() {
+        match message.unwrap() {
+            WsMessage::Text(text) => received.push(serde_json::from_str::<Value>(&text).unwrap()),
+            WsMessage::Close(_) => {
+                closed = true;
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    let types: Vec<&str> = received.iter().map(|e| e["type"].as_str().unwrap()).collect();
+    assert_eq!(
+        types,
+        vec![
+            "message_start",
+            "content_block_start",
+            "content_block_delta",
+            "content_block_delta",
+            "content_block_stop",
+            "message_delta",
+            "message_stop",
+        ]
+    );
+    assert_eq!(received[2]["delta"]["text"], "Hello");
+    assert_eq!(received[3]["delta"]["text"], " there");
+    assert!(closed);
+
+    // WebSocket traffic is accounted like /v1/messages streams
+    let mut usage = metrics.get_usage_totals();
+    for _ in 0..50 {
+        if !usage.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        usage = metrics.get_usage_totals();
+    }
+    assert_eq!(usage.len(), 1);
+    assert_eq!(usage[0].model, "claude-3-sonnet");
+    assert_eq!(usage[0].input_tokens, 5);
+    assert_eq!(usage[0].output_tokens, 2);
+    let summary = metrics.get_metrics_summary().await;
+    assert_eq!(summary.first_token_latency.len(), 1);
+    assert_eq!(summary.first_token_latency[0].count, 1);
+}
+
+/// Test that the WebSocket bridge honours the same request headers as /v1/messages
+#[tokio::test]
+async fn test_websocket_rejects_invalid_timeout_header_integration() {
+    use futures::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::{Message as WsMessage, client::IntoClientRequest};
+
+    // The request is rejected before reaching the provider
+    let mock_server = MockServer::start().await;
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let config = integration_helpers::create_test_config(mock_servers);
+    let app_state = integration_helpers::create_test_app_state(config).await;
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, create_app(app_state)).await.unwrap();
+    });
+
+    let mut upgrade = format!("ws://{}/v1/messages/ws", addr).into_client_request().unwrap();
+    upgrade.headers_mut().insert("x-ai-proxy-timeout", "soon".parse().unwrap());
+    let (mut socket, _) = tokio_tungstenite::connect_async(upgrade).await.unwrap();
+    let request = json!({
+        "model": "claude-3-sonnet",
+        "messages": [{"role": "user", "content": "Hello"}],
+        "max_tokens": 100
+    });
+    socket.send(WsMessage::Text(request.to_string().into())).await.unwrap();
+
+    let message = tokio::time::timeout(Duration::from_secs(5), socket.next()).await.unwrap().unwrap().unwrap();
+    let WsMessage::Text(text) = message else {
+        panic!("expected an error event, got {:?}", message);
+    };
+    let event: Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(event["type"], "error");
+    assert!(event["error"]["message"].as_str().unwrap().contains("x-ai-proxy-timeout"));
+}
+
+fn send_chat(addr: std::net::SocketAddr) -> tokio::task::JoinHandle<reqwest::Result<reqwest::Response>> {
+    tokio::spawn(async move {
+        Client::new()
+            .post(format!("http://{}/v1/messages", addr))
+            .json(&json!({
+                "model": "claude-3-sonnet",
+                "messages": [{"role": "user", "content": "Hello"}],
+                "max_tokens": 100
+            }))
+            .send()
+            .await
+    })
+}
+
+/// Test that a request started just before shutdown still completes during the drain window
+#[tokio::test]
+async fn test_graceful_shutdown_drains_in_flight_request() {
+    let mock_server = MockServer::start().await;
+    let (addr, shutdown_tx, server) =
+        spawn_proxy_with_slow_upstream(&mock_server, Duration::from_millis(800), Duration::from_secs(10)).await;
+
+    let in_flight = send_chat(addr);
+
+    // Signal shutdown while the upstream call is still pending
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    shutdown_tx.send(()).unwrap();
+
+    let response = in_flight.await.unwrap().expect("in-flight request should complete");
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["content"][0]["text"], "Finished");
+
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .expect("server should stop after draining")
+        .unwrap()
+        .unwrap();
+}
+
+/// Test that the drain timeout bounds how long shutdown waits for slow requests
+#[tokio::test]
+async fn test_graceful_shutdown_drain_timeout_elapses() {
+    let mock_server = MockServer::start().await;
+    let (addr, shutdown_tx, server) =
+        spawn_proxy_with_slow_upstream(&mock_server, Duration::from_secs(10), Duration::from_millis(300)).await;
+
+    let _in_flight = send_chat(addr);
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let shutdown_started = std::time::Instant::now();
+    shutdown_tx.send(()).unwrap();
+
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .expect("server should stop once the drain timeout elapses")
+        .unwrap()
+        .unwrap();
+    assert!(shutdown_started.elapsed() < Duration::from_secs(5));
+}
+
+/// Test that rapid /health/providers polls are served from the TTL cache
+#[tokio::test]
+async fn test_provider_health_checks_are_cached_integration() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": [{"id": "claude-3-sonnet", "type": "model"}]
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let mut config = integration_helpers::create_test_config(mock_servers);
+    config.performance.health_check_ttl_seconds = 60;
+
+    let app_state = integration_helpers::create_test_app_state(config).await;
+    let app = create_app(app_state);
 
+    for _ in 0..2 {
         let request = Request::builder()
-            .method("POST")
-            .uri("/v1/messages")
-            .header("content-type", "application/json")
-            .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+            .method("GET")
+            .uri("/health/providers")
+            .body(Body::empty())
             .unwrap();
-
-        // Send request and verify streaming response
-        let response = app.oneshot(request).await.unwrap();
-        
+        let response = app.clone().oneshot(request).await.unwrap();
         assert_eq!(response.status(), StatusCode::OK);
-        assert_eq!(response.headers().get("content-type").unwrap(), "text/event-stream");
-        assert_eq!(response.headers().get("cache-control").unwrap(), "no-cache");
 
-        // Parse streaming response
-        let response_body = integration_helpers::parse_response_string(response).await;
+        let response_json = integration_helpers::parse_response_json(response).await;
+        assert_eq!(response_json["providers"]["anthropic"]["status"], "healthy");
+        assert_eq!(response_json["providers"]["anthropic"]["age_seconds"], 0);
+    }
 
-        // Verify SSE format
-        assert!(response_body.contains("data: "));
-        assert!(response_body.contains("message_start"));
-        // Note: The streaming response may be truncated in tests, so we check for basic structure
-        assert!(response_body.contains("content_block_start"));
-        // assert!(response_body.contains("content_block_delta")); // May be truncated in test environment
-        // assert!(response_body.contains("message_stop")); // May be truncated in test environment
-        
-        // Verify content is streamed (basic content check)
-        // Note: The exact content may vary due to streaming conversion
-        assert!(response_body.len() > 100, "Response should have substantial content");
-        
-        // Verify proper SSE formatting
-        let lines: Vec<&str> = response_body.lines().collect();
-        let data_lines: Vec<&str> = lines.iter().filter(|line| line.starts_with("data: ")).cloned().collect();
-        assert!(data_lines.len() > 5); // Should have multiple streaming events
+    // Only the first poll reached the upstream
+    mock_server.verify().await;
+}
+
+/// Test that /health/providers reports failure history after an unhealthy check
+#[tokio::test]
+async fn test_provider_health_failure_history_integration() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .respond_with(ResponseTemplate::new(503).set_body_string("upstream down"))
+        .mount(&mock_server)
+        .await;
+
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let mut config = integration_helpers::create_test_config(mock_servers);
+    config.performance.health_check_ttl_seconds = 0;
+
+    let app_state = integration_helpers::create_test_app_state(config).await;
+    let app = create_app(app_state);
+
+    for expected_failures in 1..=2 {
+        let request = Request::builder()
+            .method("GET")
+            .uri("/health/providers")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response_json = integration_helpers::parse_response_json(response).await;
+        let entry = &response_json["providers"]["anthropic"];
+        assert_eq!(response_json["status"], "degraded");
+        assert_eq!(entry["status"], "unhealthy");
+        assert_eq!(entry["provider"], "anthropic");
+        assert!(entry["latency_ms"].is_u64());
+        assert!(entry["last_checked"].is_string());
+        assert_eq!(entry["consecutive_failures"], expected_failures);
+        assert!(entry["last_error"].as_str().unwrap().contains("Anthropic server error"));
     }
+}
 
-    /// Test complete Anthropic streaming flow
-    #[tokio::test]
-    async fn test_anthropic_streaming_end_to_end() {
-        let mock_config = integration_helpers::MockServerConfig::new()
-            .await
-            .with_anthropic()
-            .await;
-        
-        let anthropic_server = mock_config.anthropic.as_ref().unwrap();
-        integration_helpers::setup_anthropic_mocks(anthropic_server).await;
+/// Test that security.strict_role_alternation controls whether consecutive user messages are accepted
+#[tokio::test]
+async fn test_strict_role_alternation_config_integration() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_relaxed",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Done"}],
+            "model": "claude-3-sonnet",
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 6, "output_tokens": 1}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let request_body = json!({
+        "model": "claude-3-sonnet",
+        "messages": [
+            {"role": "user", "content": "First"},
+            {"role": "user", "content": "Second"}
+        ],
+        "max_tokens": 100
+    });
+
+    for (strict, expected_status) in [(true, StatusCode::BAD_REQUEST), (false, StatusCode::OK)] {
+        let mut mock_servers = HashMap::new();
+        mock_servers.insert("anthropic".to_string(), mock_server.uri());
+        let mut config = integration_helpers::create_test_config(mock_servers);
+        config.security.strict_role_alternation = strict;
 
-        let config = integration_helpers::create_test_config(mock_config.get_urls());
         let app_state = integration_helpers::create_test_app_state(config).await;
         let app = create_app(app_state);
 
-        let request_body = json!({
-            "model": "claude-3-sonnet",
-            "messages": [
-                {"role": "user", "content": "Stream test"}
-            ],
-            "max_tokens": 100,
-            "stream": true
-        });
-
         let request = Request::builder()
             .method("POST")
             .uri("/v1/messages")
             .header("content-type", "application/json")
             .body(Body::from(serde_json::to_string(&request_body).unwrap()))
             .unwrap();
-
         let response = app.oneshot(request).await.unwrap();
-        
-        assert_eq!(response.status(), StatusCode::OK);
-        assert_eq!(response.headers().get("content-type").unwrap(), "text/event-stream");
+        assert_eq!(response.status(), expected_status, "strict_role_alternation = {}", strict);
+    }
+}
 
-        let response_body = integration_helpers::parse_response_string(response).await;
+#[tokio::test]
+async fn test_embeddings_unsupported_provider_integration() {
+    let mock_server = MockServer::start().await;
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let config = integration_helpers::create_test_config(mock_servers);
 
-        // Verify Anthropic streaming events
-        assert!(response_body.contains("message_start"));
-        assert!(response_body.contains("content_block_start"));
-        // Note: The streaming response may be truncated in tests
-        // assert!(response_body.contains("content_block_delta"));
-        // assert!(response_body.contains("content_block_stop"));
-        // assert!(response_body.contains("message_delta"));
-        // assert!(response_body.contains("message_stop"));
-        
-        // Verify content (basic content check)
-        // Note: The exact content may vary due to streaming conversion
-        assert!(response_body.len() > 100, "Response should have substantial content");
-    }
+    let app_state = integration_helpers::create_test_app_state(config).await;
+    let app = create_app(app_state);
 
-    /// Test complete Gemini streaming flow
-    #[tokio::test]
-    async fn test_gemini_streaming_end_to_end() {
-        let mock_config = integration_helpers::MockServerConfig::new()
-            .await
-            .with_gemini()
-            .await;
-        
-        let gemini_server = mock_config.gemini.as_ref().unwrap();
-        integration_helpers::setup_gemini_mocks(gemini_server).await;
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/embeddings")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({"model": "claude-3-sonnet", "input": "Embed me"}).to_string(),
+        ))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_IMPLEMENTED);
 
-        let config = integration_helpers::create_test_config(mock_config.get_urls());
-        let app_state = integration_helpers::create_test_app_state(config).await;
-        let app = create_app(app_state);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["error"]["type"], "not_supported_error");
+}
 
-        let request_body = json!({
-            "model": "gemini-pro",
-            "messages": [
-                {"role": "user", "content": "Stream test"}
-            ],
-            "max_tokens": 100,
-            "stream": true
-        });
+#[tokio::test]
+async fn test_default_max_tokens_applied_integration() {
+    let mock_server = MockServer::start().await;
+
+    // Only a request carrying the configured default reaches the upstream
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(body_partial_json(json!({"max_tokens": 321})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_default_tokens",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Hi"}],
+            "model": "claude-3-sonnet",
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 3, "output_tokens": 1}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let mut config = integration_helpers::create_test_config(mock_servers);
+    config.server.default_max_tokens = 321;
+
+    let app_state = integration_helpers::create_test_app_state(config).await;
+    let app = create_app(app_state);
 
+    let cases = [
+        (json!({"model": "claude-3-sonnet", "messages": [{"role": "user", "content": "Hi"}]}), StatusCode::OK),
+        (json!({"model": "claude-3-sonnet", "messages": [{"role": "user", "content": "Hi"}], "max_tokens": 0}), StatusCode::BAD_REQUEST),
+        (json!({"model": "claude-3-sonnet", "messages": [{"role": "user", "content": "Hi"}], "max_tokens": 9000}), StatusCode::BAD_REQUEST),
+    ];
+
+    for (body, expected_status) in cases {
         let request = Request::builder()
             .method("POST")
             .uri("/v1/messages")
             .header("content-type", "application/json")
-            .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+            .body(Body::from(body.to_string()))
             .unwrap();
-
-        let response = app.oneshot(request).await.unwrap();
-        
-        assert_eq!(response.status(), StatusCode::OK);
-        assert_eq!(response.headers().get("content-type").unwrap(), "text/event-stream");
-
-        let response_body = integration_helpers::parse_response_string(response).await;
-        
-        // Verify converted Anthropic streaming format
-        assert!(response_body.contains("message_start"));
-        assert!(response_body.contains("content_block_delta"));
-        assert!(response_body.contains("message_stop"));
-        
-        // Verify content (check for the actual mock content)
-        assert!(response_body.contains("Hello there! How can I help?") || response_body.contains("Hello"));
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), expected_status, "body: {}", body);
     }
+}
 
-    /// Test streaming error handling
-    #[tokio::test]
-    async fn test_streaming_error_handling() {
-        let mock_server = MockServer::start().await;
-        
-        // Mock server returns streaming error
-        Mock::given(method("POST"))
-            .and(path("/v1/chat/completions"))
-            .respond_with(ResponseTemplate::new(200)
-                .set_body_string("data: {\"error\": {\"message\": \"Rate limit exceeded\", \"type\": \"rate_limit_error\"}}\n\n")
-                .insert_header("content-type", "text/event-stream"))
-            .mount(&mock_server)
-            .await;
+#[tokio::test]
+async fn test_model_access_per_api_key_integration() {
+    let mock_server = MockServer::start().await;
+    integration_helpers::setup_openai_mocks(&mock_server).await;
 
-        let mut mock_servers = HashMap::new();
-        mock_servers.insert("openai".to_string(), mock_server.uri());
-        let config = integration_helpers::create_test_config(mock_servers);
-        let app_state = integration_helpers::create_test_app_state(config).await;
-        let app = create_app(app_state);
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("openai".to_string(), mock_server.uri());
+    let mut config = integration_helpers::create_test_config(mock_servers);
+    config
+        .security
+        .model_access
+        .insert("tenant-key-1234567890".to_string(), vec!["gpt-4*".to_string()]);
+    config
+        .security
+        .model_access
+        .insert("exact-key-1234567890".to_string(), vec!["gpt-3.5-turbo".to_string()]);
 
-        let request_body = json!({
-            "model": "gpt-4",
-            "messages": [{"role": "user", "content": "Test"}],
-            "max_tokens": 100,
-            "stream": true
-        });
+    let app_state = integration_helpers::create_test_app_state(config).await;
+    let app = create_app(app_state);
 
+    let cases = [
+        // Exact allow-list entry
+        ("exact-key-1234567890", "gpt-3.5-turbo", StatusCode::OK),
+        // Wildcard allow-list entry
+        ("tenant-key-1234567890", "gpt-4", StatusCode::OK),
+        // Model outside the key's allow-list
+        ("tenant-key-1234567890", "gpt-3.5-turbo", StatusCode::FORBIDDEN),
+        // Keys that are not listed get no models
+        ("unlisted-key-1234567890", "gpt-4", StatusCode::FORBIDDEN),
+        // A different key with the same masked ID (tena...7890) is not the listed key
+        ("tena-forged-7890", "gpt-4", StatusCode::FORBIDDEN),
+    ];
+
+    for (api_key, model, expected_status) in cases {
         let request = Request::builder()
             .method("POST")
             .uri("/v1/messages")
             .header("content-type", "application/json")
-            .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+            .header("x-api-key", api_key)
+            .body(Body::from(
+                json!({
+                    "model": model,
+                    "messages": [{"role": "user", "content": "Hello"}],
+                    "max_tokens": 50
+                })
+                .to_string(),
+            ))
             .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        assert_eq!(status, expected_status, "key {} model {}", api_key, model);
 
-        let response = app.oneshot(request).await.unwrap();
-        let response_body = integration_helpers::parse_response_string(response).await;
-        
-        // Should contain error information or be a valid streaming response
-        // The mock server returns a streaming error, but it might be processed differently
-        assert!(response_body.contains("error") || response_body.contains("Rate limit") || response_body.contains("data:"));
+        if status == StatusCode::FORBIDDEN {
+            let body = integration_helpers::parse_response_json(response).await;
+            assert!(body["error"]["message"].as_str().unwrap().contains(model));
+        }
     }
+}
 
-    /// Test streaming with connection interruption
-    #[tokio::test]
-    async fn test_streaming_connection_interruption() {
-        let mock_server = MockServer::start().await;
-        
-        // Mock incomplete streaming response
-        Mock::given(method("POST"))
-            .and(path("/v1/chat/completions"))
-            .respond_with(ResponseTemplate::new(200)
-                .set_body_string("data: {\"id\":\"test\",\"choices\":[{\"delta\":{\"content\":\"Hello\"}}]}\n\n")
-                .insert_header("content-type", "text/event-stream"))
-            .mount(&mock_server)
-            .await;
+#[tokio::test]
+async fn test_startup_fail_fast_when_all_providers_unhealthy() {
+    // Every upstream call fails, so every health check fails
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(500).set_body_string("upstream down"))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(500).set_body_string("upstream down"))
+        .mount(&mock_server)
+        .await;
+
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("openai".to_string(), mock_server.uri());
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let mut config = integration_helpers::create_test_config(mock_servers);
+    config.server.port = 0;
 
-        let mut mock_servers = HashMap::new();
-        mock_servers.insert("openai".to_string(), mock_server.uri());
-        let config = integration_helpers::create_test_config(mock_servers);
-        let app_state = integration_helpers::create_test_app_state(config).await;
-        let app = create_app(app_state);
+    // Non-fatal by default: the sweep only logs
+    let app_state = integration_helpers::create_test_app_state(config.clone()).await;
+    assert!(preflight_health_check(&app_state).await.is_ok());
 
-        let request_body = json!({
-            "model": "gpt-4",
-            "messages": [{"role": "user", "content": "Test"}],
-            "max_tokens": 100,
-            "stream": true
-        });
+    config.server.fail_fast_on_unhealthy = true;
+    let result = tokio::time::timeout(Duration::from_secs(10), start_server(config))
+        .await
+        .expect("start_server should refuse to start instead of serving");
 
-        let request = Request::builder()
-            .method("POST")
-            .uri("/v1/messages")
-            .header("content-type", "application/json")
-            .body(Body::from(serde_json::to_string(&request_body).unwrap()))
-            .unwrap();
+    let error = result.unwrap_err().to_string();
+    assert!(error.contains("All enabled providers failed the startup health check"), "{}", error);
+    assert!(error.contains("openai") && error.contains("anthropic"), "{}", error);
+}
 
-        let response = app.oneshot(request).await.unwrap();
-        
-        // Should handle incomplete streams gracefully
-        assert_eq!(response.status(), StatusCode::OK);
-        assert_eq!(response.headers().get("content-type").unwrap(), "text/event-stream");
-    }
+#[tokio::test]
+async fn test_identical_concurrent_requests_are_coalesced() {
+    let mock_server = MockServer::start().await;
 
-    /// Test concurrent streaming requests
-    #[tokio::test]
-    async fn test_concurrent_streaming_requests() {
-        let mock_config = integration_helpers::MockServerConfig::new()
-            .await
-            .with_openai()
-            .await;
-        
-        let openai_server = mock_config.openai.as_ref().unwrap();
-        integration_helpers::setup_openai_mocks(openai_server).await;
+    // Slow upstream so every request arrives while the first is still in flight
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({
+                    "id": "msg_shared",
+                    "type": "message",
+                    "role": "assistant",
+                    "content": [{"type": "text", "text": "Shared answer"}],
+                    "model": "claude-3-sonnet",
+                    "stop_reason": "end_turn",
+                    "usage": {"input_tokens": 5, "output_tokens": 2}
+                }))
+                .set_delay(Duration::from_millis(300)),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
 
-        let config = integration_helpers::create_test_config(mock_config.get_urls());
-        let app_state = integration_helpers::create_test_app_state(config).await;
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let config = integration_helpers::create_test_config(mock_servers);
+    let app_state = integration_helpers::create_test_app_state(config).await;
+    let coalescer = app_state.coalescer.clone();
+    let app = create_app(app_state);
 
-        // Create multiple concurrent streaming requests
-        let mut handles = Vec::new();
-        
-        for i in 0..3 {
-            let app = create_app(app_state.clone());
-            let handle = tokio::spawn(async move {
-                let request_body = json!({
-                    "model": "gpt-4",
-                    "messages": [
-                        {"role": "user", "content": format!("Stream test {}", i)}
-                    ],
-                    "max_tokens": 50,
-                    "stream": true
-                });
+    let request_body = json!({
+        "model": "claude-3-sonnet",
+        "messages": [{"role": "user", "content": "What is the capital of France?"}],
+        "max_tokens": 50,
+        "temperature": 0.0
+    });
 
+    let handles: Vec<_> = (0..10)
+        .map(|_| {
+            let app = app.clone();
+            let body = request_body.to_string();
+            tokio::spawn(async move {
                 let request = Request::builder()
                     .method("POST")
                     .uri("/v1/messages")
                     .header("content-type", "application/json")
-                    .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+                    .body(Body::from(body))
                     .unwrap();
+                let response = app.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                integration_helpers::parse_response_json(response).await
+            })
+        })
+        .collect();
 
-                app.oneshot(request).await.unwrap()
-            });
-            
-            handles.push(handle);
+    for handle in handles {
+        let body = handle.await.unwrap();
+        assert_eq!(body["content"][0]["text"], "Shared answer");
+    }
+
+    // Nothing is left behind once the shared call has completed
+    assert_eq!(coalescer.in_flight_count(), 0);
+    mock_server.verify().await;
+}
+
+#[tokio::test]
+async fn test_liveness_and_readiness_probes() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(500).set_body_string("upstream down"))
+        .mount(&mock_server)
+        .await;
+
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("openai".to_string(), mock_server.uri());
+    let mut config = integration_helpers::create_test_config(mock_servers);
+    // Re-check providers on every probe
+    config.performance.health_check_ttl_seconds = 0;
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
+
+    let probe = |uri: &'static str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+    // Liveness never depends on upstream health
+    let response = app.clone().oneshot(probe("/health/live")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.clone().oneshot(probe("/health")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app.clone().oneshot(probe("/health/ready")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body = integration_helpers::parse_response_json(response).await;
+    assert_eq!(body["status"], "not_ready");
+
+    // Once a provider recovers the pod becomes ready
+    mock_server.reset().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"object": "list", "data": []})))
+        .mount(&mock_server)
+        .await;
+
+    let response = app.clone().oneshot(probe("/health/ready")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = integration_helpers::parse_response_json(response).await;
+    assert_eq!(body["status"], "ready");
+    assert_eq!(body["healthy_providers"], json!(["openai"]));
+}
+
+#[tokio::test]
+async fn test_transforms_mutate_request_and_response_integration() {
+    use ai_proxy::providers::anthropic::AnthropicResponse;
+    use ai_proxy::transforms::{PiiRedactionTransform, Transform};
+
+    /// Injects a system prompt and tags every response block
+    struct TagTransform;
+
+    #[async_trait::async_trait]
+    impl Transform for TagTransform {
+        async fn on_request(&self, req: &mut AnthropicRequest) {
+            req.system = Some("Be brief".to_string());
         }
 
-        // Wait for all streaming requests to complete
-        let responses = futures::future::join_all(handles).await;
-        
-        // Verify all streaming requests succeeded
-        for response in responses {
-            let response = response.unwrap();
-            assert_eq!(response.status(), StatusCode::OK);
-            assert_eq!(response.headers().get("content-type").unwrap(), "text/event-stream");
+        async fn on_response(&self, res: &mut AnthropicResponse) {
+            for block in &mut res.content {
+                block.text = format!("[tagged] {}", block.text);
+            }
         }
     }
 
-    /// Test streaming response parsing and validation
-    #[tokio::test]
-    async fn test_streaming_response_validation() {
-        let mock_config = integration_helpers::MockServerConfig::new()
-            .await
-            .with_openai()
-            .await;
-        
-        let openai_server = mock_config.openai.as_ref().unwrap();
-        integration_helpers::setup_openai_mocks(openai_server).await;
+    let mock_server = MockServer::start().await;
+    // Only the transformed request reaches the upstream
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(body_partial_json(json!({
+            "system": "Be brief",
+            "messages": [{"role": "user", "content": "Email [REDACTED_EMAIL] please"}]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_transformed",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Call 555-123-4567 today"}],
+            "model": "claude-3-sonnet",
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 5, "output_tokens": 4}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
 
-        let config = integration_helpers::create_test_config(mock_config.get_urls());
-        let app_state = integration_helpers::create_test_app_state(config).await;
-        let app = create_app(app_state);
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let config = integration_helpers::create_test_config(mock_servers);
+    let app_state = integration_helpers::create_test_app_state(config)
+        .await
+        .with_transform(Arc::new(TagTransform))
+        .with_transform(Arc::new(PiiRedactionTransform));
+    let app = create_app(app_state);
 
-        let request_body = json!({
-            "model": "gpt-4",
-            "messages": [{"role": "user", "content": "Stream test"}],
-            "max_tokens": 100,
-            "stream": true
-        });
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/messages")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({
+                "model": "claude-3-sonnet",
+                "messages": [{"role": "user", "content": "Email jane.doe@example.com please"}]
+            })
+            .to_string(),
+        ))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
 
-        let request = Request::builder()
-            .method("POST")
-            .uri("/v1/messages")
-            .header("content-type", "application/json")
-            .body(Body::from(serde_json::to_string(&request_body).unwrap()))
-            .unwrap();
+    // Transforms run in registration order
+    let body = integration_helpers::parse_response_json(response).await;
+    assert_eq!(body["content"][0]["text"], "[tagged] Call [REDACTED_NUMBER] today");
+    mock_server.verify().await;
+}
 
-        let response = app.oneshot(request).await.unwrap();
-        let response_body = integration_helpers::parse_response_string(response).await;
-        
-        // Parse and validate each SSE event
-        let lines: Vec<&str> = response_body.lines().collect();
-        let mut event_count = 0;
-        let mut has_message_start = false;
-        let mut _has_content_delta = false;
-        let mut _has_message_stop = false;
-        
-        for line in lines {
-            if line.starts_with("data: ") {
-                let data = &line[6..]; // Remove "data: " prefix
-                if !data.is_empty() && data != "[DONE]" {
-                    // Try to parse as JSON
-                    if let Ok(event_json) = serde_json::from_str::<Value>(data) {
-                        event_count += 1;
-                        
-                        if let Some(event_type) = event_json.get("type").and_then(|t| t.as_str()) {
-                            match event_type {
-                                "message_start" => has_message_start = true,
-                                "content_block_delta" => _has_content_delta = true,
-                                "message_stop" => _has_message_stop = true,
-                                _ => {}
-                            }
-                        }
-                    }
-                }
-            }
+/// Write a config file with the given provider tables and an admin key
+fn write_reload_config(path: &std::path::Path, providers: &str) {
+    write_reload_config_with_admin_key(path, "admin-key-1234567890", providers);
+}
+
+/// Write a config file with the given provider tables and the given admin key
+fn write_reload_config_with_admin_key(path: &std::path::Path, admin_key: &str, providers: &str) {
+    let contents = format!(
+        "[server]\nhost = \"127.0.0.1\"\nport = 8080\n\n[security]\nadmin_api_key = \"{}\"\n\n{}",
+        admin_key, providers
+    );
+    std::fs::write(path, contents).unwrap();
+}
+
+#[tokio::test]
+async fn test_admin_reload_swaps_registry_integration() {
+    let path = std::env::temp_dir().join(format!("ai-proxy-reload-{}.toml", uuid::Uuid::new_v4()));
+    let anthropic = "[providers.anthropic]\napi_key = \"test-anthropic-key-1234567890\"\napi_base = \"https://api.anthropic.com/v1/\"\nmodels = [\"claude-3-sonnet\"]\n\n";
+    write_reload_config(&path, anthropic);
+
+    let config = ai_proxy::config::load_config_from(&path).unwrap();
+    let app_state = AppState::new(config).unwrap();
+    let registry = app_state.provider_registry.clone();
+    let app = create_app(app_state);
+
+    let reload = |key: Option<&str>| {
+        let mut builder = Request::builder()
+            .method("POST")
+            .uri("/admin/reload")
+            .header("content-type", "application/json");
+        if let Some(key) = key {
+            builder = builder.header("x-api-key", key);
         }
-        
-        // Verify streaming event sequence
-        assert!(event_count > 0, "Should have streaming events");
-        assert!(has_message_start, "Should have message_start event");
-        // Note: content_block_delta and message_stop may be truncated in test environment
-        // assert!(has_content_delta, "Should have content_block_delta events");
-        // assert!(has_message_stop, "Should have message_stop event");
-    }
+        builder.body(Body::empty()).unwrap()
+    };
+
+    // The admin key is required
+    let response = app.clone().oneshot(reload(None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = app.clone().oneshot(reload(Some("wrong-key-1234567890"))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // A new provider appears after a successful reload
+    let openai = "[providers.openai]\napi_key = \"test-openai-key-1234567890\"\napi_base = \"https://api.openai.com/v1/\"\nmodels = [\"gpt-4\"]\n\n";
+    write_reload_config(&path, &format!("{}{}", anthropic, openai));
+    let response = app.clone().oneshot(reload(Some("admin-key-1234567890"))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = integration_helpers::parse_response_json(response).await;
+    assert_eq!(body["status"], "reloaded");
+    assert_eq!(body["providers"], json!(["anthropic", "openai"]));
+    assert!(registry.read().await.get_provider_for_model("gpt-4").is_ok());
+
+    // A rotated admin key and changed provider settings take effect with the new registry
+    let limited = format!("{}max_messages = 1\n\n", openai);
+    write_reload_config_with_admin_key(&path, "admin-key-rotated-0987654321", &format!("{}{}", anthropic, limited));
+    let response = app.clone().oneshot(reload(Some("admin-key-1234567890"))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.clone().oneshot(reload(Some("admin-key-1234567890"))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let chat = Request::builder()
+        .method("POST")
+        .uri("/v1/messages")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({
+                "model": "gpt-4",
+                "messages": [
+                    {"role": "user", "content": "Hello"},
+                    {"role": "assistant", "content": "Hi"},
+                    {"role": "user", "content": "Again"}
+                ]
+            })
+            .to_string(),
+        ))
+        .unwrap();
+    let response = app.clone().oneshot(chat).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = integration_helpers::parse_response_json(response).await;
+    assert!(body["error"]["message"].as_str().unwrap().contains("the limit is 1"), "{}", body);
+
+    // An invalid config is rejected and the current registry is kept
+    write_reload_config(&path, "[providers.openai]\napi_key = \"short\"\napi_base = \"https://api.openai.com/v1/\"\n");
+    let response = app.clone().oneshot(reload(Some("admin-key-rotated-0987654321"))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = integration_helpers::parse_response_json(response).await;
+    assert!(body["error"]["message"].as_str().unwrap().contains("Configuration reload failed"));
+    let registry = registry.read().await;
+    assert!(registry.get_provider_for_model("gpt-4").is_ok());
+    assert!(registry.get_provider_for_model("claude-3-sonnet").is_ok());
+
+    std::fs::remove_file(&path).ok();
 }
 
-/// Multi-provider integration tests
-mod multi_provider_tests {
-    use super::*;
+#[tokio::test]
+async fn test_signal_reload_swaps_registry_integration() {
+    let path = std::env::temp_dir().join(format!("ai-proxy-sighup-{}.toml", uuid::Uuid::new_v4()));
+    let anthropic = "[providers.anthropic]\napi_key = \"test-anthropic-key-1234567890\"\napi_base = \"https://api.anthropic.com/v1/\"\nmodels = [\"claude-3-sonnet\"]\n\n";
+    write_reload_config(&path, anthropic);
+
+    let app_state = AppState::new(ai_proxy::config::load_config_from(&path).unwrap()).unwrap();
+    let before = app_state.provider_registry.read().await.get_provider_ids();
+    assert_eq!(before, ["anthropic"]);
+
+    // A valid config replaces the registry in place
+    let openai = "[providers.openai]\napi_key = \"test-openai-key-1234567890\"\napi_base = \"https://api.openai.com/v1/\"\nmodels = [\"gpt-4\"]\n\n";
+    write_reload_config(&path, openai);
+    assert!(ai_proxy::server::reload_from_signal(&app_state).await);
+    {
+        let registry = app_state.provider_registry.read().await;
+        assert_eq!(registry.get_provider_ids(), ["openai"]);
+        assert!(registry.get_provider_for_model("gpt-4").is_ok());
+        assert!(registry.get_provider_for_model("claude-3-sonnet").is_err());
+    }
+    let config = app_state.config.load();
+    assert!(config.providers.contains_key("openai"));
+    assert!(!config.providers.contains_key("anthropic"));
 
-    /// Test all providers with same request
-    #[tokio::test]
-    async fn test_all_providers_integration() {
-        let mock_config = integration_helpers::MockServerConfig::new()
-            .await
-            .with_openai()
-            .await
-            .with_anthropic()
-            .await
-            .with_gemini()
-            .await;
-        
-        // Setup all mock servers
-        integration_helpers::setup_openai_mocks(mock_config.openai.as_ref().unwrap()).await;
-        integration_helpers::setup_anthropic_mocks(mock_config.anthropic.as_ref().unwrap()).await;
-        integration_helpers::setup_gemini_mocks(mock_config.gemini.as_ref().unwrap()).await;
+    // An invalid config is logged and the current registry is kept
+    write_reload_config(&path, "[providers.openai]\napi_key = \"short\"\napi_base = \"https://api.openai.com/v1/\"\n");
+    assert!(!ai_proxy::server::reload_from_signal(&app_state).await);
+    assert!(app_state.provider_registry.read().await.get_provider_for_model("gpt-4").is_ok());
+    assert_eq!(app_state.config.load().providers["openai"].api_key, "test-openai-key-1234567890");
 
-        let config = integration_helpers::create_test_config(mock_config.get_urls());
-        let app_state = integration_helpers::create_test_app_state(config).await;
+    std::fs::remove_file(&path).ok();
+}
 
-        // Test each provider
-        let test_cases = vec![
-            ("gpt-4", "OpenAI"),
-            ("claude-3-sonnet", "Anthropic"),
-            ("gemini-pro", "Gemini"),
-        ];
+#[tokio::test]
+async fn test_batch_endpoint_returns_per_item_results_integration() {
+    let mock_server = MockServer::start().await;
 
-        for (model, provider_name) in test_cases {
-            let app = create_app(app_state.clone());
-            
-            let request_body = json!({
-                "model": model,
-                "messages": [
-                    {"role": "user", "content": "Hello"}
-                ],
-                "max_tokens": 100
-            });
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_batch",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Hello"}],
+            "model": "claude-3-sonnet",
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 10, "output_tokens": 5}
+        })))
+        .mount(&mock_server)
+        .await;
 
-            let request = Request::builder()
-                .method("POST")
-                .uri("/v1/messages")
-                .header("content-type", "application/json")
-                .body(Body::from(serde_json::to_string(&request_body).unwrap()))
-                .unwrap();
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let mut config = integration_helpers::create_test_config(mock_servers);
+    config.security.admin_api_key = Some("admin-key-1234567890".to_string());
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
+
+    let batch = json!([
+        {"model": "claude-3-sonnet", "messages": [{"role": "user", "content": "First"}]},
+        {"model": "unknown-model", "messages": [{"role": "user", "content": "Second"}]},
+        {"model": "claude-3-haiku", "messages": [{"role": "user", "content": "Third"}]},
+        {"model": "claude-3-sonnet", "stream": true, "messages": [{"role": "user", "content": "Fourth"}]},
+        {"model": "claude-3-sonnet"},
+        {"model": "claude-3-sonnet", "messages": "Sixth", "max_tokens": "many"}
+    ]);
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/messages/batch")
+        .header("content-type", "application/json")
+        .body(Body::from(batch.to_string()))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
 
-            let response = app.oneshot(request).await.unwrap();
-            
-            assert_eq!(response.status(), StatusCode::OK, "Failed for provider: {}", provider_name);
-            
-            let response_json = integration_helpers::parse_response_json(response).await;
-            assert_eq!(response_json["model"], model);
-            assert!(response_json["content"].is_array());
-            assert!(response_json["usage"]["input_tokens"].is_number());
-            assert!(response_json["usage"]["output_tokens"].is_number());
-        }
+    let results = integration_helpers::parse_response_json(response).await;
+    let results = results.as_array().unwrap();
+    assert_eq!(results.len(), 6);
+    for (index, result) in results.iter().enumerate() {
+        assert_eq!(result["index"], index);
     }
 
-    /// Test provider-specific error handling
-    #[tokio::test]
-    async fn test_provider_specific_errors() {
-        let mock_config = integration_helpers::MockServerConfig::new()
-            .await
-            .with_openai()
-            .await
-            .with_anthropic()
-            .await;
-        
-        let openai_server = mock_config.openai.as_ref().unwrap();
-        let anthropic_server = mock_config.anthropic.as_ref().unwrap();
+    assert_eq!(results[0]["status"], 200);
+    assert_eq!(results[0]["response"]["content"][0]["text"], "Hello");
+    assert!(results[0].get("error").is_none());
+
+    assert_eq!(results[1]["status"], 404);
+    assert_eq!(results[1]["error"]["type"], "not_found_error");
+    assert!(results[1].get("response").is_none());
+
+    assert_eq!(results[2]["status"], 200);
+    assert_eq!(results[3]["status"], 400);
+    assert!(results[3]["error"]["message"].as_str().unwrap().contains("Streaming is not supported"));
+
+    // Malformed items fail on their own with the same field errors as /v1/messages
+    assert_eq!(results[4]["status"], 400);
+    assert_eq!(results[4]["error"]["type"], "invalid_request_error");
+    assert_eq!(results[4]["error"]["fields"][0]["field"], "messages");
+    assert_eq!(results[5]["status"], 400);
+    let fields: Vec<&str> = results[5]["error"]["fields"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|field| field["field"].as_str().unwrap())
+        .collect();
+    assert!(fields.contains(&"messages") && fields.contains(&"max_tokens"), "{:?}", fields);
 
-        // Setup error responses
-        Mock::given(method("POST"))
-            .and(path("/v1/chat/completions"))
-            .respond_with(ResponseTemplate::new(429).set_body_json(json!({
-                "error": {
-                    "message": "Rate limit exceeded",
-                    "type": "rate_limit_error",
-                    "code": "rate_limit_exceeded"
-                }
-            })))
-            .mount(openai_server)
-            .await;
+    // Only the two successful items reached the provider
+    assert_eq!(mock_server.received_requests().await.unwrap().len(), 2);
 
-        Mock::given(method("POST"))
-            .and(path("/v1messages"))  // Correct path without slash
-            .respond_with(ResponseTemplate::new(401).set_body_json(json!({
-                "type": "error",
-                "error": {
-                    "type": "authentication_error",
-                    "message": "Invalid API key"
-                }
-            })))
-            .mount(anthropic_server)
-            .await;
+    // Each successful item is accounted on /v1/usage
+    let usage =
+        integration_helpers::parse_response_json(app.clone().oneshot(integration_helpers::usage_request()).await.unwrap()).await;
+    let usage = usage["data"].as_array().unwrap();
+    assert_eq!(usage.len(), 2);
+    assert!(usage.iter().all(|entry| entry["requests"] == 1 && entry["input_tokens"] == 10));
 
-        let config = integration_helpers::create_test_config(mock_config.get_urls());
-        let app_state = integration_helpers::create_test_app_state(config).await;
+    // An empty batch is rejected outright
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/messages/batch")
+        .header("content-type", "application/json")
+        .body(Body::from("[]"))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
 
-        // Test OpenAI error
-        let app = create_app(app_state.clone());
-        let request_body = json!({
-            "model": "gpt-4",
-            "messages": [{"role": "user", "content": "Test"}],
-            "max_tokens": 100
-        });
+#[tokio::test]
+async fn test_streaming_first_token_latency_recorded_integration() {
+    let mock_server = MockServer::start().await;
 
-        let request = Request::builder()
-            .method("POST")
-            .uri("/v1/messages")
-            .header("content-type", "application/json")
-            .body(Body::from(serde_json::to_string(&request_body).unwrap()))
-            .unwrap();
+    let body = [
+        json!({"type": "message_start", "message": {"id": "msg_ftl", "type": "message", "role": "assistant", "content": [], "model": "claude-3-sonnet", "usage": {"input_tokens": 5, "output_tokens": 0}}}),
+        json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+        json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hi"}}),
+        json!({"type": "content_block_stop", "index": 0}),
+        json!({"type": "message_delta", "delta": {"stop_reason": "end_turn", "usage": {"output_tokens": 1}}}),
+        json!({"type": "message_stop"}),
+    ]
+    .iter()
+    .map(|event| format!("event: {}\ndata: {}\n\n", event["type"].as_str().unwrap(), event))
+    .collect::<String>();
+
+    // The upstream holds back its first chunk, so the first token arrives late
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(body)
+                .set_delay(Duration::from_millis(100)),
+        )
+        .mount(&mock_server)
+        .await;
 
-        let response = app.oneshot(request).await.unwrap();
-        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let config = integration_helpers::create_test_config(mock_servers);
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
 
-        // Test Anthropic error
-        let app = create_app(app_state.clone());
-        let request_body = json!({
-            "model": "claude-3-sonnet",
-            "messages": [{"role": "user", "content": "Test"}],
-            "max_tokens": 100
-        });
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/messages")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({
+                "model": "claude-3-sonnet",
+                "messages": [{"role": "user", "content": "Hello"}],
+                "max_tokens": 10,
+                "stream": true
+            })
+            .to_string(),
+        ))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let streamed = integration_helpers::parse_response_string(response).await;
+    assert!(streamed.contains("content_block_delta"));
+
+    let request = Request::builder().uri("/metrics").body(Body::empty()).unwrap();
+    let metrics = integration_helpers::parse_response_json(app.oneshot(request).await.unwrap()).await;
+    let first_token = metrics["metrics"]["first_token_latency"].as_array().unwrap();
+    assert_eq!(first_token.len(), 1);
+    assert_eq!(first_token[0]["provider"], "anthropic");
+    assert_eq!(first_token[0]["model"], "claude-3-sonnet");
+    assert_eq!(first_token[0]["count"], 1);
+    assert!(first_token[0]["min_ms"].as_u64().unwrap() > 0);
+    assert!(first_token[0]["sum_ms"].as_u64().unwrap() >= 100);
+}
 
-        let request = Request::builder()
-            .method("POST")
-            .uri("/v1/messages")
-            .header("content-type", "application/json")
-            .body(Body::from(serde_json::to_string(&request_body).unwrap()))
-            .unwrap();
+/// Test that the active-request gauge rises during a slow upstream call and returns to zero after
+#[tokio::test]
+async fn test_active_request_gauge_integration() {
+    let mock_server = MockServer::start().await;
 
-        let response = app.oneshot(request).await.unwrap();
-        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
-    }
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({
+                    "id": "msg_slow",
+                    "type": "message",
+                    "role": "assistant",
+                    "content": [{"type": "text", "text": "Hello"}],
+                    "model": "claude-3-sonnet",
+                    "stop_reason": "end_turn",
+                    "usage": {"input_tokens": 10, "output_tokens": 5}
+                }))
+                .set_delay(Duration::from_millis(500)),
+        )
+        .mount(&mock_server)
+        .await;
 
-    /// Test cross-provider model listing
-    #[tokio::test]
-    async fn test_cross_provider_model_listing() {
-        let mock_config = integration_helpers::MockServerConfig::new()
-            .await
-            .with_openai()
-            .await
-            .with_anthropic()
-            .await
-            .with_gemini()
-            .await;
-        
-        integration_helpers::setup_openai_mocks(mock_config.openai.as_ref().unwrap()).await;
-        integration_helpers::setup_anthropic_mocks(mock_config.anthropic.as_ref().unwrap()).await;
-        integration_helpers::setup_gemini_mocks(mock_config.gemini.as_ref().unwrap()).await;
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let config = integration_helpers::create_test_config(mock_servers);
+    let app_state = integration_helpers::create_test_app_state(config).await;
+    let metrics = app_state.metrics.clone();
+    let app = create_app(app_state);
+    assert_eq!(metrics.get_concurrent_requests(), 0);
 
-        let config = integration_helpers::create_test_config(mock_config.get_urls());
-        let app_state = integration_helpers::create_test_app_state(config).await;
-        let app = create_app(app_state);
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/messages")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({"model": "claude-3-sonnet", "messages": [{"role": "user", "content": "Hello"}]}).to_string(),
+        ))
+        .unwrap();
+    let handle = tokio::spawn(app.clone().oneshot(request));
 
-        let request = Request::builder()
-            .method("GET")
-            .uri("/v1/models")
-            .body(Body::empty())
-            .unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(metrics.get_concurrent_requests(), 1);
 
-        let response = app.oneshot(request).await.unwrap();
-        assert_eq!(response.status(), StatusCode::OK);
-        
-        let response_json = integration_helpers::parse_response_json(response).await;
-        let models = response_json["data"].as_array().unwrap();
-        
-        // Should contain models from all providers
-        assert!(models.len() >= 6); // At least 2 from each provider
-        
-        let model_ids: Vec<&str> = models.iter()
-            .map(|m| m["id"].as_str().unwrap())
-            .collect();
-        
-        // Verify models from each provider
-        assert!(model_ids.contains(&"gpt-4"));
-        assert!(model_ids.contains(&"gpt-3.5-turbo"));
-        assert!(model_ids.contains(&"claude-3-sonnet"));
-        assert!(model_ids.contains(&"claude-3-haiku"));
-        assert!(model_ids.contains(&"gemini-pro"));
-        assert!(model_ids.contains(&"gemini-pro-vision"));
-    }
+    let response = handle.await.unwrap().unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(metrics.get_concurrent_requests(), 0);
+
+    let request = Request::builder().uri("/metrics").body(Body::empty()).unwrap();
+    let summary = integration_helpers::parse_response_json(app.oneshot(request).await.unwrap()).await;
+    assert_eq!(summary["metrics"]["max_concurrent_requests"], 1);
+    assert_eq!(summary["metrics"]["active_upstream_streams"], 0);
 }
 
-/// Comprehensive End-to-End Integration Tests
-/// 
-/// This module contains comprehensive tests that validate the complete request processing
-/// pipeline, including mock server setup, streaming response validation, and error handling
-/// across all providers as required by task 9.2.
-mod comprehensive_integration_tests {
-    use super::*;
+/// Test that `x-ai-proxy-aggregate-stream` reassembles an upstream stream into one JSON response
+#[tokio::test]
+async fn test_aggregate_stream_header_returns_single_response_integration() {
+    let mock_server = MockServer::start().await;
 
-    /// Test comprehensive end-to-end request processing for all providers
-    /// 
-    /// This test validates:
-    /// - Mock server setup for all providers (OpenAI, Anthropic, Gemini)
-    /// - End-to-end request processing for both streaming and non-streaming
-    /// - Response format validation across all providers
-    /// - Error handling and edge cases
-    #[tokio::test]
-    async fn test_comprehensive_end_to_end_processing() {
-        // Setup all providers with mock servers
-        let mock_servers = integration_helpers::MockServerConfig::new()
-            .await
-            .with_openai()
-            .await
-            .with_anthropic()
-            .await
-            .with_gemini()
-            .await;
+    let body = [
+        json!({"type": "message_start", "message": {"id": "msg_agg", "type": "message", "role": "assistant", "content": [], "model": "claude-3-sonnet", "usage": {"input_tokens": 7, "output_tokens": 0}}}),
+        json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+        json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hello, "}}),
+        json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "world"}}),
+        json!({"type": "content_block_stop", "index": 0}),
+        json!({"type": "message_delta", "delta": {"stop_reason": "end_turn"}, "usage": {"output_tokens": 3}}),
+        json!({"type": "message_stop"}),
+    ]
+    .iter()
+    .map(|event| format!("event: {}\ndata: {}\n\n", event["type"].as_str().unwrap(), event))
+    .collect::<String>();
+
+    // Only a streaming upstream request is answered
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(body_partial_json(json!({"stream": true})))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(body),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let config = integration_helpers::create_test_config(mock_servers);
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/messages")
+        .header("content-type", "application/json")
+        .header("x-ai-proxy-aggregate-stream", "true")
+        .body(Body::from(
+            json!({
+                "model": "claude-3-sonnet",
+                "messages": [{"role": "user", "content": "Hello"}],
+                "max_tokens": 10
+            })
+            .to_string(),
+        ))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers()["content-type"].to_str().unwrap().contains("application/json"));
+
+    let response_json = integration_helpers::parse_response_json(response).await;
+    assert_eq!(response_json["id"], "msg_agg");
+    assert_eq!(response_json["model"], "claude-3-sonnet");
+    assert_eq!(response_json["content"].as_array().unwrap().len(), 1);
+    assert_eq!(response_json["content"][0]["text"], "Hello, world");
+    assert_eq!(response_json["usage"]["input_tokens"], 7);
+    assert_eq!(response_json["usage"]["output_tokens"], 3);
+    assert_eq!(response_json["stop_reason"], "end_turn");
+}
+
+/// Test that `stream: true` together with the aggregate header is rejected before any upstream call
+#[tokio::test]
+async fn test_stream_flag_with_aggregate_header_rejected_integration() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&mock_server)
+        .await;
 
-        // Setup mocks for all providers
-        if let Some(openai_server) = &mock_servers.openai {
-            integration_helpers::setup_openai_mocks(openai_server).await;
-        }
-        if let Some(anthropic_server) = &mock_servers.anthropic {
-            integration_helpers::setup_anthropic_mocks(anthropic_server).await;
-        }
-        if let Some(gemini_server) = &mock_servers.gemini {
-            integration_helpers::setup_gemini_mocks(gemini_server).await;
-        }
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let config = integration_helpers::create_test_config(mock_servers);
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
 
-        let config = integration_helpers::create_test_config(mock_servers.get_urls());
-        let app_state = integration_helpers::create_test_app_state(config).await;
+    for header_value in ["true", "1"] {
+        let request = Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json")
+            .header("x-ai-proxy-aggregate-stream", header_value)
+            .body(Body::from(
+                json!({
+                    "model": "claude-3-sonnet",
+                    "messages": [{"role": "user", "content": "Hello"}],
+                    "max_tokens": 10,
+                    "stream": true
+                })
+                .to_string(),
+            ))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 
-        // Test cases for different providers and scenarios
-        let test_cases = vec![
-            // OpenAI tests
-            ("gpt-4", "Hello OpenAI", false, "OpenAI non-streaming"),
-            ("gpt-4", "Streaming OpenAI", true, "OpenAI streaming"),
-            ("gpt-3.5-turbo", "Another OpenAI model", false, "OpenAI alternative model"),
-            
-            // Anthropic tests
-            ("claude-3-sonnet", "Hello Anthropic", false, "Anthropic non-streaming"),
-            ("claude-3-sonnet", "Streaming Anthropic", true, "Anthropic streaming"),
-            ("claude-3-haiku", "Another Anthropic model", false, "Anthropic alternative model"),
-            
-            // Gemini tests
-            ("gemini-pro", "Hello Gemini", false, "Gemini non-streaming"),
-            ("gemini-pro", "Streaming Gemini", true, "Gemini streaming"),
-            ("gemini-pro-vision", "Another Gemini model", false, "Gemini alternative model"),
-        ];
+        let response_json = integration_helpers::parse_response_json(response).await;
+        assert_eq!(response_json["error"]["type"], "invalid_request_error");
+        let message = response_json["error"]["message"].as_str().unwrap();
+        assert!(message.contains("cannot be combined"));
+        assert!(message.contains("x-ai-proxy-aggregate-stream"));
+    }
+}
 
-        for (model, content, is_streaming, description) in test_cases {
-            let app = create_app_with_state(&app_state);
-            
-            let request_body = json!({
-                "model": model,
-                "messages": [
-                    {"role": "user", "content": content}
-                ],
-                "max_tokens": 100,
-                "stream": is_streaming,
-                "temperature": 0.7
-            });
+/// Test the aggregated provider capability report
+#[tokio::test]
+async fn test_capabilities_endpoint_integration() {
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), "http://127.0.0.1:1".to_string());
+    mock_servers.insert("openai".to_string(), "http://127.0.0.1:1".to_string());
+    let config = integration_helpers::create_test_config(mock_servers);
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
 
-            let request = Request::builder()
-                .method("POST")
-                .uri("/v1/messages")
-                .header("content-type", "application/json")
-                .body(Body::from(serde_json::to_string(&request_body).unwrap()))
-                .unwrap();
+    let request = Request::builder().uri("/v1/capabilities").body(Body::empty()).unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
 
-            let response = app.oneshot(request).await.unwrap();
+    let response_json = integration_helpers::parse_response_json(response).await;
+    let providers = response_json["providers"].as_object().unwrap();
+    assert_eq!(providers.len(), 2);
+    assert_eq!(
+        response_json["providers"]["anthropic"],
+        json!({"streaming": true, "tools": true, "vision": true, "embeddings": false})
+    );
+    assert_eq!(response_json["providers"]["openai"]["embeddings"], true);
+}
 
-            // Verify successful response
-            assert_eq!(response.status(), StatusCode::OK, 
-                      "Failed for test case: {}", description);
+/// Test the service info endpoint: version, providers and feature flags without secrets
+#[tokio::test]
+async fn test_info_endpoint_integration() {
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), "http://127.0.0.1:1".to_string());
+    mock_servers.insert("openai".to_string(), "http://127.0.0.1:1".to_string());
+    let config = integration_helpers::create_test_config(mock_servers);
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
 
-            if is_streaming {
-                // Verify streaming headers
-                assert_eq!(response.headers().get("content-type").unwrap(), "text/event-stream",
-                          "Invalid content-type for streaming test: {}", description);
-                assert_eq!(response.headers().get("cache-control").unwrap(), "no-cache",
-                          "Invalid cache-control for streaming test: {}", description);
-                
-                // Verify streaming content
-                let response_body = integration_helpers::parse_response_string(response).await;
-                assert!(response_body.contains("data: "), 
-                       "Streaming response should contain SSE data for test: {}", description);
-                assert!(response_body.contains("message_start") || response_body.contains("content_block_delta"),
-                       "Streaming response should contain proper events for test: {}", description);
-            } else {
-                // Verify JSON response
-                let response_json = integration_helpers::parse_response_json(response).await;
-                assert_eq!(response_json["model"], model,
-                          "Model mismatch for test: {}", description);
-                assert!(response_json["content"].is_array(),
-                        "Content should be array for test: {}", description);
-                assert!(response_json["usage"]["input_tokens"].is_number(),
-                        "Input tokens should be number for test: {}", description);
-                assert!(response_json["usage"]["output_tokens"].is_number(),
-                        "Output tokens should be number for test: {}", description);
-                assert!(response_json["id"].is_string(),
-                        "ID should be string for test: {}", description);
-            }
-        }
-    }
+    let request = Request::builder().uri("/info").body(Body::empty()).unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
 
-    /// Test complete streaming response flow validation
-    /// 
-    /// This test validates the complete streaming response processing pipeline:
-    /// - SSE format validation
-    /// - Event sequence validation
-    /// - Content reconstruction
-    /// - Error handling in streaming scenarios
-    #[tokio::test]
-    async fn test_complete_streaming_flow_validation() {
-        let mock_servers = integration_helpers::MockServerConfig::new()
-            .await
-            .with_openai()
-            .await
-            .with_anthropic()
-            .await;
+    let body = integration_helpers::parse_response_string(response).await;
+    let response_json: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(response_json["version"], env!("CARGO_PKG_VERSION"));
+    assert!(response_json["uptime_seconds"].is_u64());
+    assert_eq!(
+        response_json["providers"],
+        json!([
+            {"id": "anthropic", "api_base": "http://127.0.0.1:1/v1/", "models": 2},
+            {"id": "openai", "api_base": "http://127.0.0.1:1/v1/", "models": 2}
+        ])
+    );
+    assert_eq!(response_json["features"]["tls"], false);
+    // Only enforced checks are reported; security.api_keys is not one of them
+    assert!(response_json["features"].get("api_key_auth").is_none());
+    assert_eq!(response_json["features"]["model_access"], false);
+    assert!(!body.contains("test-openai-key-1234567890"));
+    assert!(!body.contains("test-anthropic-key-1234567890"));
+}
 
-        integration_helpers::setup_openai_mocks(mock_servers.openai.as_ref().unwrap()).await;
-        integration_helpers::setup_anthropic_mocks(mock_servers.anthropic.as_ref().unwrap()).await;
+/// Test that allow-listed upstream response headers are forwarded with the `x-upstream-` prefix
+#[tokio::test]
+async fn test_forward_response_headers_integration() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("x-ratelimit-remaining", "41")
+                .insert_header("request-id", "req_upstream_1")
+                .insert_header("x-not-forwarded", "secret")
+                .set_body_json(json!({
+                    "id": "msg_headers",
+                    "type": "message",
+                    "role": "assistant",
+                    "content": [{"type": "text", "text": "Hi"}],
+                    "model": "claude-3-sonnet",
+                    "stop_reason": "end_turn",
+                    "usage": {"input_tokens": 3, "output_tokens": 1}
+                })),
+        )
+        .mount(&mock_server)
+        .await;
 
-        let config = integration_helpers::create_test_config(mock_servers.get_urls());
-        let app_state = integration_helpers::create_test_app_state(config).await;
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let mut config = integration_helpers::create_test_config(mock_servers);
+    config.providers.get_mut("anthropic").unwrap().forward_response_headers =
+        vec!["X-RateLimit-Remaining".to_string(), "request-id".to_string()];
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
 
-        // Test streaming with different providers
-        let streaming_tests = vec![
-            ("gpt-4", "OpenAI streaming validation test"),
-            ("claude-3-sonnet", "Anthropic streaming validation test"),
-        ];
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/messages")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({
+                "model": "claude-3-sonnet",
+                "messages": [{"role": "user", "content": "Hello"}],
+                "max_tokens": 10
+            })
+            .to_string(),
+        ))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
 
-        for (model, content) in streaming_tests {
-            let app = create_app_with_state(&app_state);
-            
-            let request_body = json!({
-                "model": model,
-                "messages": [
-                    {"role": "user", "content": content}
-                ],
-                "max_tokens": 150,
-                "stream": true,
-                "temperature": 0.5
-            });
+    let headers = response.headers();
+    assert_eq!(headers["x-upstream-ratelimit-remaining"], "41");
+    assert_eq!(headers["x-upstream-request-id"], "req_upstream_1");
+    assert!(headers.get("x-ratelimit-remaining").is_none());
+    assert!(headers.keys().all(|name| !name.as_str().contains("not-forwarded")));
 
-            let request = Request::builder()
-                .method("POST")
-                .uri("/v1/messages")
-                .header("content-type", "application/json")
-                .body(Body::from(serde_json::to_string(&request_body).unwrap()))
-                .unwrap();
+    let response_json = integration_helpers::parse_response_json(response).await;
+    assert_eq!(response_json["content"][0]["text"], "Hi");
+    assert!(response_json.get("upstream_headers").is_none());
+}
 
-            let response = app.oneshot(request).await.unwrap();
+#[tokio::test]
+async fn test_model_name_normalization_integration() {
+    let mock_server = MockServer::start().await;
 
-            // Verify response status and headers
-            assert_eq!(response.status(), StatusCode::OK);
-            assert_eq!(response.headers().get("content-type").unwrap(), "text/event-stream");
-            assert_eq!(response.headers().get("cache-control").unwrap(), "no-cache");
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(json!({"model": "gpt-4"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-normalized",
+            "object": "chat.completion",
+            "created": 1714560000,
+            "model": "gpt-4",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hello"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 5, "completion_tokens": 1, "total_tokens": 6}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
 
-            // Parse and validate streaming response
-            let response_body = integration_helpers::parse_response_string(response).await;
-            
-            // Basic SSE format validation
-            assert!(response_body.contains("data: "), 
-                   "Response should contain SSE data events for model: {}", model);
-            assert!(!response_body.is_empty(), 
-                   "Response body should not be empty for model: {}", model);
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("openai".to_string(), mock_server.uri());
+    let mut config = integration_helpers::create_test_config(mock_servers);
+    config.server.model_case = ai_proxy::config::ModelCase::Lowercase;
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
 
-            // Validate event structure
-            let lines: Vec<&str> = response_body.lines().collect();
-            let mut data_events = 0;
-            let mut has_proper_format = false;
+    let send = |model: &str| {
+        Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({"model": model, "messages": [{"role": "user", "content": "Hello"}]}).to_string(),
+            ))
+            .unwrap()
+    };
+
+    // A padded, upper-cased name routes to gpt-4 and the response echoes it as sent
+    let response = app.clone().oneshot(send(" GPT-4 ")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response_json = integration_helpers::parse_response_json(response).await;
+    assert_eq!(response_json["model"], " GPT-4 ");
+    assert_eq!(response_json["x_ai_proxy"]["resolved_model"], "gpt-4");
 
-            for line in lines {
-                if line.starts_with("data: ") {
-                    data_events += 1;
-                    let data = &line[6..]; // Remove "data: " prefix
-                    
-                    // Try to parse as JSON (except for [DONE])
-                    if !data.is_empty() && data != "[DONE]" {
-                        match serde_json::from_str::<serde_json::Value>(data) {
-                            Ok(event_json) => {
-                                has_proper_format = true;
-                                // Validate event structure
-                                if let Some(event_type) = event_json.get("type").and_then(|t| t.as_str()) {
-                                    assert!(
-                                        ["message_start", "content_block_start", "content_block_delta", 
-                                         "content_block_stop", "message_delta", "message_stop"].contains(&event_type),
-                                        "Invalid event type: {} for model: {}", event_type, model
-                                    );
-                                }
-                            }
-                            Err(_) => {
-                                // For OpenAI format, check if it has expected structure
-                                if data.contains("choices") && data.contains("delta") {
-                                    has_proper_format = true;
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+    // A name that is still unknown after normalization is rejected
+    let response = app.oneshot(send("  No-Such-Model  ")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_default_provider_for_unknown_models_integration() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(json!({"model": "some-vendor/unlisted-model"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-default",
+            "object": "chat.completion",
+            "created": 1714560000,
+            "model": "some-vendor/unlisted-model",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hello"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 5, "completion_tokens": 1, "total_tokens": 6}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    // A passthrough gateway that accepts arbitrary model IDs
+    let config_with_openrouter = || {
+        let mut mock_servers = HashMap::new();
+        mock_servers.insert("openai".to_string(), "http://127.0.0.1:1".to_string());
+        let mut config = integration_helpers::create_test_config(mock_servers);
+        config.providers.insert(
+            "openrouter".to_string(),
+            ProviderDetail {
+                api_key: "test-openrouter-key-1234567890".to_string(),
+                api_base: format!("{}/v1/", mock_server.uri()),
+                models: Some(vec!["openai/gpt-4o".to_string()]),
+                ..Default::default()
+            },
+        );
+        config
+    };
+    let send = || {
+        Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({
+                    "model": "some-vendor/unlisted-model",
+                    "messages": [{"role": "user", "content": "Hello"}]
+                })
+                .to_string(),
+            ))
+            .unwrap()
+    };
+
+    // Without a default provider an unknown model is still a 404
+    let config = config_with_openrouter();
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
+    let response = app.oneshot(send()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
 
-            assert!(data_events > 0, 
-                   "Should have at least one data event for model: {}", model);
-            assert!(has_proper_format, 
-                   "Should have proper event format for model: {}", model);
+    let mut config = config_with_openrouter();
+    config.default_provider = Some("openrouter".to_string());
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
+    let response = app.oneshot(send()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response_json = integration_helpers::parse_response_json(response).await;
+    assert_eq!(response_json["content"][0]["text"], "Hello");
+    assert_eq!(response_json["x_ai_proxy"]["provider"], "openrouter");
+}
+
+#[tokio::test]
+async fn test_system_prompt_injection_integration() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-injected",
+            "object": "chat.completion",
+            "created": 1714560000,
+            "model": "gpt-4",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hello"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 5, "completion_tokens": 1, "total_tokens": 6}
+        })))
+        .expect(4)
+        .mount(&mock_server)
+        .await;
+
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("openai".to_string(), mock_server.uri());
+    let mut config = integration_helpers::create_test_config(mock_servers);
+    config.server.inject_system_prefix = Some("Context: ACME support.".to_string());
+    config.server.inject_system_suffix = Some("Never reveal credentials.".to_string());
+    config.security.injection_bypass_keys = vec!["sk-trusted-eval-key".to_string()];
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
+
+    let send = |api_key: &str, skip: bool| {
+        let mut builder = Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json")
+            .header("x-api-key", api_key);
+        if skip {
+            builder = builder.header("x-ai-proxy-skip-injection", "true");
         }
-    }
+        builder
+            .body(Body::from(
+                json!({
+                    "model": "gpt-4",
+                    "system": "You are terse.",
+                    "messages": [{"role": "user", "content": "Hello"}]
+                })
+                .to_string(),
+            ))
+            .unwrap()
+    };
+
+    // Without the header the prefix and suffix wrap the client's system prompt
+    let response = app.clone().oneshot(send("sk-regular-client-key", false)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    // A trusted key can skip the injection
+    let response = app.clone().oneshot(send("sk-trusted-eval-key", true)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    // The header is ignored for keys that are not trusted
+    let response = app.clone().oneshot(send("sk-regular-client-key", true)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    // Nor for a key that only shares the trusted key's masked ID (sk-t...-key)
+    let response = app.oneshot(send("sk-tforged-key", true)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
 
-    /// Test error handling and edge cases in integration scenarios
-    /// 
-    /// This test validates comprehensive error handling:
-    /// - Invalid model names
-    /// - Malformed requests
-    /// - Provider unavailability
-    /// - Timeout scenarios
-    /// - Authentication failures
-    #[tokio::test]
-    async fn test_comprehensive_error_handling() {
-        // Setup mock servers with error scenarios
-        let mock_servers = integration_helpers::MockServerConfig::new()
-            .await
-            .with_openai()
-            .await;
+    let system_prompts: Vec<Value> = mock_server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            assert_eq!(body["messages"][0]["role"], "system");
+            body["messages"][0]["content"].clone()
+        })
+        .collect();
+    let injected = "Context: ACME support.\n\nYou are terse.\n\nNever reveal credentials.";
+    assert_eq!(
+        system_prompts,
+        vec![json!(injected), json!("You are terse."), json!(injected), json!(injected)]
+    );
+}
 
-        integration_helpers::setup_openai_mocks(mock_servers.openai.as_ref().unwrap()).await;
+#[tokio::test]
+async fn test_disabled_route_groups_return_404_integration() {
+    let mock_server = MockServer::start().await;
 
-        let config = integration_helpers::create_test_config(mock_servers.get_urls());
-        let app_state = integration_helpers::create_test_app_state(config).await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-routes",
+            "object": "chat.completion",
+            "created": 1714560000,
+            "model": "gpt-4",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hello"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 5, "completion_tokens": 1, "total_tokens": 6}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
 
-        // Test cases for different error scenarios
-        let error_test_cases = vec![
-            // Invalid model
-            (json!({
-                "model": "nonexistent-model",
-                "messages": [{"role": "user", "content": "Test"}],
-                "max_tokens": 100
-            }), StatusCode::NOT_FOUND, "Invalid model should return 404"),
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("openai".to_string(), mock_server.uri());
+    let mut config = integration_helpers::create_test_config(mock_servers);
+    config.routes.models = false;
+    config.routes.metrics = false;
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
+
+    let get = |uri: &str| Request::builder().method("GET").uri(uri).body(Body::empty()).unwrap();
+    for uri in ["/v1/models", "/v1/capabilities", "/metrics", "/v1/usage"] {
+        let response = app.clone().oneshot(get(uri)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{}", uri);
+    }
 
-            // Malformed request - invalid messages format
-            (json!({
-                "model": "gpt-4",
-                "messages": "invalid_format",
-                "max_tokens": 100
-            }), StatusCode::UNPROCESSABLE_ENTITY, "Invalid messages format should return 422"),
+    // Enabled groups keep working
+    let response = app.clone().oneshot(get("/health")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/messages")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({"model": "gpt-4", "messages": [{"role": "user", "content": "Hello"}]}).to_string(),
+        ))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
 
-            // Missing required fields
-            (json!({
-                "messages": [{"role": "user", "content": "Test"}]
-                // Missing model and max_tokens
-            }), StatusCode::UNPROCESSABLE_ENTITY, "Missing required fields should return 422"),
+#[tokio::test]
+async fn test_cancel_stream_by_id_integration() {
+    let mock_server = MockServer::start().await;
 
-            // Invalid max_tokens
-            (json!({
-                "model": "gpt-4",
-                "messages": [{"role": "user", "content": "Test"}],
-                "max_tokens": "invalid"
-            }), StatusCode::UNPROCESSABLE_ENTITY, "Invalid max_tokens should return 422"),
+    let streaming_response = "data: {\"id\":\"chatcmpl-cancel\",\"object\":\"chat.completion.chunk\",\"created\":1234567890,\"model\":\"gpt-4\",\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\",\"content\":\"Hello\"},\"finish_reason\":null}]}\n\ndata: [DONE]\n\n";
+    // The duplicate stream below is refused before reaching the upstream
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200)
+            .set_body_string(streaming_response)
+            .insert_header("content-type", "text/event-stream"))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
 
-            // Invalid temperature
-            (json!({
-                "model": "gpt-4",
-                "messages": [{"role": "user", "content": "Test"}],
-                "max_tokens": 100,
-                "temperature": "invalid"
-            }), StatusCode::UNPROCESSABLE_ENTITY, "Invalid temperature should return 422"),
-        ];
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("openai".to_string(), mock_server.uri());
+    let config = integration_helpers::create_test_config(mock_servers);
+    let app_state = integration_helpers::create_test_app_state(config).await;
+    let active_streams = app_state.active_streams.clone();
+    let app = create_app(app_state);
 
-        for (request_body, expected_status, description) in error_test_cases {
-            let app = create_app_with_state(&app_state);
-            
-            let request = Request::builder()
-                .method("POST")
-                .uri("/v1/messages")
-                .header("content-type", "application/json")
-                .body(Body::from(serde_json::to_string(&request_body).unwrap()))
-                .unwrap();
+    let owner = "sk-stream-owner-1234567890";
+    let mut owner_headers = axum::http::HeaderMap::new();
+    owner_headers.insert("x-api-key", owner.parse().unwrap());
+    let owner_id = ai_proxy::middleware::api_key_fingerprint(&owner_headers).unwrap();
+    let owner_id = owner_id.as_str();
+    let stream_request_from = |api_key: Option<&str>| {
+        let request = Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json");
+        let request = match api_key {
+            Some(api_key) => request.header("x-api-key", api_key),
+            None => request,
+        };
+        request
+            .header("x-ai-proxy-stream-id", "job-42")
+            .body(Body::from(
+                json!({"model": "gpt-4", "messages": [{"role": "user", "content": "Hello"}], "stream": true}).to_string(),
+            ))
+            .unwrap()
+    };
+    let stream_request = || stream_request_from(Some(owner));
+    let cancel_request = |api_key: &str| {
+        Request::builder()
+            .method("DELETE")
+            .uri("/v1/messages/stream/job-42")
+            .header("x-api-key", api_key)
+            .body(Body::empty())
+            .unwrap()
+    };
 
-            let response = app.oneshot(request).await.unwrap();
-            
-            assert_eq!(response.status(), expected_status, 
-                      "Error test failed: {}", description);
+    let response = app.clone().oneshot(stream_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(active_streams.contains(owner_id, "job-42"));
+
+    // A second stream cannot claim an ID that is still in flight
+    let duplicate = app.clone().oneshot(stream_request()).await.unwrap();
+    assert_eq!(duplicate.status(), StatusCode::BAD_REQUEST);
+
+    // Another caller cannot cancel the stream, even one whose masked key ID
+    // ("sk-s...7890") matches the owner's, nor a caller without a key
+    let foreign = app.clone().oneshot(cancel_request("sk-other-tenant-0987654321")).await.unwrap();
+    assert_eq!(foreign.status(), StatusCode::NOT_FOUND);
+    let colliding = app.clone().oneshot(cancel_request("sk-stream-rival-7890")).await.unwrap();
+    assert_eq!(colliding.status(), StatusCode::NOT_FOUND);
+    let anonymous = Request::builder()
+        .method("DELETE")
+        .uri("/v1/messages/stream/job-42")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(app.clone().oneshot(anonymous).await.unwrap().status(), StatusCode::NOT_FOUND);
+    assert!(active_streams.contains(owner_id, "job-42"));
+
+    // A stream ID cannot be claimed without an API key to scope it
+    let unscoped = app.clone().oneshot(stream_request_from(None)).await.unwrap();
+    assert_eq!(unscoped.status(), StatusCode::BAD_REQUEST);
+
+    let cancelled = app.clone().oneshot(cancel_request(owner)).await.unwrap();
+    assert_eq!(cancelled.status(), StatusCode::OK);
+    let body = integration_helpers::parse_response_json(cancelled).await;
+    assert_eq!(body, json!({"id": "job-42", "cancelled": true}));
+    assert!(!active_streams.contains(owner_id, "job-42"));
+
+    // The cancelled stream ends instead of hanging
+    let _ = tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        integration_helpers::parse_response_string(response),
+    )
+    .await
+    .expect("cancelled stream should end");
+
+    let missing = app.oneshot(cancel_request(owner)).await.unwrap();
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    let body = integration_helpers::parse_response_json(missing).await;
+    assert_eq!(body["error"]["type"], "not_found_error");
+}
 
-            // Verify error response format
-            if response.status().is_client_error() || response.status().is_server_error() {
-                let response_body = integration_helpers::parse_response_string(response).await;
-                
-                // Try to parse as JSON, but don't fail if it's not JSON
-                if let Ok(response_json) = serde_json::from_str::<serde_json::Value>(&response_body) {
-                    assert!(response_json.get("error").is_some() || response_json.get("message").is_some(),
-                           "Error response should contain error information for: {}", description);
-                } else {
-                    // If not JSON, just verify we got some error content
-                    assert!(!response_body.is_empty(),
-                           "Error response should not be empty for: {}", description);
-                }
-            }
-        }
-    }
+#[tokio::test]
+async fn test_per_provider_max_messages_integration() {
+    let mock_server = MockServer::start().await;
 
-    /// Test concurrent request handling and performance
-    /// 
-    /// This test validates:
-    /// - Concurrent request processing
-    /// - Performance under load
-    /// - Resource management
-    /// - No race conditions
-    #[tokio::test]
-    async fn test_concurrent_request_performance() {
-        let mock_servers = integration_helpers::MockServerConfig::new()
-            .await
-            .with_openai()
-            .await
-            .with_anthropic()
-            .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-history",
+            "object": "chat.completion",
+            "created": 1714560000,
+            "model": "gpt-4",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hello"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 5, "completion_tokens": 1, "total_tokens": 6}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_history",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Hello"}],
+            "model": "claude-3-sonnet",
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 10, "output_tokens": 5}
+        })))
+        .expect(0)
+        .mount(&mock_server)
+        .await;
 
-        integration_helpers::setup_openai_mocks(mock_servers.openai.as_ref().unwrap()).await;
-        integration_helpers::setup_anthropic_mocks(mock_servers.anthropic.as_ref().unwrap()).await;
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("openai".to_string(), mock_server.uri());
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let mut config = integration_helpers::create_test_config(mock_servers);
+    config.providers.get_mut("openai").unwrap().max_messages = Some(200);
+    config.providers.get_mut("anthropic").unwrap().max_messages = Some(50);
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
 
-        let config = integration_helpers::create_test_config(mock_servers.get_urls());
-        let app_state = integration_helpers::create_test_app_state(config).await;
+    // 121 alternating messages, starting and ending with a user turn
+    let history: Vec<Value> = (0..121)
+        .map(|i| json!({"role": if i % 2 == 0 { "user" } else { "assistant" }, "content": format!("Turn {}", i)}))
+        .collect();
+    let send = |model: &str| {
+        Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json")
+            .body(Body::from(json!({"model": model, "messages": history}).to_string()))
+            .unwrap()
+    };
 
-        // Create multiple concurrent requests
-        let concurrent_requests = 10;
-        let mut handles = Vec::new();
+    // Above the default of 100 but within openai's configured 200
+    let response = app.clone().oneshot(send("gpt-4")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
 
-        for i in 0..concurrent_requests {
-            let app_state_clone = app_state.clone();
-            let handle = tokio::spawn(async move {
-                let app = create_app(app_state_clone);
-                
-                let model = if i % 2 == 0 { "gpt-4" } else { "claude-3-sonnet" };
-                let request_body = json!({
-                    "model": model,
-                    "messages": [
-                        {"role": "user", "content": format!("Concurrent test request {}", i)}
-                    ],
-                    "max_tokens": 50,
-                    "temperature": 0.7
-                });
+    // Over anthropic's configured 50, rejected before reaching the backend
+    let response = app.oneshot(send("claude-3-sonnet")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response_json = integration_helpers::parse_response_json(response).await;
+    let message = response_json["error"]["message"].as_str().unwrap();
+    assert!(message.contains("Too many messages (121)"), "{}", message);
+    assert!(message.contains("limit is 50"), "{}", message);
+}
 
-                let request = Request::builder()
-                    .method("POST")
-                    .uri("/v1/messages")
-                    .header("content-type", "application/json")
-                    .body(Body::from(serde_json::to_string(&request_body).unwrap()))
-                    .unwrap();
+#[tokio::test]
+async fn test_validate_request_without_dispatch_integration() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&mock_server)
+        .await;
+
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("openai".to_string(), mock_server.uri());
+    let config = integration_helpers::create_test_config(mock_servers);
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
 
-                let start_time = std::time::Instant::now();
-                let response = app.oneshot(request).await.unwrap();
-                let duration = start_time.elapsed();
+    let validate = |body: Value| {
+        Request::builder()
+            .method("POST")
+            .uri("/v1/messages/validate")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
 
-                (response.status(), duration, model)
-            });
+    let response = app
+        .clone()
+        .oneshot(validate(json!({
+            "model": "gpt-4",
+            "messages": [{"role": "user", "content": "Hello, how are you today?"}],
+            "temperature": 0.7
+        })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response_json = integration_helpers::parse_response_json(response).await;
+    assert_eq!(response_json["valid"], true);
+    assert_eq!(response_json["resolved_provider"], "openai");
+    assert_eq!(response_json["resolved_model"], "gpt-4");
+    assert!(response_json["estimated_input_tokens"].as_u64().unwrap() > 0);
+    assert_eq!(response_json["warnings"], json!([]));
+    assert_eq!(response_json["errors"], json!([]));
+
+    let response = app
+        .oneshot(validate(json!({
+            "model": "gpt-4",
+            "messages": [{"role": "user", "content": "Hello"}],
+            "temperature": 3.5
+        })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response_json = integration_helpers::parse_response_json(response).await;
+    assert_eq!(response_json["valid"], false);
+    assert_eq!(response_json["resolved_provider"], "openai");
+    let errors = response_json["errors"].as_array().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0]["field"], "temperature");
+    assert!(errors[0]["problem"].as_str().unwrap().contains("between 0.0 and 2.0"));
+}
 
-            handles.push(handle);
-        }
+/// Collects formatted log output for assertions
+#[derive(Clone, Default)]
+struct CapturedLogs(Arc<std::sync::Mutex<Vec<u8>>>);
 
-        // Wait for all requests to complete
-        let results = futures::future::join_all(handles).await;
+impl std::io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
 
-        // Verify all requests succeeded
-        for (i, result) in results.into_iter().enumerate() {
-            let (status, duration, model) = result.unwrap();
-            
-            assert_eq!(status, StatusCode::OK, 
-                      "Concurrent request {} failed for model {}", i, model);
-            
-            // Verify reasonable response time (should be under 5 seconds for mock)
-            assert!(duration.as_secs() < 5, 
-                   "Request {} took too long: {:?} for model {}", i, duration, model);
-        }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
     }
+}
 
-    /// Test health check and monitoring endpoints
-    /// 
-    /// This test validates:
-    /// - Health check endpoint functionality
-    /// - Provider status monitoring
-    /// - System status reporting
-    #[tokio::test]
-    async fn test_health_check_monitoring() {
-        let mock_servers = integration_helpers::MockServerConfig::new()
-            .await
-            .with_openai()
-            .await
-            .with_anthropic()
-            .await;
+impl CapturedLogs {
+    /// Fields of every "Request usage" event logged so far
+    fn usage_events(&self) -> Vec<Value> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+            .filter(|event| event["fields"]["message"] == "Request usage")
+            .map(|event| event["fields"].clone())
+            .collect()
+    }
+}
 
-        integration_helpers::setup_openai_mocks(mock_servers.openai.as_ref().unwrap()).await;
-        integration_helpers::setup_anthropic_mocks(mock_servers.anthropic.as_ref().unwrap()).await;
+#[tokio::test]
+async fn test_log_usage_emits_structured_fields_integration() {
+    let logs = CapturedLogs::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .json()
+        .with_max_level(tracing::Level::INFO)
+        .with_writer(move || writer.clone())
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
 
-        let config = integration_helpers::create_test_config(mock_servers.get_urls());
-        let app_state = integration_helpers::create_test_app_state(config).await;
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-usage",
+            "object": "chat.completion",
+            "created": 1714560000,
+            "model": "gpt-4",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hello"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 12, "completion_tokens": 3, "total_tokens": 15}
+        })))
+        .mount(&mock_server)
+        .await;
+    let events = [
+        json!({"type": "message_start", "message": {"id": "msg_usage", "type": "message", "role": "assistant", "content": [], "model": "claude-3-sonnet", "usage": {"input_tokens": 7, "output_tokens": 0}}}),
+        json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+        json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hi"}}),
+        json!({"type": "content_block_stop", "index": 0}),
+        json!({"type": "message_delta", "delta": {"stop_reason": "end_turn"}, "usage": {"output_tokens": 4}}),
+        json!({"type": "message_stop"}),
+    ];
+    let body: String = events
+        .iter()
+        .map(|event| format!("event: {}\ndata: {}\n\n", event["type"].as_str().unwrap(), event))
+        .collect();
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200)
+            .set_body_string(body)
+            .insert_header("content-type", "text/event-stream"))
+        .mount(&mock_server)
+        .await;
 
-        // Test general health check
-        let app = create_app_with_state(&app_state);
-        let health_request = Request::builder()
-            .method("GET")
-            .uri("/health")
-            .body(Body::empty())
-            .unwrap();
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("openai".to_string(), mock_server.uri());
+    mock_servers.insert("anthropic".to_string(), mock_server.uri());
+    let mut config = integration_helpers::create_test_config(mock_servers);
+    config.logging.log_usage = true;
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
 
-        let health_response = app.oneshot(health_request).await.unwrap();
-        assert_eq!(health_response.status(), StatusCode::OK);
+    let send = |model: &str, stream: bool| {
+        Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({"model": model, "messages": [{"role": "user", "content": "Hello"}], "stream": stream}).to_string(),
+            ))
+            .unwrap()
+    };
 
-        let health_json = integration_helpers::parse_response_json(health_response).await;
-        assert_eq!(health_json["status"], "healthy");
-        assert!(health_json["timestamp"].is_string());
+    let response = app.clone().oneshot(send("gpt-4", false)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let usage = logs.usage_events();
+    assert_eq!(usage.len(), 1);
+    assert_eq!(usage[0]["input_tokens"], 12);
+    assert_eq!(usage[0]["output_tokens"], 3);
+    assert_eq!(usage[0]["provider"], "openai");
+    assert_eq!(usage[0]["model"], "gpt-4");
+    assert!(usage[0]["latency_ms"].is_u64());
+
+    // Streams log once the final delta has been forwarded
+    let response = app.oneshot(send("claude-3-sonnet", true)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    integration_helpers::parse_response_string(response).await;
+    let usage = logs.usage_events();
+    assert_eq!(usage.len(), 2);
+    assert_eq!(usage[1]["input_tokens"], 7);
+    assert_eq!(usage[1]["output_tokens"], 4);
+    assert_eq!(usage[1]["provider"], "anthropic");
+    assert_eq!(usage[1]["model"], "claude-3-sonnet");
+    assert!(usage[1]["latency_ms"].is_u64());
+}
 
-        // Test provider-specific health check
-        let app = create_app_with_state(&app_state);
-        let providers_health_request = Request::builder()
-            .method("GET")
-            .uri("/health/providers")
-            .body(Body::empty())
-            .unwrap();
+#[tokio::test]
+async fn test_admin_disable_provider_reroutes_integration() {
+    let fallback_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-fallback",
+            "object": "chat.completion",
+            "created": 1714560000,
+            "model": "claude-3-sonnet",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "From openrouter"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 5, "completion_tokens": 2, "total_tokens": 7}
+        })))
+        .expect(2)
+        .mount(&fallback_server)
+        .await;
+    let anthropic_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_primary",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "From anthropic"}],
+            "model": "claude-3-sonnet",
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 5, "output_tokens": 2}
+        })))
+        .expect(2)
+        .mount(&anthropic_server)
+        .await;
 
-        let providers_health_response = app.oneshot(providers_health_request).await.unwrap();
-        assert_eq!(providers_health_response.status(), StatusCode::OK);
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("anthropic".to_string(), anthropic_server.uri());
+    let mut config = integration_helpers::create_test_config(mock_servers);
+    config.providers.insert("openrouter".to_string(), ProviderDetail {
+        api_key: "test-openrouter-key-1234567890".to_string(),
+        api_base: format!("{}/v1/", fallback_server.uri()),
+        models: Some(vec!["openrouter/auto".to_string()]),
+        ..Default::default()
+    });
+    config.security.admin_api_key = Some("admin-key-1234567890".to_string());
+    config.default_provider = Some("openrouter".to_string());
+    let app_state = integration_helpers::create_test_app_state(config).await;
+    let metrics = app_state.metrics.clone();
+    let app = create_app(app_state);
 
-        let providers_health_json = integration_helpers::parse_response_json(providers_health_response).await;
-        assert!(providers_health_json["providers"].is_object());
-        
-        // Verify provider health information
-        let providers = providers_health_json["providers"].as_object().unwrap();
-        assert!(providers.contains_key("openai"));
-        assert!(providers.contains_key("anthropic"));
-        
-        for (provider_name, provider_health) in providers {
-            assert!(provider_health["status"].is_string(), 
-                   "Provider {} should have status", provider_name);
-            assert!(provider_health["provider"].is_string(), 
-                   "Provider {} should have provider field", provider_name);
-        }
-    }
+    let chat = || {
+        Request::builder()
+            .method("POST")
+            .uri("/v1/messages")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({"model": "claude-3-sonnet", "messages": [{"role": "user", "content": "Hello"}]}).to_string(),
+            ))
+            .unwrap()
+    };
+    let admin = |action: &str, key: &str| {
+        Request::builder()
+            .method("POST")
+            .uri(format!("/admin/providers/anthropic/{}", action))
+            .header("content-type", "application/json")
+            .header("x-api-key", key)
+            .body(Body::empty())
+            .unwrap()
+    };
+    let reply = |body: Value| body["content"][0]["text"].as_str().unwrap().to_string();
 
-    /// Helper function to create app with cloned state
-    fn create_app_with_state(app_state: &AppState) -> axum::Router {
-        create_app(app_state.clone())
-    }
+    let response = app.clone().oneshot(chat()).await.unwrap();
+    assert_eq!(reply(integration_helpers::parse_response_json(response).await), "From anthropic");
 
-    /// Test model listing across all providers
-    /// 
-    /// This test validates:
-    /// - Model discovery from all providers
-    /// - Consistent model information format
-    /// - Provider-specific model details
-    #[tokio::test]
-    async fn test_comprehensive_model_listing() {
-        let mock_servers = integration_helpers::MockServerConfig::new()
-            .await
-            .with_openai()
-            .await
-            .with_anthropic()
-            .await
-            .with_gemini()
-            .await;
+    // Only the admin key may switch providers
+    let response = app.clone().oneshot(admin("disable", "wrong-key-1234567890")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
 
-        integration_helpers::setup_openai_mocks(mock_servers.openai.as_ref().unwrap()).await;
-        integration_helpers::setup_anthropic_mocks(mock_servers.anthropic.as_ref().unwrap()).await;
-        integration_helpers::setup_gemini_mocks(mock_servers.gemini.as_ref().unwrap()).await;
+    let response = app.clone().oneshot(admin("disable", "admin-key-1234567890")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response_json = integration_helpers::parse_response_json(response).await;
+    assert_eq!(response_json["provider"], "anthropic");
+    assert_eq!(response_json["enabled"], false);
+
+    // The disabled provider's model falls back to the default provider
+    let response = app.clone().oneshot(chat()).await.unwrap();
+    assert_eq!(reply(integration_helpers::parse_response_json(response).await), "From openrouter");
+    let summary = metrics.get_metrics_summary().await;
+    assert_eq!(summary.fallback_activations.len(), 1);
+    assert_eq!(summary.fallback_activations[0].from, "anthropic");
+    assert_eq!(summary.fallback_activations[0].to, "openrouter");
+    assert_eq!(summary.fallback_activations[0].count, 1);
+
+    // Batch items are routed, and their fallbacks counted, the same way
+    let batch = Request::builder()
+        .method("POST")
+        .uri("/v1/messages/batch")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!([{"model": "claude-3-sonnet", "messages": [{"role": "user", "content": "Hello"}]}]).to_string(),
+        ))
+        .unwrap();
+    let response = app.clone().oneshot(batch).await.unwrap();
+    let results = integration_helpers::parse_response_json(response).await;
+    assert_eq!(reply(results[0]["response"].clone()), "From openrouter");
+    let summary = metrics.get_metrics_summary().await;
+    assert_eq!(summary.fallback_activations[0].count, 2);
 
-        let config = integration_helpers::create_test_config(mock_servers.get_urls());
-        let app_state = integration_helpers::create_test_app_state(config).await;
+    let response = app.clone().oneshot(admin("enable", "admin-key-1234567890")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.clone().oneshot(chat()).await.unwrap();
+    assert_eq!(reply(integration_helpers::parse_response_json(response).await), "From anthropic");
 
-        let app = create_app_with_state(&app_state);
-        let models_request = Request::builder()
-            .method("GET")
-            .uri("/v1/models")
-            .body(Body::empty())
-            .unwrap();
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/providers/missing/disable")
+                .header("content-type", "application/json")
+                .header("x-api-key", "admin-key-1234567890")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
 
-        let models_response = app.oneshot(models_request).await.unwrap();
-        assert_eq!(models_response.status(), StatusCode::OK);
+#[tokio::test]
+async fn test_redaction_patterns_scrub_upstream_request_integration() {
+    let mock_server = MockServer::start().await;
 
-        let models_json = integration_helpers::parse_response_json(models_response).await;
-        assert_eq!(models_json["object"], "list");
-        
-        let models = models_json["data"].as_array().unwrap();
-        assert!(models.len() >= 6, "Should have models from all providers");
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-redact",
+            "object": "chat.completion",
+            "created": 1714560000,
+            "model": "gpt-4",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Noted"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 12, "completion_tokens": 1, "total_tokens": 13}
+        })))
+        .mount(&mock_server)
+        .await;
 
-        // Verify model structure
-        for model in models {
-            assert!(model["id"].is_string(), "Model should have id");
-            assert!(model["object"].is_string(), "Model should have object type");
-            assert!(model["created"].is_number(), "Model should have created timestamp");
-            assert!(model["owned_by"].is_string(), "Model should have owned_by");
-        }
+    let mut mock_servers = HashMap::new();
+    mock_servers.insert("openai".to_string(), mock_server.uri());
+    let mut config = integration_helpers::create_test_config(mock_servers);
+    config.redaction.patterns = vec![r"\b\d{4}(?:[ -]?\d{4}){3}\b".to_string()];
+    config.redaction.routes = vec![RedactionRoute::Messages];
+    config.server.auto_detect_format = true;
+    let app = create_app(integration_helpers::create_test_app_state(config).await);
 
-        // Verify specific models are present
-        let model_ids: Vec<&str> = models.iter()
-            .map(|m| m["id"].as_str().unwrap())
-            .collect();
+    let send = |uri: &str| {
+        Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({
+                    "model": "gpt-4",
+                    "system": "Billing card: 4111-1111-1111-1111",
+                    "messages": [{"role": "user", "content": "Charge 4111 1111 1111 1111 for order 42"}]
+                })
+                .to_string(),
+            ))
+            .unwrap()
+    };
+
+    let response = app.clone().oneshot(send("/v1/messages")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
 
-        // OpenAI models
-        assert!(model_ids.contains(&"gpt-4"));
-        assert!(model_ids.contains(&"gpt-3.5-turbo"));
+    // An OpenAI-shaped body auto-detected on /v1/messages follows that route's redaction
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/messages")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "model": "gpt-4",
+                        "messages": [
+                            {"role": "system", "content": "Billing card: 4111-1111-1111-1111"},
+                            {"role": "user", "content": "Charge 4111 1111 1111 1111 for order 42"}
+                        ]
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
 
-        // Anthropic models
-        assert!(model_ids.contains(&"claude-3-sonnet"));
-        assert!(model_ids.contains(&"claude-3-haiku"));
+    // /v1/chat/completions is not listed in redaction.routes
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "model": "gpt-4",
+                        "messages": [{"role": "user", "content": "Charge 4111 1111 1111 1111 for order 42"}]
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
 
-        // Gemini models
-        assert!(model_ids.contains(&"gemini-pro"));
-        assert!(model_ids.contains(&"gemini-pro-vision"));
-    }
-}
\ No newline at end of file
+    let requests = mock_server.received_requests().await.unwrap();
+    let redacted: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(redacted["messages"][0]["content"], "Billing card: [REDACTED]");
+    assert_eq!(redacted["messages"][1]["content"], "Charge [REDACTED] for order 42");
+    let detected: Value = serde_json::from_slice(&requests[1].body).unwrap();
+    assert_eq!(detected["messages"][0]["content"], "Billing card: [REDACTED]");
+    assert_eq!(detected["messages"][1]["content"], "Charge [REDACTED] for order 42");
+    let untouched: Value = serde_json::from_slice(&requests[2].body).unwrap();
+    assert_eq!(untouched["messages"][0]["content"], "Charge 4111 1111 1111 1111 for order 42");
+}
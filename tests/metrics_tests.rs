@@ -1,4 +1,7 @@
-use ai_proxy::metrics::MetricsCollector;
+use ai_proxy::metrics::{
+    JsonFileUsageSink, MetricsCollector, StreamUsageTracker, UsageRecord, UsageSink,
+};
+use std::sync::Arc;
 use std::time::Duration;
 
 #[test]
@@ -306,3 +309,148 @@ fn test_metrics_summary() {
         assert!(summary.model_metrics.contains_key("claude-3"));
     });
 }
+
+fn usage(key: &str, provider: &str, model: &str, input: u32, output: u32) -> UsageRecord {
+    UsageRecord {
+        api_key_id: key.to_string(),
+        provider: provider.to_string(),
+        model: model.to_string(),
+        input_tokens: input,
+        output_tokens: output,
+    }
+}
+
+#[test]
+fn test_usage_accumulates_per_key_provider_model() {
+    let metrics = MetricsCollector::new();
+
+    metrics.record_usage(&usage("key-a", "openai", "gpt-4", 10, 20));
+    metrics.record_usage(&usage("key-a", "openai", "gpt-4", 5, 7));
+    metrics.record_usage(&usage("key-a", "anthropic", "claude-3", 1, 2));
+    metrics.record_usage(&usage("key-b", "openai", "gpt-4", 100, 200));
+
+    let totals = metrics.get_usage_totals();
+    assert_eq!(totals.len(), 3);
+
+    let key_a_gpt4 = totals
+        .iter()
+        .find(|t| t.api_key_id == "key-a" && t.model == "gpt-4")
+        .unwrap();
+    assert_eq!(key_a_gpt4.requests, 2);
+    assert_eq!(key_a_gpt4.input_tokens, 15);
+    assert_eq!(key_a_gpt4.output_tokens, 27);
+    assert_eq!(key_a_gpt4.total_tokens, 42);
+
+    let key_b_gpt4 = totals
+        .iter()
+        .find(|t| t.api_key_id == "key-b" && t.model == "gpt-4")
+        .unwrap();
+    assert_eq!(key_b_gpt4.requests, 1);
+    assert_eq!(key_b_gpt4.total_tokens, 300);
+}
+
+#[test]
+fn test_stream_usage_tracker_records_on_drop() {
+    let metrics = Arc::new(MetricsCollector::new());
+
+    let mut tracker = StreamUsageTracker::new(
+        metrics.clone(),
+        "key-a".to_string(),
+        "anthropic".to_string(),
+        "claude-3".to_string(),
+    );
+    // Events may be split across chunks
+    tracker.observe("event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":12,\"output_tokens\":0}}}\n\n");
+    tracker.observe("event: message_delta\ndata: {\"type\":\"message_delta\",\"delta\":{},");
+    tracker.observe("\"usage\":{\"output_tokens\":34}}\n\n");
+    assert!(metrics.get_usage_totals().is_empty());
+
+    drop(tracker);
+
+    let totals = metrics.get_usage_totals();
+    assert_eq!(totals.len(), 1);
+    assert_eq!(totals[0].requests, 1);
+    assert_eq!(totals[0].input_tokens, 12);
+    assert_eq!(totals[0].output_tokens, 34);
+}
+
+#[test]
+fn test_gauge_guards_decrement_on_drop() {
+    let metrics = MetricsCollector::new();
+
+    let request = metrics.track_request();
+    let stream = metrics.track_upstream_stream();
+    assert_eq!(metrics.get_concurrent_requests(), 1);
+    assert_eq!(metrics.get_active_upstream_streams(), 1);
+
+    drop(request);
+    assert_eq!(metrics.get_concurrent_requests(), 0);
+
+    // A guard held by a panicking task still releases the gauge
+    let handle = std::thread::spawn(move || {
+        let _stream = stream;
+        panic!("stream consumer failed");
+    });
+    assert!(handle.join().is_err());
+    assert_eq!(metrics.get_active_upstream_streams(), 0);
+}
+
+#[tokio::test]
+async fn test_gauge_guard_survives_reset() {
+    let metrics = MetricsCollector::new();
+    let stream = metrics.track_upstream_stream();
+    metrics.reset_metrics().await;
+    drop(stream);
+    assert_eq!(metrics.get_active_upstream_streams(), 0);
+    assert_eq!(metrics.get_metrics_summary().await.active_upstream_streams, 0);
+}
+
+#[tokio::test]
+async fn test_retry_and_fallback_counters() {
+    let metrics = MetricsCollector::new();
+    metrics.record_retry_attempt("openai", "error");
+    metrics.record_retry_attempt("openai", "error");
+    metrics.record_retry_attempt("anthropic", "success");
+    metrics.record_fallback("openai", "openrouter");
+
+    let summary = serde_json::to_value(metrics.get_metrics_summary().await).unwrap();
+    assert_eq!(
+        summary["retry_attempts"],
+        serde_json::json!([
+            {"provider": "anthropic", "outcome": "success", "count": 1},
+            {"provider": "openai", "outcome": "error", "count": 2}
+        ])
+    );
+    assert_eq!(
+        summary["fallback_activations"],
+        serde_json::json!([{"from": "openai", "to": "openrouter", "count": 1}])
+    );
+
+    metrics.reset_metrics().await;
+    let summary = metrics.get_metrics_summary().await;
+    assert!(summary.retry_attempts.is_empty());
+    assert!(summary.fallback_activations.is_empty());
+}
+
+#[test]
+fn test_json_file_usage_sink_flush_and_reload() {
+    let path = std::env::temp_dir().join(format!("ai-proxy-usage-{}.json", uuid::Uuid::new_v4()));
+
+    {
+        let sink = JsonFileUsageSink::new(&path).unwrap();
+        sink.record(&usage("key-a", "openai", "gpt-4", 10, 20));
+        sink.record(&usage("key-a", "openai", "gpt-4", 1, 2));
+        sink.flush().unwrap();
+    }
+
+    let reloaded = JsonFileUsageSink::new(&path).unwrap();
+    reloaded.record(&usage("key-a", "openai", "gpt-4", 4, 8));
+    let totals = reloaded.totals();
+    assert_eq!(totals.len(), 1);
+    assert_eq!(totals[0].requests, 3);
+    assert_eq!(totals[0].input_tokens, 15);
+    assert_eq!(totals[0].output_tokens, 30);
+
+    drop(reloaded);
+    let _ = std::fs::remove_file(&path);
+}
@@ -1,6 +1,6 @@
 use ai_proxy::{
     config::{
-        Config, LoggingConfig, PerformanceConfig, ProviderDetail, SecurityConfig, ServerConfig,
+        Config, LoggingConfig, PerformanceConfig, ProviderDetail, SecurityConfig, ServerConfig, SharedConfig,
     },
     metrics::MetricsCollector,
     middleware::{
@@ -36,6 +36,7 @@ fn create_test_app_state() -> AppState {
             max_retries: 3,
             enabled: true,
             rate_limit: None,
+            ..Default::default()
         },
     );
 
@@ -45,11 +46,13 @@ fn create_test_app_state() -> AppState {
             port: 3000,
             request_timeout_seconds: 30,
             max_request_size_bytes: 1024 * 1024,
+            ..Default::default()
         },
         providers,
         logging: LoggingConfig::default(),
         security: SecurityConfig::default(),
         performance: PerformanceConfig::default(),
+        ..Default::default()
     };
 
     let http_client = Client::new();
@@ -59,10 +62,16 @@ fn create_test_app_state() -> AppState {
     let metrics = Arc::new(MetricsCollector::new());
 
     AppState {
-        config: Arc::new(config),
+        config: Arc::new(SharedConfig::new(config)),
         http_client,
         provider_registry,
         metrics,
+        health_cache: Default::default(),
+        coalescer: Default::default(),
+        idempotency: Default::default(),
+        active_streams: Default::default(),
+        redactor: Default::default(),
+        transforms: Vec::new(),
     }
 }
 
@@ -18,6 +18,7 @@ fn create_test_config() -> Config {
             max_retries: 3,
             enabled: true,
             rate_limit: None,
+            ..Default::default()
         },
     );
     providers.insert(
@@ -33,6 +34,7 @@ fn create_test_config() -> Config {
             max_retries: 3,
             enabled: true,
             rate_limit: None,
+            ..Default::default()
         },
     );
 
@@ -42,11 +44,13 @@ fn create_test_config() -> Config {
             port: 3000,
             request_timeout_seconds: 30,
             max_request_size_bytes: 1024 * 1024,
+            ..Default::default()
         },
         providers,
         logging: LoggingConfig::default(),
         security: SecurityConfig::default(),
         performance: PerformanceConfig::default(),
+        ..Default::default()
     }
 }
 
@@ -188,6 +192,8 @@ fn test_model_info_creation() {
         object: "model".to_string(),
         created: 1234567890,
         owned_by: "test-provider".to_string(),
+        context_length: None,
+        provider: None,
     };
 
     assert_eq!(model.id, "test-model");
@@ -228,6 +234,8 @@ fn test_model_info_serialization() {
         object: "model".to_string(),
         created: 1234567890,
         owned_by: "test-provider".to_string(),
+        context_length: None,
+        provider: None,
     };
 
     let serialized = serde_json::to_string(&model);
@@ -255,3 +263,28 @@ fn test_health_status_serialization() {
     assert!(json.contains("test-provider"));
     assert!(json.contains("150"));
 }
+
+#[tokio::test]
+async fn test_provider_registry_honors_model_routes() {
+    let mut config = create_test_config();
+    config.providers.insert(
+        "openai-backup".to_string(),
+        ProviderDetail {
+            api_key: "test-backup-key-1234567890".to_string(),
+            api_base: "https://backup.example.com/v1/".to_string(),
+            models: Some(vec!["gpt-4".to_string()]),
+            ..Default::default()
+        },
+    );
+    config
+        .model_routes
+        .insert("gpt-4".to_string(), "openai-backup".to_string());
+    assert!(config.validate().is_ok());
+
+    let registry = ProviderRegistry::new(&config, reqwest::Client::new()).unwrap();
+
+    // gpt-4 always resolves to the routed provider, leaving openai with gpt-3.5-turbo only
+    let stats = registry.get_model_stats();
+    assert_eq!(stats.get("openai-backup"), Some(&1));
+    assert_eq!(stats.get("openai"), Some(&1));
+}
@@ -1,12 +1,17 @@
 use ai_proxy::{
-    config::ProviderDetail,
+    config::{OverLimitBehavior, ProviderDetail},
     providers::{
-        AIProvider,
+        AIProvider, truncate_body,
         anthropic::{AnthropicProvider, AnthropicRequest, Message},
     },
     errors::AppError,
 };
 use reqwest::Client;
+use serde_json::json;
+use wiremock::{
+    matchers::{body_partial_json, header, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
 
 /// Create a test Anthropic provider instance
 fn create_test_provider() -> AnthropicProvider {
@@ -21,6 +26,7 @@ fn create_test_provider() -> AnthropicProvider {
         max_retries: 3,
         enabled: true,
         rate_limit: None,
+        ..Default::default()
     };
     
     let client = Client::new();
@@ -37,10 +43,11 @@ fn create_test_request() -> AnthropicRequest {
                 content: "Hello, how are you?".to_string(),
             }
         ],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: Some(false),
         temperature: Some(0.7),
         top_p: Some(0.9),
+        ..Default::default()
     }
 }
 
@@ -76,10 +83,11 @@ async fn test_model_validation() {
     let valid_request = AnthropicRequest {
         model: "claude-3-haiku-20240307".to_string(),
         messages: vec![Message::user("Hello".to_string())],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: Some(false),
         temperature: Some(0.7),
         top_p: Some(0.9),
+        ..Default::default()
     };
     
     assert!(valid_request.validate().is_ok());
@@ -88,10 +96,11 @@ async fn test_model_validation() {
     let invalid_request = AnthropicRequest {
         model: "gpt-4".to_string(),
         messages: vec![Message::user("Hello".to_string())],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: Some(false),
         temperature: Some(0.7),
         top_p: Some(0.9),
+        ..Default::default()
     };
     
     // The request itself validates, but the provider would reject the model
@@ -127,6 +136,9 @@ async fn test_error_handling() {
     let provider_error = AppError::ProviderError {
         status: 500,
         message: "Test provider error".to_string(),
+        error_type: None,
+        error_code: None,
+        retry_after: None,
     };
     assert!(matches!(provider_error, AppError::ProviderError { .. }));
     
@@ -226,9 +238,9 @@ async fn test_comprehensive_request_validation() {
     request.model = "claude-3-haiku-20240307".to_string();
     
     // Test invalid max_tokens
-    request.max_tokens = 0;
+    request.max_tokens = Some(0);
     assert!(request.validate().is_err());
-    request.max_tokens = 100;
+    request.max_tokens = Some(100);
     
     // Test invalid temperature
     request.temperature = Some(-1.0);
@@ -266,6 +278,7 @@ async fn test_real_api_integration() {
         max_retries: 3,
         enabled: true,
         rate_limit: None,
+        ..Default::default()
     };
     
     let client = Client::new();
@@ -284,4 +297,610 @@ async fn test_real_api_integration() {
     let response = provider.chat(request).await.unwrap();
     assert!(!response.content.is_empty());
     assert!(response.usage.input_tokens > 0);
-}
\ No newline at end of file
+}
+
+/// Create an Anthropic provider pointed at a mock server
+fn create_mock_provider(mock_server: &MockServer) -> AnthropicProvider {
+    let config = ProviderDetail {
+        api_key: "test-key-1234567890".to_string(),
+        api_base: format!("{}/v1/", mock_server.uri()),
+        models: Some(vec!["claude-3-haiku-20240307".to_string()]),
+        ..Default::default()
+    };
+
+    AnthropicProvider::new(config, Client::new())
+}
+
+#[tokio::test]
+async fn test_health_check_uses_models_endpoint() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"data": []})))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    // The token-consuming chat probe must not be used when models is available
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&mock_server)
+        .await;
+
+    let provider = create_mock_provider(&mock_server);
+    let health = provider.health_check().await.unwrap();
+
+    assert_eq!(health.status, "healthy");
+    assert!(health.error.is_none());
+}
+
+#[tokio::test]
+async fn test_health_check_falls_back_to_count_tokens() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages/count_tokens"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"input_tokens": 3})))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = create_mock_provider(&mock_server);
+    let health = provider.health_check().await.unwrap();
+
+    assert_eq!(health.status, "healthy");
+}
+
+#[tokio::test]
+async fn test_health_check_unauthorized_is_unhealthy() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+            "type": "error",
+            "error": {"type": "authentication_error", "message": "invalid x-api-key"}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let provider = create_mock_provider(&mock_server);
+    let health = provider.health_check().await.unwrap();
+
+    assert_eq!(health.status, "unhealthy");
+    assert!(health.error.unwrap().contains("Authentication failed"));
+}
+
+#[tokio::test]
+async fn test_health_check_rate_limited_is_degraded() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .respond_with(ResponseTemplate::new(429).set_body_json(json!({
+            "type": "error",
+            "error": {"type": "rate_limit_error", "message": "slow down"}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let provider = create_mock_provider(&mock_server);
+    let health = provider.health_check().await.unwrap();
+
+    assert_eq!(health.status, "degraded");
+}
+
+#[tokio::test]
+async fn test_chat_rejects_multiple_completions() {
+    let mock_server = MockServer::start().await;
+    let provider = create_mock_provider(&mock_server);
+
+    let mut request = create_test_request();
+    request.n = Some(2);
+
+    match provider.chat(request).await {
+        Err(AppError::ValidationError(message)) => {
+            assert!(message.contains("does not support multiple completions"));
+        }
+        other => panic!("Expected validation error, got {:?}", other),
+    }
+
+    // Nothing reached the upstream API
+    assert!(mock_server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_chat_rejects_logprobs() {
+    let mock_server = MockServer::start().await;
+    let provider = create_mock_provider(&mock_server);
+
+    let mut request = create_test_request();
+    request.logprobs = Some(true);
+
+    match provider.chat(request).await {
+        Err(AppError::ValidationError(message)) => {
+            assert!(message.contains("does not support logprobs"));
+        }
+        other => panic!("Expected validation error, got {:?}", other),
+    }
+    assert!(mock_server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_chat_json_mode_becomes_system_instruction() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_json",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "{\"ok\":true}"}],
+            "model": "claude-3-haiku-20240307",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 12, "output_tokens": 4}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let provider = create_mock_provider(&mock_server);
+    let mut request = create_test_request();
+    request.system = Some("You are terse.".to_string());
+    request.response_format = Some(json!({"type": "json_object"}));
+
+    provider.chat(request).await.unwrap();
+
+    let received = mock_server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&received[0].body).unwrap();
+    let system = body["system"].as_str().unwrap();
+    assert!(system.starts_with("You are terse."));
+    assert!(system.contains("valid JSON object"));
+    assert!(body.get("response_format").is_none());
+}
+
+#[tokio::test]
+async fn test_connect_and_read_timeouts_are_distinguishable() {
+    use ai_proxy::config::NetworkConfig;
+    use ai_proxy::server::build_http_client_with;
+    use std::time::{Duration, Instant};
+
+    // A listener with a zero backlog whose single queue slot is taken: further
+    // connection attempts hang in the handshake until the connect timeout
+    let socket = tokio::net::TcpSocket::new_v4().unwrap();
+    socket.bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let address = socket.local_addr().unwrap();
+    let _listener = socket.listen(0).unwrap();
+    let _queued = std::net::TcpStream::connect(address).unwrap();
+
+    let config = ProviderDetail {
+        api_key: "test-key-1234567890".to_string(),
+        api_base: format!("http://{}/v1/", address),
+        models: Some(vec!["claude-3-haiku-20240307".to_string()]),
+        timeout_seconds: 30,
+        connect_timeout_seconds: Some(1),
+        ..Default::default()
+    };
+    let client = build_http_client_with(&NetworkConfig::default(), Some(Duration::from_secs(1)), true).unwrap();
+    let provider = AnthropicProvider::new(config, client);
+
+    let started = Instant::now();
+    match provider.chat(create_test_request()).await {
+        Err(AppError::NetworkError(message)) => assert!(message.contains("connect timed out"), "{}", message),
+        other => panic!("Expected connect timeout, got {:?}", other),
+    }
+    assert!(started.elapsed() < Duration::from_secs(10));
+
+    // The connection succeeds but the response takes longer than the read timeout
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+        .mount(&mock_server)
+        .await;
+    let config = ProviderDetail {
+        api_key: "test-key-1234567890".to_string(),
+        api_base: format!("{}/v1/", mock_server.uri()),
+        models: Some(vec!["claude-3-haiku-20240307".to_string()]),
+        timeout_seconds: 30,
+        connect_timeout_seconds: Some(1),
+        read_timeout_seconds: Some(1),
+        ..Default::default()
+    };
+    let client = build_http_client_with(&NetworkConfig::default(), Some(Duration::from_secs(1)), true).unwrap();
+    let provider = AnthropicProvider::new(config, client);
+
+    match provider.chat(create_test_request()).await {
+        Err(AppError::GatewayTimeout(message)) => assert!(message.contains("request timed out"), "{}", message),
+        other => panic!("Expected read timeout, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_configured_anthropic_version_header_is_sent() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(header("anthropic-version", "2024-10-22"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_version",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Hi"}],
+            "model": "claude-3-haiku-20240307",
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 5, "output_tokens": 1}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .and(header("anthropic-version", "2024-10-22"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"data": []})))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let config = ProviderDetail {
+        api_key: "test-key-1234567890".to_string(),
+        api_base: format!("{}/v1/", mock_server.uri()),
+        models: Some(vec!["claude-3-haiku-20240307".to_string()]),
+        anthropic_version: Some("2024-10-22".to_string()),
+        ..Default::default()
+    };
+    let provider = AnthropicProvider::new(config, Client::new());
+
+    provider.chat(create_test_request()).await.unwrap();
+    assert_eq!(provider.health_check().await.unwrap().status, "healthy");
+}
+
+#[tokio::test]
+async fn test_default_anthropic_version_header() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .and(header("anthropic-version", "2023-06-01"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"data": []})))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = create_mock_provider(&mock_server);
+    assert_eq!(provider.health_check().await.unwrap().status, "healthy");
+}
+
+#[tokio::test]
+async fn test_configured_anthropic_beta_header_is_sent() {
+    let beta = "prompt-caching-2024-07-31,output-128k-2025-02-19";
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(body_partial_json(json!({"stream": true})))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            "event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n",
+            "text/event-stream",
+        ))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_beta",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Hi"}],
+            "model": "claude-3-haiku-20240307",
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 5, "output_tokens": 1}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let config = ProviderDetail {
+        api_key: "test-key-1234567890".to_string(),
+        api_base: format!("{}/v1/", mock_server.uri()),
+        models: Some(vec!["claude-3-haiku-20240307".to_string()]),
+        anthropic_beta: Some(vec![
+            "prompt-caching-2024-07-31".to_string(),
+            "output-128k-2025-02-19".to_string(),
+        ]),
+        ..Default::default()
+    };
+    let provider = AnthropicProvider::new(config, Client::new());
+
+    provider.chat(create_test_request()).await.unwrap();
+    let mut request = create_test_request();
+    request.stream = Some(true);
+    provider.chat_stream(request).await.unwrap();
+
+    // Both chat and chat_stream carry the comma-joined header
+    let requests = mock_server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 2);
+    for request in &requests {
+        assert_eq!(request.headers.get("anthropic-beta").unwrap(), beta);
+    }
+}
+
+#[tokio::test]
+async fn test_anthropic_beta_header_absent_by_default() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_no_beta",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Hi"}],
+            "model": "claude-3-haiku-20240307",
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 5, "output_tokens": 1}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let provider = create_mock_provider(&mock_server);
+    provider.chat(create_test_request()).await.unwrap();
+
+    let requests = mock_server.received_requests().await.unwrap();
+    assert!(requests[0].headers.get("anthropic-beta").is_none());
+}
+
+#[tokio::test]
+async fn test_chat_refusal_stop_reason_is_flagged() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_refusal",
+            "type": "message",
+            "role": "assistant",
+            "content": [],
+            "model": "claude-3-haiku-20240307",
+            "stop_reason": "refusal",
+            "usage": {"input_tokens": 9, "output_tokens": 0}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let provider = create_mock_provider(&mock_server);
+    let response = provider.chat(create_test_request()).await.unwrap();
+
+    assert!(response.refused);
+    assert_eq!(response.stop_reason.as_deref(), Some("refusal"));
+    assert_eq!(response.refusal_reason.as_deref(), Some("Model declined to respond"));
+}
+
+#[test]
+fn test_truncate_body_for_logs() {
+    assert_eq!(truncate_body("short", 10), "short");
+
+    let page = format!("<html>{}</html>", "x".repeat(10_000));
+    let logged = truncate_body(&page, 64);
+    assert!(logged.starts_with("<html>xxx"));
+    assert!(logged.ends_with(&format!("… ({} more bytes)", page.len() - 64)));
+    assert!(logged.len() < 100);
+
+    // Never splits a multi-byte character
+    assert_eq!(truncate_body("héllo", 2), "h… (5 more bytes)");
+}
+
+#[tokio::test]
+async fn test_large_error_body_is_bounded_in_returned_error() {
+    let mock_server = MockServer::start().await;
+    let page = format!("<html><body>{}</body></html>", "Internal Server Error ".repeat(50_000));
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(500).set_body_string(page.clone()))
+        .mount(&mock_server)
+        .await;
+
+    let config = ProviderDetail {
+        api_key: "test-key-1234567890".to_string(),
+        api_base: format!("{}/v1/", mock_server.uri()),
+        models: Some(vec!["claude-3-haiku-20240307".to_string()]),
+        max_log_body_bytes: Some(256),
+        max_error_body_bytes: Some(1024),
+        ..Default::default()
+    };
+    let provider = AnthropicProvider::new(config, Client::new());
+
+    match provider.chat(create_test_request()).await {
+        Err(AppError::ProviderError { status, message, .. }) => {
+            assert_eq!(status, 500);
+            assert!(message.len() < 1024 + 100, "message was {} bytes", message.len());
+            assert!(message.starts_with("Anthropic API: Server error - <html><body>Internal Server Error"));
+            assert!(message.contains(&format!("({} more bytes)", page.len() - 1024)));
+        }
+        other => panic!("expected ProviderError, got {:?}", other.map(|r| r.id)),
+    }
+}
+
+/// Create an Anthropic provider with a 4096 output token cap
+fn create_capped_provider(mock_server: &MockServer, over_limit_behavior: OverLimitBehavior) -> AnthropicProvider {
+    let config = ProviderDetail {
+        api_key: "test-key-1234567890".to_string(),
+        api_base: format!("{}/v1/", mock_server.uri()),
+        models: Some(vec!["claude-3-haiku-20240307".to_string()]),
+        max_output_tokens: Some(4096),
+        over_limit_behavior,
+        ..Default::default()
+    };
+
+    AnthropicProvider::new(config, Client::new())
+}
+
+#[tokio::test]
+async fn test_max_output_tokens_clamps_request() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(body_partial_json(json!({"max_tokens": 4096})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_clamped",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "ok"}],
+            "model": "claude-3-haiku-20240307",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 12, "output_tokens": 1}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = create_capped_provider(&mock_server, OverLimitBehavior::Clamp);
+    let mut request = create_test_request();
+    request.max_tokens = Some(8000);
+
+    let response = provider.chat(request).await.unwrap();
+    assert_eq!(response.id, "msg_clamped");
+}
+
+#[tokio::test]
+async fn test_max_output_tokens_rejects_request() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&mock_server)
+        .await;
+
+    let provider = create_capped_provider(&mock_server, OverLimitBehavior::Reject);
+    let mut request = create_test_request();
+    request.max_tokens = Some(8000);
+
+    match provider.chat(request).await {
+        Err(AppError::ValidationError(message)) => {
+            assert!(message.contains("8000"));
+            assert!(message.contains("4096"));
+        }
+        other => panic!("Expected a validation error, got {:?}", other.map(|r| r.id)),
+    }
+}
+
+#[tokio::test]
+async fn test_max_output_tokens_replaces_default_cap() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(body_partial_json(json!({"max_tokens": 16000})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_large",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "ok"}],
+            "model": "claude-3-haiku-20240307",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 12, "output_tokens": 1}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    // A provider cap above 8192 lets larger requests through
+    let config = ProviderDetail {
+        api_key: "test-key-1234567890".to_string(),
+        api_base: format!("{}/v1/", mock_server.uri()),
+        models: Some(vec!["claude-3-haiku-20240307".to_string()]),
+        max_output_tokens: Some(64000),
+        ..Default::default()
+    };
+    let provider = AnthropicProvider::new(config, Client::new());
+    let mut request = create_test_request();
+    request.max_tokens = Some(16000);
+
+    provider.chat(request).await.unwrap();
+}
+
+/// Collect the SSE event names of a provider stream, failing if it never ends
+async fn collect_stream_events(provider: &AnthropicProvider, request: AnthropicRequest) -> Vec<String> {
+    use futures::StreamExt;
+
+    let stream = provider.chat_stream(request).await.unwrap();
+    let chunks: Vec<String> = tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        stream.map(|chunk| chunk.unwrap()).collect(),
+    )
+    .await
+    .expect("stream did not terminate");
+    chunks
+        .concat()
+        .lines()
+        .filter_map(|line| line.strip_prefix("event: ").map(str::to_string))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_chat_stream_closes_empty_upstream_body() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw("", "text/event-stream"))
+        .mount(&mock_server)
+        .await;
+
+    let provider = create_mock_provider(&mock_server);
+    let request = AnthropicRequest {
+        model: "claude-3-haiku-20240307".to_string(),
+        messages: vec![Message::user("Hello".to_string())],
+        stream: Some(true),
+        ..Default::default()
+    };
+    let events = collect_stream_events(&provider, request).await;
+
+    assert_eq!(
+        events,
+        vec![
+            "message_start",
+            "content_block_start",
+            "content_block_stop",
+            "message_delta",
+            "message_stop",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_chat_stream_closes_truncated_upstream_body() {
+    let mock_server = MockServer::start().await;
+    let body = concat!(
+        "event: message_start\n",
+        "data: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_1\",\"model\":\"claude-3-haiku-20240307\",\"role\":\"assistant\",\"content\":[],\"usage\":{\"input_tokens\":3,\"output_tokens\":0}}}\n\n",
+        "event: content_block_start\n",
+        "data: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n",
+        "event: content_block_delta\n",
+        "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hi\"}}\n\n",
+    );
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+        .mount(&mock_server)
+        .await;
+
+    let provider = create_mock_provider(&mock_server);
+    let request = AnthropicRequest {
+        model: "claude-3-haiku-20240307".to_string(),
+        messages: vec![Message::user("Hello".to_string())],
+        stream: Some(true),
+        ..Default::default()
+    };
+    let events = collect_stream_events(&provider, request).await;
+
+    // Only the missing closing events are appended, never a second message_start
+    assert_eq!(
+        events,
+        vec![
+            "message_start",
+            "content_block_start",
+            "content_block_delta",
+            "content_block_stop",
+            "message_delta",
+            "message_stop",
+        ]
+    );
+}
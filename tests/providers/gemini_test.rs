@@ -13,10 +13,11 @@ fn test_gemini_request_from_anthropic() {
             Message::user("Hello".to_string()),
             Message::assistant("Hi there!".to_string()),
         ],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: Some(false),
         temperature: Some(0.7),
         top_p: Some(0.9),
+        ..Default::default()
     };
 
     let gemini_request = GeminiRequest::from_anthropic(&anthropic_request).unwrap();
@@ -39,10 +40,11 @@ fn test_gemini_request_invalid_role() {
             role: "system".to_string(),
             content: "You are a helpful assistant".to_string(),
         }],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: None,
         top_p: None,
+        ..Default::default()
     };
 
     let result = GeminiRequest::from_anthropic(&anthropic_request);
@@ -63,6 +65,7 @@ fn test_gemini_response_to_anthropic() {
                 role: "model".to_string(),
                 parts: vec![GeminiPart {
                     text: "Hello! How can I help you?".to_string(),
+                    function_call: None,
                 }],
             },
             finish_reason: Some("STOP".to_string()),
@@ -74,6 +77,7 @@ fn test_gemini_response_to_anthropic() {
             prompt_token_count: Some(10),
             candidates_token_count: Some(15),
             total_token_count: Some(25),
+            thoughts_token_count: None,
         }),
         prompt_feedback: None,
         error: None,
@@ -118,6 +122,7 @@ fn test_gemini_stream_response_to_events() {
                 role: "model".to_string(),
                 parts: vec![GeminiPart {
                     text: "Hello".to_string(),
+                    function_call: None,
                 }],
             }),
             finish_reason: Some("STOP".to_string()),
@@ -127,7 +132,9 @@ fn test_gemini_stream_response_to_events() {
             prompt_token_count: Some(5),
             candidates_token_count: Some(10),
             total_token_count: Some(15),
+            thoughts_token_count: None,
         }),
+        prompt_feedback: None,
     };
 
     let events = stream_response
@@ -158,12 +165,13 @@ fn test_gemini_stream_response_to_events() {
 
 #[test]
 fn test_create_message_start_event() {
-    let event = GeminiStreamResponse::create_message_start_event("gemini-pro", "msg_123");
+    let event = GeminiStreamResponse::create_message_start_event("gemini-pro", "msg_123", 12);
 
     if let AnthropicStreamEvent::MessageStart { message } = event {
         assert_eq!(message.id, "msg_123");
         assert_eq!(message.model, "gemini-pro");
         assert_eq!(message.role, "assistant");
+        assert_eq!(message.usage.input_tokens, 12);
     } else {
         panic!("Expected MessageStart event");
     }
@@ -223,6 +231,7 @@ async fn test_gemini_provider_chat_success() {
         max_retries: 3,
         rate_limit: None,
         timeout_seconds: 60,
+        ..Default::default()
     };
 
     // Create provider instance
@@ -233,10 +242,11 @@ async fn test_gemini_provider_chat_success() {
     let request = AnthropicRequest {
         model: "gemini-pro".to_string(),
         messages: vec![Message::user("Hello".to_string())],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: Some(false),
         temperature: Some(0.7),
         top_p: Some(0.9),
+        ..Default::default()
     };
 
     // Test the chat method
@@ -277,6 +287,7 @@ async fn test_gemini_provider_chat_api_error() {
         max_retries: 3,
         rate_limit: None,
         timeout_seconds: 60,
+        ..Default::default()
     };
 
     // Create provider instance
@@ -287,10 +298,11 @@ async fn test_gemini_provider_chat_api_error() {
     let request = AnthropicRequest {
         model: "gemini-pro".to_string(),
         messages: vec![Message::user("Hello".to_string())],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: None,
         top_p: None,
+        ..Default::default()
     };
 
     // Test the chat method - should return error
@@ -313,6 +325,7 @@ async fn test_gemini_provider_chat_validation_error() {
         max_retries: 3,
         rate_limit: None,
         timeout_seconds: 60,
+        ..Default::default()
     };
 
     // Create provider instance
@@ -323,10 +336,11 @@ async fn test_gemini_provider_chat_validation_error() {
     let request = AnthropicRequest {
         model: "gemini-pro".to_string(),
         messages: vec![], // Invalid: empty messages
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: None,
         top_p: None,
+        ..Default::default()
     };
 
     // Test the chat method - should return validation error
@@ -349,6 +363,7 @@ async fn test_gemini_provider_chat_conversion_error() {
         max_retries: 3,
         rate_limit: None,
         timeout_seconds: 60,
+        ..Default::default()
     };
 
     // Create provider instance
@@ -362,10 +377,11 @@ async fn test_gemini_provider_chat_conversion_error() {
             role: "system".to_string(), // Invalid for Gemini
             content: "You are a helpful assistant".to_string(),
         }],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: None,
         top_p: None,
+        ..Default::default()
     };
 
     // Test the chat method - should return conversion error
@@ -426,6 +442,7 @@ fn test_gemini_safety_info_logging() {
                 role: "model".to_string(),
                 parts: vec![GeminiPart {
                     text: "Safe content".to_string(),
+                    function_call: None,
                 }],
             },
             finish_reason: Some("STOP".to_string()),
@@ -441,6 +458,7 @@ fn test_gemini_safety_info_logging() {
             prompt_token_count: Some(10),
             candidates_token_count: Some(5),
             total_token_count: Some(15),
+            thoughts_token_count: None,
         }),
         prompt_feedback: None,
     };
@@ -512,6 +530,7 @@ fn test_gemini_utils_extract_text_content() {
                 role: "model".to_string(),
                 parts: vec![GeminiPart {
                     text: "Extracted content".to_string(),
+                    function_call: None,
                 }],
             },
             finish_reason: Some("STOP".to_string()),
@@ -536,6 +555,7 @@ fn test_gemini_has_high_risk_safety_rating() {
                 role: "model".to_string(),
                 parts: vec![GeminiPart {
                     text: "Content".to_string(),
+                    function_call: None,
                 }],
             },
             finish_reason: Some("STOP".to_string()),
@@ -566,6 +586,7 @@ async fn test_gemini_provider_chat_network_error() {
         max_retries: 3,
         rate_limit: None,
         timeout_seconds: 60,
+        ..Default::default()
     };
 
     // Create provider instance
@@ -576,10 +597,11 @@ async fn test_gemini_provider_chat_network_error() {
     let request = AnthropicRequest {
         model: "gemini-pro".to_string(),
         messages: vec![Message::user("Hello".to_string())],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: None,
         temperature: None,
         top_p: None,
+        ..Default::default()
     };
 
     // Test the chat method - should return network error
@@ -635,6 +657,7 @@ async fn test_gemini_provider_list_models_success() {
         max_retries: 3,
         rate_limit: None,
         timeout_seconds: 60,
+        ..Default::default()
     };
 
     // Create provider instance
@@ -684,6 +707,7 @@ async fn test_gemini_provider_list_models_api_error_fallback() {
         max_retries: 3,
         rate_limit: None,
         timeout_seconds: 60,
+        ..Default::default()
     };
 
     // Create provider instance
@@ -729,6 +753,7 @@ async fn test_gemini_provider_list_models_no_config_fallback() {
         max_retries: 3,
         rate_limit: None,
         timeout_seconds: 60,
+        ..Default::default()
     };
 
     // Create provider instance
@@ -775,6 +800,7 @@ async fn test_gemini_provider_health_check_success() {
         max_retries: 3,
         rate_limit: None,
         timeout_seconds: 60,
+        ..Default::default()
     };
 
     // Create provider instance
@@ -819,6 +845,7 @@ async fn test_gemini_provider_health_check_api_error() {
         max_retries: 3,
         rate_limit: None,
         timeout_seconds: 60,
+        ..Default::default()
     };
 
     // Create provider instance
@@ -847,6 +874,7 @@ async fn test_gemini_provider_health_check_network_error() {
         max_retries: 3,
         rate_limit: None,
         timeout_seconds: 60,
+        ..Default::default()
     };
 
     // Create provider instance
@@ -870,3 +898,366 @@ async fn test_gemini_provider_health_check_network_error() {
             || error_msg.contains("HTTP")
     );
 }
+
+#[tokio::test]
+async fn test_gemini_provider_embeddings_success() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path_regex(r"/models/text-embedding-004:embedContent"))
+        .and(query_param("key", "test-api-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "embedding": {"values": [0.25, -0.5, 0.75]}
+        })))
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+
+    let config = ProviderDetail {
+        api_key: "test-api-key".to_string(),
+        api_base: mock_server.uri(),
+        models: Some(vec!["text-embedding-004".to_string()]),
+        enabled: true,
+        timeout_seconds: 60,
+        ..Default::default()
+    };
+    let provider = GeminiProvider::new(config, Client::new());
+
+    let response = provider
+        .embeddings(vec!["one".to_string(), "two".to_string()], "text-embedding-004")
+        .await
+        .unwrap();
+
+    assert_eq!(response.model, "text-embedding-004");
+    assert_eq!(response.data.len(), 2);
+    assert_eq!(response.data[1].index, 1);
+    assert_eq!(response.data[0].embedding, vec![0.25, -0.5, 0.75]);
+    assert_eq!(response.usage.prompt_tokens, 0);
+}
+
+#[test]
+fn test_gemini_stream_safety_finish_emits_error_event() {
+    let chunk: GeminiStreamResponse = serde_json::from_value(json!({
+        "candidates": [{"finishReason": "SAFETY", "index": 0}]
+    }))
+    .unwrap();
+    assert!(chunk.has_streaming_issues());
+
+    let events = chunk.to_anthropic_events("gemini-pro", "msg_123").unwrap();
+    assert_eq!(events.len(), 1);
+    match &events[0] {
+        AnthropicStreamEvent::Error { error } => {
+            assert_eq!(error.error_type, "safety_error");
+            assert!(error.message.contains("safety"));
+        }
+        other => panic!("Expected error event, got {:?}", other),
+    }
+
+    let blocked: GeminiStreamResponse = serde_json::from_value(json!({
+        "promptFeedback": {"blockReason": "SAFETY"}
+    }))
+    .unwrap();
+    let events = blocked.to_anthropic_events("gemini-pro", "msg_123").unwrap();
+    assert!(matches!(&events[..], [AnthropicStreamEvent::Error { error }] if error.message.contains("Prompt blocked")));
+}
+
+#[tokio::test]
+async fn test_gemini_provider_stream_ends_with_safety_error() {
+    use futures::StreamExt;
+
+    let mock_server = MockServer::start().await;
+    let body = concat!(
+        "{\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"text\":\"Partial\"}]},\"index\":0}]}\n",
+        "{\"candidates\":[{\"finishReason\":\"SAFETY\",\"index\":0}]}\n",
+    );
+    Mock::given(method("POST"))
+        .and(path_regex(r"/gemini-pro:streamGenerateContent"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(body))
+        .mount(&mock_server)
+        .await;
+
+    let config = ProviderDetail {
+        api_key: "test-api-key".to_string(),
+        api_base: mock_server.uri(),
+        models: Some(vec!["gemini-pro".to_string()]),
+        ..Default::default()
+    };
+    let provider = GeminiProvider::new(config, Client::new());
+
+    let request = AnthropicRequest {
+        model: "gemini-pro".to_string(),
+        messages: vec![Message::user("Hello".to_string())],
+        stream: Some(true),
+        ..Default::default()
+    };
+    let output: Vec<String> = provider
+        .chat_stream(request)
+        .await
+        .unwrap()
+        .map(|chunk| chunk.unwrap())
+        .collect()
+        .await;
+    let output = output.concat();
+
+    assert!(output.contains("Partial"));
+    assert!(output.contains("event: error"));
+    assert!(output.contains("safety_error"));
+    assert!(!output.contains("event: message_stop"));
+}
+
+#[tokio::test]
+async fn test_gemini_provider_stream_parses_sse_frames() {
+    use futures::StreamExt;
+
+    let mock_server = MockServer::start().await;
+    let body = concat!(
+        "data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"text\":\"Hello\"}]},\"index\":0}]}\r\n\r\n",
+        "data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"text\":\", \"}]},\"index\":0}]}\r\n\r\n",
+        "data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"text\":\"world\"}]},\"finishReason\":\"STOP\",\"index\":0}],",
+        "\"usageMetadata\":{\"promptTokenCount\":4,\"candidatesTokenCount\":3,\"totalTokenCount\":7}}\r\n\r\n",
+    );
+    Mock::given(method("POST"))
+        .and(path_regex(r"/gemini-pro:streamGenerateContent"))
+        .and(query_param("alt", "sse"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let config = ProviderDetail {
+        api_key: "test-api-key".to_string(),
+        api_base: mock_server.uri(),
+        models: Some(vec!["gemini-pro".to_string()]),
+        ..Default::default()
+    };
+    let provider = GeminiProvider::new(config, Client::new());
+
+    let request = AnthropicRequest {
+        model: "gemini-pro".to_string(),
+        messages: vec![Message::user("Hello".to_string())],
+        stream: Some(true),
+        ..Default::default()
+    };
+    let output: Vec<String> = provider
+        .chat_stream(request)
+        .await
+        .unwrap()
+        .map(|chunk| chunk.unwrap())
+        .collect()
+        .await;
+    let output = output.concat();
+
+    let events: Vec<(&str, serde_json::Value)> = output
+        .split("\n\n")
+        .filter_map(|frame| {
+            let name = frame.lines().find_map(|l| l.strip_prefix("event: "))?;
+            let data = frame.lines().find_map(|l| l.strip_prefix("data: "))?;
+            Some((name, serde_json::from_str(data).unwrap()))
+        })
+        .collect();
+    let names: Vec<&str> = events.iter().map(|(name, _)| *name).collect();
+    assert_eq!(
+        names,
+        vec![
+            "message_start",
+            "content_block_start",
+            "content_block_delta",
+            "content_block_delta",
+            "content_block_delta",
+            "message_delta",
+            "content_block_stop",
+            "message_stop",
+        ]
+    );
+
+    let deltas: Vec<&str> = events
+        .iter()
+        .filter(|(name, _)| *name == "content_block_delta")
+        .map(|(_, data)| data["delta"]["text"].as_str().unwrap())
+        .collect();
+    assert_eq!(deltas, vec!["Hello", ", ", "world"]);
+
+    let (_, message_delta) = &events[5];
+    assert_eq!(message_delta["delta"]["stop_reason"], "end_turn");
+    assert_eq!(message_delta["delta"]["usage"]["input_tokens"], 4);
+    assert_eq!(message_delta["delta"]["usage"]["output_tokens"], 3);
+}
+
+#[test]
+fn test_gemini_response_safety_block_is_flagged_as_refusal() {
+    let gemini_response: GeminiResponse = serde_json::from_value(json!({
+        "candidates": [{
+            "content": {"role": "model", "parts": []},
+            "finishReason": "SAFETY",
+            "index": 0,
+            "safety_ratings": [{
+                "category": "HARASSMENT",
+                "probability": "MEDIUM"
+            }]
+        }]
+    }))
+    .unwrap();
+
+    let response = gemini_response.to_anthropic("gemini-pro").unwrap();
+    assert!(response.refused);
+    assert!(response.content.is_empty());
+    assert_eq!(response.stop_reason.as_deref(), Some("content_filter"));
+    let reason = response.refusal_reason.unwrap();
+    assert!(reason.contains("finishReason: SAFETY"));
+    assert!(reason.contains("Harassment"));
+}
+
+#[test]
+fn test_gemini_response_blocked_prompt_is_flagged_as_refusal() {
+    let gemini_response: GeminiResponse = serde_json::from_value(json!({
+        "candidates": [],
+        "promptFeedback": {"blockReason": "SAFETY"},
+        "usageMetadata": {"promptTokenCount": 7}
+    }))
+    .unwrap();
+
+    let response = gemini_response.to_anthropic("gemini-pro").unwrap();
+    assert!(response.refused);
+    assert_eq!(response.refusal_reason.as_deref(), Some("Prompt blocked: Safety"));
+    assert_eq!(response.usage.input_tokens, 7);
+
+    let wire = serde_json::to_value(&response).unwrap();
+    assert_eq!(wire["refused"], true);
+}
+
+#[test]
+fn test_gemini_response_empty_parts_reports_finish_reason() {
+    let gemini_response: GeminiResponse = serde_json::from_value(json!({
+        "candidates": [{
+            "content": {"role": "model", "parts": []},
+            "finishReason": "OTHER",
+            "index": 0
+        }]
+    }))
+    .unwrap();
+
+    match gemini_response.to_anthropic("gemini-pro") {
+        Err(ai_proxy::errors::AppError::ProviderError { status, message, .. }) => {
+            assert_eq!(status, 502);
+            assert!(message.contains("finishReason: OTHER"));
+        }
+        other => panic!("Expected ProviderError, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_gemini_chat_error_preserves_upstream_status_and_reason() {
+    use ai_proxy::errors::AppError;
+
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path_regex(r"/gemini-pro:generateContent"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "error": {
+                "code": 400,
+                "message": "API key not valid. Please pass a valid API key.",
+                "status": "INVALID_ARGUMENT",
+                "details": [{
+                    "@type": "type.googleapis.com/google.rpc.ErrorInfo",
+                    "reason": "API_KEY_INVALID",
+                    "domain": "googleapis.com"
+                }]
+            }
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let config = ProviderDetail {
+        api_key: "test-api-key".to_string(),
+        api_base: mock_server.uri(),
+        models: Some(vec!["gemini-pro".to_string()]),
+        ..Default::default()
+    };
+    let provider = GeminiProvider::new(config, Client::new());
+
+    let request = AnthropicRequest {
+        model: "gemini-pro".to_string(),
+        messages: vec![Message::user("Hello".to_string())],
+        max_tokens: Some(100),
+        ..Default::default()
+    };
+
+    match provider.chat(request).await.unwrap_err() {
+        AppError::ProviderError { status, message, error_type, error_code, .. } => {
+            assert_eq!(status, 400);
+            assert_eq!(message, "Gemini API error: API key not valid. Please pass a valid API key.");
+            assert_eq!(error_type.as_deref(), Some("INVALID_ARGUMENT"));
+            assert_eq!(error_code.as_deref(), Some("API_KEY_INVALID"));
+        }
+        other => panic!("Expected ProviderError, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_gemini_chat_rejects_logprobs() {
+    use ai_proxy::errors::AppError;
+
+    let mock_server = MockServer::start().await;
+    let config = ProviderDetail {
+        api_key: "test-api-key".to_string(),
+        api_base: mock_server.uri(),
+        models: Some(vec!["gemini-pro".to_string()]),
+        ..Default::default()
+    };
+    let provider = GeminiProvider::new(config, Client::new());
+
+    let request = AnthropicRequest {
+        model: "gemini-pro".to_string(),
+        messages: vec![Message::user("Hello".to_string())],
+        max_tokens: Some(100),
+        logprobs: Some(true),
+        top_logprobs: Some(3),
+        ..Default::default()
+    };
+
+    match provider.chat(request).await {
+        Err(AppError::ValidationError(message)) => assert!(message.contains("does not support logprobs")),
+        other => panic!("Expected validation error, got {:?}", other),
+    }
+    assert!(mock_server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_gemini_provider_stream_closes_empty_body() {
+    use futures::StreamExt;
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path_regex(r"/gemini-pro:streamGenerateContent"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw("", "text/event-stream"))
+        .mount(&mock_server)
+        .await;
+
+    let config = ProviderDetail {
+        api_key: "test-api-key".to_string(),
+        api_base: mock_server.uri(),
+        models: Some(vec!["gemini-pro".to_string()]),
+        ..Default::default()
+    };
+    let provider = GeminiProvider::new(config, Client::new());
+
+    let request = AnthropicRequest {
+        model: "gemini-pro".to_string(),
+        messages: vec![Message::user("Hello".to_string())],
+        stream: Some(true),
+        ..Default::default()
+    };
+    let stream = provider.chat_stream(request).await.unwrap();
+    let output: Vec<String> = tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        stream.map(|chunk| chunk.unwrap()).collect(),
+    )
+    .await
+    .expect("stream did not terminate");
+    let output = output.concat();
+
+    assert!(output.contains("event: message_start"));
+    assert!(output.contains("event: content_block_stop"));
+    assert!(output.trim_end().ends_with("data: {\"type\":\"message_stop\"}"));
+}
@@ -0,0 +1,98 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::StreamExt;
+
+use ai_proxy::{
+    errors::AppError,
+    providers::{
+        MockProvider, ProviderRegistry,
+        anthropic::{AnthropicRequest, Message},
+    },
+};
+
+fn create_test_request(model: &str) -> AnthropicRequest {
+    AnthropicRequest {
+        model: model.to_string(),
+        messages: vec![Message::user("Hello".to_string())],
+        max_tokens: Some(100),
+        ..Default::default()
+    }
+}
+
+fn registry_with(provider: Arc<MockProvider>) -> ProviderRegistry {
+    let mut registry = ProviderRegistry::new_empty();
+    registry.register_provider("mock", provider, &["mock-model".to_string()]);
+    registry
+}
+
+#[tokio::test]
+async fn test_registry_dispatches_chat_to_mock_provider() {
+    let mock = Arc::new(MockProvider::new().with_reply("Canned answer").with_usage(12, 3));
+    let registry = registry_with(mock.clone());
+
+    let provider = registry.get_provider_for_model("mock-model").unwrap();
+    let response = provider.chat(create_test_request("mock-model")).await.unwrap();
+
+    assert_eq!(response.model, "mock-model");
+    assert_eq!(response.content[0].text, "Canned answer");
+    assert_eq!(response.usage.input_tokens, 12);
+    assert_eq!(response.usage.output_tokens, 3);
+    assert_eq!(response.stop_reason.as_deref(), Some("end_turn"));
+    assert_eq!(mock.calls(), 1);
+    assert_eq!(registry.provider_id_for_model("mock-model"), Some("mock"));
+}
+
+#[tokio::test]
+async fn test_mock_provider_streams_canned_reply() {
+    let mock = Arc::new(MockProvider::new().with_reply("Streamed"));
+    let registry = registry_with(mock.clone());
+
+    let provider = registry.get_provider_for_model("mock-model").unwrap();
+    let chunks: Vec<String> = provider
+        .chat_stream(create_test_request("mock-model"))
+        .await
+        .unwrap()
+        .map(|chunk| chunk.unwrap())
+        .collect()
+        .await;
+    let body = chunks.concat();
+
+    assert!(body.starts_with("event: message_start\n"));
+    assert!(body.contains("\"text\":\"Streamed\""));
+    assert!(body.ends_with("event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n"));
+    assert_eq!(mock.calls(), 1);
+}
+
+#[tokio::test]
+async fn test_mock_provider_error_and_latency() {
+    let mock = Arc::new(
+        MockProvider::new()
+            .with_latency(Duration::from_millis(20))
+            .with_error(AppError::ServiceUnavailable("mock outage".to_string())),
+    );
+    let registry = registry_with(mock.clone());
+    let provider = registry.get_provider_for_model("mock-model").unwrap();
+
+    let started = std::time::Instant::now();
+    let result = provider.chat(create_test_request("mock-model")).await;
+    assert!(started.elapsed() >= Duration::from_millis(20));
+    assert!(matches!(result, Err(AppError::ServiceUnavailable(message)) if message == "mock outage"));
+    assert!(provider.chat_stream(create_test_request("mock-model")).await.is_err());
+    assert_eq!(mock.calls(), 2);
+
+    let health = registry.health_check_provider("mock").await.unwrap();
+    assert_eq!(health.status, "unhealthy");
+    assert!(health.error.unwrap().contains("mock outage"));
+}
+
+#[tokio::test]
+async fn test_mock_provider_models_are_listed() {
+    let mock = Arc::new(MockProvider::new().with_models(vec!["mock-a".to_string(), "mock-b".to_string()]));
+    let registry = registry_with(mock);
+
+    let models = registry.list_all_models().await.unwrap();
+    let ids: Vec<&str> = models.iter().map(|model| model.id.as_str()).collect();
+    assert_eq!(ids, ["mock-a", "mock-b"]);
+    assert!(models.iter().all(|model| model.owned_by == "mock"));
+}
@@ -3,4 +3,9 @@
 mod registry_tests;
 mod anthropic_tests;
 mod gemini_test;
-mod openai_tests;
\ No newline at end of file
+mod openai_tests;
+mod openrouter_tests;
+mod ollama_tests;
+mod retry_tests;
+mod mock_tests;
+mod xai_tests;
@@ -0,0 +1,152 @@
+use reqwest::Client;
+use serde_json::json;
+use wiremock::{
+    matchers::{body_partial_json, header, method, path},
+    Mock, MockServer, Request, ResponseTemplate,
+};
+
+use ai_proxy::{
+    config::ProviderDetail,
+    providers::{
+        AIProvider,
+        anthropic::{AnthropicRequest, Message},
+        ollama::OllamaProvider,
+    },
+};
+
+/// Create a keyless test provider configuration pointing at Ollama's `/v1` API
+fn create_test_config(server_uri: &str) -> ProviderDetail {
+    ProviderDetail {
+        api_base: format!("{}/v1/", server_uri.trim_end_matches('/')),
+        models: Some(vec!["llama3.2".to_string()]),
+        ..Default::default()
+    }
+}
+
+/// Create a test Anthropic request for a local model
+fn create_test_request() -> AnthropicRequest {
+    AnthropicRequest {
+        model: "qwen2.5:7b".to_string(),
+        messages: vec![Message::user("Hello from local".to_string())],
+        max_tokens: Some(100),
+        stream: Some(false),
+        ..Default::default()
+    }
+}
+
+/// Matches requests that carry no `Authorization` header
+fn no_authorization(request: &Request) -> bool {
+    !request.headers.contains_key("authorization")
+}
+
+#[tokio::test]
+async fn test_ollama_chat_without_api_key_sends_no_auth_header() {
+    let mock_server = MockServer::start().await;
+    let provider = OllamaProvider::new(create_test_config(&mock_server.uri()), Client::new());
+
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(no_authorization)
+        .and(body_partial_json(json!({"model": "qwen2.5:7b"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-ollama",
+            "object": "chat.completion",
+            "created": 1714560000,
+            "model": "qwen2.5:7b",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hello from Ollama"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 5, "completion_tokens": 3, "total_tokens": 8}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let response = provider.chat(create_test_request()).await.unwrap();
+
+    assert_eq!(response.model, "qwen2.5:7b");
+    assert_eq!(response.usage.output_tokens, 3);
+}
+
+#[tokio::test]
+async fn test_ollama_chat_with_api_key_sends_bearer() {
+    let mock_server = MockServer::start().await;
+    let mut config = create_test_config(&mock_server.uri());
+    config.api_key = "local-proxy-token".to_string();
+    let provider = OllamaProvider::new(config, Client::new());
+
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(header("authorization", "Bearer local-proxy-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-ollama",
+            "object": "chat.completion",
+            "created": 1714560000,
+            "model": "qwen2.5:7b",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hi"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 5, "completion_tokens": 1, "total_tokens": 6}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    provider.chat(create_test_request()).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_ollama_list_models_maps_tags() {
+    let mock_server = MockServer::start().await;
+    let provider = OllamaProvider::new(create_test_config(&mock_server.uri()), Client::new());
+
+    Mock::given(method("GET"))
+        .and(path("/api/tags"))
+        .and(no_authorization)
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "models": [
+                {
+                    "name": "qwen2.5:7b",
+                    "model": "qwen2.5:7b",
+                    "modified_at": "2024-10-01T12:00:00Z",
+                    "size": 4683087332u64
+                },
+                {
+                    "name": "llama3.2:latest",
+                    "model": "llama3.2:latest",
+                    "modified_at": "2024-09-25T08:30:00.123456789-07:00",
+                    "size": 2019393189u64
+                }
+            ]
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let models = provider.list_models().await.unwrap();
+
+    let ids: Vec<&str> = models.iter().map(|m| m.id.as_str()).collect();
+    assert_eq!(ids, vec!["llama3.2:latest", "qwen2.5:7b"]);
+    assert!(models.iter().all(|m| m.owned_by == "ollama"));
+    assert_eq!(models[1].created, 1727784000);
+}
+
+#[tokio::test]
+async fn test_ollama_list_models_falls_back_when_server_down() {
+    let mock_server = MockServer::start().await;
+    let provider = OllamaProvider::new(create_test_config(&mock_server.uri()), Client::new());
+
+    Mock::given(method("GET"))
+        .and(path("/api/tags"))
+        .respond_with(ResponseTemplate::new(500).set_body_string("ollama not ready"))
+        .mount(&mock_server)
+        .await;
+
+    let models = provider.list_models().await.unwrap();
+    assert_eq!(models.len(), 1);
+    assert_eq!(models[0].id, "llama3.2");
+}
@@ -1,18 +1,19 @@
 use reqwest::Client;
 use serde_json::json;
 use wiremock::{
-    matchers::{header, method, path},
+    matchers::{body_partial_json, header, method, path, query_param},
     Mock, MockServer, ResponseTemplate,
 };
 
 use ai_proxy::{
-    config::ProviderDetail,
+    config::{NetworkConfig, ProviderDetail},
     errors::AppError,
     providers::{
         AIProvider,
         anthropic::{AnthropicRequest, Message},
         openai::{OpenAIProvider, openai_utils},
     },
+    server::build_http_client_with,
 };
 
 /// Create a test provider configuration
@@ -28,6 +29,7 @@ fn create_test_config(api_base: &str) -> ProviderDetail {
         max_retries: 3,
         enabled: true,
         rate_limit: None,
+        ..Default::default()
     }
 }
 
@@ -39,10 +41,11 @@ fn create_test_request() -> AnthropicRequest {
             role: "user".to_string(),
             content: "Hello, world!".to_string(),
         }],
-        max_tokens: 100,
+        max_tokens: Some(100),
         stream: Some(false),
         temperature: Some(0.7),
         top_p: Some(0.9),
+        ..Default::default()
     }
 }
 
@@ -123,6 +126,39 @@ async fn test_openai_chat_success() {
     assert_eq!(response.usage.output_tokens, 15);
 }
 
+#[tokio::test]
+async fn test_openai_chat_azure_api_style() {
+    // Setup mock server
+    let mock_server = MockServer::start().await;
+    let config = ProviderDetail {
+        api_style: Some("azure".to_string()),
+        deployment: Some("my-gpt4-deployment".to_string()),
+        api_version: Some("2024-06-01".to_string()),
+        ..create_test_config(&mock_server.uri())
+    };
+    let client = Client::new();
+    let provider = OpenAIProvider::new(config, client);
+
+    // Azure uses a deployment-scoped path, an api-version query and an api-key header
+    Mock::given(method("POST"))
+        .and(path("/openai/deployments/my-gpt4-deployment/chat/completions"))
+        .and(query_param("api-version", "2024-06-01"))
+        .and(header("api-key", "test-api-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(create_mock_chat_response()))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    // Test the chat method
+    let request = create_test_request();
+    let response = provider.chat(request).await.unwrap();
+
+    // Verify response and that no Bearer token was sent
+    assert_eq!(response.model, "gpt-4");
+    let received = mock_server.received_requests().await.unwrap();
+    assert!(received[0].headers.get("authorization").is_none());
+}
+
 #[tokio::test]
 async fn test_openai_chat_api_error() {
     // Setup mock server
@@ -154,7 +190,7 @@ async fn test_openai_chat_api_error() {
     assert!(result.is_err());
     println!("Error: {:?}", result);
     match result.unwrap_err() {
-        AppError::ProviderError { status, message } => {
+        AppError::ProviderError { status, message, .. } => {
             assert_eq!(status, 401);
             assert!(message.contains("authentication failed"));
         }
@@ -188,6 +224,26 @@ async fn test_openai_list_models_success() {
     assert!(!models.iter().any(|m| m.id == "text-embedding-ada-002"));
 }
 
+#[tokio::test]
+async fn test_openai_list_models_advertise_configured_only() {
+    let mock_server = MockServer::start().await;
+    let mut config = create_test_config(&mock_server.uri());
+    config.models = Some(vec!["gpt-4".to_string(), "gpt-4o-private".to_string()]);
+    config.advertise_configured_only = true;
+    let provider = OpenAIProvider::new(config, Client::new());
+
+    // The live list is a superset of the configured models
+    Mock::given(method("GET"))
+        .and(path("/models"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(create_mock_models_response()))
+        .mount(&mock_server)
+        .await;
+
+    let models = provider.list_models().await.unwrap();
+    let ids: Vec<&str> = models.iter().map(|m| m.id.as_str()).collect();
+    assert_eq!(ids, vec!["gpt-4"]);
+}
+
 #[tokio::test]
 async fn test_openai_list_models_fallback() {
     // Setup mock server that returns error
@@ -400,6 +456,8 @@ fn test_openai_response_conversion() {
                 role: "assistant".to_string(),
                 content: "Hello, world!".to_string(),
                 name: None,
+                tool_calls: None,
+                refusal: None,
             },
             finish_reason: Some("stop".to_string()),
             logprobs: None,
@@ -433,4 +491,594 @@ fn test_openai_response_conversion() {
     
     // Test response validation
     assert!(!openai_response.has_issues());
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn test_openai_embeddings_success() {
+    let mock_server = MockServer::start().await;
+    let config = create_test_config(&mock_server.uri());
+    let provider = OpenAIProvider::new(config, Client::new());
+
+    // Returned out of order to verify the provider sorts by index
+    Mock::given(method("POST"))
+        .and(path("/embeddings"))
+        .and(header("authorization", "Bearer test-api-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "object": "list",
+            "model": "text-embedding-3-small",
+            "data": [
+                {"object": "embedding", "index": 1, "embedding": [0.4, 0.5, 0.6]},
+                {"object": "embedding", "index": 0, "embedding": [0.1, 0.2, 0.3]}
+            ],
+            "usage": {"prompt_tokens": 8, "total_tokens": 8}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let response = provider
+        .embeddings(
+            vec!["first".to_string(), "second".to_string()],
+            "text-embedding-3-small",
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.object, "list");
+    assert_eq!(response.data.len(), 2);
+    assert_eq!(response.data[0].index, 0);
+    assert_eq!(response.data[0].embedding, vec![0.1, 0.2, 0.3]);
+    assert_eq!(response.data[1].embedding, vec![0.4, 0.5, 0.6]);
+    assert_eq!(response.usage.prompt_tokens, 8);
+    assert_eq!(response.usage.total_tokens, 8);
+}
+
+#[tokio::test]
+async fn test_openai_stream_message_start_reports_input_tokens() {
+    use futures::StreamExt;
+
+    let mock_server = MockServer::start().await;
+    let config = create_test_config(&mock_server.uri());
+    let provider = OpenAIProvider::new(config, Client::new());
+
+    let body = "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"gpt-4\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi\"},\"finish_reason\":null}]}\n\ndata: [DONE]\n\n";
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(body)
+                .insert_header("content-type", "text/event-stream"),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let mut request = create_test_request();
+    request.stream = Some(true);
+    let expected = request.estimate_input_tokens();
+
+    let chunks: Vec<String> = provider
+        .chat_stream(request)
+        .await
+        .unwrap()
+        .map(|chunk| chunk.unwrap())
+        .collect()
+        .await;
+    let output = chunks.concat();
+
+    let data = output
+        .split("\n\n")
+        .find(|event| event.starts_with("event: message_start"))
+        .and_then(|event| event.lines().find_map(|line| line.strip_prefix("data: ")))
+        .expect("message_start event");
+    let event: serde_json::Value = serde_json::from_str(data).unwrap();
+    let input_tokens = event["message"]["usage"]["input_tokens"].as_u64().unwrap();
+    assert!(input_tokens > 0);
+    assert_eq!(input_tokens, expected as u64);
+}
+
+#[tokio::test]
+async fn test_openai_sends_extra_headers() {
+    let mock_server = MockServer::start().await;
+    let mut config = create_test_config(&mock_server.uri());
+    config.extra_headers.insert("HTTP-Referer".to_string(), "https://example.com".to_string());
+    config.extra_headers.insert("X-Title".to_string(), "ai-proxy".to_string());
+    let provider = OpenAIProvider::new(config, Client::new());
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(header("authorization", "Bearer test-api-key"))
+        .and(header("http-referer", "https://example.com"))
+        .and(header("x-title", "ai-proxy"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(create_mock_chat_response()))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/models"))
+        .and(header("x-title", "ai-proxy"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(create_mock_models_response()))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    provider.chat(create_test_request()).await.unwrap();
+    let models = provider.list_models().await.unwrap();
+    assert!(models.iter().any(|m| m.id == "gpt-4"));
+}
+
+#[tokio::test]
+async fn test_openai_extra_headers_can_override_auth_when_allowed() {
+    let mock_server = MockServer::start().await;
+    let mut config = create_test_config(&mock_server.uri());
+    config.extra_headers.insert("Authorization".to_string(), "Bearer gateway-key".to_string());
+    config.allow_auth_header_override = true;
+    let provider = OpenAIProvider::new(config, Client::new());
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(header("authorization", "Bearer gateway-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(create_mock_chat_response()))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    provider.chat(create_test_request()).await.unwrap();
+
+    let received = mock_server.received_requests().await.unwrap();
+    assert_eq!(received[0].headers.get_all("authorization").iter().count(), 1);
+}
+
+#[tokio::test]
+async fn test_openai_chat_prepends_assistant_prefill() {
+    let mock_server = MockServer::start().await;
+    let provider = OpenAIProvider::new(create_test_config(&mock_server.uri()), Client::new());
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(create_mock_chat_response()))
+        .mount(&mock_server)
+        .await;
+
+    let mut request = create_test_request();
+    request.messages.push(Message {
+        role: "assistant".to_string(),
+        content: "Sure! ".to_string(),
+    });
+
+    let response = provider.chat(request).await.unwrap();
+    assert_eq!(response.content[0].text, "Sure! Hello! How can I help you today?");
+
+    // The prefill never reaches OpenAI as an assistant turn
+    let received = mock_server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&received[0].body).unwrap();
+    let messages = body["messages"].as_array().unwrap();
+    assert!(messages.iter().all(|message| message["role"] != "assistant"));
+}
+
+#[tokio::test]
+async fn test_openai_chat_malformed_body_names_failing_field() {
+    let mock_server = MockServer::start().await;
+    let config = create_test_config(&mock_server.uri());
+    let provider = OpenAIProvider::new(config, Client::new());
+
+    // A 200 body without `choices`
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-123",
+            "object": "chat.completion",
+            "created": 1677652288,
+            "model": "gpt-4"
+        })))
+        .up_to_n_times(1)
+        .mount(&mock_server)
+        .await;
+
+    match provider.chat(create_test_request()).await.unwrap_err() {
+        AppError::ProviderError { status, message, .. } => {
+            assert_eq!(status, 502);
+            assert!(message.contains("Failed to parse OpenAI response"), "{}", message);
+            assert!(message.contains("missing field `choices`"), "{}", message);
+        }
+        other => panic!("Expected ProviderError, got {:?}", other),
+    }
+
+    // A nested type mismatch reports the path to the field
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-123",
+            "object": "chat.completion",
+            "created": 1677652288,
+            "model": "gpt-4",
+            "choices": [{"index": 0, "message": {"role": "assistant", "content": 42}, "finish_reason": "stop"}]
+        })))
+        .mount(&mock_server)
+        .await;
+
+    match provider.chat(create_test_request()).await.unwrap_err() {
+        AppError::ProviderError { message, .. } => {
+            assert!(message.contains("at `choices[0].message.content`"), "{}", message);
+        }
+        other => panic!("Expected ProviderError, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_openai_chat_error_preserves_upstream_type_and_code() {
+    let mock_server = MockServer::start().await;
+    let config = create_test_config(&mock_server.uri());
+    let provider = OpenAIProvider::new(config, Client::new());
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "error": {
+                "message": "Unsupported parameter: 'temperature'",
+                "type": "invalid_request_error",
+                "param": "temperature",
+                "code": "unsupported_parameter"
+            }
+        })))
+        .mount(&mock_server)
+        .await;
+
+    match provider.chat(create_test_request()).await.unwrap_err() {
+        AppError::ProviderError { status, message, error_type, error_code, .. } => {
+            assert_eq!(status, 400);
+            assert!(message.contains("Unsupported parameter"));
+            assert_eq!(error_type.as_deref(), Some("invalid_request_error"));
+            assert_eq!(error_code.as_deref(), Some("unsupported_parameter"));
+        }
+        other => panic!("Expected ProviderError, got {:?}", other),
+    }
+
+    // Numeric codes are reported as strings; bodies without an error object carry no details
+    let (_, error_type, error_code) = openai_utils::parse_error_details(r#"{"error":{"message":"m","type":"server_error","code":500}}"#);
+    assert_eq!(error_type.as_deref(), Some("server_error"));
+    assert_eq!(error_code.as_deref(), Some("500"));
+    assert_eq!(openai_utils::parse_error_details("not json"), ("not json".to_string(), None, None));
+}
+
+#[tokio::test]
+async fn test_openai_chat_passes_logprobs_through() {
+    let mock_server = MockServer::start().await;
+    let config = create_test_config(&mock_server.uri());
+    let provider = OpenAIProvider::new(config, Client::new());
+
+    let mut mock_response = create_mock_chat_response();
+    let logprobs = json!({
+        "content": [{
+            "token": "Hello",
+            "logprob": -0.01,
+            "top_logprobs": [{"token": "Hello", "logprob": -0.01}, {"token": "Hi", "logprob": -4.6}]
+        }]
+    });
+    mock_response["choices"][0]["logprobs"] = logprobs.clone();
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({"logprobs": true, "top_logprobs": 2})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(mock_response))
+        .mount(&mock_server)
+        .await;
+
+    let mut request = create_test_request();
+    request.logprobs = Some(true);
+    request.top_logprobs = Some(2);
+    let response = provider.chat(request).await.unwrap();
+
+    assert_eq!(response.logprobs, Some(logprobs.clone()));
+    let body = serde_json::to_value(&response).unwrap();
+    assert_eq!(body["logprobs"], logprobs);
+}
+
+#[test]
+fn test_top_logprobs_requires_logprobs() {
+    let mut request = create_test_request();
+    request.top_logprobs = Some(2);
+    assert!(request.validate().unwrap_err().contains("requires logprobs"));
+
+    request.logprobs = Some(true);
+    request.top_logprobs = Some(21);
+    assert!(request.validate().unwrap_err().contains("between 0 and 20"));
+
+    request.top_logprobs = Some(20);
+    assert!(request.validate().is_ok());
+}
+
+/// Run a streaming request against `body` and return the Anthropic event names in order
+async fn stream_body(body: &str) -> String {
+    use futures::StreamExt;
+
+    let mock_server = MockServer::start().await;
+    let config = create_test_config(&mock_server.uri());
+    let provider = OpenAIProvider::new(config, Client::new());
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(body)
+                .insert_header("content-type", "text/event-stream"),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let mut request = create_test_request();
+    request.stream = Some(true);
+    let chunks: Vec<String> = provider
+        .chat_stream(request)
+        .await
+        .unwrap()
+        .map(|chunk| chunk.unwrap())
+        .collect()
+        .await;
+    chunks.concat()
+}
+
+async fn stream_event_names(body: &str) -> Vec<String> {
+    stream_body(body)
+        .await
+        .lines()
+        .filter_map(|line| line.strip_prefix("event: "))
+        .map(str::to_string)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_openai_stream_closed_at_eof_without_done() {
+    // The upstream ends the stream without a finish_reason or [DONE]
+    let body = "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"gpt-4\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi\"},\"finish_reason\":null}]}\n\n";
+    assert_eq!(
+        stream_event_names(body).await,
+        vec!["message_start", "content_block_start", "content_block_delta", "content_block_stop", "message_stop"]
+    );
+
+    // An empty upstream stream still yields a complete Anthropic message
+    assert_eq!(
+        stream_event_names("").await,
+        vec!["message_start", "content_block_start", "content_block_stop", "message_stop"]
+    );
+}
+
+#[tokio::test]
+async fn test_openai_stream_sends_message_stop_once() {
+    // A finish_reason followed by [DONE] closes the message only once
+    let body = "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"gpt-4\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi\"},\"finish_reason\":\"stop\"}]}\n\ndata: [DONE]\n\n";
+    assert_eq!(
+        stream_event_names(body).await,
+        vec!["message_start", "content_block_start", "content_block_delta", "content_block_stop", "message_delta", "message_stop"]
+    );
+}
+
+#[tokio::test]
+async fn test_openai_stream_maps_finish_reason_to_stop_reason() {
+    // The upstream stops because it ran out of tokens
+    let body = "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"gpt-4\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi\"},\"finish_reason\":\"length\"}]}\n\ndata: [DONE]\n\n";
+    let events = stream_body(body).await;
+    let message_delta: serde_json::Value = events
+        .split("\n\n")
+        .find_map(|event| event.strip_prefix("event: message_delta\ndata: "))
+        .map(|data| serde_json::from_str(data).unwrap())
+        .expect("message_delta event");
+    assert_eq!(message_delta["delta"]["stop_reason"], "max_tokens");
+}
+
+#[tokio::test]
+async fn test_openai_stream_tool_call_blocks() {
+    let tool_chunk = |delta: &str, finish: &str| {
+        format!(
+            "data: {{\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"gpt-4\",\"choices\":[{{\"index\":0,\"delta\":{},\"finish_reason\":{}}}]}}\n\n",
+            delta, finish
+        )
+    };
+    let fragments = [
+        tool_chunk(r#"{"tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"lookup","arguments":"{\"q\":"}}]}"#, "null"),
+        tool_chunk(r#"{"tool_calls":[{"index":0,"function":{"arguments":"\"rust\"}"}}]}"#, "null"),
+    ]
+    .concat();
+
+    // A finish_reason closes the tool block before message_delta
+    let body = format!("{}{}data: [DONE]\n\n", fragments, tool_chunk("{}", "\"tool_calls\""));
+    assert_eq!(
+        stream_event_names(&body).await,
+        vec![
+            "message_start", "content_block_start", "content_block_stop", "content_block_start",
+            "content_block_delta", "content_block_delta", "content_block_stop", "message_delta", "message_stop",
+        ]
+    );
+
+    // An upstream that ends early still closes the open tool block
+    assert_eq!(
+        stream_event_names(&fragments).await,
+        vec![
+            "message_start", "content_block_start", "content_block_stop", "content_block_start",
+            "content_block_delta", "content_block_delta", "content_block_stop", "message_stop",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_openai_health_probe_variants() {
+    use ai_proxy::config::HealthProbe;
+
+    let provider_with = |uri: &str, probe: HealthProbe| {
+        let mut config = create_test_config(uri);
+        config.health_check = Some(probe);
+        OpenAIProvider::new(config, Client::new())
+    };
+
+    // models_list: a single GET /models
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/models"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(create_mock_models_response()))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    let health = provider_with(&mock_server.uri(), HealthProbe::ModelsList).health_check().await.unwrap();
+    assert_eq!(health.status, "healthy");
+    mock_server.verify().await;
+
+    // minimal_chat: a one-token chat completion against the first configured model, no models call
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({"model": "gpt-4", "max_tokens": 1})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(create_mock_chat_response()))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    let health = provider_with(&mock_server.uri(), HealthProbe::MinimalChat).health_check().await.unwrap();
+    assert_eq!(health.status, "healthy");
+    mock_server.verify().await;
+
+    // tcp_connect: only opens a connection, so no HTTP request is received
+    let mock_server = MockServer::start().await;
+    let health = provider_with(&mock_server.uri(), HealthProbe::TcpConnect).health_check().await.unwrap();
+    assert_eq!(health.status, "healthy");
+    assert!(mock_server.received_requests().await.unwrap().is_empty());
+
+    let closed_port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let health = provider_with(&format!("http://127.0.0.1:{}", closed_port), HealthProbe::TcpConnect)
+        .health_check()
+        .await
+        .unwrap();
+    assert_eq!(health.status, "unhealthy");
+    assert!(health.error.unwrap().contains("TCP connect"));
+
+    // disabled: always healthy without any network call, even for an unreachable host
+    let health = provider_with(&format!("http://127.0.0.1:{}", closed_port), HealthProbe::Disabled)
+        .health_check()
+        .await
+        .unwrap();
+    assert_eq!(health.status, "healthy");
+    assert!(health.latency_ms.is_none());
+    assert!(health.error.is_none());
+}
+
+/// Stream `event` as one chunk of a chunked body, drop the connection, and
+/// return the proxied events' data
+async fn stream_then_drop(event: &'static str) -> Vec<serde_json::Value> {
+    use futures::StreamExt;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = vec![0u8; 8192];
+        let _ = socket.read(&mut buf).await;
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ntransfer-encoding: chunked\r\n\r\n{:x}\r\n{}\r\n",
+            event.len(),
+            event
+        );
+        socket.write_all(response.as_bytes()).await.unwrap();
+        socket.flush().await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    });
+
+    let provider = OpenAIProvider::new(create_test_config(&format!("http://{}", addr)), Client::new());
+    let mut request = create_test_request();
+    request.stream = Some(true);
+    let chunks: Vec<_> = provider.chat_stream(request).await.unwrap().collect().await;
+
+    // The failure is reported in-band, so the client never sees a broken stream
+    assert!(chunks.iter().all(|chunk| chunk.is_ok()));
+    let body: String = chunks.into_iter().map(|chunk| chunk.unwrap()).collect();
+    body.lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_openai_stream_mid_stream_error_reports_partial_usage() {
+    // The upstream sends part of the answer, then drops the connection
+    let events = stream_then_drop(
+        "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"gpt-4\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Partial answer text.\"},\"finish_reason\":null}]}\n\n",
+    )
+    .await;
+
+    let types: Vec<&str> = events.iter().map(|event| event["type"].as_str().unwrap()).collect();
+    assert_eq!(
+        types,
+        ["message_start", "content_block_start", "content_block_delta", "content_block_stop", "message_delta", "error"]
+    );
+    assert_eq!(events[2]["delta"]["text"], "Partial answer text.");
+    assert_eq!(events[4]["delta"]["stop_reason"], "error");
+    assert_eq!(events[4]["delta"]["usage"]["output_tokens"], 5);
+    assert_eq!(events[5]["error"]["output_tokens"], 5);
+    assert!(events[5]["error"]["message"].as_str().unwrap().contains("Streaming read error"));
+}
+
+#[tokio::test]
+async fn test_openai_stream_mid_stream_error_closes_tool_blocks() {
+    // The connection drops while a tool call's arguments are still streaming
+    let events = stream_then_drop(
+        "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"gpt-4\",\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_1\",\"type\":\"function\",\"function\":{\"name\":\"lookup\",\"arguments\":\"{\\\"q\\\":\"}}]},\"finish_reason\":null}]}\n\n",
+    )
+    .await;
+
+    let types: Vec<&str> = events.iter().map(|event| event["type"].as_str().unwrap()).collect();
+    assert_eq!(
+        types,
+        [
+            "message_start", "content_block_start", "content_block_stop", "content_block_start",
+            "content_block_delta", "content_block_stop", "message_delta", "error",
+        ]
+    );
+    // The text block closes before the tool block opens, which closes before the error
+    assert_eq!(events[2]["index"], 0);
+    assert_eq!(events[3]["index"], 1);
+    assert_eq!(events[5]["index"], 1);
+    assert_eq!(events[6]["delta"]["stop_reason"], "error");
+}
+
+#[tokio::test]
+async fn test_openai_chat_decodes_gzip_response() {
+    use flate2::{Compression, write::GzEncoder};
+    use std::io::Write;
+
+    let mock_server = MockServer::start().await;
+    let body = json!({
+        "id": "chatcmpl-gzip",
+        "object": "chat.completion",
+        "created": 1677652288,
+        "model": "gpt-4",
+        "choices": [{"index": 0, "message": {"role": "assistant", "content": "Compressed hello"}, "finish_reason": "stop"}],
+        "usage": {"prompt_tokens": 9, "completion_tokens": 2, "total_tokens": 11}
+    });
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(body.to_string().as_bytes()).unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-encoding", "gzip")
+                .insert_header("content-type", "application/json")
+                .set_body_bytes(compressed),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let client = build_http_client_with(&NetworkConfig::default(), None, true).unwrap();
+    let provider = OpenAIProvider::new(create_test_config(&mock_server.uri()), client);
+    let response = provider.chat(create_test_request()).await.unwrap();
+    assert_eq!(response.content[0].text, "Compressed hello");
+
+    let requests = mock_server.received_requests().await.unwrap();
+    let accept_encoding = requests[0].headers.get("accept-encoding").unwrap().to_str().unwrap();
+    assert!(accept_encoding.contains("gzip"), "{}", accept_encoding);
+
+    // With decompression disabled the compressed bytes reach the JSON parser
+    let client = build_http_client_with(&NetworkConfig::default(), None, false).unwrap();
+    let provider = OpenAIProvider::new(create_test_config(&mock_server.uri()), client);
+    match provider.chat(create_test_request()).await.unwrap_err() {
+        AppError::ProviderError { status, .. } => assert_eq!(status, 502),
+        other => panic!("Expected ProviderError, got {:?}", other),
+    }
+}
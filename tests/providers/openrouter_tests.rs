@@ -0,0 +1,128 @@
+use reqwest::Client;
+use serde_json::json;
+use wiremock::{
+    matchers::{body_partial_json, header, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+use ai_proxy::{
+    config::ProviderDetail,
+    providers::{
+        AIProvider,
+        anthropic::{AnthropicRequest, Message},
+        openrouter::OpenRouterProvider,
+    },
+};
+
+/// Create a test provider configuration
+fn create_test_config(api_base: &str) -> ProviderDetail {
+    ProviderDetail {
+        api_key: "test-openrouter-key".to_string(),
+        api_base: format!("{}/", api_base.trim_end_matches('/')),
+        models: Some(vec!["anthropic/claude-3-sonnet".to_string()]),
+        ..Default::default()
+    }
+}
+
+/// Create a test Anthropic request using a routed model name
+fn create_test_request() -> AnthropicRequest {
+    AnthropicRequest {
+        model: "anthropic/claude-3-sonnet".to_string(),
+        messages: vec![Message::user("Hello via OpenRouter".to_string())],
+        max_tokens: Some(100),
+        stream: Some(false),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_openrouter_chat_passes_model_and_headers() {
+    let mock_server = MockServer::start().await;
+    let provider = OpenRouterProvider::new(create_test_config(&mock_server.uri()), Client::new());
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(header("authorization", "Bearer test-openrouter-key"))
+        .and(header("http-referer", "https://github.com/yeheng/ai-proxy"))
+        .and(header("x-title", "ai-proxy"))
+        .and(body_partial_json(json!({"model": "anthropic/claude-3-sonnet"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "gen-123",
+            "object": "chat.completion",
+            "created": 1714560000,
+            "model": "anthropic/claude-3-sonnet",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hello from OpenRouter"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 6, "completion_tokens": 4, "total_tokens": 10}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let response = provider.chat(create_test_request()).await.unwrap();
+
+    assert_eq!(response.model, "anthropic/claude-3-sonnet");
+    assert_eq!(response.usage.output_tokens, 4);
+}
+
+#[tokio::test]
+async fn test_openrouter_configured_headers_take_precedence() {
+    let mock_server = MockServer::start().await;
+    let mut config = create_test_config(&mock_server.uri());
+    config.extra_headers.insert("x-title".to_string(), "My App".to_string());
+    let provider = OpenRouterProvider::new(config, Client::new());
+
+    Mock::given(method("GET"))
+        .and(path("/models"))
+        .and(header("x-title", "My App"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"data": []})))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    // Empty listing falls back to the configured models
+    let models = provider.list_models().await.unwrap();
+    assert_eq!(models[0].id, "anthropic/claude-3-sonnet");
+    assert_eq!(models[0].owned_by, "anthropic");
+}
+
+#[tokio::test]
+async fn test_openrouter_list_models_maps_metadata() {
+    let mock_server = MockServer::start().await;
+    let provider = OpenRouterProvider::new(create_test_config(&mock_server.uri()), Client::new());
+
+    Mock::given(method("GET"))
+        .and(path("/models"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": [
+                {
+                    "id": "openai/gpt-4o",
+                    "name": "OpenAI: GPT-4o",
+                    "created": 1715367049,
+                    "context_length": 128000,
+                    "pricing": {"prompt": "0.000005", "completion": "0.000015"}
+                },
+                {
+                    "id": "anthropic/claude-3-sonnet",
+                    "name": "Anthropic: Claude 3 Sonnet",
+                    "created": 1709596800,
+                    "context_length": 200000
+                }
+            ]
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let models = provider.list_models().await.unwrap();
+
+    assert_eq!(models.len(), 2);
+    assert_eq!(models[0].id, "anthropic/claude-3-sonnet");
+    assert_eq!(models[0].owned_by, "anthropic");
+    assert_eq!(models[0].context_length, Some(200000));
+    assert_eq!(models[1].id, "openai/gpt-4o");
+    assert_eq!(models[1].owned_by, "openai");
+    assert_eq!(models[1].created, 1715367049);
+}
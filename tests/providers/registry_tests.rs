@@ -1,5 +1,6 @@
 use ai_proxy::{
     config::{Config, ProviderDetail, ServerConfig},
+    errors::AppError,
     providers::ProviderRegistry,
 };
 use reqwest::Client;
@@ -15,6 +16,7 @@ fn create_test_config() -> Config {
         max_retries: 3,
         enabled: true,
         rate_limit: None,
+        ..Default::default()
     });
 
     Config {
@@ -23,11 +25,13 @@ fn create_test_config() -> Config {
             port: 3000,
             request_timeout_seconds: 30,
             max_request_size_bytes: 1024 * 1024,
+            ..Default::default()
         },
         providers,
         logging: ai_proxy::config::LoggingConfig::default(),
         security: ai_proxy::config::SecurityConfig::default(),
         performance: ai_proxy::config::PerformanceConfig::default(),
+        ..Default::default()
     }
 }
 
@@ -68,14 +72,379 @@ fn test_empty_providers_config() {
             port: 3000,
             request_timeout_seconds: 30,
             max_request_size_bytes: 1024 * 1024,
+            ..Default::default()
         },
         providers: HashMap::new(),
         logging: ai_proxy::config::LoggingConfig::default(),
         security: ai_proxy::config::SecurityConfig::default(),
         performance: ai_proxy::config::PerformanceConfig::default(),
+        ..Default::default()
     };
     let client = Client::new();
     
     let registry = ProviderRegistry::new(&config, client);
     assert!(registry.is_err());
 }
+
+fn create_stream_limited_config(
+    api_base: &str,
+    limit: u32,
+    overflow: ai_proxy::config::StreamOverflow,
+) -> Config {
+    let mut config = create_test_config();
+    config.providers.clear();
+    config.providers.insert("openai".to_string(), ProviderDetail {
+        api_key: "test-key".to_string(),
+        api_base: format!("{}/", api_base.trim_end_matches('/')),
+        models: Some(vec!["gpt-4".to_string()]),
+        max_concurrent_streams: Some(limit),
+        stream_overflow: overflow,
+        ..Default::default()
+    });
+    config
+}
+
+async fn mount_stream_mock(mock_server: &wiremock::MockServer) {
+    use wiremock::{matchers::{method, path}, Mock, ResponseTemplate};
+
+    let body = "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"gpt-4\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi\"},\"finish_reason\":null}]}\n\ndata: [DONE]\n\n";
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(body)
+                .insert_header("content-type", "text/event-stream"),
+        )
+        .mount(mock_server)
+        .await;
+}
+
+fn stream_request() -> ai_proxy::providers::anthropic::AnthropicRequest {
+    ai_proxy::providers::anthropic::AnthropicRequest {
+        model: "gpt-4".to_string(),
+        messages: vec![ai_proxy::providers::anthropic::Message {
+            role: "user".to_string(),
+            content: "Hello".to_string(),
+        }],
+        max_tokens: Some(16),
+        stream: Some(true),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_max_concurrent_streams_rejects_excess_stream() {
+    use ai_proxy::errors::AppError;
+
+    let mock_server = wiremock::MockServer::start().await;
+    mount_stream_mock(&mock_server).await;
+    let config = create_stream_limited_config(
+        &mock_server.uri(),
+        2,
+        ai_proxy::config::StreamOverflow::Reject,
+    );
+    let registry = ProviderRegistry::new(&config, Client::new()).unwrap();
+    let provider = registry.get_provider_for_model("gpt-4").unwrap();
+
+    // The first N streams proceed while held open
+    let first = provider.chat_stream(stream_request()).await.unwrap();
+    let _second = provider.chat_stream(stream_request()).await.unwrap();
+
+    // The (N+1)th is rejected
+    match provider.chat_stream(stream_request()).await {
+        Err(AppError::ServiceUnavailable(message)) => {
+            assert!(message.contains("openai"));
+            assert!(message.contains('2'));
+        }
+        Err(other) => panic!("expected ServiceUnavailable, got {:?}", other),
+        Ok(_) => panic!("expected the third stream to be rejected"),
+    }
+
+    // Dropping a stream frees its slot
+    drop(first);
+    assert!(provider.chat_stream(stream_request()).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_max_concurrent_streams_queues_excess_stream() {
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    let mock_server = wiremock::MockServer::start().await;
+    mount_stream_mock(&mock_server).await;
+    let config = create_stream_limited_config(
+        &mock_server.uri(),
+        1,
+        ai_proxy::config::StreamOverflow::Queue,
+    );
+    let registry = ProviderRegistry::new(&config, Client::new()).unwrap();
+    let provider = registry.get_provider_for_model("gpt-4").unwrap();
+
+    let first = provider.chat_stream(stream_request()).await.unwrap();
+
+    // The second stream waits for a free slot instead of failing
+    let queued = {
+        let provider = provider.clone();
+        tokio::spawn(async move { provider.chat_stream(stream_request()).await })
+    };
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!queued.is_finished());
+
+    // Draining the first stream to completion releases its slot
+    let _chunks: Vec<_> = first.collect().await;
+    let second = tokio::time::timeout(Duration::from_secs(5), queued)
+        .await
+        .expect("queued stream should start")
+        .unwrap();
+    assert!(second.is_ok());
+}
+
+#[test]
+fn test_registry_capabilities_report() {
+    let mut config = create_test_config();
+    config.providers.insert("anthropic".to_string(), ProviderDetail {
+        api_key: "test-key".to_string(),
+        api_base: "https://api.anthropic.com/v1/".to_string(),
+        models: Some(vec!["claude-3-haiku".to_string()]),
+        ..Default::default()
+    });
+    let registry = ProviderRegistry::new(&config, Client::new()).unwrap();
+
+    let capabilities = registry.capabilities();
+    assert_eq!(capabilities.keys().collect::<Vec<_>>(), vec!["anthropic", "gemini"]);
+    assert!(capabilities["anthropic"].streaming);
+    assert!(capabilities["anthropic"].tools);
+    assert!(!capabilities["anthropic"].embeddings);
+    assert!(capabilities["gemini"].embeddings);
+}
+
+/// Chat-only provider relying on the default capabilities
+struct ChatOnlyProvider;
+
+#[async_trait::async_trait]
+impl ai_proxy::providers::AIProvider for ChatOnlyProvider {
+    async fn chat(
+        &self,
+        _request: ai_proxy::providers::anthropic::AnthropicRequest,
+    ) -> Result<ai_proxy::providers::anthropic::AnthropicResponse, ai_proxy::errors::AppError> {
+        unimplemented!()
+    }
+
+    async fn chat_stream(
+        &self,
+        _request: ai_proxy::providers::anthropic::AnthropicRequest,
+    ) -> Result<ai_proxy::providers::StreamResponse, ai_proxy::errors::AppError> {
+        unimplemented!()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ai_proxy::providers::ModelInfo>, ai_proxy::errors::AppError> {
+        Ok(vec![])
+    }
+
+    async fn health_check(&self) -> Result<ai_proxy::providers::HealthStatus, ai_proxy::errors::AppError> {
+        unimplemented!()
+    }
+}
+
+#[test]
+fn test_capabilities_reject_tools_for_provider_without_tools() {
+    use ai_proxy::errors::AppError;
+    use ai_proxy::providers::{AIProvider, anthropic::ToolDefinition};
+
+    let capabilities = ChatOnlyProvider.capabilities();
+    assert!(capabilities.streaming);
+    assert!(!capabilities.tools);
+
+    let mut request = stream_request();
+    assert!(capabilities.check_request("local", &request).is_ok());
+
+    request.tools = Some(vec![ToolDefinition {
+        name: "get_weather".to_string(),
+        description: None,
+        input_schema: serde_json::json!({"type": "object"}),
+    }]);
+    match capabilities.check_request("local", &request) {
+        Err(AppError::BadRequest(message)) => {
+            assert_eq!(message, "Provider 'local' does not support tools");
+        }
+        other => panic!("expected BadRequest, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_shared_model_routes_to_highest_priority_provider() {
+    for (backup_priority, expected) in [(5, "openai-backup"), (0, "openai"), (-5, "openai")] {
+        let mut config = create_test_config();
+        let openai = ProviderDetail {
+            api_key: "test-key".to_string(),
+            api_base: "https://api.openai.com/v1/".to_string(),
+            models: Some(vec!["gpt-4".to_string()]),
+            ..Default::default()
+        };
+        let backup = ProviderDetail {
+            priority: backup_priority,
+            ..openai.clone()
+        };
+        config.providers.insert("openai".to_string(), openai);
+        config.providers.insert("openai-backup".to_string(), backup);
+
+        let registry = ProviderRegistry::new(&config, Client::new()).unwrap();
+        assert_eq!(registry.provider_id_for_model("gpt-4"), Some(expected));
+    }
+}
+
+#[tokio::test]
+async fn test_refresh_models_keeps_priority_and_model_routes() {
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    // Both providers list the same two models
+    let mock_server = MockServer::start().await;
+    let model = |id: &str| json!({"id": id, "object": "model", "created": 1, "owned_by": "openai"});
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "object": "list",
+            "data": [model("gpt-4"), model("gpt-3.5-turbo")]
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let mut config = create_test_config();
+    config.providers.clear();
+    let openai = ProviderDetail {
+        api_key: "test-openai-key-1234567890".to_string(),
+        api_base: format!("{}/v1/", mock_server.uri()),
+        models: Some(vec!["gpt-4".to_string(), "gpt-3.5-turbo".to_string()]),
+        ..Default::default()
+    };
+    let backup = ProviderDetail {
+        priority: 5,
+        ..openai.clone()
+    };
+    config.providers.insert("openai".to_string(), openai);
+    config.providers.insert("openai-backup".to_string(), backup);
+    config.model_routes.insert("gpt-3.5-turbo".to_string(), "openai".to_string());
+
+    let mut registry = ProviderRegistry::new(&config, Client::new()).unwrap();
+    let routed = |registry: &ProviderRegistry| {
+        (
+            registry.provider_id_for_model("gpt-4").map(str::to_string),
+            registry.provider_id_for_model("gpt-3.5-turbo").map(str::to_string),
+        )
+    };
+    let expected = (Some("openai-backup".to_string()), Some("openai".to_string()));
+    assert_eq!(routed(&registry), expected);
+
+    // Refreshing re-applies priority and explicit routes instead of letting the last list win
+    for _ in 0..3 {
+        registry.refresh_models().await.unwrap();
+        assert_eq!(routed(&registry), expected);
+    }
+}
+
+#[tokio::test]
+async fn test_refresh_models_breaks_priority_ties_by_provider_id() {
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "object": "list",
+            "data": [{"id": "gpt-4", "object": "model", "created": 1, "owned_by": "openai"}]
+        })))
+        .mount(&mock_server)
+        .await;
+
+    // Same priority everywhere, so the lowest provider ID must win every time
+    let mut config = create_test_config();
+    config.providers.clear();
+    for id in ["openai-c", "openai-a", "openai-b"] {
+        config.providers.insert(
+            id.to_string(),
+            ProviderDetail {
+                api_key: "test-openai-key-1234567890".to_string(),
+                api_base: format!("{}/v1/", mock_server.uri()),
+                models: Some(vec!["gpt-4".to_string()]),
+                ..Default::default()
+            },
+        );
+    }
+
+    let mut registry = ProviderRegistry::new(&config, Client::new()).unwrap();
+    assert_eq!(registry.provider_id_for_model("gpt-4"), Some("openai-a"));
+
+    for _ in 0..3 {
+        registry.refresh_models().await.unwrap();
+        assert_eq!(registry.provider_id_for_model("gpt-4"), Some("openai-a"));
+    }
+}
+
+#[test]
+fn test_default_provider_receives_unknown_models() {
+    let mut config = create_test_config();
+    config.default_provider = Some("gemini".to_string());
+
+    let registry = ProviderRegistry::new(&config, Client::new()).unwrap();
+    assert!(registry.get_provider_for_model("vendor/unlisted-model").is_ok());
+    assert_eq!(registry.provider_id_for_model("vendor/unlisted-model"), Some("gemini"));
+    assert_eq!(registry.provider_id_for_model("gemini-pro"), Some("gemini"));
+}
+
+#[test]
+fn test_set_enabled_skips_disabled_provider_in_routing() {
+    let mut config = create_test_config();
+    config.providers.insert("openai".to_string(), ProviderDetail {
+        api_key: "test-openai-key-1234567890".to_string(),
+        api_base: "https://api.openai.com/v1/".to_string(),
+        models: Some(vec!["gpt-4".to_string()]),
+        ..Default::default()
+    });
+
+    let mut registry = ProviderRegistry::new(&config, Client::new()).unwrap();
+    registry.set_enabled("gemini", false).unwrap();
+    assert!(!registry.is_enabled("gemini"));
+
+    // Without a fallback the disabled provider's models are unavailable
+    assert!(matches!(
+        registry.get_provider_for_model("gemini-pro"),
+        Err(AppError::ServiceUnavailable(_))
+    ));
+    assert_eq!(registry.provider_id_for_model("gemini-pro"), None);
+    assert_eq!(registry.provider_id_for_model("gpt-4"), Some("openai"));
+
+    registry.set_enabled("gemini", true).unwrap();
+    assert_eq!(registry.provider_id_for_model("gemini-pro"), Some("gemini"));
+    assert_eq!(registry.fallback_for_model("gemini-pro"), None);
+
+    assert!(matches!(
+        registry.set_enabled("missing", false),
+        Err(AppError::ProviderNotFound(_))
+    ));
+}
+
+#[test]
+fn test_disabled_provider_falls_back_to_default_provider() {
+    let mut config = create_test_config();
+    config.providers.insert("openai".to_string(), ProviderDetail {
+        api_key: "test-openai-key-1234567890".to_string(),
+        api_base: "https://api.openai.com/v1/".to_string(),
+        models: Some(vec!["gpt-4".to_string()]),
+        ..Default::default()
+    });
+    config.default_provider = Some("openai".to_string());
+
+    let mut registry = ProviderRegistry::new(&config, Client::new()).unwrap();
+    registry.set_enabled("gemini", false).unwrap();
+    assert!(registry.get_provider_for_model("gemini-pro").is_ok());
+    assert_eq!(registry.provider_id_for_model("gemini-pro"), Some("openai"));
+    assert_eq!(registry.fallback_for_model("gemini-pro"), Some(("gemini", "openai")));
+
+    // A disabled default provider is no fallback either
+    registry.set_enabled("openai", false).unwrap();
+    assert_eq!(registry.provider_id_for_model("vendor/unlisted-model"), None);
+}
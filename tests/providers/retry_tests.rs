@@ -0,0 +1,176 @@
+use ai_proxy::{
+    config::ProviderDetail,
+    errors::AppError,
+    providers::{
+        AIProvider, RetryPolicy, chat_with_retries, parse_retry_after,
+        anthropic::{AnthropicProvider, AnthropicRequest, Message},
+    },
+};
+use chrono::{TimeZone, Utc};
+use reqwest::Client;
+use serde_json::json;
+use std::{sync::Arc, time::Duration};
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+#[test]
+fn test_parse_retry_after_seconds() {
+    let now = Utc::now();
+    assert_eq!(parse_retry_after("120", now), Some(Duration::from_secs(120)));
+    assert_eq!(parse_retry_after(" 0 ", now), Some(Duration::ZERO));
+}
+
+#[test]
+fn test_parse_retry_after_http_date() {
+    let now = Utc.with_ymd_and_hms(2015, 10, 21, 7, 27, 30).unwrap();
+    assert_eq!(
+        parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", now),
+        Some(Duration::from_secs(30))
+    );
+
+    // A date already in the past means retry immediately
+    assert_eq!(
+        parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+        Some(Duration::ZERO)
+    );
+}
+
+#[test]
+fn test_malformed_retry_after_falls_back_to_backoff() {
+    let now = Utc::now();
+    assert_eq!(parse_retry_after("soon", now), None);
+    assert_eq!(parse_retry_after("-5", now), None);
+    assert_eq!(parse_retry_after("", now), None);
+
+    // Without a usable Retry-After the computed backoff applies
+    let policy = RetryPolicy::new(3, Duration::from_secs(30));
+    let error = AppError::provider_error(429, "Rate limited").with_retry_after(parse_retry_after("soon", now));
+    assert_eq!(policy.delay_for(1, &error), Duration::from_millis(100));
+    assert_eq!(policy.delay_for(2, &error), Duration::from_millis(200));
+}
+
+#[test]
+fn test_retry_after_is_clamped_to_configured_max() {
+    let policy = RetryPolicy::new(3, Duration::from_secs(30)).with_max_retry_after(Duration::from_secs(5));
+
+    let error = AppError::provider_error(429, "Rate limited").with_retry_after(Some(Duration::from_secs(3600)));
+    assert_eq!(policy.delay_for(1, &error), Duration::from_secs(5));
+
+    let error = AppError::provider_error(503, "Unavailable").with_retry_after(Some(Duration::from_secs(2)));
+    assert_eq!(policy.delay_for(1, &error), Duration::from_secs(2));
+}
+
+#[tokio::test]
+async fn test_chat_with_retries_waits_for_retry_after() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(429)
+                .insert_header("retry-after", "1")
+                .set_body_json(json!({"type": "error", "error": {"type": "rate_limit_error", "message": "Slow down"}})),
+        )
+        .up_to_n_times(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_retry",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Hi"}],
+            "model": "claude-3-haiku-20240307",
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 5, "output_tokens": 1}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let config = ProviderDetail {
+        api_key: "test-key-1234567890".to_string(),
+        api_base: format!("{}/v1/", mock_server.uri()),
+        models: Some(vec!["claude-3-haiku-20240307".to_string()]),
+        ..Default::default()
+    };
+    let provider: Arc<dyn AIProvider + Send + Sync> = Arc::new(AnthropicProvider::new(config, Client::new()));
+    let request = AnthropicRequest {
+        model: "claude-3-haiku-20240307".to_string(),
+        messages: vec![Message::user("Hello".to_string())],
+        max_tokens: Some(10),
+        ..Default::default()
+    };
+
+    let started = std::time::Instant::now();
+    let response = chat_with_retries(provider, request, RetryPolicy::new(1, Duration::from_secs(10)))
+        .await
+        .unwrap();
+    assert_eq!(response.id, "msg_retry");
+    assert!(started.elapsed() >= Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn test_chat_with_retries_counts_retry_attempts() {
+    use ai_proxy::metrics::MetricsCollector;
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(503)
+                .set_body_json(json!({"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}})),
+        )
+        .up_to_n_times(2)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_retry_metrics",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Hi"}],
+            "model": "claude-3-haiku-20240307",
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 5, "output_tokens": 1}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let config = ProviderDetail {
+        api_key: "test-key-1234567890".to_string(),
+        api_base: format!("{}/v1/", mock_server.uri()),
+        models: Some(vec!["claude-3-haiku-20240307".to_string()]),
+        ..Default::default()
+    };
+    let provider: Arc<dyn AIProvider + Send + Sync> = Arc::new(AnthropicProvider::new(config, Client::new()));
+    let request = AnthropicRequest {
+        model: "claude-3-haiku-20240307".to_string(),
+        messages: vec![Message::user("Hello".to_string())],
+        max_tokens: Some(10),
+        ..Default::default()
+    };
+
+    let metrics = Arc::new(MetricsCollector::new());
+    let policy = RetryPolicy::new(3, Duration::from_secs(10)).with_metrics(metrics.clone(), "anthropic");
+    let response = chat_with_retries(provider, request, policy).await.unwrap();
+    assert_eq!(response.id, "msg_retry_metrics");
+
+    // The first attempt is not a retry: one failed retry, then one successful retry
+    let retries: Vec<(String, String, u64)> = metrics
+        .get_metrics_summary()
+        .await
+        .retry_attempts
+        .into_iter()
+        .map(|retry| (retry.provider, retry.outcome, retry.count))
+        .collect();
+    assert_eq!(
+        retries,
+        vec![
+            ("anthropic".to_string(), "error".to_string(), 1),
+            ("anthropic".to_string(), "success".to_string(), 1),
+        ]
+    );
+}
@@ -0,0 +1,152 @@
+use futures::StreamExt;
+use reqwest::Client;
+use serde_json::json;
+use wiremock::{
+    matchers::{header, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+use ai_proxy::{
+    config::ProviderDetail,
+    errors::AppError,
+    providers::{
+        AIProvider,
+        anthropic::{AnthropicRequest, Message},
+        xai::{XaiProvider, xai_utils},
+    },
+};
+
+/// Create a test provider configuration
+fn create_test_config(api_base: &str) -> ProviderDetail {
+    ProviderDetail {
+        api_key: "test-xai-key".to_string(),
+        api_base: format!("{}/", api_base.trim_end_matches('/')),
+        models: Some(vec!["grok-2-latest".to_string()]),
+        timeout_seconds: 30,
+        max_retries: 3,
+        enabled: true,
+        rate_limit: None,
+        ..Default::default()
+    }
+}
+
+/// Create a test Anthropic request
+fn create_test_request() -> AnthropicRequest {
+    AnthropicRequest {
+        model: "grok-2-latest".to_string(),
+        messages: vec![Message {
+            role: "user".to_string(),
+            content: "Hello, Grok!".to_string(),
+        }],
+        max_tokens: Some(100),
+        stream: Some(false),
+        temperature: Some(0.7),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_xai_chat_success() {
+    let mock_server = MockServer::start().await;
+    let provider = XaiProvider::new(create_test_config(&mock_server.uri()), Client::new());
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(header("authorization", "Bearer test-xai-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-grok",
+            "object": "chat.completion",
+            "created": 1714560000,
+            "model": "grok-2-latest",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hi from Grok"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 9, "completion_tokens": 4, "total_tokens": 13}
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let response = provider.chat(create_test_request()).await.unwrap();
+
+    assert_eq!(response.model, "grok-2-latest");
+    assert_eq!(response.usage.input_tokens, 9);
+    assert_eq!(response.usage.output_tokens, 4);
+}
+
+#[tokio::test]
+async fn test_xai_chat_rejects_non_grok_model() {
+    let mock_server = MockServer::start().await;
+    let provider = XaiProvider::new(create_test_config(&mock_server.uri()), Client::new());
+
+    let mut request = create_test_request();
+    request.model = "gpt-4".to_string();
+
+    let result = provider.chat(request).await;
+    assert!(matches!(result, Err(AppError::ValidationError(_))));
+    assert!(xai_utils::validate_model_name("grok-beta").is_ok());
+}
+
+#[tokio::test]
+async fn test_xai_streaming_response() {
+    let mock_server = MockServer::start().await;
+    let provider = XaiProvider::new(create_test_config(&mock_server.uri()), Client::new());
+
+    let body = "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"grok-2-latest\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hel\"},\"finish_reason\":null}]}\n\n\
+                data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"grok-2-latest\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"lo\"},\"finish_reason\":null}]}\n\n\
+                data: [DONE]\n\n";
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(body)
+                .insert_header("content-type", "text/event-stream"),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let mut request = create_test_request();
+    request.stream = Some(true);
+
+    let chunks: Vec<String> = provider
+        .chat_stream(request)
+        .await
+        .unwrap()
+        .map(|chunk| chunk.unwrap())
+        .collect()
+        .await;
+    let output = chunks.concat();
+
+    assert!(output.starts_with("event: message_start"));
+    assert!(output.contains("event: content_block_start"));
+    assert!(output.contains("\"text\":\"Hel\""));
+    assert!(output.contains("\"text\":\"lo\""));
+    assert!(output.contains("event: message_stop"));
+}
+
+#[tokio::test]
+async fn test_xai_list_models_filters_and_sorts() {
+    let mock_server = MockServer::start().await;
+    let provider = XaiProvider::new(create_test_config(&mock_server.uri()), Client::new());
+
+    Mock::given(method("GET"))
+        .and(path("/models"))
+        .and(header("authorization", "Bearer test-xai-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "object": "list",
+            "data": [
+                {"id": "grok-2-mini", "object": "model", "created": 1714560000, "owned_by": "xai"},
+                {"id": "grok-2-image", "object": "model", "created": 1714560000, "owned_by": "xai"},
+                {"id": "grok-2-latest", "object": "model", "created": 1714560000, "owned_by": "xai"}
+            ]
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let models = provider.list_models().await.unwrap();
+    let ids: Vec<&str> = models.iter().map(|m| m.id.as_str()).collect();
+
+    assert_eq!(ids, vec!["grok-2-latest", "grok-2-mini"]);
+}
@@ -1,10 +1,12 @@
 use ai_proxy::{
     config::{
-        Config, LoggingConfig, PerformanceConfig, ProviderDetail, SecurityConfig, ServerConfig,
+        Config, LoggingConfig, NetworkConfig, PerformanceConfig, ProviderDetail, SecurityConfig,
+        ServerConfig, SharedConfig,
     },
+    errors::AppError,
     metrics::MetricsCollector,
     providers::registry::ProviderRegistry,
-    server::{AppState, create_app},
+    server::{AppState, build_http_client, create_app},
 };
 use axum::{
     body::Body,
@@ -29,6 +31,7 @@ fn create_test_config() -> Config {
             max_retries: 3,
             enabled: true,
             rate_limit: None,
+            ..Default::default()
         },
     );
 
@@ -38,11 +41,13 @@ fn create_test_config() -> Config {
             port: 3000,
             request_timeout_seconds: 30,
             max_request_size_bytes: 1024 * 1024,
+            ..Default::default()
         },
         providers,
         logging: LoggingConfig::default(),
         security: SecurityConfig::default(),
         performance: PerformanceConfig::default(),
+        ..Default::default()
     }
 }
 
@@ -56,10 +61,16 @@ fn create_test_app_state() -> AppState {
     let metrics = Arc::new(MetricsCollector::new());
 
     AppState {
-        config: Arc::new(config),
+        config: Arc::new(SharedConfig::new(config)),
         http_client,
         provider_registry,
         metrics,
+        health_cache: Default::default(),
+        coalescer: Default::default(),
+        idempotency: Default::default(),
+        active_streams: Default::default(),
+        redactor: Default::default(),
+        transforms: Vec::new(),
     }
 }
 
@@ -288,6 +299,37 @@ async fn test_request_id_header() {
     assert!(response.headers().contains_key("x-request-id"));
 }
 
+#[tokio::test]
+async fn test_request_id_header_echoed_unchanged() {
+    let app_state = create_test_app_state();
+    let app = create_app(app_state);
+
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri("/health")
+        .header("x-request-id", "client-supplied-id-42")
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(
+        response.headers().get("x-request-id").unwrap(),
+        "client-supplied-id-42"
+    );
+
+    // Unmatched routes still get a generated request ID
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri("/does-not-exist")
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let request_id = response.headers().get("x-request-id").unwrap();
+    assert!(uuid::Uuid::parse_str(request_id.to_str().unwrap()).is_ok());
+}
+
 #[tokio::test]
 async fn test_streaming_endpoint() {
     let app_state = create_test_app_state();
@@ -401,15 +443,21 @@ fn test_app_state_creation() {
     let metrics = Arc::new(MetricsCollector::new());
 
     let app_state = AppState {
-        config: Arc::new(config),
+        config: Arc::new(SharedConfig::new(config)),
         http_client,
         provider_registry,
         metrics,
+        health_cache: Default::default(),
+        coalescer: Default::default(),
+        idempotency: Default::default(),
+        active_streams: Default::default(),
+        redactor: Default::default(),
+        transforms: Vec::new(),
     };
 
     // Verify app state is created correctly
-    assert_eq!(app_state.config.server.port, 3000);
-    assert!(!app_state.config.providers.is_empty());
+    assert_eq!(app_state.config.load().server.port, 3000);
+    assert!(!app_state.config.load().providers.is_empty());
 }
 
 // Test concurrent request handling
@@ -469,6 +517,28 @@ async fn test_request_size_limits() {
     assert!(response.status().is_client_error() || response.status().is_success());
 }
 
+#[tokio::test]
+async fn test_request_body_exceeding_configured_limit_returns_413() {
+    let mut config = create_test_config();
+    config.server.max_request_size_bytes = 1024;
+    let app = create_app(AppState::new(config).unwrap());
+
+    let oversized_body = json!({
+        "model": "gpt-3.5-turbo",
+        "messages": [{"role": "user", "content": "a".repeat(4096)}],
+        "max_tokens": 100
+    });
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/v1/messages")
+        .header("content-type", "application/json")
+        .body(Body::from(oversized_body.to_string()))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
 // Test graceful error handling for various scenarios
 
 #[tokio::test]
@@ -508,3 +578,104 @@ async fn test_graceful_error_handling() {
         assert!(content_type.to_str().unwrap().contains("application/json"));
     }
 }
+
+#[test]
+fn test_build_http_client_with_network_settings() {
+    // No proxy or CA configured
+    assert!(build_http_client(&NetworkConfig::default()).is_ok());
+
+    let network = NetworkConfig {
+        https_proxy: Some("http://proxy.corp.example:3128".to_string()),
+        no_proxy: Some("localhost,127.0.0.1".to_string()),
+        ..Default::default()
+    };
+    assert!(build_http_client(&network).is_ok());
+}
+
+#[test]
+fn test_build_http_client_bogus_ca_path() {
+    let network = NetworkConfig {
+        ca_bundle_path: Some("/nonexistent/corp-ca.pem".to_string()),
+        ..Default::default()
+    };
+
+    match build_http_client(&network) {
+        Err(AppError::ConfigError(message)) => {
+            assert!(message.contains("ca_bundle_path"));
+            assert!(message.contains("/nonexistent/corp-ca.pem"));
+        }
+        other => panic!("Expected config error, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[tokio::test]
+async fn test_start_server_rejects_invalid_tls_cert_path() {
+    let mut config = create_test_config();
+    config.server.port = 0;
+    config.server.tls_cert_path = Some("/nonexistent/server.pem".to_string());
+    config.server.tls_key_path = Some("/nonexistent/server.key".to_string());
+
+    match ai_proxy::start_server(config).await {
+        Err(AppError::ConfigError(message)) => {
+            assert!(message.contains("tls_cert_path"), "{}", message);
+            assert!(message.contains("/nonexistent/server.pem"), "{}", message);
+        }
+        other => panic!("Expected config error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_build_http_client_sets_user_agent() {
+    use ai_proxy::providers::{AIProvider, openai::OpenAIProvider};
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{header, method, path},
+    };
+
+    let mock_server = MockServer::start().await;
+    let provider_config = ProviderDetail {
+        api_key: "test-api-key-1234567890".to_string(),
+        api_base: mock_server.uri(),
+        ..Default::default()
+    };
+
+    // Default: the real crate version
+    let default_agent = format!("ai-proxy/{}", env!("CARGO_PKG_VERSION"));
+    assert_eq!(NetworkConfig::default().user_agent(), default_agent);
+    Mock::given(method("GET"))
+        .and(path("/models"))
+        .and(header("user-agent", default_agent.as_str()))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"data": []})))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = build_http_client(&NetworkConfig::default()).unwrap();
+    let provider = OpenAIProvider::new(provider_config.clone(), client);
+    assert_eq!(provider.health_check().await.unwrap().status, "healthy");
+    mock_server.verify().await;
+    mock_server.reset().await;
+
+    // Configured override applies to every provider sharing the client
+    let network = NetworkConfig {
+        user_agent: Some("acme-gateway/2.0".to_string()),
+        ..Default::default()
+    };
+    assert!(network.validate().is_ok());
+    Mock::given(method("GET"))
+        .and(path("/models"))
+        .and(header("user-agent", "acme-gateway/2.0"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"data": []})))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = OpenAIProvider::new(provider_config, build_http_client(&network).unwrap());
+    assert_eq!(provider.health_check().await.unwrap().status, "healthy");
+
+    let invalid = NetworkConfig {
+        user_agent: Some("  ".to_string()),
+        ..Default::default()
+    };
+    assert!(invalid.validate().is_err());
+}
@@ -1,8 +1,15 @@
 use ai_proxy::providers::{
+    ActiveStreams, CancellableStream, SSE_HEARTBEAT, StreamResponse, aggregate_stream, with_backpressure, with_heartbeat, with_sse_event_names,
     anthropic::{SSEEvent, AnthropicStreamEvent, StreamMessage, ContentBlockStart, TextDelta, MessageDelta, StreamError, Usage},
-    openai::{OpenAIStreamResponse, OpenAIStreamChoice, OpenAIStreamDelta},
+    openai::{OpenAIStreamResponse, OpenAIStreamChoice, OpenAIStreamDelta, ToolCallAssembler},
     gemini::{GeminiStreamResponse, GeminiStreamCandidate, GeminiContent, GeminiPart, UsageMetadata},
 };
+use axum::body::Body;
+use futures::{StreamExt, stream};
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+};
 
 /// Test streaming functionality and Server-Sent Events processing
 /// These tests focus on streaming response handling and SSE formatting
@@ -56,6 +63,7 @@ fn test_anthropic_stream_event_message_start() {
             usage: Usage {
                 input_tokens: 15,
                 output_tokens: 0,
+                thinking_tokens: None,
             },
         },
     };
@@ -114,6 +122,7 @@ fn test_anthropic_stream_event_message_delta() {
             usage: Some(Usage {
                 input_tokens: 15,
                 output_tokens: 25,
+                thinking_tokens: None,
             }),
         },
     };
@@ -138,6 +147,7 @@ fn test_anthropic_stream_event_error() {
         error: StreamError {
             error_type: "rate_limit_error".to_string(),
             message: "Rate limit exceeded".to_string(),
+            output_tokens: None,
         },
     };
 
@@ -160,6 +170,7 @@ fn test_openai_stream_response_conversion() {
             delta: OpenAIStreamDelta {
                 role: Some("assistant".to_string()),
                 content: Some("Hello".to_string()),
+                tool_calls: None,
             },
             finish_reason: None,
             logprobs: None,
@@ -192,6 +203,7 @@ fn test_openai_stream_response_with_finish_reason() {
             delta: OpenAIStreamDelta {
                 role: None,
                 content: None,
+                tool_calls: None,
             },
             finish_reason: Some("stop".to_string()),
             logprobs: None,
@@ -211,6 +223,62 @@ fn test_openai_stream_response_with_finish_reason() {
     assert!(combined_json.contains("message_stop") || combined_json.contains("message_delta"));
 }
 
+#[test]
+fn test_openai_stream_tool_call_fragments_assembled() {
+    let chunks = [
+        r#"{"id":"c","object":"chat.completion.chunk","created":1,"model":"gpt-4","choices":[{"index":0,"delta":{"role":"assistant","tool_calls":[{"index":0,"id":"call_abc","type":"function","function":{"name":"get_weather","arguments":""}}]},"finish_reason":null}]}"#,
+        r#"{"id":"c","object":"chat.completion.chunk","created":1,"model":"gpt-4","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"loc"}}]},"finish_reason":null}]}"#,
+        r#"{"id":"c","object":"chat.completion.chunk","created":1,"model":"gpt-4","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"ation\":\"Par"}}]},"finish_reason":null}]}"#,
+        r#"{"id":"c","object":"chat.completion.chunk","created":1,"model":"gpt-4","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"is\"}"}}]},"finish_reason":null}]}"#,
+        r#"{"id":"c","object":"chat.completion.chunk","created":1,"model":"gpt-4","choices":[{"index":0,"delta":{},"finish_reason":"tool_calls"}]}"#,
+    ];
+
+    let mut assembler = ToolCallAssembler::default();
+    let events: Vec<serde_json::Value> = chunks
+        .iter()
+        .flat_map(|chunk| {
+            let stream: OpenAIStreamResponse = serde_json::from_str(chunk).unwrap();
+            stream.to_anthropic_events_with_tools("msg_tool", &mut assembler).unwrap()
+        })
+        .map(|event| serde_json::to_value(&event).unwrap())
+        .collect();
+
+    // The text block closes, then the tool_use block opens once on index 1
+    assert_eq!(events[0]["type"], "content_block_stop");
+    assert_eq!(events[0]["index"], 0);
+    assert_eq!(events[1]["type"], "content_block_start");
+    assert_eq!(events[1]["index"], 1);
+    assert_eq!(events[1]["content_block"]["type"], "tool_use");
+    assert_eq!(events[1]["content_block"]["id"], "call_abc");
+    assert_eq!(events[1]["content_block"]["name"], "get_weather");
+    let types: Vec<&str> = events.iter().map(|e| e["type"].as_str().unwrap()).collect();
+    assert_eq!(types.iter().filter(|t| **t == "content_block_stop").count(), 2);
+
+    // Each fragment is forwarded as input_json_delta and reassembles to valid JSON
+    let partial_json: String = events
+        .iter()
+        .filter(|e| e["delta"]["type"] == "input_json_delta")
+        .map(|e| e["delta"]["partial_json"].as_str().unwrap())
+        .collect();
+    let input: serde_json::Value = serde_json::from_str(&partial_json).unwrap();
+    assert_eq!(input, serde_json::json!({"location": "Paris"}));
+
+    // The tool block closes before message_delta, which maps to tool_use
+    assert_eq!(
+        &types[types.len() - 3..],
+        &["content_block_stop", "message_delta", "message_stop"]
+    );
+    assert_eq!(events[events.len() - 3]["index"], 1);
+    assert_eq!(events[events.len() - 2]["delta"]["stop_reason"], "tool_use");
+
+    // The assembled call converts to a complete tool_use block
+    let calls = assembler.tool_calls();
+    assert_eq!(calls.len(), 1);
+    let block = calls[0].to_anthropic().unwrap();
+    assert_eq!(block.input, Some(serde_json::json!({"location": "Paris"})));
+    assert!(assembler.finish().is_empty());
+}
+
 #[test]
 fn test_gemini_stream_response_conversion() {
     let gemini_stream = GeminiStreamResponse {
@@ -219,12 +287,14 @@ fn test_gemini_stream_response_conversion() {
                 role: "model".to_string(),
                 parts: vec![GeminiPart {
                     text: "Hello from Gemini".to_string(),
+                    function_call: None,
                 }],
             }),
             finish_reason: None,
             index: Some(0),
         }]),
         usage_metadata: None,
+        prompt_feedback: None,
     };
 
     let events = gemini_stream.to_anthropic_events("gemini-pro", "msg_789").unwrap();
@@ -246,6 +316,7 @@ fn test_gemini_stream_response_with_finish() {
                 role: "model".to_string(),
                 parts: vec![GeminiPart {
                     text: "Final message".to_string(),
+                    function_call: None,
                 }],
             }),
             finish_reason: Some("STOP".to_string()),
@@ -255,7 +326,9 @@ fn test_gemini_stream_response_with_finish() {
             prompt_token_count: Some(5),
             candidates_token_count: Some(10),
             total_token_count: Some(15),
+            thoughts_token_count: None,
         }),
+        prompt_feedback: None,
     };
 
     let events = gemini_stream.to_anthropic_events("gemini-pro", "msg_final").unwrap();
@@ -283,6 +356,7 @@ fn test_stream_event_sequence() {
                 usage: Usage {
                     input_tokens: 10,
                     output_tokens: 0,
+                    thinking_tokens: None,
                 },
             },
         },
@@ -314,6 +388,7 @@ fn test_stream_event_sequence() {
                 usage: Some(Usage {
                     input_tokens: 10,
                     output_tokens: 5,
+                    thinking_tokens: None,
                 }),
             },
         },
@@ -385,6 +460,7 @@ fn test_stream_error_handling() {
             error: StreamError {
                 error_type: error_type.to_string(),
                 message: error_message.to_string(),
+                output_tokens: None,
             },
         };
 
@@ -449,6 +525,7 @@ fn test_usage_metadata_in_streams() {
             usage: Some(Usage {
                 input_tokens: 25,
                 output_tokens: 50,
+                thinking_tokens: None,
             }),
         },
     };
@@ -468,4 +545,286 @@ fn test_usage_metadata_in_streams() {
     let json = serde_json::to_string(&message_delta_no_usage).unwrap();
     assert!(json.contains("\"stop_reason\":\"end_turn\""));
     assert!(!json.contains("usage"));
-}
\ No newline at end of file
+}
+/// Sets a flag when dropped, standing in for the upstream reqwest stream
+struct UpstreamDropGuard(Arc<AtomicBool>);
+
+impl Drop for UpstreamDropGuard {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Endless upstream stream that owns a drop guard
+fn endless_upstream(dropped: Arc<AtomicBool>) -> StreamResponse {
+    let guard = UpstreamDropGuard(dropped);
+    Box::pin(stream::unfold(guard, |guard| async move {
+        Some((Ok("data: chunk\n\n".to_string()), guard))
+    }))
+}
+
+#[tokio::test]
+async fn test_cancellable_stream_drops_upstream_after_first_chunk() {
+    let dropped = Arc::new(AtomicBool::new(false));
+    let mut stream = CancellableStream::wrap(
+        endless_upstream(dropped.clone()),
+        "openai",
+        "gpt-4".to_string(),
+    );
+
+    let first = stream.next().await.unwrap().unwrap();
+    assert_eq!(first, "data: chunk\n\n");
+    assert!(!dropped.load(Ordering::SeqCst));
+
+    // Client goes away mid-stream
+    drop(stream);
+    assert!(dropped.load(Ordering::SeqCst));
+}
+
+#[tokio::test]
+async fn test_cancellable_stream_dropped_with_response_body() {
+    let dropped = Arc::new(AtomicBool::new(false));
+    let stream = CancellableStream::wrap(
+        endless_upstream(dropped.clone()),
+        "anthropic",
+        "claude-3-sonnet".to_string(),
+    );
+
+    // axum owns the stream through the response body
+    let mut body = Body::from_stream(stream).into_data_stream();
+    let first = body.next().await.unwrap().unwrap();
+    assert_eq!(&first[..], b"data: chunk\n\n");
+
+    drop(body);
+    assert!(dropped.load(Ordering::SeqCst));
+}
+
+#[tokio::test]
+async fn test_cancellable_stream_passes_through_completed_stream() {
+    let inner: StreamResponse = Box::pin(stream::iter(vec![
+        Ok("data: one\n\n".to_string()),
+        Ok("data: two\n\n".to_string()),
+    ]));
+    let stream = CancellableStream::wrap(inner, "gemini", "gemini-pro".to_string());
+
+    let chunks: Vec<String> = stream.map(|c| c.unwrap()).collect().await;
+    assert_eq!(chunks, vec!["data: one\n\n", "data: two\n\n"]);
+}
+
+/// Upstream that takes `delay` before its first event, then finishes
+fn slow_start_upstream(delay: std::time::Duration) -> StreamResponse {
+    Box::pin(stream::once(async move {
+        tokio::time::sleep(delay).await;
+        Ok("event: message_start\ndata: {}\n\n".to_string())
+    }))
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_heartbeat_pings_while_waiting_for_first_token() {
+    let stream = with_heartbeat(
+        slow_start_upstream(std::time::Duration::from_secs(35)),
+        std::time::Duration::from_secs(10),
+    );
+
+    let chunks: Vec<String> = stream.map(|chunk| chunk.unwrap()).collect().await;
+
+    // Pings at 10s, 20s and 30s, then the real event; nothing after the upstream ends
+    assert_eq!(chunks.len(), 4);
+    assert!(chunks[..3].iter().all(|chunk| chunk == SSE_HEARTBEAT));
+    assert!(chunks[3].starts_with("event: message_start"));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_heartbeat_disabled_with_zero_interval() {
+    let stream = with_heartbeat(
+        slow_start_upstream(std::time::Duration::from_secs(35)),
+        std::time::Duration::ZERO,
+    );
+
+    let chunks: Vec<String> = stream.map(|chunk| chunk.unwrap()).collect().await;
+    assert_eq!(chunks.len(), 1);
+    assert!(!chunks[0].starts_with(':'));
+}
+
+#[tokio::test]
+async fn test_aggregate_stream_reassembles_split_chunks() {
+    // Events split across chunk boundaries are still parsed whole
+    let chunks = vec![
+        "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_1\",\"model\":\"gpt-4\",\"usage\":{\"input_tokens\":4,\"output_tokens\":0}}}\n\nevent: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hel\"}}\n",
+        "\nevent: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"lo\"}}\n\n",
+        "event: message_delta\ndata: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"max_tokens\",\"usage\":{\"output_tokens\":2}}}\n\n",
+    ];
+    let upstream: StreamResponse = Box::pin(stream::iter(chunks.into_iter().map(|c| Ok(c.to_string()))));
+
+    let response = aggregate_stream(upstream).await.unwrap();
+    assert_eq!(response.id, "msg_1");
+    assert_eq!(response.content[0].text, "Hello");
+    assert_eq!(response.usage.input_tokens, 4);
+    assert_eq!(response.usage.output_tokens, 2);
+    assert_eq!(response.stop_reason.as_deref(), Some("max_tokens"));
+}
+
+#[tokio::test]
+async fn test_aggregate_stream_fails_on_error_event() {
+    let chunks = vec![
+        "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"partial\"}}\n\n",
+        "event: error\ndata: {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}\n\n",
+    ];
+    let upstream: StreamResponse = Box::pin(stream::iter(chunks.into_iter().map(|c| Ok(c.to_string()))));
+
+    let error = aggregate_stream(upstream).await.unwrap_err();
+    assert!(error.to_string().contains("Overloaded"));
+}
+
+/// Mixed upstream framing: a named event, an unnamed event split across chunks, a comment and `[DONE]`
+fn mixed_framing_stream() -> StreamResponse {
+    let chunks = vec![
+        "event: message_start\ndata: {\"type\":\"message_start\"}\n\ndata: {\"type\":\"content_block_start\",",
+        "\"index\":0}\n\n: ping\n\n",
+        "data: [DONE]\n\n",
+    ];
+    Box::pin(stream::iter(chunks.into_iter().map(|c| Ok(c.to_string()))))
+}
+
+#[tokio::test]
+async fn test_sse_event_names_named_mode_names_every_event() {
+    let chunks: Vec<String> = with_sse_event_names(mixed_framing_stream(), true)
+        .map(|chunk| chunk.unwrap())
+        .collect()
+        .await;
+
+    assert_eq!(
+        chunks.concat(),
+        "event: message_start\ndata: {\"type\":\"message_start\"}\n\n\
+         event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":0}\n\n\
+         : ping\n\n\
+         data: [DONE]\n\n"
+    );
+}
+
+#[tokio::test]
+async fn test_sse_event_names_data_only_mode_strips_event_lines() {
+    let chunks: Vec<String> = with_sse_event_names(mixed_framing_stream(), false)
+        .map(|chunk| chunk.unwrap())
+        .collect()
+        .await;
+
+    let output = chunks.concat();
+    assert!(!output.contains("event:"));
+    assert_eq!(
+        output,
+        "data: {\"type\":\"message_start\"}\n\n\
+         data: {\"type\":\"content_block_start\",\"index\":0}\n\n\
+         : ping\n\n\
+         data: [DONE]\n\n"
+    );
+}
+
+/// Endless upstream that counts how many chunks were pulled from it
+struct CountingUpstream {
+    pulled: Arc<AtomicUsize>,
+    dropped: Arc<AtomicBool>,
+}
+
+impl futures::Stream for CountingUpstream {
+    type Item = Result<String, ai_proxy::errors::AppError>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        let n = self.pulled.fetch_add(1, Ordering::SeqCst);
+        std::task::Poll::Ready(Some(Ok(format!("data: {}\n\n", n))))
+    }
+}
+
+impl Drop for CountingUpstream {
+    fn drop(&mut self) {
+        self.dropped.store(true, Ordering::SeqCst);
+    }
+}
+
+#[tokio::test]
+async fn test_backpressure_pauses_upstream_for_slow_client() {
+    let pulled = Arc::new(AtomicUsize::new(0));
+    let dropped = Arc::new(AtomicBool::new(false));
+    let upstream: StreamResponse = Box::pin(CountingUpstream {
+        pulled: pulled.clone(),
+        dropped: dropped.clone(),
+    });
+    let mut stream = with_backpressure(upstream, 4);
+
+    // A client that reads one chunk and then stalls
+    assert_eq!(stream.next().await.unwrap().unwrap(), "data: 0\n\n");
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+    // One delivered, four buffered and at most one waiting for room: the producer blocked
+    assert!(pulled.load(Ordering::SeqCst) <= 6, "pulled {}", pulled.load(Ordering::SeqCst));
+
+    // Chunks keep their order once the client resumes
+    for expected in 1..10 {
+        assert_eq!(stream.next().await.unwrap().unwrap(), format!("data: {}\n\n", expected));
+    }
+
+    // A disconnecting client releases the upstream
+    drop(stream);
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    assert!(dropped.load(Ordering::SeqCst));
+}
+
+#[tokio::test]
+async fn test_active_streams_cancel_ends_registered_stream() {
+    let streams = Arc::new(ActiveStreams::new());
+    let dropped = Arc::new(AtomicBool::new(false));
+    let mut stream = streams.register("key-a", " job-1 ", endless_upstream(dropped.clone())).unwrap();
+
+    assert_eq!(stream.next().await.unwrap().unwrap(), "data: chunk\n\n");
+    assert!(streams.contains("key-a", "job-1"));
+
+    // The same ID cannot be reused by its owner while the stream is in flight
+    assert!(streams.reserve("key-a", "job-1").is_err());
+    // IDs are scoped per caller: another key neither collides with nor cancels it
+    assert!(!streams.cancel("key-b", "job-1"));
+    drop(streams.reserve("key-b", "job-1").unwrap());
+
+    // Surrounding whitespace is ignored when cancelling, as when registering
+    assert!(streams.cancel("key-a", "job-1 "));
+    assert!(stream.next().await.is_none());
+    assert!(!streams.contains("key-a", "job-1"));
+    assert!(!streams.cancel("key-a", "job-1"));
+
+    drop(stream);
+    assert!(dropped.load(Ordering::SeqCst));
+}
+
+#[tokio::test]
+async fn test_active_streams_unregisters_on_drop() {
+    let streams = Arc::new(ActiveStreams::new());
+    let stream = streams
+        .register("key-a", "job-2", endless_upstream(Arc::new(AtomicBool::new(false))))
+        .unwrap();
+    assert_eq!(streams.len(), 1);
+
+    // Client disconnects without cancelling
+    drop(stream);
+    assert!(streams.is_empty());
+
+    // A reserved slot that never gets a stream releases its ID
+    let slot = streams.reserve("key-a", "job-2").unwrap();
+    assert_eq!(streams.len(), 1);
+    drop(slot);
+    assert!(streams.is_empty());
+
+    assert!(streams.reserve("key-a", " ").is_err());
+}
+
+#[tokio::test]
+async fn test_active_streams_cancel_before_attach_ends_stream() {
+    let streams = Arc::new(ActiveStreams::new());
+    let slot = streams.reserve("key-a", "job-3").unwrap();
+
+    // Cancelled while the upstream call was still being made
+    assert!(streams.cancel("key-a", "job-3"));
+    let mut stream = slot.attach(endless_upstream(Arc::new(AtomicBool::new(false))));
+    assert!(stream.next().await.is_none());
+}
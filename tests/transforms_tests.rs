@@ -0,0 +1,29 @@
+use ai_proxy::providers::anthropic::{AnthropicRequest, Message};
+use ai_proxy::transforms::{NoopTransform, PiiRedactionTransform, Transform};
+
+#[test]
+fn test_pii_redaction_replaces_emails_and_long_numbers() {
+    assert_eq!(
+        PiiRedactionTransform::redact("Reach me at (bob@mail.example.org), or +1-555-123-4567!"),
+        "Reach me at ([REDACTED_EMAIL]), or [REDACTED_NUMBER]!"
+    );
+    // Short numbers and bare @mentions are left alone
+    assert_eq!(
+        PiiRedactionTransform::redact("Order 12345 for @team\nat 10:30"),
+        "Order 12345 for @team\nat 10:30"
+    );
+}
+
+#[tokio::test]
+async fn test_noop_transform_leaves_request_untouched() {
+    let mut request = AnthropicRequest {
+        model: "claude-3-sonnet".to_string(),
+        messages: vec![Message::user("me@example.com".to_string())],
+        ..Default::default()
+    };
+    NoopTransform.on_request(&mut request).await;
+    assert_eq!(request.messages[0].content, "me@example.com");
+
+    PiiRedactionTransform.on_request(&mut request).await;
+    assert_eq!(request.messages[0].content, "[REDACTED_EMAIL]");
+}